@@ -0,0 +1,257 @@
+//! HTTP Alternative Services (RFC 7838) for `--alt-svc`.
+//!
+//! An `Alt-Svc` response header advertises other endpoints, possibly
+//! speaking other protocols, that serve the same origin. A [`Store`] keeps
+//! them until they expire, in curl's file format:
+//!
+//! ```text
+//! h1 example.com 80 h2c alt.example.com 8080 "20301231 00:00:00" 0 0
+//! ```
+//!
+//! that is: source protocol, host and port, alternative protocol, host and
+//! port, expiry, the `persist` flag and a priority (always 0).
+
+use crate::date;
+
+/// How long an alternative is valid without an `ma` parameter.
+const DEFAULT_MAX_AGE: u64 = 24 * 3600;
+
+/// An endpoint of an origin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Endpoint {
+    /// An ALPN protocol ID, such as `h2`, `h2c` or `http/1.1`; `h1` for
+    /// the source of a plain HTTP/1 origin.
+    pub protocol: String,
+    pub host: String,
+    pub port: u16,
+}
+
+/// One alternative for one origin.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alternative {
+    pub origin: Endpoint,
+    pub service: Endpoint,
+    /// Unix seconds.
+    pub expires: u64,
+    pub persist: bool,
+}
+
+/// What an `Alt-Svc` header says.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Advertisement {
+    /// `clear`: forget every alternative of the origin.
+    Clear,
+    /// Alternative endpoints, most preferred first, with their max age in
+    /// seconds and the `persist` flag. An empty host means the origin's.
+    Services(Vec<(Endpoint, u64, bool)>),
+}
+
+/// Parses an `Alt-Svc` value such as
+/// `h2c=":8080"; ma=3600, h3="alt.example.com:443"`. Entries that do not
+/// parse are skipped; `None` if nothing does.
+pub fn parse(value: &str) -> Option<Advertisement> {
+    if value.trim().eq_ignore_ascii_case("clear") {
+        return Some(Advertisement::Clear);
+    }
+    let mut services = Vec::new();
+    for entry in value.split(',') {
+        let mut params = entry.split(';');
+        let Some((protocol, authority)) = params.next().and_then(|p| p.split_once('=')) else {
+            continue;
+        };
+        let authority = authority.trim().trim_matches('"');
+        let Some((host, port)) = authority.rsplit_once(':') else {
+            continue;
+        };
+        let Ok(port) = port.parse() else {
+            continue;
+        };
+        let mut max_age = DEFAULT_MAX_AGE;
+        let mut persist = false;
+        for param in params {
+            let (name, arg) = param.split_once('=').unwrap_or((param, ""));
+            let arg = arg.trim().trim_matches('"');
+            match name.trim().to_ascii_lowercase().as_str() {
+                "ma" => max_age = arg.parse().unwrap_or(max_age),
+                "persist" => persist = arg == "1",
+                _ => {}
+            }
+        }
+        let endpoint = Endpoint {
+            protocol: crate::url::percent_decode(protocol.trim()),
+            host: host.trim_matches(['[', ']']).to_ascii_lowercase(),
+            port,
+        };
+        services.push((endpoint, max_age, persist));
+    }
+    (!services.is_empty()).then_some(Advertisement::Services(services))
+}
+
+/// The known alternatives.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Store {
+    alternatives: Vec<Alternative>,
+}
+
+impl Store {
+    pub fn new() -> Store {
+        Store::default()
+    }
+
+    /// Reads a store in curl's format, skipping lines that do not parse.
+    pub fn load(text: &str) -> Store {
+        let mut store = Store::new();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if let Some(alternative) = parse_line(line) {
+                store.alternatives.push(alternative);
+            }
+        }
+        store
+    }
+
+    /// The store in curl's format, without entries expired by `now`.
+    pub fn to_text(&self, now: u64) -> String {
+        let mut text = String::from("# Alt-Svc cache written by http_client\n");
+        for alt in self.alternatives.iter().filter(|a| a.expires > now) {
+            text.push_str(&format!(
+                "{} {} {} {} {} {} \"{}\" {} 0\n",
+                alt.origin.protocol,
+                alt.origin.host,
+                alt.origin.port,
+                alt.service.protocol,
+                alt.service.host,
+                alt.service.port,
+                date::format_compact(alt.expires),
+                u8::from(alt.persist)
+            ));
+        }
+        text
+    }
+
+    pub fn alternatives(&self) -> &[Alternative] {
+        &self.alternatives
+    }
+
+    /// Replaces the alternatives of `origin` with what `advertisement`
+    /// says, as of `now`. Returns whether the store changed.
+    pub fn update(&mut self, origin: &Endpoint, advertisement: &Advertisement, now: u64) -> bool {
+        let before = self.alternatives.clone();
+        self.alternatives.retain(|a| a.origin != *origin);
+        if let Advertisement::Services(services) = advertisement {
+            for (service, max_age, persist) in services {
+                let mut service = service.clone();
+                if service.host.is_empty() {
+                    service.host = origin.host.clone();
+                }
+                self.alternatives.push(Alternative {
+                    origin: origin.clone(),
+                    service,
+                    expires: now.saturating_add(*max_age),
+                    persist: *persist,
+                });
+            }
+        }
+        self.alternatives != before
+    }
+
+    /// The most preferred live alternative of `origin` whose protocol is
+    /// one of `protocols`.
+    pub fn find(&self, origin: &Endpoint, protocols: &[&str], now: u64) -> Option<&Endpoint> {
+        self.alternatives
+            .iter()
+            .filter(|a| a.origin == *origin && a.expires > now)
+            .map(|a| &a.service)
+            .find(|s| protocols.contains(&s.protocol.as_str()))
+    }
+
+    /// Forgets one alternative of `origin`, for instance after failing to
+    /// connect to it.
+    pub fn remove(&mut self, origin: &Endpoint, service: &Endpoint) {
+        self.alternatives
+            .retain(|a| !(a.origin == *origin && a.service == *service));
+    }
+}
+
+fn parse_line(line: &str) -> Option<Alternative> {
+    // The expiry is quoted and contains a space.
+    let (before, rest) = line.split_once('"')?;
+    let (expiry, after) = rest.split_once('"')?;
+    let words: Vec<&str> = before.split_whitespace().collect();
+    let [src_proto, src_host, src_port, dst_proto, dst_host, dst_port] = words[..] else {
+        return None;
+    };
+    let persist = after.split_whitespace().next() == Some("1");
+    Some(Alternative {
+        origin: Endpoint {
+            protocol: src_proto.to_string(),
+            host: src_host.to_string(),
+            port: src_port.parse().ok()?,
+        },
+        service: Endpoint {
+            protocol: dst_proto.to_string(),
+            host: dst_host.to_string(),
+            port: dst_port.parse().ok()?,
+        },
+        expires: date::parse_compact(expiry)?,
+        persist,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn endpoint(protocol: &str, host: &str, port: u16) -> Endpoint {
+        Endpoint {
+            protocol: protocol.into(),
+            host: host.into(),
+            port,
+        }
+    }
+
+    #[test]
+    fn parses_headers() {
+        assert_eq!(parse("clear"), Some(Advertisement::Clear));
+        assert_eq!(
+            parse(r#"h3=":443"; ma=60, h2c="Alt.Example.com:8080"; persist=1, bad, x=":y""#),
+            Some(Advertisement::Services(vec![
+                (endpoint("h3", "", 443), 60, false),
+                (
+                    endpoint("h2c", "alt.example.com", 8080),
+                    DEFAULT_MAX_AGE,
+                    true
+                ),
+            ]))
+        );
+        assert_eq!(parse("garbage"), None);
+    }
+
+    #[test]
+    fn stores_finds_and_round_trips() {
+        let origin = endpoint("h1", "example.com", 80);
+        let mut store = Store::new();
+        let ad = parse(r#"h3=":443", h2c=":8080"; ma=100"#).unwrap();
+        assert!(store.update(&origin, &ad, 1000));
+        assert_eq!(
+            store.find(&origin, &["h2c", "http/1.1"], 1099),
+            Some(&endpoint("h2c", "example.com", 8080))
+        );
+        assert_eq!(store.find(&origin, &["h2c"], 1100), None);
+        assert_eq!(
+            store.find(&endpoint("h1", "other", 80), &["h2c"], 1000),
+            None
+        );
+
+        let text = store.to_text(1000);
+        assert!(text.contains("h1 example.com 80 h2c example.com 8080 \"19700101 00:18:20\" 0 0\n"));
+        assert_eq!(Store::load(&text), store);
+
+        store.remove(&origin, &endpoint("h2c", "example.com", 8080));
+        assert_eq!(store.find(&origin, &["h2c"], 1000), None);
+        assert!(store.update(&origin, &Advertisement::Clear, 1000));
+        assert!(store.alternatives().is_empty());
+    }
+}
@@ -0,0 +1,196 @@
+//! Building `Authorization` header values.
+
+use crate::base64;
+use crate::error::{Error, Result};
+use crate::sha256::hex;
+use crate::websocket::random_u64;
+use crate::{md5, sha256};
+
+/// The header value for HTTP Basic authentication (RFC 7617).
+pub fn basic(user: &str, password: &str) -> String {
+    format!(
+        "Basic {}",
+        base64::encode(format!("{}:{}", user, password).as_bytes())
+    )
+}
+
+/// One challenge from a `WWW-Authenticate` or `Proxy-Authenticate` header:
+/// its scheme and its `name=value` parameters, names lowercased.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Challenge {
+    pub scheme: String,
+    pub params: Vec<(String, String)>,
+}
+
+impl Challenge {
+    /// Reads a header value holding a single challenge, such as
+    /// `Digest realm="x", nonce="y"`. `None` if it has no scheme.
+    pub fn parse(value: &str) -> Option<Challenge> {
+        let value = value.trim();
+        let (scheme, mut rest) = value.split_once(' ').unwrap_or((value, ""));
+        if scheme.is_empty() {
+            return None;
+        }
+        let mut params = Vec::new();
+        loop {
+            rest = rest.trim_start_matches([' ', '\t', ',']);
+            let Some((name, after)) = rest.split_once('=') else {
+                break;
+            };
+            let after = after.trim_start();
+            let (value, remaining) = match after.strip_prefix('"') {
+                Some(quoted) => {
+                    let mut value = String::new();
+                    let mut chars = quoted.char_indices();
+                    let mut end = quoted.len();
+                    while let Some((i, c)) = chars.next() {
+                        match c {
+                            '"' => {
+                                end = i + 1;
+                                break;
+                            }
+                            '\\' => value.extend(chars.next().map(|(_, c)| c)),
+                            c => value.push(c),
+                        }
+                    }
+                    (value, &quoted[end..])
+                }
+                None => {
+                    let end = after.find(',').unwrap_or(after.len());
+                    (after[..end].trim().to_string(), &after[end..])
+                }
+            };
+            params.push((name.trim().to_ascii_lowercase(), value));
+            rest = remaining;
+        }
+        Some(Challenge {
+            scheme: scheme.to_string(),
+            params,
+        })
+    }
+
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// A fresh client nonce for [`digest`].
+pub fn cnonce() -> String {
+    format!("{:016x}", random_u64())
+}
+
+/// The header value answering a Digest challenge (RFC 7616) for `method`
+/// on `uri`, the request target as sent. MD5 and SHA-256 are supported,
+/// with or without `-sess`, and `qop=auth` is used when offered.
+pub fn digest(
+    challenge: &Challenge,
+    user: &str,
+    password: &str,
+    method: &str,
+    uri: &str,
+    cnonce: &str,
+) -> Result<String> {
+    let param = |name| challenge.param(name).unwrap_or_default();
+    let algorithm = challenge.param("algorithm").unwrap_or("MD5");
+    let hash: fn(&[u8]) -> String = match algorithm.to_ascii_uppercase().as_str() {
+        "MD5" | "MD5-SESS" => |data| hex(&md5::digest(data)),
+        "SHA-256" | "SHA-256-SESS" => |data| hex(&sha256::digest(data)),
+        _ => {
+            return Err(Error::HttpProtocol(format!(
+                "unsupported Digest algorithm '{}'",
+                algorithm
+            )))
+        }
+    };
+    let (realm, nonce) = (param("realm"), param("nonce"));
+    let qop_auth = param("qop")
+        .split(',')
+        .any(|qop| qop.trim().eq_ignore_ascii_case("auth"));
+    if challenge.param("qop").is_some() && !qop_auth {
+        return Err(Error::HttpProtocol(
+            "Digest challenge offers no qop=auth".to_string(),
+        ));
+    }
+
+    let mut ha1 = hash(format!("{}:{}:{}", user, realm, password).as_bytes());
+    if algorithm.to_ascii_uppercase().ends_with("-SESS") {
+        ha1 = hash(format!("{}:{}:{}", ha1, nonce, cnonce).as_bytes());
+    }
+    let ha2 = hash(format!("{}:{}", method, uri).as_bytes());
+    let response = if qop_auth {
+        hash(format!("{}:{}:00000001:{}:auth:{}", ha1, nonce, cnonce, ha2).as_bytes())
+    } else {
+        // RFC 2069, for servers that predate qop.
+        hash(format!("{}:{}:{}", ha1, nonce, ha2).as_bytes())
+    };
+
+    let mut value = format!(
+        "Digest username=\"{}\", realm=\"{}\", nonce=\"{}\", uri=\"{}\", response=\"{}\", algorithm={}",
+        user, realm, nonce, uri, response, algorithm
+    );
+    if qop_auth {
+        value.push_str(&format!(", qop=auth, nc=00000001, cnonce=\"{}\"", cnonce));
+    }
+    if let Some(opaque) = challenge.param("opaque") {
+        value.push_str(&format!(", opaque=\"{}\"", opaque));
+    }
+    Ok(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_challenges() {
+        let challenge =
+            Challenge::parse("Digest realm=\"a \\\"b\\\", c\", qop=\"auth,auth-int\",stale=FALSE")
+                .unwrap();
+        assert_eq!(challenge.scheme, "Digest");
+        assert_eq!(challenge.param("realm"), Some("a \"b\", c"));
+        assert_eq!(challenge.param("qop"), Some("auth,auth-int"));
+        assert_eq!(challenge.param("stale"), Some("FALSE"));
+        assert!(Challenge::parse("Basic").unwrap().params.is_empty());
+        assert_eq!(Challenge::parse(" "), None);
+    }
+
+    #[test]
+    fn answers_the_rfc_7616_examples() {
+        let text = "Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", \
+            nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", \
+            opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"";
+        let cnonce = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ";
+        let answer = |algorithm: &str| {
+            let challenge = Challenge::parse(&format!("{}, algorithm={}", text, algorithm));
+            let value = digest(
+                &challenge.unwrap(),
+                "Mufasa",
+                "Circle of Life",
+                "GET",
+                "/dir/index.html",
+                cnonce,
+            )
+            .unwrap();
+            Challenge::parse(&value).unwrap()
+        };
+        let md5 = answer("MD5");
+        assert_eq!(
+            md5.param("response"),
+            Some("8ca523f5e9506fed4657c9700eebdbec")
+        );
+        assert_eq!(md5.param("nc"), Some("00000001"));
+        assert_eq!(
+            md5.param("opaque"),
+            Some("FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS")
+        );
+        assert_eq!(
+            answer("SHA-256").param("response"),
+            Some("753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1")
+        );
+        let challenge = Challenge::parse("Digest nonce=\"n\", algorithm=SHA-512-256").unwrap();
+        assert!(digest(&challenge, "u", "p", "GET", "/", "c").is_err());
+    }
+}
@@ -0,0 +1,106 @@
+//! Standard (RFC 4648) base64 encoding, as used by Basic authentication,
+//! and its URL-safe variant, as used by `HTTP2-Settings`. Decoding is for
+//! the tokens of NTLM challenges.
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+const URL_ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+/// Encodes `input` with padding.
+pub fn encode(input: &[u8]) -> String {
+    encode_with(input, ALPHABET, true)
+}
+
+/// Encodes `input` with the URL-safe alphabet and no padding.
+pub fn encode_url(input: &[u8]) -> String {
+    encode_with(input, URL_ALPHABET, false)
+}
+
+/// Decodes standard base64, with or without padding. `None` if `input`
+/// has anything else in it.
+pub fn decode(input: &str) -> Option<Vec<u8>> {
+    let input = input.trim_end_matches('=').as_bytes();
+    let mut out = Vec::with_capacity(input.len() * 3 / 4);
+    let mut n = 0u32;
+    for (i, &c) in input.iter().enumerate() {
+        let value = ALPHABET.iter().position(|&a| a == c)? as u32;
+        n = n << 6 | value;
+        if i % 4 == 3 {
+            out.extend_from_slice(&n.to_be_bytes()[1..]);
+            n = 0;
+        }
+    }
+    match input.len() % 4 {
+        0 => {}
+        2 => out.push((n >> 4) as u8),
+        3 => out.extend_from_slice(&((n >> 2) as u16).to_be_bytes()),
+        _ => return None,
+    }
+    Some(out)
+}
+
+fn encode_with(input: &[u8], alphabet: &[u8; 64], pad: bool) -> String {
+    let mut out = String::with_capacity(input.len().div_ceil(3) * 4);
+    for chunk in input.chunks(3) {
+        let b = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let n = (b[0] as u32) << 16 | (b[1] as u32) << 8 | b[2] as u32;
+        out.push(alphabet[(n >> 18) as usize & 63] as char);
+        out.push(alphabet[(n >> 12) as usize & 63] as char);
+        if chunk.len() > 1 {
+            out.push(alphabet[(n >> 6) as usize & 63] as char);
+        } else if pad {
+            out.push('=');
+        }
+        if chunk.len() > 2 {
+            out.push(alphabet[n as usize & 63] as char);
+        } else if pad {
+            out.push('=');
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_the_rfc_4648_vectors() {
+        // Section 10.
+        for (input, encoded) in [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ] {
+            assert_eq!(encode(input.as_bytes()), encoded);
+            assert_eq!(decode(encoded).unwrap(), input.as_bytes());
+            assert_eq!(encode_url(input.as_bytes()), encoded.trim_end_matches('='));
+        }
+        assert_eq!(encode_url(&[0xfb, 0xff, 0xbf]), "-_-_");
+    }
+
+    #[test]
+    fn round_trips_and_refuses_bad_input() {
+        let bytes: Vec<u8> = (0..=255).collect();
+        for len in 0..bytes.len() {
+            let encoded = encode(&bytes[..len]);
+            assert_eq!(encoded.len() % 4, 0);
+            assert_eq!(decode(&encoded).unwrap(), &bytes[..len]);
+            assert_eq!(
+                decode(encoded.trim_end_matches('=')).unwrap(),
+                &bytes[..len]
+            );
+        }
+        assert_eq!(decode("Zm9v!"), None);
+        assert_eq!(decode("-_-_"), None);
+        // One character left over cannot hold a byte.
+        assert_eq!(decode("Zm9vY"), None);
+    }
+}
@@ -0,0 +1,344 @@
+//! `--url-file`, and URL globs: fetching a list of URLs, one after another
+//! or in parallel.
+//!
+//! Each URL may be a glob (see [`glob`]) standing for several. Responses
+//! are written in list order whatever order they arrive in, and a result
+//! line per URL goes to stderr. `-o` may name each output with `#{index}`
+//! (the URL's position in the list), `#{host}`, `#{path}` (the URL's path,
+//! as `--mirror` would lay it out), `#{name}` (its last segment) and `#1`,
+//! `#2` and so on (what the glob's patterns stood for); without any, every
+//! body goes into the one file.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use http_client::error::{Error, Result};
+use http_client::glob::{self, Expansion};
+use http_client::http;
+use http_client::response::Response;
+use http_client::template::Vars;
+use http_client::trace::Trace;
+use http_client::url::Url;
+
+use crate::cli::Options;
+use crate::i18n::{tr, Msg};
+
+/// The URLs in a list: one per line, blank lines and `#` comments skipped.
+pub fn parse_list(text: &str) -> Vec<&str> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect()
+}
+
+/// Fills in an `-o` template for the `index`-th URL, `url`, whose glob
+/// patterns stood for `matches`. The name falls back to `index.html` for
+/// URLs ending in `/` (or in `.` or `..`, which would otherwise leave the
+/// directory), and so does the path.
+pub fn output_name(template: &str, index: usize, url: &Url, matches: &[String]) -> String {
+    let name = url.path().rsplit('/').next().unwrap_or_default();
+    let name = match name {
+        "" | "." | ".." => "index.html",
+        name => name,
+    };
+    let mut path: Vec<&str> = url
+        .path()
+        .split('/')
+        .filter(|segment| !matches!(*segment, "" | "." | ".."))
+        .collect();
+    if url.path().ends_with('/') {
+        path.push("index.html");
+    }
+    let filled = template
+        .replace("#{index}", &index.to_string())
+        .replace("#{name}", name)
+        .replace("#{host}", url.host())
+        .replace("#{path}", &path.join("/"));
+    // #1, #2, ...: a number with no match is left as written.
+    let mut out = String::new();
+    let mut rest = filled.as_str();
+    while let Some(at) = rest.find('#') {
+        out.push_str(&rest[..at]);
+        rest = &rest[at + 1..];
+        let digits = rest
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(rest.len());
+        let matched = rest[..digits]
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| matches.get(n.checked_sub(1)?));
+        match matched {
+            Some(matched) => out.push_str(matched),
+            None => {
+                out.push('#');
+                out.push_str(&rest[..digits]);
+            }
+        }
+        rest = &rest[digits..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn is_template(path: &str) -> bool {
+    ["#{index}", "#{name}", "#{host}", "#{path}"]
+        .iter()
+        .any(|variable| path.contains(variable))
+        || path
+            .match_indices('#')
+            .any(|(at, _)| path[at + 1..].starts_with(|c: char| c.is_ascii_digit()))
+}
+
+/// A transfer's line in the summary printed after a batch of several URLs.
+#[derive(Debug)]
+pub struct Row {
+    pub url: String,
+    pub status: Option<u16>,
+    pub bytes: usize,
+    pub elapsed: Duration,
+    /// Why the transfer failed, if it did.
+    pub error: Option<String>,
+}
+
+/// Fetches the URL on the command line and every URL in the list at
+/// `path`, if there is one, with the other options, up to `--parallel-max`
+/// at a time with `--parallel`. Failures do not stop the run unless
+/// `--fail-early` is given; the first one in list order is returned at the
+/// end. A batch of several URLs ends with a summary table on stderr.
+pub fn run(
+    opts: &Options,
+    path: Option<&str>,
+    vars: &Vars,
+    trace: Option<&Trace>,
+    limits: &http::Limits,
+) -> Result<()> {
+    let text = path.map(crate::read_text).transpose()?.unwrap_or_default();
+    // A URL on the command line, already expanded, goes first.
+    let mut listed: Vec<String> = opts.url.iter().cloned().collect();
+    for url in parse_list(&text) {
+        listed.push(vars.expand(url)?);
+    }
+    let mut urls = Vec::new();
+    for url in &listed {
+        match opts.globoff {
+            true => urls.push(Expansion::plain(url)),
+            false => urls.extend(glob::expand(url)?),
+        }
+    }
+    let workers = if opts.parallel {
+        opts.parallel_max.min(urls.len()).max(1)
+    } else {
+        1
+    };
+
+    let next = AtomicUsize::new(0);
+    let (sender, receiver) = mpsc::channel();
+    let mut outcome = Ok(());
+    let mut rows = Vec::new();
+    thread::scope(|scope| -> Result<()> {
+        for _ in 0..workers {
+            let sender = sender.clone();
+            let (urls, next) = (&urls, &next);
+            scope.spawn(move || loop {
+                let i = next.fetch_add(1, Ordering::Relaxed);
+                let Some(url) = urls.get(i) else {
+                    return;
+                };
+                let start = Instant::now();
+                let result = fetch(opts, &url.url, trace, limits);
+                if opts.fail_early && result.is_err() {
+                    next.store(urls.len(), Ordering::Relaxed);
+                }
+                if sender.send((i, result, start.elapsed())).is_err() {
+                    return;
+                }
+            });
+        }
+        drop(sender);
+
+        // Write results in list order as soon as all earlier ones are in.
+        let mut pending = BTreeMap::new();
+        let mut written = 0;
+        let mut combined = Vec::new();
+        // After a failure with --fail-early, transfers still under way are
+        // let finish but not reported.
+        let mut stopped = false;
+        for (i, result, elapsed) in receiver {
+            pending.insert(i, (result, elapsed));
+            while let Some((result, elapsed)) = pending.remove(&written).filter(|_| !stopped) {
+                let response = result.as_ref().ok().map(|(_, response)| response);
+                let mut row = Row {
+                    url: urls[written].url.clone(),
+                    status: response.map(|response| response.status.code),
+                    bytes: response.map_or(0, |response| response.body.len()),
+                    elapsed,
+                    error: None,
+                };
+                written += 1;
+                let result = report(opts, written, &urls, result, elapsed, &mut combined);
+                row.error = result.as_ref().err().map(|e| tr(Msg::Error(e)));
+                rows.push(row);
+                if let (Ok(()), Err(e)) = (&outcome, result) {
+                    if opts.fail_early || matches!(e, Error::File { .. }) {
+                        // Nowhere to write to, or asked to stop: hand out no
+                        // more URLs.
+                        next.store(urls.len(), Ordering::Relaxed);
+                        stopped = opts.fail_early;
+                    }
+                    outcome = Err(e);
+                }
+            }
+        }
+        if let Some(path) = opts.output.as_deref().filter(|p| !is_template(p)) {
+            crate::write_file(opts, path, false, &combined)?;
+        }
+        Ok(())
+    })?;
+    if urls.len() > 1 {
+        eprintln!("{}", tr(Msg::TransferSummary(&rows)));
+    }
+    outcome
+}
+
+/// `f` applied to each of `items` on up to `workers` threads, the results
+/// in the order of the items.
+pub fn each_parallel<T, R, F>(items: &[T], workers: usize, f: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let workers = workers.min(items.len()).max(1);
+    let next = AtomicUsize::new(0);
+    let mut results = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                scope.spawn(|| {
+                    let mut done = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(item) = items.get(i) else {
+                            return done;
+                        };
+                        done.push((i, f(item)));
+                    }
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("transfer worker panicked"))
+            .collect::<Vec<_>>()
+    });
+    results.sort_by_key(|(i, _)| *i);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+fn fetch(
+    opts: &Options,
+    url: &str,
+    trace: Option<&Trace>,
+    limits: &http::Limits,
+) -> Result<(Url, Response)> {
+    let url = crate::parse_url(opts, url)?;
+    let response = crate::fetch(
+        opts,
+        url.clone(),
+        crate::request_method(opts),
+        opts.data.clone(),
+        &[],
+        trace,
+        limits,
+    )?;
+    opts.expect.check(&response).map(|()| (url, response))
+}
+
+/// Prints the result line for the `n`-th URL and writes its output:
+/// straight to stdout, to its own `-o` file, or into `combined` for a
+/// single `-o` file.
+fn report(
+    opts: &Options,
+    n: usize,
+    urls: &[Expansion],
+    result: Result<(Url, Response)>,
+    elapsed: Duration,
+    combined: &mut Vec<u8>,
+) -> Result<()> {
+    let Expansion { url, matches } = &urls[n - 1];
+    let (parsed, response) = match result {
+        Ok(ok) => ok,
+        Err(e) => {
+            eprintln!("* {}", tr(Msg::TransferFailed(n, urls.len(), url, &e)));
+            return Err(e);
+        }
+    };
+    eprintln!(
+        "* {}",
+        tr(Msg::TransferDone(
+            n,
+            urls.len(),
+            url,
+            &response.status,
+            response.body.len(),
+            elapsed
+        ))
+    );
+    let output = crate::response_output(opts, &response)?;
+    let written = match opts.output.as_deref() {
+        Some(template) if is_template(template) => {
+            let path = output_name(template, n, &parsed, matches);
+            crate::write_file(opts, &path, false, &output)
+        }
+        Some(_) => {
+            combined.extend(output);
+            Ok(())
+        }
+        None => {
+            let mut stdout = crate::Stdout;
+            stdout
+                .write_all(&output)
+                .and_then(|()| stdout.flush())
+                .map_err(|source| Error::File {
+                    path: "stdout".to_string(),
+                    source,
+                })
+        }
+    };
+    written?;
+    crate::check_budgets(opts, elapsed, response.body.len() as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_lists() {
+        let text = "# mirrors\nhttp://a/\n\n  http://b/x  \n#http://c/\n";
+        assert_eq!(parse_list(text), ["http://a/", "http://b/x"]);
+    }
+
+    #[test]
+    fn names_outputs() {
+        let url = Url::parse("http://h/files/report.pdf?v=2").unwrap();
+        assert_eq!(
+            output_name("out/#{index}-#{name}", 3, &url, &[]),
+            "out/3-report.pdf"
+        );
+        let url = Url::parse("http://h/dir/").unwrap();
+        assert_eq!(output_name("#{name}", 1, &url, &[]), "index.html");
+        assert_eq!(
+            output_name("#{host}/#{path}", 1, &url, &[]),
+            "h/dir/index.html"
+        );
+        let matches = ["a".to_string(), "07".to_string()];
+        assert_eq!(output_name("#2-#1#3.#x", 1, &url, &matches), "07-a#3.#x");
+        assert!(!is_template("plain.txt"));
+        assert!(!is_template("#notes.txt"));
+        assert!(is_template("page-#1.html"));
+    }
+}
@@ -0,0 +1,240 @@
+//! Load generation for `--bench`.
+//!
+//! [`run`] sends the same request a fixed number of times from a pool of
+//! worker threads. Each worker keeps its own [`PersistentConnection`], so
+//! the numbers reflect keep-alive traffic rather than connection setup.
+//! A [`Collector`] given to [`run`] sees each request as it finishes.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::error::Error;
+use crate::headers::HeaderMap;
+use crate::http::{Limits, PersistentConnection};
+use crate::prometheus::Collector;
+
+/// How much load to generate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Config {
+    /// Total number of requests.
+    pub requests: u64,
+    /// Number of requests in flight at once, one connection each.
+    pub concurrency: usize,
+}
+
+/// The request every worker sends.
+pub struct Request<'a> {
+    pub host: &'a str,
+    pub port: u16,
+    pub method: &'a str,
+    pub target: &'a str,
+    pub headers: &'a HeaderMap,
+    pub body: Option<&'a str>,
+}
+
+/// What a benchmark run measured.
+#[derive(Debug, Default)]
+pub struct Stats {
+    /// Wall-clock time of the whole run.
+    pub elapsed: Duration,
+    /// Latency of every request that got a response, sorted ascending.
+    pub latencies: Vec<Duration>,
+    /// Responses by status code.
+    pub statuses: BTreeMap<u16, u64>,
+    /// Requests that failed, one entry per distinct error with its count.
+    pub errors: Vec<(Error, u64)>,
+    /// Response body bytes received.
+    pub bytes: u64,
+}
+
+impl Stats {
+    /// Requests that got a response, whatever its status.
+    pub fn completed(&self) -> u64 {
+        self.latencies.len() as u64
+    }
+
+    /// Requests that failed without a response.
+    pub fn failed(&self) -> u64 {
+        self.errors.iter().map(|(_, n)| n).sum()
+    }
+
+    /// Requests per second over the whole run, failures included.
+    pub fn throughput(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs == 0.0 {
+            return 0.0;
+        }
+        (self.completed() + self.failed()) as f64 / secs
+    }
+
+    /// The latency `percent` of the responses were at or below
+    /// (nearest-rank); `None` if nothing completed.
+    pub fn percentile(&self, percent: u32) -> Option<Duration> {
+        let n = self.latencies.len();
+        if n == 0 {
+            return None;
+        }
+        let rank = (n * percent.min(100) as usize).div_ceil(100);
+        Some(self.latencies[rank.saturating_sub(1)])
+    }
+
+    fn add_error(&mut self, err: Error, count: u64) {
+        let text = err.to_string();
+        match self.errors.iter_mut().find(|(e, _)| e.to_string() == text) {
+            Some((_, n)) => *n += count,
+            None => self.errors.push((err, count)),
+        }
+    }
+
+    fn merge(&mut self, other: Stats) {
+        self.latencies.extend(other.latencies);
+        for (code, n) in other.statuses {
+            *self.statuses.entry(code).or_default() += n;
+        }
+        for (err, n) in other.errors {
+            self.add_error(err, n);
+        }
+        self.bytes += other.bytes;
+    }
+}
+
+/// Sends `request` `config.requests` times with `config.concurrency`
+/// workers. Failures are counted, not returned; redirects are not followed.
+pub fn run(
+    request: &Request,
+    config: &Config,
+    limits: &Limits,
+    collector: Option<&Collector>,
+) -> Stats {
+    let next = AtomicU64::new(0);
+    let workers = (config.concurrency.max(1) as u64).min(config.requests.max(1));
+    let start = Instant::now();
+    let mut stats = thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| scope.spawn(|| worker(request, config.requests, &next, limits, collector)))
+            .collect();
+        let mut stats = Stats::default();
+        for handle in handles {
+            stats.merge(handle.join().expect("benchmark worker panicked"));
+        }
+        stats
+    });
+    stats.elapsed = start.elapsed();
+    stats.latencies.sort();
+    stats
+}
+
+/// Takes request numbers from `next` until `total` have been claimed.
+fn worker(
+    request: &Request,
+    total: u64,
+    next: &AtomicU64,
+    limits: &Limits,
+    collector: Option<&Collector>,
+) -> Stats {
+    let mut conn = PersistentConnection::new(request.host, request.port);
+    let mut stats = Stats::default();
+    while next.fetch_add(1, Ordering::Relaxed) < total {
+        let start = Instant::now();
+        match conn.send(
+            request.method,
+            request.target,
+            request.headers,
+            request.body.map(str::as_bytes),
+            limits,
+        ) {
+            Ok(response) => {
+                let latency = start.elapsed();
+                if let Some(collector) = collector {
+                    collector.response(response.status.code, latency);
+                }
+                stats.latencies.push(latency);
+                *stats.statuses.entry(response.status.code).or_default() += 1;
+                stats.bytes += response.body.len() as u64;
+            }
+            Err(err) => {
+                if let Some(collector) = collector {
+                    collector.error();
+                }
+                stats.add_error(err, 1)
+            }
+        }
+    }
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Arc;
+
+    #[test]
+    fn percentiles_use_the_nearest_rank() {
+        let stats = Stats {
+            latencies: (1..=10).map(Duration::from_millis).collect(),
+            ..Stats::default()
+        };
+        assert_eq!(stats.percentile(50), Some(Duration::from_millis(5)));
+        assert_eq!(stats.percentile(90), Some(Duration::from_millis(9)));
+        assert_eq!(stats.percentile(99), Some(Duration::from_millis(10)));
+        assert_eq!(stats.percentile(0), Some(Duration::from_millis(1)));
+        assert_eq!(Stats::default().percentile(50), None);
+    }
+
+    #[test]
+    fn counts_responses_over_reused_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let served = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = stream.unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+                let served = served.clone();
+                thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    while crate::response::read_head(&mut reader).is_ok() {
+                        let n = served.fetch_add(1, Ordering::SeqCst) + 1;
+                        let status = if n.is_multiple_of(5) { 500 } else { 200 };
+                        let reply = format!("HTTP/1.1 {} X\r\nContent-Length: 2\r\n\r\nok", status);
+                        (&stream).write_all(reply.as_bytes()).unwrap();
+                    }
+                });
+            }
+        });
+
+        let headers: HeaderMap = [("Host", "h")].into_iter().collect();
+        let request = Request {
+            host: "127.0.0.1",
+            port,
+            method: "GET",
+            target: "/",
+            headers: &headers,
+            body: None,
+        };
+        let config = Config {
+            requests: 20,
+            concurrency: 2,
+        };
+        let collector = Collector::new();
+        let stats = run(&request, &config, &Limits::default(), Some(&collector));
+        assert_eq!(stats.completed(), 20);
+        assert_eq!(stats.failed(), 0);
+        assert_eq!(stats.statuses[&200], 16);
+        assert_eq!(stats.statuses[&500], 4);
+        assert_eq!(stats.bytes, 40);
+        assert!(stats.percentile(50) <= stats.percentile(99));
+        // At most one connection per worker.
+        assert!(connections.load(Ordering::SeqCst) <= 2);
+        let scraped = collector.render();
+        assert!(scraped.contains("http_client_requests_total{status=\"500\"} 4\n"));
+        assert!(scraped.contains("http_client_request_duration_seconds_count 20\n"));
+    }
+}
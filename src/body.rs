@@ -0,0 +1,426 @@
+//! Response body framing (RFC 9112 section 6).
+//!
+//! [`Body`] reads exactly one message body from a buffered connection,
+//! whether it is delimited by `Content-Length`, sent with chunked transfer
+//! coding, or runs until the server closes the connection.
+
+use std::io::{self, BufRead, Read};
+
+use crate::error::{Error, Result};
+use crate::headers::{is_token_byte, HeaderMap};
+use crate::response::MAX_HEAD_LEN;
+
+/// How the end of a body is found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Framing {
+    /// Exactly this many bytes; zero for responses without a body.
+    Length(u64),
+    /// `Transfer-Encoding: chunked`.
+    Chunked,
+    /// Everything until the connection closes.
+    Close,
+}
+
+impl Framing {
+    /// The framing a response with `headers` uses, given whether it may have
+    /// a body at all (see [`crate::response::has_body`]).
+    ///
+    /// Every `Transfer-Encoding` and `Content-Length` field counts, not just
+    /// the first. Lengths that disagree are an error, as RFC 9112 section
+    /// 6.3 requires. A response with both fields is framed by
+    /// Transfer-Encoding alone, unless `strict` makes it an error; either
+    /// way its connection is not reused (see [`has_conflicting_framing`]).
+    pub fn from_headers(headers: &HeaderMap, has_body: bool, strict: bool) -> Result<Framing> {
+        if !has_body {
+            return Ok(Framing::Length(0));
+        }
+        // Chunked must be the final coding for the length to be
+        // self-delimited.
+        let codings = headers
+            .get_all("Transfer-Encoding")
+            .flat_map(|value| value.split(','))
+            .map(str::trim)
+            .filter(|coding| !coding.is_empty());
+        if let Some(last) = codings.last() {
+            if strict && headers.contains("Content-Length") {
+                return Err(Error::HttpProtocol(
+                    "response has both Transfer-Encoding and Content-Length".to_string(),
+                ));
+            }
+            return Ok(if last.eq_ignore_ascii_case("chunked") {
+                Framing::Chunked
+            } else {
+                Framing::Close
+            });
+        }
+        Ok(content_length(headers)?.map_or(Framing::Close, Framing::Length))
+    }
+}
+
+/// The length every `Content-Length` field agrees on. A field may repeat
+/// the value as a list, `5, 5`, which proxies merging fields produce.
+fn content_length(headers: &HeaderMap) -> Result<Option<u64>> {
+    let mut length = None;
+    for value in headers.get_all("Content-Length") {
+        for item in value.split(',').map(str::trim) {
+            let n: u64 = item
+                .parse()
+                .ok()
+                .filter(|_| !item.is_empty() && item.bytes().all(|b| b.is_ascii_digit()))
+                .ok_or_else(|| {
+                    Error::HttpProtocol(format!("invalid Content-Length '{}'", value))
+                })?;
+            match length {
+                Some(first) if first != n => {
+                    return Err(Error::HttpProtocol(format!(
+                        "conflicting Content-Length values {} and {}",
+                        first, n
+                    )))
+                }
+                _ => length = Some(n),
+            }
+        }
+    }
+    Ok(length)
+}
+
+/// Whether a response carries both `Transfer-Encoding` and
+/// `Content-Length`, which may mean a proxy and the client disagree on
+/// where it ends; its connection is closed after it rather than risk
+/// reading the next response from the middle of this one.
+pub fn has_conflicting_framing(headers: &HeaderMap) -> bool {
+    headers.contains("Transfer-Encoding") && headers.contains("Content-Length")
+}
+
+#[derive(Debug, Clone, Copy)]
+enum State {
+    /// Bytes left in a length-delimited body or the current chunk.
+    Data(u64),
+    /// Between chunks: the next thing is a chunk-size line.
+    ChunkHeader,
+    /// Inside a chunk, with this many bytes left.
+    Chunk(u64),
+    Done,
+}
+
+/// A reader over one response body. It stops at the end of the body and
+/// never reads past it, so the connection stays positioned at the next
+/// message.
+pub struct Body<R> {
+    inner: R,
+    framing: Framing,
+    state: State,
+    received: u64,
+    /// The last chunk-size or trailer line, kept to be reused.
+    line: Vec<u8>,
+    trailers: HeaderMap,
+}
+
+impl<R: BufRead> Body<R> {
+    pub fn new(inner: R, framing: Framing) -> Body<R> {
+        let state = match framing {
+            Framing::Length(0) => State::Done,
+            Framing::Length(n) => State::Data(n),
+            Framing::Chunked => State::ChunkHeader,
+            Framing::Close => State::Data(u64::MAX),
+        };
+        Body {
+            inner,
+            framing,
+            state,
+            received: 0,
+            line: Vec::new(),
+            trailers: HeaderMap::new(),
+        }
+    }
+
+    /// The trailer fields after a chunked body, once it has been read.
+    /// Lines that are not `Name: value` are left out.
+    pub fn take_trailers(&mut self) -> HeaderMap {
+        std::mem::take(&mut self.trailers)
+    }
+
+    pub fn framing(&self) -> Framing {
+        self.framing
+    }
+
+    /// Fails if the connection closed before the body was complete. Only
+    /// meaningful once reading has returned end-of-file.
+    pub fn finish(&self) -> Result<()> {
+        match (&self.state, self.framing) {
+            (State::Done, _) | (_, Framing::Close) => Ok(()),
+            (_, Framing::Length(expected)) => Err(Error::PartialFile {
+                expected,
+                received: self.received,
+            }),
+            (_, Framing::Chunked) => Err(Error::HttpProtocol(
+                "connection closed in the middle of a chunked body".to_string(),
+            )),
+        }
+    }
+
+    /// Reads a line into `self.line` and returns it without the line end.
+    fn read_line(&mut self) -> io::Result<&[u8]> {
+        self.line.clear();
+        (&mut self.inner)
+            .take(4096)
+            .read_until(b'\n', &mut self.line)?;
+        if !self.line.ends_with(b"\n") {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "malformed chunked encoding",
+            ));
+        }
+        Ok(self.line.trim_ascii_end())
+    }
+
+    /// Reads the next chunk-size line; a zero-size chunk is followed by
+    /// optional trailer fields, which are kept, and a blank line. The
+    /// trailer section may be no longer than a response head.
+    fn next_chunk(&mut self) -> io::Result<()> {
+        let line = self.read_line()?;
+        let size = line.split(|&b| b == b';').next().unwrap_or_default();
+        let size = std::str::from_utf8(size.trim_ascii())
+            .ok()
+            .and_then(|size| u64::from_str_radix(size, 16).ok())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("invalid chunk size '{}'", String::from_utf8_lossy(line)),
+                )
+            })?;
+        if size > 0 {
+            self.state = State::Chunk(size);
+            return Ok(());
+        }
+        let mut total = 0;
+        loop {
+            let line = self.read_line()?;
+            if line.is_empty() {
+                break;
+            }
+            total += line.len();
+            if total > MAX_HEAD_LEN {
+                return Err(io::Error::other(Error::HttpProtocol(format!(
+                    "trailer fields exceed {} bytes",
+                    MAX_HEAD_LEN
+                ))));
+            }
+            let field = std::str::from_utf8(line)
+                .ok()
+                .and_then(|line| line.split_once(':'))
+                .filter(|(name, _)| !name.is_empty() && name.bytes().all(is_token_byte))
+                .map(|(name, value)| (name.to_string(), value.trim().to_string()));
+            if let Some((name, value)) = field {
+                self.trailers.append(name, value);
+            }
+        }
+        self.state = State::Done;
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for Body<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.state {
+                State::Done => return Ok(0),
+                State::ChunkHeader => {
+                    // A closed connection here leaves the state unfinished,
+                    // which `finish` reports.
+                    if self.inner.fill_buf()?.is_empty() {
+                        return Ok(0);
+                    }
+                    self.next_chunk()?;
+                }
+                State::Data(left) | State::Chunk(left) => {
+                    let max = buf.len().min(usize::try_from(left).unwrap_or(usize::MAX));
+                    let n = self.inner.read(&mut buf[..max])?;
+                    if n == 0 {
+                        if self.framing == Framing::Close {
+                            self.state = State::Done;
+                        }
+                        return Ok(0);
+                    }
+                    self.received += n as u64;
+                    let left = left - n as u64;
+                    self.state = match self.state {
+                        State::Data(_) if left == 0 => State::Done,
+                        State::Data(_) => State::Data(left),
+                        State::Chunk(_) if left == 0 => {
+                            let mut crlf = [0u8; 2];
+                            self.inner.read_exact(&mut crlf)?;
+                            if crlf != *b"\r\n" {
+                                return Err(io::Error::new(
+                                    io::ErrorKind::InvalidData,
+                                    "chunk is not followed by CRLF",
+                                ));
+                            }
+                            State::ChunkHeader
+                        }
+                        _ => State::Chunk(left),
+                    };
+                    return Ok(n);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read_all(wire: &[u8], framing: Framing) -> (Vec<u8>, Result<()>) {
+        let mut body = Body::new(wire, framing);
+        let mut out = Vec::new();
+        body.read_to_end(&mut out).unwrap();
+        let finished = body.finish();
+        (out, finished)
+    }
+
+    #[test]
+    fn picks_the_framing_from_headers() {
+        let headers: HeaderMap = [("Content-Length", "5")].into_iter().collect();
+        assert_eq!(
+            Framing::from_headers(&headers, true, false).unwrap(),
+            Framing::Length(5)
+        );
+        assert_eq!(
+            Framing::from_headers(&headers, false, false).unwrap(),
+            Framing::Length(0)
+        );
+        let headers: HeaderMap = [
+            ("Content-Length", "5"),
+            ("Transfer-Encoding", "gzip, chunked"),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(
+            Framing::from_headers(&headers, true, false).unwrap(),
+            Framing::Chunked
+        );
+        assert_eq!(
+            Framing::from_headers(&HeaderMap::new(), true, false).unwrap(),
+            Framing::Close
+        );
+        let headers: HeaderMap = [("Content-Length", "x")].into_iter().collect();
+        assert!(Framing::from_headers(&headers, true, false).is_err());
+    }
+
+    #[test]
+    fn refuses_conflicting_lengths() {
+        let framing = |fields: &[(&str, &str)], strict| {
+            let headers: HeaderMap = fields.iter().copied().collect();
+            Framing::from_headers(&headers, true, strict)
+        };
+        let same = [("Content-Length", "5"), ("content-length", "5, 5")];
+        assert_eq!(framing(&same, true).unwrap(), Framing::Length(5));
+        let err = framing(&[("Content-Length", "5"), ("Content-Length", "6")], false).unwrap_err();
+        assert!(err.to_string().contains("conflicting"), "{}", err);
+        assert!(framing(&[("Content-Length", "+5")], false).is_err());
+
+        let both = [
+            ("Transfer-Encoding", "gzip"),
+            ("Content-Length", "5"),
+            ("Transfer-Encoding", "chunked"),
+        ];
+        assert_eq!(framing(&both, false).unwrap(), Framing::Chunked);
+        assert!(framing(&both, true).is_err());
+        assert!(has_conflicting_framing(&both.into_iter().collect()));
+    }
+
+    #[test]
+    fn length_bodies_stop_at_the_length() {
+        let (out, finished) = read_all(b"hello, next", Framing::Length(5));
+        assert_eq!(out, b"hello");
+        assert!(finished.is_ok());
+        let (out, finished) = read_all(b"hel", Framing::Length(5));
+        assert_eq!(out, b"hel");
+        assert_eq!(finished.unwrap_err().exit_code(), 18);
+    }
+
+    #[test]
+    fn decodes_chunked_bodies() {
+        let wire = b"5;ext=1\r\nhello\r\n7\r\n, world\r\n0\r\nServer-Timing: db;dur=53\r\nbad line\r\nDigest: sha-256=x\r\n\r\nNEXT";
+        let mut body = Body::new(&wire[..], Framing::Chunked);
+        let mut out = Vec::new();
+        body.read_to_end(&mut out).unwrap();
+        assert_eq!(out, b"hello, world");
+        assert!(body.finish().is_ok());
+        assert_eq!(body.inner, b"NEXT");
+        let trailers = body.take_trailers();
+        assert_eq!(trailers.get("server-timing"), Some("db;dur=53"));
+        assert_eq!(trailers.get("Digest"), Some("sha-256=x"));
+        assert_eq!(trailers.iter().count(), 2);
+        assert!(body.take_trailers().is_empty());
+    }
+
+    #[test]
+    fn keeps_well_formed_trailer_fields() {
+        // No trailer section at all: the last chunk and a blank line.
+        let mut body = Body::new(&b"2\r\nhi\r\n0\r\n\r\nNEXT"[..], Framing::Chunked);
+        body.read_to_end(&mut Vec::new()).unwrap();
+        assert!(body.finish().is_ok());
+        assert!(body.take_trailers().is_empty());
+        assert_eq!(body.inner, b"NEXT");
+
+        // Lines that are not fields are skipped; repeats are kept.
+        let wire = b"0\r\nno colon\r\n: empty name\r\nbad name: x\r\n folded\r\nTag: a\r\nTag:b \r\n\xff: y\r\n\r\n";
+        let mut body = Body::new(&wire[..], Framing::Chunked);
+        body.read_to_end(&mut Vec::new()).unwrap();
+        let trailers = body.take_trailers();
+        assert_eq!(trailers.get_all("tag").collect::<Vec<_>>(), ["a", "b"]);
+        assert_eq!(trailers.iter().count(), 2);
+
+        // The trailer section must still end with a blank line.
+        let mut body = Body::new(&b"2\r\nhi\r\n0\r\nTag: a\r\n"[..], Framing::Chunked);
+        assert!(body.read_to_end(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn limits_the_trailer_section() {
+        // A server that never ends its trailers.
+        let endless = b"0\r\n".iter().copied().chain(
+            b"X-Pad: aaaaaaaaaaaaaaaaaaaaaaaa\r\n"
+                .iter()
+                .copied()
+                .cycle(),
+        );
+        let wire = io::BufReader::new(ByteIter(endless));
+        let mut body = Body::new(wire, Framing::Chunked);
+        let err = Error::from(body.read_to_end(&mut Vec::new()).unwrap_err());
+        assert!(matches!(err, Error::HttpProtocol(ref msg) if msg.contains("trailer")));
+        assert_eq!(err.exit_code(), 8);
+    }
+
+    /// Reads bytes from an iterator, which may never end.
+    struct ByteIter<I>(I);
+
+    impl<I: Iterator<Item = u8>> Read for ByteIter<I> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let mut n = 0;
+            for (slot, byte) in buf.iter_mut().zip(&mut self.0) {
+                *slot = byte;
+                n += 1;
+            }
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn reports_broken_chunked_bodies() {
+        let (out, finished) = read_all(b"5\r\nhello\r\n", Framing::Chunked);
+        assert_eq!(out, b"hello");
+        assert!(finished.is_err());
+        let mut body = Body::new(&b"zz\r\n"[..], Framing::Chunked);
+        assert!(body.read_to_end(&mut Vec::new()).is_err());
+    }
+
+    #[test]
+    fn close_delimited_bodies_run_to_eof() {
+        let (out, finished) = read_all(b"all of it", Framing::Close);
+        assert_eq!(out, b"all of it");
+        assert!(finished.is_ok());
+    }
+}
@@ -0,0 +1,310 @@
+//! A private on-disk HTTP cache for `--cache-dir` (RFC 9111).
+//!
+//! Each stored response is one file named after the SHA-1 of its URL,
+//! holding the time it was stored, the URL, the response head and the body.
+//! [`Entry::is_fresh`] decides from `Cache-Control: max-age`, `Expires` or
+//! a `Last-Modified` heuristic whether it can be used without asking the
+//! server; a stale entry is revalidated with `If-None-Match` or
+//! `If-Modified-Since`, and a `304 Not Modified` refreshes it.
+//!
+//! Only GET responses are stored, and not those that vary on request
+//! headers, since the cache does not record what was sent.
+
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::path::PathBuf;
+
+use crate::date;
+use crate::error::{Error, Result};
+use crate::headers::HeaderMap;
+use crate::response::{self, Response};
+use crate::sha1;
+
+/// The longest a `Last-Modified` heuristic may keep a response fresh.
+const MAX_HEURISTIC: u64 = 24 * 3600;
+
+/// Statuses that may be stored (the heuristically cacheable ones of RFC
+/// 9110 that this client can make use of).
+const STORABLE: [u16; 5] = [200, 203, 301, 404, 410];
+
+/// A cache directory.
+#[derive(Debug, Clone)]
+pub struct Cache {
+    dir: PathBuf,
+}
+
+/// A stored response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// When the response was stored or last revalidated, in Unix seconds.
+    pub stored: u64,
+    pub response: Response,
+}
+
+/// The directives of a `Cache-Control` header that matter here.
+#[derive(Debug, Default)]
+struct Directives {
+    no_store: bool,
+    no_cache: bool,
+    max_age: Option<u64>,
+}
+
+fn directives(headers: &HeaderMap) -> Directives {
+    let mut found = Directives::default();
+    for directive in headers.get_all("Cache-Control").flat_map(|v| v.split(',')) {
+        let (name, value) = directive.split_once('=').unwrap_or((directive, ""));
+        match name.trim().to_ascii_lowercase().as_str() {
+            "no-store" => found.no_store = true,
+            "no-cache" => found.no_cache = true,
+            "max-age" => found.max_age = value.trim().trim_matches('"').parse().ok(),
+            _ => {}
+        }
+    }
+    found
+}
+
+/// Whether `response` to a `method` request may be stored at all.
+pub fn is_storable(method: &str, response: &Response) -> bool {
+    method == "GET"
+        && STORABLE.contains(&response.status.code)
+        && !directives(&response.headers).no_store
+        && !response.headers.contains("Vary")
+}
+
+impl Cache {
+    /// Uses `dir`, creating it if needed.
+    pub fn open(dir: impl Into<PathBuf>) -> Result<Cache> {
+        let dir = dir.into();
+        fs::create_dir_all(&dir).map_err(|source| Error::File {
+            path: dir.display().to_string(),
+            source,
+        })?;
+        Ok(Cache { dir })
+    }
+
+    fn path(&self, url: &str) -> PathBuf {
+        let hex: String = sha1::digest(url.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect();
+        self.dir.join(hex)
+    }
+
+    /// The stored response for `url`, if there is a readable one.
+    pub fn get(&self, url: &str) -> Option<Entry> {
+        let file = fs::File::open(self.path(url)).ok()?;
+        let mut reader = BufReader::new(file);
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let stored = line.trim().parse().ok()?;
+        line.clear();
+        reader.read_line(&mut line).ok()?;
+        // Different URLs with the same hash are not expected, but cheap to
+        // rule out.
+        if line.trim_end_matches('\n') != url {
+            return None;
+        }
+        let head = response::read_head(&mut reader).ok()?;
+        let (status, headers) = response::parse_head(&head).ok()?;
+        let mut body = Vec::new();
+        reader.read_to_end(&mut body).ok()?;
+        Some(Entry {
+            stored,
+            response: Response {
+                status,
+                headers,
+                body,
+                trailers: HeaderMap::new(),
+            },
+        })
+    }
+
+    /// Stores `response` for `url` as of `now`, replacing any earlier one.
+    pub fn put(&self, url: &str, response: &Response, now: u64) -> Result<()> {
+        let mut data = format!("{}\n{}\n{}", now, url, response.head_text()).into_bytes();
+        data.extend_from_slice(&response.body);
+        // Write to a temporary file first so that a reader never sees half
+        // an entry.
+        let path = self.path(url);
+        let partial = path.with_extension("part");
+        fs::write(&partial, &data)
+            .and_then(|()| fs::rename(&partial, &path))
+            .map_err(|source| Error::File {
+                path: path.display().to_string(),
+                source,
+            })
+    }
+
+    /// Forgets the response for `url`, if any.
+    pub fn remove(&self, url: &str) {
+        let _ = fs::remove_file(self.path(url));
+    }
+}
+
+impl Entry {
+    /// How long the response stays fresh after it was generated.
+    fn lifetime(&self) -> u64 {
+        let headers = &self.response.headers;
+        let found = directives(headers);
+        if found.no_cache {
+            return 0;
+        }
+        if let Some(max_age) = found.max_age {
+            return max_age;
+        }
+        let date = headers
+            .get("Date")
+            .and_then(date::parse)
+            .unwrap_or(self.stored);
+        if let Some(expires) = headers.get("Expires") {
+            // An invalid date, such as "0", means already expired.
+            return date::parse(expires).map_or(0, |e| e.saturating_sub(date));
+        }
+        match headers.get("Last-Modified").and_then(date::parse) {
+            Some(modified) => (date.saturating_sub(modified) / 10).min(MAX_HEURISTIC),
+            None => 0,
+        }
+    }
+
+    /// Whether the response can be used at `now` without revalidation.
+    pub fn is_fresh(&self, now: u64) -> bool {
+        let age_when_stored = self
+            .response
+            .headers
+            .get("Age")
+            .and_then(|a| a.trim().parse().ok())
+            .unwrap_or(0u64);
+        let age = age_when_stored + now.saturating_sub(self.stored);
+        age < self.lifetime()
+    }
+
+    /// The headers that ask the server whether the response changed; empty
+    /// if it has no validators.
+    pub fn conditional_headers(&self) -> Vec<(&'static str, String)> {
+        let headers = &self.response.headers;
+        let mut conditions = Vec::new();
+        if let Some(etag) = headers.get("ETag") {
+            conditions.push(("If-None-Match", etag.to_string()));
+        }
+        if let Some(modified) = headers.get("Last-Modified") {
+            conditions.push(("If-Modified-Since", modified.to_string()));
+        }
+        conditions
+    }
+
+    /// The stored response updated with the headers of a `304 Not
+    /// Modified`, as of `now`.
+    pub fn revalidate(mut self, not_modified: &Response, now: u64) -> Entry {
+        for (name, _) in not_modified.headers.iter() {
+            if name.eq_ignore_ascii_case("Content-Length")
+                || name.eq_ignore_ascii_case("Transfer-Encoding")
+            {
+                continue;
+            }
+            self.response.headers.remove(name);
+            for value in not_modified.headers.get_all(name) {
+                self.response.headers.append(name, value);
+            }
+        }
+        // The stored Age was part of the old response.
+        if !not_modified.headers.contains("Age") {
+            self.response.headers.remove("Age");
+        }
+        self.stored = now;
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::response::StatusLine;
+
+    fn response(code: u16, headers: &[(&str, &str)]) -> Response {
+        Response {
+            status: StatusLine {
+                version: "HTTP/1.1".into(),
+                code,
+                reason: "X".into(),
+            },
+            headers: headers.iter().copied().collect(),
+            body: b"body".to_vec(),
+            trailers: HeaderMap::new(),
+        }
+    }
+
+    fn entry(headers: &[(&str, &str)]) -> Entry {
+        Entry {
+            stored: 1000,
+            response: response(200, headers),
+        }
+    }
+
+    #[test]
+    fn computes_freshness() {
+        let fresh = entry(&[("Cache-Control", "public, max-age=60")]);
+        assert!(fresh.is_fresh(1059));
+        assert!(!fresh.is_fresh(1060));
+        let aged = entry(&[("Cache-Control", "max-age=60"), ("Age", "50")]);
+        assert!(!aged.is_fresh(1010));
+        assert!(!entry(&[("Cache-Control", "max-age=60, no-cache")]).is_fresh(1000));
+
+        let date = date::format(1000);
+        let expires = entry(&[("Date", &date), ("Expires", &date::format(1100))]);
+        assert!(expires.is_fresh(1099));
+        assert!(!expires.is_fresh(1100));
+        assert!(!entry(&[("Expires", "0")]).is_fresh(1000));
+
+        let modified = entry(&[("Date", &date), ("Last-Modified", &date::format(0))]);
+        assert!(modified.is_fresh(1099));
+        assert!(!modified.is_fresh(1100));
+        assert!(!entry(&[]).is_fresh(1000));
+
+        assert!(is_storable("GET", &response(200, &[])));
+        assert!(!is_storable("POST", &response(200, &[])));
+        assert!(!is_storable("GET", &response(500, &[])));
+        assert!(!is_storable(
+            "GET",
+            &response(200, &[("Cache-Control", "no-store")])
+        ));
+        assert!(!is_storable("GET", &response(200, &[("Vary", "Cookie")])));
+    }
+
+    #[test]
+    fn stores_and_revalidates_entries() {
+        let dir = std::env::temp_dir().join(format!("http_client-cache-{}", std::process::id()));
+        let cache = Cache::open(&dir).unwrap();
+        let url = "http://example.com/a";
+        let stored = response(
+            200,
+            &[("ETag", "\"v1\""), ("Content-Length", "4"), ("Age", "5")],
+        );
+        cache.put(url, &stored, 1000).unwrap();
+        let entry = cache.get(url).unwrap();
+        assert_eq!(
+            entry,
+            Entry {
+                stored: 1000,
+                response: stored
+            }
+        );
+        assert!(cache.get("http://example.com/b").is_none());
+        assert_eq!(
+            entry.conditional_headers(),
+            [("If-None-Match", "\"v1\"".to_string())]
+        );
+
+        let not_modified = response(304, &[("ETag", "\"v1\""), ("Cache-Control", "max-age=9")]);
+        let entry = entry.revalidate(&not_modified, 2000);
+        assert_eq!(entry.stored, 2000);
+        assert_eq!(entry.response.status.code, 200);
+        assert_eq!(entry.response.body, b"body");
+        assert_eq!(entry.response.headers.get("Content-Length"), Some("4"));
+        assert_eq!(entry.response.headers.get("Age"), None);
+        assert!(entry.is_fresh(2008));
+
+        cache.remove(url);
+        assert!(cache.get(url).is_none());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,278 @@
+//! Recorded exchanges for `--record` and `--replay`, so scripts built on
+//! the client can be tested without the network, VCR style.
+//!
+//! A cassette is a YAML file with one entry per request. Strings are
+//! written as JSON strings, which YAML reads as double-quoted scalars, and
+//! bodies that are not UTF-8 are base64 instead. Only the layout written
+//! here is read back:
+//!
+//! ```text
+//! interactions:
+//!   - request:
+//!       method: "POST"
+//!       url: "http://api.test/items"
+//!       body: "{\"name\": \"x\"}"
+//!     response:
+//!       version: "HTTP/1.1"
+//!       status: 201
+//!       reason: "Created"
+//!       headers:
+//!         - ["Content-Type", "application/json"]
+//!       body: "{\"id\": 7}"
+//! ```
+
+use crate::base64;
+use crate::headers::HeaderMap;
+use crate::json::{self, Value};
+use crate::response::{Response, StatusLine};
+
+/// One request and the response it got.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Interaction {
+    pub method: String,
+    /// Without userinfo or fragment.
+    pub url: String,
+    pub body: Option<String>,
+    pub response: Response,
+}
+
+/// The interactions of a cassette, and which have been replayed.
+#[derive(Debug, Clone, Default)]
+pub struct Cassette {
+    interactions: Vec<Interaction>,
+    played: Vec<bool>,
+}
+
+fn quote(s: &str) -> String {
+    let mut out = String::new();
+    json::write_string(&mut out, s).expect("writing to a String cannot fail");
+    out
+}
+
+fn write_body(out: &mut String, indent: &str, body: &[u8]) {
+    match std::str::from_utf8(body) {
+        Ok(text) => out.push_str(&format!("{}body: {}\n", indent, quote(text))),
+        Err(_) => out.push_str(&format!(
+            "{}body_base64: {}\n",
+            indent,
+            quote(&base64::encode(body))
+        )),
+    }
+}
+
+/// A scalar after `key:`: a JSON string or a bare number.
+fn scalar(value: &str) -> Option<String> {
+    match json::parse(value).ok()? {
+        Value::String(s) => Some(s),
+        Value::Number(n) => Some(n),
+        _ => None,
+    }
+}
+
+impl Cassette {
+    pub fn new() -> Cassette {
+        Cassette::default()
+    }
+
+    /// Reads a cassette; `None` if the text is not one.
+    pub fn parse(text: &str) -> Option<Cassette> {
+        let mut cassette = Cassette::new();
+        let mut in_response = false;
+        let mut started = false;
+        for line in text.lines() {
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            if trimmed == "interactions:" {
+                started = true;
+                continue;
+            }
+            if !started {
+                return None;
+            }
+            if trimmed == "- request:" {
+                cassette.interactions.push(Interaction {
+                    method: String::new(),
+                    url: String::new(),
+                    body: None,
+                    response: Response {
+                        status: StatusLine::parse("HTTP/1.1 200").ok()?,
+                        headers: HeaderMap::new(),
+                        body: Vec::new(),
+                        trailers: HeaderMap::new(),
+                    },
+                });
+                in_response = false;
+                continue;
+            }
+            let current = cassette.interactions.last_mut()?;
+            if trimmed == "response:" {
+                in_response = true;
+                continue;
+            }
+            if trimmed == "headers:" {
+                continue;
+            }
+            if let Some(pair) = trimmed.strip_prefix("- ") {
+                let Value::Array(items) = json::parse(pair).ok()? else {
+                    return None;
+                };
+                let [name, value] = items.as_slice() else {
+                    return None;
+                };
+                current
+                    .response
+                    .headers
+                    .append(name.as_str()?, value.as_str()?);
+                continue;
+            }
+            let (key, value) = trimmed.split_once(':')?;
+            let value = scalar(value.trim())?;
+            let response = &mut current.response;
+            match (in_response, key) {
+                (false, "method") => current.method = value,
+                (false, "url") => current.url = value,
+                (false, "body") => current.body = Some(value),
+                (true, "version") => response.status.version = value,
+                (true, "status") => response.status.code = value.parse().ok()?,
+                (true, "reason") => response.status.reason = value,
+                (true, "body") => response.body = value.into_bytes(),
+                (true, "body_base64") => response.body = base64::decode(&value)?,
+                _ => return None,
+            }
+        }
+        cassette.played = vec![false; cassette.interactions.len()];
+        Some(cassette)
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut out = String::from("interactions:\n");
+        for interaction in &self.interactions {
+            out.push_str("  - request:\n");
+            out.push_str(&format!("      method: {}\n", quote(&interaction.method)));
+            out.push_str(&format!("      url: {}\n", quote(&interaction.url)));
+            if let Some(body) = &interaction.body {
+                out.push_str(&format!("      body: {}\n", quote(body)));
+            }
+            let response = &interaction.response;
+            out.push_str("    response:\n");
+            out.push_str(&format!(
+                "      version: {}\n",
+                quote(&response.status.version)
+            ));
+            out.push_str(&format!("      status: {}\n", response.status.code));
+            out.push_str(&format!(
+                "      reason: {}\n",
+                quote(&response.status.reason)
+            ));
+            out.push_str("      headers:\n");
+            for (name, value) in response.headers.iter() {
+                out.push_str(&format!("        - [{}, {}]\n", quote(name), quote(value)));
+            }
+            write_body(&mut out, "      ", &response.body);
+        }
+        out
+    }
+
+    pub fn len(&self) -> usize {
+        self.interactions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.interactions.is_empty()
+    }
+
+    pub fn record(&mut self, method: &str, url: &str, body: Option<&str>, response: &Response) {
+        self.interactions.push(Interaction {
+            method: method.to_string(),
+            url: url.to_string(),
+            body: body.map(str::to_string),
+            response: response.clone(),
+        });
+        // A recorded exchange is not there to be replayed in the same run.
+        self.played.push(true);
+    }
+
+    /// The response recorded for this request: the first one not replayed
+    /// yet, so repeated requests get their responses in order, and after
+    /// that the last one again.
+    pub fn replay(&mut self, method: &str, url: &str, body: Option<&str>) -> Option<Response> {
+        let matching: Vec<usize> = (0..self.interactions.len())
+            .filter(|&i| {
+                let recorded = &self.interactions[i];
+                recorded.method == method && recorded.url == url && recorded.body.as_deref() == body
+            })
+            .collect();
+        let i = matching
+            .iter()
+            .copied()
+            .find(|&i| !self.played[i])
+            .or(matching.last().copied())?;
+        self.played[i] = true;
+        Some(self.interactions[i].response.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(code: u16, body: &[u8]) -> Response {
+        let mut headers = HeaderMap::new();
+        headers.append("Content-Type", "text/plain; charset=\"utf-8\"");
+        Response {
+            status: StatusLine::parse(&format!("HTTP/1.1 {} Some Reason", code)).unwrap(),
+            headers,
+            body: body.to_vec(),
+            trailers: HeaderMap::new(),
+        }
+    }
+
+    #[test]
+    fn round_trips_through_yaml() {
+        let mut cassette = Cassette::new();
+        cassette.record(
+            "GET",
+            "http://a.test/",
+            None,
+            &response(200, b"line\n\"two\""),
+        );
+        cassette.record(
+            "POST",
+            "http://a.test/x",
+            Some("k=v"),
+            &response(404, b"\xff\x00"),
+        );
+        let text = cassette.to_text();
+        assert!(
+            text.contains("        - [\"Content-Type\", \"text/plain; charset=\\\"utf-8\\\"\"]\n")
+        );
+        let mut loaded = Cassette::parse(&text).unwrap();
+        assert_eq!(loaded.len(), 2);
+        let got = loaded.replay("GET", "http://a.test/", None).unwrap();
+        assert_eq!(got, response(200, b"line\n\"two\""));
+        let got = loaded
+            .replay("POST", "http://a.test/x", Some("k=v"))
+            .unwrap();
+        assert_eq!(got.body, b"\xff\x00");
+        assert_eq!(loaded.replay("POST", "http://a.test/x", None), None);
+        assert!(Cassette::parse("some: other yaml\n").is_none());
+    }
+
+    #[test]
+    fn replays_repeated_requests_in_order() {
+        let mut cassette = Cassette::new();
+        cassette.record("GET", "http://a.test/job", None, &response(202, b"pending"));
+        cassette.record("GET", "http://a.test/job", None, &response(200, b"done"));
+        let mut cassette = Cassette::parse(&cassette.to_text()).unwrap();
+        let mut replay = || {
+            cassette
+                .replay("GET", "http://a.test/job", None)
+                .unwrap()
+                .status
+                .code
+        };
+        assert_eq!([replay(), replay(), replay()], [202, 200, 200]);
+    }
+}
@@ -0,0 +1,219 @@
+//! Decoding text bodies to UTF-8 for the terminal.
+//!
+//! The multi-byte tables in `charset/` are indexed by WHATWG Encoding
+//! pointer. Each is a sequence of little-endian `u16` code points, zero for
+//! no mapping, generated from Python's `gb18030` and `cp932` codecs. GB18030's
+//! four-byte sequences are not handled and decode to U+FFFD.
+
+/// An encoding a body can be decoded from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Charset {
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// Also used for `iso-8859-1` and `us-ascii`, as browsers do.
+    Windows1252,
+    Gbk,
+    ShiftJis,
+}
+
+const GBK: &[u8] = include_bytes!("charset/gbk.bin");
+const SHIFT_JIS: &[u8] = include_bytes!("charset/shift_jis.bin");
+
+/// 0x80 to 0x9F in windows-1252; the five unassigned bytes stay C1
+/// controls.
+const WINDOWS_1252_HIGH: [u16; 32] = [
+    0x20AC, 0x0081, 0x201A, 0x0192, 0x201E, 0x2026, 0x2020, 0x2021, 0x02C6, 0x2030, 0x0160, 0x2039,
+    0x0152, 0x008D, 0x017D, 0x008F, 0x0090, 0x2018, 0x2019, 0x201C, 0x201D, 0x2022, 0x2013, 0x2014,
+    0x02DC, 0x2122, 0x0161, 0x203A, 0x0153, 0x009D, 0x017E, 0x0178,
+];
+
+impl Charset {
+    /// The charset for a `charset=` label, ignoring case.
+    pub fn from_label(label: &str) -> Option<Charset> {
+        let label = label.trim().trim_matches('"').to_ascii_lowercase();
+        Some(match label.as_str() {
+            "utf-8" | "utf8" => Charset::Utf8,
+            "utf-16le" | "utf-16" => Charset::Utf16Le,
+            "utf-16be" => Charset::Utf16Be,
+            "iso-8859-1" | "latin1" | "l1" | "us-ascii" | "ascii" | "windows-1252" | "cp1252" => {
+                Charset::Windows1252
+            }
+            "gbk" | "gb2312" | "gb18030" | "cp936" | "x-gbk" => Charset::Gbk,
+            "shift_jis" | "shift-jis" | "sjis" | "windows-31j" | "cp932" | "ms_kanji" => {
+                Charset::ShiftJis
+            }
+            _ => return None,
+        })
+    }
+
+    /// The charset of a body: its byte order mark if it has one, which
+    /// wins as in the WHATWG Encoding standard, else `Content-Type`'s
+    /// `charset` parameter. Also returns the length of the mark.
+    pub fn detect(content_type: Option<&str>, body: &[u8]) -> Option<(Charset, usize)> {
+        if body.starts_with(b"\xEF\xBB\xBF") {
+            return Some((Charset::Utf8, 3));
+        }
+        if body.starts_with(b"\xFF\xFE") {
+            return Some((Charset::Utf16Le, 2));
+        }
+        if body.starts_with(b"\xFE\xFF") {
+            return Some((Charset::Utf16Be, 2));
+        }
+        let label = content_type?.split(';').skip(1).find_map(|param| {
+            let (name, value) = param.split_once('=')?;
+            name.trim().eq_ignore_ascii_case("charset").then_some(value)
+        })?;
+        Charset::from_label(label).map(|charset| (charset, 0))
+    }
+
+    /// Decodes `bytes`, replacing anything invalid with U+FFFD.
+    pub fn decode(self, bytes: &[u8]) -> String {
+        match self {
+            Charset::Utf8 => String::from_utf8_lossy(bytes).into_owned(),
+            Charset::Utf16Le | Charset::Utf16Be => {
+                let units = bytes.chunks(2).map(|pair| match (self, pair) {
+                    (Charset::Utf16Le, [a, b]) => u16::from_le_bytes([*a, *b]),
+                    (_, [a, b]) => u16::from_be_bytes([*a, *b]),
+                    _ => 0xFFFD,
+                });
+                char::decode_utf16(units)
+                    .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+                    .collect()
+            }
+            Charset::Windows1252 => bytes
+                .iter()
+                .map(|&b| match b {
+                    0x80..=0x9F => code_point(WINDOWS_1252_HIGH[b as usize - 0x80]),
+                    b => char::from(b),
+                })
+                .collect(),
+            Charset::Gbk => decode_double(bytes, gbk_single, gbk_pointer, GBK),
+            Charset::ShiftJis => {
+                decode_double(bytes, shift_jis_single, shift_jis_pointer, SHIFT_JIS)
+            }
+        }
+    }
+}
+
+/// The body as UTF-8, if it needs decoding: it has a byte order mark or
+/// another charset is declared. `None` for UTF-8, and for charsets not
+/// known here, which are left as they are.
+pub fn to_utf8(content_type: Option<&str>, body: &[u8]) -> Option<String> {
+    match Charset::detect(content_type, body)? {
+        (Charset::Utf8, 0) => None,
+        (charset, bom) => Some(charset.decode(&body[bom..])),
+    }
+}
+
+fn code_point(value: u16) -> char {
+    char::from_u32(value as u32).unwrap_or(char::REPLACEMENT_CHARACTER)
+}
+
+/// A single-byte character, or `None` for a lead byte.
+type Single = fn(u8) -> Option<char>;
+/// The table index of a lead and trail byte pair, if valid.
+type Pointer = fn(u8, u8) -> Option<usize>;
+
+fn decode_double(bytes: &[u8], single: Single, pointer: Pointer, table: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if let Some(c) = single(bytes[i]) {
+            out.push(c);
+            i += 1;
+            continue;
+        }
+        let mapped = bytes.get(i + 1).and_then(|&trail| {
+            let index = pointer(bytes[i], trail)? * 2;
+            let value = u16::from_le_bytes([*table.get(index)?, *table.get(index + 1)?]);
+            (value != 0).then(|| code_point(value))
+        });
+        match mapped {
+            Some(c) => {
+                out.push(c);
+                i += 2;
+            }
+            None => {
+                out.push(char::REPLACEMENT_CHARACTER);
+                // An ASCII trail byte starts the next character instead.
+                i += if bytes.get(i + 1).is_some_and(|b| !b.is_ascii()) {
+                    2
+                } else {
+                    1
+                };
+            }
+        }
+    }
+    out
+}
+
+fn gbk_single(b: u8) -> Option<char> {
+    match b {
+        0x00..=0x7F => Some(char::from(b)),
+        0x80 => Some('\u{20AC}'),
+        0xFF => Some(char::REPLACEMENT_CHARACTER),
+        _ => None,
+    }
+}
+
+fn gbk_pointer(lead: u8, trail: u8) -> Option<usize> {
+    let offset = match trail {
+        0x40..=0x7E => 0x40,
+        0x80..=0xFE => 0x41,
+        _ => return None,
+    };
+    Some((lead as usize - 0x81) * 190 + trail as usize - offset)
+}
+
+fn shift_jis_single(b: u8) -> Option<char> {
+    match b {
+        0x00..=0x80 => Some(char::from(b)),
+        0xA1..=0xDF => char::from_u32(0xFF61 + (b - 0xA1) as u32),
+        0xA0 | 0xFD..=0xFF => Some(char::REPLACEMENT_CHARACTER),
+        _ => None,
+    }
+}
+
+fn shift_jis_pointer(lead: u8, trail: u8) -> Option<usize> {
+    let lead_offset = if lead < 0xA0 { 0x81 } else { 0xC1 };
+    let offset = match trail {
+        0x40..=0x7E => 0x40,
+        0x80..=0xFC => 0x41,
+        _ => return None,
+    };
+    Some((lead as usize - lead_offset) * 188 + trail as usize - offset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_charsets() {
+        let detect = Charset::detect;
+        assert_eq!(
+            detect(Some("text/html; charset=\"Shift_JIS\""), b"x"),
+            Some((Charset::ShiftJis, 0))
+        );
+        assert_eq!(
+            detect(Some("text/plain;charset=gbk"), b"\xEF\xBB\xBFx"),
+            Some((Charset::Utf8, 3))
+        );
+        assert_eq!(detect(Some("text/plain; charset=koi8-r"), b"x"), None);
+        assert_eq!(detect(None, b"\xFE\xFF\0x"), Some((Charset::Utf16Be, 2)));
+        assert_eq!(to_utf8(Some("text/plain; charset=utf-8"), b"x"), None);
+    }
+
+    #[test]
+    fn decodes_to_utf8() {
+        assert_eq!(Charset::Windows1252.decode(b"caf\xE9 \x80"), "café €");
+        assert_eq!(Charset::Gbk.decode(b"\xC4\xE3\xBA\xC3, x"), "你好, x");
+        assert_eq!(Charset::ShiftJis.decode(b"\x82\xA0\xB1a"), "あｱa");
+        assert_eq!(
+            Charset::ShiftJis.decode(b"\x82 a\x82"),
+            "\u{FFFD} a\u{FFFD}"
+        );
+        assert_eq!(to_utf8(None, b"\xFF\xFEh\0i\0").as_deref(), Some("hi"));
+    }
+}
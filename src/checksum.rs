@@ -0,0 +1,165 @@
+//! Download verification for `--expected-hash`.
+//!
+//! The digest is given as `algorithm:hex`, or read from a sidecar file in
+//! the format `sha256sum` and friends write: one `hex  name` line per file.
+
+use std::fmt;
+
+use crate::error::{Error, Result};
+use crate::sha256::hex;
+use crate::{md5, sha1, sha256};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Algorithm {
+    Sha256,
+    Sha1,
+    Md5,
+}
+
+impl Algorithm {
+    pub fn parse(name: &str) -> Option<Algorithm> {
+        match name.to_ascii_lowercase().replace('-', "").as_str() {
+            "sha256" => Some(Algorithm::Sha256),
+            "sha1" => Some(Algorithm::Sha1),
+            "md5" => Some(Algorithm::Md5),
+            _ => None,
+        }
+    }
+
+    /// The algorithm a sidecar's extension names, such as `.sha256`.
+    pub fn for_sidecar(path: &str) -> Option<Algorithm> {
+        let extension = path.rsplit_once('.')?.1;
+        Algorithm::parse(extension.strip_suffix("sum").unwrap_or(extension))
+    }
+
+    pub fn digest(self, data: &[u8]) -> Vec<u8> {
+        match self {
+            Algorithm::Sha256 => sha256::digest(data).to_vec(),
+            Algorithm::Sha1 => sha1::digest(data).to_vec(),
+            Algorithm::Md5 => md5::digest(data).to_vec(),
+        }
+    }
+
+    fn hex_len(self) -> usize {
+        match self {
+            Algorithm::Sha256 => 64,
+            Algorithm::Sha1 => 40,
+            Algorithm::Md5 => 32,
+        }
+    }
+}
+
+impl fmt::Display for Algorithm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Sha1 => "sha1",
+            Algorithm::Md5 => "md5",
+        })
+    }
+}
+
+/// The digest a download must have.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Checksum {
+    pub algorithm: Algorithm,
+    /// Lowercase hex.
+    pub expected: String,
+}
+
+impl Checksum {
+    /// Reads `sha256:HEX`; also `sha1:` and `md5:`.
+    pub fn parse(spec: &str) -> Option<Checksum> {
+        let (name, digest) = spec.split_once(':')?;
+        Checksum::new(Algorithm::parse(name)?, digest)
+    }
+
+    fn new(algorithm: Algorithm, digest: &str) -> Option<Checksum> {
+        let digest = digest.trim();
+        let valid =
+            digest.len() == algorithm.hex_len() && digest.bytes().all(|b| b.is_ascii_hexdigit());
+        valid.then(|| Checksum {
+            algorithm,
+            expected: digest.to_ascii_lowercase(),
+        })
+    }
+
+    /// Picks the digest for the file `name` out of a sidecar's text. A
+    /// sidecar with a single line is taken to be about the download
+    /// whatever name it gives.
+    pub fn from_sidecar(algorithm: Algorithm, text: &str, name: &str) -> Option<Checksum> {
+        let lines: Vec<(&str, &str)> = text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let line = line.trim();
+                let (digest, file) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+                // `*` marks a file hashed in binary mode.
+                (digest, file.trim().trim_start_matches('*'))
+            })
+            .collect();
+        let digest = match lines.as_slice() {
+            [(digest, _)] => digest,
+            lines => {
+                &lines
+                    .iter()
+                    .find(|(_, file)| file.rsplit('/').next() == Some(name))?
+                    .0
+            }
+        };
+        Checksum::new(algorithm, digest)
+    }
+
+    pub fn verify(&self, data: &[u8]) -> Result<()> {
+        let actual = hex(&self.algorithm.digest(data));
+        if actual == self.expected {
+            return Ok(());
+        }
+        Err(Error::ChecksumMismatch {
+            algorithm: self.algorithm.to_string(),
+            expected: self.expected.clone(),
+            actual,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ABC: &str = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+
+    #[test]
+    fn verifies_digests() {
+        let checksum = Checksum::parse(&format!("SHA-256:{}", ABC.to_uppercase())).unwrap();
+        assert!(checksum.verify(b"abc").is_ok());
+        let err = checksum.verify(b"abd").unwrap_err();
+        assert!(matches!(err, Error::ChecksumMismatch { .. }));
+        assert!(Checksum::parse("md5:900150983cd24fb0d6963f7d28e17f72")
+            .unwrap()
+            .verify(b"abc")
+            .is_ok());
+        assert_eq!(Checksum::parse("sha256:abc"), None);
+        assert_eq!(Checksum::parse("crc32:352441c2"), None);
+    }
+
+    #[test]
+    fn reads_sidecars() {
+        assert_eq!(
+            Algorithm::for_sidecar("a.tar.gz.sha256"),
+            Some(Algorithm::Sha256)
+        );
+        assert_eq!(Algorithm::for_sidecar("SHA1SUMS"), None);
+        assert_eq!(Algorithm::for_sidecar("x.md5sum"), Some(Algorithm::Md5));
+        let single = format!("{}  other-name.tar.gz\n", ABC);
+        let checksum = Checksum::from_sidecar(Algorithm::Sha256, &single, "a.tar.gz").unwrap();
+        assert_eq!(checksum.expected, ABC);
+        let many = format!("{}  x.zip\n{} *dist/a.tar.gz\n", "0".repeat(64), ABC);
+        let checksum = Checksum::from_sidecar(Algorithm::Sha256, &many, "a.tar.gz").unwrap();
+        assert_eq!(checksum.expected, ABC);
+        assert_eq!(
+            Checksum::from_sidecar(Algorithm::Sha256, &many, "b.zip"),
+            None
+        );
+    }
+}
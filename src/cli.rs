@@ -0,0 +1,2001 @@
+//! Command-line parsing.
+//!
+//! Every option is described once in [`OPTIONS`]; the tokenizer uses that
+//! table to accept `--long`, `--long=value`, `--long value`, `-s value`,
+//! `-svalue` and clusters of short flags such as `-iv`, and `--` ends option
+//! processing.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use http_client::cassette::Cassette;
+use http_client::checksum::Checksum;
+use http_client::error::{Error, Result};
+use http_client::expect::{Budgets, Expectations, HeaderExpectation, StatusPattern};
+use http_client::headers::{self, CustomHeader};
+use http_client::http::{HttpVersion, PersistentConnection};
+#[cfg(feature = "json")]
+use http_client::jq::Filter;
+use http_client::middleware::Registry;
+use http_client::netrc::Netrc;
+use http_client::prometheus::Collector;
+use http_client::proxy::Proxy;
+use http_client::session::Session;
+use http_client::sigv4::Credentials;
+use http_client::socket::SocketOptions;
+use http_client::trace::TraceMode;
+
+use crate::export;
+use crate::i18n::{tr, Msg, LANGS};
+
+/// The static description of one option: everything the parser, config
+/// files, the help and shell completions know about it.
+#[derive(Debug)]
+pub struct OptSpec {
+    pub long: &'static str,
+    pub short: Option<char>,
+    /// The placeholder for the option's argument; `None` for flags.
+    pub value: Option<&'static str>,
+    /// A one-line description, used by shell completions.
+    pub help: &'static str,
+    /// The accepted (or suggested) values, if the argument is an enum.
+    pub choices: &'static [&'static str],
+    /// Which of [`TOPICS`] `--help <topic>` lists the option under.
+    pub topic: &'static str,
+    /// What giving the option does.
+    pub apply: Apply,
+}
+
+/// How an option changes [`Options`] when it is given.
+#[derive(Debug, Clone, Copy)]
+pub enum Apply {
+    Flag(fn(&mut Options)),
+    /// Any value is taken as it is.
+    Value(fn(&mut Options, String)),
+    /// The value is checked first; see [`invalid`].
+    Parse(fn(&mut Options, &OptSpec, String) -> Result<()>),
+}
+
+impl OptSpec {
+    pub fn takes_value(&self) -> bool {
+        self.value.is_some()
+    }
+
+    /// Whether the argument names a local file.
+    pub fn takes_file(&self) -> bool {
+        self.value == Some("file")
+    }
+
+    const fn choices(self, choices: &'static [&'static str]) -> OptSpec {
+        OptSpec { choices, ..self }
+    }
+
+    const fn topic(self, topic: &'static str) -> OptSpec {
+        OptSpec { topic, ..self }
+    }
+}
+
+const fn flag(
+    long: &'static str,
+    short: Option<char>,
+    help: &'static str,
+    set: fn(&mut Options),
+) -> OptSpec {
+    OptSpec {
+        long,
+        short,
+        value: None,
+        help,
+        choices: &[],
+        topic: "",
+        apply: Apply::Flag(set),
+    }
+}
+
+const fn valued(
+    long: &'static str,
+    short: Option<char>,
+    value: &'static str,
+    help: &'static str,
+    set: fn(&mut Options, String),
+) -> OptSpec {
+    OptSpec {
+        apply: Apply::Value(set),
+        ..parsed(long, short, value, help, |_, _, _| Ok(()))
+    }
+}
+
+const fn parsed(
+    long: &'static str,
+    short: Option<char>,
+    value: &'static str,
+    help: &'static str,
+    parse: fn(&mut Options, &OptSpec, String) -> Result<()>,
+) -> OptSpec {
+    OptSpec {
+        long,
+        short,
+        value: Some(value),
+        help,
+        choices: &[],
+        topic: "",
+        apply: Apply::Parse(parse),
+    }
+}
+
+/// The usage error for a value `spec` does not accept.
+fn invalid(spec: &OptSpec, value: &str) -> Error {
+    Error::Usage(tr(Msg::InvalidValue(&format!("--{}", spec.long), value)))
+}
+
+fn size(spec: &OptSpec, value: &str) -> Result<u64> {
+    parse_size(value).ok_or_else(|| invalid(spec, value))
+}
+
+fn millis(spec: &OptSpec, value: &str) -> Result<Duration> {
+    let ms = value.parse().map_err(|_| invalid(spec, value))?;
+    Ok(Duration::from_millis(ms))
+}
+
+/// A socket buffer size, which must fit in memory and not be zero.
+fn buffer_size(spec: &OptSpec, value: &str) -> Result<usize> {
+    parse_size(value)
+        .and_then(|size| usize::try_from(size).ok())
+        .filter(|&size| size > 0)
+        .ok_or_else(|| invalid(spec, value))
+}
+
+/// One of `spec`'s choices.
+fn choice(spec: &OptSpec, value: String) -> Result<String> {
+    if !spec.choices.contains(&value.as_str()) {
+        return Err(Error::Usage(tr(Msg::UnsupportedChoice(
+            &format!("--{}", spec.long),
+            &value,
+            spec.choices,
+        ))));
+    }
+    Ok(value)
+}
+
+/// `--http1.1` and its kind. The last version option wins, as in curl.
+fn http_version(opts: &mut Options, version: HttpVersion) {
+    opts.http_version = version;
+    opts.http3 = false;
+    opts.http3_only = false;
+}
+
+/// What `--help <topic>` can narrow the help down to.
+pub const TOPICS: &[&str] = &[
+    "request",
+    "auth",
+    "proxy",
+    "redirects",
+    "output",
+    "connection",
+    "protocols",
+    "tls",
+    "checks",
+    "cache",
+    "batch",
+    "load",
+    "debug",
+    "general",
+];
+
+/// Shells `--completions` can generate scripts for, and `rlwrap`, which
+/// gets the words `--interactive` understands.
+pub const SHELLS: &[&str] = &["bash", "zsh", "fish", "powershell", "rlwrap"];
+
+pub const OPTIONS: &[OptSpec] = &[
+    valued(
+        "url",
+        None,
+        "url",
+        "URL to fetch when none is given as an argument",
+        |opts, value| opts.url = Some(value),
+    )
+    .topic("request"),
+    // Any token goes, so extension methods such as PURGE work; the
+    // choices are only offered for completion.
+    parsed(
+        "request",
+        Some('X'),
+        "method",
+        "Request method to use",
+        |opts, spec, value| {
+            if value.is_empty() || !value.bytes().all(headers::is_token_byte) {
+                return Err(invalid(spec, &value));
+            }
+            opts.method = Some(value);
+            Ok(())
+        },
+    )
+    .topic("request")
+    .choices(&[
+        "GET", "HEAD", "POST", "PUT", "PATCH", "DELETE", "OPTIONS", "TRACE",
+    ]),
+    flag(
+        "dry-run",
+        None,
+        "Print the request instead of sending it",
+        |opts| opts.dry_run = true,
+    )
+    .topic("request"),
+    parsed(
+        "export",
+        None,
+        "format",
+        "Print the request as a curl command or a program",
+        |opts, spec, value| {
+            opts.export = Some(choice(spec, value)?);
+            Ok(())
+        },
+    )
+    .topic("request")
+    .choices(export::FORMATS),
+    parsed(
+        "request-target",
+        None,
+        "target",
+        "Send this request target instead of the URL's path",
+        |opts, spec, value| {
+            let form = value == "*" || value.starts_with('/') || value.contains("://");
+            if !form || value.bytes().any(|b| b <= b' ' || b == 0x7f) {
+                return Err(invalid(spec, &value));
+            }
+            opts.request_target = Some(value);
+            Ok(())
+        },
+    )
+    .topic("request"),
+    valued(
+        "request-file",
+        None,
+        "file",
+        "Send a pre-composed HTTP/1.1 request from a file",
+        |opts, value| opts.request_file = Some(value),
+    )
+    .topic("request"),
+    flag(
+        "fix-request",
+        None,
+        "Correct Host, Content-Length and line endings in --request-file",
+        |opts| opts.fix_request = true,
+    )
+    .topic("request"),
+    valued(
+        "script",
+        None,
+        "file",
+        "Run a hook script on each request and the final response",
+        |opts, value| opts.script = Some(value),
+    )
+    .topic("request"),
+    parsed(
+        "header",
+        Some('H'),
+        "header",
+        "Add, replace or remove a request header",
+        |opts, _, value| {
+            opts.headers.push(CustomHeader::parse(&value)?);
+            Ok(())
+        },
+    )
+    .topic("request"),
+    valued(
+        "user-agent",
+        Some('A'),
+        "name",
+        "User-Agent to send",
+        |opts, value| opts.user_agent = Some(value),
+    )
+    .topic("request"),
+    valued(
+        "referer",
+        Some('e'),
+        "url",
+        "Referer to send",
+        |opts, value| match value.strip_suffix(";auto") {
+            Some(referer) => {
+                opts.auto_referer = true;
+                opts.referer = Some(referer.to_string()).filter(|r| !r.is_empty());
+            }
+            None => opts.referer = Some(value),
+        },
+    )
+    .topic("request"),
+    valued(
+        "oauth2-bearer",
+        None,
+        "token",
+        "Send this token as Authorization: Bearer",
+        |opts, value| opts.oauth2_bearer = Some(value),
+    )
+    .topic("auth"),
+    valued(
+        "aws-sigv4",
+        None,
+        "provider",
+        "Sign requests with AWS Signature Version 4",
+        |opts, value| opts.aws_sigv4 = Some(value),
+    )
+    .topic("auth"),
+    flag("ntlm", None, "Authenticate with NTLM", |opts| {
+        opts.ntlm = true
+    })
+    .topic("auth"),
+    flag(
+        "negotiate",
+        None,
+        "Authenticate with Negotiate (NTLM tokens only)",
+        |opts| opts.negotiate = true,
+    )
+    .topic("auth"),
+    flag(
+        "netrc",
+        Some('n'),
+        "Take credentials from ~/.netrc",
+        |opts| opts.netrc = true,
+    )
+    .topic("auth"),
+    parsed(
+        "proxy",
+        Some('x'),
+        "[protocol://]host[:port]",
+        "Send requests through this HTTP proxy",
+        |opts, _, value| {
+            opts.proxy = Some(Proxy::parse(&value)?);
+            Ok(())
+        },
+    )
+    .topic("proxy"),
+    valued(
+        "proxy-user",
+        Some('U'),
+        "user:password",
+        "Credentials for the proxy",
+        |opts, value| opts.proxy_user = Some(value),
+    )
+    .topic("proxy"),
+    flag(
+        "proxy-basic",
+        None,
+        "Use Basic authentication with the proxy",
+        |opts| opts.proxy_digest = false,
+    )
+    .topic("proxy"),
+    flag(
+        "proxy-digest",
+        None,
+        "Use Digest authentication with the proxy",
+        |opts| opts.proxy_digest = true,
+    )
+    .topic("proxy"),
+    parsed(
+        "proxy-header",
+        None,
+        "header",
+        "Add a header to requests sent to the proxy",
+        |opts, _, value| {
+            opts.proxy_headers.push(CustomHeader::parse(&value)?);
+            Ok(())
+        },
+    )
+    .topic("proxy"),
+    valued(
+        "netrc-file",
+        None,
+        "file",
+        "Take credentials from this netrc file",
+        |opts, value| opts.netrc_file = Some(value),
+    )
+    .topic("auth"),
+    valued(
+        "session",
+        None,
+        "name",
+        "Keep headers, credentials and cookies in a named session",
+        |opts, value| opts.session = Some(value),
+    )
+    .topic("auth"),
+    valued(
+        "session-read-only",
+        None,
+        "name",
+        "Use a session without saving changes to it",
+        |opts, value| {
+            opts.session = Some(value);
+            opts.session_read_only = true;
+        },
+    )
+    .topic("auth"),
+    parsed(
+        "retry",
+        None,
+        "n",
+        "Retry a transiently failed transfer up to n times",
+        |opts, spec, value| {
+            opts.retry = value.parse().map_err(|_| invalid(spec, &value))?;
+            Ok(())
+        },
+    )
+    .topic("connection"),
+    parsed(
+        "retry-delay",
+        None,
+        "seconds",
+        "Wait this long between retries",
+        |opts, spec, value| {
+            opts.retry_delay = Some(parse_duration(&value).ok_or_else(|| invalid(spec, &value))?);
+            Ok(())
+        },
+    )
+    .topic("connection"),
+    flag("location", Some('L'), "Follow redirects", |opts| {
+        opts.follow = true
+    })
+    .topic("redirects"),
+    flag(
+        "location-trusted",
+        None,
+        "Follow redirects, sending credentials to other hosts too",
+        |opts| {
+            opts.follow = true;
+            opts.location_trusted = true;
+        },
+    )
+    .topic("redirects"),
+    flag(
+        "show-redirects",
+        None,
+        "Print each redirect followed and its timing",
+        |opts| opts.show_redirects = true,
+    )
+    .topic("redirects"),
+    parsed(
+        "max-redirs",
+        None,
+        "n",
+        "Maximum number of redirects to follow",
+        |opts, spec, value| {
+            opts.max_redirs = value.parse().map_err(|_| invalid(spec, &value))?;
+            Ok(())
+        },
+    )
+    .topic("redirects"),
+    valued("data", Some('d'), "data", "Request body", |opts, value| {
+        opts.data = Some(value)
+    })
+    .topic("request"),
+    flag("compress-request", None, "Gzip the request body", |opts| {
+        opts.compress_request = true
+    })
+    .topic("request"),
+    valued(
+        "upload-file",
+        Some('T'),
+        "file",
+        "Upload a file with PUT, streaming it from disk",
+        |opts, value| opts.upload_file = Some(value),
+    )
+    .topic("request"),
+    valued(
+        "output",
+        Some('o'),
+        "file",
+        "Write the output to a file",
+        |opts, value| opts.output = Some(value),
+    )
+    .topic("output"),
+    parsed(
+        "expected-hash",
+        None,
+        "algorithm:hex|@file",
+        "Fail unless the body has this digest",
+        |opts, spec, value| {
+            let sidecar = value.starts_with('@') || value.contains(":@");
+            if !sidecar && Checksum::parse(&value).is_none() {
+                return Err(invalid(spec, &value));
+            }
+            opts.expected_hash = Some(value);
+            Ok(())
+        },
+    )
+    .topic("output"),
+    parsed(
+        "jq",
+        None,
+        "filter",
+        "Print only what a jq-style path picks from a JSON body",
+        |opts, spec, value| {
+            #[cfg(feature = "json")]
+            let filter = Filter::parse(&value).ok_or_else(|| invalid(spec, &value))?;
+            #[cfg(not(feature = "json"))]
+            let filter = {
+                let _ = spec;
+                value
+            };
+            opts.jq = Some(filter);
+            Ok(())
+        },
+    )
+    .topic("output"),
+    flag(
+        "list-links",
+        None,
+        "Print the links of an HTML page instead of the page",
+        |opts| opts.list_links = true,
+    )
+    .topic("output"),
+    flag("pretty", None, "Indent JSON output", |opts| {
+        opts.pretty = true
+    })
+    .topic("output"),
+    valued(
+        "tee",
+        None,
+        "file",
+        "Also write the output to a file",
+        |opts, value| opts.tee = Some(value),
+    )
+    .topic("output"),
+    valued(
+        "dump-header",
+        Some('D'),
+        "file",
+        "Write every response head received to a file",
+        |opts, value| opts.dump_header = Some(value),
+    )
+    .topic("output"),
+    valued(
+        "output-dir",
+        None,
+        "dir",
+        "Put output files in this directory",
+        |opts, value| opts.output_dir = Some(value),
+    )
+    .topic("output"),
+    flag(
+        "create-dirs",
+        None,
+        "Create missing directories for output files",
+        |opts| opts.create_dirs = true,
+    )
+    .topic("output"),
+    flag(
+        "no-transcode",
+        None,
+        "Print text bodies in their own charset",
+        |opts| opts.no_transcode = true,
+    )
+    .topic("output"),
+    flag(
+        "remote-name",
+        Some('O'),
+        "Write the output to a file named after the URL",
+        |opts| opts.remote_name = true,
+    )
+    .topic("output"),
+    flag(
+        "remote-header-name",
+        Some('J'),
+        "With -O, take the file name from Content-Disposition",
+        |opts| opts.remote_header_name = true,
+    )
+    .topic("output"),
+    valued(
+        "write-out",
+        Some('w'),
+        "format",
+        "Write details of the transfer after it",
+        |opts, value| opts.write_out = Some(value),
+    )
+    .topic("output"),
+    flag(
+        "compressed",
+        None,
+        "Ask for a compressed response and decompress it",
+        |opts| opts.compressed = true,
+    )
+    .topic("output"),
+    parsed(
+        "max-filesize",
+        None,
+        "bytes",
+        "Maximum response body size to accept",
+        |opts, spec, value| {
+            opts.max_filesize = Some(parse_size(&value).ok_or_else(|| invalid(spec, &value))?);
+            Ok(())
+        },
+    )
+    .topic("connection"),
+    parsed(
+        "limit-rate",
+        None,
+        "speed",
+        "Receive at most this many bytes/sec",
+        |opts, spec, value| {
+            opts.limit_rate = Some(parse_size(&value).ok_or_else(|| invalid(spec, &value))?);
+            Ok(())
+        },
+    )
+    .topic("connection"),
+    parsed(
+        "speed-limit",
+        Some('Y'),
+        "speed",
+        "Abort transfers slower than this many bytes/sec",
+        |opts, spec, value| {
+            opts.speed_limit = Some(parse_size(&value).ok_or_else(|| invalid(spec, &value))?);
+            Ok(())
+        },
+    )
+    .topic("connection"),
+    parsed(
+        "speed-time",
+        Some('y'),
+        "seconds",
+        "How long a transfer may stay below --speed-limit",
+        |opts, spec, value| {
+            opts.speed_time = Some(value.parse().map_err(|_| invalid(spec, &value))?);
+            Ok(())
+        },
+    )
+    .topic("connection"),
+    flag(
+        "tcp-nodelay",
+        None,
+        "Disable Nagle's algorithm (the default)",
+        |opts| opts.socket.nodelay = true,
+    )
+    .topic("connection"),
+    flag(
+        "no-tcp-nodelay",
+        None,
+        "Let the kernel coalesce small writes",
+        |opts| opts.socket.nodelay = false,
+    )
+    .topic("connection"),
+    parsed(
+        "keepalive-time",
+        None,
+        "seconds",
+        "Send TCP keepalive probes after this much idle time",
+        |opts, spec, value| {
+            let secs: u64 = value
+                .parse()
+                .ok()
+                .filter(|&secs| secs > 0)
+                .ok_or_else(|| invalid(spec, &value))?;
+            opts.socket.keepalive = Some(Duration::from_secs(secs));
+            Ok(())
+        },
+    )
+    .topic("connection"),
+    parsed(
+        "sndbuf",
+        None,
+        "bytes",
+        "Socket send buffer size",
+        |opts, spec, value| {
+            opts.socket.send_buffer = Some(buffer_size(spec, &value)?);
+            Ok(())
+        },
+    )
+    .topic("connection"),
+    parsed(
+        "rcvbuf",
+        None,
+        "bytes",
+        "Socket receive buffer size",
+        |opts, spec, value| {
+            opts.socket.recv_buffer = Some(buffer_size(spec, &value)?);
+            Ok(())
+        },
+    )
+    .topic("connection"),
+    parsed(
+        "local-port",
+        None,
+        "port[-port]",
+        "Connect from a local port in this range",
+        |opts, spec, value| {
+            let (first, last) = value.split_once('-').unwrap_or((&value, &value));
+            let ports = first
+                .trim()
+                .parse::<u16>()
+                .ok()
+                .zip(last.trim().parse().ok());
+            opts.socket.local_ports = Some(
+                ports
+                    .filter(|&(first, last)| first > 0 && first <= last)
+                    .ok_or_else(|| invalid(spec, &value))?,
+            );
+            Ok(())
+        },
+    )
+    .topic("connection"),
+    valued(
+        "url-query",
+        None,
+        "name=value",
+        "Append a URL-encoded query parameter",
+        |opts, value| opts.url_queries.push(value),
+    )
+    .topic("request"),
+    flag(
+        "no-idn",
+        None,
+        "Do not convert Unicode host names to punycode",
+        |opts| opts.no_idn = true,
+    )
+    .topic("request"),
+    parsed(
+        "proto-default",
+        None,
+        "scheme",
+        "Use <scheme> for URLs given without one",
+        |opts, spec, value| {
+            if !matches!(value.as_str(), "http" | "https" | "ws" | "wss" | "file") {
+                return Err(invalid(spec, &value));
+            }
+            opts.proto_default = Some(value);
+            Ok(())
+        },
+    )
+    .topic("request"),
+    flag(
+        "include",
+        Some('i'),
+        "Include response headers in the output",
+        |opts| opts.include = true,
+    )
+    .topic("output"),
+    flag(
+        "verbose",
+        Some('v'),
+        "Print request and response headers to stderr",
+        |opts| opts.verbose = true,
+    )
+    .topic("debug"),
+    flag(
+        "timings",
+        None,
+        "Print where the time went, with the server's Server-Timing",
+        |opts| opts.timings = true,
+    )
+    .topic("debug"),
+    flag("http1.1", None, "Use HTTP/1.1", |opts| {
+        http_version(opts, HttpVersion::Http11)
+    })
+    .topic("protocols"),
+    flag(
+        "http2",
+        None,
+        "Try to upgrade plain HTTP connections to HTTP/2",
+        |opts| http_version(opts, HttpVersion::Http2),
+    )
+    .topic("protocols"),
+    flag(
+        "http2-prior-knowledge",
+        None,
+        "Use HTTP/2 without an upgrade",
+        |opts| http_version(opts, HttpVersion::Http2PriorKnowledge),
+    )
+    .topic("protocols"),
+    flag(
+        "http3",
+        None,
+        "Not implemented: warns and uses HTTP/1.1",
+        |opts| {
+            opts.http_version = HttpVersion::Http11;
+            opts.http3 = true;
+            opts.http3_only = false;
+        },
+    )
+    .topic("protocols"),
+    flag(
+        "http3-only",
+        None,
+        "Not implemented: always fails",
+        |opts| opts.http3_only = true,
+    )
+    .topic("protocols"),
+    flag(
+        "strict",
+        None,
+        "Reject responses that bend the protocol's grammar",
+        |opts| opts.strict = true,
+    )
+    .topic("protocols"),
+    flag(
+        "sse",
+        None,
+        "Stream Server-Sent Events, reconnecting when dropped",
+        |opts| opts.sse = true,
+    )
+    .topic("protocols"),
+    parsed(
+        "expect-status",
+        None,
+        "code",
+        "Fail unless the status matches, e.g. 200 or 2xx",
+        |opts, spec, value| {
+            opts.expect
+                .status
+                .push(StatusPattern::parse(&value).ok_or_else(|| invalid(spec, &value))?);
+            Ok(())
+        },
+    )
+    .topic("checks"),
+    parsed(
+        "expect-header",
+        None,
+        "header",
+        "Fail unless the response has this header",
+        |opts, spec, value| {
+            opts.expect
+                .headers
+                .push(HeaderExpectation::parse(&value).ok_or_else(|| invalid(spec, &value))?);
+            Ok(())
+        },
+    )
+    .topic("checks"),
+    valued(
+        "expect-body-contains",
+        None,
+        "text",
+        "Fail unless the response body contains this text",
+        |opts, value| opts.expect.body_contains.push(value),
+    )
+    .topic("checks"),
+    parsed(
+        "max-time-warn",
+        None,
+        "ms",
+        "Warn when the transfer takes longer than this",
+        |opts, spec, value| {
+            opts.budgets.time_warn = Some(millis(spec, &value)?);
+            Ok(())
+        },
+    )
+    .topic("checks"),
+    parsed(
+        "max-time-fail",
+        None,
+        "ms",
+        "Fail when the transfer takes longer than this",
+        |opts, spec, value| {
+            opts.budgets.time_limit = Some(millis(spec, &value)?);
+            Ok(())
+        },
+    )
+    .topic("checks"),
+    parsed(
+        "max-size-warn",
+        None,
+        "size",
+        "Warn when the body is bigger than this",
+        |opts, spec, value| {
+            opts.budgets.size_warn = Some(size(spec, &value)?);
+            Ok(())
+        },
+    )
+    .topic("checks"),
+    parsed(
+        "max-size-fail",
+        None,
+        "size",
+        "Fail when the body is bigger than this",
+        |opts, spec, value| {
+            opts.budgets.size_limit = Some(size(spec, &value)?);
+            Ok(())
+        },
+    )
+    .topic("checks"),
+    valued(
+        "env",
+        None,
+        "file",
+        "Read {{name}} variables from a JSON file",
+        |opts, value| opts.env_file = Some(value),
+    )
+    .topic("request"),
+    valued(
+        "http-file",
+        None,
+        "file",
+        "Run the requests in a .http file",
+        |opts, value| opts.http_file = Some(value),
+    )
+    .topic("request"),
+    flag(
+        "bench",
+        None,
+        "Send the request repeatedly and report latency statistics",
+        |opts| opts.bench = true,
+    )
+    .topic("load"),
+    parsed(
+        "requests",
+        None,
+        "n",
+        "Number of requests to send with --bench",
+        |opts, spec, value| {
+            opts.bench_requests = value
+                .parse()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| invalid(spec, &value))?;
+            Ok(())
+        },
+    )
+    .topic("load"),
+    parsed(
+        "concurrency",
+        None,
+        "n",
+        "Number of parallel connections with --bench",
+        |opts, spec, value| {
+            opts.bench_concurrency = value
+                .parse()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| invalid(spec, &value))?;
+            Ok(())
+        },
+    )
+    .topic("load"),
+    valued(
+        "cache-dir",
+        None,
+        "dir",
+        "Cache responses in this directory",
+        |opts, value| opts.cache_dir = Some(value),
+    )
+    .topic("cache"),
+    flag("no-cache", None, "Do not use --cache-dir", |opts| {
+        opts.no_cache = true
+    })
+    .topic("cache"),
+    flag(
+        "refresh",
+        None,
+        "Ignore cached responses but store new ones",
+        |opts| opts.refresh = true,
+    )
+    .topic("cache"),
+    valued(
+        "record",
+        None,
+        "file",
+        "Save responses to this cassette as they arrive",
+        |opts, value| {
+            opts.cassette = Some(value);
+            opts.replay = false;
+        },
+    )
+    .topic("cache"),
+    valued(
+        "replay",
+        None,
+        "file",
+        "Answer requests from this cassette instead of the network",
+        |opts, value| {
+            opts.cassette = Some(value);
+            opts.replay = true;
+        },
+    )
+    .topic("cache"),
+    valued(
+        "hsts",
+        None,
+        "file",
+        "Keep the HSTS host list in this file",
+        |opts, value| opts.hsts = Some(value),
+    )
+    .topic("tls"),
+    flag(
+        "https-only",
+        None,
+        "Send every request over HTTPS, refusing plain-text ones",
+        |opts| opts.https_only = true,
+    )
+    .topic("tls"),
+    valued(
+        "alt-svc",
+        None,
+        "file",
+        "Keep advertised alternative services in this file",
+        |opts, value| opts.alt_svc = Some(value),
+    )
+    .topic("protocols"),
+    flag("no-alt-svc", None, "Do not use --alt-svc", |opts| {
+        opts.no_alt_svc = true
+    })
+    .topic("protocols"),
+    valued(
+        "url-file",
+        None,
+        "file",
+        "Fetch every URL listed in a file",
+        |opts, value| opts.url_file = Some(value),
+    )
+    .topic("batch"),
+    flag(
+        "fail-early",
+        None,
+        "Stop a batch at the first transfer that fails",
+        |opts| {
+            opts.fail_early = true;
+            opts.fail_at_end = false;
+        },
+    )
+    .topic("batch"),
+    flag(
+        "fail-at-end",
+        None,
+        "Run every transfer, failing at the end if any did",
+        |opts| {
+            opts.fail_at_end = true;
+            opts.fail_early = false;
+        },
+    )
+    .topic("batch"),
+    flag(
+        "globoff",
+        Some('g'),
+        "Take {} and [] in URLs literally",
+        |opts| opts.globoff = true,
+    )
+    .topic("batch"),
+    flag(
+        "no-clobber",
+        None,
+        "Add a number to output file names that are taken",
+        |opts| opts.no_clobber = true,
+    )
+    .topic("output"),
+    flag(
+        "parallel",
+        Some('Z'),
+        "Fetch the --url-file URLs in parallel",
+        |opts| opts.parallel = true,
+    )
+    .topic("batch"),
+    flag(
+        "check-links",
+        None,
+        "Check every link on the page for broken ones",
+        |opts| opts.check_links = true,
+    )
+    .topic("batch"),
+    flag(
+        "same-origin",
+        None,
+        "Only check links on the page's own origin",
+        |opts| opts.same_origin = true,
+    )
+    .topic("batch"),
+    flag(
+        "mirror",
+        None,
+        "Save the site the URL is on, following its links",
+        |opts| opts.mirror = true,
+    )
+    .topic("batch"),
+    parsed(
+        "level",
+        None,
+        "n",
+        "How many links away --mirror goes",
+        |opts, spec, value| {
+            opts.mirror_level = value.parse().map_err(|_| invalid(spec, &value))?;
+            Ok(())
+        },
+    )
+    .topic("batch"),
+    flag(
+        "span-hosts",
+        None,
+        "Let --mirror follow links to other hosts",
+        |opts| opts.span_hosts = true,
+    )
+    .topic("batch"),
+    flag(
+        "ignore-robots",
+        None,
+        "Let the crawl modes go where robots.txt says not to",
+        |opts| opts.ignore_robots = true,
+    )
+    .topic("batch"),
+    parsed(
+        "crawl-delay",
+        None,
+        "time",
+        "Wait this long between the crawl modes' requests to a site",
+        |opts, spec, value| {
+            opts.crawl_delay = Some(parse_duration(&value).ok_or_else(|| invalid(spec, &value))?);
+            Ok(())
+        },
+    )
+    .topic("batch"),
+    parsed(
+        "parallel-max",
+        None,
+        "n",
+        "Maximum number of parallel transfers",
+        |opts, spec, value| {
+            opts.parallel_max = value
+                .parse()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| invalid(spec, &value))?;
+            Ok(())
+        },
+    )
+    .topic("batch"),
+    parsed(
+        "watch",
+        None,
+        "interval",
+        "Repeat the request on an interval, e.g. 5s",
+        |opts, spec, value| {
+            opts.watch = Some(parse_duration(&value).ok_or_else(|| invalid(spec, &value))?);
+            Ok(())
+        },
+    )
+    .topic("load"),
+    parsed(
+        "until-status",
+        None,
+        "code",
+        "Stop --watch once the status matches",
+        |opts, spec, value| {
+            opts.until_status
+                .push(StatusPattern::parse(&value).ok_or_else(|| invalid(spec, &value))?);
+            Ok(())
+        },
+    )
+    .topic("load"),
+    parsed(
+        "max-iterations",
+        None,
+        "n",
+        "Stop --watch after this many requests",
+        |opts, spec, value| {
+            opts.max_iterations = Some(
+                value
+                    .parse()
+                    .ok()
+                    .filter(|&n| n > 0)
+                    .ok_or_else(|| invalid(spec, &value))?,
+            );
+            Ok(())
+        },
+    )
+    .topic("load"),
+    parsed(
+        "metrics-listen",
+        None,
+        "[host]:port",
+        "Serve Prometheus metrics for --bench or --watch here",
+        |opts, spec, value| {
+            // Like Prometheus itself, ":9090" listens everywhere.
+            let addr = match value.strip_prefix(':') {
+                Some(port) => format!("0.0.0.0:{}", port),
+                None => value.clone(),
+            };
+            let port = addr
+                .rsplit_once(':')
+                .and_then(|(_, p)| p.parse::<u16>().ok());
+            if port.is_none() {
+                return Err(invalid(spec, &value));
+            }
+            opts.metrics_listen = Some(addr);
+            Ok(())
+        },
+    )
+    .topic("load"),
+    valued(
+        "metrics-file",
+        None,
+        "file",
+        "Write Prometheus metrics for --bench or --watch to this file",
+        |opts, value| opts.metrics_file = Some(value),
+    )
+    .topic("load"),
+    flag(
+        "interactive",
+        None,
+        "Read requests from stdin one line at a time",
+        |opts| opts.interactive = true,
+    )
+    .topic("general"),
+    valued(
+        "meta-json",
+        None,
+        "file",
+        "Append a JSON record of each transfer to a file",
+        |opts, value| opts.meta_json = Some(value),
+    )
+    .topic("output"),
+    valued(
+        "trace",
+        None,
+        "file",
+        "Dump all traffic as hex and ASCII",
+        |opts, value| opts.trace = Some((value, TraceMode::Hex)),
+    )
+    .topic("debug"),
+    valued(
+        "trace-ascii",
+        None,
+        "file",
+        "Dump all traffic as text",
+        |opts, value| opts.trace = Some((value, TraceMode::Ascii)),
+    )
+    .topic("debug"),
+    parsed(
+        "completions",
+        None,
+        "shell",
+        "Print a shell completion script",
+        |opts, spec, value| {
+            opts.completions = Some(choice(spec, value)?);
+            Ok(())
+        },
+    )
+    .topic("general")
+    .choices(SHELLS),
+    parsed(
+        "lang",
+        None,
+        "lang",
+        "Language for messages",
+        |opts, spec, value| {
+            opts.lang = Some(choice(spec, value)?);
+            Ok(())
+        },
+    )
+    .topic("general")
+    .choices(LANGS),
+    flag("help", Some('h'), "Show help", |opts| opts.help = true).topic("general"),
+    // Acted on by main before parsing, and only as the first argument.
+    flag("disable", Some('q'), "Do not read .curlrc", |_| {}).topic("general"),
+    // Splits the command line before it is parsed; see split_next.
+    flag(
+        "next",
+        None,
+        "Start the options of another transfer",
+        |_| {},
+    )
+    .topic("request"),
+    flag(
+        "examples",
+        None,
+        "Show examples of common invocations",
+        |opts| opts.examples = true,
+    )
+    .topic("general"),
+    flag(
+        "version",
+        Some('V'),
+        "Show the version and what this build supports",
+        |opts| opts.version = true,
+    )
+    .topic("general"),
+];
+
+/// One recognized option occurrence and its argument, if it takes one.
+#[derive(Debug)]
+pub struct Arg {
+    pub spec: &'static OptSpec,
+    pub value: Option<String>,
+}
+
+pub fn find_long(name: &str) -> Result<&'static OptSpec> {
+    OPTIONS
+        .iter()
+        .find(|spec| spec.long == name)
+        .ok_or_else(|| Error::Usage(tr(Msg::UnknownOption(&format!("--{}", name)))))
+}
+
+pub fn find_short(c: char) -> Result<&'static OptSpec> {
+    OPTIONS
+        .iter()
+        .find(|spec| spec.short == Some(c))
+        .ok_or_else(|| Error::Usage(tr(Msg::UnknownOption(&format!("-{}", c)))))
+}
+
+/// Splits the command line at each `--next` into the arguments of one
+/// transfer each. A `--next` that is the value of an option, or comes
+/// after `--`, is an argument like any other.
+pub fn split_next(args: &[String]) -> Vec<&[String]> {
+    let mut transfers = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    while i < args.len() {
+        match args[i].as_str() {
+            "--" => break,
+            "--next" => {
+                transfers.push(&args[start..i]);
+                start = i + 1;
+            }
+            arg if takes_next_value(arg) => i += 1,
+            _ => {}
+        }
+        i += 1;
+    }
+    transfers.push(&args[start..]);
+    transfers
+}
+
+/// Whether `arg` is an option whose value is the argument after it.
+fn takes_next_value(arg: &str) -> bool {
+    if let Some(long) = arg.strip_prefix("--") {
+        return !long.contains('=') && find_long(long).is_ok_and(|spec| spec.takes_value());
+    }
+    let Some(shorts) = arg.strip_prefix('-') else {
+        return false;
+    };
+    // In a cluster such as -iH, the first letter that takes a value takes
+    // the rest of the argument, or else the next one.
+    for (i, c) in shorts.char_indices() {
+        match find_short(c) {
+            Ok(spec) if spec.takes_value() => return i + c.len_utf8() == shorts.len(),
+            Ok(_) => {}
+            Err(_) => return false,
+        }
+    }
+    false
+}
+
+/// Splits the command line into options and positional arguments.
+pub fn tokenize(args: &[String]) -> Result<(Vec<Arg>, Vec<String>)> {
+    let mut parsed = Vec::new();
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            positional.extend(args.by_ref().cloned());
+            break;
+        }
+
+        if let Some(long) = arg.strip_prefix("--") {
+            let (name, inline) = match long.split_once('=') {
+                Some((name, value)) => (name, Some(value.to_string())),
+                None => (long, None),
+            };
+            let spec = find_long(name)?;
+            let value = match (spec.takes_value(), inline) {
+                (true, Some(value)) => Some(value),
+                (true, None) => Some(
+                    args.next()
+                        .ok_or_else(|| {
+                            Error::Usage(tr(Msg::RequiresValue(&format!("--{}", name))))
+                        })?
+                        .clone(),
+                ),
+                (false, Some(_)) => {
+                    return Err(Error::Usage(tr(Msg::TakesNoValue(&format!("--{}", name)))));
+                }
+                (false, None) => None,
+            };
+            parsed.push(Arg { spec, value });
+        } else if let Some(cluster) = arg.strip_prefix('-').filter(|c| !c.is_empty()) {
+            // A cluster of short flags; the first one that takes a value
+            // consumes the rest of the cluster, or else the next argument.
+            for (i, c) in cluster.char_indices() {
+                let spec = find_short(c)?;
+                if !spec.takes_value() {
+                    parsed.push(Arg { spec, value: None });
+                    continue;
+                }
+                let rest = &cluster[i + c.len_utf8()..];
+                let value = if rest.is_empty() {
+                    args.next()
+                        .ok_or_else(|| Error::Usage(tr(Msg::RequiresValue(&format!("-{}", c)))))?
+                        .clone()
+                } else {
+                    rest.to_string()
+                };
+                parsed.push(Arg {
+                    spec,
+                    value: Some(value),
+                });
+                break;
+            }
+        } else {
+            positional.push(arg.clone());
+        }
+    }
+    Ok((parsed, positional))
+}
+
+/// Open connections by host, port and whether they speak TLS.
+pub type Connections = Mutex<HashMap<(String, u16, bool), PersistentConnection>>;
+
+/// Everything the command line asked for.
+pub struct Options {
+    pub method: Option<String>,
+    pub dry_run: bool,
+    /// `--export`: one of [`export::FORMATS`].
+    pub export: Option<String>,
+    /// `--request-target`: `*`, an origin-form path or an absolute URL.
+    pub request_target: Option<String>,
+    /// `--request-file`: a raw request, sent byte for byte.
+    pub request_file: Option<String>,
+    pub fix_request: bool,
+    /// `--script`: a hook script, see [`http_client::script`].
+    pub script: Option<String>,
+    /// The layers every request goes through; `run` adds the `--script`.
+    pub middleware: Registry,
+    pub headers: Vec<CustomHeader>,
+    pub data: Option<String>,
+    /// `-T`: a file sent as the body as it is read; `-` is stdin.
+    pub upload_file: Option<String>,
+    /// `--compress-request`: gzip the `-T` or `-d` body.
+    pub compress_request: bool,
+    pub output: Option<String>,
+    /// `--expected-hash`: `algorithm:hex`, or a sidecar file as `@path` or
+    /// `algorithm:@path`, read when the transfer starts.
+    pub expected_hash: Option<String>,
+    /// `--jq`: the output is what it picks from the body.
+    #[cfg(feature = "json")]
+    pub jq: Option<Filter>,
+    /// Without the `json` feature `--jq` is only kept, to be refused.
+    #[cfg(not(feature = "json"))]
+    pub jq: Option<String>,
+    /// `--list-links`: the output is the page's links, one per line.
+    pub list_links: bool,
+    /// `--pretty`: JSON bodies, records and `--jq` results are indented.
+    pub pretty: bool,
+    /// `--tee`: a file that gets a copy of the output.
+    pub tee: Option<String>,
+    /// `-D`: where each response head goes as it is received, redirects
+    /// included; `-` is stdout.
+    pub dump_header: Option<String>,
+    /// `--output-dir`: where relative output file names are put.
+    pub output_dir: Option<String>,
+    pub create_dirs: bool,
+    pub no_clobber: bool,
+    /// `--no-transcode`: leave non-UTF-8 text as it was sent.
+    pub no_transcode: bool,
+    /// `-O`: name the output file after the URL.
+    pub remote_name: bool,
+    /// `-J`: with `-O`, prefer the server's `Content-Disposition` name.
+    pub remote_header_name: bool,
+    /// `--compressed`: send Accept-Encoding and decode the body.
+    pub compressed: bool,
+    pub max_filesize: Option<u64>,
+    pub speed_limit: Option<u64>,
+    pub limit_rate: Option<u64>,
+    pub speed_time: Option<u64>,
+    /// `--tcp-nodelay`, `--keepalive-time`, `--sndbuf` and `--rcvbuf`.
+    pub socket: SocketOptions,
+    pub include: bool,
+    pub verbose: bool,
+    pub sse: bool,
+    pub http_version: HttpVersion,
+    /// `--http3` or `--http3-only`; there is no QUIC transport, so the
+    /// first falls back and the second fails.
+    pub http3: bool,
+    pub http3_only: bool,
+    /// `--strict`: bare-LF line endings and other tolerated deviations in
+    /// response heads are errors.
+    pub strict: bool,
+    pub expect: Expectations,
+    /// `--max-time-warn` and the other budgets.
+    pub budgets: Budgets,
+    pub http_file: Option<String>,
+    pub env_file: Option<String>,
+    pub bench: bool,
+    pub bench_requests: u64,
+    pub bench_concurrency: usize,
+    pub interactive: bool,
+    pub cache_dir: Option<String>,
+    pub no_cache: bool,
+    pub refresh: bool,
+    /// `--record` or `--replay`: the cassette file.
+    pub cassette: Option<String>,
+    pub replay: bool,
+    /// The cassette's contents, loaded by `run`. Recording adds to them
+    /// from every thread of a `--parallel` batch.
+    pub cassette_file: Option<Mutex<Cassette>>,
+    pub hsts: Option<String>,
+    /// `--https-only`: http URLs, redirects included, go over HTTPS, and
+    /// other plain-text schemes are refused.
+    pub https_only: bool,
+    pub alt_svc: Option<String>,
+    pub no_alt_svc: bool,
+    pub url_file: Option<String>,
+    /// `--meta-json`; `-` is stderr.
+    pub meta_json: Option<String>,
+    pub parallel: bool,
+    pub globoff: bool,
+    /// `--fail-early`: a batch stops at its first failure.
+    pub fail_early: bool,
+    /// `--fail-at-end`: later `--next` transfers run after one fails.
+    pub fail_at_end: bool,
+    pub parallel_max: usize,
+    /// `--check-links`: the page's links are checked instead of printed.
+    pub check_links: bool,
+    /// `--same-origin`: `--check-links` skips links to other origins.
+    pub same_origin: bool,
+    pub mirror: bool,
+    /// `--level`: how many links away from the first page `--mirror` goes.
+    pub mirror_level: u32,
+    pub span_hosts: bool,
+    pub ignore_robots: bool,
+    /// `--crawl-delay`: the least time between two crawl requests to a site.
+    pub crawl_delay: Option<Duration>,
+    pub watch: Option<Duration>,
+    pub until_status: Vec<StatusPattern>,
+    pub max_iterations: Option<u64>,
+    /// `--metrics-listen`, with an empty host meaning every address.
+    pub metrics_listen: Option<String>,
+    pub metrics_file: Option<String>,
+    /// Where `--bench` and `--watch` count requests when either metrics
+    /// option is given; set up by `run`.
+    pub collector: Option<Arc<Collector>>,
+    /// Where a download's body is saved as it arrives; set up by `run`.
+    pub spool: Option<crate::Spool>,
+    /// The connections shared with the other transfers of a `--next`
+    /// command line; set up by `main`.
+    pub connections: Option<Arc<Connections>>,
+    pub url_queries: Vec<String>,
+    pub no_idn: bool,
+    /// `--proto-default`: the scheme of a URL given without one; http
+    /// unless set.
+    pub proto_default: Option<String>,
+    pub user_agent: Option<String>,
+    pub referer: Option<String>,
+    pub auto_referer: bool,
+    /// `--oauth2-bearer`; `@file` is read by `run`.
+    pub oauth2_bearer: Option<String>,
+    /// `--aws-sigv4`, as `provider1[:provider2[:region[:service]]]`.
+    pub aws_sigv4: Option<String>,
+    /// From the environment or `~/.aws/credentials`, loaded by `run`.
+    pub aws_credentials: Option<Credentials>,
+    /// `--ntlm`: answer an NTLM challenge with the URL or netrc credentials.
+    pub ntlm: bool,
+    /// `--negotiate`: the same handshake under the `Negotiate` scheme.
+    pub negotiate: bool,
+    /// `-n`: use `~/.netrc` if there is one.
+    pub netrc: bool,
+    /// `--netrc-file`, which must exist.
+    pub netrc_file: Option<String>,
+    /// The netrc entries, loaded by `run` when either option is given.
+    pub credentials: Option<Netrc>,
+    /// `--session` or `--session-read-only`: a name or a path.
+    pub session: Option<String>,
+    pub session_read_only: bool,
+    /// The session's file and contents, loaded by `run`. Updated as
+    /// responses arrive, from every thread of a `--parallel` batch.
+    pub session_file: Option<(String, Mutex<Session>)>,
+    /// `-x`.
+    pub proxy: Option<Proxy>,
+    /// `-U`, as `user[:password]`; overrides the proxy URL's userinfo.
+    pub proxy_user: Option<String>,
+    /// `--proxy-digest`: no Basic credentials up front; `--proxy-basic`
+    /// turns it back off.
+    pub proxy_digest: bool,
+    pub proxy_headers: Vec<CustomHeader>,
+    /// `--retry`; 0 never retries.
+    pub retry: u32,
+    /// `--retry-delay`, instead of the doubling backoff.
+    pub retry_delay: Option<Duration>,
+    pub follow: bool,
+    /// `--location-trusted`: keep credentials on redirects to other hosts.
+    pub location_trusted: bool,
+    pub max_redirs: u32,
+    pub show_redirects: bool,
+    /// `--timings`: the phases of each transfer and any `Server-Timing`
+    /// metrics, printed to stderr.
+    pub timings: bool,
+    /// `-w`, rendered by `writeout` after the transfer.
+    pub write_out: Option<String>,
+    pub trace: Option<(String, TraceMode)>,
+    /// `-h`; the URL, if given, is the topic to show.
+    pub help: bool,
+    pub examples: bool,
+    /// `-V`: print the version, TLS backend, protocols and features.
+    pub version: bool,
+    pub completions: Option<String>,
+    pub lang: Option<String>,
+    pub url: Option<String>,
+}
+
+impl Default for Options {
+    fn default() -> Options {
+        Options {
+            method: None,
+            dry_run: false,
+            export: None,
+            request_target: None,
+            request_file: None,
+            fix_request: false,
+            script: None,
+            middleware: Registry::new(),
+            headers: Vec::new(),
+            data: None,
+            upload_file: None,
+            compress_request: false,
+            output: None,
+            expected_hash: None,
+            jq: None,
+            list_links: false,
+            pretty: false,
+            tee: None,
+            dump_header: None,
+            output_dir: None,
+            create_dirs: false,
+            no_clobber: false,
+            no_transcode: false,
+            remote_name: false,
+            remote_header_name: false,
+            compressed: false,
+            max_filesize: None,
+            speed_limit: None,
+            limit_rate: None,
+            speed_time: None,
+            socket: SocketOptions::default(),
+            include: false,
+            verbose: false,
+            sse: false,
+            http_version: HttpVersion::Http11,
+            http3: false,
+            http3_only: false,
+            strict: false,
+            expect: Expectations::default(),
+            budgets: Budgets::default(),
+            http_file: None,
+            env_file: None,
+            bench: false,
+            bench_requests: 100,
+            bench_concurrency: 10,
+            interactive: false,
+            cache_dir: None,
+            no_cache: false,
+            refresh: false,
+            cassette: None,
+            replay: false,
+            cassette_file: None,
+            hsts: None,
+            https_only: false,
+            alt_svc: None,
+            no_alt_svc: false,
+            url_file: None,
+            meta_json: None,
+            parallel: false,
+            globoff: false,
+            fail_early: false,
+            fail_at_end: false,
+            parallel_max: 50,
+            check_links: false,
+            same_origin: false,
+            mirror: false,
+            mirror_level: 5,
+            span_hosts: false,
+            ignore_robots: false,
+            crawl_delay: None,
+            watch: None,
+            until_status: Vec::new(),
+            max_iterations: None,
+            metrics_listen: None,
+            metrics_file: None,
+            collector: None,
+            spool: None,
+            connections: None,
+            url_queries: Vec::new(),
+            no_idn: false,
+            proto_default: None,
+            user_agent: None,
+            referer: None,
+            auto_referer: false,
+            oauth2_bearer: None,
+            aws_sigv4: None,
+            aws_credentials: None,
+            ntlm: false,
+            negotiate: false,
+            netrc: false,
+            netrc_file: None,
+            session: None,
+            session_read_only: false,
+            session_file: None,
+            credentials: None,
+            proxy: None,
+            proxy_user: None,
+            proxy_digest: false,
+            proxy_headers: Vec::new(),
+            retry: 0,
+            retry_delay: None,
+            follow: false,
+            location_trusted: false,
+            max_redirs: 50,
+            show_redirects: false,
+            timings: false,
+            write_out: None,
+            trace: None,
+            help: false,
+            examples: false,
+            version: false,
+            completions: None,
+            lang: None,
+            url: None,
+        }
+    }
+}
+
+/// The `--lang` value, looked up before the real parse so that parse errors
+/// can already be reported in the requested language.
+pub fn find_lang(args: &[String]) -> Option<&str> {
+    let mut args = args.iter().take_while(|a| *a != "--");
+    while let Some(arg) = args.next() {
+        if arg == "--lang" {
+            return args.next().map(String::as_str);
+        }
+        if let Some(value) = arg.strip_prefix("--lang=") {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Parses a byte count with an optional binary `k`, `M` or `G` suffix.
+pub fn parse_size(value: &str) -> Option<u64> {
+    let (digits, shift) = match value.char_indices().last()? {
+        (i, 'k' | 'K') => (&value[..i], 10),
+        (i, 'm' | 'M') => (&value[..i], 20),
+        (i, 'g' | 'G') => (&value[..i], 30),
+        _ => (value, 0),
+    };
+    let n: u64 = digits.parse().ok()?;
+    n.checked_mul(1 << shift)
+}
+
+/// Parses a duration such as `5s`, `500ms`, `2m` or `1h`; a bare number
+/// is in seconds and may have a fraction.
+pub fn parse_duration(value: &str) -> Option<Duration> {
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit() && c != '.') {
+        Some(i) => value.split_at(i),
+        None => (value, "s"),
+    };
+    let scale = match unit {
+        "ms" => 0.001,
+        "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        _ => return None,
+    };
+    let n: f64 = number.parse().ok()?;
+    Duration::try_from_secs_f64(n * scale).ok()
+}
+
+pub fn parse_args(args: &[String]) -> Result<Options> {
+    let (parsed, positional) = tokenize(args)?;
+    let mut opts = Options::default();
+
+    for Arg { spec, value } in parsed {
+        match spec.apply {
+            Apply::Flag(set) => set(&mut opts),
+            Apply::Value(set) => set(&mut opts, value.unwrap_or_default()),
+            Apply::Parse(set) => set(&mut opts, spec, value.unwrap_or_default())?,
+        }
+    }
+
+    let mut positional = positional.into_iter();
+    // An argument wins over --url, which .curlrc may have given; with -h
+    // only an argument is a topic.
+    match positional.next() {
+        Some(url) => opts.url = Some(url),
+        None if opts.help => opts.url = None,
+        None => {}
+    }
+    if positional.next().is_some() {
+        return Err(Error::Usage(tr(Msg::OnlyOneUrl)));
+    }
+    // RFC 9110 section 9.3.8: a TRACE request must not have content.
+    let trace = opts
+        .method
+        .as_deref()
+        .is_some_and(|m| m.eq_ignore_ascii_case("TRACE"));
+    if trace && opts.data.is_some() {
+        return Err(Error::Usage(tr(Msg::TraceWithBody)));
+    }
+    Ok(opts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(list: &[&str]) -> Vec<String> {
+        list.iter().map(|s| s.to_string()).collect()
+    }
+
+    fn names(list: &[&str]) -> Vec<(&'static str, Option<String>)> {
+        let (parsed, _) = tokenize(&args(list)).unwrap();
+        parsed.into_iter().map(|a| (a.spec.long, a.value)).collect()
+    }
+
+    #[test]
+    fn accepts_long_forms() {
+        assert_eq!(
+            names(&["--request", "PUT", "--header=A: b", "--include"]),
+            [
+                ("request", Some("PUT".to_string())),
+                ("header", Some("A: b".to_string())),
+                ("include", None),
+            ]
+        );
+    }
+
+    #[test]
+    fn accepts_short_clusters() {
+        assert_eq!(
+            names(&["-iv", "-XPOST", "-ivH", "A: b"]),
+            [
+                ("include", None),
+                ("verbose", None),
+                ("request", Some("POST".to_string())),
+                ("include", None),
+                ("verbose", None),
+                ("header", Some("A: b".to_string())),
+            ]
+        );
+    }
+
+    #[test]
+    fn double_dash_ends_options() {
+        let (parsed, positional) = tokenize(&args(&["-i", "--", "-v", "--x"])).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(positional, ["-v", "--x"]);
+    }
+
+    #[test]
+    fn splits_transfers_at_next() {
+        let line = args(&[
+            "-X", "PUT", "a", "--next", "-H", "--next", "-ivH", "--next", "b", "--next", "--",
+            "--next",
+        ]);
+        let transfers = split_next(&line);
+        assert_eq!(transfers.len(), 3);
+        assert_eq!(transfers[0], ["-X", "PUT", "a"]);
+        assert_eq!(transfers[1], ["-H", "--next", "-ivH", "--next", "b"]);
+        assert_eq!(transfers[2], ["--", "--next"]);
+    }
+
+    #[test]
+    fn reports_bad_options() {
+        assert!(tokenize(&args(&["--nope"])).is_err());
+        assert!(tokenize(&args(&["-Q"])).is_err());
+        assert!(tokenize(&args(&["-X"])).is_err());
+        assert!(tokenize(&args(&["--include=yes"])).is_err());
+        assert!(tokenize(&args(&["--request"])).is_err());
+    }
+
+    #[test]
+    fn default_protocols_are_ones_this_client_speaks() {
+        for scheme in ["http", "https", "ws", "wss", "file"] {
+            let opts = parse_args(&args(&["--proto-default", scheme])).unwrap();
+            assert_eq!(opts.proto_default.as_deref(), Some(scheme));
+        }
+        for scheme in ["ftp", "HTTP", "https://", ""] {
+            assert!(parse_args(&args(&["--proto-default", scheme])).is_err());
+        }
+    }
+
+    #[test]
+    fn referers_may_follow_redirects() {
+        let opts = parse_args(&args(&["-A", "", "-e", "http://a/"])).unwrap();
+        assert_eq!(opts.user_agent.as_deref(), Some(""));
+        assert_eq!(opts.referer.as_deref(), Some("http://a/"));
+        assert!(!opts.auto_referer);
+        let opts = parse_args(&args(&["-e", "http://a/;auto"])).unwrap();
+        assert_eq!(opts.referer.as_deref(), Some("http://a/"));
+        assert!(opts.auto_referer);
+        // Only the redirects get one.
+        let opts = parse_args(&args(&["-e", ";auto"])).unwrap();
+        assert_eq!(opts.referer, None);
+        assert!(opts.auto_referer);
+    }
+
+    #[test]
+    fn methods_are_tokens() {
+        for method in ["PURGE", "M-SEARCH", "get"] {
+            let opts = parse_args(&args(&["-X", method])).unwrap();
+            assert_eq!(opts.method.as_deref(), Some(method));
+        }
+        for method in ["", "GET /x", "GET\r\nX: y", "CAF\u{c9}"] {
+            assert!(parse_args(&args(&["-X", method])).is_err());
+        }
+        // RFC 9110 section 9.3.8.
+        assert!(parse_args(&args(&["-X", "trace", "-d", "x"])).is_err());
+        assert!(parse_args(&args(&["-X", "TRACE"])).is_ok());
+    }
+
+    #[test]
+    fn request_targets_are_asterisk_origin_or_absolute_form() {
+        for target in ["*", "/a?b", "http://h/x"] {
+            let opts = parse_args(&args(&["--request-target", target])).unwrap();
+            assert_eq!(opts.request_target.as_deref(), Some(target));
+        }
+        for target in ["", "a", "/a b", "/a\tb", "/\x7f"] {
+            assert!(parse_args(&args(&["--request-target", target])).is_err());
+        }
+    }
+
+    #[test]
+    fn repeated_options_accumulate_or_override() {
+        let opts = parse_args(&args(&[
+            "-H", "A: 1", "-H", "B: 2", "-X", "GET", "-X", "PUT", "u",
+        ]))
+        .unwrap();
+        assert_eq!(opts.headers.len(), 2);
+        assert_eq!(opts.method.as_deref(), Some("PUT"));
+        assert_eq!(opts.url.as_deref(), Some("u"));
+
+        let opts = parse_args(&args(&["--http3", "u"])).unwrap();
+        assert!(opts.http3 && opts.http_version == HttpVersion::Http11);
+        let opts = parse_args(&args(&["--http3-only", "--http2", "u"])).unwrap();
+        assert!(!opts.http3_only && opts.http_version == HttpVersion::Http2);
+    }
+
+    #[test]
+    fn parses_sizes() {
+        assert_eq!(parse_size("100"), Some(100));
+        assert_eq!(parse_size("2k"), Some(2048));
+        assert_eq!(parse_size("1M"), Some(1 << 20));
+        assert_eq!(parse_size("3G"), Some(3 << 30));
+        assert_eq!(parse_size(""), None);
+        assert_eq!(parse_size("k"), None);
+        assert_eq!(parse_size("-1"), None);
+        assert_eq!(parse_size("99999999999999G"), None);
+    }
+
+    #[test]
+    fn parses_durations() {
+        assert_eq!(parse_duration("5"), Some(Duration::from_secs(5)));
+        assert_eq!(parse_duration("5s"), Some(Duration::from_secs(5)));
+        assert_eq!(parse_duration("1.5s"), Some(Duration::from_millis(1500)));
+        assert_eq!(parse_duration("250ms"), Some(Duration::from_millis(250)));
+        assert_eq!(parse_duration("2m"), Some(Duration::from_secs(120)));
+        assert_eq!(parse_duration("1h"), Some(Duration::from_secs(3600)));
+        assert_eq!(parse_duration(""), None);
+        assert_eq!(parse_duration("s"), None);
+        assert_eq!(parse_duration("5d"), None);
+        assert_eq!(parse_duration("-1s"), None);
+    }
+
+    #[test]
+    fn every_option_has_a_handler() {
+        for spec in OPTIONS {
+            let mut list = vec![format!("--{}", spec.long)];
+            if spec.takes_value() {
+                list.push(match spec.long {
+                    "header" | "proxy-header" => "A: b".to_string(),
+                    "expected-hash" => "@x.sha256".to_string(),
+                    "request-target" => "*".to_string(),
+                    "max-redirs" | "max-filesize" | "speed-limit" | "limit-rate" | "speed-time"
+                    | "requests" | "concurrency" | "max-iterations" | "watch" | "parallel-max"
+                    | "retry" | "retry-delay" | "crawl-delay" | "keepalive-time" | "sndbuf"
+                    | "rcvbuf" | "local-port" | "max-time-warn" | "max-time-fail"
+                    | "max-size-warn" | "max-size-fail" | "level" => "3".to_string(),
+                    "expect-status" | "until-status" => "200".to_string(),
+                    "completions" => "bash".to_string(),
+                    "lang" => "en".to_string(),
+                    "export" => "curl".to_string(),
+                    "metrics-listen" => ":9090".to_string(),
+                    "proto-default" => "https".to_string(),
+                    "jq" => ".a".to_string(),
+                    _ => "x".to_string(),
+                });
+            }
+            parse_args(&list).unwrap();
+        }
+    }
+}
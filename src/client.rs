@@ -0,0 +1,237 @@
+//! A reusable client for embedders sending many requests.
+//!
+//! A [`Client`] keeps the connections its requests leave open in a pool,
+//! keyed by scheme, host and port, and hands them to later requests to the same
+//! place. [`PoolConfig`] bounds the pool the way reqwest's builder does:
+//! how many idle connections each host may keep, how long one may sit
+//! idle, and how long any connection may live at all.
+
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::Result;
+use crate::http::{Limits, PersistentConnection};
+use crate::request::Request;
+use crate::response::Response;
+
+/// How the pool keeps and evicts connections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PoolConfig {
+    /// Idle connections kept per host and port; 0 turns reuse off.
+    pub max_idle_per_host: usize,
+    /// How long an unused connection is kept; `None` keeps it until the
+    /// server closes it.
+    pub idle_timeout: Option<Duration>,
+    /// How long a connection is used for after it was opened, however busy;
+    /// `None` for no limit.
+    pub ttl: Option<Duration>,
+}
+
+impl Default for PoolConfig {
+    /// As reqwest: no cap on idle connections, which go after 90 seconds.
+    fn default() -> PoolConfig {
+        PoolConfig {
+            max_idle_per_host: usize::MAX,
+            idle_timeout: Some(Duration::from_secs(90)),
+            ttl: None,
+        }
+    }
+}
+
+struct Idle {
+    conn: PersistentConnection,
+    opened: Instant,
+    since: Instant,
+}
+
+/// Sends requests over pooled keep-alive connections. A client is shared
+/// by reference between threads; each request holds its connection to
+/// itself.
+pub struct Client {
+    config: PoolConfig,
+    limits: Limits,
+    idle: Mutex<HashMap<Key, Vec<Idle>>>,
+}
+
+/// Scheme, host and port: a TLS connection is never reused for plain
+/// HTTP, nor the other way around.
+type Key = (String, String, u16);
+
+impl Default for Client {
+    fn default() -> Client {
+        Client::new()
+    }
+}
+
+impl Client {
+    pub fn new() -> Client {
+        Client::with_pool(PoolConfig::default())
+    }
+
+    pub fn with_pool(config: PoolConfig) -> Client {
+        Client {
+            config,
+            limits: Limits::default(),
+            idle: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Bounds every response, as `--max-filesize` and friends do.
+    pub fn limits(mut self, limits: Limits) -> Client {
+        self.limits = limits;
+        self
+    }
+
+    /// Sends `request` over HTTP/1.1 on a pooled connection, without
+    /// following redirects.
+    pub fn send(&self, request: &Request) -> Result<Response> {
+        let url = &request.url;
+        let key = (
+            url.scheme().to_string(),
+            url.connect_host().to_string(),
+            url.port_or_default(),
+        );
+        let (mut conn, opened) = match self.checkout(&key) {
+            Some(idle) => (idle.conn, idle.opened),
+            None => (PersistentConnection::new(&key.1, key.2), Instant::now()),
+        };
+        let result = self.limits.for_url(url).and_then(|limits| {
+            conn.send(
+                request.method.as_str(),
+                &url.request_target(),
+                &request.headers,
+                request.body.as_deref().map(str::as_bytes),
+                &limits,
+            )
+        });
+        let closing = request
+            .headers
+            .get_all("Connection")
+            .any(|value| value.eq_ignore_ascii_case("close"));
+        if conn.is_open() && !closing {
+            self.checkin(key, conn, opened);
+        }
+        result
+    }
+
+    /// Idle connections to every host, for tests and diagnostics.
+    pub fn idle_connections(&self) -> usize {
+        let idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        idle.values().map(Vec::len).sum()
+    }
+
+    fn expired(&self, idle: &Idle, now: Instant) -> bool {
+        let over = |limit: Option<Duration>, since: Instant| {
+            limit.is_some_and(|limit| now.duration_since(since) >= limit)
+        };
+        over(self.config.idle_timeout, idle.since) || over(self.config.ttl, idle.opened)
+    }
+
+    /// The most recently used live connection to `key`, dropping any that
+    /// have expired on the way.
+    fn checkout(&self, key: &Key) -> Option<Idle> {
+        let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        let conns = idle.get_mut(key)?;
+        let now = Instant::now();
+        conns.retain(|conn| !self.expired(conn, now));
+        conns.pop()
+    }
+
+    fn checkin(&self, key: Key, conn: PersistentConnection, opened: Instant) {
+        let now = Instant::now();
+        let conn = Idle {
+            conn,
+            opened,
+            since: now,
+        };
+        if self.expired(&conn, now) {
+            return;
+        }
+        let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        let conns = idle.entry(key).or_default();
+        conns.retain(|conn| !self.expired(conn, now));
+        if conns.len() < self.config.max_idle_per_host {
+            conns.push(conn);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::thread;
+
+    /// A keep-alive server answering `ok`, and how many connections it got.
+    fn server() -> (Request, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let counter = connections.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let stream = stream.unwrap();
+                counter.fetch_add(1, Ordering::SeqCst);
+                thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    while crate::response::read_head(&mut reader).is_ok() {
+                        let reply = "HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+                        (&stream).write_all(reply.as_bytes()).unwrap();
+                    }
+                });
+            }
+        });
+        let url = format!("http://127.0.0.1:{}/", port);
+        (Request::builder().url(&url).build().unwrap(), connections)
+    }
+
+    #[test]
+    fn reuses_idle_connections() {
+        let (request, connections) = server();
+        let client = Client::new();
+        for _ in 0..3 {
+            let response = client.send(&request).unwrap();
+            assert_eq!(response.body, b"ok");
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+        assert_eq!(client.idle_connections(), 1);
+
+        let mut closing = request.clone();
+        closing.headers.insert("Connection", "close");
+        client.send(&closing).unwrap();
+        assert_eq!(client.idle_connections(), 0);
+    }
+
+    #[test]
+    fn evicts_by_idle_timeout_and_ttl() {
+        let (request, connections) = server();
+        let client = Client::with_pool(PoolConfig {
+            idle_timeout: Some(Duration::ZERO),
+            ..PoolConfig::default()
+        });
+        client.send(&request).unwrap();
+        client.send(&request).unwrap();
+        assert_eq!(connections.load(Ordering::SeqCst), 2);
+
+        let client = Client::with_pool(PoolConfig {
+            max_idle_per_host: 0,
+            ..PoolConfig::default()
+        });
+        client.send(&request).unwrap();
+        assert_eq!(client.idle_connections(), 0);
+
+        let client = Client::with_pool(PoolConfig {
+            ttl: Some(Duration::from_millis(50)),
+            ..PoolConfig::default()
+        });
+        client.send(&request).unwrap();
+        client.send(&request).unwrap();
+        thread::sleep(Duration::from_millis(60));
+        client.send(&request).unwrap();
+        assert_eq!(connections.load(Ordering::SeqCst), 5);
+    }
+}
@@ -0,0 +1,92 @@
+//! Content codings (RFC 9110 section 8.4) for `--compressed`: what to ask
+//! for in `Accept-Encoding`, and undoing what a response's
+//! `Content-Encoding` says was applied.
+//!
+//! `gzip` and `deflate` are always understood (see [`gzip`], which has no
+//! dependencies); `br` comes with the `brotli` feature and `zstd` with the
+//! `zstd` feature, each from a crate, as their decoders are much larger.
+
+#[cfg(any(feature = "brotli", feature = "zstd"))]
+use std::io::Read;
+
+use crate::error::{Error, Result};
+use crate::gzip;
+
+/// The `Accept-Encoding` of a `--compressed` request: every coding this
+/// build decodes.
+pub fn accept_encoding() -> String {
+    let mut codings = vec!["gzip", "deflate"];
+    if cfg!(feature = "brotli") {
+        codings.push("br");
+    }
+    if cfg!(feature = "zstd") {
+        codings.push("zstd");
+    }
+    codings.join(", ")
+}
+
+/// `body` with the codings of the `Content-Encoding` value `encodings`
+/// undone, last applied first. Fails past `max` bytes.
+pub fn decode(encodings: &str, body: Vec<u8>, max: u64) -> Result<Vec<u8>> {
+    let mut body = body;
+    for coding in encodings.rsplit(',').map(str::trim) {
+        body = match coding.to_ascii_lowercase().as_str() {
+            "" | "identity" => body,
+            "gzip" | "x-gzip" => gzip::decompress(&body, max)?,
+            "deflate" => gzip::inflate(&body, max)?,
+            #[cfg(feature = "brotli")]
+            "br" => read_all(brotli_decompressor::Decompressor::new(&body[..], 4096), max)?,
+            #[cfg(feature = "zstd")]
+            "zstd" => {
+                let decoder = ruzstd::decoding::StreamingDecoder::new(&body[..])
+                    .map_err(|e| Error::BadContentEncoding(format!("zstd: {}", e)))?;
+                read_all(decoder, max)?
+            }
+            _ => {
+                return Err(Error::BadContentEncoding(format!(
+                    "'{}' is not supported",
+                    coding
+                )))
+            }
+        };
+    }
+    Ok(body)
+}
+
+/// Everything `decoder` gives, up to `max` bytes.
+#[cfg(any(feature = "brotli", feature = "zstd"))]
+fn read_all(decoder: impl Read, max: u64) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    decoder
+        .take(max.saturating_add(1))
+        .read_to_end(&mut out)
+        .map_err(|e| Error::BadContentEncoding(e.to_string()))?;
+    if out.len() as u64 > max {
+        return Err(Error::FileSizeExceeded(max));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Read;
+
+    use super::*;
+
+    #[test]
+    fn undoes_codings_last_first() {
+        let mut gzipped = Vec::new();
+        gzip::Encoder::new(&b"data"[..])
+            .read_to_end(&mut gzipped)
+            .unwrap();
+        let mut twice = Vec::new();
+        gzip::Encoder::new(&gzipped[..])
+            .read_to_end(&mut twice)
+            .unwrap();
+        assert_eq!(decode("gzip, identity, GZIP", twice, 100).unwrap(), b"data");
+        assert_eq!(decode("", b"plain".to_vec(), 100).unwrap(), b"plain");
+        let err = decode("compress", gzipped, 100).unwrap_err();
+        assert_eq!(err.exit_code(), 61);
+        assert!(accept_encoding().starts_with("gzip, deflate"));
+    }
+}
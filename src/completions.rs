@@ -0,0 +1,235 @@
+//! Shell completion scripts, generated from [`crate::cli::OPTIONS`] so they always
+//! match what the parser accepts.
+
+use crate::cli::{OptSpec, OPTIONS};
+use crate::repl;
+
+const BIN: &str = "http_client";
+
+/// The completion script for `shell`, one of [`crate::cli::SHELLS`].
+pub fn generate(shell: &str) -> String {
+    match shell {
+        "bash" => bash(),
+        "zsh" => zsh(),
+        "fish" => fish(),
+        "powershell" => powershell(),
+        "rlwrap" => rlwrap(),
+        other => unreachable!("shell '{}' is rejected by the parser", other),
+    }
+}
+
+fn forms(spec: &OptSpec) -> Vec<String> {
+    let mut forms = Vec::new();
+    if let Some(short) = spec.short {
+        forms.push(format!("-{}", short));
+    }
+    forms.push(format!("--{}", spec.long));
+    forms
+}
+
+fn bash() -> String {
+    let all: Vec<String> = OPTIONS.iter().flat_map(forms).collect();
+    let mut cases = String::new();
+    for spec in OPTIONS.iter().filter(|s| s.takes_value()) {
+        let action = if !spec.choices.is_empty() {
+            format!(
+                "COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))",
+                spec.choices.join(" ")
+            )
+        } else if spec.takes_file() {
+            "COMPREPLY=($(compgen -f -- \"$cur\"))".to_string()
+        } else {
+            "COMPREPLY=()".to_string()
+        };
+        cases.push_str(&format!(
+            "        {})\n            {}\n            return\n            ;;\n",
+            forms(spec).join("|"),
+            action
+        ));
+    }
+    format!(
+        "_{bin}() {{
+    local cur prev
+    cur=\"${{COMP_WORDS[COMP_CWORD]}}\"
+    prev=\"${{COMP_WORDS[COMP_CWORD-1]}}\"
+    case \"$prev\" in
+{cases}    esac
+    if [[ \"$cur\" == -* ]]; then
+        COMPREPLY=($(compgen -W \"{all}\" -- \"$cur\"))
+    fi
+}}
+complete -o default -F _{bin} {bin}
+",
+        bin = BIN,
+        cases = cases,
+        all = all.join(" ")
+    )
+}
+
+fn zsh_escape(text: &str) -> String {
+    text.replace('\'', "'\\''")
+        .replace('[', "\\[")
+        .replace(']', "\\]")
+        .replace(':', "\\:")
+}
+
+fn zsh() -> String {
+    let mut lines = Vec::new();
+    for spec in OPTIONS {
+        let help = zsh_escape(spec.help);
+        let argument = match spec.value {
+            None => String::new(),
+            Some(value) if !spec.choices.is_empty() => {
+                format!(":{}:({})", value, spec.choices.join(" "))
+            }
+            Some(value) if spec.takes_file() => format!(":{}:_files", value),
+            Some(value) => format!(":{}: ", value),
+        };
+        // Every option may be repeated, hence the leading '*'. The brace
+        // form lists the short and long spelling and is expanded by zsh.
+        let line = match (spec.short, spec.takes_value()) {
+            (Some(short), true) => format!(
+                "    '*'{{-{}+,--{}=}}'[{}]{}'",
+                short, spec.long, help, argument
+            ),
+            (Some(short), false) => {
+                format!("    '*'{{-{},--{}}}'[{}]'", short, spec.long, help)
+            }
+            (None, true) => format!("    '*--{}=[{}]{}'", spec.long, help, argument),
+            (None, false) => format!("    '*--{}[{}]'", spec.long, help),
+        };
+        lines.push(line);
+    }
+    lines.push("    '*:url:_urls'".to_string());
+    format!(
+        "#compdef {bin}\n\n_arguments -s \\\n{}\n",
+        lines.join(" \\\n"),
+        bin = BIN
+    )
+}
+
+fn fish() -> String {
+    let mut out = String::new();
+    for spec in OPTIONS {
+        let mut line = format!("complete -c {}", BIN);
+        if let Some(short) = spec.short {
+            line.push_str(&format!(" -s {}", short));
+        }
+        line.push_str(&format!(" -l {}", spec.long));
+        line.push_str(&format!(" -d '{}'", spec.help.replace('\'', "\\'")));
+        if spec.takes_value() {
+            if !spec.choices.is_empty() {
+                line.push_str(&format!(" -x -a '{}'", spec.choices.join(" ")));
+            } else if spec.takes_file() {
+                line.push_str(" -r -F");
+            } else {
+                line.push_str(" -x");
+            }
+        }
+        out.push_str(&line);
+        out.push('\n');
+    }
+    out
+}
+
+fn powershell() -> String {
+    let mut options = String::new();
+    let mut choices = String::new();
+    for spec in OPTIONS {
+        for form in forms(spec) {
+            options.push_str(&format!(
+                "        @('{}', '{}'),\n",
+                form,
+                spec.help.replace('\'', "''")
+            ));
+            if !spec.choices.is_empty() {
+                choices.push_str(&format!(
+                    "        '{}' = @({})\n",
+                    form,
+                    spec.choices
+                        .iter()
+                        .map(|c| format!("'{}'", c))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+        }
+    }
+    format!(
+        "Register-ArgumentCompleter -Native -CommandName '{bin}' -ScriptBlock {{
+    param($wordToComplete, $commandAst, $cursorPosition)
+    $options = @(
+{options}    )
+    $choices = @{{
+{choices}    }}
+    $elements = $commandAst.CommandElements | ForEach-Object {{ $_.ToString() }}
+    $previous = if ($wordToComplete) {{ $elements[-2] }} else {{ $elements[-1] }}
+    if ($choices.ContainsKey($previous)) {{
+        $choices[$previous] | Where-Object {{ $_ -like \"$wordToComplete*\" }} | ForEach-Object {{
+            [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+        }}
+        return
+    }}
+    $options | Where-Object {{ $_[0] -like \"$wordToComplete*\" }} | ForEach-Object {{
+        [System.Management.Automation.CompletionResult]::new($_[0], $_[0], 'ParameterName', $_[1])
+    }}
+}}
+",
+        bin = BIN,
+        options = options,
+        choices = choices
+    )
+}
+
+/// A word list for `rlwrap -f`, which completes the `--interactive`
+/// commands and the request methods from it, one per line.
+fn rlwrap() -> String {
+    let methods = OPTIONS
+        .iter()
+        .find(|spec| spec.long == "request")
+        .map_or(&[][..], |spec| spec.choices);
+    let mut words = String::new();
+    for word in repl::COMMANDS.iter().chain(methods) {
+        words.push_str(word);
+        words.push('\n');
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::SHELLS;
+
+    #[test]
+    fn every_script_mentions_every_option() {
+        for shell in SHELLS.iter().filter(|&&shell| shell != "rlwrap") {
+            let script = generate(shell);
+            for spec in OPTIONS {
+                assert!(
+                    script.contains(spec.long),
+                    "{} completion is missing --{}",
+                    shell,
+                    spec.long
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rlwrap_gets_the_interactive_words() {
+        let words = generate("rlwrap");
+        let words: Vec<&str> = words.lines().collect();
+        for word in ["header", "cookies", "clear", "quit", "GET", "DELETE"] {
+            assert!(words.contains(&word), "{}", word);
+        }
+        assert!(!words.iter().any(|word| word.starts_with('-')));
+    }
+
+    #[test]
+    fn enum_values_are_offered() {
+        assert!(bash().contains("compgen -W \"bash zsh fish powershell rlwrap\""));
+        assert!(fish().contains("-l request -d 'Request method to use' -x -a 'GET HEAD"));
+        assert!(zsh().contains("'*'{-X+,--request=}'[Request method to use]:method:(GET HEAD"));
+    }
+}
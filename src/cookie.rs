@@ -0,0 +1,221 @@
+//! An in-memory cookie jar.
+//!
+//! [`Jar::store`] takes the `Set-Cookie` headers of a response and
+//! [`Jar::header`] builds the `Cookie` header for a later request, with the
+//! domain and path matching of RFC 6265. Cookies live as long as the jar:
+//! `Expires` is ignored and only `Max-Age=0` (or less) removes one.
+
+use crate::headers::HeaderMap;
+use crate::url::Url;
+
+/// One stored cookie.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cookie {
+    pub name: String,
+    pub value: String,
+    /// Lowercase, without a leading dot.
+    pub domain: String,
+    /// Without a `Domain` attribute the cookie goes back only to the exact
+    /// host that set it.
+    pub host_only: bool,
+    pub path: String,
+}
+
+impl Cookie {
+    /// Parses a `Set-Cookie` value received from `url`. Returns the cookie
+    /// and whether it asks to be removed; `None` if it is malformed or
+    /// names a domain `url` is not part of.
+    fn parse(value: &str, url: &Url) -> Option<(Cookie, bool)> {
+        let mut parts = value.split(';');
+        let (name, value) = parts.next()?.split_once('=')?;
+        let name = name.trim();
+        if name.is_empty() {
+            return None;
+        }
+        let host = url.host().to_ascii_lowercase();
+        let mut cookie = Cookie {
+            name: name.to_string(),
+            value: value.trim().to_string(),
+            domain: host.clone(),
+            host_only: true,
+            path: default_path(url.path()),
+        };
+        let mut expired = false;
+        for attr in parts {
+            let (key, val) = attr.split_once('=').unwrap_or((attr, ""));
+            let val = val.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "domain" if !val.is_empty() => {
+                    let domain = val.trim_start_matches('.').to_ascii_lowercase();
+                    if !domain_matches(&host, &domain) {
+                        return None;
+                    }
+                    cookie.domain = domain;
+                    cookie.host_only = false;
+                }
+                "path" if val.starts_with('/') => cookie.path = val.to_string(),
+                "max-age" => expired = val.parse::<i64>().is_ok_and(|age| age <= 0),
+                _ => {}
+            }
+        }
+        Some((cookie, expired))
+    }
+
+    fn matches(&self, url: &Url) -> bool {
+        let host = url.host().to_ascii_lowercase();
+        let domain_ok = if self.host_only {
+            host == self.domain
+        } else {
+            domain_matches(&host, &self.domain)
+        };
+        domain_ok && path_matches(url.path(), &self.path)
+    }
+}
+
+/// The directory of the request path, as the default cookie path.
+fn default_path(path: &str) -> String {
+    match path.rfind('/') {
+        Some(0) | None => "/".to_string(),
+        Some(i) => path[..i].to_string(),
+    }
+}
+
+fn domain_matches(host: &str, domain: &str) -> bool {
+    host == domain
+        || (host.len() > domain.len()
+            && host.ends_with(domain)
+            && host.as_bytes()[host.len() - domain.len() - 1] == b'.')
+}
+
+fn path_matches(path: &str, cookie_path: &str) -> bool {
+    let path = if path.is_empty() { "/" } else { path };
+    path == cookie_path
+        || (path.starts_with(cookie_path)
+            && (cookie_path.ends_with('/') || path[cookie_path.len()..].starts_with('/')))
+}
+
+/// Cookies in the order they were first set.
+#[derive(Debug, Clone, Default)]
+pub struct Jar {
+    cookies: Vec<Cookie>,
+}
+
+impl Jar {
+    pub fn new() -> Jar {
+        Jar::default()
+    }
+
+    /// Stores, replaces or removes cookies from the `Set-Cookie` headers of
+    /// a response to `url`. Malformed ones are skipped.
+    pub fn store(&mut self, url: &Url, headers: &HeaderMap) {
+        for value in headers.get_all("Set-Cookie") {
+            let Some((cookie, expired)) = Cookie::parse(value, url) else {
+                continue;
+            };
+            let existing = self.cookies.iter().position(|c| {
+                c.name == cookie.name && c.domain == cookie.domain && c.path == cookie.path
+            });
+            match (existing, expired) {
+                (Some(i), true) => {
+                    self.cookies.remove(i);
+                }
+                (Some(i), false) => self.cookies[i] = cookie,
+                (None, false) => self.cookies.push(cookie),
+                (None, true) => {}
+            }
+        }
+    }
+
+    /// The `Cookie` header value for a request to `url`, if any cookie
+    /// applies. Longer paths come first, as RFC 6265 recommends.
+    pub fn header(&self, url: &Url) -> Option<String> {
+        let mut matching: Vec<&Cookie> = self.cookies.iter().filter(|c| c.matches(url)).collect();
+        if matching.is_empty() {
+            return None;
+        }
+        matching.sort_by_key(|c| std::cmp::Reverse(c.path.len()));
+        let pairs: Vec<String> = matching
+            .iter()
+            .map(|c| format!("{}={}", c.name, c.value))
+            .collect();
+        Some(pairs.join("; "))
+    }
+
+    /// Adds `cookie`, replacing one with the same name, domain and path.
+    pub fn add(&mut self, cookie: Cookie) {
+        let existing = self
+            .cookies
+            .iter_mut()
+            .find(|c| c.name == cookie.name && c.domain == cookie.domain && c.path == cookie.path);
+        match existing {
+            Some(c) => *c = cookie,
+            None => self.cookies.push(cookie),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Cookie> {
+        self.cookies.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cookies.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.cookies.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn store(jar: &mut Jar, url: &str, cookies: &[&str]) {
+        let headers: HeaderMap = cookies.iter().map(|c| ("Set-Cookie", *c)).collect();
+        jar.store(&Url::parse(url).unwrap(), &headers);
+    }
+
+    fn header(jar: &Jar, url: &str) -> Option<String> {
+        jar.header(&Url::parse(url).unwrap())
+    }
+
+    #[test]
+    fn matches_domains_and_paths() {
+        let mut jar = Jar::new();
+        store(
+            &mut jar,
+            "http://www.example.com/app/login",
+            &[
+                "sid=1; Path=/; HttpOnly",
+                "pref=dark; Domain=.example.com; Path=/",
+                "deep=x",
+                "evil=1; Domain=other.com",
+                "novalue",
+            ],
+        );
+        assert_eq!(jar.iter().count(), 3);
+        assert_eq!(
+            header(&jar, "http://www.example.com/app/items").as_deref(),
+            Some("deep=x; sid=1; pref=dark")
+        );
+        assert_eq!(
+            header(&jar, "http://api.example.com/").as_deref(),
+            Some("pref=dark")
+        );
+        assert_eq!(
+            header(&jar, "http://www.example.com/application").as_deref(),
+            Some("sid=1; pref=dark")
+        );
+        assert_eq!(header(&jar, "http://notexample.com/"), None);
+    }
+
+    #[test]
+    fn replaces_and_removes_cookies() {
+        let mut jar = Jar::new();
+        store(&mut jar, "http://h/", &["a=1", "b=2"]);
+        store(&mut jar, "http://h/", &["a=3", "b=; Max-Age=0"]);
+        assert_eq!(header(&jar, "http://h/").as_deref(), Some("a=3"));
+        jar.clear();
+        assert!(jar.is_empty());
+    }
+}
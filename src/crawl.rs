@@ -0,0 +1,129 @@
+//! What the crawl modes, `--check-links` and `--mirror`, do to be polite:
+//! each site's robots.txt is fetched once and obeyed unless
+//! `--ignore-robots` is given, and the requests to a site are spaced
+//! `--crawl-delay` apart, or by the site's own `Crawl-delay` if that is
+//! longer. A site is an origin, the scheme, host and port robots.txt
+//! applies to.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use http_client::http::{self, DEFAULT_USER_AGENT};
+use http_client::robots::Robots;
+use http_client::trace::Trace;
+use http_client::url::Url;
+
+use crate::cli::Options;
+use crate::i18n::{tr, Msg};
+
+/// The sites a crawl has visited.
+pub struct Sites<'a> {
+    opts: &'a Options,
+    trace: Option<&'a Trace>,
+    limits: &'a http::Limits,
+    sites: Mutex<HashMap<String, Arc<Site>>>,
+}
+
+struct Site {
+    robots: OnceLock<Robots>,
+    /// When the next request to the site may go.
+    next: Mutex<Instant>,
+}
+
+impl<'a> Sites<'a> {
+    pub fn new(opts: &'a Options, trace: Option<&'a Trace>, limits: &'a http::Limits) -> Sites<'a> {
+        Sites {
+            opts,
+            trace,
+            limits,
+            sites: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Whether `url` may be fetched, after waiting for the site's turn if
+    /// it may. With `obey` false, as for the URL the user gave, robots.txt
+    /// is not asked.
+    pub fn visit(&self, url: &Url, obey: bool) -> bool {
+        let site = self.site(url);
+        let robots = match self.opts.ignore_robots {
+            true => None,
+            false => Some(site.robots.get_or_init(|| self.robots(url, &site))),
+        };
+        if obey && robots.is_some_and(|robots| !robots.allows(&url.request_target())) {
+            if self.opts.verbose {
+                eprintln!("* {}", tr(Msg::RobotsDisallowed(&url.to_string())));
+            }
+            return false;
+        }
+        let delay = self.opts.crawl_delay.unwrap_or_default();
+        let delay = delay.max(
+            robots
+                .and_then(|robots| robots.crawl_delay)
+                .unwrap_or_default(),
+        );
+        wait(&site, delay);
+        true
+    }
+
+    fn site(&self, url: &Url) -> Arc<Site> {
+        let origin = format!(
+            "{}://{}:{}",
+            url.scheme(),
+            url.host(),
+            url.port_or_default()
+        );
+        let mut sites = self.sites.lock().unwrap_or_else(|e| e.into_inner());
+        let site = sites.entry(origin).or_insert_with(|| {
+            Arc::new(Site {
+                robots: OnceLock::new(),
+                next: Mutex::new(Instant::now()),
+            })
+        });
+        site.clone()
+    }
+
+    /// Fetches the robots.txt of `url`'s site. One that is missing says
+    /// nothing; one the server fails to give (a 5xx) is taken to disallow
+    /// everything, as RFC 9309 asks. A site that cannot be reached at all
+    /// is left to fail on its own requests.
+    fn robots(&self, url: &Url, site: &Site) -> Robots {
+        let Ok(robots_url) = url.join("/robots.txt") else {
+            return Robots::default();
+        };
+        wait(site, self.opts.crawl_delay.unwrap_or_default());
+        let agent = self
+            .opts
+            .user_agent
+            .as_deref()
+            .unwrap_or(DEFAULT_USER_AGENT);
+        let token = agent.split('/').next().unwrap_or_default().trim();
+        let method = "GET".to_string();
+        match crate::fetch(
+            self.opts,
+            robots_url,
+            method,
+            None,
+            &[],
+            self.trace,
+            self.limits,
+        ) {
+            Ok(response) if response.status.code < 300 => Robots::parse(&response.text(), token),
+            Ok(response) if response.status.code >= 500 => Robots::disallow_all(),
+            _ => Robots::default(),
+        }
+    }
+}
+
+/// Waits until `site` may be sent another request, and holds its next
+/// one back by `delay`.
+fn wait(site: &Site, delay: Duration) {
+    let start = {
+        let mut next = site.next.lock().unwrap_or_else(|e| e.into_inner());
+        let start = (*next).max(Instant::now());
+        *next = start + delay;
+        start
+    };
+    thread::sleep(start.saturating_duration_since(Instant::now()));
+}
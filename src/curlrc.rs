@@ -0,0 +1,163 @@
+//! curl's default config file, so defaults kept there carry over.
+//!
+//! Each line holds one option and its value, as curl reads them: the
+//! long name with or without its dashes (`header = "X-A: 1"`, `--location`,
+//! `-H "X-A: 1"`), then the value after whitespace or, for a name without
+//! dashes, `=` or `:`. A value in double quotes may contain spaces and the
+//! escapes `\"`, `\\`, `\t`, `\n`, `\r` and `\v`; otherwise it ends at the
+//! first space. `#` starts a comment line. The options are the command
+//! line's, `url` among them, and an option the file names that this
+//! client does not have is skipped rather than failing every run.
+
+use std::env;
+use std::path::PathBuf;
+
+use http_client::error::{Error, Result};
+
+use crate::cli::{self, OptSpec};
+use crate::i18n::{tr, Msg};
+
+/// The options read from a config file, as arguments to go before the
+/// command line's.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Config {
+    pub args: Vec<String>,
+    /// Options this client does not know, with their line numbers.
+    pub unknown: Vec<(usize, String)>,
+}
+
+/// Where curl looks: `$CURL_HOME/.curlrc`, `$XDG_CONFIG_HOME/curlrc`, then
+/// `~/.curlrc`; the first that exists.
+pub fn find() -> Option<PathBuf> {
+    let candidates = [
+        env::var_os("CURL_HOME").map(|dir| PathBuf::from(dir).join(".curlrc")),
+        env::var_os("XDG_CONFIG_HOME").map(|dir| PathBuf::from(dir).join("curlrc")),
+        env::var_os("HOME").map(|dir| PathBuf::from(dir).join(".curlrc")),
+    ];
+    candidates.into_iter().flatten().find(|path| path.is_file())
+}
+
+/// Reads the options in `text`; `path` names the file in errors.
+pub fn parse(path: &str, text: &str) -> Result<Config> {
+    let mut config = Config::default();
+    for (i, line) in text.lines().enumerate() {
+        let number = i + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let name_end = line
+            .find(|c: char| c.is_whitespace() || c == '=' || c == ':')
+            .unwrap_or(line.len());
+        let (name, rest) = line.split_at(name_end);
+        // Only a bare name may be followed by '=' or ':'.
+        let rest = match name.starts_with('-') {
+            true => rest.trim_start(),
+            false => rest
+                .trim_start()
+                .trim_start_matches(['=', ':'])
+                .trim_start(),
+        };
+        let value = value(rest)
+            .ok_or_else(|| Error::Usage(tr(Msg::ConfigUnterminatedQuote(path, number))))?;
+        let Some(spec) = lookup(name) else {
+            config.unknown.push((number, name.to_string()));
+            continue;
+        };
+        match spec.takes_value() {
+            // `=` keeps a value that starts with a dash a value.
+            true => config.args.push(format!("--{}={}", spec.long, value)),
+            false => config.args.push(format!("--{}", spec.long)),
+        }
+    }
+    Ok(config)
+}
+
+/// The option `name` refers to: `-H`, `--header` or `header`.
+fn lookup(name: &str) -> Option<&'static OptSpec> {
+    if let Some(long) = name.strip_prefix("--") {
+        return cli::find_long(long).ok();
+    }
+    match name.strip_prefix('-') {
+        Some(short) => {
+            let mut chars = short.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => cli::find_short(c).ok(),
+                _ => None,
+            }
+        }
+        None => cli::find_long(name).ok(),
+    }
+}
+
+/// The value at the start of `rest`: quoted, or up to the first space.
+/// `None` for a quote that is never closed.
+fn value(rest: &str) -> Option<String> {
+    let Some(quoted) = rest.strip_prefix('"') else {
+        return Some(
+            rest.split_whitespace()
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+        );
+    };
+    let mut value = String::new();
+    let mut chars = quoted.chars();
+    loop {
+        match chars.next()? {
+            '"' => return Some(value),
+            '\\' => value.push(match chars.next()? {
+                't' => '\t',
+                'n' => '\n',
+                'r' => '\r',
+                'v' => '\x0b',
+                other => other,
+            }),
+            c => value.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_curl_config_lines() {
+        let text = "# defaults\n\
+                    header = \"X-A: one two\"\n\
+                    --location\n\
+                    -H \"X-B: \\\"q\\\"\"\n\
+                    user-agent: probe/1 ignored\n\
+                    max-redirs 3\n\
+                    \n\
+                    url = \"http://example.test/\"\n\
+                    proxy-header=-x\n";
+        let config = parse(".curlrc", text).unwrap();
+        assert_eq!(
+            config.args,
+            [
+                "--header=X-A: one two",
+                "--location",
+                "--header=X-B: \"q\"",
+                "--user-agent=probe/1",
+                "--max-redirs=3",
+                "--url=http://example.test/",
+                "--proxy-header=-x",
+            ]
+        );
+        assert!(config.unknown.is_empty());
+    }
+
+    #[test]
+    fn skips_unknown_options_and_refuses_open_quotes() {
+        let config = parse(".curlrc", "silent\ntcp-fastopen\n-L\n").unwrap();
+        assert_eq!(config.args, ["--location"]);
+        assert_eq!(
+            config.unknown,
+            [(1, "silent".to_string()), (2, "tcp-fastopen".to_string())]
+        );
+        let err = parse(".curlrc", "\nheader = \"X-A: 1\n").unwrap_err();
+        assert!(err.to_string().contains(".curlrc:2"), "{}", err);
+    }
+}
@@ -0,0 +1,180 @@
+//! HTTP dates (RFC 9110 section 5.6.7) as Unix timestamps.
+//!
+//! [`parse`] accepts the preferred IMF-fixdate form as well as the obsolete
+//! RFC 850 and asctime forms, as recipients must; [`format`] always writes
+//! IMF-fixdate.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const DAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Seconds since the Unix epoch, now.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let m = month as i64;
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// The inverse of [`days_from_civil`]: year, month and day.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn month(name: &str) -> Option<u32> {
+    MONTHS.iter().position(|m| *m == name).map(|i| i as u32 + 1)
+}
+
+fn time_of_day(text: &str) -> Option<u64> {
+    let mut parts = text.split(':').map(|p| p.parse::<u64>().ok());
+    let (h, m, s) = (parts.next()??, parts.next()??, parts.next()??);
+    if parts.next().is_some() || h > 23 || m > 59 || s > 60 {
+        return None;
+    }
+    Some(h * 3600 + m * 60 + s)
+}
+
+fn timestamp(year: i64, month: u32, day: u32, time: u64) -> Option<u64> {
+    if !(1..=31).contains(&day) || year < 1970 {
+        return None;
+    }
+    let days = u64::try_from(days_from_civil(year, month, day)).ok()?;
+    Some(days * 86_400 + time)
+}
+
+/// Parses an HTTP date, such as `Sun, 06 Nov 1994 08:49:37 GMT`.
+pub fn parse(text: &str) -> Option<u64> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    match words[..] {
+        // IMF-fixdate: Sun, 06 Nov 1994 08:49:37 GMT
+        [_, day, mon, year, time, "GMT"] => timestamp(
+            year.parse().ok()?,
+            month(mon)?,
+            day.parse().ok()?,
+            time_of_day(time)?,
+        ),
+        // RFC 850: Sunday, 06-Nov-94 08:49:37 GMT
+        [_, date, time, "GMT"] => {
+            let mut parts = date.split('-');
+            let (day, mon, year) = (parts.next()?, parts.next()?, parts.next()?);
+            // Two-digit years from 70 on are 19xx, the rest 20xx.
+            let year: i64 = year.parse().ok()?;
+            let year = match year {
+                0..=69 => 2000 + year,
+                70..=99 => 1900 + year,
+                _ => year,
+            };
+            timestamp(year, month(mon)?, day.parse().ok()?, time_of_day(time)?)
+        }
+        // asctime: Sun Nov  6 08:49:37 1994
+        [_, mon, day, time, year] => timestamp(
+            year.parse().ok()?,
+            month(mon)?,
+            day.parse().ok()?,
+            time_of_day(time)?,
+        ),
+        _ => None,
+    }
+}
+
+/// Parses `YYYYMMDD HH:MM:SS` in UTC, the form of curl's HSTS and Alt-Svc
+/// files.
+pub fn parse_compact(text: &str) -> Option<u64> {
+    let (day, time) = text.trim().split_once(' ')?;
+    if day.len() != 8 || !day.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    timestamp(
+        day[..4].parse().ok()?,
+        day[4..6].parse().ok().filter(|m| (1..=12).contains(m))?,
+        day[6..].parse().ok()?,
+        time_of_day(time)?,
+    )
+}
+
+/// Formats a timestamp for [`parse_compact`].
+pub fn format_compact(secs: u64) -> String {
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let time = secs % 86_400;
+    format!(
+        "{:04}{:02}{:02} {:02}:{:02}:{:02}",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+/// Formats a timestamp as IMF-fixdate.
+pub fn format(secs: u64) -> String {
+    let days = (secs / 86_400) as i64;
+    let time = secs % 86_400;
+    let (year, month, day) = civil_from_days(days);
+    // 1970-01-01 was a Thursday.
+    let weekday = DAYS[((days + 3) % 7) as usize];
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        weekday,
+        day,
+        MONTHS[month as usize - 1],
+        year,
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_all_three_forms() {
+        let expected = Some(784_111_777);
+        assert_eq!(parse("Sun, 06 Nov 1994 08:49:37 GMT"), expected);
+        assert_eq!(parse("Sunday, 06-Nov-94 08:49:37 GMT"), expected);
+        assert_eq!(parse("Sun Nov  6 08:49:37 1994"), expected);
+        assert_eq!(parse("Thu, 01 Jan 1970 00:00:00 GMT"), Some(0));
+        assert_eq!(parse("Sun, 06 Nov 1994 08:49:37 PST"), None);
+        assert_eq!(parse("Sun, 06 Foo 1994 08:49:37 GMT"), None);
+        assert_eq!(parse("Sun, 06 Nov 1994 24:00:00 GMT"), None);
+        assert_eq!(parse("soon"), None);
+    }
+
+    #[test]
+    fn formats_imf_fixdate() {
+        assert_eq!(format(784_111_777), "Sun, 06 Nov 1994 08:49:37 GMT");
+        assert_eq!(format(0), "Thu, 01 Jan 1970 00:00:00 GMT");
+        assert_eq!(format(951_782_400), "Tue, 29 Feb 2000 00:00:00 GMT");
+        let t = 1_790_000_000;
+        assert_eq!(parse(&format(t)), Some(t));
+        assert_eq!(format_compact(784_111_777), "19941106 08:49:37");
+        assert_eq!(parse_compact("19941106 08:49:37"), Some(784_111_777));
+        assert_eq!(parse_compact(&format_compact(t)), Some(t));
+        assert_eq!(parse_compact("19941306 08:49:37"), None);
+    }
+}
@@ -0,0 +1,133 @@
+//! File names from `Content-Disposition` (RFC 6266), for `-J`.
+//!
+//! The name comes from the server, so it is only ever used as a bare file
+//! name in the current directory: see [`sanitize`].
+
+use crate::url::percent_decode_bytes;
+
+/// The file name a `Content-Disposition` value suggests, if any. An RFC
+/// 5987 `filename*=charset'lang'value` is preferred over a plain
+/// `filename=`, as RFC 6266 asks. The name is returned as sent; pass it
+/// through [`sanitize`] before using it.
+pub fn filename(value: &str) -> Option<String> {
+    let mut plain = None;
+    let mut extended = None;
+    for param in split_params(value).into_iter().skip(1) {
+        let Some((name, value)) = param.split_once('=') else {
+            continue;
+        };
+        match name.trim().to_ascii_lowercase().as_str() {
+            "filename" => plain = Some(unquote(value.trim())),
+            "filename*" => extended = extended.or_else(|| decode_extended(value.trim())),
+            _ => {}
+        }
+    }
+    extended.or(plain)
+}
+
+/// Reduces a server-supplied name to one safe to create in the current
+/// directory: anything up to the last `/` or `\` is dropped, as are
+/// control characters, and `None` is returned if nothing usable is left.
+pub fn sanitize(name: &str) -> Option<String> {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or_default();
+    let clean: String = base.chars().filter(|c| !c.is_control()).collect();
+    let clean = clean.trim();
+    match clean {
+        "" | "." | ".." => None,
+        name => Some(name.to_string()),
+    }
+}
+
+/// Splits on `;` outside quoted strings.
+fn split_params(value: &str) -> Vec<&str> {
+    let mut params = Vec::new();
+    let (mut start, mut quoted, mut escaped) = (0, false, false);
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            ';' if !quoted => {
+                params.push(&value[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    params.push(&value[start..]);
+    params
+}
+
+fn unquote(value: &str) -> String {
+    let Some(inner) = value.strip_prefix('"') else {
+        return value.to_string();
+    };
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => break,
+            '\\' => out.extend(chars.next()),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// `charset'language'percent-encoded`. Only UTF-8 and ISO-8859-1, the
+/// charsets RFC 5987 requires, are understood.
+fn decode_extended(value: &str) -> Option<String> {
+    let mut parts = value.splitn(3, '\'');
+    let (charset, _language, encoded) = (parts.next()?, parts.next()?, parts.next()?);
+    let bytes = percent_decode_bytes(encoded);
+    match charset.to_ascii_lowercase().as_str() {
+        "utf-8" => String::from_utf8(bytes).ok(),
+        "iso-8859-1" => Some(bytes.into_iter().map(char::from).collect()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_plain_and_extended_names() {
+        assert_eq!(
+            filename("attachment; filename=\"a; \\\"b\\\".txt\"").as_deref(),
+            Some("a; \"b\".txt")
+        );
+        assert_eq!(
+            filename("attachment;filename=report.pdf").as_deref(),
+            Some("report.pdf")
+        );
+        assert_eq!(
+            filename(
+                "attachment; filename=\"fallback.txt\"; filename*=UTF-8''%E2%82%AC%20rates.txt"
+            )
+            .as_deref(),
+            Some("€ rates.txt")
+        );
+        assert_eq!(
+            filename("attachment; filename*=iso-8859-1'en'%A3%20rates").as_deref(),
+            Some("£ rates")
+        );
+        assert_eq!(
+            filename("attachment; filename*=koi8-r''%C1; filename=x").as_deref(),
+            Some("x")
+        );
+        assert_eq!(filename("inline"), None);
+    }
+
+    #[test]
+    fn strips_directories_from_names() {
+        assert_eq!(sanitize("../../etc/passwd").as_deref(), Some("passwd"));
+        assert_eq!(
+            sanitize("C:\\Windows\\evil.exe").as_deref(),
+            Some("evil.exe")
+        );
+        assert_eq!(sanitize("a\nb\u{7}.txt").as_deref(), Some("ab.txt"));
+        assert_eq!(sanitize("dir/.."), None);
+        assert_eq!(sanitize("/"), None);
+    }
+}
@@ -0,0 +1,229 @@
+//! The crate-wide error type.
+
+use std::fmt;
+use std::io;
+
+/// Everything that can go wrong while preparing or performing a transfer.
+#[derive(Debug)]
+pub enum Error {
+    /// The command line was malformed.
+    Usage(String),
+    /// The URL's scheme is not one this client speaks.
+    UnsupportedProtocol(String),
+    /// A URL could not be parsed.
+    UrlParse(String),
+    /// A request header was malformed.
+    InvalidHeader(String),
+    /// The method or request target would corrupt the request line.
+    InvalidRequest(String),
+    /// The host name could not be resolved.
+    Dns { host: String, source: io::Error },
+    /// No connection could be established to any resolved address.
+    Connect {
+        host: String,
+        port: u16,
+        source: io::Error,
+    },
+    /// The TLS layer failed or is unavailable.
+    Tls(String),
+    /// Reading from or writing to the connection failed.
+    Io(io::Error),
+    /// The server sent something that is not valid HTTP.
+    HttpProtocol(String),
+    /// An operation took longer than allowed.
+    Timeout(String),
+    /// The redirect limit was reached.
+    TooManyRedirects(u32),
+    /// The response body is larger than `--max-filesize` allows.
+    FileSizeExceeded(u64),
+    /// A `--compressed` body is in a coding this build cannot decode, or
+    /// is corrupt.
+    BadContentEncoding(String),
+    /// The connection closed before the announced body was complete.
+    PartialFile { expected: u64, received: u64 },
+    /// A local file could not be opened, created or written.
+    File { path: String, source: io::Error },
+    /// A `file:` URL could not be read.
+    ReadFile { path: String, source: io::Error },
+    /// A `{{name}}` placeholder or a variables file could not be resolved.
+    Template(String),
+    /// A `--script` could not be parsed or failed while running.
+    Script(String),
+    /// A `--jq` filter could not be applied to a body or a JSON Lines
+    /// record.
+    Query(String),
+    /// The response did not meet the `--expect-*` assertions; one entry
+    /// per unmet assertion.
+    ExpectationFailed(Vec<String>),
+    /// `--check-links` found links that fail or answer 4xx or 5xx.
+    BrokenLinks { broken: usize, checked: usize },
+    /// The transfer went over a `--max-time-fail` or `--max-size-fail`
+    /// budget; one message per budget.
+    OverBudget(Vec<String>),
+    /// The body does not have the `--expected-hash` digest.
+    ChecksumMismatch {
+        algorithm: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    /// The process exit code for this error. The numbers follow curl's so
+    /// that scripts written against curl keep working.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::UnsupportedProtocol(_) => 1,
+            Error::Usage(_) | Error::Template(_) | Error::Script(_) => 2,
+            Error::UrlParse(_) => 3,
+            Error::Dns { .. } => 6,
+            Error::Connect { .. } => 7,
+            Error::HttpProtocol(_) => 8,
+            Error::PartialFile { .. } => 18,
+            // What curl uses for --fail.
+            Error::ExpectationFailed(_) | Error::BrokenLinks { .. } => 22,
+            Error::File { .. } => 23,
+            Error::Timeout(_) => 28,
+            Error::Tls(_) => 35,
+            Error::ReadFile { .. } => 37,
+            Error::InvalidHeader(_) | Error::InvalidRequest(_) => 43,
+            Error::TooManyRedirects(_) => 47,
+            Error::Io(_) => 56,
+            Error::BadContentEncoding(_) => 61,
+            Error::FileSizeExceeded(_) => 63,
+            // curl has no checksums; this is past any code it uses.
+            Error::ChecksumMismatch { .. } => 101,
+            // Apart from 22, so a probe can tell slow from wrong.
+            Error::OverBudget(_) => 102,
+            Error::Query(_) => 103,
+        }
+    }
+}
+
+impl Error {
+    /// Whether trying again later may succeed: the network failed, as
+    /// opposed to the request, the response or a local file being wrong.
+    pub fn is_transient(&self) -> bool {
+        matches!(
+            self,
+            Error::Dns { .. }
+                | Error::Connect { .. }
+                | Error::Io(_)
+                | Error::Timeout(_)
+                | Error::PartialFile { .. }
+        )
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Usage(msg)
+            | Error::UrlParse(msg)
+            | Error::InvalidHeader(msg)
+            | Error::InvalidRequest(msg)
+            | Error::Tls(msg)
+            | Error::HttpProtocol(msg)
+            | Error::Timeout(msg)
+            | Error::Template(msg) => f.write_str(msg),
+            Error::Script(msg) => write!(f, "script: {}", msg),
+            Error::Query(msg) => write!(f, "jq: {}", msg),
+            Error::UnsupportedProtocol(scheme) => write!(f, "unsupported protocol '{}'", scheme),
+            Error::Dns { host, source } => write!(f, "could not resolve host {}: {}", host, source),
+            Error::Connect { host, port, source } => {
+                write!(f, "failed to connect to {}:{}: {}", host, port, source)
+            }
+            Error::Io(source) => write!(f, "connection error: {}", source),
+            Error::TooManyRedirects(n) => write!(f, "maximum ({}) redirects followed", n),
+            Error::BadContentEncoding(why) => write!(f, "bad content encoding: {}", why),
+            Error::FileSizeExceeded(max) => {
+                write!(f, "maximum file size exceeded ({} bytes)", max)
+            }
+            Error::PartialFile { expected, received } => write!(
+                f,
+                "transfer closed with {} of {} bytes received",
+                received, expected
+            ),
+            Error::File { path, source } => write!(f, "{}: {}", path, source),
+            Error::ReadFile { path, source } => write!(f, "could not read {}: {}", path, source),
+            Error::ExpectationFailed(failures) => write!(
+                f,
+                "response did not meet expectations: {}",
+                failures.join("; ")
+            ),
+            Error::BrokenLinks { broken, checked } => {
+                write!(f, "{} of {} links are broken", broken, checked)
+            }
+            Error::OverBudget(failures) => {
+                write!(f, "response over budget: {}", failures.join("; "))
+            }
+            Error::ChecksumMismatch {
+                algorithm,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{} checksum mismatch: expected {}, got {}",
+                algorithm, expected, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Dns { source, .. }
+            | Error::Connect { source, .. }
+            | Error::Io(source)
+            | Error::File { source, .. }
+            | Error::ReadFile { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// Connection I/O errors; timeouts get their own variant so callers can
+/// tell them apart.
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Error {
+        // Readers wrap our own errors to pass them through `io::Read`.
+        if err.get_ref().is_some_and(|inner| inner.is::<Error>()) {
+            if let Ok(inner) = err.into_inner().unwrap().downcast::<Error>() {
+                return *inner;
+            }
+            unreachable!("the inner error was checked to be an Error");
+        }
+        match err.kind() {
+            // Our own timeouts carry a complete message.
+            io::ErrorKind::TimedOut if err.get_ref().is_some() => Error::Timeout(err.to_string()),
+            io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => {
+                Error::Timeout(format!("operation timed out: {}", err))
+            }
+            _ => Error::Io(err),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn io_timeouts_become_timeout_errors() {
+        let err = Error::from(io::Error::new(io::ErrorKind::TimedOut, "slow"));
+        assert_eq!(err.exit_code(), 28);
+        let err = Error::from(io::Error::new(io::ErrorKind::ConnectionReset, "reset"));
+        assert_eq!(err.exit_code(), 56);
+        assert!(std::error::Error::source(&err).is_some());
+    }
+
+    #[test]
+    fn wrapped_errors_come_back_out() {
+        let wrapped = io::Error::other(Error::HttpProtocol("bad".to_string()));
+        let err = Error::from(wrapped);
+        assert!(matches!(err, Error::HttpProtocol(ref msg) if msg == "bad"));
+    }
+}
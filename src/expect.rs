@@ -0,0 +1,291 @@
+//! Response assertions for `--expect-status`, `--expect-header` and
+//! `--expect-body-contains`, and the time and size budgets of
+//! `--max-time-warn` and friends.
+//!
+//! [`Expectations::check`] reports every unmet assertion at once, so a
+//! failing smoke test says everything that was wrong in one run.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::error::{Error, Result};
+use crate::headers;
+use crate::response::Response;
+
+/// An accepted status: an exact code such as `204`, or a class such as
+/// `2xx`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatusPattern {
+    Exact(u16),
+    Class(u16),
+}
+
+impl StatusPattern {
+    pub fn parse(value: &str) -> Option<StatusPattern> {
+        let bytes = value.as_bytes();
+        if bytes.len() != 3 || !(b'1'..=b'5').contains(&bytes[0]) {
+            return None;
+        }
+        if value[1..].eq_ignore_ascii_case("xx") {
+            return Some(StatusPattern::Class((bytes[0] - b'0') as u16));
+        }
+        value.parse().ok().map(StatusPattern::Exact)
+    }
+
+    pub fn matches(&self, code: u16) -> bool {
+        match *self {
+            StatusPattern::Exact(expected) => code == expected,
+            StatusPattern::Class(class) => code / 100 == class,
+        }
+    }
+}
+
+impl fmt::Display for StatusPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StatusPattern::Exact(code) => write!(f, "{}", code),
+            StatusPattern::Class(class) => write!(f, "{}xx", class),
+        }
+    }
+}
+
+/// A header that must be present, optionally with a value it must contain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderExpectation {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+impl HeaderExpectation {
+    /// Parses `Name: value` or a bare `Name`.
+    pub fn parse(arg: &str) -> Option<HeaderExpectation> {
+        let (name, value) = match arg.split_once(':') {
+            Some((name, value)) => (name.trim(), Some(value.trim().to_string())),
+            None => (arg.trim(), None),
+        };
+        if name.is_empty() || !name.bytes().all(headers::is_token_byte) {
+            return None;
+        }
+        Some(HeaderExpectation {
+            name: name.to_string(),
+            value: value.filter(|v| !v.is_empty()),
+        })
+    }
+}
+
+/// Everything the response has to satisfy.
+#[derive(Debug, Clone, Default)]
+pub struct Expectations {
+    /// The status must match one of these, if any are given.
+    pub status: Vec<StatusPattern>,
+    pub headers: Vec<HeaderExpectation>,
+    /// Byte strings that must all occur in the body.
+    pub body_contains: Vec<String>,
+}
+
+impl Expectations {
+    pub fn is_empty(&self) -> bool {
+        self.status.is_empty() && self.headers.is_empty() && self.body_contains.is_empty()
+    }
+
+    /// Checks `response`. Header values match when they contain the
+    /// expected text, ignoring case, so `application/json` also accepts
+    /// `application/json; charset=utf-8`.
+    pub fn check(&self, response: &Response) -> Result<()> {
+        let mut failures = Vec::new();
+        let code = response.status.code;
+        if !self.status.is_empty() && !self.status.iter().any(|p| p.matches(code)) {
+            let expected: Vec<String> = self.status.iter().map(|p| p.to_string()).collect();
+            failures.push(format!(
+                "status is {}, expected {}",
+                code,
+                expected.join(" or ")
+            ));
+        }
+        for header in &self.headers {
+            let mut values = response.headers.get_all(&header.name).peekable();
+            if values.peek().is_none() {
+                failures.push(format!("header {} is missing", header.name));
+                continue;
+            }
+            let Some(expected) = &header.value else {
+                continue;
+            };
+            let wanted = expected.to_ascii_lowercase();
+            let values: Vec<&str> = values.collect();
+            if !values
+                .iter()
+                .any(|v| v.to_ascii_lowercase().contains(&wanted))
+            {
+                failures.push(format!(
+                    "header {} is '{}', expected it to contain '{}'",
+                    header.name,
+                    values.join(", "),
+                    expected
+                ));
+            }
+        }
+        for needle in &self.body_contains {
+            let found = needle.is_empty()
+                || response
+                    .body
+                    .windows(needle.len())
+                    .any(|w| w == needle.as_bytes());
+            if !found {
+                failures.push(format!("body does not contain '{}'", needle));
+            }
+        }
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::ExpectationFailed(failures))
+        }
+    }
+}
+
+/// How long a transfer may take and how big its body may be, for using
+/// the client as a monitoring probe. Going over a warning budget is only
+/// reported; going over a hard limit fails the run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Budgets {
+    pub time_warn: Option<Duration>,
+    pub time_limit: Option<Duration>,
+    pub size_warn: Option<u64>,
+    pub size_limit: Option<u64>,
+}
+
+impl Budgets {
+    /// Checks a transfer that took `elapsed` for a body of `size` bytes,
+    /// returning the warnings to report. An error lists every hard limit
+    /// that was exceeded; a warning is not repeated for one of those.
+    pub fn check(&self, elapsed: Duration, size: u64) -> Result<Vec<String>> {
+        let mut warnings = Vec::new();
+        let mut failures = Vec::new();
+        let over_time = |budget: Duration| {
+            format!(
+                "took {} ms, over the {} ms budget",
+                elapsed.as_millis(),
+                budget.as_millis()
+            )
+        };
+        match (self.time_limit, self.time_warn) {
+            (Some(limit), _) if elapsed > limit => failures.push(over_time(limit)),
+            (_, Some(warn)) if elapsed > warn => warnings.push(over_time(warn)),
+            _ => {}
+        }
+        let over_size =
+            |budget: u64| format!("body is {} bytes, over the {} byte budget", size, budget);
+        match (self.size_limit, self.size_warn) {
+            (Some(limit), _) if size > limit => failures.push(over_size(limit)),
+            (_, Some(warn)) if size > warn => warnings.push(over_size(warn)),
+            _ => {}
+        }
+        if failures.is_empty() {
+            Ok(warnings)
+        } else {
+            Err(Error::OverBudget(failures))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headers::HeaderMap;
+    use crate::response::StatusLine;
+
+    fn response() -> Response {
+        Response {
+            status: StatusLine {
+                version: "HTTP/1.1".into(),
+                code: 201,
+                reason: "Created".into(),
+            },
+            headers: [("Content-Type", "application/json; charset=utf-8")]
+                .into_iter()
+                .collect(),
+            body: br#"{"ok": true}"#.to_vec(),
+            trailers: HeaderMap::new(),
+        }
+    }
+
+    #[test]
+    fn parses_patterns() {
+        assert_eq!(StatusPattern::parse("204"), Some(StatusPattern::Exact(204)));
+        assert_eq!(StatusPattern::parse("2XX"), Some(StatusPattern::Class(2)));
+        assert_eq!(StatusPattern::parse("20"), None);
+        assert_eq!(StatusPattern::parse("6xx"), None);
+        assert_eq!(
+            HeaderExpectation::parse("Content-Type: application/json"),
+            Some(HeaderExpectation {
+                name: "Content-Type".into(),
+                value: Some("application/json".into()),
+            })
+        );
+        assert_eq!(HeaderExpectation::parse("ETag").unwrap().value, None);
+        assert_eq!(HeaderExpectation::parse("Bad Name: x"), None);
+    }
+
+    #[test]
+    fn passes_when_everything_holds() {
+        let expect = Expectations {
+            status: vec![StatusPattern::Exact(200), StatusPattern::Class(2)],
+            headers: vec![HeaderExpectation::parse("content-type: Application/JSON").unwrap()],
+            body_contains: vec!["\"ok\"".into()],
+        };
+        assert!(expect.check(&response()).is_ok());
+    }
+
+    #[test]
+    fn reports_every_failure() {
+        let expect = Expectations {
+            status: vec![StatusPattern::Exact(200)],
+            headers: vec![
+                HeaderExpectation::parse("Content-Type: text/html").unwrap(),
+                HeaderExpectation::parse("ETag").unwrap(),
+            ],
+            body_contains: vec!["error".into()],
+        };
+        let err = expect.check(&response()).unwrap_err();
+        assert_eq!(err.exit_code(), 22);
+        let Error::ExpectationFailed(failures) = err else {
+            unreachable!()
+        };
+        assert_eq!(
+            failures,
+            [
+                "status is 201, expected 200",
+                "header Content-Type is 'application/json; charset=utf-8', expected it to contain 'text/html'",
+                "header ETag is missing",
+                "body does not contain 'error'",
+            ]
+        );
+    }
+
+    #[test]
+    fn warns_and_fails_over_budget() {
+        let budgets = Budgets {
+            time_warn: Some(Duration::from_millis(100)),
+            time_limit: Some(Duration::from_millis(500)),
+            size_warn: Some(1024),
+            size_limit: None,
+        };
+        assert_eq!(
+            budgets.check(Duration::from_millis(100), 1024).unwrap(),
+            Vec::<String>::new()
+        );
+        assert_eq!(
+            budgets.check(Duration::from_millis(250), 2048).unwrap(),
+            [
+                "took 250 ms, over the 100 ms budget",
+                "body is 2048 bytes, over the 1024 byte budget",
+            ]
+        );
+        let err = budgets.check(Duration::from_millis(501), 0).unwrap_err();
+        assert_eq!(err.exit_code(), 102);
+        let Error::OverBudget(failures) = err else {
+            unreachable!()
+        };
+        assert_eq!(failures, ["took 501 ms, over the 500 ms budget"]);
+    }
+}
@@ -0,0 +1,209 @@
+//! `--export`: the composed request as a curl command or as a program
+//! using Python's requests or Rust's reqwest, for sharing a reproduction
+//! with people who do not use this client.
+
+use http_client::headers::HeaderMap;
+use http_client::json;
+use http_client::proxy::Proxy;
+
+/// The formats `--export` takes.
+pub const FORMATS: &[&str] = &["curl", "python", "rust"];
+
+/// Headers the other tools work out for themselves.
+const MANAGED: &[&str] = &["Host", "Content-Length", "Connection"];
+
+/// Everything about the request that the other tools need told.
+pub struct Request<'a> {
+    pub method: &'a str,
+    /// Without userinfo; credentials are in the headers.
+    pub url: &'a str,
+    /// Only given when it is not the URL's own.
+    pub target: Option<&'a str>,
+    pub headers: &'a HeaderMap,
+    pub body: Option<&'a str>,
+    pub proxy: Option<&'a Proxy>,
+}
+
+type Headers<'a> = Vec<(&'a str, &'a str)>;
+
+impl Request<'_> {
+    /// Headers for the server, then those for the proxy.
+    fn headers(&self) -> (Headers<'_>, Headers<'_>) {
+        let sent = self
+            .headers
+            .iter()
+            .filter(|(name, _)| !MANAGED.iter().any(|m| m.eq_ignore_ascii_case(name)));
+        sent.partition(|(name, _)| !name.to_ascii_lowercase().starts_with("proxy-"))
+    }
+
+    fn proxy_url(&self) -> Option<String> {
+        self.proxy
+            .map(|proxy| format!("http://{}:{}", proxy.host, proxy.port))
+    }
+}
+
+/// `request` in `format`, one of [`FORMATS`].
+pub fn render(format: &str, request: &Request) -> String {
+    match format {
+        "curl" => curl(request),
+        "python" => python(request),
+        "rust" => rust(request),
+        other => unreachable!("format '{}' is rejected by the parser", other),
+    }
+}
+
+/// Single-quoted for a POSIX shell.
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+/// A Python string literal; JSON's escapes are all valid Python.
+fn python_quote(s: &str) -> String {
+    let mut out = String::new();
+    json::write_string(&mut out, s).expect("writing to a String cannot fail");
+    out
+}
+
+fn curl(request: &Request) -> String {
+    let mut args = vec!["curl".to_string()];
+    match (request.method, request.body) {
+        ("GET", None) | ("POST", Some(_)) => {}
+        // -X HEAD would wait for a body that never comes.
+        ("HEAD", _) => args.push("--head".to_string()),
+        (method, _) => args.push(format!("-X {}", shell_quote(method))),
+    }
+    if let Some(target) = request.target {
+        args.push(format!("--request-target {}", shell_quote(target)));
+    }
+    let (headers, proxy_headers) = request.headers();
+    for (name, value) in headers {
+        args.push(format!(
+            "-H {}",
+            shell_quote(&format!("{}: {}", name, value))
+        ));
+    }
+    if let Some(proxy) = request.proxy_url() {
+        args.push(format!("-x {}", shell_quote(&proxy)));
+    }
+    for (name, value) in proxy_headers {
+        let header = format!("{}: {}", name, value);
+        args.push(format!("--proxy-header {}", shell_quote(&header)));
+    }
+    if let Some(body) = request.body {
+        args.push(format!("--data-raw {}", shell_quote(body)));
+    }
+    args.push(shell_quote(request.url));
+    format!("{}\n", args.join(" \\\n  "))
+}
+
+fn python(request: &Request) -> String {
+    let mut out = String::from("import requests\n\n");
+    out.push_str("response = requests.request(\n");
+    out.push_str(&format!("    {},\n", python_quote(request.method)));
+    out.push_str(&format!("    {},\n", python_quote(request.url)));
+    let (headers, proxy_headers) = request.headers();
+    // requests sends proxy headers with the request when it is proxied.
+    let headers: Vec<_> = headers.into_iter().chain(proxy_headers).collect();
+    if !headers.is_empty() {
+        out.push_str("    headers={\n");
+        for (name, value) in headers {
+            out.push_str(&format!(
+                "        {}: {},\n",
+                python_quote(name),
+                python_quote(value)
+            ));
+        }
+        out.push_str("    },\n");
+    }
+    if let Some(body) = request.body {
+        out.push_str(&format!("    data={},\n", python_quote(body)));
+    }
+    if let Some(proxy) = request.proxy_url() {
+        out.push_str(&format!(
+            "    proxies={{\"http\": {}}},\n",
+            python_quote(&proxy)
+        ));
+    }
+    out.push_str(")\nprint(response.text)\n");
+    out
+}
+
+fn rust(request: &Request) -> String {
+    let mut out = String::from("fn main() -> Result<(), Box<dyn std::error::Error>> {\n");
+    out.push_str("    let client = reqwest::blocking::Client::builder()\n");
+    if let Some(proxy) = request.proxy_url() {
+        out.push_str(&format!(
+            "        .proxy(reqwest::Proxy::http({:?})?)\n",
+            proxy
+        ));
+    }
+    out.push_str("        .build()?;\n");
+    out.push_str("    let response = client\n");
+    out.push_str(&format!(
+        "        .request(reqwest::Method::from_bytes({:?}.as_bytes())?, {:?})\n",
+        request.method, request.url
+    ));
+    let (headers, proxy_headers) = request.headers();
+    for (name, value) in headers.into_iter().chain(proxy_headers) {
+        out.push_str(&format!("        .header({:?}, {:?})\n", name, value));
+    }
+    if let Some(body) = request.body {
+        out.push_str(&format!("        .body({:?})\n", body));
+    }
+    out.push_str("        .send()?;\n");
+    out.push_str("    println!(\"{}\", response.text()?);\n");
+    out.push_str("    Ok(())\n}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request<'a>(headers: &'a HeaderMap, body: Option<&'a str>) -> Request<'a> {
+        Request {
+            method: "POST",
+            url: "http://example.test/a?b=1",
+            target: None,
+            headers,
+            body,
+            proxy: None,
+        }
+    }
+
+    #[test]
+    fn exports_curl_commands() {
+        let mut headers = HeaderMap::new();
+        headers.append("Host", "example.test");
+        headers.append("X-Note", "it's");
+        headers.append("Proxy-Authorization", "Basic eA==");
+        let mut request = request(&headers, Some("a=1"));
+        let proxy = Proxy::parse("proxy.test:3128").unwrap();
+        request.proxy = Some(&proxy);
+        assert_eq!(
+            render("curl", &request),
+            "curl \\\n  -H 'X-Note: it'\\''s' \\\n  -x 'http://proxy.test:3128' \\\n  \
+             --proxy-header 'Proxy-Authorization: Basic eA==' \\\n  --data-raw 'a=1' \\\n  \
+             'http://example.test/a?b=1'\n"
+        );
+        request.body = None;
+        request.method = "HEAD";
+        assert!(render("curl", &request).starts_with("curl \\\n  --head \\\n"));
+    }
+
+    #[test]
+    fn exports_programs() {
+        let mut headers = HeaderMap::new();
+        headers.append("Content-Length", "9");
+        headers.append("Accept", "*/*");
+        let request = request(&headers, Some("{\"k\": 1}"));
+        let python = render("python", &request);
+        assert!(python.contains("    \"POST\",\n    \"http://example.test/a?b=1\",\n"));
+        assert!(python.contains("        \"Accept\": \"*/*\",\n"));
+        assert!(python.contains("    data=\"{\\\"k\\\": 1}\",\n"));
+        assert!(!python.contains("Content-Length"));
+        let rust = render("rust", &request);
+        assert!(rust.contains(".header(\"Accept\", \"*/*\")"));
+        assert!(rust.contains(".body(\"{\\\"k\\\": 1}\")"));
+    }
+}
@@ -0,0 +1,52 @@
+//! Reading `file:` URLs, so local sources go through the same output
+//! handling as downloads.
+
+use std::fs::File;
+use std::io::Read;
+
+use crate::error::{Error, Result};
+use crate::http::Limits;
+use crate::url::Url;
+
+/// Reads the file a `file:` URL names, enforcing `--max-filesize`.
+pub fn read(url: &Url, limits: &Limits) -> Result<Vec<u8>> {
+    let path = url.to_file_path()?;
+    let read_error = |source| Error::ReadFile {
+        path: path.display().to_string(),
+        source,
+    };
+    let file = File::open(&path).map_err(read_error)?;
+    let max = limits.max_filesize.unwrap_or(u64::MAX);
+    let mut data = Vec::new();
+    file.take(max.saturating_add(1))
+        .read_to_end(&mut data)
+        .map_err(read_error)?;
+    if data.len() as u64 > max {
+        return Err(Error::FileSizeExceeded(max));
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_local_files() {
+        let path = std::env::temp_dir().join("http_client file test.txt");
+        std::fs::write(&path, b"local data").unwrap();
+        let url = Url::parse(&format!(
+            "file://{}",
+            path.display().to_string().replace(' ', "%20")
+        ))
+        .unwrap();
+        assert_eq!(read(&url, &Limits::default()).unwrap(), b"local data");
+        let limits = Limits {
+            max_filesize: Some(4),
+            ..Limits::default()
+        };
+        assert_eq!(read(&url, &limits).unwrap_err().exit_code(), 63);
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(read(&url, &Limits::default()).unwrap_err().exit_code(), 37);
+    }
+}
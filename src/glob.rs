@@ -0,0 +1,208 @@
+//! URL globbing, as curl does it.
+//!
+//! `{a,b,c}` stands for each of its alternatives, and `[1-10]`, `[001-100]`
+//! or `[a-z]` for each item of a range, every `n`th with `[0-100:10]`; a
+//! range that starts with a zero is padded to its width. A URL with several
+//! patterns stands for every combination, the last varying fastest. What
+//! each pattern stood for is kept, for `-o` templates to name as `#1`, `#2`
+//! and so on. A backslash makes a bracket or brace plain, and the brackets
+//! of an IPv6 host, as in `http://[::1]/`, are not a pattern.
+
+use crate::error::{Error, Result};
+
+/// One of the URLs a glob stands for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expansion {
+    pub url: String,
+    /// What each pattern stood for, in order.
+    pub matches: Vec<String>,
+}
+
+impl Expansion {
+    /// A URL that is not a glob, as an expansion of itself.
+    pub fn plain(url: &str) -> Expansion {
+        Expansion {
+            url: url.to_string(),
+            matches: Vec::new(),
+        }
+    }
+}
+
+enum Piece {
+    Text(String),
+    Choice(Vec<String>),
+}
+
+/// Every URL `glob` stands for.
+pub fn expand(glob: &str) -> Result<Vec<Expansion>> {
+    let mut expansions = vec![Expansion::plain("")];
+    for piece in pieces(glob)? {
+        match piece {
+            Piece::Text(text) => {
+                for expansion in &mut expansions {
+                    expansion.url.push_str(&text);
+                }
+            }
+            Piece::Choice(items) => {
+                expansions = expansions
+                    .iter()
+                    .flat_map(|expansion| {
+                        items.iter().map(move |item| {
+                            let mut next = expansion.clone();
+                            next.url.push_str(item);
+                            next.matches.push(item.clone());
+                            next
+                        })
+                    })
+                    .collect();
+            }
+        }
+    }
+    Ok(expansions)
+}
+
+/// Whether `url` has a pattern in it, or a malformed one.
+pub fn is_glob(url: &str) -> bool {
+    match pieces(url) {
+        Ok(pieces) => pieces.iter().any(|piece| matches!(piece, Piece::Choice(_))),
+        Err(_) => true,
+    }
+}
+
+fn pieces(glob: &str) -> Result<Vec<Piece>> {
+    let bad = |why: &str| Error::UrlParse(format!("bad URL glob '{}': {}", glob, why));
+    let mut pieces = Vec::new();
+    let mut text = String::new();
+    let mut rest = glob;
+    while let Some(c) = rest.chars().next() {
+        let done = glob.len() - rest.len();
+        rest = &rest[c.len_utf8()..];
+        let close = match c {
+            '\\' if rest.starts_with(['[', ']', '{', '}']) => {
+                text.push(rest.as_bytes()[0] as char);
+                rest = &rest[1..];
+                continue;
+            }
+            '[' if is_ipv6_host(&glob[..done]) => {
+                let end = rest.find(']').map_or(rest.len(), |end| end + 1);
+                text.push(c);
+                text.push_str(&rest[..end]);
+                rest = &rest[end..];
+                continue;
+            }
+            '{' => '}',
+            '[' => ']',
+            ']' | '}' => return Err(bad("unmatched close")),
+            c => {
+                text.push(c);
+                continue;
+            }
+        };
+        let end = rest.find(close).ok_or_else(|| bad("unmatched open"))?;
+        let body = &rest[..end];
+        rest = &rest[end + 1..];
+        let items = match close {
+            '}' => body.split(',').map(str::to_string).collect(),
+            _ => range(body).ok_or_else(|| bad("bad range"))?,
+        };
+        pieces.push(Piece::Text(std::mem::take(&mut text)));
+        pieces.push(Piece::Choice(items));
+    }
+    pieces.push(Piece::Text(text));
+    Ok(pieces)
+}
+
+/// Whether a `[` after `before` opens an IPv6 host rather than a range.
+fn is_ipv6_host(before: &str) -> bool {
+    let Some((_, authority)) = before.split_once("://") else {
+        return false;
+    };
+    authority.is_empty() || (authority.ends_with('@') && !authority.contains('/'))
+}
+
+/// The items of `a-b` or `a-b:step`.
+fn range(body: &str) -> Option<Vec<String>> {
+    let (span, step) = match body.split_once(':') {
+        Some((span, step)) => (span, step.parse().ok().filter(|&step| step > 0)?),
+        None => (body, 1),
+    };
+    let (start, end) = span.split_once('-')?;
+    let letters = |s: &str| {
+        let mut chars = s.chars();
+        chars
+            .next()
+            .filter(|c| c.is_ascii_alphabetic() && chars.next().is_none())
+    };
+    if let (Some(start), Some(end)) = (letters(start), letters(end)) {
+        if start > end || start.is_ascii_lowercase() != end.is_ascii_lowercase() {
+            return None;
+        }
+        let items = (start..=end).step_by(step).map(String::from).collect();
+        return Some(items);
+    }
+    let width = match start.len() > 1 && start.starts_with('0') {
+        true => start.len(),
+        false => 0,
+    };
+    let (start, end): (u64, u64) = (start.parse().ok()?, end.parse().ok()?);
+    if start > end {
+        return None;
+    }
+    let items = (start..=end)
+        .step_by(step)
+        .map(|n| format!("{:0width$}", n, width = width))
+        .collect();
+    Some(items)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls(glob: &str) -> Vec<String> {
+        expand(glob).unwrap().into_iter().map(|e| e.url).collect()
+    }
+
+    #[test]
+    fn expands_sets_and_ranges() {
+        assert_eq!(
+            urls("http://h/{a,b}/[1-2]"),
+            [
+                "http://h/a/1",
+                "http://h/a/2",
+                "http://h/b/1",
+                "http://h/b/2"
+            ]
+        );
+        assert_eq!(
+            urls("http://h/[08-10]"),
+            ["http://h/08", "http://h/09", "http://h/10"]
+        );
+        assert_eq!(
+            urls("http://h/[a-e:2]"),
+            ["http://h/a", "http://h/c", "http://h/e"]
+        );
+        let expansions = expand("http://h/{x,y}[3-3]").unwrap();
+        assert_eq!(expansions[1].matches, ["y", "3"]);
+    }
+
+    #[test]
+    fn leaves_ipv6_hosts_and_escapes_alone() {
+        assert_eq!(
+            urls("http://[::1]:8080/[1-2]"),
+            ["http://[::1]:8080/1", "http://[::1]:8080/2"]
+        );
+        assert_eq!(urls("http://u@[::1]/\\{x\\}"), ["http://u@[::1]/{x}"]);
+        assert!(!is_glob("http://[::1]/plain"));
+        assert!(is_glob("http://h/[1-"));
+        for bad in [
+            "http://h/[5-1]",
+            "http://h/[a-Z]",
+            "http://h/{a",
+            "http://h/]",
+            "http://h/[1-2:0]",
+        ] {
+            assert_eq!(expand(bad).unwrap_err().exit_code(), 3, "{}", bad);
+        }
+    }
+}
@@ -0,0 +1,655 @@
+//! gzip (RFC 1952) and deflate: compressing request bodies for
+//! `--compress-request`, and decompressing responses for `--compressed`.
+//!
+//! [`Encoder`] compresses whatever it reads, so a body can be compressed
+//! while it streams. The deflate stream (RFC 1951) is a series of blocks
+//! with the fixed Huffman codes, each the LZ77 matches of up to 64 KiB of
+//! input against the 32 KiB before it. That is not as tight as zlib's
+//! dynamic codes, but it is most of the way for the repetitive text, JSON
+//! and logs that are worth compressing.
+//!
+//! [`decompress`] and [`inflate`] read any deflate stream, whole.
+//!
+//! This is written here rather than taken from a crate such as flate2 so
+//! that the `compression` feature adds no dependency.
+
+use std::io::{self, Read};
+
+use crate::error::{Error, Result};
+
+/// Input compressed as one block.
+const BLOCK: usize = 64 * 1024;
+/// How far back a match may start.
+const WINDOW: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+/// How many earlier positions with the same hash are tried for a match.
+const MAX_CHAIN: usize = 64;
+const HASH_BITS: u32 = 15;
+
+/// The first lengths for length codes 257 to 285, and their extra bits.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+/// The first distances for distance codes 0 to 29, and their extra bits.
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// A reader of the gzip compression of what `inner` reads.
+pub struct Encoder<R> {
+    inner: R,
+    /// The end of the input before the block being compressed, for matches.
+    history: Vec<u8>,
+    bits: Bits,
+    /// Compressed bytes not yet read, from `at` on.
+    out: Vec<u8>,
+    at: usize,
+    crc: u32,
+    size: u32,
+    done: bool,
+}
+
+impl<R: Read> Encoder<R> {
+    pub fn new(inner: R) -> Encoder<R> {
+        // No file name or time; the OS is "unknown".
+        let out = vec![0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+        Encoder {
+            inner,
+            history: Vec::new(),
+            bits: Bits::default(),
+            out,
+            at: 0,
+            crc: 0,
+            size: 0,
+            done: false,
+        }
+    }
+
+    /// Compresses the next block of input, or ends the stream.
+    fn fill(&mut self) -> io::Result<()> {
+        let mut block = Vec::with_capacity(BLOCK);
+        (&mut self.inner)
+            .take(BLOCK as u64)
+            .read_to_end(&mut block)?;
+        self.out.clear();
+        self.at = 0;
+        if block.is_empty() {
+            // A last, empty block, then the trailer.
+            self.bits.put(1, 1);
+            self.bits.put(1, 2);
+            self.bits.put_code(256);
+            self.bits.align();
+            self.bits.drain(&mut self.out);
+            self.out.extend_from_slice(&self.crc.to_le_bytes());
+            self.out.extend_from_slice(&self.size.to_le_bytes());
+            self.done = true;
+            return Ok(());
+        }
+        self.crc = crc32(self.crc, &block);
+        self.size = self.size.wrapping_add(block.len() as u32);
+        let start = self.history.len();
+        let mut data = std::mem::take(&mut self.history);
+        data.extend_from_slice(&block);
+        self.bits.put(0, 1);
+        self.bits.put(1, 2);
+        compress(&data, start, &mut self.bits);
+        self.bits.put_code(256);
+        self.bits.drain(&mut self.out);
+        self.history = data[data.len().saturating_sub(WINDOW)..].to_vec();
+        Ok(())
+    }
+}
+
+impl<R: Read> Read for Encoder<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.at == self.out.len() {
+            if self.done {
+                return Ok(0);
+            }
+            self.fill()?;
+        }
+        let n = buf.len().min(self.out.len() - self.at);
+        buf[..n].copy_from_slice(&self.out[self.at..self.at + n]);
+        self.at += n;
+        Ok(n)
+    }
+}
+
+/// Writes the symbols for `data[start..]`, matching against all of `data`
+/// before each position.
+fn compress(data: &[u8], start: usize, bits: &mut Bits) {
+    let mut chains = Chains {
+        head: vec![usize::MAX; 1 << HASH_BITS],
+        prev: vec![usize::MAX; data.len()],
+    };
+    for at in start.saturating_sub(WINDOW)..start {
+        chains.insert(data, at);
+    }
+    let mut at = start;
+    while at < data.len() {
+        let (length, distance) = chains.longest_match(data, at);
+        if length >= MIN_MATCH {
+            bits.put_length(length);
+            bits.put_distance(distance);
+            for next in at..at + length {
+                chains.insert(data, next);
+            }
+            at += length;
+        } else {
+            bits.put_code(u16::from(data[at]));
+            chains.insert(data, at);
+            at += 1;
+        }
+    }
+}
+
+/// The positions of the input by the hash of the three bytes there: the
+/// latest for each hash, and the one before each position.
+struct Chains {
+    head: Vec<usize>,
+    prev: Vec<usize>,
+}
+
+impl Chains {
+    fn insert(&mut self, data: &[u8], at: usize) {
+        if at + MIN_MATCH <= data.len() {
+            let h = hash(data, at);
+            self.prev[at] = self.head[h];
+            self.head[h] = at;
+        }
+    }
+
+    /// The longest earlier run `data[at..]` starts with, as its length and
+    /// how far back it is.
+    fn longest_match(&self, data: &[u8], at: usize) -> (usize, usize) {
+        if at + MIN_MATCH > data.len() {
+            return (0, 0);
+        }
+        let max = MAX_MATCH.min(data.len() - at);
+        let (mut best, mut distance) = (0, 0);
+        let mut candidate = self.head[hash(data, at)];
+        for _ in 0..MAX_CHAIN {
+            if candidate == usize::MAX || at - candidate > WINDOW {
+                break;
+            }
+            let length = data[candidate..]
+                .iter()
+                .zip(&data[at..at + max])
+                .take_while(|(a, b)| a == b)
+                .count();
+            if length > best {
+                (best, distance) = (length, at - candidate);
+                if length == max {
+                    break;
+                }
+            }
+            candidate = self.prev[candidate];
+        }
+        (best, distance)
+    }
+}
+
+fn hash(data: &[u8], at: usize) -> usize {
+    let key = u32::from_le_bytes([data[at], data[at + 1], data[at + 2], 0]);
+    (key.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+}
+
+/// A deflate bit stream: values go in from the least significant bit,
+/// Huffman codes from the most.
+#[derive(Default)]
+struct Bits {
+    bytes: Vec<u8>,
+    acc: u64,
+    count: u32,
+}
+
+impl Bits {
+    fn put(&mut self, value: u32, count: u32) {
+        self.acc |= u64::from(value) << self.count;
+        self.count += count;
+        while self.count >= 8 {
+            self.bytes.push(self.acc as u8);
+            self.acc >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn put_huffman(&mut self, code: u32, length: u32) {
+        self.put(code.reverse_bits() >> (32 - length), length);
+    }
+
+    /// A literal/length symbol, in the fixed code.
+    fn put_code(&mut self, symbol: u16) {
+        let symbol = u32::from(symbol);
+        match symbol {
+            0..=143 => self.put_huffman(0x30 + symbol, 8),
+            144..=255 => self.put_huffman(0x190 + symbol - 144, 9),
+            256..=279 => self.put_huffman(symbol - 256, 7),
+            _ => self.put_huffman(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    fn put_length(&mut self, length: usize) {
+        let code = LENGTH_BASE.partition_point(|&base| usize::from(base) <= length) - 1;
+        self.put_code(257 + code as u16);
+        let extra = length - usize::from(LENGTH_BASE[code]);
+        self.put(extra as u32, u32::from(LENGTH_EXTRA[code]));
+    }
+
+    fn put_distance(&mut self, distance: usize) {
+        let code = DISTANCE_BASE.partition_point(|&base| usize::from(base) <= distance) - 1;
+        self.put_huffman(code as u32, 5);
+        let extra = distance - usize::from(DISTANCE_BASE[code]);
+        self.put(extra as u32, u32::from(DISTANCE_EXTRA[code]));
+    }
+
+    /// Pads to a whole byte, as the end of the stream.
+    fn align(&mut self) {
+        if self.count > 0 {
+            self.put(0, 8 - self.count);
+        }
+    }
+
+    /// Moves the finished bytes to `out`.
+    fn drain(&mut self, out: &mut Vec<u8>) {
+        out.append(&mut self.bytes);
+    }
+}
+
+/// The data in the gzip file `data`, which may be several members one
+/// after another, failing past `max` bytes.
+pub fn decompress(data: &[u8], max: u64) -> Result<Vec<u8>> {
+    let mut out = Vec::new();
+    let mut rest = data;
+    while !rest.is_empty() {
+        let start = out.len();
+        let mut input = Input::new(gzip_header(rest)?);
+        inflate_into(&mut input, &mut out, max)?;
+        let trailer = rest
+            .len()
+            .checked_sub(input.remaining().len())
+            .map(|used| &rest[used..])
+            .filter(|trailer| trailer.len() >= 8)
+            .ok_or_else(|| bad("gzip trailer is missing"))?;
+        let crc = u32::from_le_bytes([trailer[0], trailer[1], trailer[2], trailer[3]]);
+        let size = u32::from_le_bytes([trailer[4], trailer[5], trailer[6], trailer[7]]);
+        if crc != crc32(0, &out[start..]) || size != (out.len() - start) as u32 {
+            return Err(bad("gzip checksum mismatch"));
+        }
+        rest = &trailer[8..];
+    }
+    Ok(out)
+}
+
+/// The data in the `deflate` content coding `data`: a zlib stream (RFC
+/// 1950) as the coding is defined, or a bare deflate stream as some
+/// servers send instead. Fails past `max` bytes.
+pub fn inflate(data: &[u8], max: u64) -> Result<Vec<u8>> {
+    let zlib = data.len() >= 2
+        && data[0] & 0x0f == 8
+        && u16::from_be_bytes([data[0], data[1]]).is_multiple_of(31)
+        && data[1] & 0x20 == 0;
+    let mut out = Vec::new();
+    inflate_into(
+        &mut Input::new(if zlib { &data[2..] } else { data }),
+        &mut out,
+        max,
+    )?;
+    Ok(out)
+}
+
+/// Where the deflate stream in the gzip member at the start of `data`
+/// begins.
+fn gzip_header(data: &[u8]) -> Result<&[u8]> {
+    if data.len() < 10 || data[..3] != [0x1f, 0x8b, 8] {
+        return Err(bad("not gzip data"));
+    }
+    let flags = data[3];
+    let mut at = 10;
+    let short = || bad("gzip header is cut short");
+    if flags & 4 != 0 {
+        let extra = data.get(at..at + 2).ok_or_else(short)?;
+        at += 2 + usize::from(u16::from_le_bytes([extra[0], extra[1]]));
+    }
+    // The file name and comment end with a zero byte.
+    for flag in [8, 16] {
+        if flags & flag != 0 {
+            let end = data
+                .get(at..)
+                .and_then(|rest| rest.iter().position(|&b| b == 0));
+            at += end.ok_or_else(short)? + 1;
+        }
+    }
+    if flags & 2 != 0 {
+        at += 2;
+    }
+    data.get(at..).ok_or_else(short)
+}
+
+fn bad(why: &str) -> Error {
+    Error::BadContentEncoding(why.to_string())
+}
+
+/// Bits read from the least significant end of each byte.
+struct Input<'a> {
+    data: &'a [u8],
+    at: usize,
+    acc: u32,
+    count: u32,
+}
+
+impl<'a> Input<'a> {
+    fn new(data: &'a [u8]) -> Input<'a> {
+        Input {
+            data,
+            at: 0,
+            acc: 0,
+            count: 0,
+        }
+    }
+
+    fn bits(&mut self, count: u32) -> Result<u32> {
+        while self.count < count {
+            let byte = *self
+                .data
+                .get(self.at)
+                .ok_or_else(|| bad("deflate data is cut short"))?;
+            self.acc |= u32::from(byte) << self.count;
+            self.at += 1;
+            self.count += 8;
+        }
+        let value = self.acc & ((1u64 << count) - 1) as u32;
+        self.acc = self.acc.checked_shr(count).unwrap_or(0);
+        self.count -= count;
+        Ok(value)
+    }
+
+    /// Drops the bits left of the current byte.
+    fn align(&mut self) {
+        self.acc = 0;
+        self.count = 0;
+    }
+
+    /// The whole bytes not read yet.
+    fn remaining(&self) -> &'a [u8] {
+        &self.data[self.at - (self.count / 8) as usize..]
+    }
+
+    /// The next symbol in `code`.
+    fn decode(&mut self, code: &Huffman) -> Result<u16> {
+        // Codes of each length follow on from the shorter ones (RFC 1951
+        // 3.2.2), so one pass over the lengths finds the symbol.
+        let (mut value, mut first, mut index) = (0, 0, 0);
+        for &count in &code.counts[1..] {
+            value |= self.bits(1)? as usize;
+            if value < first + usize::from(count) {
+                return Ok(code.symbols[index + value - first]);
+            }
+            index += usize::from(count);
+            first = (first + usize::from(count)) << 1;
+            value <<= 1;
+        }
+        Err(bad("bad deflate code"))
+    }
+}
+
+/// A canonical Huffman code: how many codes there are of each length, and
+/// the symbols in code order.
+struct Huffman {
+    counts: [u16; 16],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Huffman> {
+        let mut counts = [0u16; 16];
+        for &length in lengths {
+            counts[usize::from(length)] += 1;
+        }
+        counts[0] = 0;
+        // More codes of a length than there is room for is no code.
+        let mut left = 1i32;
+        for &count in &counts[1..] {
+            left = (left << 1) - i32::from(count);
+            if left < 0 {
+                return Err(bad("bad deflate code lengths"));
+            }
+        }
+        let mut offsets = [0usize; 16];
+        for length in 1..15 {
+            offsets[length + 1] = offsets[length] + usize::from(counts[length]);
+        }
+        let mut symbols = vec![0; lengths.len()];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length != 0 {
+                symbols[offsets[usize::from(length)]] = symbol as u16;
+                offsets[usize::from(length)] += 1;
+            }
+        }
+        Ok(Huffman { counts, symbols })
+    }
+
+    /// The fixed literal/length and distance codes.
+    fn fixed() -> (Huffman, Huffman) {
+        let mut lengths = [8u8; 288];
+        lengths[144..256].fill(9);
+        lengths[256..280].fill(7);
+        let literals = Huffman::new(&lengths).expect("the fixed code is complete");
+        let distances = Huffman::new(&[5; 30]).expect("the fixed code is complete");
+        (literals, distances)
+    }
+}
+
+/// The order code length code lengths come in.
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Decompresses the deflate stream at the start of `input` onto `out`.
+fn inflate_into(input: &mut Input, out: &mut Vec<u8>, max: u64) -> Result<()> {
+    loop {
+        let last = input.bits(1)? == 1;
+        match input.bits(2)? {
+            0 => {
+                input.align();
+                let rest = input.remaining();
+                if rest.len() < 4 {
+                    return Err(bad("deflate data is cut short"));
+                }
+                let length = usize::from(u16::from_le_bytes([rest[0], rest[1]]));
+                if u16::from_le_bytes([rest[2], rest[3]]) != !(length as u16) {
+                    return Err(bad("bad stored deflate block"));
+                }
+                let stored = rest
+                    .get(4..4 + length)
+                    .ok_or_else(|| bad("deflate data is cut short"))?;
+                out.extend_from_slice(stored);
+                input.at += 4 + length;
+            }
+            1 => {
+                let (literals, distances) = Huffman::fixed();
+                codes(input, out, &literals, &distances, max)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic(input)?;
+                codes(input, out, &literals, &distances, max)?;
+            }
+            _ => return Err(bad("bad deflate block type")),
+        }
+        if out.len() as u64 > max {
+            return Err(Error::FileSizeExceeded(max));
+        }
+        if last {
+            return Ok(());
+        }
+    }
+}
+
+/// The codes a dynamic block starts by describing.
+fn dynamic(input: &mut Input) -> Result<(Huffman, Huffman)> {
+    let literals = input.bits(5)? as usize + 257;
+    let distances = input.bits(5)? as usize + 1;
+    let code_lengths = input.bits(4)? as usize + 4;
+    let mut lengths = [0u8; 19];
+    for &symbol in &CODE_LENGTH_ORDER[..code_lengths] {
+        lengths[symbol] = input.bits(3)? as u8;
+    }
+    let code = Huffman::new(&lengths)?;
+    let mut lengths = Vec::with_capacity(literals + distances);
+    while lengths.len() < literals + distances {
+        let (length, repeat) = match input.decode(&code)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths
+                    .last()
+                    .ok_or_else(|| bad("bad deflate code lengths"))?;
+                (previous, 3 + input.bits(2)?)
+            }
+            17 => (0, 3 + input.bits(3)?),
+            _ => (0, 11 + input.bits(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(length, repeat as usize));
+    }
+    if lengths.len() > literals + distances || lengths[256] == 0 {
+        return Err(bad("bad deflate code lengths"));
+    }
+    let (literal_lengths, distance_lengths) = lengths.split_at(literals);
+    Ok((
+        Huffman::new(literal_lengths)?,
+        Huffman::new(distance_lengths)?,
+    ))
+}
+
+/// The literals and matches of a block, up to its end.
+fn codes(
+    input: &mut Input,
+    out: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+    max: u64,
+) -> Result<()> {
+    loop {
+        let symbol = input.decode(literals)?;
+        let code = match symbol {
+            0..=255 => {
+                out.push(symbol as u8);
+                continue;
+            }
+            256 => return Ok(()),
+            _ => usize::from(symbol - 257),
+        };
+        let (Some(&base), Some(&extra)) = (LENGTH_BASE.get(code), LENGTH_EXTRA.get(code)) else {
+            return Err(bad("bad deflate length"));
+        };
+        let length = usize::from(base) + input.bits(u32::from(extra))? as usize;
+        let code = usize::from(input.decode(distances)?);
+        let (Some(&base), Some(&extra)) = (DISTANCE_BASE.get(code), DISTANCE_EXTRA.get(code))
+        else {
+            return Err(bad("bad deflate distance"));
+        };
+        let distance = usize::from(base) + input.bits(u32::from(extra))? as usize;
+        if distance > out.len() {
+            return Err(bad("deflate distance is too far back"));
+        }
+        // The match may overlap what it produces, so a byte at a time.
+        let start = out.len() - distance;
+        for i in 0..length {
+            out.push(out[start + i]);
+        }
+        if out.len() as u64 > max {
+            return Err(Error::FileSizeExceeded(max));
+        }
+    }
+}
+
+/// `crc` updated with `data`: the CRC-32 gzip ends with.
+fn crc32(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &byte in data {
+        crc ^= u32::from(byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gzip(data: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        Encoder::new(data).read_to_end(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn frames_the_stream() {
+        assert_eq!(crc32(0, b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(crc32(0, b"1234"), b"56789"), 0xCBF4_3926);
+        assert_eq!(
+            gzip(b""),
+            [0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+        // A block of the literal 'a' (0x30 + 0x61 in 8 bits) and the end,
+        // then the last, empty block.
+        assert_eq!(&gzip(b"a")[10..14], [0x4a, 0x04, 0x0c, 0x00]);
+    }
+
+    #[test]
+    fn decompresses_what_zlib_makes() {
+        // Python's gzip.compress(b"hello hello hello\n", mtime=0): one
+        // block with the fixed codes.
+        let gzipped = [
+            0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 2, 3, 0xcb, 0x48, 0xcd, 0xc9, 0xc9, 0x57, 0xc8, 0x40,
+            0x90, 0x5c, 0, 0x3b, 0x7c, 0x8a, 0xdf, 0x12, 0, 0, 0,
+        ];
+        assert_eq!(decompress(&gzipped, 100).unwrap(), b"hello hello hello\n");
+        assert!(matches!(
+            decompress(&gzipped, 5),
+            Err(Error::FileSizeExceeded(5))
+        ));
+        let mut corrupt = gzipped;
+        corrupt[22] ^= 1;
+        assert!(decompress(&corrupt, 100).is_err());
+        assert!(decompress(b"plain", 100).is_err());
+        // zlib.compress(digits, 9): one block with its own codes.
+        let zlib = [
+            0x78, 0xda, 0x0d, 0xca, 0xc1, 0x01, 0x00, 0x30, 0x08, 0xc2, 0xc0, 0x95, 0x44, 0x50,
+            0xcb, 0xfe, 0x8b, 0x95, 0x6f, 0x2e, 0x05, 0x19, 0xdb, 0xc3, 0x95, 0x57, 0x0f, 0x6c,
+            0xe9, 0xae, 0x9b, 0x89, 0x1e, 0xf2, 0x0a, 0xc1, 0x50, 0x36, 0xd1, 0x9a, 0x7a, 0x3b,
+            0x1f, 0x23, 0x99, 0x0a, 0xd4,
+        ];
+        let digits = b"01491625364964813244772231629533701253964491439450865";
+        assert_eq!(inflate(&zlib, 100).unwrap(), digits);
+        // The same without the zlib wrapper, as some servers send it.
+        assert_eq!(inflate(&zlib[2..], 100).unwrap(), digits);
+    }
+
+    #[test]
+    fn shrinks_repetitive_input_across_blocks() {
+        let data = "{\"id\": 1, \"name\": \"item\"},\n".repeat(10_000);
+        let out = gzip(data.as_bytes());
+        assert!(out.len() < data.len() / 20, "{} bytes", out.len());
+        let trailer = &out[out.len() - 8..];
+        assert_eq!(trailer[..4], crc32(0, data.as_bytes()).to_le_bytes());
+        assert_eq!(trailer[4..], (data.len() as u32).to_le_bytes());
+        let max = data.len() as u64;
+        assert_eq!(decompress(&out, max).unwrap(), data.as_bytes());
+    }
+}
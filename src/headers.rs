@@ -0,0 +1,338 @@
+//! An ordered, case-insensitive multimap of header fields.
+
+use std::borrow::Cow;
+use std::fmt;
+
+use crate::error::{Error, Result};
+
+/// Header fields in insertion order.
+///
+/// Names are compared case-insensitively but stored as given, so output
+/// shows them exactly as they were received or configured. A name may occur
+/// more than once (`Set-Cookie` being the usual example).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct HeaderMap {
+    entries: Vec<(String, String)>,
+}
+
+impl HeaderMap {
+    pub fn new() -> HeaderMap {
+        HeaderMap::default()
+    }
+
+    /// Adds a field, keeping any existing fields with the same name.
+    pub fn append(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.entries.push((name.into(), value.into()));
+    }
+
+    /// Sets a field, replacing every existing field with the same name. The
+    /// new value takes the position of the first one replaced.
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        let name = name.into();
+        let value = value.into();
+        match self.position(&name) {
+            Some(index) => {
+                self.entries[index] = (name.clone(), value);
+                let mut seen = false;
+                self.entries.retain(|(n, _)| {
+                    if !n.eq_ignore_ascii_case(&name) {
+                        return true;
+                    }
+                    let keep = !seen;
+                    seen = true;
+                    keep
+                });
+            }
+            None => self.entries.push((name, value)),
+        }
+    }
+
+    /// Removes every field named `name`; returns whether any existed.
+    pub fn remove(&mut self, name: &str) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|(n, _)| !n.eq_ignore_ascii_case(name));
+        self.entries.len() != before
+    }
+
+    /// The first value of `name`.
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.position(name).map(|i| self.entries[i].1.as_str())
+    }
+
+    /// Every value of `name`, in order.
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.entries
+            .iter()
+            .filter(move |(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.position(name).is_some()
+    }
+
+    /// All fields as `(name, value)` pairs, in order.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(n, v)| (n.as_str(), v.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Checks every field with [`validate`], so that nothing can break out
+    /// of its header line when the map is serialized.
+    pub fn validate(&self) -> Result<()> {
+        self.iter()
+            .try_for_each(|(name, value)| validate(name, value))
+    }
+
+    fn position(&self, name: &str) -> Option<usize> {
+        self.entries
+            .iter()
+            .position(|(n, _)| n.eq_ignore_ascii_case(name))
+    }
+}
+
+/// One `Name: value\r\n` line per field (`Name:` when the value is empty).
+impl fmt::Display for HeaderMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, value) in self.iter() {
+            if value.is_empty() {
+                write!(f, "{}:\r\n", name)?;
+            } else {
+                write!(f, "{}: {}\r\n", name, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<N: Into<String>, V: Into<String>> FromIterator<(N, V)> for HeaderMap {
+    fn from_iter<I: IntoIterator<Item = (N, V)>>(iter: I) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in iter {
+            map.append(name, value);
+        }
+        map
+    }
+}
+
+/// Whether `b` may appear in a token (RFC 9110 section 5.6.2), the syntax
+/// of header names and methods.
+pub fn is_token_byte(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b)
+}
+
+/// Rejects a header whose name is not a token or whose value contains CR,
+/// LF or NUL, any of which would let it smuggle extra lines into a request.
+pub fn validate(name: &str, value: &str) -> Result<()> {
+    if name.is_empty() || !name.bytes().all(is_token_byte) {
+        return Err(Error::InvalidHeader(format!(
+            "invalid header name '{}'",
+            name.escape_debug()
+        )));
+    }
+    if value.bytes().any(|b| matches!(b, b'\r' | b'\n' | 0)) {
+        return Err(Error::InvalidHeader(format!(
+            "header '{}' has a line break or NUL in its value",
+            name
+        )));
+    }
+    Ok(())
+}
+
+/// One `-H` argument, in curl's syntax.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CustomHeader {
+    /// `Name: value` sets a header; `Name;` sets it with an empty value.
+    Set(String, String),
+    /// `Name:` with nothing after the colon removes a built-in header.
+    Remove(String),
+}
+
+impl CustomHeader {
+    pub fn parse(arg: &str) -> Result<CustomHeader> {
+        let arg = arg.trim();
+        if let Some(name) = arg.strip_suffix(';') {
+            if !name.contains(':') {
+                return Ok(CustomHeader::Set(check_name(name, arg)?, String::new()));
+            }
+        }
+        let (name, value) = arg.split_once(':').ok_or_else(|| {
+            Error::InvalidHeader(format!("header '{}' must be 'Name: value'", arg))
+        })?;
+        let name = check_name(name, arg)?;
+        let value = value.trim();
+        validate(&name, value)?;
+        if value.is_empty() {
+            Ok(CustomHeader::Remove(name))
+        } else {
+            Ok(CustomHeader::Set(name, value.to_string()))
+        }
+    }
+}
+
+fn check_name(name: &str, arg: &str) -> Result<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(Error::InvalidHeader(format!(
+            "header '{}' has an empty name",
+            arg
+        )));
+    }
+    validate(name, "")?;
+    Ok(name.to_string())
+}
+
+/// A header value as `-v` shows it: the credentials of `Authorization` and
+/// `Proxy-Authorization` become `***`, keeping the scheme.
+pub fn redact<'a>(name: &str, value: &'a str) -> Cow<'a, str> {
+    let secret = name.eq_ignore_ascii_case("Authorization")
+        || name.eq_ignore_ascii_case("Proxy-Authorization");
+    match value.trim().split_once(' ') {
+        _ if !secret => Cow::Borrowed(value),
+        Some((scheme, _)) => Cow::Owned(format!("{} ***", scheme)),
+        None => Cow::Borrowed("***"),
+    }
+}
+
+/// Applies custom headers on top of the built-in ones. The first custom
+/// header with a built-in's name replaces it in place; further ones with the
+/// same name, and names that are not built in, are appended.
+pub fn apply_custom(headers: &mut HeaderMap, custom: &[CustomHeader]) {
+    let built_in = headers.clone();
+    let mut overridden: Vec<&str> = Vec::new();
+    for header in custom {
+        match header {
+            CustomHeader::Remove(name) => {
+                headers.remove(name);
+            }
+            CustomHeader::Set(name, value) => {
+                let replaces = built_in.contains(name)
+                    && !overridden.iter().any(|n| n.eq_ignore_ascii_case(name));
+                if replaces {
+                    headers.insert(name.as_str(), value.as_str());
+                    overridden.push(name);
+                } else {
+                    headers.append(name.as_str(), value.as_str());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lookup_is_case_insensitive() {
+        let mut map = HeaderMap::new();
+        map.append("Content-Type", "text/html");
+        assert_eq!(map.get("content-type"), Some("text/html"));
+        assert_eq!(map.get("CONTENT-TYPE"), Some("text/html"));
+        assert!(map.contains("Content-type"));
+        assert_eq!(map.get("Content-Length"), None);
+    }
+
+    #[test]
+    fn append_keeps_duplicates_in_order() {
+        let mut map = HeaderMap::new();
+        map.append("Set-Cookie", "a=1");
+        map.append("X-Other", "x");
+        map.append("set-cookie", "b=2");
+        assert_eq!(
+            map.get_all("Set-Cookie").collect::<Vec<_>>(),
+            ["a=1", "b=2"]
+        );
+        assert_eq!(map.len(), 3);
+    }
+
+    #[test]
+    fn insert_replaces_all_and_keeps_position() {
+        let mut map: HeaderMap = [("A", "1"), ("B", "2"), ("a", "3"), ("C", "4")]
+            .into_iter()
+            .collect();
+        map.insert("A", "new");
+        assert_eq!(
+            map.iter().collect::<Vec<_>>(),
+            [("A", "new"), ("B", "2"), ("C", "4")]
+        );
+    }
+
+    #[test]
+    fn redacts_credentials() {
+        assert_eq!(redact("authorization", "Bearer abc.def"), "Bearer ***");
+        assert_eq!(redact("Proxy-Authorization", "Basic eDp5"), "Basic ***");
+        assert_eq!(redact("Authorization", "token"), "***");
+        assert_eq!(redact("Accept", "*/*"), "*/*");
+    }
+
+    #[test]
+    fn remove_drops_every_instance() {
+        let mut map: HeaderMap = [("A", "1"), ("B", "2"), ("a", "3")].into_iter().collect();
+        assert!(map.remove("A"));
+        assert!(!map.remove("A"));
+        assert_eq!(map.iter().collect::<Vec<_>>(), [("B", "2")]);
+    }
+
+    #[test]
+    fn parses_custom_header_syntax() {
+        assert_eq!(
+            CustomHeader::parse("Accept: text/plain").unwrap(),
+            CustomHeader::Set("Accept".into(), "text/plain".into())
+        );
+        assert_eq!(
+            CustomHeader::parse("Accept:").unwrap(),
+            CustomHeader::Remove("Accept".into())
+        );
+        assert_eq!(
+            CustomHeader::parse("X-Empty;").unwrap(),
+            CustomHeader::Set("X-Empty".into(), String::new())
+        );
+        assert_eq!(
+            CustomHeader::parse("X-Semi: a;").unwrap(),
+            CustomHeader::Set("X-Semi".into(), "a;".into())
+        );
+        assert!(CustomHeader::parse("no-colon").is_err());
+        assert!(CustomHeader::parse(": value").is_err());
+        assert!(CustomHeader::parse("X Y: value").is_err());
+        assert!(CustomHeader::parse("X: a\r\nInjected: 1").is_err());
+    }
+
+    #[test]
+    fn custom_headers_override_and_remove_built_ins() {
+        let mut headers: HeaderMap = [("Host", "h"), ("User-Agent", "ua"), ("Accept", "*/*")]
+            .into_iter()
+            .collect();
+        let custom = [
+            CustomHeader::parse("user-agent: mine").unwrap(),
+            CustomHeader::parse("Accept:").unwrap(),
+            CustomHeader::parse("X-Empty;").unwrap(),
+            CustomHeader::parse("X-Twice: 1").unwrap(),
+            CustomHeader::parse("X-Twice: 2").unwrap(),
+        ];
+        apply_custom(&mut headers, &custom);
+        assert_eq!(
+            headers.iter().collect::<Vec<_>>(),
+            [
+                ("Host", "h"),
+                ("user-agent", "mine"),
+                ("X-Empty", ""),
+                ("X-Twice", "1"),
+                ("X-Twice", "2"),
+            ]
+        );
+    }
+
+    #[test]
+    fn displays_as_header_lines() {
+        let map: HeaderMap = [("A", "1"), ("B", "")].into_iter().collect();
+        assert_eq!(map.to_string(), "A: 1\r\nB:\r\n");
+    }
+}
@@ -0,0 +1,193 @@
+//! `--help`, whole or narrowed to one of [`TOPICS`].
+//!
+//! The option list is laid out from [`OPTIONS`]: each option's names as
+//! the table spells them, then its description in the current language,
+//! wrapped to fit. A topic's help is the entries of the options filed
+//! under it, so the table is the only list of options there is.
+
+use http_client::error::{Error, Result};
+
+use crate::cli::{OptSpec, OPTIONS, TOPICS};
+use crate::i18n::{self, lang, render as msg, Lang, Msg};
+
+/// How wide the help is laid out.
+const WIDTH: usize = 78;
+/// The column descriptions start at.
+const INDENT: usize = 16;
+
+/// The full help: the usage line, every option, then the notes.
+pub fn usage(lang: Lang) -> String {
+    let mut out = format!(
+        "{}\n\n{}\n",
+        msg(lang, Msg::Usage),
+        msg(lang, Msg::HelpTopic(None))
+    );
+    for spec in OPTIONS {
+        out.push_str(&entry(lang, spec));
+    }
+    out.push('\n');
+    out.push_str(&msg(lang, Msg::UsageNotes));
+    out
+}
+
+/// The help for `topic`, or all of it; `all` is the same as none.
+pub fn render(topic: Option<&str>) -> Result<String> {
+    let lang = lang();
+    let topic = match topic {
+        None | Some("all") => return Ok(format!("{}\n", usage(lang))),
+        Some(topic) if TOPICS.contains(&topic) => topic,
+        Some(other) => {
+            return Err(Error::Usage(msg(
+                lang,
+                Msg::UnsupportedChoice("--help", other, TOPICS),
+            )))
+        }
+    };
+    let mut out = format!(
+        "{}\n\n{}\n",
+        msg(lang, Msg::Usage),
+        msg(lang, Msg::HelpTopic(Some(topic)))
+    );
+    for spec in OPTIONS.iter().filter(|spec| spec.topic == topic) {
+        out.push_str(&entry(lang, spec));
+    }
+    Ok(out)
+}
+
+/// The lines documenting `spec`: its names, and its description from
+/// [`INDENT`] on, starting beside the names if they leave room.
+fn entry(lang: Lang, spec: &OptSpec) -> String {
+    let mut names = match spec.short {
+        Some(short) => format!("-{}, --{}", short, spec.long),
+        None => format!("--{}", spec.long),
+    };
+    if let Some(value) = spec.value {
+        names.push_str(&format!(" <{}>", value));
+    }
+    // The topic is an argument, not the option's value.
+    if spec.long == "help" {
+        names.push_str(" [topic]");
+    }
+    let description = wrap(&msg(lang, Msg::OptionHelp(spec.long)), WIDTH - INDENT);
+    let mut lines = description.iter();
+    let mut out = format!("  {}", names);
+    if names.len() + 3 <= INDENT {
+        if let Some(first) = lines.next() {
+            out = format!("  {:<width$} {}", names, first, width = INDENT - 3);
+        }
+    }
+    out.push('\n');
+    for line in lines {
+        out.push_str(&format!("{:INDENT$}{}\n", "", line));
+    }
+    out
+}
+
+/// `text` in lines at most `width` columns wide. Its own line breaks and
+/// indentation are kept. Lines break at spaces, and in Chinese between
+/// any two characters, but not before closing punctuation.
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        let mut current = " ".repeat(indent);
+        for (spaced, word) in pieces(line.trim_start_matches(' ')) {
+            let start = current.trim_start().is_empty();
+            let joined = if spaced && !start { 1 } else { 0 };
+            if !start && i18n::width(&current) + joined + i18n::width(word) > width {
+                lines.push(current);
+                current = " ".repeat(indent);
+            } else if joined == 1 {
+                current.push(' ');
+            }
+            current.push_str(word);
+        }
+        lines.push(current);
+    }
+    lines
+}
+
+/// Marks a line must not start with.
+const CLOSING: &str = "、。,;:!?)";
+
+/// The pieces of `line` a break may come between, each with whether a
+/// space came before it. Chinese text, which has no spaces, may break
+/// between any two characters and around its punctuation.
+fn pieces(line: &str) -> Vec<(bool, &str)> {
+    let mut pieces = Vec::new();
+    for word in line.split(' ').filter(|word| !word.is_empty()) {
+        let mut start = 0;
+        let mut previous: Option<char> = None;
+        for (i, c) in word.char_indices() {
+            let breaks = previous.is_some_and(|p| {
+                let into = !c.is_ascii() || (c == '(' && !p.is_ascii());
+                let out_of = !p.is_ascii() || ",;:)".contains(p);
+                ((out_of && into) || "、。".contains(p)) && p != '(' && !CLOSING.contains(c)
+            });
+            if breaks {
+                pieces.push((start == 0, &word[start..i]));
+                start = i;
+            }
+            previous = Some(c);
+        }
+        pieces.push((start == 0, &word[start..]));
+    }
+    pieces
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn every_option_has_a_topic_and_an_entry() {
+        for spec in OPTIONS {
+            assert!(TOPICS.contains(&spec.topic), "--{} has no topic", spec.long);
+            for lang in [Lang::En, Lang::Zh] {
+                let entry = entry(lang, spec);
+                assert!(entry.starts_with("  -"), "{}", entry);
+                assert!(entry.contains(&format!("--{}", spec.long)), "{}", entry);
+                for line in entry.lines() {
+                    assert!(
+                        i18n::width(line) <= WIDTH || !line.contains(' '),
+                        "too wide: {}",
+                        line
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lays_out_names_and_descriptions() {
+        let spec = OPTIONS
+            .iter()
+            .find(|spec| spec.long == "user-agent")
+            .unwrap();
+        assert_eq!(
+            entry(Lang::En, spec),
+            "  -A, --user-agent <name>\n                User-Agent to send (an empty name sends none)\n"
+        );
+        let spec = OPTIONS.iter().find(|spec| spec.long == "netrc").unwrap();
+        assert!(entry(Lang::En, spec).starts_with("  -n, --netrc   Send Basic auth "));
+        assert_eq!(wrap("aaa bbb ccc\n  x", 7), ["aaa bbb", "ccc", "  x"]);
+        // Chinese breaks between characters, not before punctuation.
+        assert_eq!(wrap("一二三,四五", 6), ["一二", "三,四", "五"]);
+        assert_eq!(wrap("用 HTTP/1.1 发送", 10), ["用", "HTTP/1.1", "发送"]);
+    }
+
+    #[test]
+    fn narrows_help_to_a_topic() {
+        let help = render(Some("proxy")).unwrap();
+        assert!(help.starts_with("Usage: "));
+        assert!(help.contains("\n  -x, --proxy <"));
+        assert!(help.contains("\n  --proxy-digest\n"));
+        assert!(!help.contains("--location"));
+        assert!(!help.contains("Options may be repeated"));
+
+        let help = render(Some("connection")).unwrap();
+        assert!(help.contains("\n  --sndbuf <bytes>\n"));
+        assert!(help.contains("\n  --rcvbuf <bytes>\n"));
+        assert_eq!(render(Some("nope")).unwrap_err().exit_code(), 2);
+    }
+}
@@ -0,0 +1,124 @@
+//! The redirects a transfer followed, for `-v`, `--show-redirects` and
+//! `-w`.
+
+use std::time::Duration;
+
+use http_client::metrics::Metrics;
+use http_client::response::Response;
+use http_client::url::Url;
+
+use crate::i18n::{tr, Msg};
+
+/// One redirect: the URL that answered with it, and when it did.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hop {
+    /// Without userinfo, as every URL here is for display.
+    pub url: String,
+    pub status: u16,
+    /// The resolved `Location`.
+    pub location: String,
+    /// Since the transfer started.
+    pub at: Duration,
+}
+
+impl Hop {
+    pub fn new(url: &Url, status: u16, location: &Url, at: Duration) -> Hop {
+        Hop {
+            url: url.without_userinfo().to_string(),
+            status,
+            location: location.without_userinfo().to_string(),
+            at,
+        }
+    }
+}
+
+/// Everything about a transfer besides its response.
+pub struct Transfer {
+    /// The last URL requested.
+    pub url: Url,
+    pub hops: Vec<Hop>,
+    pub metrics: Metrics,
+    /// When the last response was read.
+    pub total: Duration,
+    /// Request body bytes.
+    pub upload: usize,
+    /// How many times `--retry` sent the transfer again.
+    pub retries: u32,
+    /// The size of a body saved straight to a file, which the response
+    /// then does not hold.
+    pub saved: Option<u64>,
+}
+
+/// Milliseconds, the resolution a person reading a summary cares about.
+fn ms(duration: Duration) -> String {
+    format!("{} ms", duration.as_millis())
+}
+
+impl Transfer {
+    /// The size of the final response's body, wherever it went.
+    pub fn body_size(&self, response: &Response) -> u64 {
+        self.saved.unwrap_or(response.body.len() as u64)
+    }
+
+    /// The `--show-redirects` lines: each hop with its status and how long
+    /// it took, then where the transfer ended up. `status` is the final
+    /// response's, if there was one.
+    pub fn summary(&self, status: Option<u16>) -> String {
+        let mut out = format!("* {}\n", tr(Msg::RedirectHistory(self.hops.len())));
+        let mut previous = Duration::ZERO;
+        for (i, hop) in self.hops.iter().enumerate() {
+            out.push_str(&format!(
+                "*   {}. {} {} ({})\n",
+                i + 1,
+                hop.status,
+                hop.url,
+                ms(hop.at.saturating_sub(previous))
+            ));
+            previous = hop.at;
+        }
+        let status = status.map_or("---".to_string(), |code| code.to_string());
+        out.push_str(&format!(
+            "*   => {} {} ({})\n",
+            status,
+            self.url.without_userinfo(),
+            ms(self.total.saturating_sub(previous))
+        ));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn summarizes_each_hop() {
+        let url = |s| Url::parse(s).unwrap();
+        let (a, b, c) = (
+            url("http://u:p@a.test/"),
+            url("http://b.test/x"),
+            url("http://c.test/"),
+        );
+        let ms = Duration::from_millis;
+        let transfer = Transfer {
+            url: c.clone(),
+            hops: vec![Hop::new(&a, 301, &b, ms(10)), Hop::new(&b, 302, &c, ms(25))],
+            metrics: Metrics::new(),
+            total: ms(40),
+            upload: 0,
+            retries: 0,
+            saved: None,
+        };
+        assert_eq!(transfer.hops[0].url, "http://a.test/");
+        let summary = transfer.summary(Some(200));
+        let lines: Vec<&str> = summary.lines().skip(1).collect();
+        assert_eq!(
+            lines,
+            [
+                "*   1. 301 http://a.test/ (10 ms)",
+                "*   2. 302 http://b.test/x (15 ms)",
+                "*   => 200 http://c.test/ (15 ms)",
+            ]
+        );
+    }
+}
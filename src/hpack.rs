@@ -0,0 +1,653 @@
+//! HPACK header compression for HTTP/2 (RFC 7541).
+//!
+//! The [`Decoder`] understands every representation a server may send,
+//! including Huffman-coded strings and dynamic table updates. The
+//! [`Encoder`] only emits literals without indexing, which every decoder
+//! accepts and which keeps no state to get out of sync.
+
+use crate::error::{Error, Result};
+
+/// The static table of RFC 7541 Appendix A; index 1 is the first entry.
+const STATIC_TABLE: [(&str, &str); 61] = [
+    (":authority", ""),
+    (":method", "GET"),
+    (":method", "POST"),
+    (":path", "/"),
+    (":path", "/index.html"),
+    (":scheme", "http"),
+    (":scheme", "https"),
+    (":status", "200"),
+    (":status", "204"),
+    (":status", "206"),
+    (":status", "304"),
+    (":status", "400"),
+    (":status", "404"),
+    (":status", "500"),
+    ("accept-charset", ""),
+    ("accept-encoding", "gzip, deflate"),
+    ("accept-language", ""),
+    ("accept-ranges", ""),
+    ("accept", ""),
+    ("access-control-allow-origin", ""),
+    ("age", ""),
+    ("allow", ""),
+    ("authorization", ""),
+    ("cache-control", ""),
+    ("content-disposition", ""),
+    ("content-encoding", ""),
+    ("content-language", ""),
+    ("content-length", ""),
+    ("content-location", ""),
+    ("content-range", ""),
+    ("content-type", ""),
+    ("cookie", ""),
+    ("date", ""),
+    ("etag", ""),
+    ("expect", ""),
+    ("expires", ""),
+    ("from", ""),
+    ("host", ""),
+    ("if-match", ""),
+    ("if-modified-since", ""),
+    ("if-none-match", ""),
+    ("if-range", ""),
+    ("if-unmodified-since", ""),
+    ("last-modified", ""),
+    ("link", ""),
+    ("location", ""),
+    ("max-forwards", ""),
+    ("proxy-authenticate", ""),
+    ("proxy-authorization", ""),
+    ("range", ""),
+    ("referer", ""),
+    ("refresh", ""),
+    ("retry-after", ""),
+    ("server", ""),
+    ("set-cookie", ""),
+    ("strict-transport-security", ""),
+    ("transfer-encoding", ""),
+    ("user-agent", ""),
+    ("vary", ""),
+    ("via", ""),
+    ("www-authenticate", ""),
+];
+
+/// The Huffman code (right-aligned) and its bit length for every octet, and
+/// for EOS at index 256 (RFC 7541 Appendix B).
+const HUFFMAN: [(u32, u8); 257] = [
+    (0x1ff8, 13),
+    (0x7fffd8, 23),
+    (0xfffffe2, 28),
+    (0xfffffe3, 28),
+    (0xfffffe4, 28),
+    (0xfffffe5, 28),
+    (0xfffffe6, 28),
+    (0xfffffe7, 28),
+    (0xfffffe8, 28),
+    (0xffffea, 24),
+    (0x3ffffffc, 30),
+    (0xfffffe9, 28),
+    (0xfffffea, 28),
+    (0x3ffffffd, 30),
+    (0xfffffeb, 28),
+    (0xfffffec, 28),
+    (0xfffffed, 28),
+    (0xfffffee, 28),
+    (0xfffffef, 28),
+    (0xffffff0, 28),
+    (0xffffff1, 28),
+    (0xffffff2, 28),
+    (0x3ffffffe, 30),
+    (0xffffff3, 28),
+    (0xffffff4, 28),
+    (0xffffff5, 28),
+    (0xffffff6, 28),
+    (0xffffff7, 28),
+    (0xffffff8, 28),
+    (0xffffff9, 28),
+    (0xffffffa, 28),
+    (0xffffffb, 28),
+    (0x14, 6),
+    (0x3f8, 10),
+    (0x3f9, 10),
+    (0xffa, 12),
+    (0x1ff9, 13),
+    (0x15, 6),
+    (0xf8, 8),
+    (0x7fa, 11),
+    (0x3fa, 10),
+    (0x3fb, 10),
+    (0xf9, 8),
+    (0x7fb, 11),
+    (0xfa, 8),
+    (0x16, 6),
+    (0x17, 6),
+    (0x18, 6),
+    (0x0, 5),
+    (0x1, 5),
+    (0x2, 5),
+    (0x19, 6),
+    (0x1a, 6),
+    (0x1b, 6),
+    (0x1c, 6),
+    (0x1d, 6),
+    (0x1e, 6),
+    (0x1f, 6),
+    (0x5c, 7),
+    (0xfb, 8),
+    (0x7ffc, 15),
+    (0x20, 6),
+    (0xffb, 12),
+    (0x3fc, 10),
+    (0x1ffa, 13),
+    (0x21, 6),
+    (0x5d, 7),
+    (0x5e, 7),
+    (0x5f, 7),
+    (0x60, 7),
+    (0x61, 7),
+    (0x62, 7),
+    (0x63, 7),
+    (0x64, 7),
+    (0x65, 7),
+    (0x66, 7),
+    (0x67, 7),
+    (0x68, 7),
+    (0x69, 7),
+    (0x6a, 7),
+    (0x6b, 7),
+    (0x6c, 7),
+    (0x6d, 7),
+    (0x6e, 7),
+    (0x6f, 7),
+    (0x70, 7),
+    (0x71, 7),
+    (0x72, 7),
+    (0xfc, 8),
+    (0x73, 7),
+    (0xfd, 8),
+    (0x1ffb, 13),
+    (0x7fff0, 19),
+    (0x1ffc, 13),
+    (0x3ffc, 14),
+    (0x22, 6),
+    (0x7ffd, 15),
+    (0x3, 5),
+    (0x23, 6),
+    (0x4, 5),
+    (0x24, 6),
+    (0x5, 5),
+    (0x25, 6),
+    (0x26, 6),
+    (0x27, 6),
+    (0x6, 5),
+    (0x74, 7),
+    (0x75, 7),
+    (0x28, 6),
+    (0x29, 6),
+    (0x2a, 6),
+    (0x7, 5),
+    (0x2b, 6),
+    (0x76, 7),
+    (0x2c, 6),
+    (0x8, 5),
+    (0x9, 5),
+    (0x2d, 6),
+    (0x77, 7),
+    (0x78, 7),
+    (0x79, 7),
+    (0x7a, 7),
+    (0x7b, 7),
+    (0x7ffe, 15),
+    (0x7fc, 11),
+    (0x3ffd, 14),
+    (0x1ffd, 13),
+    (0xffffffc, 28),
+    (0xfffe6, 20),
+    (0x3fffd2, 22),
+    (0xfffe7, 20),
+    (0xfffe8, 20),
+    (0x3fffd3, 22),
+    (0x3fffd4, 22),
+    (0x3fffd5, 22),
+    (0x7fffd9, 23),
+    (0x3fffd6, 22),
+    (0x7fffda, 23),
+    (0x7fffdb, 23),
+    (0x7fffdc, 23),
+    (0x7fffdd, 23),
+    (0x7fffde, 23),
+    (0xffffeb, 24),
+    (0x7fffdf, 23),
+    (0xffffec, 24),
+    (0xffffed, 24),
+    (0x3fffd7, 22),
+    (0x7fffe0, 23),
+    (0xffffee, 24),
+    (0x7fffe1, 23),
+    (0x7fffe2, 23),
+    (0x7fffe3, 23),
+    (0x7fffe4, 23),
+    (0x1fffdc, 21),
+    (0x3fffd8, 22),
+    (0x7fffe5, 23),
+    (0x3fffd9, 22),
+    (0x7fffe6, 23),
+    (0x7fffe7, 23),
+    (0xffffef, 24),
+    (0x3fffda, 22),
+    (0x1fffdd, 21),
+    (0xfffe9, 20),
+    (0x3fffdb, 22),
+    (0x3fffdc, 22),
+    (0x7fffe8, 23),
+    (0x7fffe9, 23),
+    (0x1fffde, 21),
+    (0x7fffea, 23),
+    (0x3fffdd, 22),
+    (0x3fffde, 22),
+    (0xfffff0, 24),
+    (0x1fffdf, 21),
+    (0x3fffdf, 22),
+    (0x7fffeb, 23),
+    (0x7fffec, 23),
+    (0x1fffe0, 21),
+    (0x1fffe1, 21),
+    (0x3fffe0, 22),
+    (0x1fffe2, 21),
+    (0x7fffed, 23),
+    (0x3fffe1, 22),
+    (0x7fffee, 23),
+    (0x7fffef, 23),
+    (0xfffea, 20),
+    (0x3fffe2, 22),
+    (0x3fffe3, 22),
+    (0x3fffe4, 22),
+    (0x7ffff0, 23),
+    (0x3fffe5, 22),
+    (0x3fffe6, 22),
+    (0x7ffff1, 23),
+    (0x3ffffe0, 26),
+    (0x3ffffe1, 26),
+    (0xfffeb, 20),
+    (0x7fff1, 19),
+    (0x3fffe7, 22),
+    (0x7ffff2, 23),
+    (0x3fffe8, 22),
+    (0x1ffffec, 25),
+    (0x3ffffe2, 26),
+    (0x3ffffe3, 26),
+    (0x3ffffe4, 26),
+    (0x7ffffde, 27),
+    (0x7ffffdf, 27),
+    (0x3ffffe5, 26),
+    (0xfffff1, 24),
+    (0x1ffffed, 25),
+    (0x7fff2, 19),
+    (0x1fffe3, 21),
+    (0x3ffffe6, 26),
+    (0x7ffffe0, 27),
+    (0x7ffffe1, 27),
+    (0x3ffffe7, 26),
+    (0x7ffffe2, 27),
+    (0xfffff2, 24),
+    (0x1fffe4, 21),
+    (0x1fffe5, 21),
+    (0x3ffffe8, 26),
+    (0x3ffffe9, 26),
+    (0xffffffd, 28),
+    (0x7ffffe3, 27),
+    (0x7ffffe4, 27),
+    (0x7ffffe5, 27),
+    (0xfffec, 20),
+    (0xfffff3, 24),
+    (0xfffed, 20),
+    (0x1fffe6, 21),
+    (0x3fffe9, 22),
+    (0x1fffe7, 21),
+    (0x1fffe8, 21),
+    (0x7ffff3, 23),
+    (0x3fffea, 22),
+    (0x3fffeb, 22),
+    (0x1ffffee, 25),
+    (0x1ffffef, 25),
+    (0xfffff4, 24),
+    (0xfffff5, 24),
+    (0x3ffffea, 26),
+    (0x7ffff4, 23),
+    (0x3ffffeb, 26),
+    (0x7ffffe6, 27),
+    (0x3ffffec, 26),
+    (0x3ffffed, 26),
+    (0x7ffffe7, 27),
+    (0x7ffffe8, 27),
+    (0x7ffffe9, 27),
+    (0x7ffffea, 27),
+    (0x7ffffeb, 27),
+    (0xffffffe, 28),
+    (0x7ffffec, 27),
+    (0x7ffffed, 27),
+    (0x7ffffee, 27),
+    (0x7ffffef, 27),
+    (0x7fffff0, 27),
+    (0x3ffffee, 26),
+    (0x3fffffff, 30),
+];
+
+/// The dynamic table size both sides start with.
+pub const DEFAULT_TABLE_SIZE: usize = 4096;
+
+fn compression_error(msg: &str) -> Error {
+    Error::HttpProtocol(format!("HPACK: {}", msg))
+}
+
+/// Appends `value` as an integer with an `n`-bit prefix; `flags` fills the
+/// bits above the prefix in the first octet.
+fn encode_int(out: &mut Vec<u8>, value: usize, n: u32, flags: u8) {
+    let max = (1usize << n) - 1;
+    if value < max {
+        out.push(flags | value as u8);
+        return;
+    }
+    out.push(flags | max as u8);
+    let mut rest = value - max;
+    while rest >= 128 {
+        out.push((rest % 128) as u8 | 0x80);
+        rest /= 128;
+    }
+    out.push(rest as u8);
+}
+
+/// A cursor over a header block.
+struct Input<'a> {
+    data: &'a [u8],
+}
+
+impl Input<'_> {
+    fn peek(&self) -> Result<u8> {
+        self.data
+            .first()
+            .copied()
+            .ok_or_else(|| compression_error("truncated header block"))
+    }
+
+    fn take(&mut self, n: usize) -> Result<&[u8]> {
+        if n > self.data.len() {
+            return Err(compression_error("truncated header block"));
+        }
+        let (head, rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(head)
+    }
+
+    fn int(&mut self, n: u32) -> Result<usize> {
+        let max = (1usize << n) - 1;
+        let mut value = self.take(1)?[0] as usize & max;
+        if value < max {
+            return Ok(value);
+        }
+        let mut shift = 0;
+        loop {
+            let b = self.take(1)?[0];
+            let part = ((b & 0x7f) as usize)
+                .checked_shl(shift)
+                .filter(|_| shift < 28)
+                .ok_or_else(|| compression_error("integer overflow"))?;
+            value += part;
+            shift += 7;
+            if b & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let huffman = self.peek()? & 0x80 != 0;
+        let len = self.int(7)?;
+        let raw = self.take(len)?;
+        let bytes = if huffman {
+            huffman_decode(raw)?
+        } else {
+            raw.to_vec()
+        };
+        String::from_utf8(bytes).map_err(|_| compression_error("header is not valid UTF-8"))
+    }
+}
+
+/// Decodes a Huffman-coded string. Padding must be a prefix of EOS and
+/// shorter than a byte.
+pub fn huffman_decode(input: &[u8]) -> Result<Vec<u8>> {
+    let mut out = Vec::with_capacity(input.len() * 8 / 5);
+    let mut code: u32 = 0;
+    let mut len: u8 = 0;
+    for &byte in input {
+        for bit in (0..8).rev() {
+            code = code << 1 | (byte >> bit & 1) as u32;
+            len += 1;
+            if len < 5 {
+                continue;
+            }
+            if let Some(symbol) = HUFFMAN.iter().position(|&(c, l)| l == len && c == code) {
+                if symbol == 256 {
+                    return Err(compression_error("EOS in Huffman string"));
+                }
+                out.push(symbol as u8);
+                code = 0;
+                len = 0;
+            } else if len >= 30 {
+                return Err(compression_error("invalid Huffman code"));
+            }
+        }
+    }
+    if len > 7 || code != (1 << len) - 1 {
+        return Err(compression_error("invalid Huffman padding"));
+    }
+    Ok(out)
+}
+
+/// Decoding state for one connection: the dynamic table.
+#[derive(Debug)]
+pub struct Decoder {
+    /// Newest entry first, as the indices count.
+    dynamic: Vec<(String, String)>,
+    size: usize,
+    max_size: usize,
+}
+
+impl Default for Decoder {
+    fn default() -> Decoder {
+        Decoder {
+            dynamic: Vec::new(),
+            size: 0,
+            max_size: DEFAULT_TABLE_SIZE,
+        }
+    }
+}
+
+impl Decoder {
+    pub fn new() -> Decoder {
+        Decoder::default()
+    }
+
+    fn entry(&self, index: usize) -> Result<(String, String)> {
+        let entry = match index {
+            0 => None,
+            1..=61 => {
+                let (name, value) = STATIC_TABLE[index - 1];
+                Some((name.to_string(), value.to_string()))
+            }
+            _ => self.dynamic.get(index - 62).cloned(),
+        };
+        entry.ok_or_else(|| compression_error("header index out of range"))
+    }
+
+    fn insert(&mut self, name: String, value: String) {
+        self.size += name.len() + value.len() + 32;
+        self.dynamic.insert(0, (name, value));
+        self.evict();
+    }
+
+    fn evict(&mut self) {
+        while self.size > self.max_size {
+            match self.dynamic.pop() {
+                Some((name, value)) => self.size -= name.len() + value.len() + 32,
+                None => break,
+            }
+        }
+    }
+
+    /// Decodes a complete header block into `(name, value)` pairs.
+    pub fn decode(&mut self, block: &[u8]) -> Result<Vec<(String, String)>> {
+        let mut input = Input { data: block };
+        let mut fields = Vec::new();
+        while !input.data.is_empty() {
+            let first = input.peek()?;
+            if first & 0x80 != 0 {
+                fields.push(self.entry(input.int(7)?)?);
+            } else if first & 0xc0 == 0x40 {
+                let field = self.literal(&mut input, 6)?;
+                self.insert(field.0.clone(), field.1.clone());
+                fields.push(field);
+            } else if first & 0xe0 == 0x20 {
+                let size = input.int(5)?;
+                if size > DEFAULT_TABLE_SIZE {
+                    return Err(compression_error("table size update too large"));
+                }
+                self.max_size = size;
+                self.evict();
+            } else {
+                // Without indexing (0000) or never indexed (0001).
+                fields.push(self.literal(&mut input, 4)?);
+            }
+        }
+        Ok(fields)
+    }
+
+    fn literal(&self, input: &mut Input, prefix: u32) -> Result<(String, String)> {
+        let name = match input.int(prefix)? {
+            0 => input.string()?,
+            index => self.entry(index)?.0,
+        };
+        Ok((name, input.string()?))
+    }
+}
+
+/// Stateless encoding of header fields as literals without indexing.
+#[derive(Debug, Default)]
+pub struct Encoder;
+
+impl Encoder {
+    /// Encodes `fields` into one header block. Names must already be
+    /// lowercase, as HTTP/2 requires.
+    pub fn encode<'a>(&self, fields: impl IntoIterator<Item = (&'a str, &'a str)>) -> Vec<u8> {
+        let mut out = Vec::new();
+        for (name, value) in fields {
+            match STATIC_TABLE.iter().position(|&(n, _)| n == name) {
+                Some(index) => encode_int(&mut out, index + 1, 4, 0),
+                None => {
+                    out.push(0);
+                    encode_int(&mut out, name.len(), 7, 0);
+                    out.extend_from_slice(name.as_bytes());
+                }
+            }
+            encode_int(&mut out, value.len(), 7, 0);
+            out.extend_from_slice(value.as_bytes());
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unhex(text: &str) -> Vec<u8> {
+        let digits: Vec<u8> = text.bytes().filter(u8::is_ascii_hexdigit).collect();
+        digits
+            .chunks(2)
+            .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap(), 16).unwrap())
+            .collect()
+    }
+
+    fn pairs(fields: &[(String, String)]) -> Vec<(&str, &str)> {
+        fields
+            .iter()
+            .map(|(n, v)| (n.as_str(), v.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn integers_use_prefix_and_continuation() {
+        let mut out = Vec::new();
+        encode_int(&mut out, 10, 5, 0);
+        encode_int(&mut out, 1337, 5, 0);
+        assert_eq!(out, [10, 31, 154, 10]);
+        let mut input = Input { data: &out };
+        assert_eq!(input.int(5).unwrap(), 10);
+        assert_eq!(input.int(5).unwrap(), 1337);
+    }
+
+    #[test]
+    fn decodes_rfc_request_examples() {
+        // RFC 7541 C.3.1 (plain) and C.4.1 (Huffman).
+        let expected = [
+            (":method", "GET"),
+            (":scheme", "http"),
+            (":path", "/"),
+            (":authority", "www.example.com"),
+        ];
+        let plain = unhex("8286 8441 0f77 7777 2e65 7861 6d70 6c65 2e63 6f6d");
+        let huffman = unhex("8286 8441 8cf1 e3c2 e5f2 3a6b a0ab 90f4 ff");
+        for block in [plain, huffman] {
+            let mut decoder = Decoder::new();
+            assert_eq!(pairs(&decoder.decode(&block).unwrap()), expected);
+            assert_eq!(decoder.dynamic.len(), 1);
+            assert_eq!(decoder.size, 57);
+        }
+    }
+
+    #[test]
+    fn dynamic_entries_are_reused_and_evicted() {
+        // RFC 7541 C.5.1 then C.5.2, with a 256-byte table.
+        let mut decoder = Decoder::new();
+        decoder.max_size = 256;
+        let first = unhex(
+            "4803 3330 3258 0770 7269 7661 7465 611d 4d6f 6e2c 2032 3120 4f63 7420 3230 3133
+             2032 303a 3133 3a32 3120 474d 546e 1768 7474 7073 3a2f 2f77 7777 2e65 7861 6d70
+             6c65 2e63 6f6d",
+        );
+        let fields = decoder.decode(&first).unwrap();
+        assert_eq!(fields[0], (":status".into(), "302".into()));
+        assert_eq!(decoder.size, 222);
+        let second = unhex("4803 3330 37c1 c0bf");
+        let fields = decoder.decode(&second).unwrap();
+        assert_eq!(
+            pairs(&fields),
+            [
+                (":status", "307"),
+                ("cache-control", "private"),
+                ("date", "Mon, 21 Oct 2013 20:13:21 GMT"),
+                ("location", "https://www.example.com"),
+            ]
+        );
+        assert_eq!(decoder.dynamic.len(), 4);
+        assert_eq!(decoder.size, 222);
+    }
+
+    #[test]
+    fn rejects_bad_huffman_padding() {
+        assert!(huffman_decode(&[0x00]).is_err());
+        assert!(huffman_decode(&[0xff, 0xff, 0xff, 0xff]).is_err());
+    }
+
+    #[test]
+    fn encoded_blocks_decode_back() {
+        let fields = [
+            (":method", "GET"),
+            (":path", "/a?b=c"),
+            ("x-custom", "value"),
+        ];
+        let block = Encoder.encode(fields);
+        assert_eq!(pairs(&Decoder::new().decode(&block).unwrap()), fields);
+    }
+}
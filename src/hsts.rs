@@ -0,0 +1,193 @@
+//! HTTP Strict Transport Security (RFC 6797) for `--hsts`.
+//!
+//! A [`Store`] remembers the hosts that sent `Strict-Transport-Security`
+//! over HTTPS, and [`Store::must_upgrade`] says whether a plain `http://`
+//! request to a host has to become `https://`. The file format is curl's:
+//! one `host "YYYYMMDD HH:MM:SS"` line per host, with a leading dot for
+//! `includeSubDomains` and `unlimited` for entries that never expire.
+
+use crate::date;
+
+/// One known HSTS host.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Lowercase, without a leading dot.
+    pub host: String,
+    pub include_subdomains: bool,
+    /// Unix seconds; `None` for never.
+    pub expires: Option<u64>,
+}
+
+/// The known HSTS hosts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Store {
+    entries: Vec<Entry>,
+}
+
+/// Host names are compared without case and without a trailing dot.
+fn normalize(host: &str) -> String {
+    host.trim_end_matches('.').to_ascii_lowercase()
+}
+
+/// IP literals never get HSTS (RFC 6797 section 8.1.1).
+fn is_ip_literal(host: &str) -> bool {
+    host.starts_with('[') || host.parse::<std::net::Ipv4Addr>().is_ok()
+}
+
+impl Store {
+    pub fn new() -> Store {
+        Store::default()
+    }
+
+    /// Reads a store in curl's format. Comments, blank lines and lines that
+    /// do not parse are skipped.
+    pub fn load(text: &str) -> Store {
+        let mut store = Store::new();
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((host, expiry)) = line.split_once(char::is_whitespace) else {
+                continue;
+            };
+            let expiry = expiry.trim().trim_matches('"');
+            let expires = match expiry {
+                "unlimited" => None,
+                _ => match date::parse_compact(expiry) {
+                    Some(t) => Some(t),
+                    None => continue,
+                },
+            };
+            let include_subdomains = host.starts_with('.');
+            store.entries.push(Entry {
+                host: normalize(host.trim_start_matches('.')),
+                include_subdomains,
+                expires,
+            });
+        }
+        store
+    }
+
+    /// The store in curl's format, without entries expired by `now`.
+    pub fn to_text(&self, now: u64) -> String {
+        let mut text = String::from("# HSTS cache written by http_client\n");
+        for entry in self
+            .entries
+            .iter()
+            .filter(|e| e.expires.is_none_or(|t| t > now))
+        {
+            let expiry = entry
+                .expires
+                .map_or_else(|| "unlimited".to_string(), date::format_compact);
+            let dot = if entry.include_subdomains { "." } else { "" };
+            text.push_str(&format!("{}{} \"{}\"\n", dot, entry.host, expiry));
+        }
+        text
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// Whether plain HTTP to `host` has to be upgraded at `now`: the host
+    /// is known, or a parent domain is known with `includeSubDomains`.
+    pub fn must_upgrade(&self, host: &str, now: u64) -> bool {
+        let host = normalize(host);
+        if is_ip_literal(&host) {
+            return false;
+        }
+        self.entries.iter().any(|entry| {
+            let live = entry.expires.is_none_or(|t| t > now);
+            let matches = host == entry.host
+                || (entry.include_subdomains
+                    && host.len() > entry.host.len()
+                    && host.ends_with(&entry.host)
+                    && host.as_bytes()[host.len() - entry.host.len() - 1] == b'.');
+            live && matches
+        })
+    }
+
+    /// Records a `Strict-Transport-Security` value received from `host` at
+    /// `now`; `max-age=0` forgets the host. Only call this for responses
+    /// that came over HTTPS. Returns whether the store changed.
+    pub fn update(&mut self, host: &str, value: &str, now: u64) -> bool {
+        let host = normalize(host);
+        if is_ip_literal(&host) {
+            return false;
+        }
+        let mut max_age = None;
+        let mut include_subdomains = false;
+        for directive in value.split(';') {
+            let (name, arg) = directive.split_once('=').unwrap_or((directive, ""));
+            match name.trim().to_ascii_lowercase().as_str() {
+                "max-age" => max_age = arg.trim().trim_matches('"').parse::<u64>().ok(),
+                "includesubdomains" => include_subdomains = true,
+                _ => {}
+            }
+        }
+        // Without a valid max-age the header must be ignored.
+        let Some(max_age) = max_age else {
+            return false;
+        };
+        let before = self.entries.clone();
+        self.entries.retain(|e| e.host != host);
+        if max_age > 0 {
+            self.entries.push(Entry {
+                host,
+                include_subdomains,
+                expires: Some(now.saturating_add(max_age)),
+            });
+        }
+        self.entries != before
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upgrades_known_hosts_and_subdomains() {
+        let mut store = Store::new();
+        assert!(store.update("Example.com", "max-age=100; includeSubDomains", 1000));
+        assert!(store.update("only.test", "max-age=100", 1000));
+        assert!(!store.update("bad.test", "includeSubDomains", 1000));
+        assert!(!store.update("127.0.0.1", "max-age=100", 1000));
+
+        assert!(store.must_upgrade("example.com", 1099));
+        assert!(store.must_upgrade("api.EXAMPLE.com.", 1099));
+        assert!(!store.must_upgrade("notexample.com", 1099));
+        assert!(!store.must_upgrade("example.com", 1100));
+        assert!(store.must_upgrade("only.test", 1050));
+        assert!(!store.must_upgrade("www.only.test", 1050));
+
+        assert!(store.update("only.test", "max-age=0", 1050));
+        assert!(!store.must_upgrade("only.test", 1050));
+    }
+
+    #[test]
+    fn round_trips_curl_files() {
+        let text =
+            "# comment\n.example.com \"19941106 08:49:37\"\nforever.test unlimited\nbroken\n";
+        let store = Store::load(text);
+        assert_eq!(
+            store.entries(),
+            [
+                Entry {
+                    host: "example.com".into(),
+                    include_subdomains: true,
+                    expires: Some(784_111_777),
+                },
+                Entry {
+                    host: "forever.test".into(),
+                    include_subdomains: false,
+                    expires: None,
+                },
+            ]
+        );
+        let written = store.to_text(0);
+        assert!(written.contains(".example.com \"19941106 08:49:37\"\n"));
+        assert_eq!(Store::load(&written), store);
+        assert!(!store.to_text(784_111_777).contains("example.com"));
+    }
+}
@@ -0,0 +1,1293 @@
+//! Sending a single HTTP request over TCP, or over TLS for `https://`.
+//!
+//! HTTP/1.1 is the default; HTTP/2 over cleartext is in `crate::http2`
+//! when the `http2` feature is on.
+
+use std::io::{self, BufRead, BufReader, IoSlice, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::Arc;
+
+use crate::body::{self, Body, Framing};
+use crate::error::{Error, Result};
+use crate::headers::{self, HeaderMap};
+#[cfg(feature = "http2")]
+use crate::http2;
+use crate::metrics::Metrics;
+use crate::raw;
+use crate::response::{self, Response, StatusLine};
+use crate::socket::SocketOptions;
+use crate::speed::{self, RateLimit, SpeedCheck, SpeedLimit, Throttled};
+use crate::tls::{self, Connector, Stream};
+use crate::trace::{Trace, Traced};
+use crate::transport::{Tcp, Transport};
+use crate::url::Url;
+
+/// The HTTP version to speak, from `--http1.1`, `--http2` and
+/// `--http2-prior-knowledge`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum HttpVersion {
+    #[default]
+    Http11,
+    /// Ask for an upgrade to h2c and fall back to HTTP/1.1 if the server
+    /// declines. Requests with a body are sent as HTTP/1.1, since the
+    /// upgrade would have to carry the whole body first.
+    Http2,
+    /// Speak HTTP/2 from the first byte.
+    Http2PriorKnowledge,
+}
+
+/// The `User-Agent` sent unless overridden with `-A` or `-H`.
+pub const DEFAULT_USER_AGENT: &str = concat!("http_client/", env!("CARGO_PKG_VERSION"));
+
+#[cfg(feature = "doh")]
+compile_error!(
+    "DNS-over-HTTPS is not implemented; host names are resolved by the system \
+     through std's to_socket_addrs"
+);
+
+/// Resolves `host` and connects to the first address that accepts, with
+/// the default socket options.
+pub fn connect(host: &str, port: u16) -> Result<TcpStream> {
+    connect_measured(host, port, None, &SocketOptions::default())
+}
+
+/// [`connect`], noting when each step finished in `metrics`.
+fn connect_measured(
+    host: &str,
+    port: u16,
+    metrics: Option<&Metrics>,
+    socket: &SocketOptions,
+) -> Result<TcpStream> {
+    let addrs = (host, port)
+        .to_socket_addrs()
+        .map_err(|source| Error::Dns {
+            host: host.to_string(),
+            source,
+        })?;
+    if let Some(metrics) = metrics {
+        metrics.resolved();
+    }
+    let mut last_error = None;
+    for addr in addrs {
+        match socket.connect(addr) {
+            Ok(stream) => {
+                if let Some(metrics) = metrics {
+                    metrics.connected(&stream);
+                }
+                return Ok(stream);
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(Error::Connect {
+        host: host.to_string(),
+        port,
+        source: last_error
+            .unwrap_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no addresses found")),
+    })
+}
+
+/// Bounds on what a server may make the client do, and how the
+/// connections it is reached over are set up.
+#[derive(Debug, Clone, Default)]
+pub struct Limits {
+    /// The largest response body accepted, from `--max-filesize`.
+    pub max_filesize: Option<u64>,
+    /// The low-speed abort, from `--speed-limit` and `--speed-time`.
+    pub speed_limit: Option<SpeedLimit>,
+    /// The cap on how fast bodies arrive, from `--limit-rate`; every
+    /// transfer given these limits, or a clone of them, shares it.
+    pub rate_limit: Option<Arc<RateLimit>>,
+    pub socket: SocketOptions,
+    /// What connections are wrapped in; `None` for plain TCP.
+    pub tls: Option<Arc<dyn Connector>>,
+    /// Refuse response heads that bend the grammar, from `--strict`; see
+    /// [`response::parse_head_with`].
+    pub strict: bool,
+}
+
+impl Limits {
+    /// These limits for a request to `url`: TLS from the build's default
+    /// backend for `https` and `wss`, unless one was already given, and
+    /// plain TCP for `http` and `ws`. Anything else is not spoken here.
+    pub fn for_url(&self, url: &Url) -> Result<Limits> {
+        let tls = match url.scheme() {
+            "http" | "ws" => None,
+            "https" | "wss" => match &self.tls {
+                Some(tls) => Some(tls.clone()),
+                None => Some(tls::default_connector()?),
+            },
+            scheme => return Err(Error::UnsupportedProtocol(scheme.to_string())),
+        };
+        Ok(Limits {
+            tls,
+            ..self.clone()
+        })
+    }
+}
+
+/// The headers sent when the caller does not override them.
+pub fn default_headers(host_header: &str, body_len: Option<usize>) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.append("Host", host_header);
+    headers.append("User-Agent", DEFAULT_USER_AGENT);
+    headers.append("Accept", "*/*");
+    if let Some(len) = body_len {
+        headers.append("Content-Length", len.to_string());
+    }
+    headers.append("Connection", "close");
+    headers
+}
+
+/// Checks that the request line and headers are well-formed, so that no
+/// argument can inject extra header lines or a second request.
+pub fn validate_request(method: &str, target: &str, headers: &HeaderMap) -> Result<()> {
+    if method.is_empty() || !method.bytes().all(headers::is_token_byte) {
+        return Err(Error::InvalidRequest(format!(
+            "invalid method '{}'",
+            method.escape_debug()
+        )));
+    }
+    // Url percent-encodes its components, so this only trips on targets
+    // built by hand.
+    if target.is_empty() || target.bytes().any(|b| b <= b' ' || b == 0x7f) {
+        return Err(Error::InvalidRequest(format!(
+            "invalid request target '{}'",
+            target.escape_debug()
+        )));
+    }
+    headers.validate()
+}
+
+/// The request line, headers and body as they are sent.
+pub fn request_text(
+    method: &str,
+    target: &str,
+    headers: &HeaderMap,
+    body: Option<&[u8]>,
+) -> Vec<u8> {
+    let mut request = request_head(method, target, headers).into_bytes();
+    request.extend_from_slice(body.unwrap_or_default());
+    request
+}
+
+/// The request line and headers, up to and including the blank line.
+fn request_head(method: &str, target: &str, headers: &HeaderMap) -> String {
+    format!("{} {} HTTP/1.1\r\n{}\r\n", method, target, headers)
+}
+
+/// Writes the request line, headers and body, echoing the head to stderr
+/// with `verbose`.
+pub(crate) fn write_request<W: Write>(
+    stream: &mut W,
+    method: &str,
+    target: &str,
+    headers: &HeaderMap,
+    body: Option<&[u8]>,
+    verbose: bool,
+) -> Result<()> {
+    validate_request(method, target, headers)?;
+    let head = request_head(method, target, headers);
+
+    if verbose {
+        for line in head.split("\r\n").take_while(|l| !l.is_empty()) {
+            match line.split_once(": ") {
+                Some((name, value)) => eprintln!("> {}: {}", name, headers::redact(name, value)),
+                None => eprintln!("> {}", line),
+            }
+        }
+        eprintln!(">");
+    }
+
+    // The body goes out as it is, after the head, rather than being copied
+    // in behind it.
+    let body = body.unwrap_or_default();
+    write_all_vectored(
+        stream,
+        &mut [IoSlice::new(head.as_bytes()), IoSlice::new(body)],
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// `Write::write_all` for several buffers, in as few writes as the stream
+/// takes them in.
+fn write_all_vectored<W: Write>(stream: &mut W, mut bufs: &mut [IoSlice<'_>]) -> io::Result<()> {
+    IoSlice::advance_slices(&mut bufs, 0);
+    while !bufs.is_empty() {
+        match stream.write_vectored(bufs) {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(n) => IoSlice::advance_slices(&mut bufs, n),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+/// The connection a response body is read from.
+pub type Connection<'a> = BufReader<Box<dyn Read + 'a>>;
+
+/// A response whose head has been read but whose body is still on the
+/// connection, for callers that consume it as it arrives.
+pub struct StreamingResponse<'a> {
+    pub status: StatusLine,
+    pub headers: HeaderMap,
+    pub body: Body<Connection<'a>>,
+}
+
+impl StreamingResponse<'_> {
+    /// The status code.
+    pub fn status(&self) -> u16 {
+        self.status.code
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Reads the rest of the body into a [`Response`].
+    pub fn into_response(mut self, limits: &Limits) -> Result<Response> {
+        let body = read_body(&mut self.body, limits)?;
+        Ok(Response {
+            status: self.status,
+            headers: self.headers,
+            body,
+            trailers: self.body.take_trailers(),
+        })
+    }
+}
+
+/// Reading a streaming response reads its body, as it arrives.
+impl Read for StreamingResponse<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.body.read(buf)
+    }
+}
+
+/// One request as it goes out: where it is sent, exactly what is sent,
+/// and how the transfer is watched. [`Exchange::new`] makes a quiet,
+/// untraced HTTP/1.1 request without a body; the rest is set with struct
+/// update syntax:
+///
+/// ```no_run
+/// use http_client::http::{self, Exchange, Limits};
+///
+/// let limits = Limits::default();
+/// let headers = http::default_headers("example.com", Some(2));
+/// let response = http::send_http_request(&Exchange {
+///     body: Some(b"{}"),
+///     verbose: true,
+///     ..Exchange::new("example.com", 80, "POST", "/items", &headers, &limits)
+/// })?;
+/// # Ok::<(), http_client::error::Error>(())
+/// ```
+///
+/// `'w` is how long the `trace` and `metrics` live, which a streaming
+/// response keeps writing to.
+#[derive(Clone, Copy)]
+pub struct Exchange<'r, 'w> {
+    pub host: &'r str,
+    pub port: u16,
+    pub method: &'r str,
+    /// As it goes in the request line.
+    pub target: &'r str,
+    /// Exactly the headers sent; nothing is added.
+    pub headers: &'r HeaderMap,
+    pub body: Option<&'r [u8]>,
+    /// Echo the request and response heads to stderr.
+    pub verbose: bool,
+    /// Where every byte on the wire is dumped.
+    pub trace: Option<&'w Trace>,
+    /// Where how the transfer went is recorded.
+    pub metrics: Option<&'w Metrics>,
+    pub limits: &'r Limits,
+    pub version: HttpVersion,
+}
+
+impl<'r> Exchange<'r, '_> {
+    pub fn new(
+        host: &'r str,
+        port: u16,
+        method: &'r str,
+        target: &'r str,
+        headers: &'r HeaderMap,
+        limits: &'r Limits,
+    ) -> Self {
+        Exchange {
+            host,
+            port,
+            method,
+            target,
+            headers,
+            body: None,
+            verbose: false,
+            trace: None,
+            metrics: None,
+            limits,
+            version: HttpVersion::Http11,
+        }
+    }
+}
+
+/// Sends one request and returns the parsed response.
+pub fn send_http_request(request: &Exchange) -> Result<Response> {
+    let upgrade = match request.version {
+        HttpVersion::Http11 => None,
+        HttpVersion::Http2 => request.body.is_none().then_some(true),
+        HttpVersion::Http2PriorKnowledge => Some(false),
+    };
+    #[cfg(not(feature = "http2"))]
+    if upgrade.is_some() {
+        return Err(Error::UnsupportedProtocol("HTTP/2".to_string()));
+    }
+    #[cfg(feature = "http2")]
+    if let Some(upgrade) = upgrade {
+        let Exchange {
+            host,
+            port,
+            method,
+            target,
+            headers,
+            limits,
+            ..
+        } = *request;
+        validate_request(method, target, headers)?;
+        let stream = open_connection(host, port, request.trace, request.metrics, limits)?;
+        let request = Http2Request {
+            method,
+            target,
+            headers,
+            body: request.body,
+            verbose: request.verbose,
+            limits,
+            upgrade,
+        };
+        return match (limits.speed_limit, limits.rate_limit.clone()) {
+            (Some(limit), Some(rate)) => {
+                request.send(SpeedCheck::new(Throttled::new(stream, rate), limit))
+            }
+            (Some(limit), None) => request.send(SpeedCheck::new(stream, limit)),
+            (None, Some(rate)) => request.send(Throttled::new(stream, rate)),
+            (None, None) => request.send(stream),
+        };
+    }
+    let transport = Tcp {
+        trace: request.trace,
+        metrics: request.metrics,
+        limits: request.limits,
+    };
+    send_over(&transport, request)
+}
+
+/// Sends one HTTP/1.1 request over a connection from `transport`, which
+/// can be a Unix socket or an in-memory [`crate::transport::Mock`] as well
+/// as TCP. The transport does any tracing, so the request's `trace`,
+/// `metrics` and `version` are not used.
+pub fn send_over<T: Transport>(transport: &T, request: &Exchange) -> Result<Response> {
+    let Exchange {
+        method,
+        target,
+        headers,
+        verbose,
+        limits,
+        ..
+    } = *request;
+    validate_request(method, target, headers)?;
+    let mut stream = transport.connect(request.host, request.port)?;
+    write_request(&mut stream, method, target, headers, request.body, verbose)?;
+    let mut response = read_response(stream, method, verbose, limits)?;
+    let body = read_body(&mut response.body, limits)?;
+    Ok(Response {
+        status: response.status,
+        headers: response.headers,
+        body,
+        trailers: response.body.take_trailers(),
+    })
+}
+
+/// Like [`send_http_request`] over HTTP/1.1, but returns as soon as the
+/// response head has been read.
+pub fn open_request<'w>(request: &Exchange<'_, 'w>) -> Result<StreamingResponse<'w>> {
+    let Exchange {
+        method,
+        target,
+        headers,
+        verbose,
+        limits,
+        ..
+    } = *request;
+    validate_request(method, target, headers)?;
+    let mut stream = open_connection(
+        request.host,
+        request.port,
+        request.trace,
+        request.metrics,
+        limits,
+    )?;
+    write_request(&mut stream, method, target, headers, request.body, verbose)?;
+    read_response(stream, method, verbose, limits)
+}
+
+/// How much of an [`Upload`] is read and sent at a time.
+pub const UPLOAD_CHUNK: usize = 64 * 1024;
+
+/// A request body read from a file or pipe while it is sent, for `-T`.
+pub struct Upload<'u> {
+    pub body: &'u mut dyn Read,
+    /// The body's size, sent as `Content-Length`; with `None` the body is
+    /// sent chunked until `body` ends.
+    pub length: Option<u64>,
+    /// Told how many bytes have been sent so far, after every chunk.
+    pub progress: &'u mut dyn FnMut(u64),
+}
+
+/// Like [`open_request`], but sends the body from `upload`, in place of
+/// the request's, one [`UPLOAD_CHUNK`] at a time, so a file of any size
+/// goes out in the same memory. Reading waits for each write, so a slow
+/// server slows the reading down rather than letting it pile up.
+/// `Content-Length` or `Transfer-Encoding` is set from `upload.length`.
+pub fn open_upload<'w>(
+    request: &Exchange<'_, 'w>,
+    upload: Upload<'_>,
+) -> Result<StreamingResponse<'w>> {
+    let Exchange {
+        method,
+        target,
+        verbose,
+        limits,
+        ..
+    } = *request;
+    let mut headers = request.headers.clone();
+    match upload.length {
+        Some(length) => {
+            headers.remove("Transfer-Encoding");
+            headers.insert("Content-Length", length.to_string());
+        }
+        None => {
+            headers.remove("Content-Length");
+            headers.insert("Transfer-Encoding", "chunked");
+        }
+    }
+    validate_request(method, target, &headers)?;
+    let mut stream = open_connection(
+        request.host,
+        request.port,
+        request.trace,
+        request.metrics,
+        limits,
+    )?;
+    write_request(&mut stream, method, target, &headers, None, verbose)?;
+    send_upload(&mut stream, upload)?;
+    read_response(stream, method, verbose, limits)
+}
+
+fn send_upload<W: Write>(stream: &mut W, upload: Upload<'_>) -> Result<()> {
+    let mut buf = vec![0; UPLOAD_CHUNK];
+    let mut sent = 0;
+    loop {
+        let want = match upload.length {
+            Some(length) => {
+                usize::try_from(length - sent).map_or(buf.len(), |left| left.min(buf.len()))
+            }
+            None => buf.len(),
+        };
+        if want == 0 {
+            break;
+        }
+        let n = match upload.body.read(&mut buf[..want]) {
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e.into()),
+        };
+        if n == 0 {
+            match upload.length {
+                // The file shrank since its size was taken.
+                Some(length) => {
+                    return Err(Error::Io(io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        format!("the upload ended after {} of {} bytes", sent, length),
+                    )))
+                }
+                None => break,
+            }
+        }
+        match upload.length {
+            Some(_) => stream.write_all(&buf[..n])?,
+            None => {
+                let size = format!("{:x}\r\n", n);
+                let mut chunk = [
+                    IoSlice::new(size.as_bytes()),
+                    IoSlice::new(&buf[..n]),
+                    IoSlice::new(b"\r\n"),
+                ];
+                write_all_vectored(stream, &mut chunk)?;
+            }
+        }
+        sent += n as u64;
+        (upload.progress)(sent);
+    }
+    if upload.length.is_none() {
+        stream.write_all(b"0\r\n\r\n")?;
+    }
+    stream.flush()?;
+    Ok(())
+}
+
+/// Sends `request` exactly as given, for `--request-file`, and reads the
+/// response. The method for working out whether the response has a body is
+/// taken from the request line.
+pub fn send_raw(
+    host: &str,
+    port: u16,
+    request: &[u8],
+    verbose: bool,
+    trace: Option<&Trace>,
+    metrics: Option<&Metrics>,
+    limits: &Limits,
+) -> Result<Response> {
+    let mut stream = open_connection(host, port, trace, metrics, limits)?;
+    if verbose {
+        for line in raw::head_lines(request) {
+            match line.split_once(": ") {
+                Some((name, value)) => eprintln!("> {}: {}", name, headers::redact(name, value)),
+                None => eprintln!("> {}", line),
+            }
+        }
+        eprintln!(">");
+    }
+    stream.write_all(request)?;
+    stream.flush()?;
+    let method = raw::method(request).unwrap_or("GET");
+    let mut response = read_response(stream, method, verbose, limits)?;
+    let body = read_body(&mut response.body, limits)?;
+    Ok(Response {
+        status: response.status,
+        headers: response.headers,
+        body,
+        trailers: response.body.take_trailers(),
+    })
+}
+
+/// Reads the response head to a request already written to `stream`.
+fn read_response<'a, S: Read + 'a>(
+    stream: S,
+    method: &str,
+    verbose: bool,
+    limits: &Limits,
+) -> Result<StreamingResponse<'a>> {
+    let stream: Box<dyn Read + 'a> = match &limits.rate_limit {
+        Some(rate) => Box::new(Throttled::new(stream, rate.clone())),
+        None => Box::new(stream),
+    };
+    let stream: Box<dyn Read + 'a> = match limits.speed_limit {
+        Some(limit) => Box::new(SpeedCheck::new(stream, limit)),
+        None => Box::new(stream),
+    };
+    // Bytes that arrive with the headers stay buffered for the body.
+    let mut stream = BufReader::new(stream);
+    let (status, headers) = read_final_head(&mut stream, verbose, limits.strict)?;
+
+    let framing = framing(method, &status, &headers, limits)?;
+    Ok(StreamingResponse {
+        status,
+        headers,
+        body: Body::new(stream, framing),
+    })
+}
+
+/// Connects, notes the peer in the trace, prepares the socket for the
+/// low-speed check and runs the TLS handshake if there is one.
+pub(crate) fn open_connection<'a>(
+    host: &str,
+    port: u16,
+    trace: Option<&'a Trace>,
+    metrics: Option<&'a Metrics>,
+    limits: &Limits,
+) -> Result<Traced<'a, Box<dyn Stream>>> {
+    let stream = connect_measured(host, port, metrics, &limits.socket)?;
+    if let Some(trace) = trace {
+        if let Ok(addr) = stream.peer_addr() {
+            trace.info(&format!(
+                "Connected to {} ({}) port {}",
+                host,
+                addr.ip(),
+                port
+            ));
+        }
+    }
+    if limits.speed_limit.is_some() {
+        stream.set_read_timeout(Some(speed::SAMPLE_INTERVAL))?;
+    }
+    let stream = secure(host, stream, limits)?;
+    Ok(Traced::new(stream, trace).measured(metrics))
+}
+
+/// `tcp` as it is talked over: through `limits.tls` if set.
+pub(crate) fn secure(host: &str, tcp: TcpStream, limits: &Limits) -> Result<Box<dyn Stream>> {
+    match &limits.tls {
+        Some(tls) => tls.connect(host, tcp),
+        None => Ok(Box::new(tcp)),
+    }
+}
+
+/// One request sent over HTTP/2, either with prior knowledge or after an
+/// `Upgrade: h2c` round trip.
+#[cfg(feature = "http2")]
+struct Http2Request<'r> {
+    method: &'r str,
+    target: &'r str,
+    headers: &'r HeaderMap,
+    body: Option<&'r [u8]>,
+    verbose: bool,
+    limits: &'r Limits,
+    upgrade: bool,
+}
+
+#[cfg(feature = "http2")]
+impl Http2Request<'_> {
+    fn send<S: Read + Write>(&self, stream: S) -> Result<Response> {
+        let max_body = self.limits.max_filesize.unwrap_or(u64::MAX);
+        let mut conn = BufReader::new(stream);
+        if !self.upgrade {
+            let mut client = http2::Client::new(conn, self.verbose, max_body);
+            client.handshake()?;
+            let fields = http2::request_fields(self.method, "http", self.target, self.headers);
+            client.send_request(&fields, self.body)?;
+            return client.read_response();
+        }
+
+        let mut headers = self.headers.clone();
+        headers.insert("Connection", "Upgrade, HTTP2-Settings");
+        headers.insert("Upgrade", "h2c");
+        headers.insert("HTTP2-Settings", http2::upgrade_settings());
+        write_request(
+            conn.get_mut(),
+            self.method,
+            self.target,
+            &headers,
+            None,
+            self.verbose,
+        )?;
+        let (status, headers) = read_final_head(&mut conn, self.verbose, self.limits.strict)?;
+        if status.code != 101 {
+            // The server declined and answered over HTTP/1.1.
+            let framing = framing(self.method, &status, &headers, self.limits)?;
+            let mut reader = Body::new(&mut conn, framing);
+            let body = read_body(&mut reader, self.limits)?;
+            return Ok(Response {
+                status,
+                headers,
+                body,
+                trailers: reader.take_trailers(),
+            });
+        }
+        // The upgrade request became stream 1; its response follows the
+        // server's SETTINGS.
+        let mut client = http2::Client::new(conn, self.verbose, max_body);
+        client.handshake()?;
+        client.read_response()
+    }
+}
+
+/// A connection to one server that is reused for sequential HTTP/1.1
+/// requests for as long as the server keeps it open.
+///
+/// Nothing is traced and neither `--speed-limit` nor `--limit-rate` is
+/// applied; this is meant for sending many small requests quickly, or a
+/// handshake that must stay on one connection.
+pub struct PersistentConnection {
+    host: String,
+    port: u16,
+    conn: Option<BufReader<Box<dyn Stream>>>,
+    verbose: bool,
+}
+
+impl PersistentConnection {
+    /// Connects lazily, on the first request.
+    pub fn new(host: &str, port: u16) -> PersistentConnection {
+        PersistentConnection {
+            host: host.to_string(),
+            port,
+            conn: None,
+            verbose: false,
+        }
+    }
+
+    /// Echoes request and response heads to stderr, as `-v` does.
+    pub fn verbose(mut self, verbose: bool) -> PersistentConnection {
+        self.verbose = verbose;
+        self
+    }
+
+    /// Whether a connection is currently open.
+    pub fn is_open(&self) -> bool {
+        self.conn.is_some()
+    }
+
+    /// Sends one request and reads the whole response. `headers` should not
+    /// contain `Connection: close` unless reuse is unwanted.
+    ///
+    /// A server may close an idle connection at any time, so a request that
+    /// gets no response at all on a reused connection is sent once more on
+    /// a new one.
+    pub fn send(
+        &mut self,
+        method: &str,
+        target: &str,
+        headers: &HeaderMap,
+        body: Option<&[u8]>,
+        limits: &Limits,
+    ) -> Result<Response> {
+        validate_request(method, target, headers)?;
+        let reused = self.conn.is_some();
+        let (status, headers) = match self.send_head(method, target, headers, body, limits) {
+            Err(_) if reused => self.send_head(method, target, headers, body, limits),
+            result => result,
+        }?;
+        let Some(conn) = self.conn.as_mut() else {
+            unreachable!("send_head leaves the connection open");
+        };
+
+        let result = framing(method, &status, &headers, limits).and_then(|framing| {
+            let mut reader = Body::new(conn, framing);
+            let body = read_body(&mut reader, limits)?;
+            Ok((framing, body, reader.take_trailers()))
+        });
+        match &result {
+            Ok((framing, ..)) if *framing != Framing::Close && keeps_alive(&status, &headers) => {}
+            _ => self.conn = None,
+        }
+        let (_, body, trailers) = result?;
+        Ok(Response {
+            status,
+            headers,
+            body,
+            trailers,
+        })
+    }
+
+    /// Writes the request and reads the final response head, dropping the
+    /// connection on failure.
+    fn send_head(
+        &mut self,
+        method: &str,
+        target: &str,
+        headers: &HeaderMap,
+        body: Option<&[u8]>,
+        limits: &Limits,
+    ) -> Result<(StatusLine, HeaderMap)> {
+        let conn = match self.conn.as_mut() {
+            Some(conn) => conn,
+            None => {
+                let stream = connect_measured(&self.host, self.port, None, &limits.socket)?;
+                let stream = secure(&self.host, stream, limits)?;
+                self.conn.insert(BufReader::new(stream))
+            }
+        };
+        let result = write_request(conn.get_mut(), method, target, headers, body, self.verbose)
+            .and_then(|()| read_final_head(conn, self.verbose, limits.strict));
+        if result.is_err() {
+            self.conn = None;
+        }
+        result
+    }
+}
+
+/// How the body of a response to `method` ends.
+fn framing(
+    method: &str,
+    status: &StatusLine,
+    headers: &HeaderMap,
+    limits: &Limits,
+) -> Result<Framing> {
+    let has_body = response::has_body(method, status.code);
+    Framing::from_headers(headers, has_body, limits.strict)
+}
+
+/// Whether an HTTP/1 response to `method` has a body that runs to the end
+/// of the connection although the server did not say it would close it.
+/// Such a body is read that way all the same, as RFC 9112 section 6.3
+/// says, but a server that keeps the connection open anyway holds the
+/// transfer until it gives up, so `-v` points it out, as curl does.
+pub fn reads_until_close(method: &str, status: &StatusLine, headers: &HeaderMap) -> bool {
+    let has_body = response::has_body(method, status.code);
+    status.version.starts_with("HTTP/1")
+        && Framing::from_headers(headers, has_body, false).is_ok_and(|f| f == Framing::Close)
+        && keeps_alive(status, headers)
+}
+
+/// Whether the server lets the connection stay open after this response:
+/// HTTP/1.1 does unless told `close`, HTTP/1.0 only with `keep-alive`.
+/// A response framed two ways never leaves it open.
+fn keeps_alive(status: &StatusLine, headers: &HeaderMap) -> bool {
+    let has_token = |token: &str| {
+        headers
+            .get_all("Connection")
+            .flat_map(|value| value.split(','))
+            .any(|t| t.trim().eq_ignore_ascii_case(token))
+    };
+    if body::has_conflicting_framing(headers) {
+        false
+    } else if status.version == "HTTP/1.0" {
+        has_token("keep-alive")
+    } else {
+        !has_token("close")
+    }
+}
+
+/// Reads response heads until the final one, skipping interim 1xx responses
+/// such as `100 Continue` and `103 Early Hints`. `101 Switching Protocols`
+/// is final. With `verbose` every head, interim or not, is echoed.
+pub(crate) fn read_final_head<R: BufRead>(
+    stream: &mut R,
+    verbose: bool,
+    strict: bool,
+) -> Result<(StatusLine, HeaderMap)> {
+    loop {
+        let head = response::read_head(stream)?;
+        let (status, headers) = response::parse_head_with(&head, strict)?;
+        if verbose {
+            eprintln!("< {}", status);
+            for (name, value) in headers.iter() {
+                eprintln!("< {}: {}", name, value);
+            }
+            eprintln!("<");
+        }
+        if !(100..200).contains(&status.code) || status.code == 101 {
+            return Ok((status, headers));
+        }
+    }
+}
+
+/// The most [`read_body`] reserves before any of the body has arrived.
+const PREALLOCATE: u64 = 1 << 20;
+
+/// Reads a whole body into memory, enforcing `--max-filesize`.
+///
+/// Up to [`PREALLOCATE`] bytes of an announced length are reserved at
+/// once, so typical bodies are read without reallocating; past that the
+/// buffer grows with the data actually received rather than with what the
+/// server announced.
+pub fn read_body<R: BufRead>(body: &mut Body<R>, limits: &Limits) -> Result<Vec<u8>> {
+    let max = limits.max_filesize.unwrap_or(u64::MAX);
+    if let Framing::Length(length) = body.framing() {
+        if length > max {
+            return Err(Error::FileSizeExceeded(max));
+        }
+    }
+    let mut out = match body.framing() {
+        Framing::Length(length) => Vec::with_capacity(length.min(PREALLOCATE) as usize),
+        _ => Vec::new(),
+    };
+    // One byte past the limit is enough to know it was exceeded.
+    body.by_ref()
+        .take(max.saturating_add(1))
+        .read_to_end(&mut out)?;
+    if out.len() as u64 > max {
+        return Err(Error::FileSizeExceeded(max));
+    }
+    body.finish()?;
+    Ok(out)
+}
+
+/// Copies a whole body to `out` as it arrives, enforcing `--max-filesize`
+/// like [`read_body`], and returns its length. Given a [`io::BufWriter`],
+/// the body is read straight into the writer's buffer.
+pub fn copy_body<R: BufRead, W: Write>(
+    body: &mut Body<R>,
+    out: &mut W,
+    limits: &Limits,
+) -> Result<u64> {
+    let max = limits.max_filesize.unwrap_or(u64::MAX);
+    if let Framing::Length(length) = body.framing() {
+        if length > max {
+            return Err(Error::FileSizeExceeded(max));
+        }
+    }
+    let copied = io::copy(&mut body.by_ref().take(max.saturating_add(1)), out)?;
+    if copied > max {
+        return Err(Error::FileSizeExceeded(max));
+    }
+    body.finish()?;
+    out.flush()?;
+    Ok(copied)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(max: u64) -> Limits {
+        Limits {
+            max_filesize: Some(max),
+            ..Limits::default()
+        }
+    }
+
+    #[test]
+    fn rejects_line_breaks_in_the_request() {
+        let mut headers = HeaderMap::new();
+        headers.append("Host", "h");
+        assert!(validate_request("GET", "/a?b", &headers).is_ok());
+        assert!(validate_request("GET / HTTP/1.1\r\nX: y\r\n\r\nGET", "/", &headers).is_err());
+        assert!(validate_request("", "/", &headers).is_err());
+        assert!(validate_request("GET", "/a b", &headers).is_err());
+        assert!(validate_request("GET", "/\r\nX: y", &headers).is_err());
+        headers.append("User-Agent", "a\r\nInjected: 1");
+        let err = validate_request("GET", "/", &headers).unwrap_err();
+        assert_eq!(err.exit_code(), 43);
+    }
+
+    #[test]
+    fn request_text_is_what_goes_on_the_wire() {
+        let mut headers = HeaderMap::new();
+        headers.append("Host", "h");
+        headers.append("Content-Length", "2");
+        assert_eq!(
+            request_text("POST", "/a", &headers, Some(b"hi")),
+            b"POST /a HTTP/1.1\r\nHost: h\r\nContent-Length: 2\r\n\r\nhi"
+        );
+        assert_eq!(
+            request_text("OPTIONS", "*", &HeaderMap::new(), None),
+            b"OPTIONS * HTTP/1.1\r\n\r\n"
+        );
+    }
+
+    #[test]
+    fn bodies_without_length_run_to_the_close() {
+        let headers = |fields: &[(&str, &str)]| {
+            let mut headers = HeaderMap::new();
+            for (name, value) in fields {
+                headers.append(*name, *value);
+            }
+            headers
+        };
+        let http11 = StatusLine::parse("HTTP/1.1 200 OK").unwrap();
+        let http10 = StatusLine::parse("HTTP/1.0 200 OK").unwrap();
+        let limits = Limits::default();
+        let get = framing("GET", &http11, &headers(&[]), &limits).unwrap();
+        assert_eq!(get, Framing::Close);
+        // The cases -v points out: the server did not say it would close.
+        assert!(reads_until_close("GET", &http11, &headers(&[])));
+        assert!(!reads_until_close(
+            "GET",
+            &http11,
+            &headers(&[("Content-Length", "0")])
+        ));
+        assert!(!reads_until_close("HEAD", &http11, &headers(&[])));
+        let http2 = StatusLine::parse("HTTP/2 200").unwrap();
+        assert!(!reads_until_close("GET", &http2, &headers(&[])));
+        assert!(keeps_alive(&http11, &headers(&[])));
+        assert!(keeps_alive(
+            &http10,
+            &headers(&[("Connection", "Keep-Alive")])
+        ));
+        assert!(!keeps_alive(
+            &http11,
+            &headers(&[("Connection", "x, close")])
+        ));
+        assert!(!keeps_alive(&http10, &headers(&[])));
+        let head = framing("HEAD", &http11, &headers(&[]), &limits).unwrap();
+        assert_ne!(head, Framing::Close);
+    }
+
+    #[test]
+    fn writes_head_and_body_together() {
+        /// Takes at most 10 bytes per write, and counts the writes.
+        #[derive(Default)]
+        struct Slow {
+            out: Vec<u8>,
+            writes: usize,
+        }
+        impl Write for Slow {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.write_vectored(&[IoSlice::new(buf)])
+            }
+            fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+                self.writes += 1;
+                let mut n = 0;
+                for buf in bufs {
+                    let take = buf.len().min(10 - n);
+                    self.out.extend_from_slice(&buf[..take]);
+                    n += take;
+                }
+                Ok(n)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let headers: HeaderMap = [("Host", "h")].into_iter().collect();
+        let mut slow = Slow::default();
+        write_request(&mut slow, "PUT", "/", &headers, Some(b"0123456789"), false).unwrap();
+        let expected = request_text("PUT", "/", &headers, Some(b"0123456789"));
+        assert_eq!(slow.out, expected);
+        assert_eq!(slow.writes, expected.len().div_ceil(10));
+    }
+
+    #[test]
+    fn uploads_are_sent_in_chunks() {
+        let data = vec![b'x'; UPLOAD_CHUNK + 10];
+        let mut seen = Vec::new();
+        let mut out = Vec::new();
+        let upload = Upload {
+            body: &mut &data[..],
+            length: None,
+            progress: &mut |sent| seen.push(sent),
+        };
+        send_upload(&mut out, upload).unwrap();
+        let expected = [
+            format!("{:x}\r\n", UPLOAD_CHUNK).as_bytes(),
+            &data[..UPLOAD_CHUNK],
+            b"\r\na\r\nxxxxxxxxxx\r\n0\r\n\r\n",
+        ]
+        .concat();
+        assert_eq!(out, expected);
+        assert_eq!(seen, [UPLOAD_CHUNK as u64, data.len() as u64]);
+
+        // A file that shrank since its size was taken is not padded out.
+        let upload = Upload {
+            body: &mut &data[..5],
+            length: Some(10),
+            progress: &mut |_| {},
+        };
+        let err = send_upload(&mut Vec::new(), upload).unwrap_err();
+        assert!(err.to_string().contains("after 5 of 10 bytes"));
+    }
+
+    #[test]
+    fn skips_interim_responses() {
+        let mut wire: &[u8] = b"HTTP/1.1 100 Continue\r\n\r\n\
+            HTTP/1.1 103 Early Hints\r\nLink: </style.css>; rel=preload\r\n\r\n\
+            HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok";
+        let (status, headers) = read_final_head(&mut wire, false, false).unwrap();
+        assert_eq!(status.code, 200);
+        assert_eq!(headers.get("Content-Length"), Some("2"));
+        assert!(!headers.contains("Link"));
+        assert_eq!(wire, b"ok");
+    }
+
+    #[test]
+    fn switching_protocols_is_final() {
+        let mut wire: &[u8] = b"HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\r\n";
+        let (status, _) = read_final_head(&mut wire, false, false).unwrap();
+        assert_eq!(status.code, 101);
+    }
+
+    #[cfg(feature = "http2")]
+    #[test]
+    fn upgrades_to_h2c() {
+        use crate::hpack::Encoder;
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let head = response::read_head(&mut reader).unwrap();
+            let mut out = &stream;
+            out.write_all(
+                b"HTTP/1.1 101 Switching Protocols\r\nConnection: Upgrade\r\nUpgrade: h2c\r\n\r\n",
+            )
+            .unwrap();
+            let block = Encoder.encode([(":status", "200")]);
+            http2::write_frame(&mut out, 0x4, 0, 0, &[]).unwrap();
+            http2::write_frame(&mut out, 0x1, 0x4, 1, &block).unwrap();
+            http2::write_frame(&mut out, 0x0, 0x1, 1, b"upgraded").unwrap();
+
+            let mut preface = [0u8; 24];
+            reader.read_exact(&mut preface).unwrap();
+            assert_eq!(preface, http2::PREFACE);
+            let settings = http2::read_frame(&mut reader).unwrap();
+            assert_eq!(settings.kind, 0x4);
+            String::from_utf8(head).unwrap()
+        });
+
+        let headers = default_headers(&format!("127.0.0.1:{}", port), None);
+        let limits = Limits::default();
+        let response = send_http_request(&Exchange {
+            version: HttpVersion::Http2,
+            ..Exchange::new("127.0.0.1", port, "GET", "/", &headers, &limits)
+        })
+        .unwrap();
+        assert_eq!(response.status.to_string(), "HTTP/2 200");
+        assert_eq!(response.body, b"upgraded");
+        let head = server.join().unwrap();
+        assert!(head.contains("Connection: Upgrade, HTTP2-Settings\r\n"));
+        assert!(head.contains("Upgrade: h2c\r\n"));
+        assert!(head.contains("HTTP2-Settings: AAIAAAAA\r\n"));
+    }
+
+    #[test]
+    fn persistent_connections_are_reused_until_closed() {
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let mut heads = Vec::new();
+            // The first connection answers two requests and then closes
+            // without a word; the second one says it is closing.
+            for replies in [
+                &[
+                    &b"HTTP/1.1 200 OK\r\nContent-Length: 1\r\n\r\na"[..],
+                    b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n1\r\nb\r\n0\r\n\r\n",
+                ][..],
+                &[b"HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 1\r\n\r\nc"],
+            ] {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                for reply in replies {
+                    heads.push(response::read_head(&mut reader).unwrap());
+                    (&stream).write_all(reply).unwrap();
+                }
+            }
+            heads.len()
+        });
+
+        let mut conn = PersistentConnection::new("127.0.0.1", port);
+        let headers: HeaderMap = [("Host", "h")].into_iter().collect();
+        let mut get = || {
+            conn.send("GET", "/", &headers, None, &Limits::default())
+                .unwrap()
+                .body
+        };
+        assert_eq!(get(), b"a");
+        assert_eq!(get(), b"b");
+        // The server dropped the idle connection; this goes out again on a
+        // new one.
+        assert_eq!(get(), b"c");
+        assert!(!conn.is_open());
+        assert_eq!(server.join().unwrap(), 3);
+    }
+
+    /// A "TLS" backend that wraps nothing and notes who it was asked for.
+    #[derive(Debug, Default)]
+    struct Plain {
+        hosts: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl Connector for Plain {
+        fn connect(&self, host: &str, tcp: TcpStream) -> Result<Box<dyn Stream>> {
+            self.hosts.lock().unwrap().push(host.to_string());
+            Ok(Box::new(tcp))
+        }
+    }
+
+    #[test]
+    fn https_goes_through_the_connector() {
+        use std::net::TcpListener;
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            response::read_head(&mut reader).unwrap();
+            (&stream)
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+                .unwrap();
+        });
+
+        let plain = Arc::new(Plain::default());
+        let given = Limits {
+            tls: Some(plain.clone()),
+            ..Limits::default()
+        };
+        let url = Url::parse(&format!("https://127.0.0.1:{}/", port)).unwrap();
+        let limits = given.for_url(&url).unwrap();
+        let headers: HeaderMap = [("Host", "h")].into_iter().collect();
+        let response = send_http_request(&Exchange::new(
+            url.connect_host(),
+            port,
+            "GET",
+            "/",
+            &headers,
+            &limits,
+        ))
+        .unwrap();
+        assert_eq!(response.body, b"ok");
+        assert_eq!(*plain.hosts.lock().unwrap(), ["127.0.0.1"]);
+
+        let http = Url::parse("http://h/").unwrap();
+        assert!(given.for_url(&http).unwrap().tls.is_none());
+        let ws = Url::parse("ws://h/").unwrap();
+        assert!(given.for_url(&ws).unwrap().tls.is_none());
+        let wss = Url::parse("wss://h/").unwrap();
+        assert!(given.for_url(&wss).unwrap().tls.is_some());
+        let ftp = Url::parse("ftp://h/").unwrap();
+        assert_eq!(given.for_url(&ftp).unwrap_err().exit_code(), 1);
+    }
+
+    #[test]
+    fn reads_chunked_bodies_whole() {
+        let wire: &[u8] = b"3\r\nabc\r\n0\r\n\r\n";
+        let body = read_body(&mut Body::new(wire, Framing::Chunked), &limits(3)).unwrap();
+        assert_eq!(body, b"abc");
+        let wire: &[u8] = b"3\r\nabc\r\n2\r\nde\r\n0\r\n\r\n";
+        assert!(read_body(&mut Body::new(wire, Framing::Chunked), &limits(4)).is_err());
+    }
+
+    #[test]
+    fn reads_exactly_content_length() {
+        let wire: &[u8] = b"hello, trailing";
+        let body = read_body(&mut Body::new(wire, Framing::Length(5)), &Limits::default()).unwrap();
+        assert_eq!(body, b"hello");
+    }
+
+    #[test]
+    fn short_bodies_are_partial() {
+        let wire: &[u8] = b"hel";
+        let err =
+            read_body(&mut Body::new(wire, Framing::Length(5)), &Limits::default()).unwrap_err();
+        assert_eq!(err.exit_code(), 18);
+    }
+
+    #[test]
+    fn huge_content_length_is_not_preallocated() {
+        let wire: &[u8] = b"tiny";
+        let err = read_body(
+            &mut Body::new(wire, Framing::Length(u64::MAX)),
+            &Limits::default(),
+        )
+        .unwrap_err();
+        assert!(matches!(err, Error::PartialFile { received: 4, .. }));
+    }
+
+    #[test]
+    fn copies_bodies_through_a_writer() {
+        let wire: &[u8] = b"5\r\nhello\r\n0\r\n\r\n";
+        let mut out = io::BufWriter::new(Vec::new());
+        let copied =
+            copy_body(&mut Body::new(wire, Framing::Chunked), &mut out, &limits(5)).unwrap();
+        assert_eq!((copied, out.into_inner().unwrap()), (5, b"hello".to_vec()));
+        let err = copy_body(
+            &mut Body::new(wire, Framing::Chunked),
+            &mut Vec::new(),
+            &limits(4),
+        );
+        assert!(matches!(err, Err(Error::FileSizeExceeded(4))));
+    }
+
+    #[test]
+    fn max_filesize_applies_with_and_without_length() {
+        let wire: &[u8] = b"0123456789";
+        assert!(matches!(
+            read_body(&mut Body::new(wire, Framing::Length(10)), &limits(4)),
+            Err(Error::FileSizeExceeded(4))
+        ));
+        let wire: &[u8] = b"0123456789";
+        assert!(matches!(
+            read_body(&mut Body::new(wire, Framing::Close), &limits(4)),
+            Err(Error::FileSizeExceeded(4))
+        ));
+        let wire: &[u8] = b"0123";
+        assert_eq!(
+            read_body(&mut Body::new(wire, Framing::Close), &limits(4)).unwrap(),
+            b"0123"
+        );
+    }
+}
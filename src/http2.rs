@@ -0,0 +1,627 @@
+//! HTTP/2 over cleartext TCP (h2c, RFC 9113).
+//!
+//! A [`Client`] runs one request on stream 1 of a fresh connection, which
+//! is all a command-line transfer needs. It is used both with prior
+//! knowledge and after an `Upgrade: h2c` handshake, in which case the
+//! HTTP/1.1 request already was stream 1 and only the response is read.
+//! Server push is disabled; priorities are ignored.
+
+use std::io::{BufReader, Read, Write};
+
+use crate::base64;
+use crate::error::{Error, Result};
+use crate::headers::{self, HeaderMap};
+use crate::hpack::{Decoder, Encoder};
+use crate::response::{Response, StatusLine};
+
+/// The client connection preface.
+pub const PREFACE: &[u8] = b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n";
+
+const DATA: u8 = 0x0;
+const HEADERS: u8 = 0x1;
+const RST_STREAM: u8 = 0x3;
+const SETTINGS: u8 = 0x4;
+const PUSH_PROMISE: u8 = 0x5;
+const PING: u8 = 0x6;
+const GOAWAY: u8 = 0x7;
+const WINDOW_UPDATE: u8 = 0x8;
+const CONTINUATION: u8 = 0x9;
+
+const END_STREAM: u8 = 0x1;
+const ACK: u8 = 0x1;
+const END_HEADERS: u8 = 0x4;
+const PADDED: u8 = 0x8;
+const PRIORITY: u8 = 0x20;
+
+const SETTINGS_ENABLE_PUSH: u16 = 0x2;
+const SETTINGS_INITIAL_WINDOW_SIZE: u16 = 0x4;
+const SETTINGS_MAX_FRAME_SIZE: u16 = 0x5;
+
+/// The frame and window sizes every endpoint starts with.
+const DEFAULT_MAX_FRAME_SIZE: usize = 16_384;
+const DEFAULT_WINDOW: i64 = 65_535;
+
+/// The only stream a client opens.
+const STREAM: u32 = 1;
+
+/// Headers that are specific to an HTTP/1.1 connection and must not be
+/// sent over HTTP/2 (RFC 9113 section 8.2.2).
+const CONNECTION_HEADERS: &[&str] = &[
+    "connection",
+    "keep-alive",
+    "proxy-connection",
+    "transfer-encoding",
+    "upgrade",
+    "http2-settings",
+];
+
+fn protocol_error(msg: impl Into<String>) -> Error {
+    Error::HttpProtocol(msg.into())
+}
+
+/// One frame: type, flags, stream identifier and payload.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub kind: u8,
+    pub flags: u8,
+    pub stream: u32,
+    pub payload: Vec<u8>,
+}
+
+/// Reads one frame, refusing payloads larger than we advertised.
+pub fn read_frame<R: Read>(input: &mut R) -> Result<Frame> {
+    let mut head = [0u8; 9];
+    input.read_exact(&mut head)?;
+    let len = u32::from_be_bytes([0, head[0], head[1], head[2]]) as usize;
+    if len > DEFAULT_MAX_FRAME_SIZE {
+        return Err(protocol_error(format!(
+            "HTTP/2 frame of {} bytes exceeds the maximum frame size",
+            len
+        )));
+    }
+    let mut payload = vec![0u8; len];
+    input.read_exact(&mut payload)?;
+    Ok(Frame {
+        kind: head[3],
+        flags: head[4],
+        stream: u32::from_be_bytes([head[5], head[6], head[7], head[8]]) & 0x7fff_ffff,
+        payload,
+    })
+}
+
+/// Writes one frame.
+pub fn write_frame<W: Write>(
+    out: &mut W,
+    kind: u8,
+    flags: u8,
+    stream: u32,
+    payload: &[u8],
+) -> Result<()> {
+    let len = (payload.len() as u32).to_be_bytes();
+    let mut frame = Vec::with_capacity(9 + payload.len());
+    frame.extend_from_slice(&len[1..]);
+    frame.push(kind);
+    frame.push(flags);
+    frame.extend_from_slice(&stream.to_be_bytes());
+    frame.extend_from_slice(payload);
+    out.write_all(&frame)?;
+    Ok(())
+}
+
+/// The SETTINGS this client sends: server push off, defaults otherwise.
+pub fn settings_payload() -> Vec<u8> {
+    let mut payload = SETTINGS_ENABLE_PUSH.to_be_bytes().to_vec();
+    payload.extend_from_slice(&0u32.to_be_bytes());
+    payload
+}
+
+/// The `HTTP2-Settings` header value for an h2c upgrade.
+pub fn upgrade_settings() -> String {
+    base64::encode_url(&settings_payload())
+}
+
+/// Maps an HTTP/1.1 request onto HTTP/2 header fields: the pseudo-headers
+/// first, `Host` as `:authority`, names lowercased and connection-specific
+/// headers dropped.
+pub fn request_fields(
+    method: &str,
+    scheme: &str,
+    target: &str,
+    headers: &HeaderMap,
+) -> Vec<(String, String)> {
+    let mut fields = vec![
+        (":method".to_string(), method.to_string()),
+        (":scheme".to_string(), scheme.to_string()),
+    ];
+    if let Some(host) = headers.get("Host") {
+        fields.push((":authority".to_string(), host.to_string()));
+    }
+    fields.push((":path".to_string(), target.to_string()));
+    for (name, value) in headers.iter() {
+        let name = name.to_ascii_lowercase();
+        let te_trailers = name == "te" && value.eq_ignore_ascii_case("trailers");
+        if name == "host" || CONNECTION_HEADERS.contains(&name.as_str()) {
+            continue;
+        }
+        if name == "te" && !te_trailers {
+            continue;
+        }
+        fields.push((name, value.to_string()));
+    }
+    fields
+}
+
+/// Removes the padding from a DATA or HEADERS payload.
+fn unpad(frame: &Frame) -> Result<&[u8]> {
+    let payload = frame.payload.as_slice();
+    if frame.flags & PADDED == 0 {
+        return Ok(payload);
+    }
+    let (&pad, rest) = payload
+        .split_first()
+        .ok_or_else(|| protocol_error("padded HTTP/2 frame is empty"))?;
+    rest.len()
+        .checked_sub(pad as usize)
+        .map(|end| &rest[..end])
+        .ok_or_else(|| protocol_error("HTTP/2 padding exceeds the frame"))
+}
+
+/// The response being assembled on stream 1.
+#[derive(Default)]
+struct Progress {
+    status: Option<u16>,
+    headers: HeaderMap,
+    body: Vec<u8>,
+    trailers: HeaderMap,
+    done: bool,
+}
+
+/// One HTTP/2 connection carrying a single request on stream 1.
+pub struct Client<S> {
+    conn: BufReader<S>,
+    decoder: Decoder,
+    peer_max_frame: usize,
+    conn_window: i64,
+    stream_window: i64,
+    verbose: bool,
+    max_body: u64,
+    progress: Progress,
+}
+
+impl<S: Read + Write> Client<S> {
+    /// Takes over `conn`, which may already hold buffered bytes from the
+    /// server, such as frames sent right after `101 Switching Protocols`.
+    /// The response body is limited to `max_body` bytes.
+    pub fn new(conn: BufReader<S>, verbose: bool, max_body: u64) -> Client<S> {
+        Client {
+            conn,
+            decoder: Decoder::new(),
+            peer_max_frame: DEFAULT_MAX_FRAME_SIZE,
+            conn_window: DEFAULT_WINDOW,
+            stream_window: DEFAULT_WINDOW,
+            verbose,
+            max_body,
+            progress: Progress::default(),
+        }
+    }
+
+    fn write(&mut self, kind: u8, flags: u8, stream: u32, payload: &[u8]) -> Result<()> {
+        write_frame(self.conn.get_mut(), kind, flags, stream, payload)
+    }
+
+    fn flush(&mut self) -> Result<()> {
+        self.conn.get_mut().flush()?;
+        Ok(())
+    }
+
+    /// Sends the connection preface and our SETTINGS.
+    pub fn handshake(&mut self) -> Result<()> {
+        self.conn.get_mut().write_all(PREFACE)?;
+        self.write(SETTINGS, 0, 0, &settings_payload())?;
+        self.flush()
+    }
+
+    /// Opens stream 1 with `fields` (see [`request_fields`]) and sends the
+    /// body, honoring the server's flow-control windows.
+    pub fn send_request(&mut self, fields: &[(String, String)], body: Option<&[u8]>) -> Result<()> {
+        if self.verbose {
+            let field = |name: &str| {
+                fields
+                    .iter()
+                    .find(|(n, _)| n == name)
+                    .map_or("", |(_, v)| v.as_str())
+            };
+            eprintln!("> {} {} HTTP/2", field(":method"), field(":path"));
+            for (name, value) in fields.iter().filter(|(n, _)| !n.starts_with(':')) {
+                eprintln!("> {}: {}", name, headers::redact(name, value));
+            }
+            eprintln!(">");
+        }
+
+        let block = Encoder.encode(fields.iter().map(|(n, v)| (n.as_str(), v.as_str())));
+        let body = body.filter(|b| !b.is_empty());
+        let mut chunks: Vec<&[u8]> = block.chunks(self.peer_max_frame).collect();
+        if chunks.is_empty() {
+            chunks.push(&[]);
+        }
+        let last = chunks.len() - 1;
+        for (i, chunk) in chunks.into_iter().enumerate() {
+            let (kind, mut flags) = match i {
+                0 if body.is_none() => (HEADERS, END_STREAM),
+                0 => (HEADERS, 0),
+                _ => (CONTINUATION, 0),
+            };
+            if i == last {
+                flags |= END_HEADERS;
+            }
+            self.write(kind, flags, STREAM, chunk)?;
+        }
+        self.flush()?;
+
+        let Some(mut rest) = body else {
+            return Ok(());
+        };
+        while !rest.is_empty() {
+            if self.progress.done {
+                // The server answered before reading the whole body.
+                return Ok(());
+            }
+            let window = self.conn_window.min(self.stream_window);
+            if window <= 0 {
+                self.pump()?;
+                continue;
+            }
+            let n = rest.len().min(window as usize).min(self.peer_max_frame);
+            let flags = if n == rest.len() { END_STREAM } else { 0 };
+            self.write(DATA, flags, STREAM, &rest[..n])?;
+            self.conn_window -= n as i64;
+            self.stream_window -= n as i64;
+            rest = &rest[n..];
+        }
+        self.flush()
+    }
+
+    /// Reads frames until stream 1 is complete and returns the response.
+    pub fn read_response(mut self) -> Result<Response> {
+        while !self.progress.done {
+            self.pump()?;
+        }
+        // Best effort: tell the server we are done with the connection.
+        let mut goaway = STREAM.to_be_bytes().to_vec();
+        goaway.extend_from_slice(&0u32.to_be_bytes());
+        let _ = self
+            .write(GOAWAY, 0, 0, &goaway)
+            .and_then(|()| self.flush());
+
+        let progress = self.progress;
+        Ok(Response {
+            status: StatusLine {
+                version: "HTTP/2".to_string(),
+                code: progress.status.unwrap_or_default(),
+                reason: String::new(),
+            },
+            headers: progress.headers,
+            body: progress.body,
+            trailers: progress.trailers,
+        })
+    }
+
+    /// Reads and handles one frame (plus any CONTINUATION frames).
+    fn pump(&mut self) -> Result<()> {
+        let frame = read_frame(&mut self.conn)?;
+        match frame.kind {
+            SETTINGS if frame.flags & ACK == 0 => {
+                self.apply_settings(&frame.payload)?;
+                self.write(SETTINGS, ACK, 0, &[])?;
+                self.flush()?;
+            }
+            PING if frame.flags & ACK == 0 => {
+                self.write(PING, ACK, 0, &frame.payload)?;
+                self.flush()?;
+            }
+            WINDOW_UPDATE => {
+                let increment = frame
+                    .payload
+                    .get(..4)
+                    .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]) & 0x7fff_ffff)
+                    .ok_or_else(|| protocol_error("short WINDOW_UPDATE frame"))?;
+                match frame.stream {
+                    0 => self.conn_window += increment as i64,
+                    STREAM => self.stream_window += increment as i64,
+                    _ => {}
+                }
+            }
+            GOAWAY => {
+                let code = frame
+                    .payload
+                    .get(4..8)
+                    .map_or(0, |b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+                return Err(protocol_error(format!(
+                    "server closed the HTTP/2 connection (error code {})",
+                    code
+                )));
+            }
+            RST_STREAM if frame.stream == STREAM => {
+                let code = frame
+                    .payload
+                    .get(..4)
+                    .map_or(0, |b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]));
+                return Err(protocol_error(format!(
+                    "server reset the HTTP/2 stream (error code {})",
+                    code
+                )));
+            }
+            PUSH_PROMISE => return Err(protocol_error("server push was not enabled")),
+            HEADERS if frame.stream == STREAM => self.headers(frame)?,
+            DATA if frame.stream == STREAM => self.data(frame)?,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    fn apply_settings(&mut self, payload: &[u8]) -> Result<()> {
+        if !payload.len().is_multiple_of(6) {
+            return Err(protocol_error("malformed SETTINGS frame"));
+        }
+        for setting in payload.chunks(6) {
+            let id = u16::from_be_bytes([setting[0], setting[1]]);
+            let value = u32::from_be_bytes([setting[2], setting[3], setting[4], setting[5]]);
+            match id {
+                SETTINGS_INITIAL_WINDOW_SIZE => {
+                    // Applies retroactively to the open stream's window.
+                    self.stream_window += value as i64 - DEFAULT_WINDOW;
+                }
+                SETTINGS_MAX_FRAME_SIZE => {
+                    self.peer_max_frame = (value as usize).max(DEFAULT_MAX_FRAME_SIZE)
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn headers(&mut self, frame: Frame) -> Result<()> {
+        let end_stream = frame.flags & END_STREAM != 0;
+        let mut block = unpad(&frame)?;
+        if frame.flags & PRIORITY != 0 {
+            block = block
+                .get(5..)
+                .ok_or_else(|| protocol_error("short HTTP/2 HEADERS frame"))?;
+        }
+        let mut block = block.to_vec();
+        let mut flags = frame.flags;
+        while flags & END_HEADERS == 0 {
+            let next = read_frame(&mut self.conn)?;
+            if next.kind != CONTINUATION || next.stream != STREAM {
+                return Err(protocol_error("expected an HTTP/2 CONTINUATION frame"));
+            }
+            block.extend_from_slice(&next.payload);
+            flags = next.flags;
+        }
+
+        let fields = self.decoder.decode(&block)?;
+        if self.progress.status.is_none() {
+            let status = fields
+                .iter()
+                .find(|(n, _)| n == ":status")
+                .and_then(|(_, v)| v.parse::<u16>().ok())
+                .filter(|code| (100..1000).contains(code))
+                .ok_or_else(|| protocol_error("HTTP/2 response has no valid :status"))?;
+            let headers: HeaderMap = fields
+                .iter()
+                .filter(|(n, _)| !n.starts_with(':'))
+                .map(|(n, v)| (n.as_str(), v.as_str()))
+                .collect();
+            if self.verbose {
+                eprintln!("< HTTP/2 {}", status);
+                for (name, value) in headers.iter() {
+                    eprintln!("< {}: {}", name, value);
+                }
+                eprintln!("<");
+            }
+            // Interim responses are skipped, as for HTTP/1.1.
+            if !(100..200).contains(&status) {
+                self.progress.status = Some(status);
+                self.progress.headers = headers;
+            }
+        } else {
+            // A second header block on the stream carries trailers.
+            for (name, value) in fields.iter().filter(|(n, _)| !n.starts_with(':')) {
+                if self.verbose {
+                    eprintln!("< {}: {}", name, value);
+                }
+                self.progress.trailers.append(name.as_str(), value.as_str());
+            }
+        }
+        if end_stream {
+            self.finish()?;
+        }
+        Ok(())
+    }
+
+    fn data(&mut self, frame: Frame) -> Result<()> {
+        if self.progress.status.is_none() {
+            return Err(protocol_error("HTTP/2 DATA before the response headers"));
+        }
+        let data = unpad(&frame)?;
+        if self.progress.body.len() as u64 + data.len() as u64 > self.max_body {
+            return Err(Error::FileSizeExceeded(self.max_body));
+        }
+        self.progress.body.extend_from_slice(data);
+        let end_stream = frame.flags & END_STREAM != 0;
+        if end_stream {
+            return self.finish();
+        }
+        // Padding counts against flow control too, so replenish it all.
+        let len = frame.payload.len() as u32;
+        if len > 0 {
+            self.write(WINDOW_UPDATE, 0, 0, &len.to_be_bytes())?;
+            self.write(WINDOW_UPDATE, 0, STREAM, &len.to_be_bytes())?;
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> Result<()> {
+        if self.progress.status.is_none() {
+            return Err(protocol_error("HTTP/2 stream ended without a response"));
+        }
+        self.progress.done = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    /// A scripted server: reads come from `input`, writes are collected.
+    struct Scripted {
+        input: Cursor<Vec<u8>>,
+        output: Vec<u8>,
+    }
+
+    impl Read for Scripted {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.input.read(buf)
+        }
+    }
+
+    impl Write for Scripted {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.output.write(buf)
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn frame(kind: u8, flags: u8, stream: u32, payload: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        write_frame(&mut out, kind, flags, stream, payload).unwrap();
+        out
+    }
+
+    fn client(server: Vec<u8>) -> Client<Scripted> {
+        let conn = Scripted {
+            input: Cursor::new(server),
+            output: Vec::new(),
+        };
+        Client::new(BufReader::new(conn), false, 1 << 20)
+    }
+
+    /// The frames the client wrote, after the preface.
+    fn written(output: &[u8]) -> Vec<Frame> {
+        let mut input = output.strip_prefix(PREFACE).unwrap_or(output);
+        let mut frames = Vec::new();
+        while !input.is_empty() {
+            frames.push(read_frame(&mut input).unwrap());
+        }
+        frames
+    }
+
+    #[test]
+    fn maps_request_headers() {
+        let headers: HeaderMap = [
+            ("Host", "example.com"),
+            ("User-Agent", "t"),
+            ("Connection", "close"),
+            ("TE", "gzip"),
+        ]
+        .into_iter()
+        .collect();
+        let fields = request_fields("GET", "http", "/x", &headers);
+        let fields: Vec<(&str, &str)> = fields
+            .iter()
+            .map(|(n, v)| (n.as_str(), v.as_str()))
+            .collect();
+        assert_eq!(
+            fields,
+            [
+                (":method", "GET"),
+                (":scheme", "http"),
+                (":authority", "example.com"),
+                (":path", "/x"),
+                ("user-agent", "t"),
+            ]
+        );
+    }
+
+    #[test]
+    fn exchanges_a_request_with_prior_knowledge() {
+        let response_block = Encoder.encode([(":status", "200"), ("content-type", "text/plain")]);
+        let mut server = frame(SETTINGS, 0, 0, &[0, 5, 0, 0, 0x80, 0]);
+        server.extend(frame(PING, 0, 0, b"12345678"));
+        server.extend(frame(HEADERS, END_HEADERS, STREAM, &response_block));
+        server.extend(frame(DATA, PADDED, STREAM, b"\x02hel\0\0"));
+        server.extend(frame(DATA, END_STREAM, STREAM, b"lo"));
+
+        let mut client = client(server);
+        client.handshake().unwrap();
+        let fields = vec![
+            (":method".to_string(), "GET".to_string()),
+            (":path".to_string(), "/".to_string()),
+        ];
+        client.send_request(&fields, None).unwrap();
+        while !client.progress.done {
+            client.pump().unwrap();
+        }
+        assert_eq!(client.peer_max_frame, 32_768);
+        let output = client.conn.get_ref().output.clone();
+        let response = client.read_response().unwrap();
+        assert_eq!(response.status.to_string(), "HTTP/2 200");
+        assert_eq!(response.headers.get("Content-Type"), Some("text/plain"));
+        assert_eq!(response.body, b"hello");
+
+        let frames = written(&output);
+        assert_eq!(frames[0].kind, SETTINGS);
+        assert_eq!(frames[1].kind, HEADERS);
+        assert_eq!(frames[1].flags, END_STREAM | END_HEADERS);
+        let sent = Decoder::new().decode(&frames[1].payload).unwrap();
+        assert_eq!(sent, fields);
+        assert!(frames.iter().any(|f| f.kind == SETTINGS && f.flags == ACK));
+        assert!(frames
+            .iter()
+            .any(|f| f.kind == PING && f.flags == ACK && f.payload == b"12345678"));
+        assert!(frames.iter().any(|f| f.kind == WINDOW_UPDATE));
+    }
+
+    #[test]
+    fn request_bodies_wait_for_window_updates() {
+        let body = vec![b'x'; 70_000];
+        let response_block = Encoder.encode([(":status", "201")]);
+        let mut server = frame(WINDOW_UPDATE, 0, 0, &10_000u32.to_be_bytes());
+        server.extend(frame(WINDOW_UPDATE, 0, STREAM, &10_000u32.to_be_bytes()));
+        server.extend(frame(
+            HEADERS,
+            END_HEADERS | END_STREAM,
+            STREAM,
+            &response_block,
+        ));
+
+        let mut client = client(server);
+        client.handshake().unwrap();
+        let fields = vec![(":method".to_string(), "POST".to_string())];
+        client.send_request(&fields, Some(&body)).unwrap();
+        let sent: usize = written(&client.conn.get_ref().output)
+            .iter()
+            .filter(|f| f.kind == DATA)
+            .map(|f| f.payload.len())
+            .sum();
+        assert_eq!(sent, 70_000);
+        assert_eq!(client.read_response().unwrap().status.code, 201);
+    }
+
+    #[test]
+    fn reports_resets_and_skips_interim_responses() {
+        let interim = Encoder.encode([(":status", "103"), ("link", "</a>")]);
+        let mut server = frame(HEADERS, END_HEADERS, STREAM, &interim);
+        server.extend(frame(RST_STREAM, 0, STREAM, &8u32.to_be_bytes()));
+        let mut client = client(server);
+        client.handshake().unwrap();
+        let err = client.read_response().unwrap_err();
+        assert!(err.to_string().contains("error code 8"), "{}", err);
+    }
+}
@@ -0,0 +1,257 @@
+//! Request files in the `.http` / `.rest` format of VS Code's REST Client.
+//!
+//! A file holds any number of requests separated by lines starting with
+//! `###`. Each request is a request line (`METHOD URL [HTTP/1.1]`, or just
+//! a URL for GET), optional query lines starting with `?` or `&`, header
+//! lines up to the first blank line, and then the body. Lines starting with
+//! `#` or `//` are comments, except inside a body.
+//!
+//! `@name = value` lines before a request line define file variables for
+//! `{{name}}` placeholders (see [`crate::template`]), which are expanded
+//! when the request is sent.
+//!
+//! A `# @name login` comment before a request line names the request; later
+//! requests can then use its response, as in
+//! `{{login.response.headers.Location}}` or
+//! `{{login.response.body.$.token}}`.
+
+use crate::error::{Error, Result};
+use crate::headers::{self, CustomHeader};
+
+/// Everything in a request file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RequestFile {
+    /// `@name = value` definitions in file order; values are unexpanded.
+    pub variables: Vec<(String, String)>,
+    pub requests: Vec<FileRequest>,
+}
+
+/// One request from a file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileRequest {
+    /// The 1-based line of the request line, for messages.
+    pub line: usize,
+    /// From a `# @name` comment, for referring to the response.
+    pub name: Option<String>,
+    pub method: String,
+    pub url: String,
+    /// In `-H` syntax, so they override the built-in headers the same way.
+    pub headers: Vec<CustomHeader>,
+    pub body: Option<String>,
+}
+
+fn is_comment(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with('#') || line.starts_with("//")
+}
+
+/// The request name in a `# @name login` or `// @name login` comment.
+fn request_name(line: &str) -> Option<&str> {
+    let line = line.trim_start();
+    let text = line.strip_prefix('#').or_else(|| line.strip_prefix("//"))?;
+    let name = text.trim_start().strip_prefix("@name")?;
+    if !name.starts_with(char::is_whitespace) {
+        return None;
+    }
+    Some(name.trim()).filter(|n| !n.is_empty())
+}
+
+/// Prefixes an error with the line it was found on.
+fn at_line(line: usize, err: Error) -> Error {
+    match err {
+        Error::InvalidHeader(msg) => Error::InvalidHeader(format!("line {}: {}", line, msg)),
+        Error::InvalidRequest(msg) => Error::InvalidRequest(format!("line {}: {}", line, msg)),
+        other => other,
+    }
+}
+
+/// Splits a request line into method and URL; a trailing HTTP version is
+/// accepted and ignored.
+fn request_line(text: &str, line: usize) -> Result<(String, String)> {
+    let mut words: Vec<&str> = text.split_whitespace().collect();
+    if words.len() > 1 && words[words.len() - 1].starts_with("HTTP/") {
+        words.pop();
+    }
+    match words[..] {
+        [url] => Ok(("GET".to_string(), url.to_string())),
+        [method, url] if method.bytes().all(headers::is_token_byte) => {
+            Ok((method.to_string(), url.to_string()))
+        }
+        _ => Err(Error::InvalidRequest(format!(
+            "line {}: expected 'METHOD URL', got '{}'",
+            line, text
+        ))),
+    }
+}
+
+/// Parses a whole file. Blocks with nothing but comments, variables and
+/// blank lines are skipped.
+pub fn parse(text: &str) -> Result<RequestFile> {
+    let mut file = RequestFile::default();
+    let mut block: Vec<(usize, &str)> = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        if line.starts_with("###") {
+            parse_block(&block, &mut file)?;
+            block.clear();
+        } else {
+            block.push((i + 1, line));
+        }
+    }
+    parse_block(&block, &mut file)?;
+    Ok(file)
+}
+
+/// Parses `@name = value`.
+fn variable(text: &str, line: usize) -> Result<(String, String)> {
+    let definition = text.trim_start().trim_start_matches('@');
+    match definition.split_once('=') {
+        Some((name, value)) if !name.trim().is_empty() => {
+            Ok((name.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(Error::InvalidRequest(format!(
+            "line {}: expected '@name = value', got '{}'",
+            line, text
+        ))),
+    }
+}
+
+fn parse_block(block: &[(usize, &str)], file: &mut RequestFile) -> Result<()> {
+    let mut lines = block.iter().peekable();
+    let mut name = None;
+    let (line, first) = loop {
+        let Some(&(n, text)) = lines.next() else {
+            return Ok(());
+        };
+        if let Some(n) = request_name(text) {
+            name = Some(n.to_string());
+        } else if text.trim_start().starts_with('@') {
+            file.variables.push(variable(text, n)?);
+        } else if !text.trim().is_empty() && !is_comment(text) {
+            break (n, text);
+        }
+    };
+    let (method, mut url) = request_line(first.trim(), line)?;
+
+    while let Some((_, query)) = lines.next_if(|(_, l)| {
+        let l = l.trim_start();
+        l.starts_with('?') || l.starts_with('&')
+    }) {
+        // The HTTP version may follow the last query line.
+        url.push_str(query.split_whitespace().next().unwrap_or_default());
+    }
+
+    let mut headers = Vec::new();
+    for &(n, text) in lines.by_ref() {
+        if text.trim().is_empty() {
+            break;
+        }
+        if is_comment(text) {
+            continue;
+        }
+        headers.push(CustomHeader::parse(text).map_err(|e| at_line(n, e))?);
+    }
+
+    let body: Vec<&str> = lines.map(|(_, l)| *l).collect();
+    let end = body
+        .iter()
+        .rposition(|l| !l.trim().is_empty())
+        .map_or(0, |i| i + 1);
+    let body = Some(body[..end].join("\n")).filter(|b| !b.is_empty());
+    file.requests.push(FileRequest {
+        line,
+        name,
+        method,
+        url,
+        headers,
+        body,
+    });
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_requests_with_headers_and_bodies() {
+        let text = "\
+# A comment before the first request
+@base = https://example.com
+GET {{base}}/items
+    ?page=2
+    &size=10 HTTP/1.1
+Accept: application/json
+// disabled header
+
+### Create one
+POST http://localhost/items
+Content-Type: application/json
+
+{
+  \"name\": \"x\"
+}
+
+
+###
+# nothing here
+###
+http://localhost/health
+";
+        let file = parse(text).unwrap();
+        assert_eq!(
+            file.variables,
+            [("base".to_string(), "https://example.com".to_string())]
+        );
+        let requests = file.requests;
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].method, "GET");
+        assert_eq!(requests[0].url, "{{base}}/items?page=2&size=10");
+        assert_eq!(
+            requests[0].headers,
+            [CustomHeader::Set(
+                "Accept".into(),
+                "application/json".into()
+            )]
+        );
+        assert_eq!(requests[0].body, None);
+        assert_eq!(requests[1].line, 10);
+        assert_eq!(requests[1].body.as_deref(), Some("{\n  \"name\": \"x\"\n}"));
+        assert_eq!(requests[2].method, "GET");
+        assert_eq!(requests[2].url, "http://localhost/health");
+        assert_eq!(requests[2].name, None);
+    }
+
+    #[test]
+    fn reads_request_names() {
+        let text = "\
+# @name login
+POST http://h/login
+
+###
+// @name  me
+# @named no
+GET http://h/me
+Authorization: Bearer {{login.response.body.$.token}}
+";
+        let requests = parse(text).unwrap().requests;
+        assert_eq!(requests[0].name.as_deref(), Some("login"));
+        assert_eq!(requests[1].name.as_deref(), Some("me"));
+        assert_eq!(
+            requests[1].headers,
+            [CustomHeader::Set(
+                "Authorization".into(),
+                "Bearer {{login.response.body.$.token}}".into()
+            )]
+        );
+    }
+
+    #[test]
+    fn reports_the_offending_line() {
+        let err = parse("GET http://h/\nBad Header\n").unwrap_err();
+        assert!(err.to_string().starts_with("line 2: "), "{}", err);
+        let err = parse("\n\nGET a b c\n").unwrap_err();
+        assert!(err.to_string().starts_with("line 3: "), "{}", err);
+        let err = parse("@novalue\n").unwrap_err();
+        assert!(err.to_string().starts_with("line 1: "), "{}", err);
+    }
+}
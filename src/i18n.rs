@@ -0,0 +1,1928 @@
+//! The message catalog for the command-line front end.
+//!
+//! Messages are keyed by [`Msg`] and rendered in the language chosen once at
+//! startup: `--lang` if given, else the first of `LC_ALL`, `LC_MESSAGES` and
+//! `LANG` that is set. Anything that is not Chinese falls back to English.
+
+use std::env;
+use std::io;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+use http_client::bench::Stats;
+
+use crate::batch::Row;
+use crate::help;
+use http_client::error::Error;
+use http_client::metrics::Metrics;
+use http_client::response::StatusLine;
+use http_client::servertiming::Metric;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lang {
+    En,
+    Zh,
+}
+
+/// The languages accepted by `--lang`.
+pub const LANGS: &[&str] = &["en", "zh"];
+
+static LANG: OnceLock<Lang> = OnceLock::new();
+
+impl Lang {
+    /// Maps a locale name such as `zh_CN.UTF-8` or `en` to a language.
+    pub fn from_locale(locale: &str) -> Lang {
+        if locale.to_ascii_lowercase().starts_with("zh") {
+            Lang::Zh
+        } else {
+            Lang::En
+        }
+    }
+
+    /// The language from the environment, in POSIX precedence order.
+    pub fn from_env() -> Lang {
+        ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| env::var(var).ok())
+            .find(|value| !value.is_empty())
+            .map_or(Lang::En, |value| Lang::from_locale(&value))
+    }
+}
+
+/// Fixes the language for the rest of the run. Only the first call counts.
+pub fn init(lang: Lang) {
+    let _ = LANG.set(lang);
+}
+
+/// The active language; the environment's if [`init`] was never called.
+pub fn lang() -> Lang {
+    *LANG.get_or_init(Lang::from_env)
+}
+
+/// Every user-facing message of the front end.
+#[derive(Debug)]
+pub enum Msg<'a> {
+    /// The first line of `--help`.
+    Usage,
+    /// The heading of the option list, or of one topic's.
+    HelpTopic(Option<&'a str>),
+    /// The description of an option, by long name.
+    OptionHelp(&'a str),
+    /// What follows the option list.
+    UsageNotes,
+    /// `--examples`.
+    Examples,
+    NoUrl,
+    /// `--dry-run` or `--export`, then the mode sending requests of its
+    /// own it was given with; None for a URL glob.
+    NoPreview(&'a str, Option<&'a str>),
+    /// `--aws-sigv4` found no key to sign with.
+    NoAwsCredentials,
+    OnlyOneUrl,
+    TraceWithBody,
+    /// Where `--dry-run` would have connected: host and port.
+    WouldConnect(&'a str, u16),
+    UnknownOption(&'a str),
+    /// A `.curlrc` option this client does not have: file, line, name.
+    ConfigUnknownOption(&'a str, usize, &'a str),
+    /// A `.curlrc` value whose quote is not closed: file and line.
+    ConfigUnterminatedQuote(&'a str, usize),
+    RequiresValue(&'a str),
+    TakesNoValue(&'a str),
+    InvalidValue(&'a str, &'a str),
+    UnsupportedChoice(&'a str, &'a str, &'a [&'a str]),
+    /// A redirect's status, the URL it leads to and how long it took in ms.
+    FollowingRedirect(u16, &'a str, u128),
+    /// The heading of `--show-redirects`, with the number of redirects.
+    RedirectHistory(usize),
+    /// `--retry`: the wait in seconds and how many retries are left.
+    Retrying(f64, u32),
+    /// A redirect went to another host without `--location-trusted`.
+    DroppingCredentials(&'a str),
+    /// The file `-O` is writing.
+    SavingTo(&'a str),
+    /// A binary body was about to be written to a terminal.
+    BinaryOutput,
+    /// A `--session` file that does not hold a session.
+    NotASession,
+    /// A session name with no home directory to keep it in.
+    NoSessionDir(&'a str),
+    /// A `--max-time-warn` or `--max-size-warn` budget that was exceeded.
+    OverBudget(&'a str),
+    /// Bytes of an upload sent so far, and of how many if known.
+    Uploaded(u64, Option<u64>),
+    /// A `--metrics-listen` address that could not be bound.
+    CannotListen(&'a str, &'a io::Error),
+    /// A `--record` or `--replay` file that does not hold a cassette.
+    NotACassette,
+    /// Method and URL of a request `--replay` has no response for.
+    NotRecorded(&'a str, &'a str),
+    /// Request number, total, method and URL in a `--http-file` run.
+    RunningRequest(usize, usize, &'a str, &'a str),
+    WebSocketClosed(Option<u16>, &'a str),
+    /// The stream ended, with the error if it broke; then the delay in ms.
+    SseReconnecting(Option<&'a Error>, u128),
+    /// The command list shown by `help` in `--interactive`.
+    ReplHelp,
+    ReplUnknownCommand(&'a str),
+    /// A `!n` or `!!` that refers to nothing.
+    ReplNoHistory(&'a str),
+    /// A response served from `--cache-dir` without a request.
+    CacheHit(&'a str),
+    /// A response served from `--replay` without a request.
+    Replaying(&'a str, &'a str),
+    /// A stored response the server confirmed with 304 Not Modified.
+    CacheRevalidated(&'a str),
+    /// A plain HTTP request switched to HTTPS because of `--hsts`.
+    HstsUpgrade(&'a str),
+    /// Shown with `-v` for a body that ran to the end of the connection
+    /// although the server did not say it would close it.
+    BodyUntilClose,
+    /// Host and new scheme of a URL switched by `--https-only`.
+    HttpsOnlyUpgrade(&'a str, &'a str),
+    /// Sending a request again with the proxy credentials, for a scheme.
+    ProxyChallenge(&'a str),
+    /// A final 407 with no proxy credentials to answer it; the schemes
+    /// offered.
+    ProxyAuthRequired(&'a str),
+    /// A final 407 after the proxy credentials were sent.
+    ProxyAuthRejected(&'a str),
+    /// `--http3` without a QUIC transport.
+    Http3Fallback,
+    /// Protocol and `host:port` of the alternative service being used.
+    UsingAltSvc(&'a str, &'a str),
+    /// A `--url-file` transfer: number, total, URL, status, body size and
+    /// time taken.
+    TransferDone(usize, usize, &'a str, &'a StatusLine, usize, Duration),
+    TransferFailed(usize, usize, &'a str, &'a Error),
+    /// A `--check-links` link answered with an error status, or that could
+    /// not be fetched.
+    LinkBroken(&'a str, &'a StatusLine),
+    LinkFailed(&'a str, &'a Error),
+    /// `--check-links` found no broken link among this many.
+    LinksWork(usize),
+    /// A crawl mode skipping a URL that robots.txt disallows.
+    RobotsDisallowed(&'a str),
+    /// `--mirror` is done: files saved and their total size.
+    Mirrored(usize, usize),
+    /// The table after a batch of several URLs.
+    TransferSummary(&'a [Row]),
+    /// `--timings`: the phases of a transfer that took this long in all,
+    /// then what the server reported.
+    Timings(&'a Metrics, Duration, &'a [Metric]),
+    /// One `--watch` attempt: the status, body size and latency.
+    WatchResponse(&'a StatusLine, usize, Duration),
+    /// The summary printed after `--bench`.
+    BenchReport(&'a Stats),
+    /// A library error, shown as the final diagnostic.
+    Error(&'a Error),
+}
+
+/// Renders `msg` in the active language.
+pub fn tr(msg: Msg) -> String {
+    render(lang(), msg)
+}
+
+pub fn render(lang: Lang, msg: Msg) -> String {
+    use Msg::*;
+    match (lang, msg) {
+        (Lang::En, Usage) => "Usage: http_client [options] <url>".to_string(),
+        (Lang::Zh, Usage) => "用法: http_client [选项] <url>".to_string(),
+        (Lang::En, HelpTopic(None)) => "Options:".to_string(),
+        (Lang::Zh, HelpTopic(None)) => "选项:".to_string(),
+        (Lang::En, HelpTopic(Some(topic))) => format!("Options for {}:", topic),
+        (Lang::Zh, HelpTopic(Some(topic))) => format!("{} 相关选项:", topic),
+        (Lang::En, OptionHelp(long)) => option_help(OPTION_HELP_EN, long),
+        (Lang::Zh, OptionHelp(long)) => option_help(OPTION_HELP_ZH, long),
+        (Lang::En, UsageNotes) => USAGE_NOTES_EN.to_string(),
+        (Lang::Zh, UsageNotes) => USAGE_NOTES_ZH.to_string(),
+        (Lang::En, Examples) => EXAMPLES_EN.to_string(),
+        (Lang::Zh, Examples) => EXAMPLES_ZH.to_string(),
+        (Lang::En, NoUrl) => format!("no URL given\n\n{}", help::usage(lang)),
+        (Lang::Zh, NoUrl) => format!("未指定 URL\n\n{}", help::usage(lang)),
+        (Lang::En, NoPreview(flag, Some(mode))) => format!(
+            "{} cannot be used with {}, which sends requests of its own; nothing was sent",
+            flag, mode
+        ),
+        (Lang::Zh, NoPreview(flag, Some(mode))) => {
+            format!("{} 不能与 {} 一起使用,后者会自行发送请求;未发送任何内容", flag, mode)
+        }
+        (Lang::En, NoPreview(flag, None)) => format!(
+            "{} cannot be used with a URL glob, which sends a request per URL; nothing was sent \
+             (--globoff sends the URL as it is)",
+            flag
+        ),
+        (Lang::Zh, NoPreview(flag, None)) => format!(
+            "{} 不能与 URL 通配一起使用,后者会为每个 URL 发送请求;未发送任何内容(--globoff 按原样发送 URL)",
+            flag
+        ),
+        (Lang::En, NoAwsCredentials) => "--aws-sigv4 needs credentials: put KEY:SECRET in the \
+            URL, set AWS_ACCESS_KEY_ID and AWS_SECRET_ACCESS_KEY, or add a profile to \
+            ~/.aws/credentials"
+            .to_string(),
+        (Lang::Zh, NoAwsCredentials) => "--aws-sigv4 需要凭据:在 URL 中写入 KEY:SECRET,\
+            设置 AWS_ACCESS_KEY_ID 和 AWS_SECRET_ACCESS_KEY,或在 ~/.aws/credentials 中添加配置"
+            .to_string(),
+        (Lang::En, OnlyOneUrl) => "only one URL may be given".to_string(),
+        (Lang::Zh, OnlyOneUrl) => "只能指定一个 URL".to_string(),
+        (Lang::En, WouldConnect(host, port)) => {
+            format!("Would connect to {} port {} (dry run)", host, port)
+        }
+        (Lang::Zh, WouldConnect(host, port)) => format!("将连接到 {} 端口 {}(试运行)", host, port),
+        (Lang::En, TraceWithBody) => "a TRACE request cannot have a body (-d)".to_string(),
+        (Lang::Zh, TraceWithBody) => "TRACE 请求不能带请求体(-d)".to_string(),
+        (Lang::En, UnknownOption(opt)) => format!("unknown option {}", opt),
+        (Lang::Zh, UnknownOption(opt)) => format!("未知选项 {}", opt),
+        (Lang::En, ConfigUnknownOption(path, line, name)) => format!(
+            "warning: {}:{}: skipping unknown option '{}'",
+            path, line, name
+        ),
+        (Lang::Zh, ConfigUnknownOption(path, line, name)) => {
+            format!("警告: {}:{}: 跳过未知选项 '{}'", path, line, name)
+        }
+        (Lang::En, ConfigUnterminatedQuote(path, line)) => {
+            format!("{}:{}: unterminated quoted value", path, line)
+        }
+        (Lang::Zh, ConfigUnterminatedQuote(path, line)) => {
+            format!("{}:{}: 引号未闭合", path, line)
+        }
+        (Lang::En, RequiresValue(opt)) => format!("option {} requires a value", opt),
+        (Lang::Zh, RequiresValue(opt)) => format!("选项 {} 需要一个值", opt),
+        (Lang::En, TakesNoValue(opt)) => format!("option {} does not take a value", opt),
+        (Lang::Zh, TakesNoValue(opt)) => format!("选项 {} 不接受值", opt),
+        (Lang::En, InvalidValue(opt, value)) => format!("invalid {} value '{}'", opt, value),
+        (Lang::Zh, InvalidValue(opt, value)) => format!("{} 的值 '{}' 无效", opt, value),
+        (Lang::En, UnsupportedChoice(opt, value, choices)) => format!(
+            "unsupported {} value '{}' (expected one of: {})",
+            opt,
+            value,
+            choices.join(", ")
+        ),
+        (Lang::Zh, UnsupportedChoice(opt, value, choices)) => {
+            format!("{} 不支持值 '{}'(可选: {})", opt, value, choices.join(", "))
+        }
+        (Lang::En, FollowingRedirect(code, url, ms)) => {
+            format!("Following {} redirect to {} (after {} ms)", code, url, ms)
+        }
+        (Lang::Zh, FollowingRedirect(code, url, ms)) => {
+            format!("跟随 {} 重定向到 {}(耗时 {} ms)", code, url, ms)
+        }
+        (Lang::En, Retrying(secs, 1)) => {
+            format!(
+                "Transient problem: retrying in {} seconds, 1 retry left",
+                secs
+            )
+        }
+        (Lang::En, Retrying(secs, left)) => format!(
+            "Transient problem: retrying in {} seconds, {} retries left",
+            secs, left
+        ),
+        (Lang::Zh, Retrying(secs, left)) => {
+            format!("临时故障:{} 秒后重试,还剩 {} 次", secs, left)
+        }
+        (Lang::En, DroppingCredentials(host)) => format!(
+            "Not sending credentials to {} (use --location-trusted to allow it)",
+            host
+        ),
+        (Lang::Zh, DroppingCredentials(host)) => {
+            format!("不向 {} 发送凭据(使用 --location-trusted 可允许)", host)
+        }
+        (Lang::En, SavingTo(path)) => format!("Saving to '{}'", path),
+        (Lang::Zh, SavingTo(path)) => format!("保存到 '{}'", path),
+        (Lang::En, BinaryOutput) => "binary output can mess up your terminal; use \"--output -\" \
+             to print it anyway, or \"--output <file>\" to save it"
+            .to_string(),
+        (Lang::Zh, BinaryOutput) => {
+            "二进制输出可能弄乱终端;使用 \"--output -\" 仍然输出,或使用 \"--output <file>\" 保存到文件"
+                .to_string()
+        }
+        (Lang::En, NotASession) => "not a session file".to_string(),
+        (Lang::Zh, NotASession) => "不是会话文件".to_string(),
+        (Lang::En, NoSessionDir(name)) => format!(
+            "no home directory to keep session '{}' in; give a file path instead",
+            name
+        ),
+        (Lang::Zh, NoSessionDir(name)) => {
+            format!("找不到用于保存会话 '{}' 的主目录;请改为指定文件路径", name)
+        }
+        (Lang::En, OverBudget(warning)) => format!("warning: {}", warning),
+        (Lang::Zh, OverBudget(warning)) => format!("警告: {}", warning),
+        (Lang::En, Uploaded(sent, Some(total))) => format!(
+            "uploaded {} of {} bytes ({}%)",
+            sent,
+            total,
+            (sent * 100).checked_div(total).unwrap_or(100)
+        ),
+        (Lang::En, Uploaded(sent, None)) => format!("uploaded {} bytes", sent),
+        (Lang::Zh, Uploaded(sent, Some(total))) => format!(
+            "已上传 {} / {} 字节({}%)",
+            sent,
+            total,
+            (sent * 100).checked_div(total).unwrap_or(100)
+        ),
+        (Lang::Zh, Uploaded(sent, None)) => format!("已上传 {} 字节", sent),
+        (Lang::En, CannotListen(addr, e)) => format!("cannot listen on {}: {}", addr, e),
+        (Lang::Zh, CannotListen(addr, e)) => format!("无法监听 {}: {}", addr, e),
+        (Lang::En, NotACassette) => "not a cassette file".to_string(),
+        (Lang::Zh, NotACassette) => "不是录制文件".to_string(),
+        (Lang::En, NotRecorded(method, url)) => {
+            format!("no recorded response for {} {}", method, url)
+        }
+        (Lang::Zh, NotRecorded(method, url)) => format!("没有 {} {} 的录制响应", method, url),
+        (Lang::En, RedirectHistory(1)) => "1 redirect:".to_string(),
+        (Lang::En, RedirectHistory(n)) => format!("{} redirects:", n),
+        (Lang::Zh, RedirectHistory(n)) => format!("{} 次重定向:", n),
+        (Lang::En, RunningRequest(n, total, method, url)) => {
+            format!("Request {}/{}: {} {}", n, total, method, url)
+        }
+        (Lang::Zh, RunningRequest(n, total, method, url)) => {
+            format!("请求 {}/{}: {} {}", n, total, method, url)
+        }
+        (Lang::En, ReplHelp) => REPL_HELP_EN.to_string(),
+        (Lang::Zh, ReplHelp) => REPL_HELP_ZH.to_string(),
+        (Lang::En, ReplUnknownCommand(line)) => {
+            format!("unknown command '{}'; type 'help' for a list", line)
+        }
+        (Lang::Zh, ReplUnknownCommand(line)) => format!("未知命令 '{}';输入 'help' 查看列表", line),
+        (Lang::En, ReplNoHistory(line)) => format!("{}: no such history entry", line),
+        (Lang::Zh, ReplNoHistory(line)) => format!("{}: 没有这条历史记录", line),
+        (Lang::En, WebSocketClosed(Some(code), reason)) => {
+            format!("WebSocket closed by server: {} {}", code, reason)
+        }
+        (Lang::Zh, WebSocketClosed(Some(code), reason)) => {
+            format!("服务器关闭了 WebSocket: {} {}", code, reason)
+        }
+        (Lang::En, WebSocketClosed(None, _)) => "WebSocket closed by server".to_string(),
+        (Lang::Zh, WebSocketClosed(None, _)) => "服务器关闭了 WebSocket".to_string(),
+        (Lang::En, SseReconnecting(None, ms)) => {
+            format!("Event stream ended; reconnecting in {} ms", ms)
+        }
+        (Lang::Zh, SseReconnecting(None, ms)) => format!("事件流已结束;{} 毫秒后重新连接", ms),
+        (Lang::En, SseReconnecting(Some(err), ms)) => {
+            format!(
+                "{}; reconnecting in {} ms",
+                render(Lang::En, Error(err)),
+                ms
+            )
+        }
+        (Lang::Zh, SseReconnecting(Some(err), ms)) => {
+            format!("{};{} 毫秒后重新连接", render(Lang::Zh, Error(err)), ms)
+        }
+        (Lang::En, CacheHit(url)) => format!("Using cached response for {}", url),
+        (Lang::Zh, CacheHit(url)) => format!("使用 {} 的缓存响应", url),
+        (Lang::En, Replaying(method, url)) => {
+            format!("Replaying recorded response for {} {}", method, url)
+        }
+        (Lang::Zh, Replaying(method, url)) => format!("回放 {} {} 的录制响应", method, url),
+        (Lang::En, CacheRevalidated(url)) => {
+            format!("Cached response for {} is still valid", url)
+        }
+        (Lang::Zh, CacheRevalidated(url)) => format!("{} 的缓存响应仍然有效", url),
+        (Lang::En, HstsUpgrade(host)) => format!("Switching to HTTPS for {} (HSTS)", host),
+        (Lang::Zh, HstsUpgrade(host)) => format!("根据 HSTS 对 {} 改用 HTTPS", host),
+        (Lang::En, BodyUntilClose) => "no Content-Length, chunked coding or Connection: close; \
+             the body ends when the server closes the connection"
+            .to_string(),
+        (Lang::Zh, BodyUntilClose) => {
+            "没有 Content-Length、分块编码或 Connection: close；响应体在服务器关闭连接时结束"
+                .to_string()
+        }
+        (Lang::En, HttpsOnlyUpgrade(host, scheme)) => {
+            format!("Switching to {}:// for {} (--https-only)", scheme, host)
+        }
+        (Lang::Zh, HttpsOnlyUpgrade(host, scheme)) => {
+            format!("根据 --https-only 对 {} 改用 {}://", host, scheme)
+        }
+        (Lang::En, ProxyChallenge(scheme)) => {
+            format!("Answering the proxy's {} challenge", scheme)
+        }
+        (Lang::Zh, ProxyChallenge(scheme)) => format!("应答代理的 {} 质询", scheme),
+        (Lang::En, ProxyAuthRequired(offered)) => format!(
+            "The proxy requires authentication (it offers {}); give its credentials with -U user:password",
+            offered
+        ),
+        (Lang::Zh, ProxyAuthRequired(offered)) => format!(
+            "代理要求认证(支持 {});请用 -U 用户:密码 提供代理凭据",
+            offered
+        ),
+        (Lang::En, ProxyAuthRejected(offered)) => {
+            format!("The proxy rejected its credentials (it offers {})", offered)
+        }
+        (Lang::Zh, ProxyAuthRejected(offered)) => {
+            format!("代理拒绝了代理凭据(支持 {})", offered)
+        }
+        (Lang::En, Http3Fallback) => {
+            "warning: HTTP/3 is not implemented, using HTTP/1.1".to_string()
+        }
+        (Lang::Zh, Http3Fallback) => "警告: 尚未实现 HTTP/3,改用 HTTP/1.1".to_string(),
+        (Lang::En, UsingAltSvc(protocol, endpoint)) => {
+            format!("Using alternative service {} at {}", protocol, endpoint)
+        }
+        (Lang::Zh, UsingAltSvc(protocol, endpoint)) => {
+            format!("使用位于 {} 的备用服务 {}", endpoint, protocol)
+        }
+        (Lang::En, TransferDone(n, total, url, status, bytes, time)) => format!(
+            "[{}/{}] {}: {} {}, {} bytes in {}",
+            n,
+            total,
+            url,
+            status.code,
+            status.reason,
+            bytes,
+            millis(time)
+        ),
+        (Lang::Zh, TransferDone(n, total, url, status, bytes, time)) => format!(
+            "[{}/{}] {}: {} {},{} 字节,用时 {}",
+            n,
+            total,
+            url,
+            status.code,
+            status.reason,
+            bytes,
+            millis(time)
+        ),
+        (Lang::En, TransferFailed(n, total, url, err)) => format!(
+            "[{}/{}] {}: {}",
+            n,
+            total,
+            url,
+            render(Lang::En, Error(err))
+        ),
+        (Lang::Zh, TransferFailed(n, total, url, err)) => format!(
+            "[{}/{}] {}: {}",
+            n,
+            total,
+            url,
+            render(Lang::Zh, Error(err))
+        ),
+        (Lang::En, LinkBroken(url, status)) => {
+            format!("{}: {} {}", url, status.code, status.reason)
+        }
+        (Lang::Zh, LinkBroken(url, status)) => {
+            format!("{}: {} {}", url, status.code, status.reason)
+        }
+        (Lang::En, LinkFailed(url, err)) => format!("{}: {}", url, render(Lang::En, Error(err))),
+        (Lang::Zh, LinkFailed(url, err)) => format!("{}: {}", url, render(Lang::Zh, Error(err))),
+        (Lang::En, LinksWork(n)) => format!("All {} links work", n),
+        (Lang::Zh, LinksWork(n)) => format!("全部 {} 个链接均可访问", n),
+        (Lang::En, RobotsDisallowed(url)) => {
+            format!("Skipping {}: robots.txt disallows it", url)
+        }
+        (Lang::Zh, RobotsDisallowed(url)) => format!("跳过 {}: robots.txt 不允许访问", url),
+        (Lang::En, Mirrored(files, bytes)) => format!("Saved {} files, {} bytes", files, bytes),
+        (Lang::Zh, Mirrored(files, bytes)) => format!("已保存 {} 个文件,共 {} 字节", files, bytes),
+        (Lang::En, WatchResponse(status, bytes, latency)) => format!(
+            "{} {}  {}  {} bytes",
+            status.code,
+            status.reason,
+            millis(latency),
+            bytes
+        ),
+        (Lang::Zh, WatchResponse(status, bytes, latency)) => format!(
+            "{} {}  {}  {} 字节",
+            status.code,
+            status.reason,
+            millis(latency),
+            bytes
+        ),
+        (lang, BenchReport(stats)) => bench_report(lang, stats),
+        (lang, TransferSummary(rows)) => transfer_summary(lang, rows),
+        (lang, Timings(metrics, total, server)) => timings(lang, metrics, total, server),
+        (Lang::En, Error(err)) => err.to_string(),
+        (Lang::Zh, Error(err)) => describe_zh(err),
+    }
+}
+
+fn millis(d: Duration) -> String {
+    format!("{:.2} ms", d.as_secs_f64() * 1000.0)
+}
+
+/// The `--bench` summary as a table of labelled rows.
+fn bench_report(lang: Lang, stats: &Stats) -> String {
+    let zh = lang == Lang::Zh;
+    let t = |en: &'static str, zh_text: &'static str| if zh { zh_text } else { en };
+    let mut rows = vec![
+        (
+            t("Requests:", "请求:"),
+            if zh {
+                format!("完成 {} 个,失败 {} 个", stats.completed(), stats.failed())
+            } else {
+                format!("{} completed, {} failed", stats.completed(), stats.failed())
+            },
+        ),
+        (
+            t("Time:", "耗时:"),
+            format!("{:.2} {}", stats.elapsed.as_secs_f64(), t("s", "秒")),
+        ),
+        (
+            t("Throughput:", "吞吐量:"),
+            if zh {
+                format!("每秒 {:.1} 个请求", stats.throughput())
+            } else {
+                format!("{:.1} requests/sec", stats.throughput())
+            },
+        ),
+        (
+            t("Transferred:", "传输量:"),
+            format!("{} {}", stats.bytes, t("bytes", "字节")),
+        ),
+    ];
+    if let (Some(min), Some(max)) = (stats.latencies.first(), stats.latencies.last()) {
+        let pct = |p| stats.percentile(p).map(millis).unwrap_or_default();
+        rows.push((
+            t("Latency:", "延迟:"),
+            format!(
+                "{} {}, p50 {}, p90 {}, p99 {}, {} {}",
+                t("min", "最小"),
+                millis(*min),
+                pct(50),
+                pct(90),
+                pct(99),
+                t("max", "最大"),
+                millis(*max)
+            ),
+        ));
+    }
+    if !stats.statuses.is_empty() {
+        let codes: Vec<String> = stats
+            .statuses
+            .iter()
+            .map(|(code, n)| format!("{} × {}", code, n))
+            .collect();
+        rows.push((t("Status codes:", "状态码:"), codes.join(", ")));
+    }
+    for (i, (err, n)) in stats.errors.iter().enumerate() {
+        let label = if i == 0 { t("Errors:", "错误:") } else { "" };
+        rows.push((label, format!("{} × {}", n, render(lang, Msg::Error(err)))));
+    }
+
+    rows.iter()
+        .map(|(label, value)| format!("{}{}{}", label, " ".repeat(15 - width(label)), value))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The batch summary: a line per transfer under a heading, in columns.
+fn transfer_summary(lang: Lang, rows: &[Row]) -> String {
+    let zh = lang == Lang::Zh;
+    let t = |en: &'static str, zh_text: &'static str| if zh { zh_text } else { en };
+    let mut table = vec![[
+        "#".to_string(),
+        "URL".to_string(),
+        t("Status", "状态").to_string(),
+        t("Bytes", "字节").to_string(),
+        t("Time", "用时").to_string(),
+        t("Result", "结果").to_string(),
+    ]];
+    for (i, row) in rows.iter().enumerate() {
+        table.push([
+            (i + 1).to_string(),
+            row.url.clone(),
+            row.status.map_or("-".to_string(), |code| code.to_string()),
+            row.bytes.to_string(),
+            millis(row.elapsed),
+            row.error
+                .clone()
+                .unwrap_or_else(|| t("ok", "成功").to_string()),
+        ]);
+    }
+    columns(&table)
+}
+
+/// The `--timings` report. Waiting runs from the connection being up to the
+/// first response byte, so it covers a TLS handshake, sending the request
+/// and the server's work; a phase that did not happen, as the lookup on a
+/// reused connection, is left out.
+fn timings(lang: Lang, metrics: &Metrics, total: Duration, server: &[Metric]) -> String {
+    let zh = lang == Lang::Zh;
+    let t = |en: &'static str, zh_text: &'static str| if zh { zh_text } else { en };
+    let lookup = metrics.name_lookup();
+    let connected = metrics.connect().or(lookup);
+    let first_byte = metrics.first_byte();
+    let phases = [
+        (t("DNS lookup", "域名解析"), lookup),
+        (
+            t("TCP connect", "建立连接"),
+            metrics.connect().map(|at| at - lookup.unwrap_or_default()),
+        ),
+        (
+            t("Waiting", "等待响应"),
+            first_byte.map(|at| at.saturating_sub(connected.unwrap_or_default())),
+        ),
+        (
+            t("Download", "接收数据"),
+            first_byte.map(|at| total.saturating_sub(at)),
+        ),
+        (t("Total", "总计"), Some(total)),
+    ];
+    let table: Vec<[String; 3]> = phases
+        .into_iter()
+        .filter_map(|(label, took)| Some([format!("  {}", label), millis(took?), String::new()]))
+        .collect();
+    let mut text = format!("{}\n{}", t("Timings:", "计时:"), columns(&table));
+    if !server.is_empty() {
+        let table: Vec<[String; 3]> = server
+            .iter()
+            .map(|metric| {
+                [
+                    format!("  {}", metric.name),
+                    metric
+                        .duration
+                        .map_or("-".to_string(), |ms| format!("{:.2} ms", ms)),
+                    metric.description.clone().unwrap_or_default(),
+                ]
+            })
+            .collect();
+        text.push_str(&format!(
+            "\n{}\n{}",
+            t("Server-Timing:", "服务器计时(Server-Timing):"),
+            columns(&table)
+        ));
+    }
+    text
+}
+
+/// `table` in columns two spaces apart, the last one not padded.
+fn columns<const N: usize>(table: &[[String; N]]) -> String {
+    let mut widths = [0; N];
+    for line in table {
+        for (column, cell) in line.iter().enumerate() {
+            widths[column] = widths[column].max(width(cell));
+        }
+    }
+    table
+        .iter()
+        .map(|line| {
+            let mut text = String::new();
+            for (column, cell) in line.iter().enumerate() {
+                text.push_str(cell);
+                if column + 1 < line.len() {
+                    text.push_str(&" ".repeat(widths[column] - width(cell) + 2));
+                }
+            }
+            text.trim_end().to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The description of the option spelled `long` in `table`.
+fn option_help(table: &[(&str, &str)], long: &str) -> String {
+    table
+        .iter()
+        .find(|(name, _)| *name == long)
+        .map_or_else(String::new, |(_, help)| help.to_string())
+}
+
+/// Display width: CJK characters take two columns.
+pub fn width(s: &str) -> usize {
+    s.chars().map(|c| if c.is_ascii() { 1 } else { 2 }).sum()
+}
+
+/// Library errors carry English details; in Chinese they get a translated
+/// summary with the detail appended.
+fn describe_zh(err: &Error) -> String {
+    match err {
+        // Already rendered through the catalog by the front end.
+        Error::Usage(msg) => msg.clone(),
+        Error::UnsupportedProtocol(scheme) => format!("不支持的协议 '{}'", scheme),
+        Error::UrlParse(msg) => format!("URL 无效: {}", msg),
+        Error::InvalidHeader(msg) => format!("请求头无效: {}", msg),
+        Error::InvalidRequest(msg) => format!("请求无效: {}", msg),
+        Error::Dns { host, source } => format!("无法解析主机 {}: {}", host, source),
+        Error::Connect { host, port, source } => {
+            format!("无法连接到 {}:{}: {}", host, port, source)
+        }
+        Error::Tls(msg) => format!("TLS 错误: {}", msg),
+        Error::Io(source) => format!("连接错误: {}", source),
+        Error::HttpProtocol(msg) => format!("HTTP 协议错误: {}", msg),
+        Error::Timeout(msg) => format!("超时: {}", msg),
+        Error::TooManyRedirects(n) => format!("已达到重定向次数上限({})", n),
+        Error::FileSizeExceeded(max) => format!("超过最大文件大小({} 字节)", max),
+        Error::BadContentEncoding(why) => format!("内容编码无效: {}", why),
+        Error::PartialFile { expected, received } => {
+            format!("传输在收到 {}/{} 字节时关闭", received, expected)
+        }
+        Error::File { path, source } => format!("文件 {} 出错: {}", path, source),
+        Error::ReadFile { path, source } => format!("无法读取 {}: {}", path, source),
+        Error::Template(msg) => format!("变量替换失败: {}", msg),
+        Error::Script(msg) => format!("脚本: {}", msg),
+        Error::Query(msg) => format!("jq 过滤失败: {}", msg),
+        Error::ExpectationFailed(failures) => {
+            format!("响应不符合预期: {}", failures.join("; "))
+        }
+        Error::BrokenLinks { broken, checked } => {
+            format!("{} 个链接中有 {} 个失效", checked, broken)
+        }
+        Error::OverBudget(failures) => format!("响应超出预算: {}", failures.join("; ")),
+        Error::ChecksumMismatch {
+            algorithm,
+            expected,
+            actual,
+        } => format!(
+            "{} 校验和不匹配: 应为 {},实际为 {}",
+            algorithm, expected, actual
+        ),
+    }
+}
+
+/// What `--help` says after the option list.
+const USAGE_NOTES_EN: &str = "\
+Options may be repeated; for single-valued ones the last occurrence wins.
+Use -- to mark the end of the options. The options in the curl config
+file, one per line as in 'header = \"X-Name: value\"', come before those on
+the command line; ones http_client does not have are skipped with a warning.
+
+{{name}} in the URL, -H values and -d data is replaced by the variable of
+that name: from the file's '@name = value' lines with --http-file, then
+from --env, then from the environment. An undefined name is an error;
+write \\{{ for a literal {{. In a .http file, a '# @name login' line
+before a request lets later requests use its response:
+{{login.response.headers.Name}}, {{login.response.body}}, or a JSONPath
+such as {{login.response.body.$.token}}.
+
+A file:// URL reads a local file and writes it like a download.
+With a ws:// or wss:// URL, each line read from stdin is sent as a text
+message and every message received is written to the output until either
+side closes.";
+
+/// Each option's `--help` description, by long name. Line breaks are
+/// kept; [`crate::help`] wraps the rest.
+const OPTION_HELP_EN: &[(&str, &str)] = &[
+    (
+        "url",
+        "The URL to fetch when none is given as an argument; mostly for a url line in .curlrc",
+    ),
+    (
+        "request",
+        "Request method (default GET, or POST with -d); any method name is sent as given. \
+         Without -i, an OPTIONS response with no body prints its Allow header",
+    ),
+    (
+        "dry-run",
+        "Print the request exactly as it would be sent, with every default, cookie, credential \
+         and signature applied, and where it would be sent to stderr, without connecting",
+    ),
+    (
+        "export",
+        "Instead of sending the request, print it as a curl command (curl) or as a program \
+         using Python's requests (python) or Rust's reqwest (rust)",
+    ),
+    (
+        "request-target",
+        "Send <target> on the request line instead of the URL's path: * (as in OPTIONS * \
+         HTTP/1.1), another path, or an absolute URL for testing proxies. The URL still says \
+         where to connect; redirects use their own targets",
+    ),
+    (
+        "request-file",
+        "Send the contents of <file> (- for stdin) as the request, byte for byte, for \
+         replaying captured traffic or testing unusual framing. The connection goes to the \
+         URL's host and port if one is given, else to the file's Host header on port 80 (443 \
+         with --https-only)",
+    ),
+    (
+        "fix-request",
+        "With --request-file, first convert the head's line endings to CRLF, set Host and \
+         recompute Content-Length from the body (unless it is chunked)",
+    ),
+    (
+        "script",
+        "Run the hook script <file>: its 'on request' statements can set and remove headers \
+         and change the method or body of each request, e.g. to add a signature, and its \
+         'on response' statements check the final response. Scripts are not Rhai or Lua, which \
+         would need crates this client does without, but a small line-based language of its \
+         own. For example:\n  on request\n    header X-Sig = hmac_sha256(env(\"KEY\"), method + \
+         path)\n  on response\n    assert status == \"200\"\n    assert json(\"$.ok\") == \
+         \"true\"\nStatements, one per line:\n  let NAME = EXPR\n  header NAME = EXPR\n  remove \
+         header NAME\n  method = EXPR, body = EXPR (requests only)\n  assert EXPR [OP EXPR], OP \
+         one of == != contains < >\n  print EXPR (to stderr)\nAn EXPR joins \"strings\", names \
+         and calls with +. Names: method, url, host, path, body, and status and reason for \
+         responses. Functions: header, env, now, sha256, sha1, md5, hmac_sha256, \
+         hmac_sha256_base64, base64, url_encode, lower, upper, trim, len, and json(\"$.path\") for \
+         responses. Every value is a string. A failed assert exits with code 22",
+    ),
+    (
+        "header",
+        "Extra request header, e.g. -H 'Accept: text/plain'. A built-in header is replaced by \
+         one of the same name; 'Name:' removes it and 'Name;' sends it empty",
+    ),
+    (
+        "user-agent",
+        "User-Agent to send (an empty name sends none)",
+    ),
+    (
+        "referer",
+        "Referer to send; append ';auto' to also set it on redirects",
+    ),
+    (
+        "oauth2-bearer",
+        "Send 'Authorization: Bearer <token>'. '@file' reads the token from a file ('@-' for \
+         stdin) and '{{NAME}}' from the environment, keeping it out of the shell history. -v \
+         shows credentials as ***",
+    ),
+    (
+        "aws-sigv4",
+        "Sign requests with AWS Signature Version 4, e.g. 'aws:amz:us-east-1:s3'; a missing \
+         region and service are taken from a host such as s3.us-east-1.amazonaws.com. The key \
+         and secret come from the URL (KEY:SECRET@host), else from AWS_ACCESS_KEY_ID, \
+         AWS_SECRET_ACCESS_KEY and AWS_SESSION_TOKEN, else from the AWS_PROFILE (or default) \
+         profile in ~/.aws/credentials",
+    ),
+    (
+        "ntlm",
+        "Authenticate with NTLMv2, as IIS and Windows intranet servers ask for, using the \
+         URL's user and password (DOMAIN%5Cuser:password@host) or else ~/.netrc with -n. The \
+         handshake keeps one HTTP/1.1 connection open; a request body is only sent once it has \
+         succeeded",
+    ),
+    (
+        "negotiate",
+        "Like --ntlm, but under the 'Negotiate' scheme. Only NTLM tokens are sent; Kerberos is \
+         not supported",
+    ),
+    (
+        "netrc",
+        "Send Basic auth with the login and password that ~/.netrc has for the host, unless \
+         the URL has a password or -H sets Authorization",
+    ),
+    (
+        "proxy",
+        "Send requests through this HTTP proxy (port 1080 unless given), e.g. -x \
+         user:password@proxy.example:3128",
+    ),
+    (
+        "proxy-user",
+        "Credentials for the proxy, instead of the ones in -x. They are sent with Basic auth, \
+         and a 407 asking for Basic or Digest is answered with them by sending the request \
+         again",
+    ),
+    (
+        "proxy-basic",
+        "Send the proxy credentials with Basic auth (the default)",
+    ),
+    (
+        "proxy-digest",
+        "Do not send the proxy credentials with Basic auth; only answer the proxy's 407 Digest \
+         challenge with them",
+    ),
+    (
+        "proxy-header",
+        "Extra header for requests sent through the proxy, like -H; it is not sent without -x",
+    ),
+    ("netrc-file", "Like -n, but read <file>, which must exist"),
+    (
+        "session",
+        "Send the headers, credentials and cookies saved in session <name>, and save the ones \
+         this request adds. A name is kept in ~/.config/http_client/sessions/<name>.json; a \
+         name with a / or ending in .json is a file path. Headers that only make sense for one \
+         request, such as Content-Type or If-*, are not kept; -H 'Name:' removes one from the \
+         session",
+    ),
+    (
+        "session-read-only",
+        "Like --session, but leave the session as it was",
+    ),
+    (
+        "retry",
+        "Send the transfer again, up to <n> times, when it fails with a network error, a \
+         timeout or a 408, 429, 500, 502, 503 or 504 status. The wait is the server's \
+         Retry-After (in seconds or as a date) if it sent one, else 1 second and doubling",
+    ),
+    (
+        "retry-delay",
+        "Wait this long between retries instead of doubling; a Retry-After still takes \
+         precedence",
+    ),
+    (
+        "location",
+        "Follow redirects. Authorization and Cookie headers, bearer tokens and AWS signatures \
+         are only sent to the host the transfer started at",
+    ),
+    (
+        "location-trusted",
+        "Like -L, but send them to every host redirected to",
+    ),
+    (
+        "show-redirects",
+        "Print each redirect followed, with its status and how long it took, to stderr after \
+         the transfer",
+    ),
+    (
+        "max-redirs",
+        "Maximum number of redirects to follow (default 50)",
+    ),
+    ("data", "Request body"),
+    (
+        "compress-request",
+        "Gzip the -T or -d body and send it with Content-Encoding: gzip, for APIs that take \
+         compressed uploads. -d data is then sent once, the way -T sends a file, and a file is \
+         sent chunked",
+    ),
+    (
+        "upload-file",
+        "Send <file> as the body, with PUT unless -X says otherwise, streaming it from disk \
+         in chunks so files of any size fit; - reads stdin and sends it chunked. A URL ending \
+         in / gets the file's name appended. Progress is shown on a terminal",
+    ),
+    (
+        "output",
+        "Write the output to <file> instead of stdout; - is stdout, even for a binary body \
+         on a terminal",
+    ),
+    (
+        "expected-hash",
+        "Fail with exit code 101, writing no output, unless the body's sha256, sha1 or md5 \
+         digest is <hex>. @<file> reads the digest from a sidecar such as foo.tar.gz.sha256, \
+         in sha256sum format; write <algorithm>:@<file> when its name does not say which",
+    ),
+    (
+        "jq",
+        "Print only the values a jq-style path picks from the JSON body, one per line, strings \
+         without quotes: . for all of it, .name or .[\"name\"] for a member, .[0] for \
+         an element (.[-1] the last) and .[] for each, as in .items[].id. A JSON Lines body \
+         (application/x-ndjson) on stdout is shown a record at a time as it arrives, each \
+         record filtered alone",
+    ),
+    (
+        "list-links",
+        "Print the http and https links of an HTML page (anchors, frames, images, scripts, \
+         stylesheets, media) instead of the page, resolved against its final URL or <base \
+         href>, once each, one per line: a list --url-file can read",
+    ),
+    (
+        "pretty",
+        "Indent JSON bodies, JSON Lines records and --jq results; anything else is printed as \
+         it is",
+    ),
+    (
+        "tee",
+        "Also write the output to <file>, as it was received, while it still goes to stdout \
+         (or -o or -O)",
+    ),
+    (
+        "dump-header",
+        "Write the status line and headers of every response to <file>, redirects included, \
+         with repeated headers such as Set-Cookie kept as sent, and any trailers after them; - \
+         is stdout",
+    ),
+    (
+        "output-dir",
+        "Put output files given by relative names, from -o or -O or a --url-file template, \
+         in <dir>",
+    ),
+    (
+        "create-dirs",
+        "Create any missing directories an output file needs",
+    ),
+    (
+        "no-transcode",
+        "On a terminal, print text in the charset the server sent it in rather than converting \
+         it to UTF-8 by its Content-Type charset or byte order mark",
+    ),
+    (
+        "remote-name",
+        "Write the output to a file in the current directory named after the last segment of \
+         the URL's path",
+    ),
+    (
+        "remote-header-name",
+        "With -O, use the file name from the response's Content-Disposition header instead, \
+         without directories, and refuse to overwrite an existing file",
+    ),
+    (
+        "write-out",
+        "Write <format> to stdout after the transfer, with variables such as %{http_code}, \
+         %{url_effective}, %{num_redirects}, %{redirect_url}, %{size_download} and \
+         %{time_total} filled in and \\n for a newline",
+    ),
+    (
+        "compressed",
+        "Ask for a compressed response with Accept-Encoding and decompress the body: gzip and \
+         deflate, and br and zstd in builds with the brotli and zstd features. The body is \
+         then read into memory before it is written",
+    ),
+    (
+        "max-filesize",
+        "Fail if the response body is larger than <bytes>; k, M and G suffixes are accepted",
+    ),
+    (
+        "limit-rate",
+        "Receive at most <speed> bytes/sec, with k, M or G for binary multiples; with \
+         --parallel, --check-links or --mirror the cap is for all transfers together",
+    ),
+    (
+        "speed-limit",
+        "Abort if the transfer stays below <speed> bytes/sec for --speed-time seconds (default \
+         1)",
+    ),
+    (
+        "speed-time",
+        "The time allowed below --speed-limit (default 30)",
+    ),
+    (
+        "tcp-nodelay",
+        "Send small writes at once rather than coalescing them (the default)",
+    ),
+    (
+        "no-tcp-nodelay",
+        "Let the kernel coalesce small writes (Nagle's algorithm)",
+    ),
+    (
+        "keepalive-time",
+        "Send TCP keepalive probes once a connection has been idle for <seconds>, to keep \
+         long-lived streams open through NATs and firewalls",
+    ),
+    (
+        "sndbuf",
+        "Set the socket's send buffer size; k, M and G suffixes are accepted",
+    ),
+    (
+        "rcvbuf",
+        "Set the socket's receive buffer size; k, M and G suffixes are accepted",
+    ),
+    (
+        "local-port",
+        "Connect from the first free local port in the range, for firewalls that only let some \
+         source ports through",
+    ),
+    (
+        "url-query",
+        "Append a URL-encoded query parameter (repeatable); a leading '+' sends the pair as-is",
+    ),
+    ("no-idn", "Do not convert Unicode host names to punycode"),
+    (
+        "proto-default",
+        "The scheme for a URL given without one, such as example.com/x: http (the default), \
+         https, ws, wss or file",
+    ),
+    (
+        "include",
+        "Include the response status line and headers in the output, and any trailers after \
+         the body",
+    ),
+    ("verbose", "Print request and response headers to stderr"),
+    (
+        "timings",
+        "Print how long each phase of the transfer took to stderr, followed by the metrics of \
+         any Server-Timing header or trailer",
+    ),
+    ("http1.1", "Use HTTP/1.1 (the default)"),
+    (
+        "http2",
+        "Ask the server to upgrade the connection to HTTP/2 (h2c) and fall back to HTTP/1.1 if \
+         it declines; requests with a body stay on HTTP/1.1",
+    ),
+    (
+        "http2-prior-knowledge",
+        "Speak HTTP/2 from the start, for servers known to support it",
+    ),
+    (
+        "http3",
+        "Not implemented: there is no QUIC transport, so no request is ever made over HTTP/3. \
+         This only warns and sends the request over HTTP/1.1",
+    ),
+    (
+        "http3-only",
+        "Not implemented: always fails with an unsupported-protocol error, as there is no \
+         HTTP/3",
+    ),
+    (
+        "strict",
+        "Fail on response heads that bend HTTP/1.1's grammar, such as lines ending in a bare \
+         LF, a status line without a reason phrase separator, or both Transfer-Encoding and \
+         Content-Length, instead of accepting them as curl does. Folded header lines are \
+         unfolded either way",
+    ),
+    (
+        "sse",
+        "Read a Server-Sent Events stream, printing each event as it arrives and reconnecting \
+         with Last-Event-ID when the connection drops",
+    ),
+    (
+        "expect-status",
+        "Exit with code 22 unless the status is <code> or in a class such as 2xx (repeatable: \
+         any may match)",
+    ),
+    (
+        "expect-header",
+        "Exit with code 22 unless the response has the header; with 'Name: value' its value \
+         must contain value, ignoring case",
+    ),
+    (
+        "expect-body-contains",
+        "Exit with code 22 unless the response body contains <text>",
+    ),
+    (
+        "max-time-warn",
+        "Warn on stderr when the transfer takes longer than <ms> milliseconds",
+    ),
+    (
+        "max-time-fail",
+        "Exit with code 102 when the transfer takes longer than <ms> milliseconds",
+    ),
+    (
+        "max-size-warn",
+        "Warn on stderr when the response body is bigger than <size> bytes (k, M and G \
+         suffixes allowed)",
+    ),
+    (
+        "max-size-fail",
+        "Exit with code 102 when the response body is bigger than <size> bytes",
+    ),
+    (
+        "env",
+        "Read variables for {{name}} placeholders from a JSON object in <file>; nested objects \
+         define dotted names",
+    ),
+    (
+        "http-file",
+        "Instead of a URL, run the requests in a REST Client style .http file ('-' for stdin) \
+         in order, separated by '###' lines, and write each response",
+    ),
+    (
+        "bench",
+        "Send the request --requests times over --concurrency keep-alive connections and \
+         report throughput, status codes, errors and latency percentiles instead of the \
+         response",
+    ),
+    ("requests", "Number of requests with --bench (default 100)"),
+    (
+        "concurrency",
+        "Number of parallel connections with --bench (default 10)",
+    ),
+    (
+        "cache-dir",
+        "Keep GET responses in <dir> and reuse them while Cache-Control, Expires or \
+         Last-Modified say they are fresh; stale ones are revalidated with ETag or \
+         Last-Modified",
+    ),
+    ("no-cache", "Neither use nor update --cache-dir"),
+    ("refresh", "Ignore cached responses, but store the new ones"),
+    (
+        "record",
+        "Save each request and its response to the cassette <file>, adding to the ones already \
+         there",
+    ),
+    (
+        "replay",
+        "Answer requests with the responses recorded in <file> instead of sending them, \
+         matching on method, URL and body; a request that was not recorded fails",
+    ),
+    (
+        "hsts",
+        "Remember hosts that send Strict-Transport-Security over HTTPS in <file> (curl's \
+         format) and send plain http:// requests to them over HTTPS instead",
+    ),
+    (
+        "https-only",
+        "Send http:// URLs, redirects and --request-file included, over HTTPS instead and ws:// \
+         over wss://, failing if the server does not speak TLS; other plain-text URLs are refused",
+    ),
+    (
+        "alt-svc",
+        "Remember the alternative services servers advertise with Alt-Svc in <file> (curl's \
+         format) and use them for later requests to the same origin: h2c means HTTP/2 without \
+         an upgrade, http/1.1 another host or port. h2 and h3 need TLS or QUIC and are only \
+         remembered. An alternative that cannot be reached is dropped and the origin is used",
+    ),
+    ("no-alt-svc", "Do not use --alt-svc"),
+    (
+        "url-file",
+        "Fetch every URL listed in <file> ('-' for stdin), one per line, after the URL \
+         given on the command line if any. A result line per URL goes to stderr; -o may \
+         contain #{index}, #{host}, #{path}, #{name} (the last path segment) and #1, #2, ... \
+         (what the URL's glob patterns stood for) to give each response its own file",
+    ),
+    (
+        "fail-early",
+        "Stop a batch of URLs at the first transfer that fails; by default every URL is tried, \
+         and a batch of several ends with a table of the transfers on stderr",
+    ),
+    (
+        "fail-at-end",
+        "With --next, run every transfer even after one fails, and fail at the end with the \
+         first error",
+    ),
+    (
+        "globoff",
+        "Take { } and [ ] in URLs literally. Otherwise a URL may be a glob: {a,b} for each \
+         alternative, [1-10], [01-10] or [a-z] for each item of a range ([1-9:2] every other), \
+         and all combinations are fetched as with --url-file",
+    ),
+    (
+        "no-clobber",
+        "Do not overwrite output files: write file.1, file.2 and so on (up to 100) beside one \
+         that exists",
+    ),
+    (
+        "parallel",
+        "With --url-file, run several transfers at once; output stays in list order",
+    ),
+    (
+        "check-links",
+        "Fetch the page at the URL and check every link on it (see --list-links) with a HEAD \
+         request, or a GET where HEAD is refused; list the ones that fail or answer 4xx or \
+         5xx, and exit with code 22 if there are any. Also run as http_client check-links <url>",
+    ),
+    (
+        "same-origin",
+        "With --check-links, only check links on the page's origin",
+    ),
+    (
+        "mirror",
+        "Save the page at the URL and, like wget --mirror, every page and file it links to \
+         on the same host, level by level, as <host>/<path> under --output-dir (index.html for \
+         a path ending in /). Redirects are followed as links; up to --parallel-max transfers \
+         run at once, through --cache-dir if given",
+    ),
+    (
+        "level",
+        "With --mirror, follow links at most <n> away from the first page (default 5)",
+    ),
+    (
+        "span-hosts",
+        "With --mirror, follow links to other hosts too",
+    ),
+    (
+        "ignore-robots",
+        "With --check-links or --mirror, do not fetch and obey each site's robots.txt, which \
+         otherwise keeps them from the URLs it disallows (the URL given is always fetched)",
+    ),
+    (
+        "crawl-delay",
+        "With --check-links or --mirror, wait <time> (such as 1s or 250ms) between requests to \
+         a site, or the Crawl-delay of its robots.txt if that is longer",
+    ),
+    (
+        "parallel-max",
+        "Number of transfers at once with --parallel or --check-links (default 50)",
+    ),
+    (
+        "watch",
+        "Send the request again every <interval> (such as 5s, 500ms or 2m) and print a \
+         timestamped line with the status, latency and size of each response instead of the \
+         body; -o receives the latest body. Network errors are printed and retried",
+    ),
+    (
+        "until-status",
+        "With --watch, stop at the first status that matches, such as 200 or 2xx; running out \
+         of --max-iterations first exits with code 22",
+    ),
+    ("max-iterations", "With --watch, stop after <n> requests"),
+    (
+        "metrics-listen",
+        "With --bench or --watch, serve request counts, error counts and a latency histogram \
+         in Prometheus format on this address while the run lasts; :9090 listens on every \
+         address",
+    ),
+    (
+        "metrics-file",
+        "With --bench or --watch, write the same metrics to <file> when the run ends and after \
+         each --watch request, e.g. for node_exporter's textfile collector",
+    ),
+    (
+        "interactive",
+        "Read commands from stdin, one per line, keeping connections, cookies and headers \
+         between requests; type 'help' at the prompt for the commands. There is no tab \
+         completion of its own (see --completions rlwrap)",
+    ),
+    (
+        "meta-json",
+        "After each transfer append a line of JSON to <file> ('-' for stderr) with the final \
+         URL, status, headers, timings (namelookup, connect, starttransfer, total), sizes, \
+         local and remote addresses, and the error and exit code if it failed",
+    ),
+    (
+        "trace",
+        "Write a hex and ASCII dump of all traffic to <file> ('-' for stdout)",
+    ),
+    ("trace-ascii", "Like --trace, but without the hex columns"),
+    (
+        "completions",
+        "Print a completion script for bash, zsh, fish or powershell, or with rlwrap the \
+         words --interactive understands",
+    ),
+    (
+        "lang",
+        "Message language: en or zh (default from LC_ALL, LC_MESSAGES or LANG)",
+    ),
+    (
+        "help",
+        "Show this help, or only the options about <topic>: request, auth, proxy, redirects, \
+         output, connection, protocols, tls, checks, cache, batch, load, debug or general",
+    ),
+    (
+        "disable",
+        "Do not read the curl config file ($CURL_HOME/.curlrc, $XDG_CONFIG_HOME/curlrc or \
+         ~/.curlrc); only works as the first option",
+    ),
+    (
+        "next",
+        "Start the options of another transfer: the options before it apply to the URL before \
+         it only. The transfers run in turn, reusing connections where they can, and stop at \
+         the first that fails unless --fail-at-end is given",
+    ),
+    ("examples", "Show examples of common invocations"),
+    (
+        "version",
+        "Show the version, the TLS backend, and the protocols and features this build has; \
+         include it in bug reports",
+    ),
+];
+
+const USAGE_NOTES_ZH: &str = "\
+选项可以重复;只接受单个值的选项以最后一次为准。
+使用 -- 表示选项结束。curl 配置文件中的选项(每行一个,如
+'header = \"X-Name: value\"')排在命令行选项之前;http_client 没有的选项
+会被跳过并给出警告。
+
+URL、-H 的值和 -d 数据中的 {{name}} 会替换为同名变量:使用 --http-file
+时先取文件中的 '@name = value' 行,然后是 --env,最后是环境变量。未定义的
+名称会报错;字面量 {{ 写作 \\{{。在 .http 文件中,请求前的 '# @name login'
+行让后续请求可以引用其响应:{{login.response.headers.Name}}、
+{{login.response.body}},或 {{login.response.body.$.token}} 这样的 JSONPath。
+
+file:// URL 读取本地文件,并像下载内容一样输出。
+使用 ws:// 或 wss:// URL 时,从标准输入读取的每一行作为文本消息发送,收到的
+每条消息写入输出,直到任一方关闭连接。";
+
+const OPTION_HELP_ZH: &[(&str, &str)] = &[
+    ("url", "未在参数中给出 URL 时要获取的 URL;主要用于 .curlrc 中的 url 行"),
+    (
+        "request",
+        "请求方法(默认 GET,使用 -d 时为 POST);任何方法名都按原样发送。未使用 -i 时,没有响应体\
+         的 OPTIONS 响应会输出其 Allow 头",
+    ),
+    (
+        "dry-run",
+        "不建立连接,只输出将要发送的完整请求(包括所有默认值、Cookie、凭据和签名),并在标准错误中\
+         输出发送目标",
+    ),
+    (
+        "export",
+        "不发送请求,而是将其输出为 curl 命令(curl)或使用 Python requests(python)、Rust \
+         reqwest(rust)的程序",
+    ),
+    (
+        "request-target",
+        "在请求行中发送 <target> 而不是 URL 的路径:*(如 OPTIONS * HTTP/1.1)、其他路径,或用于测\
+         试代理的绝对 URL。连接目标仍由 URL 决定;重定向使用各自的目标",
+    ),
+    (
+        "request-file",
+        "将 <file>(- 表示标准输入)的内容逐字节作为请求发送,用于重放抓取的流量或测试特殊的报文格\
+         式。给出 URL 时连接其主机和端口,否则连接文件中 Host 头指定的主机的 80 端口(使用 --https-only \
+         时为 443 端口)",
+    ),
+    (
+        "fix-request",
+        "与 --request-file 一起使用时,先将请求头的换行统一为 CRLF,设置 Host,并按请求体重新计算 \
+         Content-Length(分块传输时除外)",
+    ),
+    (
+        "script",
+        "运行钩子脚本 <file>:其中 'on request' 部分的语句可以设置和删除请求头、修改每个请求的方\
+         法或请求体(例如添加签名),'on response' 部分的语句检查最终响应。脚本不是 Rhai 或 Lua(它们\
+         需要本客户端不使用的 crate),而是本客户端自己的一种按行书写的小语言。例\
+         如:\n  on request\n    header X-Sig = hmac_sha256(env(\"KEY\"), method + \
+         path)\n  on response\n    assert status == \"200\"\n    assert json(\"$.ok\") == \
+         \"true\"\n语句每行一条:\n  let NAME = EXPR\n  header NAME = EXPR\n  remove header \
+         NAME\n  method = EXPR、body = EXPR(仅用于请求)\n  assert EXPR [OP EXPR],OP 为 == != \
+         contains < > 之一\n  print EXPR(输出到标准错误)\nEXPR 用 + 连接 \"字符串\"、名称和函数调\
+         用。名称:method、url、host、path、body,响应中还有 status 和 reason。函数:header、env、\
+         now、sha256、sha1、md5、hmac_sha256、hmac_sha256_base64、base64、url_encode、lower、upper、\
+         trim、len,响应中还有 json(\"$.path\")。所有值都是字符串。断言失败时以退出码 22 结束",
+    ),
+    (
+        "header",
+        "附加请求头,例如 -H 'Accept: text/plain'。与内置请求头同名时替换之;'Name:' 删除该请求\
+         头,'Name;' 发送空值",
+    ),
+    ("user-agent", "要发送的 User-Agent(为空则不发送)"),
+    ("referer", "要发送的 Referer;末尾加 ';auto' 时重定向也会设置"),
+    (
+        "oauth2-bearer",
+        "发送 'Authorization: Bearer <token>'。'@file' 从文件读取令牌('@-' 为标准输\
+         入),'{{NAME}}' 从环境变量读取,令牌不会留在 shell 历史中。-v 将凭据显示为 ***",
+    ),
+    (
+        "aws-sigv4",
+        "使用 AWS Signature Version 4 对请求签名,例如 'aws:amz:us-east-1:s3';省略的区域和服务取\
+         自形如 s3.us-east-1.amazonaws.com 的主机名。密钥依次取自 URL (KEY:SECRET@host)、环境变\
+         量 AWS_ACCESS_KEY_ID、AWS_SECRET_ACCESS_KEY 和 AWS_SESSION_TOKEN,以及 \
+         ~/.aws/credentials 中 AWS_PROFILE(或 default)指定的配置",
+    ),
+    (
+        "ntlm",
+        "使用 NTLMv2 认证(IIS 和 Windows 内网服务器常用),凭据取自 URL 中的用户名和密\
+         码(DOMAIN%5Cuser:password@host),或配合 -n 取自 ~/.netrc。握手期间保持同一个 HTTP/1.1 \
+         连接;请求体在握手成功后才发送",
+    ),
+    ("negotiate", "与 --ntlm 相同,但使用 'Negotiate' 方案。只发送 NTLM 令牌,不支持 Kerberos"),
+    (
+        "netrc",
+        "使用 ~/.netrc 中该主机的登录名和密码发送 Basic 认证,除非 URL 中含有密码或 -H 设置了 \
+         Authorization",
+    ),
+    ("proxy", "通过此 HTTP 代理发送请求(未指定端口时为 1080),例如 -x user:password@proxy.example:3128"),
+    (
+        "proxy-user",
+        "代理的凭据,代替 -x 中的凭据。凭据以 Basic 认证发送;代理以 407 要求 Basic 或 Digest 认\
+         证时,用凭据应答并重新发送请求",
+    ),
+    ("proxy-basic", "使用 Basic 认证发送代理凭据(默认)"),
+    ("proxy-digest", "不以 Basic 认证发送代理凭据,只用它们应答代理的 407 Digest 质询"),
+    ("proxy-header", "经代理发送的请求的额外请求头,用法同 -H;没有 -x 时不发送"),
+    ("netrc-file", "与 -n 相同,但读取 <file>,该文件必须存在"),
+    (
+        "session",
+        "发送会话 <name> 中保存的请求头、凭据和 Cookie,并保存本次请求新增的内容。会话保存在 \
+         ~/.config/http_client/sessions/<name>.json;包含 / 或以 .json 结尾的名称视为文件路径。\
+         只对单个请求有意义的请求头(如 Content-Type、If-*)不会保存;-H 'Name:' 将其从会话中删除",
+    ),
+    ("session-read-only", "与 --session 相同,但不修改会话"),
+    (
+        "retry",
+        "传输因网络错误、超时或 408、429、500、502、503、504 状态码失败时最多重新发送 <n> 次。\
+         服务器发送了 Retry-After(秒数或日期)时按其等待,否则从 1 秒开始逐次加倍",
+    ),
+    ("retry-delay", "重试之间固定等待这么久而不是加倍;Retry-After 仍然优先"),
+    (
+        "location",
+        "跟随重定向。Authorization 和 Cookie 请求头、bearer 令牌以及 AWS 签名只发送给最初请求的\
+         主机",
+    ),
+    ("location-trusted", "与 -L 相同,但也发送给重定向到的所有主机"),
+    ("show-redirects", "传输结束后在标准错误输出中列出跟随的每次重定向及其状态码和耗时"),
+    ("max-redirs", "最多跟随的重定向次数(默认 50)"),
+    ("data", "请求体"),
+    (
+        "compress-request",
+        "用 gzip 压缩 -T 或 -d 的请求体,并带 Content-Encoding: gzip 发送,适用于接受压缩上传的 \
+         API。此时 -d 数据像 -T 的文件一样只发送一次,文件则以分块编码发送",
+    ),
+    (
+        "upload-file",
+        "将 <file> 作为请求体发送,除非 -X 另有指定否则使用 PUT;从磁盘分块流式读取,任意大小的文\
+         件都可以发送;- 读取标准输入并以分块编码发送。以 / 结尾的 URL 会追加文件名。终端上显示\
+         上传进度",
+    ),
+    ("output", "将输出写入 <file> 而不是标准输出;- 表示标准输出,即使终端上的响应体是二进制内容"),
+    (
+        "expected-hash",
+        "响应体的 sha256、sha1 或 md5 摘要不是 <hex> 时以退出码 101 失败,且不写出任何输\
+         出。@<file> 从 sha256sum 格式的校验文件(如 foo.tar.gz.sha256)中读取摘要;文件名看不出算\
+         法时写作 <algorithm>:@<file>",
+    ),
+    (
+        "jq",
+        "只输出 jq 风格路径从 JSON 响应体中选出的值,每行一个,字符串不带引号:. 表示全部,.name \
+         或 .[\"name\"] 表示成员,.[0] 表示元素(.[-1] 为最后一个),.[] 表示每一个,如 \
+         .items[].id。输出到标准输出的 JSON Lines 响应体(application/x-ndjson)会随到达逐条显\
+         示,每条记录单独过滤",
+    ),
+    (
+        "list-links",
+        "不输出 HTML 页面本身,而是输出其中的 http 和 https 链接(超链接、框架、图片、脚本、样式\
+         表、媒体),按最终 URL 或 <base href> 解析为绝对地址,去重后每行一个,可直接交给 \
+         --url-file 读取",
+    ),
+    ("pretty", "缩进显示 JSON 响应体、JSON Lines 记录和 --jq 结果;其他内容原样输出"),
+    ("tee", "同时将原样收到的输出写入 <file>,输出仍照常写入标准输出(或 -o、-O)"),
+    (
+        "dump-header",
+        "将每个响应(包括重定向)的状态行和响应头写入 <file>, Set-Cookie 等重复的响应头按收到的原\
+         样保留,其后附上尾部字段; - 表示标准输出",
+    ),
+    ("output-dir", "将以相对路径指定的输出文件(来自 -o、-O 或 --url-file 模板)放在 <dir> 中"),
+    ("create-dirs", "自动创建输出文件所需但不存在的目录"),
+    (
+        "no-transcode",
+        "在终端上按服务器发送的字符集原样输出文本,不根据 Content-Type 的 charset 或字节顺序标记\
+         转换为 UTF-8",
+    ),
+    ("remote-name", "将输出写入当前目录下以 URL 路径最后一段命名的文件"),
+    (
+        "remote-header-name",
+        "与 -O 一起使用时改用响应 Content-Disposition 头中的文件名(去掉目录部分),且不覆盖已有文\
+         件",
+    ),
+    (
+        "write-out",
+        "传输结束后将 <format> 写入标准输出,其中 \
+         %{http_code}、%{url_effective}、%{num_redirects}、%{redirect_url}、%{size_download}、%{time_total} 等变量会被替换,\\n 表示换行",
+    ),
+    (
+        "compressed",
+        "用 Accept-Encoding 请求压缩的响应并解压响应体:支持 gzip 和 deflate,启用 brotli 和 \
+         zstd 特性构建时还支持 br 和 zstd。此时响应体会先读入内存再写出",
+    ),
+    ("max-filesize", "响应体超过 <bytes> 时失败;可使用 k、M、G 后缀"),
+    (
+        "limit-rate",
+        "每秒最多接收 <speed> 字节,可用 k、M 或 G(二进制倍数);与 --parallel、--check-links 或 \
+         --mirror 一起使用时为所有传输合计的上限",
+    ),
+    ("speed-limit", "传输速度低于每秒 <speed> 字节持续 --speed-time 秒时中止(默认 1)"),
+    ("speed-time", "允许低于 --speed-limit 的时长(默认 30 秒)"),
+    ("tcp-nodelay", "小块数据立即发送而不合并(默认)"),
+    ("no-tcp-nodelay", "允许内核合并小块数据(Nagle 算法)"),
+    ("keepalive-time", "连接空闲 <seconds> 秒后发送 TCP keepalive 探测,使长连接在 NAT 和防火墙后保持打开"),
+    ("sndbuf", "设置套接字的发送缓冲区大小;可使用 k、M、G 后缀"),
+    ("rcvbuf", "设置套接字的接收缓冲区大小;可使用 k、M、G 后缀"),
+    ("local-port", "从该范围内第一个空闲的本地端口发起连接,用于只放行特定源端口的防火墙"),
+    ("url-query", "追加一个 URL 编码的查询参数(可重复);以 '+' 开头时原样发送"),
+    ("no-idn", "不将 Unicode 主机名转换为 punycode"),
+    ("proto-default", "没有协议的 URL(例如 example.com/x)使用的协议:http(默认)、https、ws、wss 或 file"),
+    ("include", "在输出中包含响应状态行和响应头,响应体之后附上尾部字段"),
+    ("verbose", "将请求头和响应头打印到标准错误"),
+    ("timings", "在标准错误输出中打印传输各阶段的耗时,以及 Server-Timing 响应头或尾部字段中的服务器指标"),
+    ("http1.1", "使用 HTTP/1.1(默认)"),
+    (
+        "http2",
+        "请求服务器将连接升级为 HTTP/2(h2c),服务器拒绝时继续使用 HTTP/1.1;带请求体的请求始终使\
+         用 HTTP/1.1",
+    ),
+    ("http2-prior-knowledge", "直接使用 HTTP/2,适用于已知支持它的服务器"),
+    (
+        "http3",
+        "未实现:没有 QUIC 传输,任何请求都不会通过 HTTP/3 发送。此选项只给出警告并用 HTTP/1.1 发送请\
+         求",
+    ),
+    ("http3-only", "未实现:由于没有 HTTP/3,总是以不支持的协议错误失败"),
+    (
+        "strict",
+        "响应头不符合 HTTP/1.1 语法时(例如行尾只有 LF、状态行缺少原因短语前的空格,或同时带有 \
+         Transfer-Encoding 和 Content-Length)直接失败,而不是像 curl 那样接受。折行的响应头无论\
+         如何都会被合并",
+    ),
+    ("sse", "读取 Server-Sent Events 流,逐个打印收到的事件,连接断开时携带 Last-Event-ID 重新连接"),
+    ("expect-status", "状态码不是 <code> 或不属于 2xx 这类范围时以代码 22 退出(可重复,满足其一即可)"),
+    ("expect-header", "响应缺少该响应头时以代码 22 退出;使用 'Name: value' 时要求其值包含 value(不区分大小写)"),
+    ("expect-body-contains", "响应体不包含 <text> 时以代码 22 退出"),
+    ("max-time-warn", "传输耗时超过 <ms> 毫秒时在标准错误输出警告"),
+    ("max-time-fail", "传输耗时超过 <ms> 毫秒时以代码 102 退出"),
+    ("max-size-warn", "响应体超过 <size> 字节时在标准错误输出警告(可使用 k、M、G 后缀)"),
+    ("max-size-fail", "响应体超过 <size> 字节时以代码 102 退出"),
+    ("env", "从 <file> 中的 JSON 对象读取 {{name}} 占位符的变量;嵌套对象定义以点分隔的名称"),
+    (
+        "http-file",
+        "不使用 URL,而是依次执行 REST Client 风格的 .http 文件('-' 为标准输入)中以 '###' 分隔的\
+         请求,并输出每个响应",
+    ),
+    (
+        "bench",
+        "通过 --concurrency 个长连接发送 --requests 次请求,输出吞吐量、状态码、错误和延迟百分位\
+         数,而不是响应内容",
+    ),
+    ("requests", "--bench 发送的请求数(默认 100)"),
+    ("concurrency", "--bench 使用的并行连接数(默认 10)"),
+    (
+        "cache-dir",
+        "将 GET 响应保存在 <dir> 中,在 Cache-Control、Expires 或 Last-Modified 表明仍然新鲜时直\
+         接复用;过期的响应通过 ETag 或 Last-Modified 重新验证",
+    ),
+    ("no-cache", "不使用也不更新 --cache-dir"),
+    ("refresh", "忽略已缓存的响应,但保存新的响应"),
+    ("record", "将每个请求及其响应保存到录制文件 <file> 中,追加在已有记录之后"),
+    ("replay", "使用 <file> 中录制的响应回答请求而不发送,按方法、URL 和请求体匹配;未录制的请求将失败"),
+    (
+        "hsts",
+        "将通过 HTTPS 发送 Strict-Transport-Security 的主机记录在 <file> (curl 的格式)中,并将发\
+         往这些主机的 http:// 请求改用 HTTPS",
+    ),
+    (
+        "https-only",
+        "将 http:// URL(包括重定向和 --request-file)改用 HTTPS 发送,ws:// 改用 wss://,服务器不支持 \
+         TLS 时失败;拒绝其他明文 URL",
+    ),
+    (
+        "alt-svc",
+        "将服务器通过 Alt-Svc 公布的备用服务记录在 <file>(curl 的格式)中,并用于之后对同一源的请\
+         求:h2c 表示不经升级直接使用 HTTP/2, http/1.1 表示另一个主机或端口。h2 和 h3 需要 TLS \
+         或 QUIC,只会被记录。无法连接的备用服务会被删除并改用源服务器",
+    ),
+    ("no-alt-svc", "不使用 --alt-svc"),
+    (
+        "url-file",
+        "依次获取 <file>('-' 为标准输入)中每行列出的 URL,命令行给出的 URL 排在最前。每个 URL 的\
+         结果行写入标准错误;-o 中可以使用 #{index}、#{host}、#{path}、#{name}(路径的最后一\
+         段)和 #1、#2 等(URL 中通配模式所代表的内容)为每个响应指定单独的文件",
+    ),
+    (
+        "fail-early",
+        "批量获取时在第一个失败的传输处停止;默认会尝试每个 URL,多个 URL 结束后在标准错误输出传\
+         输汇总表",
+    ),
+    ("fail-at-end", "与 --next 一起使用时即使有传输失败也运行所有传输,最后以第一个错误失败"),
+    (
+        "globoff",
+        "按字面处理 URL 中的 { } 和 [ ]。否则 URL 可以是通配模式:{a,b} 表示每个备选\
+         项,[1-10]、[01-10] 或 [a-z] 表示范围中的每一项([1-9:2] 为每隔一项),所有组合都会像 \
+         --url-file 一样获取",
+    ),
+    ("no-clobber", "不覆盖输出文件:文件已存在时改写 file.1、file.2 等(最多 100 个)"),
+    ("parallel", "与 --url-file 一起使用时同时进行多个传输;输出仍按列表顺序"),
+    (
+        "check-links",
+        "获取该 URL 的页面,用 HEAD 请求(不接受 HEAD 时改用 GET)检查其中的每个链接(见 \
+         --list-links),列出失败或返回 4xx、5xx 的链接,有则以代码 22 退出。也可写作 http_client \
+         check-links <url>",
+    ),
+    ("same-origin", "与 --check-links 一起使用时只检查与页面同源的链接"),
+    (
+        "mirror",
+        "像 wget --mirror 一样保存该 URL 的页面,并逐层保存其链接到的同一主机上的每个页面和文\
+         件,存为 --output-dir 下的 <host>/<path>(以 / 结尾的路径存为 index.html)。重定向按链接\
+         跟随;最多同时进行 --parallel-max 个传输,给出 --cache-dir 时经由缓存",
+    ),
+    ("level", "与 --mirror 一起使用时,最多跟随距第一个页面 <n> 层的链接(默认 5)"),
+    ("span-hosts", "与 --mirror 一起使用时也跟随指向其他主机的链接"),
+    (
+        "ignore-robots",
+        "与 --check-links 或 --mirror 一起使用时不获取也不遵守各站点的 robots.txt;否则不会访问\
+         其禁止的 URL(给出的 URL 总会获取)",
+    ),
+    (
+        "crawl-delay",
+        "与 --check-links 或 --mirror 一起使用时,对同一站点的请求之间等待 <time>(如 1s 或 \
+         250ms);robots.txt 的 Crawl-delay 更长时以其为准",
+    ),
+    ("parallel-max", "--parallel 或 --check-links 同时进行的传输数(默认 50)"),
+    (
+        "watch",
+        "每隔 <interval>(如 5s、500ms 或 2m)重新发送请求,每次输出一行带时间戳的状态、延迟和大\
+         小,而不是响应体;-o 保存最新的响应体。网络错误会输出并重试",
+    ),
+    (
+        "until-status",
+        "与 --watch 一起使用,状态码匹配(如 200 或 2xx)时停止;先用完 --max-iterations 则以代码 \
+         22 退出",
+    ),
+    ("max-iterations", "与 --watch 一起使用,发送 <n> 次请求后停止"),
+    (
+        "metrics-listen",
+        "与 --bench 或 --watch 一起使用,运行期间在此地址以 Prometheus 格式提供请求数、错误数和\
+         延迟直方图;:9090 表示监听所有地址",
+    ),
+    (
+        "metrics-file",
+        "与 --bench 或 --watch 一起使用,在运行结束时以及每次 --watch 请求后将同样的指标写入 \
+         <file>,例如供 node_exporter 的 textfile 收集器使用",
+    ),
+    (
+        "interactive",
+        "从标准输入逐行读取命令,在请求之间保留连接、Cookie 和请求头;在提示符下输入 'help' 查看\
+         命令。本身不支持 Tab 补全(见 --completions rlwrap)",
+    ),
+    (
+        "meta-json",
+        "每次传输后向 <file>('-' 为标准错误)追加一行 JSON,包含最终 URL、状态码、响应头、各阶段\
+         耗时(namelookup、connect、starttransfer、total)、大小、本地和远端地址,失败时还有错误信\
+         息和退出码",
+    ),
+    ("trace", "将全部流量以十六进制和 ASCII 形式写入 <file>('-' 为标准输出)"),
+    ("trace-ascii", "与 --trace 相同,但不含十六进制列"),
+    (
+        "completions",
+        "输出 bash、zsh、fish 或 powershell 的补全脚本;rlwrap 则输出 --interactive 能理解的词",
+    ),
+    ("lang", "消息语言:en 或 zh(默认取自 LC_ALL/LC_MESSAGES/LANG)"),
+    (
+        "help",
+        "显示本帮助,或只显示与 <topic> 有关的选\
+         项:request、auth、proxy、redirects、output、connection、protocols、tls、checks、cache、batch、load、debug 或 general",
+    ),
+    (
+        "disable",
+        "不读取 curl 配置文件($CURL_HOME/.curlrc、$XDG_CONFIG_HOME/curlrc 或 ~/.curlrc);只能作\
+         为第一个选项",
+    ),
+    (
+        "next",
+        "开始另一个传输的选项:它之前的选项只作用于它之前的 URL。各传输依次进行,尽可能复用连\
+         接,遇到第一个失败即停止(除非指定 --fail-at-end)",
+    ),
+    ("examples", "显示常见用法示例"),
+    ("version", "显示版本、TLS 后端以及此版本支持的协议和功能;报告问题时请附上"),
+];
+
+const EXAMPLES_EN: &str = "\
+Examples:
+  Fetch a page and show the response headers too:
+    http_client -i https://example.com/
+
+  Post JSON and fail unless the answer is a 2xx:
+    http_client -H 'Content-Type: application/json' -d '{\"a\": 1}' \\
+      --expect-status 2xx https://api.example/items
+
+  Download a file under its own name, following redirects:
+    http_client -L -O https://example.com/files/report.pdf
+
+  Upload a file with PUT:
+    http_client -T backup.tar https://files.example/uploads/
+
+  Authenticate with a netrc entry and keep the cookies in a session:
+    http_client -n --session api https://api.example/login
+
+  See what would be sent without sending it:
+    http_client --dry-run -X DELETE https://api.example/items/7
+
+  Fetch many URLs, four at a time, saving each one:
+    http_client --url-file urls.txt -Z --parallel-max 4 -o '#{name}'
+
+  Load-test an endpoint with 1000 requests over 20 connections:
+    http_client --bench --requests 1000 --concurrency 20 http://localhost:8080/
+
+See --help <topic> for the options about one thing.
+";
+
+const EXAMPLES_ZH: &str = "\
+示例:
+  获取页面,同时显示响应头:
+    http_client -i https://example.com/
+
+  发送 JSON,响应不是 2xx 时失败:
+    http_client -H 'Content-Type: application/json' -d '{\"a\": 1}' \\
+      --expect-status 2xx https://api.example/items
+
+  跟随重定向,以文件本身的名称下载:
+    http_client -L -O https://example.com/files/report.pdf
+
+  用 PUT 上传文件:
+    http_client -T backup.tar https://files.example/uploads/
+
+  使用 netrc 中的凭据认证,并将 Cookie 保存在会话中:
+    http_client -n --session api https://api.example/login
+
+  只查看将要发送的请求,不实际发送:
+    http_client --dry-run -X DELETE https://api.example/items/7
+
+  同时下载多个 URL,每次四个,分别保存:
+    http_client --url-file urls.txt -Z --parallel-max 4 -o '#{name}'
+
+  通过 20 个连接向一个端点发送 1000 个请求进行压测:
+    http_client --bench --requests 1000 --concurrency 20 http://localhost:8080/
+
+使用 --help <topic> 查看某一方面的选项。
+";
+
+const REPL_HELP_EN: &str = "\
+Commands:
+  METHOD <url> [body]  Send a request, e.g. POST /items {\"a\": 1}
+  <url>                Send a GET request
+  header Name: value   Send this header with every request
+  header Name:         Stop sending the header, built-in ones included
+  header               List the headers set with 'header'
+  cookies [clear]      List or forget the cookies received
+  history              List earlier commands
+  !!, !<n>             Run the last or the n-th command again
+  help                 Show this list
+  quit, exit           Leave
+
+A <url> is resolved against the previous one, so /other is enough for a
+second request to the same server. Lines have no editing keys or tab
+completion of their own; for both, run
+  rlwrap -f <(http_client --completions rlwrap) http_client --interactive";
+
+const REPL_HELP_ZH: &str = "\
+命令:
+  METHOD <url> [body]  发送请求,例如 POST /items {\"a\": 1}
+  <url>                发送 GET 请求
+  header Name: value   每个请求都发送此请求头
+  header Name:         不再发送该请求头,内置请求头也可移除
+  header               列出用 'header' 设置的请求头
+  cookies [clear]      列出或清除收到的 Cookie
+  history              列出之前的命令
+  !!, !<n>             重新执行上一条或第 n 条命令
+  help                 显示本列表
+  quit, exit           退出
+
+<url> 相对于上一个 URL 解析,因此对同一服务器的第二个请求只需写 /other。
+本身不支持行编辑和 Tab 补全;如需这两项,请运行
+  rlwrap -f <(http_client --completions rlwrap) http_client --interactive";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_language_from_locale() {
+        assert_eq!(Lang::from_locale("zh_CN.UTF-8"), Lang::Zh);
+        assert_eq!(Lang::from_locale("zh_TW"), Lang::Zh);
+        assert_eq!(Lang::from_locale("en_US.UTF-8"), Lang::En);
+        assert_eq!(Lang::from_locale("C"), Lang::En);
+        assert_eq!(Lang::from_locale("de_DE"), Lang::En);
+    }
+
+    #[test]
+    fn renders_both_languages() {
+        assert_eq!(
+            render(Lang::En, Msg::UnknownOption("--x")),
+            "unknown option --x"
+        );
+        assert_eq!(render(Lang::Zh, Msg::UnknownOption("--x")), "未知选项 --x");
+        let err = Error::TooManyRedirects(3);
+        assert_eq!(
+            render(Lang::En, Msg::Error(&err)),
+            "maximum (3) redirects followed"
+        );
+        assert_eq!(
+            render(Lang::Zh, Msg::Error(&err)),
+            "已达到重定向次数上限(3)"
+        );
+    }
+
+    #[test]
+    fn bench_reports_align_in_both_languages() {
+        let stats = Stats {
+            elapsed: Duration::from_secs(2),
+            latencies: vec![Duration::from_millis(1), Duration::from_millis(3)],
+            statuses: [(200, 2)].into_iter().collect(),
+            errors: vec![(Error::TooManyRedirects(3), 1)],
+            bytes: 10,
+        };
+        let en = render(Lang::En, Msg::BenchReport(&stats));
+        assert!(
+            en.contains("Requests:      2 completed, 1 failed\n"),
+            "{}",
+            en
+        );
+        assert!(en.contains("Throughput:    1.5 requests/sec\n"), "{}", en);
+        assert!(en.contains("p50 1.00 ms, p90 3.00 ms"), "{}", en);
+        assert!(en.ends_with("Errors:        1 × maximum (3) redirects followed"));
+        let zh = render(Lang::Zh, Msg::BenchReport(&stats));
+        assert!(zh.contains("吞吐量:        每秒 1.5 个请求\n"), "{}", zh);
+        assert!(zh.contains("状态码:        200 × 2\n"), "{}", zh);
+    }
+
+    #[test]
+    fn transfer_summaries_line_up() {
+        let rows = [
+            Row {
+                url: "http://h.test/a".to_string(),
+                status: Some(200),
+                bytes: 1234,
+                elapsed: Duration::from_millis(5),
+                error: None,
+            },
+            Row {
+                url: "http://h.test/long/b".to_string(),
+                status: None,
+                bytes: 0,
+                elapsed: Duration::from_millis(12),
+                error: Some("timed out".to_string()),
+            },
+        ];
+        let en = render(Lang::En, Msg::TransferSummary(&rows));
+        let lines: Vec<&str> = en.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("#  URL                   Status  Bytes"));
+        assert!(lines[1].ends_with("  ok"), "{}", en);
+        assert!(
+            lines[2].contains("http://h.test/long/b  -       0 "),
+            "{}",
+            en
+        );
+        let zh = render(Lang::Zh, Msg::TransferSummary(&rows));
+        assert!(zh.contains("状态  字节  用时"), "{}", zh);
+        assert!(zh.contains("成功"), "{}", zh);
+    }
+
+    #[test]
+    fn timings_list_the_phases_and_server_metrics() {
+        // Nothing was measured, so only the total is known.
+        let metrics = Metrics::new();
+        let server = [
+            Metric {
+                name: "db".to_string(),
+                duration: Some(53.2),
+                description: Some("Database".to_string()),
+            },
+            Metric {
+                name: "cache".to_string(),
+                duration: None,
+                description: None,
+            },
+        ];
+        let total = Duration::from_millis(80);
+        let en = render(Lang::En, Msg::Timings(&metrics, total, &server));
+        assert_eq!(
+            en,
+            "Timings:\n  Total  80.00 ms\nServer-Timing:\n  db     53.20 ms  Database\n  cache  -"
+        );
+        let zh = render(Lang::Zh, Msg::Timings(&metrics, total, &[]));
+        assert_eq!(zh, "计时:\n  总计  80.00 ms");
+    }
+
+    #[test]
+    fn every_option_is_described_in_every_language() {
+        let longs: Vec<&str> = crate::cli::OPTIONS.iter().map(|spec| spec.long).collect();
+        for table in [OPTION_HELP_EN, OPTION_HELP_ZH] {
+            let described: Vec<&str> = table.iter().map(|(long, _)| *long).collect();
+            assert_eq!(described, longs);
+            assert!(table.iter().all(|(_, help)| !help.is_empty()));
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Internationalized domain names: converting Unicode host names to their
+//! ASCII-compatible "xn--" form (RFC 3490 / RFC 3492).
+//!
+//! Full UTS #46 mapping tables are out of scope; labels are lowercased and
+//! the ideographic full stops are treated as dots, which covers the host
+//! names people actually type.
+
+use crate::error::{Error, Result};
+
+const BASE: u32 = 36;
+const T_MIN: u32 = 1;
+const T_MAX: u32 = 26;
+const SKEW: u32 = 38;
+const DAMP: u32 = 700;
+const INITIAL_BIAS: u32 = 72;
+const INITIAL_N: u32 = 128;
+
+/// Converts a host name to A-labels. ASCII labels are only lowercased.
+pub fn to_ascii(host: &str) -> Result<String> {
+    let host: String = host
+        .chars()
+        .map(|c| match c {
+            '\u{3002}' | '\u{FF0E}' | '\u{FF61}' => '.',
+            c => c,
+        })
+        .collect();
+
+    let mut labels = Vec::new();
+    for label in host.split('.') {
+        let lower = label.to_lowercase();
+        let ascii = if lower.is_ascii() {
+            lower
+        } else {
+            let encoded = punycode_encode(&lower).ok_or_else(|| {
+                Error::UrlParse(format!("host label '{}' cannot be punycode-encoded", label))
+            })?;
+            format!("xn--{}", encoded)
+        };
+        if ascii.len() > 63 {
+            return Err(Error::UrlParse(format!(
+                "host label '{}' is longer than 63 bytes",
+                label
+            )));
+        }
+        labels.push(ascii);
+    }
+    Ok(labels.join("."))
+}
+
+/// Punycode-encodes one label (without the `xn--` prefix). Returns `None` on
+/// arithmetic overflow, which only absurdly long labels can trigger.
+pub fn punycode_encode(input: &str) -> Option<String> {
+    let chars: Vec<u32> = input.chars().map(|c| c as u32).collect();
+    let mut output: String = input.chars().filter(char::is_ascii).collect();
+    let basic = output.len() as u32;
+    let mut handled = basic;
+    if basic > 0 {
+        output.push('-');
+    }
+
+    let mut n = INITIAL_N;
+    let mut delta: u32 = 0;
+    let mut bias = INITIAL_BIAS;
+    while (handled as usize) < chars.len() {
+        let m = chars.iter().copied().filter(|&c| c >= n).min()?;
+        delta = delta.checked_add((m - n).checked_mul(handled + 1)?)?;
+        n = m;
+        for &c in &chars {
+            if c < n {
+                delta = delta.checked_add(1)?;
+            }
+            if c == n {
+                let mut q = delta;
+                let mut k = BASE;
+                loop {
+                    let t = if k <= bias {
+                        T_MIN
+                    } else if k >= bias + T_MAX {
+                        T_MAX
+                    } else {
+                        k - bias
+                    };
+                    if q < t {
+                        break;
+                    }
+                    output.push(digit(t + (q - t) % (BASE - t)));
+                    q = (q - t) / (BASE - t);
+                    k += BASE;
+                }
+                output.push(digit(q));
+                bias = adapt(delta, handled + 1, handled == basic);
+                delta = 0;
+                handled += 1;
+            }
+        }
+        delta = delta.checked_add(1)?;
+        n = n.checked_add(1)?;
+    }
+    Some(output)
+}
+
+fn adapt(delta: u32, num_points: u32, first_time: bool) -> u32 {
+    let mut delta = if first_time { delta / DAMP } else { delta / 2 };
+    delta += delta / num_points;
+    let mut k = 0;
+    while delta > ((BASE - T_MIN) * T_MAX) / 2 {
+        delta /= BASE - T_MIN;
+        k += BASE;
+    }
+    k + (BASE - T_MIN + 1) * delta / (delta + SKEW)
+}
+
+fn digit(d: u32) -> char {
+    match d {
+        0..=25 => (b'a' + d as u8) as char,
+        _ => (b'0' + (d - 26) as u8) as char,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encodes_the_rfc_3492_samples() {
+        // Section 7.1, samples A, B, I, L, M, R and S.
+        for (input, encoded) in [
+            ("ليهمابتكلموشعربي؟", "egbpdaj6bu4bxfgehfvwxn"),
+            ("他们为什么不说中文", "ihqwcrb4cv8a8dqg056pqjye"),
+            (
+                "почемужеонинеговорятпорусски",
+                "b1abfaaepdrnnbgefbadotcwatmq2g4l",
+            ),
+            ("3年B組金八先生", "3B-ww4c5e180e575a65lsy2b"),
+            (
+                "安室奈美恵-with-SUPER-MONKEYS",
+                "-with-SUPER-MONKEYS-pc58ag80a8qai00g7n9n",
+            ),
+            ("そのスピードで", "d9juau41awczczp"),
+            ("-> $1.00 <-", "-> $1.00 <--"),
+        ] {
+            assert_eq!(
+                punycode_encode(input).as_deref(),
+                Some(encoded),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn converts_hosts_and_reports_failures() {
+        assert_eq!(
+            to_ascii("Bücher。Example").unwrap(),
+            "xn--bcher-kva.example"
+        );
+        assert_eq!(to_ascii("plain.example").unwrap(), "plain.example");
+        // The gap to U+10FFFF times the 4001 code points handled by then
+        // does not fit in 32 bits.
+        let overflowing = format!("{}\u{10FFFF}", "a".repeat(4000));
+        assert_eq!(punycode_encode(&overflowing), None);
+        assert_eq!(to_ascii(&overflowing).unwrap_err().exit_code(), 3);
+        let long = "他们为什么不说中文".repeat(8);
+        let err = to_ascii(&format!("{}.example", long)).unwrap_err();
+        assert!(err.to_string().contains("longer than 63 bytes"), "{}", err);
+    }
+}
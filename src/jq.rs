@@ -0,0 +1,235 @@
+//! A jq-like filter for picking values out of a JSON body, for `--jq`.
+//!
+//! Only paths are understood, which covers most of what scripts pipe to
+//! jq for: `.` is the whole document, `.name` and `."any name"` or
+//! `.["any name"]` a member, `.[2]` an element (`.[-1]` the last), and
+//! `.[]` every element or member value, so `.items[].id` yields one id
+//! per item. As in jq, a missing member or element is `null`; indexing
+//! anything but an object or an array, or `null`, is an error.
+
+use crate::error::{Error, Result};
+use crate::json::{self, Value};
+
+/// A parsed filter. The default, `.`, is the whole document.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filter {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Step {
+    Key(String),
+    Index(i64),
+    Each,
+}
+
+static NULL: Value = Value::Null;
+
+impl Filter {
+    /// `None` if `text` is not a path as described above.
+    pub fn parse(text: &str) -> Option<Filter> {
+        let mut rest = text.trim().strip_prefix('.')?;
+        let mut steps = Vec::new();
+        // A name may only follow a dot; a bracket may follow anything.
+        let mut after_dot = true;
+        while !rest.is_empty() {
+            let (step, after) = if let Some(after) = rest.strip_prefix('[') {
+                bracket(after)?
+            } else if after_dot && rest.starts_with('"') {
+                let (key, after) = string(rest)?;
+                (Step::Key(key), after)
+            } else if after_dot {
+                let end = rest
+                    .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                    .unwrap_or(rest.len());
+                if end == 0 || rest.starts_with(|c: char| c.is_ascii_digit()) {
+                    return None;
+                }
+                (Step::Key(rest[..end].to_string()), &rest[end..])
+            } else {
+                rest = rest.strip_prefix('.')?;
+                if rest.is_empty() {
+                    return None;
+                }
+                after_dot = true;
+                continue;
+            };
+            steps.push(step);
+            rest = after;
+            after_dot = false;
+        }
+        Some(Filter { steps })
+    }
+
+    /// The values the filter picks from `document`, in document order.
+    pub fn apply<'v>(&self, document: &'v Value) -> Result<Vec<&'v Value>> {
+        let mut values = vec![document];
+        for step in &self.steps {
+            let mut next = Vec::new();
+            for value in values {
+                match (step, value) {
+                    (Step::Key(_) | Step::Index(_), Value::Null) => next.push(&NULL),
+                    (Step::Key(key), Value::Object(_)) => {
+                        next.push(value.get(key).unwrap_or(&NULL))
+                    }
+                    (Step::Index(index), Value::Array(items)) => {
+                        let index = match *index < 0 {
+                            true => items.len() as i64 + index,
+                            false => *index,
+                        };
+                        let item = usize::try_from(index).ok().and_then(|i| items.get(i));
+                        next.push(item.unwrap_or(&NULL));
+                    }
+                    (Step::Each, Value::Array(items)) => next.extend(items),
+                    (Step::Each, Value::Object(members)) => {
+                        next.extend(members.iter().map(|(_, value)| value))
+                    }
+                    (Step::Key(key), value) => {
+                        return Err(Error::Query(format!(
+                            "cannot index {} with \"{}\"",
+                            kind(value),
+                            key
+                        )))
+                    }
+                    (Step::Index(_), value) => {
+                        return Err(Error::Query(format!(
+                            "cannot index {} with a number",
+                            kind(value)
+                        )))
+                    }
+                    (Step::Each, value) => {
+                        return Err(Error::Query(format!("cannot iterate over {}", kind(value))))
+                    }
+                }
+            }
+            values = next;
+        }
+        Ok(values)
+    }
+
+    /// The filter's results for `document`, one per line as [`print`]
+    /// has them.
+    pub fn results(&self, document: &Value, pretty: bool) -> Result<String> {
+        let mut out = String::new();
+        for value in self.apply(document)? {
+            out.push_str(&print(value, pretty));
+            out.push('\n');
+        }
+        Ok(out)
+    }
+
+    /// [`results`](Filter::results) for the JSON text `body`. An empty body
+    /// has none.
+    pub fn render(&self, body: &[u8], pretty: bool) -> Result<String> {
+        if body.iter().all(u8::is_ascii_whitespace) {
+            return Ok(String::new());
+        }
+        let text = std::str::from_utf8(body)
+            .map_err(|_| Error::Query("the body is not UTF-8 text".to_string()))?;
+        let document =
+            json::parse(text).map_err(|e| Error::Query(format!("the body is not JSON: {}", e)))?;
+        self.results(&document, pretty)
+    }
+}
+
+/// A result as `jq -r` prints it: a string as it is, anything else as
+/// compact JSON, or indented if `pretty`.
+pub fn print(value: &Value, pretty: bool) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other if pretty => other.to_pretty(),
+        other => other.to_string(),
+    }
+}
+
+/// The step in `[...]`, whose opening bracket `rest` follows, and what is
+/// after the closing one.
+fn bracket(rest: &str) -> Option<(Step, &str)> {
+    if let Some(after) = rest.strip_prefix(']') {
+        return Some((Step::Each, after));
+    }
+    if rest.starts_with('"') {
+        let (key, after) = string(rest)?;
+        return Some((Step::Key(key), after.strip_prefix(']')?));
+    }
+    let end = rest.find(']')?;
+    let index = rest[..end].trim().parse().ok()?;
+    Some((Step::Index(index), &rest[end + 1..]))
+}
+
+/// The JSON string literal `rest` starts with, and what follows it.
+fn string(rest: &str) -> Option<(String, &str)> {
+    let mut escaped = false;
+    for (i, c) in rest.char_indices().skip(1) {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => {
+                let Value::String(s) = json::parse(&rest[..=i]).ok()? else {
+                    return None;
+                };
+                return Some((s, &rest[i + 1..]));
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// What jq calls the type of `value` in its errors.
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_paths() {
+        for ok in [
+            ".",
+            ".a",
+            ".items[0].id",
+            ".items[].id",
+            ".[-1]",
+            ".a.[0]",
+            ".\"odd key\".b",
+            ".[\"x]\\\"y\"]",
+            " .a_1 ",
+        ] {
+            assert!(Filter::parse(ok).is_some(), "{}", ok);
+        }
+        for bad in ["", "a", "..", ".a.", ".1", ".a[", ".a[x]", ".a b", ".a|.b"] {
+            assert!(Filter::parse(bad).is_none(), "{}", bad);
+        }
+    }
+
+    #[test]
+    fn picks_values_like_jq() {
+        let body = br#"{"items": [{"id": 1, "tag": "a b"}, {"id": 2}], "odd key": "x]\"y"}"#;
+        let render = |filter: &str| Filter::parse(filter).unwrap().render(body, false);
+        assert_eq!(render(".items[0].id").unwrap(), "1\n");
+        assert_eq!(render(".items[].id").unwrap(), "1\n2\n");
+        assert_eq!(render(".items[-1]").unwrap(), "{\"id\":2}\n");
+        assert_eq!(render(".items[].tag").unwrap(), "a b\nnull\n");
+        assert_eq!(render(".[\"odd key\"]").unwrap(), "x]\"y\n");
+        assert_eq!(render(".missing.deeper[3]").unwrap(), "null\n");
+
+        let err = render(".items.id").unwrap_err();
+        assert_eq!(err.to_string(), "jq: cannot index array with \"id\"");
+        assert!(render(".items[0].id[]").is_err());
+        let filter = Filter::parse(".a").unwrap();
+        assert!(filter.render(b"<html>", false).is_err());
+        assert_eq!(filter.render(b"", false).unwrap(), "");
+        let pretty = Filter::default().render(b"[1]", true).unwrap();
+        assert_eq!(pretty, "[\n  1\n]\n");
+    }
+}
@@ -0,0 +1,68 @@
+//! A small curl-like HTTP/1.1 client.
+//!
+//! The binary in `main.rs` is a thin command-line front end; everything that
+//! talks to the network lives in this library so it can be reused.
+
+pub mod altsvc;
+pub mod auth;
+pub mod base64;
+pub mod bench;
+pub mod body;
+pub mod cache;
+pub mod cassette;
+pub mod charset;
+pub mod checksum;
+pub mod client;
+#[cfg(feature = "compression")]
+pub mod coding;
+pub mod cookie;
+pub mod date;
+pub mod disposition;
+pub mod error;
+pub mod expect;
+pub mod file;
+pub mod glob;
+#[cfg(feature = "compression")]
+pub mod gzip;
+pub mod headers;
+#[cfg(feature = "http2")]
+pub mod hpack;
+pub mod hsts;
+pub mod http;
+#[cfg(feature = "http2")]
+pub mod http2;
+pub mod httpfile;
+pub mod idna;
+#[cfg(feature = "json")]
+pub mod jq;
+pub mod json;
+pub mod links;
+pub mod md4;
+pub mod md5;
+pub mod metrics;
+pub mod middleware;
+#[cfg(feature = "json")]
+pub mod ndjson;
+pub mod netrc;
+pub mod ntlm;
+pub mod prometheus;
+pub mod proxy;
+pub mod raw;
+pub mod request;
+pub mod response;
+pub mod robots;
+pub mod script;
+pub mod servertiming;
+pub mod session;
+pub mod sha1;
+pub mod sha256;
+pub mod sigv4;
+pub mod socket;
+pub mod speed;
+pub mod sse;
+pub mod template;
+pub mod tls;
+pub mod trace;
+pub mod transport;
+pub mod url;
+pub mod websocket;
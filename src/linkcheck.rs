@@ -0,0 +1,137 @@
+//! `--check-links` (also `http_client check-links <url>`): fetching a page
+//! and checking every link on it.
+//!
+//! Each link gets a HEAD request, or a GET where the server does not take
+//! HEAD, up to `--parallel-max` at a time, and redirects are followed with
+//! `-L` as for any transfer. A link is broken if its request fails or is
+//! answered with a 4xx or 5xx status; the broken ones are listed in page
+//! order with what went wrong. Links robots.txt disallows are not checked
+//! (see [`crawl`](crate::crawl)).
+
+use http_client::error::{Error, Result};
+use http_client::http;
+use http_client::links::{self, Link};
+use http_client::response::StatusLine;
+use http_client::trace::Trace;
+use http_client::url::Url;
+
+use crate::batch;
+use crate::cli::Options;
+use crate::crawl::Sites;
+use crate::i18n::{tr, Msg};
+
+/// Checks the links on the page at `url`. Fails with
+/// [`Error::BrokenLinks`] if any is broken, the page itself included.
+pub fn run(opts: &Options, url: Url, trace: Option<&Trace>, limits: &http::Limits) -> Result<()> {
+    let method = crate::request_method(opts);
+    let data = opts.data.clone();
+    let (page, transfer) = crate::run_transfer(opts, url, method, data, &[], trace, limits);
+    let page = page?;
+    if is_broken(&page.status) {
+        let line = tr(Msg::LinkBroken(&transfer.url.to_string(), &page.status));
+        crate::write_output(opts, format!("{}\n", line).as_bytes())?;
+        return Err(Error::BrokenLinks {
+            broken: 1,
+            checked: 1,
+        });
+    }
+    let targets = targets(
+        links::extract(&page.text(), &transfer.url),
+        &transfer.url,
+        opts.same_origin,
+    );
+
+    let sites = Sites::new(opts, trace, limits);
+    let results = batch::each_parallel(&targets, opts.parallel_max, |url| {
+        sites
+            .visit(url, true)
+            .then(|| check(opts, url, trace, limits))
+    });
+
+    let mut output = String::new();
+    let checked = results.iter().flatten().count();
+    for (url, result) in targets.iter().zip(&results) {
+        let url = url.to_string();
+        let line = match result {
+            None => continue,
+            Some(Ok(status)) if !is_broken(status) => continue,
+            Some(Ok(status)) => tr(Msg::LinkBroken(&url, status)),
+            Some(Err(e)) => tr(Msg::LinkFailed(&url, e)),
+        };
+        output.push_str(&line);
+        output.push('\n');
+    }
+    crate::write_output(opts, output.as_bytes())?;
+    match output.lines().count() {
+        0 => {
+            eprintln!("* {}", tr(Msg::LinksWork(checked)));
+            Ok(())
+        }
+        broken => Err(Error::BrokenLinks { broken, checked }),
+    }
+}
+
+/// The URLs to check: every link, or with `same_origin` only those on the
+/// origin of `page`.
+fn targets(links: Vec<Link>, page: &Url, same_origin: bool) -> Vec<Url> {
+    links
+        .into_iter()
+        .map(|link| link.url)
+        .filter(|url| !same_origin || url.same_origin(page))
+        .collect()
+}
+
+/// The status `url` answers with.
+fn check(
+    opts: &Options,
+    url: &Url,
+    trace: Option<&Trace>,
+    limits: &http::Limits,
+) -> Result<StatusLine> {
+    let head = crate::fetch(
+        opts,
+        url.clone(),
+        "HEAD".to_string(),
+        None,
+        &[],
+        trace,
+        limits,
+    )?;
+    // Method Not Allowed or Not Implemented: the link may still work.
+    if matches!(head.status.code, 405 | 501) {
+        let get = crate::fetch(
+            opts,
+            url.clone(),
+            "GET".to_string(),
+            None,
+            &[],
+            trace,
+            limits,
+        )?;
+        return Ok(get.status);
+    }
+    Ok(head.status)
+}
+
+fn is_broken(status: &StatusLine) -> bool {
+    status.code >= 400
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_only_same_origin_links_when_asked() {
+        let page = Url::parse("http://h.test/dir/").unwrap();
+        let html = "<a href=a>a</a><a href='http://other.test/'>o</a>\
+                    <img src='https://h.test/i.png'><a href='//h.test:80/b'>b</a>";
+        let all = targets(links::extract(html, &page), &page, false);
+        assert_eq!(all.len(), 4);
+        let own: Vec<String> = targets(links::extract(html, &page), &page, true)
+            .iter()
+            .map(Url::to_string)
+            .collect();
+        assert_eq!(own, ["http://h.test/dir/a", "http://h.test/b"]);
+    }
+}
@@ -0,0 +1,294 @@
+//! The links in an HTML page, for `--list-links` and the crawl modes.
+//!
+//! This is a scanner, not a parser: it finds start tags and their
+//! attributes, skips comments and the contents of `<script>` and
+//! `<style>`, and decodes the common character references in attribute
+//! values. That is enough for pages as they are written, if not for every
+//! page the HTML standard can parse. Links are resolved against the
+//! page's URL, or its `<base href>`.
+
+use crate::url::Url;
+
+/// What a link is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Kind {
+    /// Another page: `<a>`, `<area>`, `<frame>` and `<iframe>`.
+    Page,
+    /// Something the page itself loads: images, scripts, stylesheets,
+    /// media and the like.
+    Resource,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Link {
+    pub url: Url,
+    pub kind: Kind,
+}
+
+/// Whether a `Content-Type` is HTML, whose links are worth following.
+pub fn is_html(content_type: &str) -> bool {
+    let media = content_type.split(';').next().unwrap_or_default().trim();
+    media.eq_ignore_ascii_case("text/html") || media.eq_ignore_ascii_case("application/xhtml+xml")
+}
+
+/// The attributes that hold links, by tag.
+const LINK_ATTRIBUTES: &[(&str, &str, Kind)] = &[
+    ("a", "href", Kind::Page),
+    ("area", "href", Kind::Page),
+    ("frame", "src", Kind::Page),
+    ("iframe", "src", Kind::Page),
+    ("img", "src", Kind::Resource),
+    ("img", "srcset", Kind::Resource),
+    ("source", "src", Kind::Resource),
+    ("source", "srcset", Kind::Resource),
+    ("script", "src", Kind::Resource),
+    ("link", "href", Kind::Resource),
+    ("video", "src", Kind::Resource),
+    ("video", "poster", Kind::Resource),
+    ("audio", "src", Kind::Resource),
+    ("track", "src", Kind::Resource),
+    ("embed", "src", Kind::Resource),
+    ("object", "data", Kind::Resource),
+];
+
+/// The `http` and `https` links in `html`, a page fetched from `url`, each
+/// once, in the order they first appear and without fragments. Links to
+/// other schemes, such as `mailto:` and `javascript:`, are left out.
+pub fn extract(html: &str, url: &Url) -> Vec<Link> {
+    let mut base = url.clone();
+    let mut seen_base = false;
+    let mut links: Vec<Link> = Vec::new();
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        rest = &rest[start + 1..];
+        if let Some(after) = rest.strip_prefix("!--") {
+            rest = after.find("-->").map_or("", |end| &after[end + 3..]);
+            continue;
+        }
+        let name_end = rest
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(rest.len());
+        if name_end == 0 {
+            continue;
+        }
+        let tag = rest[..name_end].to_ascii_lowercase();
+        let (attributes, after) = attributes(&rest[name_end..]);
+        rest = after;
+        if tag == "base" && !seen_base {
+            // Only the first <base> counts.
+            seen_base = true;
+            if let Some((_, href)) = attributes.iter().find(|(name, _)| name == "href") {
+                base = url.join(href).unwrap_or(base);
+            }
+            continue;
+        }
+        for (name, value) in &attributes {
+            let Some(&(_, attribute, kind)) = LINK_ATTRIBUTES
+                .iter()
+                .find(|(t, a, _)| *t == tag && a == name)
+            else {
+                continue;
+            };
+            let references = match attribute {
+                // "small.jpg 1x, large.jpg 2x"
+                "srcset" => value
+                    .split(',')
+                    .filter_map(|candidate| candidate.split_whitespace().next())
+                    .collect(),
+                _ => vec![value.as_str()],
+            };
+            for reference in references {
+                let Some(url) = resolve(&base, reference) else {
+                    continue;
+                };
+                if !links.iter().any(|link| link.url == url) {
+                    links.push(Link { url, kind });
+                }
+            }
+        }
+        if tag == "script" || tag == "style" {
+            let closing = rest.match_indices("</").find(|(i, _)| {
+                rest.get(i + 2..i + 2 + tag.len())
+                    .is_some_and(|name| name.eq_ignore_ascii_case(&tag))
+            });
+            rest = closing.map_or("", |(i, _)| &rest[i..]);
+        }
+    }
+    links
+}
+
+/// `reference` resolved against `base`, if it is an `http` or `https`
+/// link.
+fn resolve(base: &Url, reference: &str) -> Option<Url> {
+    let reference = reference.trim();
+    if reference.is_empty() || reference.starts_with('#') {
+        return None;
+    }
+    // Url::join takes anything without "://" for a path.
+    let scheme_end = reference.find(':').filter(|&end| {
+        let scheme = &reference[..end];
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    });
+    if let Some(end) = scheme_end {
+        let scheme = reference[..end].to_ascii_lowercase();
+        if scheme != "http" && scheme != "https" {
+            return None;
+        }
+    }
+    let mut url = base.join(reference).ok()?;
+    url.set_fragment(None);
+    Some(url)
+}
+
+/// The attributes of a start tag whose name `rest` follows, lowercased
+/// and with values decoded, and what is after the tag.
+fn attributes(mut rest: &str) -> (Vec<(String, String)>, &str) {
+    let mut attributes = Vec::new();
+    loop {
+        rest = rest.trim_start_matches(|c: char| c.is_ascii_whitespace() || c == '/');
+        if rest.is_empty() {
+            return (attributes, rest);
+        }
+        if let Some(after) = rest.strip_prefix('>') {
+            return (attributes, after);
+        }
+        let name_end = rest
+            .find(|c: char| c.is_ascii_whitespace() || matches!(c, '=' | '>' | '/'))
+            .unwrap_or(rest.len())
+            .max(1);
+        let name = rest[..name_end].to_ascii_lowercase();
+        rest = rest[name_end..].trim_start();
+        let Some(after) = rest.strip_prefix('=') else {
+            attributes.push((name, String::new()));
+            continue;
+        };
+        rest = after.trim_start();
+        let value = match rest.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let end = rest[1..].find(quote).map_or(rest.len(), |end| end + 1);
+                let value = &rest[1..end];
+                rest = rest.get(end + 1..).unwrap_or("");
+                value
+            }
+            _ => {
+                let end = rest
+                    .find(|c: char| c.is_ascii_whitespace() || c == '>')
+                    .unwrap_or(rest.len());
+                let value = &rest[..end];
+                rest = &rest[end..];
+                value
+            }
+        };
+        attributes.push((name, decode_references(value)));
+    }
+}
+
+/// Decodes `&amp;`, `&lt;`, `&gt;`, `&quot;`, `&apos;` and numeric
+/// character references; anything else is kept as written.
+fn decode_references(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let decoded = rest.find(';').and_then(|end| {
+            let c = match &rest[1..end] {
+                "amp" => '&',
+                "lt" => '<',
+                "gt" => '>',
+                "quot" => '"',
+                "apos" => '\'',
+                name => {
+                    let code = match name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+                        Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+                        None => name.strip_prefix('#')?.parse().ok()?,
+                    };
+                    char::from_u32(code)?
+                }
+            };
+            Some((c, end + 1))
+        });
+        match decoded {
+            Some((c, len)) => {
+                out.push(c);
+                rest = &rest[len..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn urls(html: &str) -> Vec<String> {
+        let page = Url::parse("http://h.test/dir/page.html").unwrap();
+        extract(html, &page)
+            .into_iter()
+            .map(|link| link.url.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn finds_and_resolves_links() {
+        let html = r##"<html><head>
+            <link rel=stylesheet href="/s.css">
+            <script src='app.js'></script>
+            <script>if (a < b) document.write("<a href='/no'>")</script>
+            </head><body>
+            <!-- <a href="/commented"> -->
+            <A HREF="other.html#top">x</A> <a href="other.html">again</a>
+            <a href="?q=1&amp;r=2">q</a>
+            <img src="//cdn.test/i.png" srcset="i1.png 1x, i2.png 2x">
+            <a href="mailto:a@b.test">m</a> <a href="javascript:void(0)">j</a>
+            <a href="#frag">f</a> <a href=../up.html>up</a>
+            <a href="HTTPS://x.test/">x</a>
+        "##;
+        assert_eq!(
+            urls(html),
+            [
+                "http://h.test/s.css",
+                "http://h.test/dir/app.js",
+                "http://h.test/dir/other.html",
+                "http://h.test/dir/page.html?q=1&r=2",
+                "http://cdn.test/i.png",
+                "http://h.test/dir/i1.png",
+                "http://h.test/dir/i2.png",
+                "http://h.test/up.html",
+                "https://x.test/",
+            ]
+        );
+    }
+
+    #[test]
+    fn honours_the_first_base_and_tells_pages_from_resources() {
+        let page = Url::parse("http://h.test/a/b").unwrap();
+        let html = "<base href='http://base.test/x/'><base href='/ignored/'>\
+                    <a href=p>p</a><iframe src=f></iframe><img src=i>";
+        let links = extract(html, &page);
+        let found: Vec<(String, Kind)> = links
+            .into_iter()
+            .map(|link| (link.url.to_string(), link.kind))
+            .collect();
+        assert_eq!(
+            found,
+            [
+                ("http://base.test/x/p".to_string(), Kind::Page),
+                ("http://base.test/x/f".to_string(), Kind::Page),
+                ("http://base.test/x/i".to_string(), Kind::Resource),
+            ]
+        );
+        assert_eq!(decode_references("&#x41;&#66;&nbsp;&"), "AB&nbsp;&");
+        assert!(is_html("Text/HTML; charset=utf-8"));
+        assert!(!is_html("text/plain"));
+    }
+}
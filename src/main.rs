@@ -0,0 +1,2387 @@
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::env;
+use std::fs;
+use std::io::{self, IsTerminal, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use http_client::altsvc;
+use http_client::auth;
+use http_client::bench;
+use http_client::body::Framing;
+use http_client::cache::{self, Cache};
+use http_client::cassette::Cassette;
+use http_client::charset;
+use http_client::checksum::{Algorithm, Checksum};
+#[cfg(feature = "compression")]
+use http_client::coding;
+use http_client::date;
+use http_client::disposition;
+use http_client::error::{Error, Result};
+use http_client::expect::Expectations;
+use http_client::file;
+use http_client::glob;
+#[cfg(feature = "compression")]
+use http_client::gzip;
+use http_client::headers::{self, CustomHeader, HeaderMap};
+use http_client::hsts;
+use http_client::http::{self, send_http_request, Exchange, HttpVersion, PersistentConnection};
+use http_client::httpfile::{self, FileRequest};
+#[cfg(feature = "json")]
+use http_client::jq::Filter;
+use http_client::links;
+use http_client::metrics::Metrics;
+use http_client::middleware;
+#[cfg(feature = "json")]
+use http_client::ndjson;
+use http_client::netrc::{self, Netrc};
+use http_client::ntlm;
+use http_client::prometheus::{self, Collector};
+use http_client::raw;
+use http_client::request::Method;
+use http_client::response::Response;
+use http_client::script::Script;
+use http_client::session::{self, Session};
+use http_client::sigv4::{self, Payload};
+use http_client::speed::{RateLimit, SpeedLimit};
+use http_client::sse::{Event, EventSource};
+use http_client::template::Vars;
+use http_client::trace::{self, Trace};
+use http_client::url::{self, Url};
+use http_client::websocket;
+
+mod batch;
+mod cli;
+mod completions;
+mod crawl;
+mod curlrc;
+mod export;
+mod help;
+mod history;
+mod i18n;
+mod linkcheck;
+mod meta;
+mod mirror;
+mod repl;
+mod version;
+mod writeout;
+
+use cli::Options;
+use history::{Hop, Transfer};
+use i18n::{tr, Lang, Msg};
+
+fn run(mut opts: Options) -> Result<()> {
+    let mut vars = Vars::new(true);
+    if let Some(path) = &opts.env_file {
+        vars.load_json(&read_text(path)?)?;
+    }
+    opts.url = opts.url.map(|url| vars.expand(&url)).transpose()?;
+    opts.data = opts.data.map(|data| vars.expand(&data)).transpose()?;
+    opts.oauth2_bearer = match opts.oauth2_bearer.map(|t| vars.expand(&t)).transpose()? {
+        Some(token) => match token.strip_prefix('@') {
+            Some(path) => Some(read_text(path)?.trim().to_string()),
+            None => Some(token),
+        },
+        None => None,
+    };
+    opts.headers = opts
+        .headers
+        .iter()
+        .map(|header| expand_header(&vars, header))
+        .collect::<Result<_>>()?;
+
+    // HTTP/3 needs QUIC, which this client does not implement.
+    if opts.http3_only {
+        return Err(Error::UnsupportedProtocol("HTTP/3".to_string()));
+    }
+    if opts.http3 {
+        eprintln!("{}", tr(Msg::Http3Fallback));
+    }
+    if cfg!(not(feature = "http2")) && opts.http_version != HttpVersion::Http11 {
+        return Err(Error::UnsupportedProtocol("HTTP/2".to_string()));
+    }
+    if cfg!(not(feature = "compression")) && (opts.compressed || opts.compress_request) {
+        return Err(Error::UnsupportedProtocol("compression".to_string()));
+    }
+    if cfg!(not(feature = "json")) && (opts.jq.is_some() || opts.pretty) {
+        return Err(Error::UnsupportedProtocol("json".to_string()));
+    }
+
+    opts.credentials = match (&opts.netrc_file, opts.netrc) {
+        (Some(path), _) => Some(Netrc::parse(&read_text(path)?)),
+        // A missing ~/.netrc just means no credentials.
+        (None, true) => Some(
+            netrc::default_path()
+                .and_then(|path| fs::read_to_string(path).ok())
+                .map_or_else(Netrc::default, |text| Netrc::parse(&text)),
+        ),
+        (None, false) => None,
+    };
+
+    if opts.aws_sigv4.is_some() {
+        let profile = env::var("AWS_PROFILE").unwrap_or_else(|_| "default".to_string());
+        opts.aws_credentials = sigv4::Credentials::from_env().or_else(|| {
+            let text = fs::read_to_string(sigv4::credentials_path()?).ok()?;
+            sigv4::Credentials::from_profile(&text, &profile)
+        });
+    }
+
+    if let Some(path) = &opts.script {
+        let script = Script::parse(&read_text(path)?)?;
+        opts.middleware.push(script);
+    }
+    if let Some(name) = &opts.session {
+        let session = load_session(&opts, name)?;
+        opts.session_file = Some(session);
+    }
+    if let Some(path) = &opts.cassette {
+        let cassette = load_cassette(path, opts.replay)?;
+        opts.cassette_file = Some(Mutex::new(cassette));
+    }
+    if opts.metrics_listen.is_some() || opts.metrics_file.is_some() {
+        let collector = Arc::new(Collector::new());
+        if let Some(addr) = &opts.metrics_listen {
+            let listener = TcpListener::bind(addr)
+                .map_err(|e| Error::Usage(tr(Msg::CannotListen(addr, &e))))?;
+            prometheus::serve(listener, collector.clone());
+        }
+        opts.collector = Some(collector);
+    }
+
+    let trace = match &opts.trace {
+        Some((path, mode)) => Some(Trace::open(path, *mode)?),
+        None => None,
+    };
+    if let Some(path) = opts.dump_header.as_deref().filter(|path| *path != "-") {
+        fs::File::create(path).map_err(|source| Error::File {
+            path: path.to_string(),
+            source,
+        })?;
+    }
+    let limits = http::Limits {
+        max_filesize: opts.max_filesize,
+        // As in curl, either option alone enables the check; the other
+        // defaults to 1 byte/sec or 30 seconds.
+        speed_limit: match (opts.speed_limit, opts.speed_time) {
+            (None, None) => None,
+            (limit, time) => Some(SpeedLimit {
+                bytes_per_sec: limit.unwrap_or(1),
+                time: Duration::from_secs(time.unwrap_or(30)),
+            }),
+        },
+        rate_limit: opts.limit_rate.map(|rate| Arc::new(RateLimit::new(rate))),
+        socket: opts.socket,
+        tls: None,
+        strict: opts.strict,
+    };
+    if let Some(path) = &opts.http_file {
+        no_preview(&opts, Some("--http-file"))?;
+        return run_request_file(&opts, path, vars, trace.as_ref(), &limits);
+    }
+    if let Some(path) = &opts.url_file {
+        no_preview(&opts, Some("--url-file"))?;
+        return batch::run(&opts, Some(path), &vars, trace.as_ref(), &limits);
+    }
+    if !opts.globoff && opts.url.as_deref().is_some_and(glob::is_glob) {
+        no_preview(&opts, None)?;
+        return batch::run(&opts, None, &vars, trace.as_ref(), &limits);
+    }
+    if let Some(path) = &opts.request_file {
+        no_preview(&opts, Some("--request-file"))?;
+        return send_request_file(&opts, path, trace.as_ref(), &limits);
+    }
+    if opts.interactive {
+        no_preview(&opts, Some("--interactive"))?;
+        return repl::run(&opts, &limits, io::stdin().lock(), &mut Stdout);
+    }
+
+    let url = opts
+        .url
+        .clone()
+        .ok_or_else(|| Error::Usage(tr(Msg::NoUrl)))?;
+    let mut url = parse_url(&opts, &url)?;
+    for query in &opts.url_queries {
+        url.append_query(&encode_query_pair(query));
+    }
+
+    if opts.check_links {
+        no_preview(&opts, Some("--check-links"))?;
+        return linkcheck::run(&opts, url, trace.as_ref(), &limits);
+    }
+    if opts.mirror {
+        no_preview(&opts, Some("--mirror"))?;
+        return mirror::run(&opts, url, trace.as_ref(), &limits);
+    }
+
+    let method = request_method(&opts);
+    let data = opts.data.clone();
+    let referer = opts.referer.clone();
+
+    if matches!(url.scheme(), "ws" | "wss") {
+        no_preview(&opts, Some("ws://"))?;
+        let mut headers = request_headers(&opts, &url, None, referer.as_deref(), &[], true);
+        sign_aws(&opts, "GET", &url, &mut headers, Payload::Bytes(b""))?;
+        let close = websocket::session(
+            &url,
+            &headers,
+            opts.verbose,
+            trace.as_ref(),
+            io::BufReader::new(io::stdin()),
+            &mut Stdout,
+            &limits,
+        )?;
+        if opts.verbose {
+            let reason = close.reason.trim();
+            eprintln!("* {}", tr(Msg::WebSocketClosed(close.code, reason)));
+        }
+        return Ok(());
+    }
+
+    if url.scheme() == "file" {
+        return write_output(&opts, &file::read(&url, &limits)?);
+    }
+
+    if opts.dry_run || opts.export.is_some() {
+        return dry_run(&opts, &url, &method, data.as_deref(), referer.as_deref());
+    }
+
+    if opts.sse {
+        let limits = limits.for_url(&url)?;
+        let mut headers = request_headers(&opts, &url, None, referer.as_deref(), &[], true);
+        sign_aws(&opts, "GET", &url, &mut headers, Payload::Bytes(b""))?;
+        return stream_events(
+            EventSource::new(url, headers),
+            &opts,
+            trace.as_ref(),
+            &limits,
+        );
+    }
+
+    if opts.bench {
+        let limits = limits.for_url(&url)?;
+        let mut headers =
+            request_headers(&opts, &url, data.as_deref(), referer.as_deref(), &[], true);
+        let body = data.as_deref().unwrap_or_default().as_bytes();
+        sign_aws(&opts, &method, &url, &mut headers, Payload::Bytes(body))?;
+        let target = url.request_target();
+        let request = bench::Request {
+            host: url.connect_host(),
+            port: url.port_or_default(),
+            method: &method,
+            target: &target,
+            headers: &headers,
+            body: data.as_deref(),
+        };
+        let config = bench::Config {
+            requests: opts.bench_requests,
+            concurrency: opts.bench_concurrency,
+        };
+        let mut stats = bench::run(&request, &config, &limits, opts.collector.as_deref());
+        write_metrics(&opts)?;
+        write_output(
+            &opts,
+            format!("{}\n", tr(Msg::BenchReport(&stats))).as_bytes(),
+        )?;
+        // Only a run in which nothing got through counts as a failure.
+        if stats.completed() == 0 && !stats.errors.is_empty() {
+            return Err(stats.errors.swap_remove(0).0);
+        }
+        return Ok(());
+    }
+
+    if let Some(path) = &opts.upload_file {
+        return upload(&opts, url, &method, path, trace.as_ref(), &limits);
+    }
+
+    if let Some(interval) = opts.watch {
+        return watch(&opts, url, method, data, interval, trace.as_ref(), &limits);
+    }
+
+    let checksum = expected_hash(&opts, &url)?;
+    opts.spool = Spool::for_run(&opts);
+    let (result, transfer) = run_transfer(&opts, url, method, data, &[], trace.as_ref(), &limits);
+    // A download that fails verification is not written anywhere.
+    let verified = match (&result, &checksum) {
+        (Ok(response), Some(checksum)) => checksum.verify(&response.body),
+        _ => Ok(()),
+    };
+    if let (Ok(response), Ok(()), None) = (&result, &verified, transfer.saved) {
+        let output = match opts.list_links {
+            true => link_list(&opts, &transfer.url, response),
+            false => response_output(&opts, response)?,
+        };
+        // First, so the copy is kept even if stdout refuses a binary body.
+        if let Some(path) = &opts.tee {
+            write_file(&opts, path, false, &output)?;
+        }
+        // Without the head if that went out as soon as it came.
+        let shown = opts.spool.as_ref().and_then(Spool::head_shown);
+        let unshown = |output: Vec<u8>| match &shown {
+            Some(head) if output.starts_with(head.as_bytes()) => output[head.len()..].to_vec(),
+            _ => output,
+        };
+        match remote_name(&opts, &transfer.url, response) {
+            Some((path, fresh)) => {
+                if opts.verbose {
+                    eprintln!("* {}", tr(Msg::SavingTo(&path)));
+                }
+                write_file(&opts, &path, fresh, &output)?
+            }
+            None if opts.output.is_none() && io::stdout().is_terminal() => {
+                write_stdout(&unshown(terminal_output(&opts, response, output)?))?
+            }
+            None => write_output(&opts, &unshown(output))?,
+        }
+    }
+    // Like curl, -w is written for failed transfers too.
+    if let Some(format) = &opts.write_out {
+        let text = writeout::render(format, |name| writeout::variable(name, &transfer, &result));
+        write_stdout(text.as_bytes())?;
+    }
+    let response = result?;
+    verified?;
+    // The output is written either way, to show what did not match.
+    opts.expect.check(&response)?;
+    check_budgets(&opts, transfer.total, transfer.body_size(&response))
+}
+
+/// Sends the `-T` file at `path` as the body of one request, and writes the
+/// response. The file is streamed rather than read into memory, so
+/// redirects, retries and the other features that resend a body do not
+/// apply, and `--aws-sigv4` leaves the body unsigned. Otherwise it goes
+/// through what [`follow`] and [`run_transfer`] do for one request, from
+/// the scripts to `-w`. With `--compress-request` it is gzipped on the way
+/// and sent chunked, as its compressed size is not known up front.
+fn upload(
+    opts: &Options,
+    mut url: Url,
+    method: &str,
+    path: &str,
+    trace: Option<&Trace>,
+    limits: &http::Limits,
+) -> Result<()> {
+    let (body, length): (Box<dyn Read + '_>, _) = if path == "-" {
+        (Box::new(io::stdin().lock()), None)
+    } else {
+        let read_error = |source| Error::ReadFile {
+            path: path.to_string(),
+            source,
+        };
+        let file = fs::File::open(path).map_err(read_error)?;
+        let length = file.metadata().map_err(read_error)?.len();
+        // As in curl, a URL naming a directory gets the file's name.
+        let name = Path::new(path).file_name().and_then(|name| name.to_str());
+        if let (true, Some(name)) = (url.path().ends_with('/'), name) {
+            url = url.join(&url::encode_component(name))?;
+        }
+        (Box::new(file), Some(length))
+    };
+    let (mut body, length) = compress_request(opts, body, length);
+    let limits = &limits.for_url(&url)?;
+    if limits.tls.is_some() && opts.proxy.is_some() {
+        return Err(Error::UnsupportedProtocol(
+            "https through a proxy".to_string(),
+        ));
+    }
+    let mut headers = request_headers(opts, &url, None, opts.referer.as_deref(), &[], true);
+    if opts.compress_request {
+        headers.insert("Content-Encoding", "gzip");
+    }
+    add_proxy_headers(opts, &mut headers);
+    let mut request = before_sending(opts, method, &url, headers, None)?;
+    sign_aws(
+        opts,
+        request.method.as_str(),
+        &request.url,
+        &mut request.headers,
+        Payload::Unsigned,
+    )?;
+    let url = &request.url;
+    let (host, port, target) = match &opts.proxy {
+        Some(proxy) => (proxy.host.as_str(), proxy.port, url.absolute_form()),
+        None => (
+            url.connect_host(),
+            url.port_or_default(),
+            url.request_target(),
+        ),
+    };
+    let show = io::stderr().is_terminal();
+    let uploaded = Cell::new(0);
+    let mut progress = |sent| {
+        uploaded.set(sent);
+        if show {
+            eprint!("\r* {}", tr(Msg::Uploaded(sent, length)));
+        }
+    };
+    let upload = http::Upload {
+        body: &mut body,
+        length,
+        progress: &mut progress,
+    };
+    let started = Instant::now();
+    let metrics = Metrics::new();
+    let exchange = Exchange {
+        verbose: opts.verbose,
+        trace,
+        metrics: Some(&metrics),
+        ..Exchange::new(
+            host,
+            port,
+            request.method.as_str(),
+            &target,
+            &request.headers,
+            limits,
+        )
+    };
+    let result = http::open_upload(&exchange, upload);
+    if show {
+        eprintln!();
+    }
+    let result = result
+        .and_then(|mut response| {
+            let body = http::read_body(&mut response.body, limits)?;
+            Ok(Response {
+                status: response.status,
+                headers: response.headers,
+                body,
+                trailers: response.body.take_trailers(),
+            })
+        })
+        .and_then(|response| {
+            update_session(opts, url, Some(&request.headers), &response);
+            dump_header(opts, &response)?;
+            opts.middleware.on_response(&request, &response)?;
+            decompressed(opts, response, limits)
+        });
+    let transfer = Transfer {
+        url: url.clone(),
+        hops: Vec::new(),
+        metrics,
+        total: started.elapsed(),
+        upload: uploaded.get() as usize,
+        retries: 0,
+        saved: None,
+    };
+    let result = describe(opts, &transfer, result);
+    if let Ok(response) = &result {
+        write_output(opts, &response_output(opts, response)?)?;
+    }
+    if let Some(format) = &opts.write_out {
+        let text = writeout::render(format, |name| writeout::variable(name, &transfer, &result));
+        write_stdout(text.as_bytes())?;
+    }
+    let response = result?;
+    opts.expect.check(&response)?;
+    check_budgets(opts, transfer.total, transfer.body_size(&response))
+}
+
+/// Reports the `--max-*-warn` budgets `response` went over, and fails if
+/// it went over a hard one.
+fn check_budgets(opts: &Options, elapsed: Duration, size: u64) -> Result<()> {
+    for warning in opts.budgets.check(elapsed, size)? {
+        eprintln!("* {}", tr(Msg::OverBudget(&warning)));
+    }
+    Ok(())
+}
+
+/// `--dry-run`: writes the first request to stdout exactly as it would be
+/// sent, and where it would go to stderr, without connecting. Except for
+/// the NTLM and Digest handshakes, which need a server's challenge, every
+/// header the transfer would add is there. With `--export`, the same
+/// request is written in another tool's terms instead.
+/// Refuses `--dry-run` and `--export` in a mode that sends requests of its
+/// own, which they cannot show; `mode` names it, or is None for a URL glob.
+fn no_preview(opts: &Options, mode: Option<&str>) -> Result<()> {
+    let flag = match (opts.dry_run, &opts.export) {
+        (true, _) => "--dry-run",
+        (false, Some(_)) => "--export",
+        (false, None) => return Ok(()),
+    };
+    Err(Error::Usage(tr(Msg::NoPreview(flag, mode))))
+}
+
+fn dry_run(
+    opts: &Options,
+    url: &Url,
+    method: &str,
+    data: Option<&str>,
+    referer: Option<&str>,
+) -> Result<()> {
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(Error::UnsupportedProtocol(url.scheme().to_string()));
+    }
+    let mut headers = request_headers(opts, url, data, referer, &[], true);
+    add_proxy_headers(opts, &mut headers);
+    let mut request = before_sending(opts, method, url, headers, data)?;
+    // An exported command carries the body as text, so it is not gzipped.
+    let body = match &opts.export {
+        Some(_) => request
+            .body
+            .as_deref()
+            .map(|body| Cow::Borrowed(body.as_bytes())),
+        None => encoded_body(opts, &mut request.headers, request.body.as_deref()),
+    };
+    let wire = body.as_deref();
+    let payload = Payload::Bytes(wire.unwrap_or_default());
+    sign_aws(
+        opts,
+        request.method.as_str(),
+        &request.url,
+        &mut request.headers,
+        payload,
+    )?;
+    let (method, url, headers, data) = (
+        request.method.as_str(),
+        &request.url,
+        &request.headers,
+        request.body.as_deref(),
+    );
+    let (host, port, target) = match &opts.proxy {
+        Some(proxy) => (proxy.host.as_str(), proxy.port, url.absolute_form()),
+        None => (
+            url.connect_host(),
+            url.port_or_default(),
+            url.request_target(),
+        ),
+    };
+    let target = opts.request_target.clone().unwrap_or(target);
+    http::validate_request(method, &target, headers)?;
+    if let Some(format) = &opts.export {
+        let url = url.without_userinfo().to_string();
+        let request = export::Request {
+            method,
+            url: &url,
+            target: opts.request_target.as_deref(),
+            headers,
+            body: data,
+            proxy: opts.proxy.as_ref(),
+        };
+        return write_stdout(export::render(format, &request).as_bytes());
+    }
+    eprintln!("* {}", tr(Msg::WouldConnect(host, port)));
+    write_stdout(&http::request_text(method, &target, headers, wire))
+}
+
+/// Runs `--script` and any other middleware on a request about to be
+/// sent, which they may change.
+fn before_sending(
+    opts: &Options,
+    method: &str,
+    url: &Url,
+    headers: HeaderMap,
+    data: Option<&str>,
+) -> Result<middleware::Request> {
+    let mut request = middleware::Request {
+        method: Method::parse(method)?,
+        url: url.clone(),
+        headers,
+        body: data.map(str::to_string),
+    };
+    opts.middleware.on_request(&mut request)?;
+    Ok(request)
+}
+
+/// `-X`, or else POST with `-d` and GET without.
+fn request_method(opts: &Options) -> String {
+    opts.method.clone().unwrap_or_else(|| {
+        if opts.upload_file.is_some() {
+            "PUT".to_string()
+        } else if opts.data.is_some() {
+            "POST".to_string()
+        } else {
+            "GET".to_string()
+        }
+    })
+}
+
+/// Sends one request, following redirects with `-L`, and writes its
+/// `--meta-json` record. `extra` headers go on top of the built-in ones and
+/// under `-H`.
+fn fetch(
+    opts: &Options,
+    url: Url,
+    method: String,
+    data: Option<String>,
+    extra: &[CustomHeader],
+    trace: Option<&Trace>,
+    limits: &http::Limits,
+) -> Result<Response> {
+    run_transfer(opts, url, method, data, extra, trace, limits).0
+}
+
+/// [`fetch`], also returning the redirects and timings for `-w`. With
+/// `--retry` a transfer that failed in a way that may pass is sent again,
+/// and only the last attempt is described. The `--show-redirects` summary
+/// and the `--timings` report are printed here.
+fn run_transfer(
+    opts: &Options,
+    url: Url,
+    method: String,
+    data: Option<String>,
+    extra: &[CustomHeader],
+    trace: Option<&Trace>,
+    limits: &http::Limits,
+) -> (Result<Response>, Transfer) {
+    let started = Instant::now();
+    let upload = data.as_ref().map_or(0, String::len);
+    let mut retries = 0;
+    let (mut result, url, hops, metrics) = loop {
+        let metrics = Metrics::new();
+        let mut url = url.clone();
+        let mut hops = Vec::new();
+        let (method, data) = (method.clone(), data.clone());
+        let result = follow(
+            opts, &mut url, &mut hops, method, data, extra, trace, &metrics, limits,
+        );
+        let Some(delay) = retry_delay(opts, &result, retries) else {
+            break (result, url, hops, metrics);
+        };
+        let left = opts.retry - retries - 1;
+        eprintln!("* {}", tr(Msg::Retrying(delay.as_secs_f64(), left)));
+        thread::sleep(delay);
+        retries += 1;
+    };
+    result = result.and_then(|response| decompressed(opts, response, limits));
+    let transfer = Transfer {
+        url,
+        hops,
+        metrics,
+        total: started.elapsed(),
+        upload,
+        retries,
+        saved: opts.spool.as_ref().and_then(Spool::saved),
+    };
+    let result = describe(opts, &transfer, result);
+    (result, transfer)
+}
+
+/// Prints the `--show-redirects` summary and the `--timings` report for a
+/// finished `transfer`, and writes its `--meta-json` record, failing the
+/// transfer if that cannot be written.
+fn describe(opts: &Options, transfer: &Transfer, mut result: Result<Response>) -> Result<Response> {
+    if opts.show_redirects {
+        eprint!(
+            "{}",
+            transfer.summary(result.as_ref().ok().map(|r| r.status.code))
+        );
+    }
+    if opts.timings {
+        let server = result.as_ref().map_or(Vec::new(), Response::server_timing);
+        let total = transfer.metrics.elapsed();
+        eprintln!("{}", tr(Msg::Timings(&transfer.metrics, total, &server)));
+    }
+    if let Some(path) = &opts.meta_json {
+        let written = meta::write(path, &meta::record(transfer, &result));
+        result = result.and_then(|response| written.map(|()| response));
+    }
+    result
+}
+
+/// With `--compress-request`, an upload `body` of `length` bytes gzipped,
+/// and its new length, which is not known up front.
+#[cfg(feature = "compression")]
+fn compress_request<'a>(
+    opts: &Options,
+    body: Box<dyn Read + 'a>,
+    length: Option<u64>,
+) -> (Box<dyn Read + 'a>, Option<u64>) {
+    match opts.compress_request {
+        true => (Box::new(gzip::Encoder::new(body)), None),
+        false => (body, length),
+    }
+}
+
+/// The `-d` `data` as it is sent: gzipped with `--compress-request`, which
+/// then also sets `Content-Encoding` and the new `Content-Length` in
+/// `headers`.
+#[cfg(feature = "compression")]
+fn encoded_body<'a>(
+    opts: &Options,
+    headers: &mut HeaderMap,
+    data: Option<&'a str>,
+) -> Option<Cow<'a, [u8]>> {
+    let data = data?.as_bytes();
+    if !opts.compress_request {
+        return Some(Cow::Borrowed(data));
+    }
+    let mut compressed = Vec::new();
+    gzip::Encoder::new(data)
+        .read_to_end(&mut compressed)
+        .expect("reading from memory does not fail");
+    headers.insert("Content-Encoding", "gzip");
+    headers.insert("Content-Length", compressed.len().to_string());
+    Some(Cow::Owned(compressed))
+}
+
+/// Without the `compression` feature `--compress-request` is refused up
+/// front.
+#[cfg(not(feature = "compression"))]
+fn compress_request<'a>(
+    _: &Options,
+    body: Box<dyn Read + 'a>,
+    length: Option<u64>,
+) -> (Box<dyn Read + 'a>, Option<u64>) {
+    (body, length)
+}
+
+/// Without the `compression` feature `--compress-request` is refused up
+/// front.
+#[cfg(not(feature = "compression"))]
+fn encoded_body<'a>(
+    _: &Options,
+    _: &mut HeaderMap,
+    data: Option<&'a str>,
+) -> Option<Cow<'a, [u8]>> {
+    data.map(|data| Cow::Borrowed(data.as_bytes()))
+}
+
+/// With `--compressed`, `response` with the codings of its body undone.
+#[cfg(feature = "compression")]
+fn decompressed(opts: &Options, mut response: Response, limits: &http::Limits) -> Result<Response> {
+    let encodings = response.headers.get("Content-Encoding");
+    if let (true, Some(encodings)) = (opts.compressed && !response.body.is_empty(), encodings) {
+        let max = limits.max_filesize.unwrap_or(u64::MAX);
+        response.body = coding::decode(encodings, std::mem::take(&mut response.body), max)?;
+    }
+    Ok(response)
+}
+
+/// Without the `compression` feature `--compressed` is refused up front.
+#[cfg(not(feature = "compression"))]
+fn decompressed(_: &Options, response: Response, _: &http::Limits) -> Result<Response> {
+    Ok(response)
+}
+
+/// How long to wait before retrying a transfer that ended with `result`
+/// after `retries` retries, if `--retry` allows another. Network failures
+/// and the statuses curl treats as transient are retried. The wait doubles
+/// from one second up to ten minutes, unless `--retry-delay` fixes it or the
+/// server sent `Retry-After`.
+fn retry_delay(opts: &Options, result: &Result<Response>, retries: u32) -> Option<Duration> {
+    if retries >= opts.retry || stdout_closed() {
+        return None;
+    }
+    let asked = match result {
+        Ok(response) if matches!(response.status.code, 408 | 429 | 500 | 502 | 503 | 504) => {
+            response.retry_after(date::now())
+        }
+        Err(e) if e.is_transient() => None,
+        _ => return None,
+    };
+    let backoff = Duration::from_secs(1 << retries.min(9)).min(Duration::from_secs(600));
+    Some(asked.or(opts.retry_delay).unwrap_or(backoff))
+}
+
+/// The redirect loop of [`fetch`], leaving the last URL tried in `url`.
+#[allow(clippy::too_many_arguments)]
+fn follow(
+    opts: &Options,
+    url: &mut Url,
+    hops: &mut Vec<Hop>,
+    mut method: String,
+    mut data: Option<String>,
+    extra: &[CustomHeader],
+    trace: Option<&Trace>,
+    metrics: &Metrics,
+    limits: &http::Limits,
+) -> Result<Response> {
+    let mut referer = opts.referer.clone();
+    // Only where the transfer started gets the credentials given for it.
+    let first = url.clone();
+    let cache = match &opts.cache_dir {
+        Some(dir) if !opts.no_cache => Some(Cache::open(dir)?),
+        _ => None,
+    };
+    // A missing file is an empty store.
+    let mut hsts = opts.hsts.as_ref().map(|path| {
+        fs::read_to_string(path).map_or_else(|_| hsts::Store::new(), |t| hsts::Store::load(&t))
+    });
+    let alt_svc_file = opts.alt_svc.as_ref().filter(|_| !opts.no_alt_svc);
+    let mut alt_svc = alt_svc_file.map(|path| {
+        fs::read_to_string(path).map_or_else(|_| altsvc::Store::new(), |t| altsvc::Store::load(&t))
+    });
+    loop {
+        if let Some(store) = &hsts {
+            if url.scheme() == "http" && store.must_upgrade(url.host(), date::now()) {
+                if opts.verbose {
+                    eprintln!("* {}", tr(Msg::HstsUpgrade(url.host())));
+                }
+                url.upgrade_to_tls();
+            }
+        }
+        https_only(opts, url)?;
+        let limits = &limits.for_url(url)?;
+        let https = limits.tls.is_some();
+        if https && opts.proxy.is_some() {
+            // That would take a CONNECT tunnel, which is not implemented.
+            return Err(Error::UnsupportedProtocol(
+                "https through a proxy".to_string(),
+            ));
+        }
+        let trusted = opts.location_trusted || url.same_origin(&first);
+        // Redirects go where they point, not to the --request-target.
+        let custom_target = opts.request_target.clone().filter(|_| hops.is_empty());
+        let mut headers = request_headers(
+            opts,
+            url,
+            data.as_deref(),
+            referer.as_deref(),
+            extra,
+            trusted,
+        );
+        add_proxy_headers(opts, &mut headers);
+        let request = before_sending(opts, &method, url, headers, data.as_deref())?;
+        let mut headers = request.headers.clone();
+        method = request.method.to_string();
+        data.clone_from(&request.body);
+        url.clone_from(&request.url);
+        let body = encoded_body(opts, &mut headers, data.as_deref());
+        if trusted {
+            let payload = Payload::Bytes(body.as_deref().unwrap_or_default());
+            sign_aws(opts, &method, url, &mut headers, payload)?;
+        }
+        let origin = altsvc::Endpoint {
+            protocol: "h1".to_string(),
+            host: url.host().to_ascii_lowercase(),
+            port: url.port_or_default(),
+        };
+        let alternative = alt_svc
+            .as_ref()
+            .filter(|_| opts.proxy.is_none() && !https)
+            .and_then(|store| store.find(&origin, USABLE_ALT_SVC, date::now()))
+            .cloned();
+        let send_to = |service: Option<&altsvc::Endpoint>, headers: &HeaderMap| {
+            let (host, port, version) = match service {
+                Some(s) if s.protocol == "h2c" => {
+                    (s.host.as_str(), s.port, HttpVersion::Http2PriorKnowledge)
+                }
+                Some(s) => (s.host.as_str(), s.port, opts.http_version),
+                // HTTP/2 is only spoken in cleartext.
+                None if https => (
+                    url.connect_host(),
+                    url.port_or_default(),
+                    HttpVersion::Http11,
+                ),
+                None => (url.connect_host(), url.port_or_default(), opts.http_version),
+            };
+            // A proxy takes the whole URL, over HTTP/1.1.
+            let (host, port, version, target) = match &opts.proxy {
+                Some(proxy) => {
+                    let target = url.absolute_form();
+                    (proxy.host.as_str(), proxy.port, HttpVersion::Http11, target)
+                }
+                None => (host, port, version, url.request_target()),
+            };
+            let target = custom_target.clone().unwrap_or(target);
+            let request = Exchange {
+                body: body.as_deref(),
+                verbose: opts.verbose,
+                trace,
+                metrics: Some(metrics),
+                version,
+                ..Exchange::new(host, port, &method, &target, headers, limits)
+            };
+            let identity = credentials(opts, url)
+                .filter(|_| (opts.ntlm || opts.negotiate) && !headers.contains("Authorization"));
+            if let Some((user, password)) = identity {
+                let identity = ntlm::Identity::new(&user, &password);
+                return send_ntlm(opts, &request, &identity);
+            }
+            let shared = shared_connections(opts).filter(|_| version == HttpVersion::Http11);
+            let send_via = |headers: &HeaderMap| {
+                let request = Exchange { headers, ..request };
+                match (&opts.spool, shared) {
+                    (_, Some(connections)) => send_shared(opts, connections, &request),
+                    (Some(spool), None) if version == HttpVersion::Http11 => {
+                        let response = http::open_request(&request)?;
+                        spool.finish(opts, url, response, limits)
+                    }
+                    _ => send_http_request(&request),
+                }
+            };
+            let mut response = send_via(headers)?;
+            if let Some(answer) = proxy_answer(opts, &method, &target, headers, &response)? {
+                if opts.verbose {
+                    let scheme = answer.split(' ').next().unwrap_or_default();
+                    eprintln!("* {}", tr(Msg::ProxyChallenge(scheme)));
+                }
+                let mut headers = headers.clone();
+                headers.insert("Proxy-Authorization", answer);
+                response = send_via(&headers)?;
+            }
+            if opts.proxy.is_some() && response.status.code == 407 {
+                let offered = proxy_schemes(&response);
+                let msg = match proxy_credentials(opts) {
+                    Some(_) => Msg::ProxyAuthRejected(&offered),
+                    None => Msg::ProxyAuthRequired(&offered),
+                };
+                eprintln!("* {}", tr(msg));
+            }
+            if opts.verbose && http::reads_until_close(&method, &response.status, &response.headers)
+            {
+                eprintln!("* {}", tr(Msg::BodyUntilClose));
+            }
+            Ok(response)
+        };
+        // An unreachable alternative falls back to the origin itself.
+        let unreachable = Cell::new(false);
+        let send = |headers: &HeaderMap| {
+            let Some(service) = &alternative else {
+                return send_to(None, headers);
+            };
+            if opts.verbose {
+                let endpoint = format!("{}:{}", service.host, service.port);
+                eprintln!("* {}", tr(Msg::UsingAltSvc(&service.protocol, &endpoint)));
+            }
+            match send_to(Some(service), headers) {
+                Err(Error::Dns { .. } | Error::Connect { .. }) => {
+                    unreachable.set(true);
+                    send_to(None, headers)
+                }
+                result => result,
+            }
+        };
+        let recorded = replay(opts, &method, url, data.as_deref())?;
+        let response = match (recorded, &cache) {
+            (Some(response), _) => response,
+            (None, Some(cache)) if method == "GET" => {
+                send_cached(opts, cache, url, headers.clone(), send)?
+            }
+            (None, _) => send(&headers)?,
+        };
+        record(opts, &method, url, data.as_deref(), &response);
+        update_session(opts, url, hops.is_empty().then_some(&headers), &response);
+        dump_header(opts, &response)?;
+
+        if let (Some(store), Some(path), "https") = (&mut hsts, &opts.hsts, url.scheme()) {
+            let header = response.headers.get("Strict-Transport-Security");
+            let now = date::now();
+            if header.is_some_and(|value| store.update(url.host(), value, now)) {
+                save_state(opts, path, &store.to_text(now));
+            }
+        }
+        if let (Some(store), Some(path)) = (&mut alt_svc, alt_svc_file) {
+            let now = date::now();
+            let mut changed = false;
+            if let (true, Some(service)) = (unreachable.get(), &alternative) {
+                store.remove(&origin, service);
+                changed = true;
+            }
+            // An alternative speaks for the origin (RFC 7838 section 3).
+            if let Some(ad) = response.headers.get("Alt-Svc").and_then(altsvc::parse) {
+                changed |= store.update(&origin, &ad, now);
+            }
+            if changed {
+                save_state(opts, path, &store.to_text(now));
+            }
+        }
+
+        let location = response.headers.get("Location");
+        let code = response.status.code;
+        let (true, Some(location), 301 | 302 | 303 | 307 | 308) = (opts.follow, location, code)
+        else {
+            opts.middleware.on_response(&request, &response)?;
+            return Ok(response);
+        };
+        if hops.len() as u32 == opts.max_redirs {
+            return Err(Error::TooManyRedirects(opts.max_redirs));
+        }
+        let next = url.join(location)?;
+        let since = hops.last().map_or(Duration::ZERO, |hop| hop.at);
+        let hop = Hop::new(url, code, &next, metrics.elapsed());
+        let took = hop.at.saturating_sub(since);
+        hops.push(hop);
+
+        if opts.auto_referer {
+            let mut previous = url.without_userinfo();
+            previous.set_fragment(None);
+            referer = Some(previous.to_string());
+        }
+        // Like browsers, turn a redirected POST into a GET except for the
+        // method-preserving 307 and 308.
+        if code == 303 || (matches!(code, 301 | 302) && method == "POST") {
+            if method != "HEAD" {
+                method = "GET".to_string();
+            }
+            data = None;
+        }
+        if opts.verbose {
+            let target = next.without_userinfo().to_string();
+            let ms = took.as_millis();
+            eprintln!("* {}", tr(Msg::FollowingRedirect(code, &target, ms)));
+        }
+        *url = next;
+    }
+}
+
+/// Sends the request every `interval` and prints a line per attempt.
+/// Network failures are printed and retried. With `--until-status` the
+/// first matching status ends the run, and running out of
+/// `--max-iterations` before that is a failure.
+fn watch(
+    opts: &Options,
+    url: Url,
+    method: String,
+    data: Option<String>,
+    interval: Duration,
+    trace: Option<&Trace>,
+    limits: &http::Limits,
+) -> Result<()> {
+    let until = Expectations {
+        status: opts.until_status.clone(),
+        ..Expectations::default()
+    };
+    let mut iteration = 0;
+    loop {
+        iteration += 1;
+        let start = Instant::now();
+        let result = fetch(
+            opts,
+            url.clone(),
+            method.clone(),
+            data.clone(),
+            &[],
+            trace,
+            limits,
+        );
+        let elapsed = start.elapsed();
+        if let Some(collector) = &opts.collector {
+            match &result {
+                Ok(response) => collector.response(response.status.code, elapsed),
+                Err(_) => collector.error(),
+            }
+            write_metrics(opts)?;
+        }
+        let line = match &result {
+            Ok(response) => tr(Msg::WatchResponse(
+                &response.status,
+                response.body.len(),
+                elapsed,
+            )),
+            Err(e) if e.is_transient() => tr(Msg::Error(e)),
+            Err(_) => return result.map(|_| ()),
+        };
+        let mut stdout = Stdout;
+        writeln!(stdout, "{}  {}", trace::timestamp(), line)
+            .and_then(|()| stdout.flush())
+            .map_err(|source| Error::File {
+                path: "stdout".to_string(),
+                source,
+            })?;
+
+        let done = match &result {
+            Ok(response) => {
+                if opts.output.is_some() {
+                    write_output(opts, &response_output(opts, response)?)?;
+                }
+                !until.is_empty() && until.check(response).is_ok()
+            }
+            Err(_) => false,
+        };
+        if done {
+            return Ok(());
+        }
+        if opts.max_iterations == Some(iteration) {
+            return match result {
+                Ok(response) => until.check(&response),
+                Err(e) => Err(e),
+            };
+        }
+        thread::sleep(interval.saturating_sub(elapsed));
+    }
+}
+
+/// Writes `--metrics-file` by way of a temporary file, so whatever reads
+/// it never sees half of it.
+fn write_metrics(opts: &Options) -> Result<()> {
+    let (Some(path), Some(collector)) = (&opts.metrics_file, &opts.collector) else {
+        return Ok(());
+    };
+    let partial = format!("{}.tmp", path);
+    fs::write(&partial, collector.render())
+        .and_then(|()| fs::rename(&partial, path))
+        .map_err(|source| Error::File {
+            path: path.clone(),
+            source,
+        })
+}
+
+/// The alternative protocols this client can switch to.
+#[cfg(feature = "http2")]
+const USABLE_ALT_SVC: &[&str] = &["h2c", "http/1.1"];
+#[cfg(not(feature = "http2"))]
+const USABLE_ALT_SVC: &[&str] = &["http/1.1"];
+
+/// Writes an `--hsts` or `--alt-svc` file. Like the cache these are
+/// best-effort, so a failure is only mentioned with `-v`.
+fn save_state(opts: &Options, path: &str, text: &str) {
+    if let Err(source) = fs::write(path, text) {
+        if opts.verbose {
+            let e = Error::File {
+                path: path.to_string(),
+                source,
+            };
+            eprintln!("* {}", tr(Msg::Error(&e)));
+        }
+    }
+}
+
+/// Answers a GET from the cache when the stored response is fresh, and
+/// otherwise sends it with `send`, conditionally if there is a stale one.
+/// Failing to update the cache does not fail the request.
+fn send_cached(
+    opts: &Options,
+    cache: &Cache,
+    url: &Url,
+    mut headers: HeaderMap,
+    send: impl Fn(&HeaderMap) -> Result<Response>,
+) -> Result<Response> {
+    let mut key = url.without_userinfo();
+    key.set_fragment(None);
+    let key = key.to_string();
+    let now = date::now();
+    let stored = if opts.refresh { None } else { cache.get(&key) };
+    if let Some(entry) = &stored {
+        if entry.is_fresh(now) {
+            if opts.verbose {
+                eprintln!("* {}", tr(Msg::CacheHit(&key)));
+            }
+            return Ok(entry.response.clone());
+        }
+        // Conditions of our own would make a 304 ambiguous.
+        if !headers.contains("If-None-Match") && !headers.contains("If-Modified-Since") {
+            for (name, value) in entry.conditional_headers() {
+                headers.insert(name, value);
+            }
+        }
+    }
+    let response = send(&headers)?;
+    let store = |response: &Response| {
+        if let Err(e) = cache.put(&key, response, now) {
+            if opts.verbose {
+                eprintln!("* {}", tr(Msg::Error(&e)));
+            }
+        }
+    };
+    match stored {
+        Some(entry) if response.status.code == 304 && !entry.conditional_headers().is_empty() => {
+            if opts.verbose {
+                eprintln!("* {}", tr(Msg::CacheRevalidated(&key)));
+            }
+            let entry = entry.revalidate(&response, now);
+            store(&entry.response);
+            Ok(entry.response)
+        }
+        _ if cache::is_storable("GET", &response) => {
+            store(&response);
+            Ok(response)
+        }
+        _ => {
+            cache.remove(&key);
+            Ok(response)
+        }
+    }
+}
+
+/// What `-o` or stdout gets for a response: the head with `-i`, then the
+/// body, or what `--jq` picks from it, then any trailers with `-i`. A
+/// bodiless answer to OPTIONS shows its `Allow` headers instead.
+fn response_output(opts: &Options, response: &Response) -> Result<Vec<u8>> {
+    let mut output = Vec::new();
+    if opts.include {
+        output.extend_from_slice(response.head_text().as_bytes());
+    } else if response.body.is_empty() && request_method(opts) == "OPTIONS" {
+        // The answer to OPTIONS is in its headers, which would otherwise
+        // not be shown at all.
+        for name in ["Allow", "Access-Control-Allow-Methods"] {
+            for value in response.headers.get_all(name) {
+                output.extend_from_slice(format!("{}: {}\n", name, value).as_bytes());
+            }
+        }
+    }
+    output.extend_from_slice(&reshaped(opts, &response.body)?);
+    if opts.include {
+        output.extend_from_slice(response.trailer_text().as_bytes());
+    }
+    Ok(output)
+}
+
+/// `body` as `--jq` and `--pretty` print it.
+#[cfg(feature = "json")]
+fn reshaped<'a>(opts: &Options, body: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+    Ok(match &opts.jq {
+        Some(filter) => Cow::Owned(filter.render(body, opts.pretty)?.into_bytes()),
+        // Anything but JSON is left as it is.
+        None if opts.pretty => match Filter::default().render(body, true) {
+            Ok(text) => Cow::Owned(text.into_bytes()),
+            Err(_) => Cow::Borrowed(body),
+        },
+        None => Cow::Borrowed(body),
+    })
+}
+
+/// Without the `json` feature `--jq` and `--pretty` are refused up front.
+#[cfg(not(feature = "json"))]
+fn reshaped<'a>(_: &Options, body: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+    Ok(Cow::Borrowed(body))
+}
+
+/// Runs every request of a `.http` file in order and writes the responses
+/// one after another: to stdout as they arrive, to `-o` at the end. The
+/// first failure stops the run; what was received until then is still
+/// written.
+fn run_request_file(
+    opts: &Options,
+    path: &str,
+    mut vars: Vars,
+    trace: Option<&Trace>,
+    limits: &http::Limits,
+) -> Result<()> {
+    let file = httpfile::parse(&read_text(path)?)?;
+    // File variables are expanded where they are used, so they can refer
+    // to --env values and to responses of earlier requests.
+    for (name, value) in &file.variables {
+        vars.define(name.as_str(), value.as_str());
+    }
+    let requests = file.requests;
+
+    let mut output = Vec::new();
+    let mut result = Ok(());
+    for (i, request) in requests.iter().enumerate() {
+        eprintln!(
+            "* {}",
+            tr(Msg::RunningRequest(
+                i + 1,
+                requests.len(),
+                &request.method,
+                &request.url
+            ))
+        );
+        let response = expand_request(&vars, request).and_then(|(url, body, headers)| {
+            let url = parse_url(opts, &url)?;
+            let method = request.method.clone();
+            fetch(opts, url, method, body, &headers, trace, limits)
+        });
+        let response = match response {
+            Ok(response) => response,
+            Err(e) => {
+                result = Err(e);
+                break;
+            }
+        };
+        // Keep the next response, and the next progress line, apart.
+        let mut chunk = response_output(opts, &response)?;
+        if !chunk.is_empty() && !chunk.ends_with(b"\n") {
+            chunk.push(b'\n');
+        }
+        if opts.output.is_some() {
+            output.extend(chunk);
+        } else {
+            write_output(opts, &chunk)?;
+        }
+        if let Err(e) = opts.expect.check(&response) {
+            result = Err(e);
+            break;
+        }
+        if let Some(name) = &request.name {
+            vars.add_response(name.as_str(), response);
+        }
+    }
+    if opts.output.is_some() {
+        write_output(opts, &output)?;
+    }
+    result
+}
+
+/// `--request-file`: sends a pre-composed request to the URL's host and
+/// port, or failing that to the file's `Host`.
+fn send_request_file(
+    opts: &Options,
+    path: &str,
+    trace: Option<&Trace>,
+    limits: &http::Limits,
+) -> Result<()> {
+    let mut request = read_bytes(path)?;
+    let url = match (&opts.url, raw::header(&request, "Host")) {
+        (Some(url), _) => parse_url(opts, url)?,
+        (None, Some(host)) => parse_url(opts, &format!("http://{}/", host))?,
+        (None, None) => return Err(Error::Usage(tr(Msg::NoUrl))),
+    };
+    if !matches!(url.scheme(), "http" | "https") {
+        return Err(Error::UnsupportedProtocol(url.scheme().to_string()));
+    }
+    let limits = &limits.for_url(&url)?;
+    if opts.fix_request {
+        request = raw::fix_up(&request, &url.host_header());
+    }
+    let response = http::send_raw(
+        url.connect_host(),
+        url.port_or_default(),
+        &request,
+        opts.verbose,
+        trace,
+        None,
+        limits,
+    )?;
+    write_output(opts, &response_output(opts, &response)?)?;
+    opts.expect.check(&response)
+}
+
+/// Reads the session `name`, which starts out empty, and applies the
+/// command line's header removals to it.
+fn load_session(opts: &Options, name: &str) -> Result<(String, Mutex<Session>)> {
+    let path = session::path(name).ok_or_else(|| Error::Usage(tr(Msg::NoSessionDir(name))))?;
+    let path = path.to_string_lossy().into_owned();
+    let mut session = match fs::read_to_string(&path) {
+        Ok(text) => Session::parse(&text).ok_or_else(|| Error::ReadFile {
+            path: path.clone(),
+            source: io::Error::new(io::ErrorKind::InvalidData, tr(Msg::NotASession)),
+        })?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Session::new(),
+        Err(source) => return Err(Error::ReadFile { path, source }),
+    };
+    for header in &opts.headers {
+        if let CustomHeader::Remove(name) = header {
+            session.headers.remove(name);
+        }
+    }
+    if !opts.session_read_only {
+        if let Some(dir) = Path::new(&path)
+            .parent()
+            .filter(|d| !d.as_os_str().is_empty())
+        {
+            fs::create_dir_all(dir).map_err(|source| Error::File {
+                path: dir.display().to_string(),
+                source,
+            })?;
+        }
+    }
+    Ok((path, Mutex::new(session)))
+}
+
+/// Updates the session with a response to `url` and, for the request the
+/// transfer started with, the headers it was sent with: those from `-H`
+/// and any credentials.
+fn update_session(opts: &Options, url: &Url, sent: Option<&HeaderMap>, response: &Response) {
+    let Some((path, session)) = &opts.session_file else {
+        return;
+    };
+    let mut session = session.lock().unwrap_or_else(|e| e.into_inner());
+    session.cookies.store(url, &response.headers);
+    if let Some(sent) = sent {
+        let mut kept: HeaderMap = opts
+            .headers
+            .iter()
+            .filter_map(|header| match header {
+                CustomHeader::Set(name, value) => Some((name.as_str(), value.as_str())),
+                CustomHeader::Remove(_) => None,
+            })
+            .collect();
+        // A signature only fits the request it was made for.
+        if let (Some(value), None) = (sent.get("Authorization"), &opts.aws_sigv4) {
+            kept.insert("Authorization", value);
+        }
+        session.remember(&kept);
+    }
+    if !opts.session_read_only {
+        save_state(opts, path, &session.to_text());
+    }
+}
+
+/// Reads the cassette at `path`. One being recorded starts out empty; one
+/// being replayed has to exist.
+fn load_cassette(path: &str, replay: bool) -> Result<Cassette> {
+    match fs::read_to_string(path) {
+        Ok(text) => Cassette::parse(&text).ok_or_else(|| Error::ReadFile {
+            path: path.to_string(),
+            source: io::Error::new(io::ErrorKind::InvalidData, tr(Msg::NotACassette)),
+        }),
+        Err(e) if e.kind() == io::ErrorKind::NotFound && !replay => Ok(Cassette::new()),
+        Err(source) => Err(Error::ReadFile {
+            path: path.to_string(),
+            source,
+        }),
+    }
+}
+
+/// The recorded response to this request under `--replay`, or `None` when
+/// not replaying. A request that was never recorded fails as if the server
+/// were unreachable, so nothing falls through to the network.
+fn replay(opts: &Options, method: &str, url: &Url, body: Option<&str>) -> Result<Option<Response>> {
+    let (Some(cassette), true) = (&opts.cassette_file, opts.replay) else {
+        return Ok(None);
+    };
+    let key = cassette_key(url);
+    let mut cassette = cassette.lock().unwrap_or_else(|e| e.into_inner());
+    match cassette.replay(method, &key, body) {
+        Some(response) => {
+            if opts.verbose {
+                eprintln!("* {}", tr(Msg::Replaying(method, &key)));
+            }
+            Ok(Some(response))
+        }
+        None => Err(Error::Connect {
+            host: url.host().to_string(),
+            port: url.port_or_default(),
+            source: io::Error::new(io::ErrorKind::NotFound, tr(Msg::NotRecorded(method, &key))),
+        }),
+    }
+}
+
+/// What a cassette knows a request's URL by: neither credentials nor the
+/// fragment are sent.
+fn cassette_key(url: &Url) -> String {
+    let mut url = url.without_userinfo();
+    url.set_fragment(None);
+    url.to_string()
+}
+
+/// Adds a response to the cassette under `--record` and saves it.
+fn record(opts: &Options, method: &str, url: &Url, body: Option<&str>, response: &Response) {
+    let (Some(cassette), Some(path), false) = (&opts.cassette_file, &opts.cassette, opts.replay)
+    else {
+        return;
+    };
+    let mut cassette = cassette.lock().unwrap_or_else(|e| e.into_inner());
+    let key = cassette_key(url);
+    cassette.record(method, &key, body, response);
+    save_state(opts, path, &cassette.to_text());
+}
+
+/// Reads a whole file as bytes, or stdin for `-`.
+fn read_bytes(path: &str) -> Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let read = if path == "-" {
+        io::stdin().read_to_end(&mut bytes).map(|_| ())
+    } else {
+        fs::read(path).map(|b| bytes = b)
+    };
+    read.map_err(|source| Error::ReadFile {
+        path: path.to_string(),
+        source,
+    })?;
+    Ok(bytes)
+}
+
+/// Reads a whole text file, or stdin for `-`.
+fn read_text(path: &str) -> Result<String> {
+    let mut text = String::new();
+    let read = if path == "-" {
+        io::stdin().read_to_string(&mut text).map(|_| ())
+    } else {
+        fs::read_to_string(path).map(|t| text = t)
+    };
+    read.map_err(|source| Error::ReadFile {
+        path: path.to_string(),
+        source,
+    })?;
+    Ok(text)
+}
+
+fn expand_header(vars: &Vars, header: &CustomHeader) -> Result<CustomHeader> {
+    match header {
+        CustomHeader::Set(name, value) => Ok(CustomHeader::Set(name.clone(), vars.expand(value)?)),
+        CustomHeader::Remove(_) => Ok(header.clone()),
+    }
+}
+
+/// The URL, body and headers of a file request with placeholders filled.
+fn expand_request(
+    vars: &Vars,
+    request: &FileRequest,
+) -> Result<(String, Option<String>, Vec<CustomHeader>)> {
+    let url = vars.expand(&request.url)?;
+    let body = request
+        .body
+        .as_deref()
+        .map(|b| vars.expand(b))
+        .transpose()?;
+    let headers = request
+        .headers
+        .iter()
+        .map(|header| expand_header(vars, header))
+        .collect::<Result<_>>()?;
+    Ok((url, body, headers))
+}
+
+/// `output` made fit for a terminal: binary bodies are refused, and text
+/// in another charset is decoded to UTF-8 unless `--no-transcode` is given.
+fn terminal_output(opts: &Options, response: &Response, output: Vec<u8>) -> Result<Vec<u8>> {
+    if response.is_binary() {
+        return Err(Error::File {
+            path: "stdout".to_string(),
+            source: io::Error::other(tr(Msg::BinaryOutput)),
+        });
+    }
+    // What --jq, --pretty and --list-links print is UTF-8 already.
+    if opts.jq.is_some() || opts.pretty || opts.list_links {
+        return Ok(output);
+    }
+    let content_type = response.headers.get("Content-Type");
+    let text = match charset::to_utf8(content_type, &response.body) {
+        Some(text) if !opts.no_transcode => text,
+        _ => return Ok(output),
+    };
+    let mut output = Vec::new();
+    if opts.include {
+        output.extend_from_slice(response.head_text().as_bytes());
+    }
+    output.extend_from_slice(text.as_bytes());
+    if opts.include {
+        output.extend_from_slice(response.trailer_text().as_bytes());
+    }
+    Ok(output)
+}
+
+/// `--list-links`: the head with `-i`, then the links of the page `url`
+/// answered with, one per line.
+fn link_list(opts: &Options, url: &Url, response: &Response) -> Vec<u8> {
+    let mut output = Vec::new();
+    if opts.include {
+        output.extend_from_slice(response.head_text().as_bytes());
+    }
+    for link in links::extract(&response.text(), url) {
+        output.extend_from_slice(format!("{}\n", link.url).as_bytes());
+    }
+    if opts.include {
+        output.extend_from_slice(response.trailer_text().as_bytes());
+    }
+    output
+}
+
+/// Writes the result to `-o` or stdout.
+fn write_output(opts: &Options, output: &[u8]) -> Result<()> {
+    match &opts.output {
+        Some(path) => write_file(opts, path, false, output),
+        None => write_stdout(output),
+    }
+}
+
+/// The `--expected-hash` digest. A sidecar file may list several files;
+/// the one named like the URL's last path segment is used.
+fn expected_hash(opts: &Options, url: &Url) -> Result<Option<Checksum>> {
+    let Some(spec) = &opts.expected_hash else {
+        return Ok(None);
+    };
+    let (algorithm, path) = match spec.split_once('@') {
+        None => return Ok(Checksum::parse(spec)),
+        Some(("", path)) => (Algorithm::for_sidecar(path), path),
+        Some((name, path)) => (Algorithm::parse(name.trim_end_matches(':')), path),
+    };
+    let invalid = || Error::Usage(tr(Msg::InvalidValue("--expected-hash", spec)));
+    let algorithm = algorithm.ok_or_else(invalid)?;
+    let name = batch::output_name("#{name}", 1, url, &[]);
+    Checksum::from_sidecar(algorithm, &read_text(path)?, &name)
+        .map(Some)
+        .ok_or_else(invalid)
+}
+
+/// The file `-O` writes to, and whether it must not exist yet: with `-J`,
+/// the sanitized `Content-Disposition` name, as a server should not be able
+/// to replace a file it did not create; otherwise the last segment of the
+/// URL's path.
+fn remote_name(opts: &Options, url: &Url, response: &Response) -> Option<(String, bool)> {
+    if !opts.remote_name {
+        return None;
+    }
+    let suggested = opts
+        .remote_header_name
+        .then(|| response.headers.get("Content-Disposition"))
+        .flatten()
+        .and_then(disposition::filename)
+        .and_then(|name| disposition::sanitize(&name));
+    Some(match suggested {
+        Some(name) => (name, true),
+        None => (batch::output_name("#{name}", 1, url, &[]), false),
+    })
+}
+
+/// Writes an output file, under `--output-dir` if `path` is relative and
+/// creating missing directories with `--create-dirs`. With `fresh`, an
+/// existing file is an error rather than replaced.
+fn write_file(opts: &Options, path: &str, fresh: bool, output: &[u8]) -> Result<()> {
+    if path == "-" {
+        return write_stdout(output);
+    }
+    let (mut file, path) = create_file(opts, path, fresh)?;
+    file.write_all(output).map_err(|source| Error::File {
+        path: path.display().to_string(),
+        source,
+    })
+}
+
+/// A URL from the command line or a file. One without a scheme, such as
+/// `example.com/path`, gets `--proto-default`'s, as curl and browsers
+/// would, instead of being refused.
+fn parse_url(opts: &Options, input: &str) -> Result<Url> {
+    let input = input.trim();
+    let has_scheme = input.split_once("://").is_some_and(|(scheme, _)| {
+        !scheme.is_empty()
+            && scheme
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b"+-.".contains(&b))
+    });
+    let mut url = match has_scheme {
+        true => Url::parse_with_idn(input, !opts.no_idn)?,
+        false => {
+            let scheme = opts.proto_default.as_deref().unwrap_or("http");
+            Url::parse_with_idn(&format!("{}://{}", scheme, input), !opts.no_idn)?
+        }
+    };
+    https_only(opts, &mut url)?;
+    Ok(url)
+}
+
+/// With `--https-only`, switches an http `url` to https, or a ws one to
+/// wss, and refuses one that would still go over the network in plain
+/// text. There is no going back: a server that does not speak TLS fails
+/// the transfer.
+fn https_only(opts: &Options, url: &mut Url) -> Result<()> {
+    if !opts.https_only {
+        return Ok(());
+    }
+    match url.scheme() {
+        "https" | "wss" | "file" => Ok(()),
+        "http" | "ws" => {
+            url.upgrade_to_tls();
+            if opts.verbose {
+                eprintln!("* {}", tr(Msg::HttpsOnlyUpgrade(url.host(), url.scheme())));
+            }
+            Ok(())
+        }
+        scheme => Err(Error::UnsupportedProtocol(format!(
+            "{} (--https-only)",
+            scheme
+        ))),
+    }
+}
+
+/// Adds a response head, and its trailers, to the `-D` file, which [`run`]
+/// emptied first.
+fn dump_header(opts: &Options, response: &Response) -> Result<()> {
+    let Some(path) = &opts.dump_header else {
+        return Ok(());
+    };
+    let head = response.head_text() + &response.trailer_text();
+    if path == "-" {
+        return write_stdout(head.as_bytes());
+    }
+    fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .and_then(|mut file| file.write_all(head.as_bytes()))
+        .map_err(|source| Error::File {
+            path: path.clone(),
+            source,
+        })
+}
+
+/// Opens an output file for [`write_file`], returning where it really is:
+/// with `--no-clobber`, beside a file already there rather than over it.
+fn create_file(opts: &Options, path: &str, fresh: bool) -> Result<(fs::File, PathBuf)> {
+    let path = match &opts.output_dir {
+        Some(dir) if Path::new(path).is_relative() => Path::new(dir).join(path),
+        _ => PathBuf::from(path),
+    };
+    let error = |source| Error::File {
+        path: path.display().to_string(),
+        source,
+    };
+    if opts.create_dirs {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent).map_err(error)?;
+        }
+    }
+    let open = |path: &Path| match fresh || opts.no_clobber {
+        true => fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(path),
+        false => fs::File::create(path),
+    };
+    let mut file = open(&path);
+    // --no-clobber: file.txt is taken, so file.txt.1, file.txt.2, ...
+    let mut taken = path.clone();
+    for n in 1..=100 {
+        if !opts.no_clobber
+            || !file
+                .as_ref()
+                .is_err_and(|e| e.kind() == io::ErrorKind::AlreadyExists)
+        {
+            break;
+        }
+        taken = PathBuf::from(format!("{}.{}", path.display(), n));
+        file = open(&taken);
+    }
+    Ok((file.map_err(error)?, taken))
+}
+
+/// How much of a download is collected before it is written to its file.
+const DOWNLOAD_BUFFER: usize = 1 << 20;
+
+/// Writes out the final response as it arrives rather than once it is
+/// all in. A body bound for a file or a pipe goes there as it comes, so it
+/// is never held in memory whole and a reader that stops early stops the
+/// transfer; with `-i` to a terminal the head is printed as soon as it is
+/// read, ahead of a slow body. Responses that are only a step on the way,
+/// redirects to follow and proxy challenges to answer, are read in full as
+/// usual.
+pub struct Spool {
+    to: SpoolTo,
+    /// Whether a JSON Lines body goes to stdout a record at a time.
+    #[cfg(feature = "json")]
+    records: bool,
+    /// The size of the body saved, once one has been.
+    saved: Mutex<Option<u64>>,
+    /// The head printed, so the output can leave it out.
+    head_shown: Mutex<Option<String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SpoolTo {
+    /// The body goes into the output file.
+    File,
+    /// The body goes to stdout, which is not a terminal.
+    Stdout,
+    /// Only the head goes out early, to stdout.
+    Head,
+    /// Nothing goes out early unless the body is JSON Lines.
+    Records,
+}
+
+impl Spool {
+    /// A spool for the transfer, if its output can be written early. A
+    /// body can go out as it arrives when nothing needs it in memory: the
+    /// cache, a cassette, middleware, `--tee`, `--expected-hash` and
+    /// `--expect-body` all do, and so does a terminal, which is shown
+    /// text rather than bytes. Failing that, a head on stdout can be
+    /// printed early unless the whole output has to pass `--expected-hash`
+    /// first, or the cache may answer in its place. Output to stdout is
+    /// held back in a `--next` run, whose connections are only shared
+    /// with transfers read whole.
+    ///
+    /// A JSON Lines body on stdout goes out a record at a time, through
+    /// `--jq` and `--pretty`, whenever nothing else needs it.
+    fn for_run(opts: &Options) -> Option<Spool> {
+        let to_file = opts.remote_name || opts.output.as_deref().is_some_and(|path| path != "-");
+        let cached = opts.cache_dir.is_some() && !opts.no_cache;
+        let needs_body = opts.tee.is_some()
+            || opts.expected_hash.is_some()
+            || cached
+            || opts.cassette.is_some()
+            || !opts.middleware.is_empty()
+            || !opts.expect.body_contains.is_empty()
+            || opts.list_links
+            // Compressed bodies are decoded whole.
+            || opts.compressed
+            // An empty answer is replaced by its Allow headers.
+            || request_method(opts) == "OPTIONS";
+        let reshaped = opts.jq.is_some() || opts.pretty;
+        let records = cfg!(feature = "json")
+            && !to_file
+            && !needs_body
+            && opts.dump_header.as_deref() != Some("-")
+            && opts.connections.is_none();
+        let to = if to_file {
+            (!needs_body && !reshaped).then_some(SpoolTo::File)
+        } else if opts.dump_header.as_deref() == Some("-") {
+            // The head is dumped ahead of the body once it is all in.
+            None
+        } else if opts.connections.is_some() {
+            None
+        } else if !needs_body && !reshaped && !io::stdout().is_terminal() {
+            Some(SpoolTo::Stdout)
+        } else {
+            let early_head = opts.include && opts.expected_hash.is_none() && !cached;
+            early_head.then_some(SpoolTo::Head)
+        };
+        let to = to.or(records.then_some(SpoolTo::Records));
+        to.map(|to| Spool {
+            to,
+            #[cfg(feature = "json")]
+            records,
+            saved: Mutex::new(None),
+            head_shown: Mutex::new(None),
+        })
+    }
+
+    /// The size of the body saved, if the final response has been.
+    fn saved(&self) -> Option<u64> {
+        *self.saved.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// The last head printed early, if any was.
+    fn head_shown(&self) -> Option<String> {
+        self.head_shown
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    /// Finishes reading `response` to a request for `url`. A final one
+    /// being downloaded goes into its file, and the returned response has
+    /// an empty body but the trailers; anything else is read into memory, after showing the
+    /// head of a final one.
+    fn finish(
+        &self,
+        opts: &Options,
+        url: &Url,
+        mut response: http::StreamingResponse,
+        limits: &http::Limits,
+    ) -> Result<Response> {
+        let code = response.status.code;
+        let redirect = opts.follow
+            && response.headers.contains("Location")
+            && matches!(code, 301 | 302 | 303 | 307 | 308);
+        // A 407 the proxy credentials may answer is only read, not shown.
+        let challenge = code == 407 && opts.proxy.is_some() && proxy_credentials(opts).is_some();
+        if redirect || challenge {
+            return response.into_response(limits);
+        }
+        let mut head = Response {
+            status: response.status.clone(),
+            headers: response.headers.clone(),
+            body: Vec::new(),
+            trailers: HeaderMap::new(),
+        };
+        #[cfg(feature = "json")]
+        if self.records
+            && head
+                .headers
+                .get("Content-Type")
+                .is_some_and(ndjson::is_json_lines)
+        {
+            if opts.include {
+                write_stdout(head.head_text().as_bytes())?;
+            }
+            let mut out = ndjson::Records::new(Stdout, opts.jq.clone(), opts.pretty);
+            let copied = http::copy_body(&mut response.body, &mut out, limits);
+            let size = out.finish(copied)?;
+            *self.saved.lock().unwrap_or_else(|e| e.into_inner()) = Some(size);
+            head.trailers = response.body.take_trailers();
+            return Ok(head);
+        }
+        if self.to == SpoolTo::Records {
+            return response.into_response(limits);
+        }
+        if self.to == SpoolTo::Head {
+            let text = head.head_text();
+            write_stdout(text.as_bytes())?;
+            *self.head_shown.lock().unwrap_or_else(|e| e.into_inner()) = Some(text);
+            return response.into_response(limits);
+        }
+        if self.to == SpoolTo::Stdout {
+            let mut out = Stdout;
+            if opts.include {
+                write_stdout(head.head_text().as_bytes())?;
+            }
+            let size = http::copy_body(&mut response.body, &mut out, limits)?;
+            *self.saved.lock().unwrap_or_else(|e| e.into_inner()) = Some(size);
+            head.trailers = response.body.take_trailers();
+            if opts.include {
+                write_stdout(head.trailer_text().as_bytes())?;
+            }
+            return Ok(head);
+        }
+        let (path, fresh) = remote_name(opts, url, &head)
+            .unwrap_or_else(|| (opts.output.clone().unwrap_or_default(), false));
+        if opts.verbose && opts.remote_name {
+            eprintln!("* {}", tr(Msg::SavingTo(&path)));
+        }
+        // Rather than leave an empty file behind.
+        if let (Framing::Length(length), Some(max)) = (response.body.framing(), limits.max_filesize)
+        {
+            if length > max {
+                return Err(Error::FileSizeExceeded(max));
+            }
+        }
+        // A retry starts the file it made over.
+        let fresh = fresh && self.saved().is_none();
+        let (file, path) = create_file(opts, &path, fresh)?;
+        let mut out = io::BufWriter::with_capacity(DOWNLOAD_BUFFER, file);
+        if opts.include {
+            out.write_all(head.head_text().as_bytes())
+                .map_err(|source| Error::File {
+                    path: path.display().to_string(),
+                    source,
+                })?;
+        }
+        let size = http::copy_body(&mut response.body, &mut out, limits)?;
+        *self.saved.lock().unwrap_or_else(|e| e.into_inner()) = Some(size);
+        head.trailers = response.body.take_trailers();
+        if opts.include {
+            out.write_all(head.trailer_text().as_bytes())
+                .and_then(|()| out.flush())
+                .map_err(|source| Error::File {
+                    path: path.display().to_string(),
+                    source,
+                })?;
+        }
+        Ok(head)
+    }
+}
+
+/// Set once a write to stdout fails because its reader has gone away.
+static STDOUT_CLOSED: AtomicBool = AtomicBool::new(false);
+
+/// Stdout, noting in [`STDOUT_CLOSED`] when the reader has gone away, as
+/// when the output is piped into `head`. The failed write still fails
+/// whatever was writing, which stops the transfer; `main` then exits
+/// without a word and with 0.
+struct Stdout;
+
+impl Stdout {
+    fn watch<T>(result: io::Result<T>) -> io::Result<T> {
+        if result
+            .as_ref()
+            .is_err_and(|e| e.kind() == io::ErrorKind::BrokenPipe)
+        {
+            STDOUT_CLOSED.store(true, Ordering::Relaxed);
+        }
+        result
+    }
+}
+
+impl Write for Stdout {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Stdout::watch(io::stdout().write(buf))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Stdout::watch(io::stdout().flush())
+    }
+}
+
+fn stdout_closed() -> bool {
+    STDOUT_CLOSED.load(Ordering::Relaxed)
+}
+
+fn write_stdout(output: &[u8]) -> Result<()> {
+    let mut stdout = Stdout;
+    stdout
+        .write_all(output)
+        .and_then(|()| stdout.flush())
+        .map_err(|source| Error::File {
+            path: "stdout".to_string(),
+            source,
+        })
+}
+
+/// Prints events until the server ends the stream for good. Network
+/// failures and clean ends of the stream both lead to a reconnect after
+/// the server's retry delay.
+fn stream_events(
+    mut source: EventSource,
+    opts: &Options,
+    trace: Option<&Trace>,
+    limits: &http::Limits,
+) -> Result<()> {
+    let mut stdout = Stdout;
+    let mut print = |event: &Event| {
+        stdout
+            .write_all(event.to_wire().as_bytes())
+            .and_then(|()| stdout.flush())
+            .map_err(|source| Error::File {
+                path: "stdout".to_string(),
+                source,
+            })
+    };
+    loop {
+        let failure = match source.connect(opts.verbose, trace, limits, &mut print) {
+            Ok(()) => None,
+            Err(e) if e.is_transient() => Some(e),
+            Err(e) => return Err(e),
+        };
+        let delay = source.retry();
+        if opts.verbose {
+            let ms = delay.as_millis();
+            eprintln!("* {}", tr(Msg::SseReconnecting(failure.as_ref(), ms)));
+        }
+        thread::sleep(delay);
+    }
+}
+
+/// The complete header set for one request: built-ins, the convenience
+/// flags, then `extra` and `-H` overrides. Unless an Authorization header
+/// was supplied, `--oauth2-bearer` is sent, or else the credentials
+/// embedded in the URL or found in the netrc file become Basic auth.
+/// With `--aws-sigv4` that is left to [`sign_aws`].
+///
+/// A host that is not `trusted`, one a redirect led to, is sent none of
+/// the credentials meant for the first: no Authorization or Cookie from
+/// `extra` or `-H`, and no bearer token. Its own URL and netrc
+/// credentials are still used.
+fn request_headers(
+    opts: &Options,
+    url: &Url,
+    data: Option<&str>,
+    referer: Option<&str>,
+    extra: &[CustomHeader],
+    trusted: bool,
+) -> HeaderMap {
+    let mut headers = http::default_headers(&url.host_header(), data.map(str::len));
+    if opts.bench || opts.interactive || shared_connections(opts).is_some() {
+        // Keep the connection open for the next request.
+        headers.remove("Connection");
+    }
+    let session = opts
+        .session_file
+        .as_ref()
+        .map(|(_, session)| session.lock().unwrap_or_else(|e| e.into_inner()));
+    if let Some(session) = &session {
+        session.apply(&mut headers);
+        // Credentials on the command line replace the saved ones.
+        if opts.oauth2_bearer.is_some() || credentials(opts, url).is_some() {
+            headers.remove("Authorization");
+        }
+    }
+    match opts.user_agent.as_deref() {
+        Some("") => {
+            headers.remove("User-Agent");
+        }
+        Some(agent) => headers.insert("User-Agent", agent),
+        None => {}
+    }
+    if let Some(referer) = referer {
+        headers.insert("Referer", referer);
+    }
+    headers::apply_custom(&mut headers, extra);
+    headers::apply_custom(&mut headers, &opts.headers);
+    #[cfg(feature = "compression")]
+    if opts.compressed && !headers.contains("Accept-Encoding") {
+        headers.insert("Accept-Encoding", coding::accept_encoding());
+    }
+    if !trusted {
+        let dropped = headers.remove("Authorization") | headers.remove("Cookie");
+        if opts.verbose && (dropped || opts.oauth2_bearer.is_some()) {
+            eprintln!("* {}", tr(Msg::DroppingCredentials(url.host())));
+        }
+    }
+    // The session's cookies know where they may go.
+    let cookies = session.and_then(|session| session.cookies.header(url));
+    if let (Some(cookies), false) = (cookies, headers.contains("Cookie")) {
+        headers.append("Cookie", cookies);
+    }
+    if opts.aws_sigv4.is_some() || opts.ntlm || opts.negotiate {
+        return headers;
+    }
+    if let (Some(token), true) = (&opts.oauth2_bearer, trusted) {
+        if !headers.contains("Authorization") {
+            headers.append("Authorization", format!("Bearer {}", token));
+        }
+    }
+    if !headers.contains("Authorization") {
+        if let Some((user, password)) = credentials(opts, url) {
+            headers.append("Authorization", auth::basic(&user, &password));
+        }
+    }
+    headers
+}
+
+/// The user and password to authenticate to `url` with: its own, or else
+/// the netrc entry for its host.
+fn credentials(opts: &Options, url: &Url) -> Option<(String, String)> {
+    let mut user = url.username();
+    let mut password = url.password();
+    if password.is_none() {
+        let entry = opts
+            .credentials
+            .as_ref()
+            .and_then(|netrc| netrc.find(url.host(), user.as_deref()));
+        if let Some(entry) = entry {
+            user = entry.login.clone().or(user);
+            password = entry.password.clone();
+        }
+    }
+    Some((user?, password.unwrap_or_default()))
+}
+
+/// The connections of a `--next` command line, if this transfer can go
+/// over one: a [`PersistentConnection`] neither traces, times, streams
+/// nor limits speed, and a proxy is left to a connection of its own.
+fn shared_connections(opts: &Options) -> Option<&cli::Connections> {
+    let plain = opts.spool.is_none()
+        && opts.trace.is_none()
+        && opts.write_out.is_none()
+        && opts.meta_json.is_none()
+        && !opts.timings
+        && opts.collector.is_none()
+        && opts.speed_limit.is_none()
+        && opts.limit_rate.is_none()
+        && opts.proxy.is_none();
+    opts.connections.as_deref().filter(|_| plain)
+}
+
+/// Sends `request` over the connection to its host an earlier transfer
+/// left open, or else a new one, which is kept for the next if the server
+/// allows.
+fn send_shared(
+    opts: &Options,
+    connections: &cli::Connections,
+    request: &Exchange,
+) -> Result<Response> {
+    let Exchange {
+        host, port, limits, ..
+    } = *request;
+    let mut connections = connections.lock().unwrap_or_else(|e| e.into_inner());
+    let key = (host.to_string(), port, limits.tls.is_some());
+    let mut conn = connections
+        .remove(&key)
+        .unwrap_or_else(|| PersistentConnection::new(host, port))
+        .verbose(opts.verbose);
+    let response = conn.send(
+        request.method,
+        request.target,
+        request.headers,
+        request.body,
+        limits,
+    );
+    if conn.is_open() {
+        connections.insert(key, conn);
+    }
+    response
+}
+
+/// Sends a request with `--ntlm` or `--negotiate`. The handshake needs one
+/// connection throughout, so this is always HTTP/1.1 without `--trace`.
+fn send_ntlm(opts: &Options, request: &Exchange, identity: &ntlm::Identity) -> Result<Response> {
+    let scheme = if opts.negotiate { "Negotiate" } else { "NTLM" };
+    let mut headers = request.headers.clone();
+    headers.remove("Connection");
+    let mut conn = PersistentConnection::new(request.host, request.port).verbose(opts.verbose);
+    let request = Exchange {
+        headers: &headers,
+        ..*request
+    };
+    ntlm::send(&mut conn, scheme, identity, &request)
+}
+
+/// The proxy's user and password: `-U`, or else the userinfo of `-x`.
+fn proxy_credentials(opts: &Options) -> Option<(String, String)> {
+    if let Some(user) = &opts.proxy_user {
+        let (user, password) = user.split_once(':').unwrap_or((user, ""));
+        return Some((user.to_string(), password.to_string()));
+    }
+    let proxy = opts.proxy.as_ref()?;
+    Some((
+        proxy.user.clone()?,
+        proxy.password.clone().unwrap_or_default(),
+    ))
+}
+
+/// Adds what only a proxy should see: Basic credentials, unless Digest was
+/// asked for or `-H` sets them, and the `--proxy-header`s.
+fn add_proxy_headers(opts: &Options, headers: &mut HeaderMap) {
+    if opts.proxy.is_none() {
+        return;
+    }
+    if let (false, Some((user, password))) = (opts.proxy_digest, proxy_credentials(opts)) {
+        if !headers.contains("Proxy-Authorization") {
+            headers.insert("Proxy-Authorization", auth::basic(&user, &password));
+        }
+    }
+    headers::apply_custom(headers, &opts.proxy_headers);
+}
+
+/// The `Proxy-Authorization` answering a 407 to a request sent with
+/// `headers`, if the proxy credentials can answer one of its challenges:
+/// Digest, which is preferred, or Basic when the request did not already
+/// carry Basic credentials (as it does unless `--proxy-digest` is given).
+fn proxy_answer(
+    opts: &Options,
+    method: &str,
+    target: &str,
+    headers: &HeaderMap,
+    response: &Response,
+) -> Result<Option<String>> {
+    if opts.proxy.is_none() || response.status.code != 407 {
+        return Ok(None);
+    }
+    let Some((user, password)) = proxy_credentials(opts) else {
+        return Ok(None);
+    };
+    let challenges: Vec<auth::Challenge> = response
+        .headers
+        .get_all("Proxy-Authenticate")
+        .filter_map(auth::Challenge::parse)
+        .collect();
+    let offered = |scheme: &str| {
+        challenges
+            .iter()
+            .find(|c| c.scheme.eq_ignore_ascii_case(scheme))
+    };
+    if let Some(challenge) = offered("Digest") {
+        let cnonce = auth::cnonce();
+        return auth::digest(challenge, &user, &password, method, target, &cnonce).map(Some);
+    }
+    let sent_basic = headers
+        .get("Proxy-Authorization")
+        .is_some_and(|value| value.starts_with("Basic "));
+    match offered("Basic") {
+        Some(_) if !sent_basic => Ok(Some(auth::basic(&user, &password))),
+        _ => Ok(None),
+    }
+}
+
+/// The schemes a 407's challenges offer, such as `Basic, Digest`.
+fn proxy_schemes(response: &Response) -> String {
+    let schemes: Vec<String> = response
+        .headers
+        .get_all("Proxy-Authenticate")
+        .filter_map(auth::Challenge::parse)
+        .map(|challenge| challenge.scheme)
+        .collect();
+    match schemes.is_empty() {
+        true => "none".to_string(),
+        false => schemes.join(", "),
+    }
+}
+
+/// Signs a request for `--aws-sigv4` once its other headers are final,
+/// with the key and secret in the URL or else from the environment or
+/// profile. An Authorization header from `-H` leaves it unsigned.
+fn sign_aws(
+    opts: &Options,
+    method: &str,
+    url: &Url,
+    headers: &mut HeaderMap,
+    payload: Payload,
+) -> Result<()> {
+    let Some(spec) = &opts.aws_sigv4 else {
+        return Ok(());
+    };
+    if headers.contains("Authorization") {
+        return Ok(());
+    }
+    let scope = sigv4::Scope::parse(spec, url.host())?;
+    let from_url = url.username().map(|access_key| sigv4::Credentials {
+        access_key,
+        secret_key: url.password().unwrap_or_default(),
+        session_token: None,
+    });
+    let credentials = from_url
+        .or_else(|| opts.aws_credentials.clone())
+        .ok_or_else(|| Error::Usage(tr(Msg::NoAwsCredentials)))?;
+    sigv4::sign(
+        &scope,
+        &credentials,
+        method,
+        url,
+        headers,
+        payload,
+        date::now(),
+    );
+    Ok(())
+}
+
+/// Encodes a `--url-query` argument: `name=value` has both sides encoded,
+/// a bare word is encoded whole, and `+name=value` is passed through.
+fn encode_query_pair(arg: &str) -> String {
+    if let Some(raw) = arg.strip_prefix('+') {
+        return raw.to_string();
+    }
+    match arg.split_once('=') {
+        Some((name, value)) => format!(
+            "{}={}",
+            url::encode_component(name),
+            url::encode_component(value)
+        ),
+        None => url::encode_component(arg),
+    }
+}
+
+/// The curl config file and what it holds, unless the first argument is
+/// `-q`; curl only heeds it there, before any config is read.
+fn read_curlrc(args: &[String]) -> Result<(String, curlrc::Config)> {
+    let disabled = matches!(args.first().map(String::as_str), Some("-q" | "--disable"));
+    let Some(path) = curlrc::find().filter(|_| !disabled) else {
+        return Ok(Default::default());
+    };
+    let path = path.display().to_string();
+    let text = fs::read_to_string(&path).map_err(|source| Error::File {
+        path: path.clone(),
+        source,
+    })?;
+    let config = curlrc::parse(&path, &text)?;
+    Ok((path, config))
+}
+
+/// Runs the transfers `--next` separates one after another, over
+/// connections they share, until one fails; with `--fail-at-end`, all of
+/// them, failing at the end with the first error.
+fn run_all(transfers: Vec<Options>) -> Result<()> {
+    let connections = (transfers.len() > 1).then(Arc::default);
+    // With --fail-at-end anywhere, the first failure is kept for the end.
+    let at_end = transfers.iter().any(|opts| opts.fail_at_end);
+    let mut outcome = Ok(());
+    for mut opts in transfers {
+        opts.connections.clone_from(&connections);
+        match dispatch(opts) {
+            Err(e) if !at_end => return Err(e),
+            Err(e) if outcome.is_ok() => outcome = Err(e),
+            _ => {}
+        }
+        if stdout_closed() {
+            break;
+        }
+    }
+    outcome
+}
+
+fn dispatch(opts: Options) -> Result<()> {
+    if opts.help {
+        return write_stdout(help::render(opts.url.as_deref())?.as_bytes());
+    }
+    if opts.examples {
+        return write_stdout(tr(Msg::Examples).as_bytes());
+    }
+    if let Some(shell) = &opts.completions {
+        return write_stdout(completions::generate(shell).as_bytes());
+    }
+    if opts.version {
+        return write_stdout(version::report().as_bytes());
+    }
+    run(opts)
+}
+
+fn main() {
+    let mut args: Vec<String> = env::args().skip(1).collect();
+    // `check-links <url>` is the spelling of --check-links as a command.
+    if args.first().is_some_and(|first| first == "check-links") {
+        args[0] = "--check-links".to_string();
+    }
+    let config = read_curlrc(&args);
+    let args = match &config {
+        Ok((_, config)) => [config.args.as_slice(), &args].concat(),
+        Err(_) => args,
+    };
+    if let Some(lang) = cli::find_lang(&args) {
+        i18n::init(Lang::from_locale(lang));
+    }
+    let result = config.and_then(|(path, config)| {
+        for (line, name) in &config.unknown {
+            eprintln!("{}", tr(Msg::ConfigUnknownOption(&path, *line, name)));
+        }
+        // Every transfer's options are checked before any is sent.
+        cli::split_next(&args)
+            .into_iter()
+            .map(cli::parse_args)
+            .collect::<Result<Vec<_>>>()
+    });
+    let result = result.and_then(run_all);
+    // Whatever read the output has all it wanted.
+    if stdout_closed() {
+        process::exit(0);
+    }
+    if let Err(e) = result {
+        eprintln!("http_client: {}", tr(Msg::Error(&e)));
+        process::exit(e.exit_code());
+    }
+}
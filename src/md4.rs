@@ -0,0 +1,81 @@
+//! MD4 (RFC 1320), needed for the NT hash of NTLM.
+//!
+//! MD4 is long broken; NTLM uses it to hash the password, and nothing else
+//! should.
+
+/// Pads `data` into 64-byte blocks with its bit length, little-endian.
+fn padded(data: &[u8]) -> Vec<u8> {
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend_from_slice(&((data.len() as u64) * 8).to_le_bytes());
+    message
+}
+
+/// The 16-byte digest of `data`.
+pub fn digest(data: &[u8]) -> [u8; 16] {
+    let mut h: [u32; 4] = [0x67452301, 0xefcdab89, 0x98badcfe, 0x10325476];
+
+    for block in padded(data).chunks(64) {
+        let mut x = [0u32; 16];
+        for (i, word) in block.chunks(4).enumerate() {
+            x[i] = u32::from_le_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        let [mut a, mut b, mut c, mut d] = h;
+        for i in 0..48 {
+            let (f, k, s, constant) = match i {
+                0..=15 => ((b & c) | (!b & d), i, [3, 7, 11, 19][i % 4], 0),
+                16..=31 => (
+                    (b & c) | (b & d) | (c & d),
+                    (i % 4) * 4 + (i - 16) / 4,
+                    [3, 5, 9, 13][i % 4],
+                    0x5a827999,
+                ),
+                _ => (
+                    b ^ c ^ d,
+                    [0, 8, 4, 12, 2, 10, 6, 14, 1, 9, 5, 13, 3, 11, 7, 15][i - 32],
+                    [3, 9, 11, 15][i % 4],
+                    0x6ed9eba1,
+                ),
+            };
+            let temp = a
+                .wrapping_add(f)
+                .wrapping_add(x[k])
+                .wrapping_add(constant)
+                .rotate_left(s);
+            a = d;
+            d = c;
+            c = b;
+            b = temp;
+        }
+        for (state, value) in h.iter_mut().zip([a, b, c, d]) {
+            *state = state.wrapping_add(value);
+        }
+    }
+
+    let mut out = [0u8; 16];
+    for (chunk, word) in out.chunks_mut(4).zip(h) {
+        chunk.copy_from_slice(&word.to_le_bytes());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha256::hex;
+
+    #[test]
+    fn matches_rfc_1320_digests() {
+        assert_eq!(hex(&digest(b"")), "31d6cfe0d16ae931b73c59d7e0c089c0");
+        assert_eq!(hex(&digest(b"abc")), "a448017aaf21d8525fc10ae87aa6729d");
+        assert_eq!(
+            hex(&digest(
+                b"12345678901234567890123456789012345678901234567890123456789012345678901234567890"
+            )),
+            "e33b4ddc9c38f2199c3e7b164fcc0536"
+        );
+    }
+}
@@ -0,0 +1,196 @@
+//! `--meta-json`: one JSON object per transfer, for monitoring and CI.
+//!
+//! Each record is a single line with the final URL, the redirect and
+//! retry counts, the status and headers (and any `Retry-After` in
+//! seconds), the timing phases, the sizes and both socket addresses, or
+//! the error and exit code of a failed transfer. Fields that do not apply,
+//! such as the addresses of a response served from the cache, are `null`.
+
+use std::fs::OpenOptions;
+use std::io::{self, Write};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use http_client::date;
+use http_client::error::{Error, Result};
+use http_client::json::Value;
+use http_client::response::Response;
+
+use crate::history::Transfer;
+
+fn string(s: impl Into<String>) -> Value {
+    Value::String(s.into())
+}
+
+fn number(n: impl ToString) -> Value {
+    Value::Number(n.to_string())
+}
+
+/// Seconds with microseconds, as curl writes its `time_*` variables.
+fn seconds(duration: Option<Duration>) -> Value {
+    duration.map_or(Value::Null, |d| {
+        Value::Number(format!("{:.6}", d.as_secs_f64()))
+    })
+}
+
+fn object(members: Vec<(&str, Value)>) -> Value {
+    Value::Object(
+        members
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v))
+            .collect(),
+    )
+}
+
+/// Headers as an object from lowercase names to arrays of values, like
+/// curl's `%{header_json}`, so repeated headers keep every value.
+fn headers(response: &Response) -> Value {
+    let mut members: Vec<(String, Value)> = Vec::new();
+    for (name, value) in response.headers.iter() {
+        let name = name.to_ascii_lowercase();
+        match members.iter_mut().find(|(n, _)| *n == name) {
+            Some((_, Value::Array(values))) => values.push(string(value)),
+            _ => members.push((name, Value::Array(vec![string(value)]))),
+        }
+    }
+    Value::Object(members)
+}
+
+/// The record of a finished transfer.
+pub fn record(transfer: &Transfer, result: &Result<Response>) -> Value {
+    let (url, metrics) = (&transfer.url, &transfer.metrics);
+    let response = result.as_ref().ok();
+    let address = |addr: Option<SocketAddr>| addr.map_or(Value::Null, |a| string(a.to_string()));
+    object(vec![
+        ("url", string(url.without_userinfo().to_string())),
+        ("redirects", number(transfer.hops.len())),
+        ("retries", number(transfer.retries)),
+        (
+            "retry_after",
+            response
+                .and_then(|r| r.retry_after(date::now()))
+                .map_or(Value::Null, |wait| number(wait.as_secs())),
+        ),
+        (
+            "status",
+            response.map_or(Value::Null, |r| number(r.status.code)),
+        ),
+        (
+            "http_version",
+            response.map_or(Value::Null, |r| string(r.status.version.clone())),
+        ),
+        ("headers", response.map_or(Value::Null, headers)),
+        (
+            "time",
+            object(vec![
+                ("namelookup", seconds(metrics.name_lookup())),
+                ("connect", seconds(metrics.connect())),
+                ("starttransfer", seconds(metrics.first_byte())),
+                ("total", seconds(Some(metrics.elapsed()))),
+            ]),
+        ),
+        (
+            "size",
+            object(vec![
+                ("upload", number(transfer.upload)),
+                (
+                    "header",
+                    response.map_or(Value::Null, |r| number(r.head_text().len())),
+                ),
+                (
+                    "body",
+                    response.map_or(Value::Null, |r| number(transfer.body_size(r))),
+                ),
+                ("sent", number(metrics.bytes_sent())),
+                ("received", number(metrics.bytes_received())),
+            ]),
+        ),
+        ("local_address", address(metrics.local_addr())),
+        ("remote_address", address(metrics.remote_addr())),
+        (
+            "error",
+            result
+                .as_ref()
+                .err()
+                .map_or(Value::Null, |e| string(e.to_string())),
+        ),
+        (
+            "exit_code",
+            number(result.as_ref().err().map_or(0, Error::exit_code)),
+        ),
+    ])
+}
+
+/// Appends `record` as a line to `path`, or writes it to stderr for `-`.
+pub fn write(path: &str, record: &Value) -> Result<()> {
+    let line = format!("{}\n", record);
+    let written = if path == "-" {
+        io::stderr().write_all(line.as_bytes())
+    } else {
+        // One write per record keeps parallel transfers from interleaving.
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(line.as_bytes()))
+    };
+    written.map_err(|source| Error::File {
+        path: path.to_string(),
+        source,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_client::headers::HeaderMap;
+    use http_client::metrics::Metrics;
+    use http_client::response::StatusLine;
+    use http_client::url::Url;
+
+    #[test]
+    fn records_responses_and_errors() {
+        let transfer = Transfer {
+            url: Url::parse("http://user:pw@example.com/a").unwrap(),
+            hops: Vec::new(),
+            metrics: Metrics::new(),
+            total: Duration::ZERO,
+            upload: 3,
+            retries: 1,
+            saved: None,
+        };
+        let response = Response {
+            status: StatusLine {
+                version: "HTTP/1.1".into(),
+                code: 200,
+                reason: "OK".into(),
+            },
+            headers: [
+                ("Set-Cookie", "a=1"),
+                ("set-cookie", "b=2"),
+                ("Retry-After", "5"),
+            ]
+            .into_iter()
+            .collect(),
+            body: b"body".to_vec(),
+            trailers: HeaderMap::new(),
+        };
+        let ok = record(&transfer, &Ok(response));
+        assert_eq!(ok.path("$.url").unwrap().to_text(), "http://example.com/a");
+        assert_eq!(ok.path("$.status").unwrap().to_text(), "200");
+        assert_eq!(
+            ok.path("$.headers").unwrap().to_string(),
+            r#"{"set-cookie":["a=1","b=2"],"retry-after":["5"]}"#
+        );
+        assert_eq!(ok.path("$.retries").unwrap().to_text(), "1");
+        assert_eq!(ok.path("$.retry_after").unwrap().to_text(), "5");
+        assert_eq!(ok.path("$.size.body").unwrap().to_text(), "4");
+        assert_eq!(ok.path("$.time.connect"), Some(&Value::Null));
+        assert_eq!(ok.path("$.exit_code").unwrap().to_text(), "0");
+
+        let failed = record(&transfer, &Err(Error::TooManyRedirects(0)));
+        assert_eq!(failed.path("$.status"), Some(&Value::Null));
+        assert_eq!(failed.path("$.exit_code").unwrap().to_text(), "47");
+        assert!(!failed.to_string().contains('\n'));
+    }
+}
@@ -0,0 +1,154 @@
+//! Per-transfer measurements for `--meta-json`.
+//!
+//! A [`Metrics`] is started before a request and filled in by the
+//! connection code as the transfer goes: when the host name was resolved,
+//! when the TCP connection was up and when the first response byte arrived,
+//! both socket addresses and the bytes written and read. Times count from
+//! the start, like curl's `time_namelookup` and friends; after a redirect
+//! they describe the last connection made.
+
+use std::cell::Cell;
+use std::net::{SocketAddr, TcpStream};
+use std::time::{Duration, Instant};
+
+/// What one transfer did on the network.
+#[derive(Debug)]
+pub struct Metrics {
+    start: Instant,
+    name_lookup: Cell<Option<Duration>>,
+    connect: Cell<Option<Duration>>,
+    first_byte: Cell<Option<Duration>>,
+    local_addr: Cell<Option<SocketAddr>>,
+    remote_addr: Cell<Option<SocketAddr>>,
+    bytes_sent: Cell<u64>,
+    bytes_received: Cell<u64>,
+}
+
+impl Default for Metrics {
+    fn default() -> Metrics {
+        Metrics::new()
+    }
+}
+
+impl Metrics {
+    /// Starts measuring now.
+    pub fn new() -> Metrics {
+        Metrics {
+            start: Instant::now(),
+            name_lookup: Cell::new(None),
+            connect: Cell::new(None),
+            first_byte: Cell::new(None),
+            local_addr: Cell::new(None),
+            remote_addr: Cell::new(None),
+            bytes_sent: Cell::new(0),
+            bytes_received: Cell::new(0),
+        }
+    }
+
+    /// Time since the start.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+
+    /// Marks the end of a name lookup, which begins a new connection.
+    pub(crate) fn resolved(&self) {
+        self.name_lookup.set(Some(self.elapsed()));
+        self.connect.set(None);
+        self.first_byte.set(None);
+    }
+
+    pub(crate) fn connected(&self, stream: &TcpStream) {
+        self.connect.set(Some(self.elapsed()));
+        self.local_addr.set(stream.local_addr().ok());
+        self.remote_addr.set(stream.peer_addr().ok());
+    }
+
+    pub(crate) fn sent(&self, n: usize) {
+        self.bytes_sent.set(self.bytes_sent.get() + n as u64);
+    }
+
+    pub(crate) fn received(&self, n: usize) {
+        if n > 0 && self.first_byte.get().is_none() {
+            self.first_byte.set(Some(self.elapsed()));
+        }
+        self.bytes_received
+            .set(self.bytes_received.get() + n as u64);
+    }
+
+    pub fn name_lookup(&self) -> Option<Duration> {
+        self.name_lookup.get()
+    }
+
+    pub fn connect(&self) -> Option<Duration> {
+        self.connect.get()
+    }
+
+    /// When the first byte of the response arrived.
+    pub fn first_byte(&self) -> Option<Duration> {
+        self.first_byte.get()
+    }
+
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local_addr.get()
+    }
+
+    pub fn remote_addr(&self) -> Option<SocketAddr> {
+        self.remote_addr.get()
+    }
+
+    /// Bytes written to the network, headers included.
+    pub fn bytes_sent(&self) -> u64 {
+        self.bytes_sent.get()
+    }
+
+    /// Bytes read from the network, headers included.
+    pub fn bytes_received(&self) -> u64 {
+        self.bytes_received.get()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headers::HeaderMap;
+    use crate::http::{self, Exchange, Limits};
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    #[test]
+    fn measures_a_transfer() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut line = String::new();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+                .unwrap();
+        });
+
+        let metrics = Metrics::new();
+        assert_eq!(metrics.connect(), None);
+        let mut headers = HeaderMap::new();
+        headers.append("Host", "h");
+        let limits = Limits::default();
+        let response = http::send_http_request(&Exchange {
+            metrics: Some(&metrics),
+            ..Exchange::new("127.0.0.1", port, "GET", "/", &headers, &limits)
+        })
+        .unwrap();
+        server.join().unwrap();
+
+        assert_eq!(response.body, b"ok");
+        let (lookup, connect) = (metrics.name_lookup().unwrap(), metrics.connect().unwrap());
+        assert!(lookup <= connect && connect <= metrics.first_byte().unwrap());
+        assert_eq!(metrics.remote_addr().unwrap().port(), port);
+        assert!(metrics.local_addr().unwrap().ip().is_loopback());
+        assert_eq!(metrics.bytes_sent(), 27);
+        assert_eq!(metrics.bytes_received(), 40);
+    }
+}
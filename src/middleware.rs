@@ -0,0 +1,140 @@
+//! Hooks around each request, for layering behaviour such as logging,
+//! signing or metrics on top of the client without threading it through
+//! every call site.
+//!
+//! A [`Middleware`] sees each request just before it is sent, and may
+//! change it, and each response as it comes back. A [`Registry`] runs its
+//! middleware in the order they were added for requests and in reverse for
+//! responses, so the first one added wraps all the others.
+
+use crate::error::Result;
+use crate::http::Limits;
+pub use crate::request::Request;
+use crate::response::Response;
+
+/// One layer. Both hooks do nothing unless overridden.
+///
+/// Middleware is shared by the threads of a parallel transfer, so state
+/// has to be kept behind a lock or in atomics.
+pub trait Middleware: Send + Sync {
+    /// Called before `request` is sent. An error stops it being sent.
+    fn on_request(&self, request: &mut Request) -> Result<()> {
+        let _ = request;
+        Ok(())
+    }
+
+    /// Called with the response a transfer ends with, whatever its
+    /// status. An error fails the transfer.
+    fn on_response(&self, request: &Request, response: &Response) -> Result<()> {
+        let _ = (request, response);
+        Ok(())
+    }
+}
+
+/// The middleware a client runs, in order.
+#[derive(Default)]
+pub struct Registry {
+    layers: Vec<Box<dyn Middleware>>,
+}
+
+impl Registry {
+    pub fn new() -> Registry {
+        Registry::default()
+    }
+
+    /// Adds `middleware` inside the ones already added.
+    pub fn push(&mut self, middleware: impl Middleware + 'static) {
+        self.layers.push(Box::new(middleware));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.layers.is_empty()
+    }
+
+    pub fn on_request(&self, request: &mut Request) -> Result<()> {
+        self.layers
+            .iter()
+            .try_for_each(|layer| layer.on_request(request))
+    }
+
+    pub fn on_response(&self, request: &Request, response: &Response) -> Result<()> {
+        self.layers
+            .iter()
+            .rev()
+            .try_for_each(|layer| layer.on_response(request, response))
+    }
+
+    /// Runs the middleware around a single HTTP/1.1 request, for embedders
+    /// that want the hooks without the command line's redirect and retry
+    /// handling. The request is sent with exactly the headers it ends up
+    /// with.
+    pub fn send(&self, mut request: Request, limits: &Limits) -> Result<Response> {
+        self.on_request(&mut request)?;
+        let response = request.send_with(limits)?;
+        self.on_response(&request, &response)?;
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    struct Tag(&'static str, Arc<Mutex<Vec<String>>>);
+
+    impl Middleware for Tag {
+        fn on_request(&self, request: &mut Request) -> Result<()> {
+            let seen = request.headers.get("X-Layers").unwrap_or_default();
+            request
+                .headers
+                .insert("X-Layers", format!("{}{}", seen, self.0));
+            Ok(())
+        }
+
+        fn on_response(&self, _: &Request, response: &Response) -> Result<()> {
+            let mut log = self.1.lock().unwrap();
+            log.push(format!("{} {}", self.0, response.status.code));
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn runs_layers_around_a_request() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut head = String::new();
+            while reader.read_line(&mut head).unwrap() > 2 {}
+            let body = if head.contains("X-Layers: ab\r\n") {
+                "ab"
+            } else {
+                ""
+            };
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            reader.get_mut().write_all(response.as_bytes()).unwrap();
+        });
+
+        let log = Arc::new(Mutex::new(Vec::new()));
+        let mut registry = Registry::new();
+        registry.push(Tag("a", log.clone()));
+        registry.push(Tag("b", log.clone()));
+        let request = Request::builder()
+            .url(&format!("http://127.0.0.1:{}/", port))
+            .build()
+            .unwrap();
+        let response = registry.send(request, &Limits::default()).unwrap();
+        server.join().unwrap();
+        assert_eq!(response.body, b"ab");
+        assert_eq!(*log.lock().unwrap(), ["b 200", "a 200"]);
+    }
+}
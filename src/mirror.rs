@@ -0,0 +1,189 @@
+//! `--mirror`: copying a site the way `wget --mirror` does.
+//!
+//! The page at the URL is saved and the links on it (see [`links`]) are
+//! followed, a level at a time, down to `--level` links away; only links
+//! on the starting host unless `--span-hosts` is given. Redirects are
+//! followed too, as links of the level after. Each file goes to
+//! `<host>/<path>` under `--output-dir`, or the current directory, with
+//! `index.html` for paths ending in `/`, so the copy has the site's layout.
+//! A level's URLs are fetched up to `--parallel-max` at a time, through
+//! `--cache-dir` when one is given, and what robots.txt disallows is left
+//! out (see [`crawl`](crate::crawl)). Failures do not stop the run; the
+//! first is returned at the end.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use http_client::error::{Error, Result};
+use http_client::http;
+use http_client::links;
+use http_client::response::Response;
+use http_client::trace::Trace;
+use http_client::url::Url;
+
+use crate::batch;
+use crate::cli::Options;
+use crate::crawl::Sites;
+use crate::i18n::{tr, Msg};
+
+/// Mirrors the site at `url`.
+pub fn run(opts: &Options, url: Url, trace: Option<&Trace>, limits: &http::Limits) -> Result<()> {
+    let mut host = url.host().to_string();
+    let mut seen = HashSet::from([url.to_string()]);
+    let mut level = vec![url];
+    let (mut files, mut bytes) = (0, 0);
+    let mut outcome = Ok(());
+    let sites = Sites::new(opts, trace, limits);
+    for depth in 0..=opts.mirror_level {
+        if level.is_empty() {
+            break;
+        }
+        let results = batch::each_parallel(&level, opts.parallel_max, |url| {
+            // The first page is the user's to ask for.
+            if !sites.visit(url, depth > 0) {
+                return None;
+            }
+            let method = "GET".to_string();
+            let (result, transfer) =
+                crate::run_transfer(opts, url.clone(), method, None, &[], trace, limits);
+            Some(result.map(|response| (transfer.url, response)))
+        });
+        let mut next = Vec::new();
+        for (url, result) in level.iter().zip(results) {
+            let (url, response) = match result {
+                None => continue,
+                Some(Ok(done)) => done,
+                Some(Err(e)) => {
+                    eprintln!("* {}", tr(Msg::LinkFailed(&url.to_string(), &e)));
+                    if outcome.is_ok() {
+                        outcome = Err(e);
+                    }
+                    continue;
+                }
+            };
+            // Links on the page the start redirected to count as the site's.
+            if depth == 0 {
+                host = url.host().to_string();
+            }
+            seen.insert(url.to_string());
+            let found = match response.status.code {
+                300..=399 => redirect(&url, &response),
+                400.. => {
+                    eprintln!(
+                        "* {}",
+                        tr(Msg::LinkBroken(&url.to_string(), &response.status))
+                    );
+                    continue;
+                }
+                _ => {
+                    let path = local_path(&url);
+                    save(opts, &path, &response.body)?;
+                    if opts.verbose {
+                        eprintln!("* {}", tr(Msg::SavingTo(&path.display().to_string())));
+                    }
+                    files += 1;
+                    bytes += response.body.len();
+                    let content_type = response.headers.get("Content-Type").unwrap_or_default();
+                    match links::is_html(content_type) {
+                        true => links::extract(&response.text(), &url)
+                            .into_iter()
+                            .map(|link| link.url)
+                            .collect(),
+                        false => Vec::new(),
+                    }
+                }
+            };
+            if depth == opts.mirror_level {
+                continue;
+            }
+            for link in found {
+                let on_site = opts.span_hosts || link.host().eq_ignore_ascii_case(&host);
+                if on_site && seen.insert(link.to_string()) {
+                    next.push(link);
+                }
+            }
+        }
+        level = next;
+    }
+    eprintln!("* {}", tr(Msg::Mirrored(files, bytes)));
+    outcome
+}
+
+/// Where a redirect points, if it is a link this client can follow.
+fn redirect(url: &Url, response: &Response) -> Vec<Url> {
+    let location = response.headers.get("Location");
+    let Some(mut target) = location.and_then(|location| url.join(location).ok()) else {
+        return Vec::new();
+    };
+    target.set_fragment(None);
+    match target.scheme() {
+        "http" | "https" => vec![target],
+        _ => Vec::new(),
+    }
+}
+
+/// The file `url` is saved as: `<host>/<path>`, with the port after the
+/// host when it is not the scheme's default, `index.html` for a directory
+/// and the query kept in the name. The path stays percent-encoded, so
+/// nothing in it can climb out of the host's directory.
+fn local_path(url: &Url) -> PathBuf {
+    let mut path = PathBuf::from(match url.port() {
+        Some(port) => format!("{}:{}", url.host(), port),
+        None => url.host().to_string(),
+    });
+    for segment in url.path().split('/') {
+        if !matches!(segment, "" | "." | "..") {
+            path.push(segment);
+        }
+    }
+    if url.path().ends_with('/') {
+        path.push("index.html");
+    }
+    if let Some(query) = url.query() {
+        let mut name = path.file_name().unwrap_or_default().to_os_string();
+        name.push(format!("?{}", query.replace('/', "%2F")));
+        path.set_file_name(name);
+    }
+    path
+}
+
+/// Writes a mirrored file under `--output-dir`, creating its directories.
+fn save(opts: &Options, path: &Path, body: &[u8]) -> Result<()> {
+    let path = match &opts.output_dir {
+        Some(dir) => Path::new(dir).join(path),
+        None => path.to_path_buf(),
+    };
+    let error = |source| Error::File {
+        path: path.display().to_string(),
+        source,
+    };
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        fs::create_dir_all(parent).map_err(error)?;
+    }
+    fs::write(&path, body).map_err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lays_files_out_like_the_site() {
+        let path = |url: &str| local_path(&Url::parse(url).unwrap());
+        assert_eq!(path("http://h.test/"), Path::new("h.test/index.html"));
+        assert_eq!(path("http://h.test/a/b.css"), Path::new("h.test/a/b.css"));
+        assert_eq!(
+            path("http://h.test:8080/a/"),
+            Path::new("h.test:8080/a/index.html")
+        );
+        assert_eq!(
+            path("https://h.test/list?page=2&s=a/b"),
+            Path::new("h.test/list?page=2&s=a%2Fb")
+        );
+        assert_eq!(
+            path("http://h.test/%2E%2E/x%2Fy"),
+            Path::new("h.test/%2E%2E/x%2Fy")
+        );
+    }
+}
@@ -0,0 +1,154 @@
+//! JSON Lines bodies (also called NDJSON): one JSON value per line, as
+//! log-tailing and streaming APIs send them.
+//!
+//! [`Records`] sits between a body being copied and where it goes, and
+//! passes each record on, flushed, as soon as its line is complete, so a
+//! stream that never ends is still shown as it arrives.
+
+use std::io::{self, Write};
+
+use crate::error::{Error, Result};
+use crate::jq::{self, Filter};
+use crate::json;
+
+/// Whether a `Content-Type` is one of the names JSON Lines goes by.
+pub fn is_json_lines(content_type: &str) -> bool {
+    let media = content_type.split(';').next().unwrap_or_default().trim();
+    [
+        "application/x-ndjson",
+        "application/ndjson",
+        "application/jsonl",
+        "application/x-jsonlines",
+        "application/jsonlines",
+    ]
+    .iter()
+    .any(|name| media.eq_ignore_ascii_case(name))
+}
+
+/// A writer that passes the records written to it on to `out` one at a
+/// time: as they are, or, given a filter or `pretty`, each record's
+/// results as [`jq::print`] has them. Empty lines are dropped. A record
+/// that is not JSON fails a filter; `pretty` alone leaves it as it is.
+pub struct Records<W: Write> {
+    out: W,
+    filter: Option<Filter>,
+    pretty: bool,
+    line: Vec<u8>,
+    records: usize,
+    /// Why a record could not be passed on, for [`Records::finish`].
+    error: Option<Error>,
+}
+
+impl<W: Write> Records<W> {
+    pub fn new(out: W, filter: Option<Filter>, pretty: bool) -> Records<W> {
+        Records {
+            out,
+            filter,
+            pretty,
+            line: Vec::new(),
+            records: 0,
+            error: None,
+        }
+    }
+
+    /// Passes on a last record whose line was not ended, and turns
+    /// `copied`, how copying the body in went, into the error a record
+    /// caused if one did.
+    pub fn finish(mut self, copied: Result<u64>) -> Result<u64> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        let copied = copied?;
+        if !self.line.is_empty() {
+            if let Err(e) = self.pass_on() {
+                return Err(self.error.take().unwrap_or(Error::Io(e)));
+            }
+        }
+        Ok(copied)
+    }
+
+    fn pass_on(&mut self) -> io::Result<()> {
+        let line = std::mem::take(&mut self.line);
+        let record = match self.render(line.trim_ascii()) {
+            Ok(record) => record,
+            Err(e) => {
+                let message = e.to_string();
+                self.error = Some(e);
+                return Err(io::Error::other(message));
+            }
+        };
+        self.out.write_all(&record)?;
+        self.out.flush()
+    }
+
+    fn render(&mut self, line: &[u8]) -> Result<Vec<u8>> {
+        if line.is_empty() {
+            return Ok(Vec::new());
+        }
+        self.records += 1;
+        let value = std::str::from_utf8(line)
+            .ok()
+            .filter(|_| self.filter.is_some() || self.pretty)
+            .and_then(|text| json::parse(text).ok());
+        let text = match (&self.filter, value) {
+            (Some(filter), Some(value)) => filter.results(&value, self.pretty)?,
+            (Some(_), None) => {
+                return Err(Error::Query(format!("record {} is not JSON", self.records)))
+            }
+            (None, Some(value)) => format!("{}\n", jq::print(&value, self.pretty)),
+            (None, None) => return Ok([line, b"\n"].concat()),
+        };
+        Ok(text.into_bytes())
+    }
+}
+
+impl<W: Write> Write for Records<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for piece in buf.split_inclusive(|&b| b == b'\n') {
+            self.line.extend_from_slice(piece);
+            if piece.ends_with(b"\n") {
+                self.pass_on()?;
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn recognizes_json_lines_types() {
+        assert!(is_json_lines("application/x-ndjson"));
+        assert!(is_json_lines("Application/JSONL; charset=utf-8"));
+        assert!(!is_json_lines("application/json"));
+    }
+
+    #[test]
+    fn passes_records_on_as_their_lines_end() {
+        let mut out = Vec::new();
+        let mut records = Records::new(&mut out, None, true);
+        records
+            .write_all(b"{\"a\": 1}\n\nnot json\n{\"a\"")
+            .unwrap();
+        assert_eq!(records.out.as_slice(), b"{\n  \"a\": 1\n}\nnot json\n");
+        records.write_all(b": [2]}").unwrap();
+        assert_eq!(records.finish(Ok(4)).unwrap(), 4);
+        assert!(out.ends_with(b"not json\n{\n  \"a\": [\n    2\n  ]\n}\n"));
+
+        let filter = Filter::parse(".a").unwrap();
+        let mut records = Records::new(Vec::new(), Some(filter), false);
+        records
+            .write_all(b"{\"a\": \"x\"}\r\n{\"a\": [1]}\n")
+            .unwrap();
+        assert_eq!(records.out, b"x\n[1]\n");
+        assert!(records.write_all(b"oops\n").is_err());
+        let err = records.finish(Ok(0)).unwrap_err();
+        assert_eq!(err.to_string(), "jq: record 3 is not JSON");
+    }
+}
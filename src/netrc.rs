@@ -0,0 +1,185 @@
+//! `.netrc` files for `-n` and `--netrc-file`.
+//!
+//! The format is a stream of whitespace-separated tokens: `machine <host>`
+//! or `default` starts an entry, and `login`, `password` and `account`
+//! fill it in. `macdef <name>` defines an ftp macro, which runs until the
+//! next blank line and is skipped. As in curl, values may be quoted with
+//! `"` and backslash escapes, and `#` starts a comment.
+
+use std::env;
+use std::path::PathBuf;
+
+/// One `machine` or `default` entry.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Entry {
+    /// `None` for `default`.
+    pub machine: Option<String>,
+    pub login: Option<String>,
+    pub password: Option<String>,
+}
+
+/// The entries of a file, in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Netrc {
+    entries: Vec<Entry>,
+}
+
+/// `~/.netrc`, from `HOME` or else `USERPROFILE`.
+pub fn default_path() -> Option<PathBuf> {
+    env::var_os("HOME")
+        .or_else(|| env::var_os("USERPROFILE"))
+        .map(|home| PathBuf::from(home).join(".netrc"))
+}
+
+/// Splits `text` into tokens, dropping comments and macro definitions.
+fn tokens(text: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let Some(&first) = chars.peek() else {
+            return tokens;
+        };
+        if first == '#' {
+            while chars.next_if(|&c| c != '\n').is_some() {}
+            continue;
+        }
+        let mut token = String::new();
+        if first == '"' {
+            chars.next();
+            while let Some(c) = chars.next() {
+                match c {
+                    '"' => break,
+                    '\\' => match chars.next() {
+                        Some('n') => token.push('\n'),
+                        Some('r') => token.push('\r'),
+                        Some('t') => token.push('\t'),
+                        Some(c) => token.push(c),
+                        None => {}
+                    },
+                    c => token.push(c),
+                }
+            }
+        } else {
+            while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                token.push(c);
+            }
+        }
+        if token == "macdef" && first != '"' {
+            // The macro's name is on the same line; its body ends at the
+            // first empty line.
+            let mut previous = ' ';
+            for c in chars.by_ref() {
+                if c == '\n' && previous == '\n' {
+                    break;
+                }
+                if c != '\r' {
+                    previous = c;
+                }
+            }
+            continue;
+        }
+        tokens.push(token);
+    }
+}
+
+impl Netrc {
+    /// Reads a file's text. Unknown tokens are skipped, as are values that
+    /// come before the first `machine` or `default`.
+    pub fn parse(text: &str) -> Netrc {
+        let mut entries: Vec<Entry> = Vec::new();
+        let mut tokens = tokens(text).into_iter();
+        while let Some(token) = tokens.next() {
+            match token.as_str() {
+                "machine" => entries.push(Entry {
+                    machine: tokens.next(),
+                    ..Entry::default()
+                }),
+                "default" => entries.push(Entry::default()),
+                "login" | "password" | "account" => {
+                    let value = tokens.next();
+                    let Some(entry) = entries.last_mut() else {
+                        continue;
+                    };
+                    match token.as_str() {
+                        "login" => entry.login = value,
+                        "password" => entry.password = value,
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+        Netrc { entries }
+    }
+
+    pub fn entries(&self) -> &[Entry] {
+        &self.entries
+    }
+
+    /// The credentials for `host`: the first entry for that machine (with
+    /// `login`, if one is given), or else the `default` entry.
+    pub fn find(&self, host: &str, login: Option<&str>) -> Option<&Entry> {
+        let matches_login = |entry: &&Entry| login.is_none() || entry.login.as_deref() == login;
+        self.entries
+            .iter()
+            .filter(|e| {
+                e.machine
+                    .as_deref()
+                    .is_some_and(|m| m.eq_ignore_ascii_case(host))
+            })
+            .find(matches_login)
+            .or_else(|| {
+                self.entries
+                    .iter()
+                    .filter(|e| e.machine.is_none())
+                    .find(matches_login)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(machine: Option<&str>, login: &str, password: &str) -> Entry {
+        Entry {
+            machine: machine.map(str::to_string),
+            login: Some(login.to_string()),
+            password: Some(password.to_string()),
+        }
+    }
+
+    #[test]
+    fn parses_entries() {
+        let text = "# comment\n\
+            machine example.com login alice password \"s3 \\\"cret\"\n\
+            macdef init\ncd /pub\nlogin mallory\n\n\
+            machine other.test\n  login bob\n  account x\n  password pw # note\n\
+            default login anonymous password guest\n";
+        assert_eq!(
+            Netrc::parse(text).entries(),
+            [
+                entry(Some("example.com"), "alice", "s3 \"cret"),
+                entry(Some("other.test"), "bob", "pw"),
+                entry(None, "anonymous", "guest"),
+            ]
+        );
+        assert!(Netrc::parse("login stray password x").entries().is_empty());
+    }
+
+    #[test]
+    fn finds_hosts_logins_and_the_default() {
+        let netrc = Netrc::parse(
+            "machine a.test login one password 1\n\
+             machine A.test login two password 2\n\
+             default login anonymous password guest",
+        );
+        let login = |host, login| netrc.find(host, login).and_then(|e| e.login.as_deref());
+        assert_eq!(login("a.test", None), Some("one"));
+        assert_eq!(login("a.test", Some("two")), Some("two"));
+        assert_eq!(login("a.test", Some("three")), None);
+        assert_eq!(login("b.test", None), Some("anonymous"));
+        assert_eq!(Netrc::parse("machine a.test").find("b.test", None), None);
+    }
+}
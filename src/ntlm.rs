@@ -0,0 +1,382 @@
+//! NTLM authentication (MS-NLMP) for `--ntlm` and `--negotiate`.
+//!
+//! The handshake is three messages on one connection: the client's
+//! NEGOTIATE (type 1), the server's CHALLENGE (type 2) in a 401, and the
+//! client's AUTHENTICATE (type 3), which answers it with an NTLMv2
+//! response. Only NTLMv2 is spoken; the older LM and NTLMv1 responses are
+//! refused by any current Windows server anyway.
+//!
+//! NTLM authenticates the connection rather than the request, so [`send`]
+//! runs the whole handshake on one [`PersistentConnection`]. `Negotiate`
+//! (SPNEGO) servers such as IIS accept the same raw NTLM tokens; Kerberos
+//! would need the system's GSSAPI and is not supported.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::base64;
+use crate::error::{Error, Result};
+use crate::headers::HeaderMap;
+use crate::http::{Exchange, PersistentConnection};
+use crate::response::Response;
+use crate::websocket::random_u64;
+use crate::{md4, md5};
+
+const SIGNATURE: &[u8; 8] = b"NTLMSSP\0";
+
+const NEGOTIATE_UNICODE: u32 = 0x0000_0001;
+const NEGOTIATE_OEM: u32 = 0x0000_0002;
+const REQUEST_TARGET: u32 = 0x0000_0004;
+const NEGOTIATE_NTLM: u32 = 0x0000_0200;
+const NEGOTIATE_ALWAYS_SIGN: u32 = 0x0000_8000;
+const NEGOTIATE_EXTENDED_SESSIONSECURITY: u32 = 0x0008_0000;
+
+/// What the client offers in its NEGOTIATE message.
+const FLAGS: u32 = NEGOTIATE_UNICODE
+    | NEGOTIATE_OEM
+    | REQUEST_TARGET
+    | NEGOTIATE_NTLM
+    | NEGOTIATE_ALWAYS_SIGN
+    | NEGOTIATE_EXTENDED_SESSIONSECURITY;
+
+/// The AV pair holding the server's clock, in the challenge's target info.
+const AV_TIMESTAMP: u16 = 7;
+
+/// Seconds from 1601-01-01, where Windows time starts, to the Unix epoch.
+const FILETIME_EPOCH_OFFSET: u64 = 11_644_473_600;
+
+/// Who to authenticate as.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Identity {
+    pub domain: String,
+    pub user: String,
+    pub password: String,
+}
+
+impl Identity {
+    /// `DOMAIN\user` names the domain; any other user name, including a
+    /// `user@domain` principal name, is sent whole with no domain.
+    pub fn new(user: &str, password: &str) -> Identity {
+        let (domain, user) = user.split_once('\\').unwrap_or(("", user));
+        Identity {
+            domain: domain.to_string(),
+            user: user.to_string(),
+            password: password.to_string(),
+        }
+    }
+}
+
+/// The server's CHALLENGE message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Challenge {
+    pub flags: u32,
+    pub server_challenge: [u8; 8],
+    /// AV pairs describing the server, which the response must cover.
+    pub target_info: Vec<u8>,
+}
+
+fn protocol_error(msg: &str) -> Error {
+    Error::HttpProtocol(format!("NTLM: {}", msg))
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(
+        data.get(offset..offset + 2)?.try_into().ok()?,
+    ))
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(
+        data.get(offset..offset + 4)?.try_into().ok()?,
+    ))
+}
+
+/// Appends a security buffer (length, allocated length, offset) pointing
+/// at `payload_offset`.
+fn push_field(message: &mut Vec<u8>, len: usize, payload_offset: usize) {
+    message.extend_from_slice(&(len as u16).to_le_bytes());
+    message.extend_from_slice(&(len as u16).to_le_bytes());
+    message.extend_from_slice(&(payload_offset as u32).to_le_bytes());
+}
+
+fn utf16le(text: &str) -> Vec<u8> {
+    text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+}
+
+/// The NEGOTIATE message, with no domain or workstation.
+pub fn negotiate() -> Vec<u8> {
+    let mut message = SIGNATURE.to_vec();
+    message.extend_from_slice(&1u32.to_le_bytes());
+    message.extend_from_slice(&FLAGS.to_le_bytes());
+    push_field(&mut message, 0, 0);
+    push_field(&mut message, 0, 0);
+    message
+}
+
+impl Challenge {
+    /// Reads a CHALLENGE message.
+    pub fn parse(message: &[u8]) -> Result<Challenge> {
+        if !message.starts_with(SIGNATURE) || u32_at(message, 8) != Some(2) {
+            return Err(protocol_error("not a challenge message"));
+        }
+        let truncated = || protocol_error("truncated challenge message");
+        let flags = u32_at(message, 20).ok_or_else(truncated)?;
+        let server_challenge = message
+            .get(24..32)
+            .and_then(|bytes| bytes.try_into().ok())
+            .ok_or_else(truncated)?;
+        // Target info is a later addition; very old servers leave it out.
+        let target_info = match (u16_at(message, 40), u32_at(message, 44)) {
+            (Some(len), Some(offset)) if len > 0 => message
+                .get(offset as usize..offset as usize + len as usize)
+                .ok_or_else(truncated)?
+                .to_vec(),
+            _ => Vec::new(),
+        };
+        Ok(Challenge {
+            flags,
+            server_challenge,
+            target_info,
+        })
+    }
+
+    /// The server's clock from the target info, if it sent one.
+    fn timestamp(&self) -> Option<u64> {
+        let info = &self.target_info;
+        let mut offset = 0;
+        while let (Some(id), Some(len)) = (u16_at(info, offset), u16_at(info, offset + 2)) {
+            let value = info.get(offset + 4..offset + 4 + len as usize)?;
+            if id == AV_TIMESTAMP {
+                return Some(u64::from_le_bytes(value.try_into().ok()?));
+            }
+            offset += 4 + len as usize;
+        }
+        None
+    }
+}
+
+/// The current time as a Windows FILETIME: 100ns ticks since 1601.
+pub fn filetime(now: SystemTime) -> u64 {
+    let since_epoch = now.duration_since(UNIX_EPOCH).unwrap_or_default();
+    (since_epoch.as_secs() + FILETIME_EPOCH_OFFSET) * 10_000_000
+        + u64::from(since_epoch.subsec_nanos()) / 100
+}
+
+/// NTOWFv2: the key both responses are made with.
+fn ntowf_v2(identity: &Identity) -> [u8; 16] {
+    let nt_hash = md4::digest(&utf16le(&identity.password));
+    let who = format!("{}{}", identity.user.to_uppercase(), identity.domain);
+    md5::hmac(&nt_hash, &utf16le(&who))
+}
+
+/// The NTLMv2 and LMv2 responses to `challenge`.
+fn responses(
+    challenge: &Challenge,
+    identity: &Identity,
+    client_challenge: [u8; 8],
+    now: u64,
+) -> (Vec<u8>, Vec<u8>) {
+    let key = ntowf_v2(identity);
+    let timestamp = challenge.timestamp();
+
+    let mut blob = vec![1, 1, 0, 0, 0, 0, 0, 0];
+    blob.extend_from_slice(&timestamp.unwrap_or(now).to_le_bytes());
+    blob.extend_from_slice(&client_challenge);
+    blob.extend_from_slice(&[0; 4]);
+    blob.extend_from_slice(&challenge.target_info);
+    blob.extend_from_slice(&[0; 4]);
+
+    let mut signed = challenge.server_challenge.to_vec();
+    signed.extend_from_slice(&blob);
+    let mut nt = md5::hmac(&key, &signed).to_vec();
+    nt.extend_from_slice(&blob);
+
+    // A server that sends its clock wants no LMv2 response (MS-NLMP
+    // section 3.1.5.1.2).
+    let lm = if timestamp.is_some() {
+        vec![0; 24]
+    } else {
+        let mut signed = challenge.server_challenge.to_vec();
+        signed.extend_from_slice(&client_challenge);
+        let mut lm = md5::hmac(&key, &signed).to_vec();
+        lm.extend_from_slice(&client_challenge);
+        lm
+    };
+    (nt, lm)
+}
+
+/// The AUTHENTICATE message answering `challenge`. `client_challenge`
+/// should be random, and `now` is a [`filetime`].
+pub fn authenticate(
+    challenge: &Challenge,
+    identity: &Identity,
+    client_challenge: [u8; 8],
+    now: u64,
+) -> Vec<u8> {
+    let (nt, lm) = responses(challenge, identity, client_challenge, now);
+    let domain = utf16le(&identity.domain);
+    let user = utf16le(&identity.user);
+    let fields: [&[u8]; 6] = [&lm, &nt, &domain, &user, &[], &[]];
+
+    let mut message = SIGNATURE.to_vec();
+    message.extend_from_slice(&3u32.to_le_bytes());
+    let mut offset = 64;
+    for field in fields {
+        push_field(&mut message, field.len(), offset);
+        offset += field.len();
+    }
+    let flags = (challenge.flags & FLAGS & !NEGOTIATE_OEM) | NEGOTIATE_UNICODE;
+    message.extend_from_slice(&flags.to_le_bytes());
+    for field in fields {
+        message.extend_from_slice(field);
+    }
+    message
+}
+
+/// The token of the first `WWW-Authenticate` challenge for `scheme`.
+fn challenge_token<'a>(headers: &'a HeaderMap, scheme: &str) -> Option<&'a str> {
+    headers.get_all("WWW-Authenticate").find_map(|value| {
+        let (name, token) = value.trim().split_once(' ')?;
+        name.eq_ignore_ascii_case(scheme).then(|| token.trim())
+    })
+}
+
+/// Sends `request` over `conn`, authenticated with `scheme` (`NTLM` or
+/// `Negotiate`); its host and port are `conn`'s.
+///
+/// The NEGOTIATE message goes with a bodiless copy of the request, and
+/// the real request, body included, only follows the server's challenge;
+/// as with curl, a server that answers the first leg with anything but a
+/// 401 challenge gets that copy. Its headers must not say
+/// `Connection: close`.
+pub fn send(
+    conn: &mut PersistentConnection,
+    scheme: &str,
+    identity: &Identity,
+    request: &Exchange,
+) -> Result<Response> {
+    let Exchange {
+        method,
+        target,
+        headers,
+        body,
+        limits,
+        ..
+    } = *request;
+    let mut first = headers.clone();
+    first.insert(
+        "Authorization",
+        format!("{} {}", scheme, base64::encode(&negotiate())),
+    );
+    if body.is_some() {
+        first.insert("Content-Length", "0");
+    }
+    let response = conn.send(method, target, &first, None, limits)?;
+    let token = match response.status.code {
+        401 => challenge_token(&response.headers, scheme),
+        _ => None,
+    };
+    let Some(token) = token else {
+        return Ok(response);
+    };
+    let message = base64::decode(token).ok_or_else(|| protocol_error("bad challenge token"))?;
+    let challenge = Challenge::parse(&message)?;
+    if !conn.is_open() {
+        return Err(protocol_error(
+            "the server closed the connection after its challenge",
+        ));
+    }
+
+    let client_challenge = random_u64().to_le_bytes();
+    let now = filetime(SystemTime::now());
+    let answer = authenticate(&challenge, identity, client_challenge, now);
+    let mut last = headers.clone();
+    last.insert(
+        "Authorization",
+        format!("{} {}", scheme, base64::encode(&answer)),
+    );
+    conn.send(method, target, &last, body, limits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sha256::hex;
+
+    /// The NTLMv2 example of MS-NLMP section 4.2.4.
+    fn example() -> (Challenge, Identity) {
+        let mut target_info = vec![2, 0, 12, 0];
+        target_info.extend_from_slice(&utf16le("Domain"));
+        target_info.extend_from_slice(&[1, 0, 12, 0]);
+        target_info.extend_from_slice(&utf16le("Server"));
+        target_info.extend_from_slice(&[0, 0, 0, 0]);
+        let challenge = Challenge {
+            flags: 0xe28a_8233,
+            server_challenge: [0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef],
+            target_info,
+        };
+        (challenge, Identity::new("Domain\\User", "Password"))
+    }
+
+    #[test]
+    fn matches_the_specification_example() {
+        let (challenge, identity) = example();
+        assert_eq!(identity.domain, "Domain");
+        assert_eq!(
+            hex(&ntowf_v2(&identity)),
+            "0c868a403bfd7a93a3001ef22ef02e3f"
+        );
+        let (nt, lm) = responses(&challenge, &identity, [0xaa; 8], 0);
+        assert_eq!(hex(&nt[..16]), "68cd0ab851e51c96aabc927bebef6a1c");
+        assert_eq!(hex(&lm), "86c35097ac9cec102554764a57cccc19aaaaaaaaaaaaaaaa");
+    }
+
+    #[test]
+    fn parses_challenges_and_builds_the_answer() {
+        let (expected, identity) = example();
+        let mut message = SIGNATURE.to_vec();
+        message.extend_from_slice(&2u32.to_le_bytes());
+        push_field(&mut message, 0, 48);
+        message.extend_from_slice(&expected.flags.to_le_bytes());
+        message.extend_from_slice(&expected.server_challenge);
+        message.extend_from_slice(&[0; 8]);
+        push_field(&mut message, expected.target_info.len(), 48);
+        message.extend_from_slice(&expected.target_info);
+        let challenge = Challenge::parse(&message).unwrap();
+        assert_eq!(challenge, expected);
+        assert!(Challenge::parse(&negotiate()).is_err());
+        assert!(Challenge::parse(&message[..30]).is_err());
+
+        let answer = authenticate(&challenge, &identity, [0xaa; 8], 0);
+        assert_eq!(u32_at(&answer, 8), Some(3));
+        let field = |index: usize| {
+            let len = u16_at(&answer, 12 + index * 8).unwrap() as usize;
+            let offset = u32_at(&answer, 16 + index * 8).unwrap() as usize;
+            &answer[offset..offset + len]
+        };
+        assert_eq!(field(2), utf16le("Domain"));
+        assert_eq!(field(3), utf16le("User"));
+        assert_eq!(hex(&field(1)[..16]), "68cd0ab851e51c96aabc927bebef6a1c");
+    }
+
+    #[test]
+    fn splits_domains_and_reads_server_clocks() {
+        let upn = Identity::new("user@corp.example", "pw");
+        assert_eq!(
+            (upn.domain.as_str(), upn.user.as_str()),
+            ("", "user@corp.example")
+        );
+        assert_eq!(filetime(UNIX_EPOCH), 116_444_736_000_000_000);
+
+        let (mut challenge, identity) = example();
+        assert_eq!(challenge.timestamp(), None);
+        let mut info = vec![7, 0, 8, 0];
+        info.extend_from_slice(&42u64.to_le_bytes());
+        info.extend_from_slice(&challenge.target_info);
+        challenge.target_info = info;
+        assert_eq!(challenge.timestamp(), Some(42));
+        // The server's clock goes into the blob, and LMv2 is left empty.
+        let (nt, lm) = responses(&challenge, &identity, [0xaa; 8], 0);
+        assert_eq!(nt[24..32], 42u64.to_le_bytes());
+        assert_eq!(lm, [0; 24]);
+    }
+}
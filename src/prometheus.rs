@@ -0,0 +1,168 @@
+//! Load-test metrics in the Prometheus text exposition format, for
+//! `--metrics-listen` and `--metrics-file` with `--bench` and `--watch`.
+//!
+//! A [`Collector`] is updated from every worker as requests finish, so a
+//! scrape in the middle of a run sees the numbers so far. [`serve`] answers
+//! every request on a listener with the current [`Collector::render`].
+
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+/// Upper bounds of the latency histogram, in seconds.
+pub const BUCKETS: &[f64] = &[
+    0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+#[derive(Debug, Default)]
+struct Counts {
+    statuses: BTreeMap<u16, u64>,
+    errors: u64,
+    /// Responses at or under each of [`BUCKETS`].
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+/// Request counts, error counts and a latency histogram.
+#[derive(Debug, Default)]
+pub struct Collector {
+    counts: Mutex<Counts>,
+}
+
+impl Collector {
+    pub fn new() -> Collector {
+        Collector::default()
+    }
+
+    /// Counts a response with `status` that took `latency`.
+    pub fn response(&self, status: u16, latency: Duration) {
+        let mut counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        *counts.statuses.entry(status).or_default() += 1;
+        if counts.buckets.is_empty() {
+            counts.buckets = vec![0; BUCKETS.len()];
+        }
+        let secs = latency.as_secs_f64();
+        for (count, bound) in counts.buckets.iter_mut().zip(BUCKETS) {
+            if secs <= *bound {
+                *count += 1;
+            }
+        }
+        counts.sum += secs;
+        counts.count += 1;
+    }
+
+    /// Counts a request that failed without a response.
+    pub fn error(&self) {
+        self.counts.lock().unwrap_or_else(|e| e.into_inner()).errors += 1;
+    }
+
+    /// Everything so far, as a scrape would see it.
+    pub fn render(&self) -> String {
+        let counts = self.counts.lock().unwrap_or_else(|e| e.into_inner());
+        let mut out = String::new();
+        let mut line = |text: String| {
+            out.push_str(&text);
+            out.push('\n');
+        };
+        line("# HELP http_client_requests_total Requests that got a response.".into());
+        line("# TYPE http_client_requests_total counter".into());
+        for (status, n) in &counts.statuses {
+            line(format!(
+                "http_client_requests_total{{status=\"{}\"}} {}",
+                status, n
+            ));
+        }
+        line("# HELP http_client_errors_total Requests that failed without a response.".into());
+        line("# TYPE http_client_errors_total counter".into());
+        line(format!("http_client_errors_total {}", counts.errors));
+        let name = "http_client_request_duration_seconds";
+        line(format!("# HELP {} Time until the response was read.", name));
+        line(format!("# TYPE {} histogram", name));
+        for (i, bound) in BUCKETS.iter().enumerate() {
+            let n = counts.buckets.get(i).copied().unwrap_or(0);
+            line(format!("{}_bucket{{le=\"{}\"}} {}", name, bound, n));
+        }
+        line(format!("{}_bucket{{le=\"+Inf\"}} {}", name, counts.count));
+        line(format!("{}_sum {}", name, counts.sum));
+        line(format!("{}_count {}", name, counts.count));
+        out
+    }
+}
+
+/// Answers every request on `listener` with the metrics, on a thread of
+/// its own that lives as long as the process.
+pub fn serve(listener: TcpListener, collector: Arc<Collector>) {
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+            // The request itself does not matter; read its head so the
+            // client is not reset before it sees the answer.
+            let mut reader = BufReader::new(&stream);
+            let mut line = String::new();
+            while reader.read_line(&mut line).is_ok_and(|n| n > 0) && !line.trim().is_empty() {
+                line.clear();
+            }
+            let body = collector.render();
+            let mut response = String::new();
+            let _ = write!(
+                response,
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = stream.write_all(response.as_bytes());
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+    use std::net::TcpStream;
+
+    #[test]
+    fn renders_counters_and_a_histogram() {
+        let collector = Collector::new();
+        collector.response(200, Duration::from_millis(3));
+        collector.response(200, Duration::from_millis(40));
+        collector.response(503, Duration::from_secs(20));
+        collector.error();
+        let text = collector.render();
+        assert!(text.contains("http_client_requests_total{status=\"200\"} 2\n"));
+        assert!(text.contains("http_client_requests_total{status=\"503\"} 1\n"));
+        assert!(text.contains("http_client_errors_total 1\n"));
+        assert!(text.contains("http_client_request_duration_seconds_bucket{le=\"0.0025\"} 0\n"));
+        assert!(text.contains("http_client_request_duration_seconds_bucket{le=\"0.005\"} 1\n"));
+        assert!(text.contains("http_client_request_duration_seconds_bucket{le=\"0.05\"} 2\n"));
+        assert!(text.contains("http_client_request_duration_seconds_bucket{le=\"10\"} 2\n"));
+        assert!(text.contains("http_client_request_duration_seconds_bucket{le=\"+Inf\"} 3\n"));
+        assert!(text.contains("http_client_request_duration_seconds_count 3\n"));
+    }
+
+    #[test]
+    fn serves_the_current_numbers() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let collector = Arc::new(Collector::new());
+        serve(listener, collector.clone());
+        collector.error();
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream
+            .write_all(b"GET /metrics HTTP/1.1\r\nHost: x\r\n\r\n")
+            .unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with(&collector.render()));
+        assert!(response.contains("http_client_errors_total 1\n"));
+    }
+}
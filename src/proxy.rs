@@ -0,0 +1,65 @@
+//! Forward HTTP proxies for `-x`.
+//!
+//! A proxied `http://` request goes to the proxy with the whole URL as its
+//! target (see [`Url::absolute_form`]), and the proxy makes the request on
+//! the client's behalf. `Proxy-Authorization` and any `--proxy-header`s
+//! are meant for the proxy and are never sent without one.
+
+use crate::error::{Error, Result};
+use crate::url::Url;
+
+/// The port a proxy listens on when none is given, as in curl.
+pub const DEFAULT_PORT: u16 = 1080;
+
+/// Where to send proxied requests, and as whom.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proxy {
+    pub host: String,
+    pub port: u16,
+    pub user: Option<String>,
+    pub password: Option<String>,
+}
+
+impl Proxy {
+    /// Reads `[http://][user:password@]host[:port]`. Other schemes, such as
+    /// `socks5://` or `https://`, are not supported.
+    pub fn parse(spec: &str) -> Result<Proxy> {
+        let url = if spec.contains("://") {
+            Url::parse(spec)?
+        } else {
+            Url::parse(&format!("http://{}", spec))?
+        };
+        if url.scheme() != "http" {
+            return Err(Error::UnsupportedProtocol(format!(
+                "{} (proxy)",
+                url.scheme()
+            )));
+        }
+        Ok(Proxy {
+            host: url.connect_host().to_string(),
+            port: url.port().unwrap_or(DEFAULT_PORT),
+            user: url.username(),
+            password: url.password(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_proxy_specs() {
+        let proxy = Proxy::parse("u%40corp:p@[::1]:3128").unwrap();
+        assert_eq!(proxy.host, "::1");
+        assert_eq!(proxy.port, 3128);
+        assert_eq!(proxy.user.as_deref(), Some("u@corp"));
+        assert_eq!(proxy.password.as_deref(), Some("p"));
+        let proxy = Proxy::parse("http://proxy.test").unwrap();
+        assert_eq!((proxy.port, proxy.user), (DEFAULT_PORT, None));
+        assert!(matches!(
+            Proxy::parse("socks5://proxy.test"),
+            Err(Error::UnsupportedProtocol(_))
+        ));
+    }
+}
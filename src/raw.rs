@@ -0,0 +1,105 @@
+//! Pre-composed requests for `--request-file`, such as captured traffic or
+//! hand-written edge cases.
+//!
+//! A request file is sent byte for byte. [`fix_up`] optionally repairs the
+//! parts that usually go stale when a capture is edited or replayed
+//! elsewhere: line endings, `Host` and `Content-Length`.
+
+/// The method on the request line, if there is one.
+pub fn method(request: &[u8]) -> Option<&str> {
+    let line = request.split(|&b| b == b'\n').next()?;
+    let method = std::str::from_utf8(line).ok()?.split(' ').next()?;
+    (!method.is_empty()).then_some(method)
+}
+
+/// The head and the body, split at the first blank line whether lines end
+/// in CRLF or a bare LF.
+fn split_head(request: &[u8]) -> (&[u8], &[u8]) {
+    for i in 0..request.len() {
+        for end in [&b"\r\n\r\n"[..], b"\n\n", b"\n\r\n"] {
+            if request[i..].starts_with(end) {
+                return (&request[..i], &request[i + end.len()..]);
+            }
+        }
+    }
+    (request, &[])
+}
+
+/// The head's lines without their endings, for `-v`.
+pub fn head_lines(request: &[u8]) -> impl Iterator<Item = String> + '_ {
+    let (head, _) = split_head(request);
+    head.split(|&b| b == b'\n').map(|line| {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        String::from_utf8_lossy(line).into_owned()
+    })
+}
+
+/// The value of the first header called `name` in the head.
+pub fn header(request: &[u8], name: &str) -> Option<String> {
+    head_lines(request).skip(1).find_map(|line| {
+        let (n, value) = line.split_once(':')?;
+        n.trim()
+            .eq_ignore_ascii_case(name)
+            .then(|| value.trim().to_string())
+    })
+}
+
+/// `request` with CRLF line endings in its head, `Host` set to `host`, and
+/// `Content-Length` matching the body unless it is sent chunked. The body
+/// itself is left alone.
+pub fn fix_up(request: &[u8], host: &str) -> Vec<u8> {
+    let (head, body) = split_head(request);
+    let mut lines: Vec<String> = head_lines(head).filter(|line| !line.is_empty()).collect();
+    let is = |line: &str, name: &str| {
+        line.split_once(':')
+            .is_some_and(|(n, _)| n.trim().eq_ignore_ascii_case(name))
+    };
+    let chunked = lines.iter().any(|line| is(line, "Transfer-Encoding"));
+    lines.retain(|line| !is(line, "Host") && (chunked || !is(line, "Content-Length")));
+    let at = 1.min(lines.len());
+    lines.insert(at, format!("Host: {}", host));
+    if !chunked && (!body.is_empty() || method(request).is_some_and(|m| m == "POST" || m == "PUT"))
+    {
+        lines.push(format!("Content-Length: {}", body.len()));
+    }
+    let mut out = Vec::with_capacity(request.len() + 64);
+    for line in &lines {
+        out.extend_from_slice(line.as_bytes());
+        out.extend_from_slice(b"\r\n");
+    }
+    out.extend_from_slice(b"\r\n");
+    out.extend_from_slice(body);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_the_request_line() {
+        assert_eq!(method(b"DELETE /x HTTP/1.1\r\n\r\n"), Some("DELETE"));
+        assert_eq!(method(b""), None);
+        let lines: Vec<String> = head_lines(b"GET / HTTP/1.1\r\nA: 1\n\nbody").collect();
+        assert_eq!(lines, ["GET / HTTP/1.1", "A: 1"]);
+        let request = b"GET / HTTP/1.1\nHOST:  a.test:81 \n\nHost: body";
+        assert_eq!(header(request, "host").as_deref(), Some("a.test:81"));
+        assert_eq!(header(request, "Accept"), None);
+    }
+
+    #[test]
+    fn fixes_host_length_and_line_endings() {
+        let fixed = fix_up(
+            b"POST /a HTTP/1.1\nhost: old.test\nContent-Length: 99\nX: 1\n\nhello",
+            "new.test:8080",
+        );
+        assert_eq!(
+            fixed,
+            b"POST /a HTTP/1.1\r\nHost: new.test:8080\r\nX: 1\r\nContent-Length: 5\r\n\r\nhello"
+        );
+        let chunked = b"POST / HTTP/1.1\r\nTransfer-Encoding: chunked\r\n\r\n0\r\n\r\n";
+        let fixed = fix_up(chunked, "h");
+        assert!(!String::from_utf8_lossy(&fixed).contains("Content-Length"));
+        assert!(fixed.ends_with(b"\r\n\r\n0\r\n\r\n"));
+    }
+}
@@ -0,0 +1,365 @@
+//! `--interactive`: a read-eval-print loop for sending requests.
+//!
+//! Each input line is one command. Connections stay open between requests
+//! to the same server, cookies set by responses are sent back, and headers
+//! given with `header` stick until removed, so a request can be refined
+//! without retyping everything. Lines are read plainly, with no editing
+//! keys or tab completion of their own; `rlwrap` can add both, completing
+//! the words `--completions rlwrap` lists.
+
+use std::collections::HashMap;
+use std::io::{BufRead, Write};
+
+use http_client::cookie::Jar;
+use http_client::error::{Error, Result};
+use http_client::headers::CustomHeader;
+use http_client::http::{self, PersistentConnection};
+use http_client::sigv4::Payload;
+use http_client::url::Url;
+
+use crate::cli::Options;
+use crate::i18n::{tr, Msg};
+
+/// The words a command line starts with, besides methods and URLs.
+pub const COMMANDS: &[&str] = &[
+    "header", "cookies", "clear", "history", "help", "quit", "exit",
+];
+
+/// One parsed input line.
+#[derive(Debug, PartialEq, Eq)]
+enum Command<'a> {
+    Request {
+        method: &'a str,
+        url: &'a str,
+        body: Option<&'a str>,
+    },
+    /// `header Name: value` sets, `header Name:` removes, `header` lists.
+    Header(Option<&'a str>),
+    Cookies,
+    ClearCookies,
+    History,
+    Help,
+    Quit,
+}
+
+impl<'a> Command<'a> {
+    fn parse(line: &'a str) -> Option<Command<'a>> {
+        let line = line.trim();
+        let (word, rest) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let rest = rest.trim();
+        match (word, rest) {
+            ("header", "") => Some(Command::Header(None)),
+            ("header", header) => Some(Command::Header(Some(header))),
+            ("cookies", "") => Some(Command::Cookies),
+            ("cookies", "clear") => Some(Command::ClearCookies),
+            ("history", "") => Some(Command::History),
+            ("help", "") => Some(Command::Help),
+            ("quit" | "exit", "") => Some(Command::Quit),
+            (url, "") if url.starts_with('/') || url.contains("://") => Some(Command::Request {
+                method: "GET",
+                url,
+                body: None,
+            }),
+            (method, rest)
+                if !rest.is_empty()
+                    && method.bytes().all(|b| b.is_ascii_uppercase() || b == b'-') =>
+            {
+                let (url, body) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+                let body = Some(body.trim()).filter(|b| !b.is_empty());
+                Some(Command::Request { method, url, body })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The state kept from one command to the next.
+struct Session<'a> {
+    opts: &'a Options,
+    limits: &'a http::Limits,
+    /// The last URL requested; relative URLs are resolved against it.
+    base: Option<Url>,
+    headers: Vec<CustomHeader>,
+    cookies: Jar,
+    connections: HashMap<(String, u16), PersistentConnection>,
+    history: Vec<String>,
+}
+
+/// Runs commands from `input` until it ends or `quit`, writing responses
+/// and listings to `out`. A failed command is reported and the loop goes
+/// on.
+pub fn run<R: BufRead, W: Write>(
+    opts: &Options,
+    limits: &http::Limits,
+    mut input: R,
+    out: &mut W,
+) -> Result<()> {
+    let mut session = Session {
+        opts,
+        limits,
+        base: None,
+        headers: Vec::new(),
+        cookies: Jar::new(),
+        connections: HashMap::new(),
+        history: Vec::new(),
+    };
+    if let Some(url) = &opts.url {
+        session.base = Some(crate::parse_url(opts, url)?);
+    }
+    let write_err = |source| Error::File {
+        path: "stdout".to_string(),
+        source,
+    };
+    loop {
+        out.write_all(b"> ")
+            .and_then(|()| out.flush())
+            .map_err(write_err)?;
+        let mut line = String::new();
+        let read = input
+            .read_line(&mut line)
+            .map_err(|source| Error::ReadFile {
+                path: "stdin".to_string(),
+                source,
+            })?;
+        if read == 0 {
+            return Ok(());
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let line = match session.recall(line) {
+            Some(Ok(recalled)) => {
+                writeln!(out, "{}", recalled).map_err(write_err)?;
+                recalled
+            }
+            Some(Err(e)) => {
+                eprintln!("http_client: {}", tr(Msg::Error(&e)));
+                continue;
+            }
+            None => line.to_string(),
+        };
+        let Some(command) = Command::parse(&line) else {
+            let e = Error::Usage(tr(Msg::ReplUnknownCommand(&line)));
+            eprintln!("http_client: {}", tr(Msg::Error(&e)));
+            continue;
+        };
+        if command == Command::Quit {
+            return Ok(());
+        }
+        if command != Command::History {
+            session.history.push(line.clone());
+        }
+        if let Err(e) = session.execute(command, out) {
+            if let Error::File { .. } = e {
+                return Err(e);
+            }
+            eprintln!("http_client: {}", tr(Msg::Error(&e)));
+        }
+    }
+}
+
+impl Session<'_> {
+    /// Expands `!!` and `!n` from the history; `None` for other lines.
+    fn recall(&self, line: &str) -> Option<Result<String>> {
+        let reference = line.strip_prefix('!')?;
+        let index = match reference {
+            "!" => self.history.len().checked_sub(1),
+            n => n
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| n.checked_sub(1))
+                .filter(|&i| i < self.history.len()),
+        };
+        Some(
+            index
+                .map(|i| self.history[i].clone())
+                .ok_or_else(|| Error::Usage(tr(Msg::ReplNoHistory(line)))),
+        )
+    }
+
+    fn execute<W: Write>(&mut self, command: Command, out: &mut W) -> Result<()> {
+        let write_err = |source| Error::File {
+            path: "stdout".to_string(),
+            source,
+        };
+        match command {
+            Command::Request { method, url, body } => {
+                let output = self.request(method, url, body)?;
+                out.write_all(&output).map_err(write_err)
+            }
+            Command::Header(Some(header)) => {
+                let header = CustomHeader::parse(header)?;
+                let name = match &header {
+                    CustomHeader::Set(name, _) | CustomHeader::Remove(name) => name.clone(),
+                };
+                self.headers.retain(|h| match h {
+                    CustomHeader::Set(n, _) | CustomHeader::Remove(n) => {
+                        !n.eq_ignore_ascii_case(&name)
+                    }
+                });
+                // A removal stays, to keep built-in headers away too.
+                self.headers.push(header);
+                Ok(())
+            }
+            Command::Header(None) => {
+                for header in &self.headers {
+                    match header {
+                        CustomHeader::Set(name, value) => writeln!(out, "{}: {}", name, value),
+                        CustomHeader::Remove(name) => writeln!(out, "{}:", name),
+                    }
+                    .map_err(write_err)?;
+                }
+                Ok(())
+            }
+            Command::Cookies => {
+                for cookie in self.cookies.iter() {
+                    writeln!(
+                        out,
+                        "{}={}\t{}{}",
+                        cookie.name, cookie.value, cookie.domain, cookie.path
+                    )
+                    .map_err(write_err)?;
+                }
+                Ok(())
+            }
+            Command::ClearCookies => {
+                self.cookies.clear();
+                Ok(())
+            }
+            Command::History => {
+                for (i, line) in self.history.iter().enumerate() {
+                    writeln!(out, "{:4}  {}", i + 1, line).map_err(write_err)?;
+                }
+                Ok(())
+            }
+            Command::Help => writeln!(out, "{}", tr(Msg::ReplHelp)).map_err(write_err),
+            Command::Quit => Ok(()),
+        }
+    }
+
+    /// Sends one request and returns the head and body to print.
+    fn request(&mut self, method: &str, url: &str, body: Option<&str>) -> Result<Vec<u8>> {
+        let url = match &self.base {
+            Some(base) => base.join(url)?,
+            None => crate::parse_url(self.opts, url)?,
+        };
+        if url.scheme() != "http" {
+            return Err(Error::UnsupportedProtocol(url.scheme().to_string()));
+        }
+        let mut headers = crate::request_headers(self.opts, &url, body, None, &self.headers, true);
+        if let Some(cookie) = self.cookies.header(&url) {
+            if !headers.contains("Cookie") {
+                headers.insert("Cookie", cookie);
+            }
+        }
+        let body = body.map(str::as_bytes);
+        let payload = Payload::Bytes(body.unwrap_or_default());
+        crate::sign_aws(self.opts, method, &url, &mut headers, payload)?;
+
+        let host = url.connect_host().to_string();
+        let port = url.port_or_default();
+        let conn = self
+            .connections
+            .entry((host.clone(), port))
+            .or_insert_with(|| PersistentConnection::new(&host, port));
+        let response = conn.send(method, &url.request_target(), &headers, body, self.limits)?;
+        self.cookies.store(&url, &response.headers);
+        self.base = Some(url);
+
+        let mut output = response.head_text().into_bytes();
+        output.extend_from_slice(&response.body);
+        if !output.ends_with(b"\n") {
+            output.push(b'\n');
+        }
+        Ok(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufReader, Read};
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            Command::parse("POST /items {\"a\": 1}"),
+            Some(Command::Request {
+                method: "POST",
+                url: "/items",
+                body: Some("{\"a\": 1}"),
+            })
+        );
+        assert_eq!(
+            Command::parse("http://h/x"),
+            Some(Command::Request {
+                method: "GET",
+                url: "http://h/x",
+                body: None,
+            })
+        );
+        assert_eq!(
+            Command::parse("header X-A: 1"),
+            Some(Command::Header(Some("X-A: 1")))
+        );
+        assert_eq!(Command::parse("cookies clear"), Some(Command::ClearCookies));
+        assert_eq!(Command::parse("exit"), Some(Command::Quit));
+        assert_eq!(Command::parse("get /x"), None);
+        assert_eq!(Command::parse("GET"), None);
+    }
+
+    #[test]
+    fn keeps_connections_cookies_and_headers() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            // Every request must arrive over the one connection.
+            let (stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut seen = Vec::new();
+            for i in 0..3 {
+                let head = http_client::response::read_head(&mut reader).unwrap();
+                let head = String::from_utf8(head).unwrap();
+                if head.starts_with("POST") {
+                    let mut body = [0; 2];
+                    reader.read_exact(&mut body).unwrap();
+                }
+                seen.push(head);
+                let reply = format!(
+                    "HTTP/1.1 200 OK\r\nSet-Cookie: n={}\r\nContent-Length: 2\r\n\r\nok",
+                    i
+                );
+                (&stream).write_all(reply.as_bytes()).unwrap();
+            }
+            seen
+        });
+
+        let opts = Options {
+            url: Some(format!("http://127.0.0.1:{}/", port)),
+            interactive: true,
+            ..Options::default()
+        };
+        let input = "/a\nheader X-Test: yes\nPOST /b hi\nheader X-Test:\n!1\nhistory\nbogus\n";
+        let mut out = Vec::new();
+        run(&opts, &http::Limits::default(), input.as_bytes(), &mut out).unwrap();
+
+        let seen = server.join().unwrap();
+        assert!(seen[0].starts_with("GET /a "));
+        assert!(!seen[0].contains("Cookie"));
+        assert!(!seen[0].to_ascii_lowercase().contains("connection: close"));
+        assert!(seen[1].starts_with("POST /b "));
+        assert!(seen[1].contains("\r\nX-Test: yes\r\n"));
+        assert!(seen[1].contains("\r\nCookie: n=0\r\n"));
+        assert!(seen[2].starts_with("GET /a "));
+        assert!(!seen[2].contains("X-Test"));
+        assert!(seen[2].contains("\r\nCookie: n=1\r\n"));
+
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(out.matches("HTTP/1.1 200 OK").count(), 3);
+        assert!(out.contains("> /a\n"));
+        assert!(out.contains("   1  /a\n   2  header X-Test: yes\n"));
+    }
+}
@@ -0,0 +1,278 @@
+//! Requests for library users, put together with a builder:
+//!
+//! ```no_run
+//! use http_client::request::{Method, Request};
+//!
+//! let response = Request::builder()
+//!     .method(Method::Post)
+//!     .url("http://api.example/items")
+//!     .header("Content-Type", "application/json")
+//!     .body(r#"{"name": "x"}"#)
+//!     .build()?
+//!     .send()?;
+//! # Ok::<(), http_client::error::Error>(())
+//! ```
+//!
+//! Methods, URLs and headers are checked as they are given, so a
+//! [`Request`] that was built can be sent as it is. Whatever was wrong is
+//! reported by [`RequestBuilder::build`].
+
+use std::fmt;
+
+use crate::error::{Error, Result};
+use crate::headers::{self, HeaderMap};
+use crate::http::{self, Exchange, Limits, StreamingResponse};
+use crate::response::Response;
+use crate::url::Url;
+
+/// A request method: one of RFC 9110's, PATCH, or any other token.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Method {
+    Get,
+    Head,
+    Post,
+    Put,
+    Delete,
+    Connect,
+    Options,
+    Trace,
+    Patch,
+    /// An extension method such as `PROPFIND`.
+    Other(String),
+}
+
+impl Method {
+    /// A method by name. Methods are case-sensitive, so `get` is an
+    /// extension method and not [`Method::Get`].
+    pub fn parse(name: &str) -> Result<Method> {
+        Ok(match name {
+            "GET" => Method::Get,
+            "HEAD" => Method::Head,
+            "POST" => Method::Post,
+            "PUT" => Method::Put,
+            "DELETE" => Method::Delete,
+            "CONNECT" => Method::Connect,
+            "OPTIONS" => Method::Options,
+            "TRACE" => Method::Trace,
+            "PATCH" => Method::Patch,
+            _ if !name.is_empty() && name.bytes().all(headers::is_token_byte) => {
+                Method::Other(name.to_string())
+            }
+            _ => {
+                return Err(Error::InvalidRequest(format!(
+                    "invalid method '{}'",
+                    name.escape_debug()
+                )))
+            }
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Method::Get => "GET",
+            Method::Head => "HEAD",
+            Method::Post => "POST",
+            Method::Put => "PUT",
+            Method::Delete => "DELETE",
+            Method::Connect => "CONNECT",
+            Method::Options => "OPTIONS",
+            Method::Trace => "TRACE",
+            Method::Patch => "PATCH",
+            Method::Other(name) => name,
+        }
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl PartialEq<str> for Method {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Method {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+/// A request ready to be sent.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: Method,
+    /// Where the request goes; changing it changes where it is sent.
+    pub url: Url,
+    /// Every header that will be sent, built-in ones included.
+    pub headers: HeaderMap,
+    pub body: Option<String>,
+}
+
+impl Request {
+    pub fn builder() -> RequestBuilder {
+        RequestBuilder::default()
+    }
+
+    /// Sends the request once over HTTP/1.1, without following redirects.
+    /// `https://` URLs go over the TLS backend the crate was built with.
+    pub fn send(&self) -> Result<Response> {
+        self.send_with(&Limits::default())
+    }
+
+    /// Sends the request and returns once the response head is in; the
+    /// body is read from the returned response as it arrives.
+    pub fn stream(&self, limits: &Limits) -> Result<StreamingResponse<'static>> {
+        let limits = &limits.for_url(&self.url)?;
+        http::open_request(&self.exchange(&self.url.request_target(), limits))
+    }
+
+    pub fn send_with(&self, limits: &Limits) -> Result<Response> {
+        let limits = &limits.for_url(&self.url)?;
+        http::send_http_request(&self.exchange(&self.url.request_target(), limits))
+    }
+
+    fn exchange<'r>(&'r self, target: &'r str, limits: &'r Limits) -> Exchange<'r, 'static> {
+        Exchange {
+            body: self.body.as_deref().map(str::as_bytes),
+            ..Exchange::new(
+                self.url.connect_host(),
+                self.url.port_or_default(),
+                self.method.as_str(),
+                target,
+                &self.headers,
+                limits,
+            )
+        }
+    }
+}
+
+/// Collects the parts of a [`Request`]. The first mistake is kept and
+/// returned by [`build`](RequestBuilder::build).
+#[derive(Debug, Default)]
+pub struct RequestBuilder {
+    method: Option<Method>,
+    url: Option<Url>,
+    headers: HeaderMap,
+    body: Option<String>,
+    error: Option<Error>,
+}
+
+impl RequestBuilder {
+    /// GET unless set.
+    pub fn method(mut self, method: Method) -> RequestBuilder {
+        self.method = Some(method);
+        self
+    }
+
+    pub fn url(mut self, url: &str) -> RequestBuilder {
+        match Url::parse(url) {
+            Ok(url) => self.url = Some(url),
+            Err(e) => self.fail(e),
+        }
+        self
+    }
+
+    /// Adds a header, keeping any already given with the same name. The
+    /// first one given for a built-in header such as `User-Agent`
+    /// replaces it.
+    pub fn header(mut self, name: &str, value: &str) -> RequestBuilder {
+        match headers::validate(name, value) {
+            Ok(()) => self.headers.append(name, value),
+            Err(e) => self.fail(e),
+        }
+        self
+    }
+
+    pub fn body(mut self, body: impl Into<String>) -> RequestBuilder {
+        self.body = Some(body.into());
+        self
+    }
+
+    fn fail(&mut self, e: Error) {
+        self.error.get_or_insert(e);
+    }
+
+    /// The request, with `Host`, `User-Agent`, `Accept` and
+    /// `Content-Length` filled in unless given. `Connection` is left to
+    /// whatever sends it.
+    pub fn build(self) -> Result<Request> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        let url = self
+            .url
+            .ok_or_else(|| Error::InvalidRequest("no URL given".to_string()))?;
+        let mut headers =
+            http::default_headers(&url.host_header(), self.body.as_ref().map(String::len));
+        headers.remove("Connection");
+        for (name, _) in self.headers.iter() {
+            headers.remove(name);
+        }
+        for (name, value) in self.headers.iter() {
+            headers.append(name, value);
+        }
+        Ok(Request {
+            method: self.method.unwrap_or(Method::Get),
+            url,
+            headers,
+            body: self.body,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_methods() {
+        assert_eq!(Method::parse("PATCH").unwrap(), Method::Patch);
+        assert_eq!(
+            Method::parse("PROPFIND").unwrap(),
+            Method::Other("PROPFIND".into())
+        );
+        assert_eq!(Method::parse("get").unwrap().as_str(), "get");
+        assert_eq!(Method::parse("GET /x").unwrap_err().exit_code(), 43);
+        assert!(Method::Delete == "DELETE");
+    }
+
+    #[test]
+    fn builds_requests() {
+        let request = Request::builder()
+            .method(Method::Put)
+            .url("http://a.test:8080/x?y=1")
+            .header("X-One", "1")
+            .header("X-One", "2")
+            .header("User-Agent", "probe/1")
+            .body("hello")
+            .build()
+            .unwrap();
+        assert_eq!(request.method, Method::Put);
+        let headers: Vec<(&str, &str)> = request.headers.iter().collect();
+        assert_eq!(
+            headers,
+            [
+                ("Host", "a.test:8080"),
+                ("Accept", "*/*"),
+                ("Content-Length", "5"),
+                ("X-One", "1"),
+                ("X-One", "2"),
+                ("User-Agent", "probe/1"),
+            ]
+        );
+
+        let err = Request::builder()
+            .url("http://a.test/")
+            .header("Bad\r\nName", "x")
+            .header("Second", "bad\n")
+            .build()
+            .unwrap_err();
+        assert!(err.to_string().contains("invalid header name"));
+        let err = Request::builder().build().unwrap_err();
+        assert_eq!(err.exit_code(), 43);
+    }
+}
@@ -0,0 +1,506 @@
+//! Parsed HTTP responses.
+//!
+//! The header block is split into a [`StatusLine`] and a list of header
+//! fields (a [`HeaderMap`]); the body is kept as raw bytes so binary payloads survive intact.
+
+use std::fmt;
+use std::io::BufRead;
+use std::time::Duration;
+
+use crate::charset::{self, Charset};
+use crate::date;
+use crate::error::{Error, Result};
+use crate::headers::HeaderMap;
+use crate::json::{self, FromJson};
+use crate::servertiming::{self, Metric};
+
+/// The first line of a response, e.g. `HTTP/1.1 404 Not Found`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatusLine {
+    /// The protocol version as sent, e.g. `HTTP/1.1`.
+    pub version: String,
+    /// The three-digit status code.
+    pub code: u16,
+    /// The reason phrase; may be empty.
+    pub reason: String,
+}
+
+impl StatusLine {
+    /// Parses a status line (without the trailing CRLF).
+    pub fn parse(line: &str) -> Result<StatusLine> {
+        let mut parts = line.splitn(3, ' ');
+        let version = parts.next().unwrap_or_default();
+        if !version.starts_with("HTTP/") {
+            return Err(Error::HttpProtocol(format!(
+                "malformed status line '{}'",
+                line
+            )));
+        }
+        let code = parts.next().ok_or_else(|| {
+            Error::HttpProtocol(format!("status line '{}' has no status code", line))
+        })?;
+        if code.len() != 3 || !code.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(Error::HttpProtocol(format!(
+                "status line '{}' has an invalid status code '{}'",
+                line, code
+            )));
+        }
+        Ok(StatusLine {
+            version: version.to_string(),
+            code: code.parse().expect("three ASCII digits"),
+            reason: parts.next().unwrap_or_default().to_string(),
+        })
+    }
+}
+
+impl fmt::Display for StatusLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}", self.version, self.code)?;
+        if !self.reason.is_empty() {
+            write!(f, " {}", self.reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// A complete response: status, headers in received order, and body.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub status: StatusLine,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+    /// Fields sent after the body: the trailer section of a chunked body
+    /// (RFC 9112 section 7.1.2) or of an HTTP/2 stream. Usually empty.
+    pub trailers: HeaderMap,
+}
+
+impl Response {
+    /// The status code.
+    pub fn status(&self) -> u16 {
+        self.status.code
+    }
+
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.body
+    }
+
+    /// The body as text, decoded from the charset `Content-Type` or a byte
+    /// order mark names. Bytes that are not valid in it are replaced.
+    pub fn text(&self) -> String {
+        let content_type = self.headers.get("Content-Type");
+        charset::to_utf8(content_type, &self.body)
+            .unwrap_or_else(|| String::from_utf8_lossy(&self.body).into_owned())
+    }
+
+    /// The body parsed as JSON and turned into a `T`, such as a
+    /// [`json::Value`] or a `Vec<String>`.
+    ///
+    /// There is no serde support: `T` implements this crate's own
+    /// [`FromJson`], not `serde::Deserialize`.
+    pub fn json<T: FromJson>(&self) -> Result<T> {
+        let value = json::parse(&self.text())
+            .map_err(|e| Error::HttpProtocol(format!("body is not JSON: {}", e)))?;
+        T::from_json(&value).ok_or_else(|| {
+            Error::HttpProtocol("body does not have the expected JSON shape".to_string())
+        })
+    }
+
+    /// The status line and headers as they would appear on the wire,
+    /// including the terminating blank line.
+    pub fn head_text(&self) -> String {
+        let mut out = format!("{}\r\n", self.status);
+        out.push_str(&self.headers.to_string());
+        out.push_str("\r\n");
+        out
+    }
+
+    /// The trailer fields as they appear on the wire, ending with a blank
+    /// line; empty when there are none.
+    pub fn trailer_text(&self) -> String {
+        match self.trailers.is_empty() {
+            true => String::new(),
+            false => format!("{}\r\n", self.trailers),
+        }
+    }
+
+    /// How long `Retry-After` asks the client to wait, given as seconds or
+    /// as an HTTP date compared with `now` (RFC 9110 section 10.2.3). A date
+    /// in the past means no wait.
+    pub fn retry_after(&self, now: u64) -> Option<Duration> {
+        let value = self.headers.get("Retry-After")?.trim();
+        let secs = match value.parse::<u64>() {
+            Ok(secs) => secs,
+            Err(_) => date::parse(value)?.saturating_sub(now),
+        };
+        Some(Duration::from_secs(secs))
+    }
+
+    /// The metrics of every `Server-Timing` header and trailer, in order.
+    pub fn server_timing(&self) -> Vec<Metric> {
+        self.headers
+            .get_all("Server-Timing")
+            .chain(self.trailers.get_all("Server-Timing"))
+            .flat_map(servertiming::parse)
+            .collect()
+    }
+
+    /// Whether the body is binary rather than text: it has a NUL byte near
+    /// the start, as curl checks, unless it is UTF-16 text, or `Content-Type` names a binary format.
+    pub fn is_binary(&self) -> bool {
+        let content_type = self.headers.get("Content-Type");
+        let utf16 = matches!(
+            Charset::detect(content_type, &self.body),
+            Some((Charset::Utf16Le | Charset::Utf16Be, _))
+        );
+        if !utf16 && self.body.iter().take(2000).any(|&b| b == 0) {
+            return true;
+        }
+        let Some(content_type) = content_type else {
+            return false;
+        };
+        let media = content_type.split(';').next().unwrap_or_default().trim();
+        let media = media.to_ascii_lowercase();
+        if media.ends_with("+xml") || media.ends_with("+json") {
+            return false;
+        }
+        let (kind, subtype) = media.split_once('/').unwrap_or((&media, ""));
+        matches!(kind, "image" | "audio" | "video" | "font")
+            || (kind == "application"
+                && matches!(
+                    subtype,
+                    "octet-stream" | "zip" | "gzip" | "x-tar" | "pdf" | "wasm" | "zstd"
+                ))
+    }
+}
+
+/// Whether a response with status `code` to a `method` request carries a
+/// body. Responses to HEAD, 1xx, 204 and 304 never do, whatever their
+/// Content-Length says (RFC 9112 section 6.3).
+pub fn has_body(method: &str, code: u16) -> bool {
+    !(method.eq_ignore_ascii_case("HEAD")
+        || (100..200).contains(&code)
+        || code == 204
+        || code == 304)
+}
+
+/// The largest header block accepted, as curl's `CURL_MAX_HTTP_HEADER`.
+pub const MAX_HEAD_LEN: usize = 100 * 1024;
+
+/// Reads a header block up to and including the blank line that ends it.
+/// Lines may end in a bare LF, as some servers send them; [`parse_head_with`]
+/// decides whether that is accepted.
+///
+/// The reader is consumed exactly up to the end of the block, so whatever
+/// the server sent after it is still buffered in `reader` for the body.
+pub fn read_head<R: BufRead>(reader: &mut R) -> Result<Vec<u8>> {
+    let mut head = Vec::new();
+    loop {
+        let chunk = reader.fill_buf()?;
+        if chunk.is_empty() {
+            return Err(Error::HttpProtocol(
+                "connection closed before the response headers ended".to_string(),
+            ));
+        }
+        // The terminator may straddle the previous chunk and this one.
+        let before = head.len();
+        head.extend_from_slice(chunk);
+        let len = chunk.len();
+        if let Some(end) = head_end(&head, before) {
+            reader.consume(end - before);
+            head.truncate(end);
+            return Ok(head);
+        }
+        reader.consume(len);
+        if head.len() > MAX_HEAD_LEN {
+            return Err(Error::HttpProtocol(format!(
+                "response headers exceed {} bytes",
+                MAX_HEAD_LEN
+            )));
+        }
+    }
+}
+
+/// Where the blank line ending a head is, just past it, looking only at
+/// line ends from `from` on (less the two bytes that may start one).
+fn head_end(head: &[u8], from: usize) -> Option<usize> {
+    let start = from.saturating_sub(2);
+    head[start..]
+        .iter()
+        .enumerate()
+        .filter(|&(_, &b)| b == b'\n')
+        .map(|(i, _)| start + i)
+        .find(|&i| head[..i].ends_with(b"\n") || head[..i].ends_with(b"\n\r"))
+        .map(|i| i + 1)
+}
+
+/// Parses a header block (status line and header fields, with or without
+/// the final blank line), tolerating what common servers get wrong.
+pub fn parse_head(head: &[u8]) -> Result<(StatusLine, HeaderMap)> {
+    parse_head_with(head, false)
+}
+
+/// Parses a header block. Long values folded onto lines that start with
+/// whitespace (RFC 9112's obsolete line folding) are joined with a space
+/// either way. With `strict`, lines ending in a bare LF and a status line
+/// without the space before its reason phrase are errors; otherwise they
+/// are accepted as curl and browsers do.
+pub fn parse_head_with(head: &[u8], strict: bool) -> Result<(StatusLine, HeaderMap)> {
+    let text = String::from_utf8_lossy(head);
+    let bare_lf = text
+        .match_indices('\n')
+        .find(|&(i, _)| !text[..i].ends_with('\r'));
+    if let Some((pos, _)) = bare_lf.filter(|_| strict) {
+        return Err(Error::HttpProtocol(format!(
+            "line ending without CR at byte {} of the response head",
+            pos
+        )));
+    }
+    let mut lines = text.split('\n').map(|l| l.strip_suffix('\r').unwrap_or(l));
+    let first = lines.next().unwrap_or_default();
+    let status = StatusLine::parse(first)?;
+    if strict && first.splitn(3, ' ').count() < 3 {
+        return Err(Error::HttpProtocol(format!(
+            "status line '{}' has no space before a reason phrase",
+            first
+        )));
+    }
+
+    let mut fields: Vec<(&str, String)> = Vec::new();
+    for line in lines.take_while(|l| !l.is_empty()) {
+        if line.starts_with([' ', '\t']) {
+            let (_, value) = fields.last_mut().ok_or_else(|| {
+                Error::HttpProtocol(format!("folded line '{}' before any header", line))
+            })?;
+            if !value.is_empty() {
+                value.push(' ');
+            }
+            value.push_str(line.trim());
+            continue;
+        }
+        let (name, value) = line
+            .split_once(':')
+            .ok_or_else(|| Error::HttpProtocol(format!("malformed header line '{}'", line)))?;
+        if name.is_empty() || name.ends_with([' ', '\t']) {
+            return Err(Error::HttpProtocol(format!(
+                "malformed header name in '{}'",
+                line
+            )));
+        }
+        fields.push((name, value.trim().to_string()));
+    }
+    let mut headers = HeaderMap::new();
+    for (name, value) in fields {
+        headers.append(name, value);
+    }
+    Ok((status, headers))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_retry_after_seconds_and_dates() {
+        let response = |value: &str| Response {
+            status: StatusLine::parse("HTTP/1.1 503 Service Unavailable").unwrap(),
+            headers: [("Retry-After", value)].into_iter().collect(),
+            body: Vec::new(),
+            trailers: HeaderMap::new(),
+        };
+        let now = date::parse("Sun, 06 Nov 1994 08:49:37 GMT").unwrap();
+        let wait = |value: &str| response(value).retry_after(now);
+        assert_eq!(wait("120"), Some(Duration::from_secs(120)));
+        assert_eq!(
+            wait("Sun, 06 Nov 1994 08:50:07 GMT"),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(wait("Sat, 05 Nov 1994 08:49:37 GMT"), Some(Duration::ZERO));
+        assert_eq!(wait("soon"), None);
+        assert_eq!(wait("-1"), None);
+    }
+
+    #[test]
+    fn parses_status_line() {
+        let status = StatusLine::parse("HTTP/1.1 404 Not Found").unwrap();
+        assert_eq!(status.version, "HTTP/1.1");
+        assert_eq!(status.code, 404);
+        assert_eq!(status.reason, "Not Found");
+    }
+
+    #[test]
+    fn reason_phrase_is_optional() {
+        let status = StatusLine::parse("HTTP/1.1 204").unwrap();
+        assert_eq!(status.code, 204);
+        assert_eq!(status.reason, "");
+    }
+
+    #[test]
+    fn rejects_bad_status_lines() {
+        assert!(StatusLine::parse("").is_err());
+        assert!(StatusLine::parse("FTP/1.0 200 OK").is_err());
+        assert!(StatusLine::parse("HTTP/1.1 20 OK").is_err());
+        assert!(StatusLine::parse("HTTP/1.1 abc OK").is_err());
+    }
+
+    #[test]
+    fn parses_headers_in_order() {
+        let head = b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nSet-Cookie: a=1\r\nset-cookie: b=2\r\n\r\n";
+        let (status, headers) = parse_head(head).unwrap();
+        assert_eq!(status.code, 200);
+        assert_eq!(headers.len(), 3);
+        assert_eq!(headers.get("content-type"), Some("text/plain"));
+        assert_eq!(
+            headers.get_all("SET-COOKIE").collect::<Vec<_>>(),
+            ["a=1", "b=2"]
+        );
+        assert_eq!(headers.get("Location"), None);
+    }
+
+    #[test]
+    fn trims_header_values() {
+        let (_, headers) = parse_head(b"HTTP/1.1 200 OK\r\nX-A:   spaced  \r\n\r\n").unwrap();
+        assert_eq!(headers.iter().collect::<Vec<_>>(), [("X-A", "spaced")]);
+    }
+
+    #[test]
+    fn unfolds_continuation_lines() {
+        let head = b"HTTP/1.1 200 OK\r\nX-Long: one,\r\n  two,\r\n\tthree\r\nX-B: b\r\n\r\n";
+        for strict in [false, true] {
+            let (_, headers) = parse_head_with(head, strict).unwrap();
+            assert_eq!(
+                headers.iter().collect::<Vec<_>>(),
+                [("X-Long", "one, two, three"), ("X-B", "b")]
+            );
+        }
+        assert!(parse_head(b"HTTP/1.1 200 OK\r\n folded: x\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn strict_mode_refuses_what_lenient_mode_accepts() {
+        let mut reader: &[u8] = b"HTTP/1.1 200\nA: 1\n\nbody";
+        let head = read_head(&mut reader).unwrap();
+        assert_eq!(reader, b"body");
+        let (status, headers) = parse_head(&head).unwrap();
+        assert_eq!((status.code, headers.get("A")), (200, Some("1")));
+        let err = parse_head_with(&head, true).unwrap_err();
+        assert!(err.to_string().contains("without CR"), "{}", err);
+        let err = parse_head_with(b"HTTP/1.1 204\r\n\r\n", true).unwrap_err();
+        assert!(err.to_string().contains("no space"), "{}", err);
+        assert!(parse_head_with(b"HTTP/1.1 204 \r\n\r\n", true).is_ok());
+    }
+
+    #[test]
+    fn rejects_malformed_header_lines() {
+        assert!(parse_head(b"HTTP/1.1 200 OK\r\nno colon here\r\n\r\n").is_err());
+        assert!(parse_head(b"HTTP/1.1 200 OK\r\nBad : value\r\n\r\n").is_err());
+    }
+
+    #[test]
+    fn decodes_text_and_json_bodies() {
+        let response = |content_type: &str, body: &[u8]| Response {
+            status: StatusLine::parse("HTTP/1.1 200 OK").unwrap(),
+            headers: [("Content-Type", content_type)].into_iter().collect(),
+            body: body.to_vec(),
+            trailers: HeaderMap::new(),
+        };
+        assert_eq!(
+            response("text/plain; charset=latin1", b"caf\xe9").text(),
+            "café"
+        );
+        assert_eq!(response("text/plain", b"ok \xff").text(), "ok \u{fffd}");
+        let list = response("application/json", br#"["a", null]"#);
+        assert_eq!(
+            list.json::<Vec<Option<String>>>().unwrap(),
+            [Some("a".into()), None]
+        );
+        assert_eq!(list.json::<Vec<String>>().unwrap_err().exit_code(), 8);
+        assert!(response("application/json", b"{")
+            .json::<json::Value>()
+            .is_err());
+    }
+
+    #[test]
+    fn knows_which_responses_have_no_body() {
+        assert!(has_body("GET", 200));
+        assert!(has_body("POST", 404));
+        assert!(!has_body("HEAD", 200));
+        assert!(!has_body("head", 404));
+        assert!(!has_body("GET", 101));
+        assert!(!has_body("GET", 204));
+        assert!(!has_body("GET", 304));
+    }
+
+    /// Hands out at most `step` bytes per read, like a slow connection.
+    struct Trickle<'a> {
+        data: &'a [u8],
+        step: usize,
+    }
+
+    impl std::io::Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            let n = self.step.min(buf.len()).min(self.data.len());
+            buf[..n].copy_from_slice(&self.data[..n]);
+            self.data = &self.data[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn read_head_leaves_the_body_buffered() {
+        use std::io::{BufReader, Read};
+
+        let wire = b"HTTP/1.1 200 OK\r\nA: 1\r\n\r\nbody\r\n\r\nmore";
+        for step in [1, 2, 3, 5, 64] {
+            let mut reader = BufReader::with_capacity(step, Trickle { data: wire, step });
+            let head = read_head(&mut reader).unwrap();
+            assert_eq!(head, b"HTTP/1.1 200 OK\r\nA: 1\r\n\r\n", "step {}", step);
+            let mut rest = Vec::new();
+            reader.read_to_end(&mut rest).unwrap();
+            assert_eq!(rest, b"body\r\n\r\nmore", "step {}", step);
+        }
+    }
+
+    #[test]
+    fn read_head_rejects_truncated_and_oversized_heads() {
+        let mut truncated: &[u8] = b"HTTP/1.1 200 OK\r\nA: 1\r\n";
+        assert!(read_head(&mut truncated).is_err());
+        let huge = vec![b'a'; MAX_HEAD_LEN + 10];
+        assert!(read_head(&mut huge.as_slice()).is_err());
+    }
+
+    #[test]
+    fn head_text_round_trips() {
+        let head = "HTTP/1.1 200 OK\r\nSet-Cookie: a=1\r\nB: 2\r\nset-cookie: b=2\r\n\r\n";
+        let (status, headers) = parse_head(head.as_bytes()).unwrap();
+        let response = Response {
+            status,
+            headers,
+            body: Vec::new(),
+            trailers: HeaderMap::new(),
+        };
+        assert_eq!(response.head_text(), head);
+    }
+
+    #[test]
+    fn tells_binary_bodies_from_text() {
+        let response = |content_type: &str, body: &[u8]| {
+            let head = format!("HTTP/1.1 200 OK\r\nContent-Type: {}\r\n\r\n", content_type);
+            let (status, headers) = parse_head(head.as_bytes()).unwrap();
+            Response {
+                status,
+                headers,
+                body: body.to_vec(),
+                trailers: HeaderMap::new(),
+            }
+        };
+        assert!(response("text/plain", b"a\0b").is_binary());
+        assert!(response("image/png", b"PNG").is_binary());
+        assert!(response("Application/Octet-Stream; x=y", b"").is_binary());
+        assert!(!response("image/svg+xml", b"<svg/>").is_binary());
+        assert!(!response("application/json", b"{}").is_binary());
+    }
+}
@@ -0,0 +1,182 @@
+//! robots.txt (RFC 9309), which the crawl modes honour.
+//!
+//! A file is a list of groups, each one or more `User-agent` lines and the
+//! `Allow` and `Disallow` rules after them. A crawler obeys the groups
+//! naming its product token, or the `*` groups if none does. The rule with
+//! the longest pattern matching a path decides, `Allow` winning a tie, and
+//! a path no rule matches is allowed. Patterns may use `*` for any run of
+//! characters and end with `$` to match only at the end. The
+//! `Crawl-delay` extension, seconds between requests, is read as well.
+
+use std::time::Duration;
+
+/// The rules of a site for one crawler.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Robots {
+    /// `(allow, pattern)` pairs.
+    rules: Vec<(bool, String)>,
+    pub crawl_delay: Option<Duration>,
+}
+
+impl Robots {
+    /// The rules in `text` for the crawler whose product token is `agent`,
+    /// such as `http_client`.
+    pub fn parse(text: &str, agent: &str) -> Robots {
+        struct Group {
+            agents: Vec<String>,
+            robots: Robots,
+        }
+        let mut groups: Vec<Group> = Vec::new();
+        // Consecutive User-agent lines share the group they start.
+        let mut open = false;
+        for line in text.lines() {
+            let line = line.split('#').next().unwrap_or_default();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "user-agent" => {
+                    if !open {
+                        groups.push(Group {
+                            agents: Vec::new(),
+                            robots: Robots::default(),
+                        });
+                        open = true;
+                    }
+                    let group = groups.last_mut().expect("a group was just opened");
+                    group.agents.push(value.to_ascii_lowercase());
+                }
+                key @ ("allow" | "disallow") => {
+                    open = false;
+                    // An empty Disallow allows everything; it is no rule.
+                    if let Some(group) = groups.last_mut().filter(|_| !value.is_empty()) {
+                        group.robots.rules.push((key == "allow", value.to_string()));
+                    }
+                }
+                "crawl-delay" => {
+                    open = false;
+                    let delay = value
+                        .parse()
+                        .ok()
+                        .and_then(|secs| Duration::try_from_secs_f64(secs).ok());
+                    if let Some(group) = groups.last_mut() {
+                        group.robots.crawl_delay = delay.or(group.robots.crawl_delay);
+                    }
+                }
+                _ => {}
+            }
+        }
+        let agent = agent.to_ascii_lowercase();
+        let named = groups.iter().any(|group| group.agents.contains(&agent));
+        let chosen = if named { agent } else { "*".to_string() };
+        let mut robots = Robots::default();
+        for group in groups
+            .into_iter()
+            .filter(|group| group.agents.contains(&chosen))
+        {
+            robots.rules.extend(group.robots.rules);
+            robots.crawl_delay = robots.crawl_delay.or(group.robots.crawl_delay);
+        }
+        robots
+    }
+
+    /// What a site that cannot be asked is taken to say: nothing may be
+    /// crawled.
+    pub fn disallow_all() -> Robots {
+        Robots {
+            rules: vec![(false, "/".to_string())],
+            crawl_delay: None,
+        }
+    }
+
+    /// Whether `target`, a path with its query, may be crawled.
+    pub fn allows(&self, target: &str) -> bool {
+        let mut best: Option<(usize, bool)> = None;
+        for (allow, pattern) in &self.rules {
+            if !matches(pattern, target) {
+                continue;
+            }
+            let better = match best {
+                None => true,
+                Some((len, allowed)) => pattern.len() > len || (pattern.len() == len && !allowed),
+            };
+            if better {
+                best = Some((pattern.len(), *allow));
+            }
+        }
+        best.is_none_or(|(_, allow)| allow)
+    }
+}
+
+/// Whether `pattern` matches the start of `path`, or all of it for a
+/// pattern ending in `$`.
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut pieces = pattern.split('*');
+    let first = pieces.next().unwrap_or_default();
+    let Some(mut rest) = path.strip_prefix(first) else {
+        return false;
+    };
+    let pieces: Vec<&str> = pieces.collect();
+    for (i, piece) in pieces.iter().enumerate() {
+        // The last piece of an anchored pattern has to be the path's end.
+        if anchored && i == pieces.len() - 1 {
+            return rest.ends_with(piece);
+        }
+        match rest.find(piece) {
+            Some(at) => rest = &rest[at + piece.len()..],
+            None => return false,
+        }
+    }
+    !anchored || rest.is_empty()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picks_the_crawlers_group() {
+        let text = "User-agent: *\n\
+                    Disallow: /\n\
+                    \n\
+                    User-agent: other\n\
+                    User-agent: HTTP_CLIENT # us\n\
+                    Disallow: /private\n\
+                    Allow: /private/open\n\
+                    Crawl-delay: 1.5\n\
+                    Disallow:\n";
+        let robots = Robots::parse(text, "http_client");
+        assert!(robots.allows("/"));
+        assert!(!robots.allows("/private/x"));
+        assert!(robots.allows("/private/open/x"));
+        assert_eq!(robots.crawl_delay, Some(Duration::from_millis(1500)));
+        let robots = Robots::parse(text, "someone");
+        assert!(!robots.allows("/anything"));
+        assert!(Robots::parse("", "http_client").allows("/"));
+        assert!(!Robots::disallow_all().allows("/"));
+    }
+
+    #[test]
+    fn matches_wildcards_longest_first() {
+        let robots = Robots::parse(
+            "user-agent: *\n\
+             disallow: /*.pdf$\n\
+             disallow: /a\n\
+             allow: /a\n\
+             disallow: /search?*q=\n",
+            "x",
+        );
+        assert!(!robots.allows("/docs/x.pdf"));
+        assert!(robots.allows("/docs/x.pdf?v=1"));
+        assert!(robots.allows("/abc"));
+        assert!(!robots.allows("/search?lang=en&q=rust"));
+        assert!(robots.allows("/search?lang=en"));
+        assert!(matches("/a*b*c$", "/a-b-b-c"));
+        assert!(!matches("/a*c$", "/a-c-d"));
+    }
+}
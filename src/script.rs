@@ -0,0 +1,587 @@
+//! `--script`: hooks that change outgoing requests and check responses,
+//! written in a small line-based language of its own so the crate keeps
+//! no dependencies. It stands in for an embedded engine such as Rhai or
+//! Lua, which this crate does not have; the `--script` help says so and
+//! lists the grammar below.
+//!
+//! A script has an `on request` section, run before each request is sent,
+//! and an `on response` section, run on the response a transfer ends with.
+//! Every value is a string. For example:
+//!
+//! ```text
+//! on request
+//!     let ts = now()
+//!     header X-Timestamp = ts
+//!     header X-Signature = hmac_sha256(env("API_SECRET"), method + "\n" + path + "\n" + ts)
+//!     remove header X-Debug
+//! on response
+//!     assert status == "200"
+//!     assert header("Content-Type") contains "json"
+//!     assert json("$.items[0].id") != ""
+//!     print "request id " + header("X-Request-Id")
+//! ```
+//!
+//! Statements are `let NAME = EXPR`, `header NAME = EXPR`,
+//! `remove header NAME`, `method = EXPR` and `body = EXPR` (requests only),
+//! `assert EXPR [OP EXPR]` with `==`, `!=`, `contains`, `<` or `>` (the last
+//! two comparing numbers; a bare expression must be non-empty), and
+//! `print EXPR`, which writes to stderr. Expressions join string literals,
+//! names and function calls with `+`. The names are `method`, `url`,
+//! `host`, `path` and `body`, plus `status` and `reason` for responses,
+//! where `body` is the response's. The functions are `header`, `env`,
+//! `now`, `sha256`, `sha1`, `md5`, `hmac_sha256`, `hmac_sha256_base64`,
+//! `base64`, `url_encode`, `lower`, `upper`, `trim`, `len` and, for
+//! responses, `json` with a JSONPath. Failed assertions are reported
+//! together, as with `--expect-*`.
+
+use std::collections::BTreeMap;
+use std::env;
+
+use crate::base64;
+use crate::date;
+use crate::error::{Error, Result};
+use crate::json;
+use crate::middleware::{Middleware, Request};
+use crate::request::Method;
+use crate::response::Response;
+use crate::sha256::{self, hex};
+use crate::url;
+use crate::{md5, sha1};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Literal(String),
+    Name(String),
+    Call(String, Vec<Expr>),
+    Concat(Vec<Expr>),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Contains,
+    Lt,
+    Gt,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Action {
+    Let(String, Expr),
+    Header(String, Expr),
+    RemoveHeader(String),
+    Method(Expr),
+    Body(Expr),
+    /// The condition, and the statement as written for the report.
+    Assert(Expr, Option<(Op, Expr)>, String),
+    Print(Expr),
+}
+
+/// A statement and the line it is on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Statement {
+    line: usize,
+    action: Action,
+}
+
+/// A parsed script.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Script {
+    request: Vec<Statement>,
+    response: Vec<Statement>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Str(String),
+    Word(String),
+    Punct(&'static str),
+}
+
+fn tokenize(line: &str) -> std::result::Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = line.char_indices().peekable();
+    while let Some(&(i, c)) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '#' => break,
+            '"' => {
+                chars.next();
+                let mut s = String::new();
+                loop {
+                    match chars.next() {
+                        Some((_, '"')) => break,
+                        Some((_, '\\')) => match chars.next() {
+                            Some((_, 'n')) => s.push('\n'),
+                            Some((_, 't')) => s.push('\t'),
+                            Some((_, 'r')) => s.push('\r'),
+                            Some((_, c)) => s.push(c),
+                            None => return Err("unterminated string".to_string()),
+                        },
+                        Some((_, c)) => s.push(c),
+                        None => return Err("unterminated string".to_string()),
+                    }
+                }
+                tokens.push(Token::Str(s));
+            }
+            _ => {
+                let rest = &line[i..];
+                let punct = ["==", "!=", "(", ")", ",", "+", "=", "<", ">"]
+                    .into_iter()
+                    .find(|p| rest.starts_with(p));
+                if let Some(p) = punct {
+                    tokens.push(Token::Punct(p));
+                    for _ in 0..p.len() {
+                        chars.next();
+                    }
+                    continue;
+                }
+                let len = rest
+                    .find(|c: char| !(c.is_alphanumeric() || matches!(c, '_' | '-' | '.')))
+                    .unwrap_or(rest.len());
+                if len == 0 {
+                    return Err(format!("unexpected '{}'", c));
+                }
+                tokens.push(Token::Word(rest[..len].to_string()));
+                for _ in rest[..len].chars() {
+                    chars.next();
+                }
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+/// Reads expressions from a line's tokens.
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn eat(&mut self, punct: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Punct(p)) if *p == punct);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, punct: &str) -> std::result::Result<(), String> {
+        if self.eat(punct) {
+            Ok(())
+        } else {
+            Err(format!("expected '{}'", punct))
+        }
+    }
+
+    fn word(&mut self) -> std::result::Result<String, String> {
+        match self.next() {
+            Some(Token::Word(word)) => Ok(word),
+            _ => Err("expected a name".to_string()),
+        }
+    }
+
+    fn expr(&mut self) -> std::result::Result<Expr, String> {
+        let mut parts = vec![self.term()?];
+        while self.eat("+") {
+            parts.push(self.term()?);
+        }
+        Ok(if parts.len() == 1 {
+            parts.remove(0)
+        } else {
+            Expr::Concat(parts)
+        })
+    }
+
+    fn term(&mut self) -> std::result::Result<Expr, String> {
+        match self.next() {
+            Some(Token::Str(s)) => Ok(Expr::Literal(s)),
+            Some(Token::Word(word)) if word.starts_with(|c: char| c.is_ascii_digit()) => {
+                Ok(Expr::Literal(word))
+            }
+            Some(Token::Word(word)) if self.eat("(") => {
+                let mut args = Vec::new();
+                if !self.eat(")") {
+                    loop {
+                        args.push(self.expr()?);
+                        if self.eat(")") {
+                            break;
+                        }
+                        self.expect(",")?;
+                    }
+                }
+                Ok(Expr::Call(word, args))
+            }
+            Some(Token::Word(word)) => Ok(Expr::Name(word)),
+            _ => Err("expected a value".to_string()),
+        }
+    }
+
+    fn op(&mut self) -> Option<Op> {
+        let op = match self.peek()? {
+            Token::Punct("==") => Op::Eq,
+            Token::Punct("!=") => Op::Ne,
+            Token::Punct("<") => Op::Lt,
+            Token::Punct(">") => Op::Gt,
+            Token::Word(w) if w == "contains" => Op::Contains,
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(op)
+    }
+
+    fn finish(&self) -> std::result::Result<(), String> {
+        match self.peek() {
+            None => Ok(()),
+            Some(_) => Err("unexpected text at the end of the line".to_string()),
+        }
+    }
+}
+
+fn parse_statement(line: &str, in_request: bool) -> std::result::Result<Action, String> {
+    let mut p = Parser {
+        tokens: tokenize(line)?,
+        pos: 0,
+    };
+    let keyword = p.word()?;
+    let action = match keyword.as_str() {
+        "let" => {
+            let name = p.word()?;
+            p.expect("=")?;
+            Action::Let(name, p.expr()?)
+        }
+        "assert" => {
+            let left = p.expr()?;
+            let right = match p.op() {
+                Some(op) => Some((op, p.expr()?)),
+                None => None,
+            };
+            Action::Assert(left, right, line.trim().to_string())
+        }
+        "print" => Action::Print(p.expr()?),
+        "header" | "remove" | "method" | "body" if !in_request => {
+            return Err(format!("'{}' only works in 'on request'", keyword));
+        }
+        "header" => {
+            let name = p.word()?;
+            p.expect("=")?;
+            Action::Header(name, p.expr()?)
+        }
+        "remove" => {
+            if p.word()? != "header" {
+                return Err("expected 'remove header NAME'".to_string());
+            }
+            Action::RemoveHeader(p.word()?)
+        }
+        "method" => {
+            p.expect("=")?;
+            Action::Method(p.expr()?)
+        }
+        "body" => {
+            p.expect("=")?;
+            Action::Body(p.expr()?)
+        }
+        other => return Err(format!("unknown statement '{}'", other)),
+    };
+    p.finish()?;
+    Ok(action)
+}
+
+/// What the statements of one run can see.
+struct Scope<'a> {
+    request: &'a Request,
+    response: Option<&'a Response>,
+    vars: &'a BTreeMap<String, String>,
+}
+
+impl Scope<'_> {
+    fn name(&self, name: &str) -> std::result::Result<String, String> {
+        if let Some(value) = self.vars.get(name) {
+            return Ok(value.clone());
+        }
+        let request = self.request;
+        let value = match (name, self.response) {
+            ("method", _) => request.method.to_string(),
+            ("url", _) => request.url.to_string(),
+            ("host", _) => request.url.host().to_string(),
+            ("path", _) => request.url.request_target(),
+            ("body", None) => request.body.clone().unwrap_or_default(),
+            ("body", Some(r)) => String::from_utf8_lossy(&r.body).into_owned(),
+            ("status", Some(r)) => r.status.code.to_string(),
+            ("reason", Some(r)) => r.status.reason.clone(),
+            _ => return Err(format!("unknown name '{}'", name)),
+        };
+        Ok(value)
+    }
+
+    fn eval(&self, expr: &Expr) -> std::result::Result<String, String> {
+        match expr {
+            Expr::Literal(s) => Ok(s.clone()),
+            Expr::Name(name) => self.name(name),
+            Expr::Concat(parts) => parts.iter().map(|part| self.eval(part)).collect(),
+            Expr::Call(function, args) => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval(arg))
+                    .collect::<std::result::Result<Vec<_>, _>>()?;
+                self.call(function, &args)
+            }
+        }
+    }
+
+    fn call(&self, function: &str, args: &[String]) -> std::result::Result<String, String> {
+        let value = match (function, args) {
+            ("header", [name]) => match self.response {
+                Some(r) => r.headers.get(name),
+                None => self.request.headers.get(name),
+            }
+            .unwrap_or_default()
+            .to_string(),
+            ("env", [name]) => {
+                env::var(name).map_err(|_| format!("environment variable '{}' is not set", name))?
+            }
+            ("now", []) => date::now().to_string(),
+            ("sha256", [s]) => hex(&sha256::digest(s.as_bytes())),
+            ("sha1", [s]) => hex(&sha1::digest(s.as_bytes())),
+            ("md5", [s]) => hex(&md5::digest(s.as_bytes())),
+            ("hmac_sha256", [key, data]) => hex(&sha256::hmac(key.as_bytes(), data.as_bytes())),
+            ("hmac_sha256_base64", [key, data]) => {
+                base64::encode(&sha256::hmac(key.as_bytes(), data.as_bytes()))
+            }
+            ("base64", [s]) => base64::encode(s.as_bytes()),
+            ("url_encode", [s]) => url::encode_component(s),
+            ("lower", [s]) => s.to_lowercase(),
+            ("upper", [s]) => s.to_uppercase(),
+            ("trim", [s]) => s.trim().to_string(),
+            ("len", [s]) => s.chars().count().to_string(),
+            ("json", [path]) => {
+                let Some(response) = self.response else {
+                    return Err("json() only works in 'on response'".to_string());
+                };
+                let body = String::from_utf8_lossy(&response.body);
+                json::parse(&body)
+                    .ok()
+                    .and_then(|value| value.path(path).map(json::Value::to_text))
+                    .unwrap_or_default()
+            }
+            (function, args) => {
+                return Err(format!(
+                    "no function {}() taking {} argument(s)",
+                    function,
+                    args.len()
+                ))
+            }
+        };
+        Ok(value)
+    }
+}
+
+fn holds(op: Op, left: &str, right: &str) -> bool {
+    let number = |s: &str| s.trim().parse::<f64>().ok();
+    match op {
+        Op::Eq => left == right,
+        Op::Ne => left != right,
+        Op::Contains => left.contains(right),
+        Op::Lt => matches!((number(left), number(right)), (Some(l), Some(r)) if l < r),
+        Op::Gt => matches!((number(left), number(right)), (Some(l), Some(r)) if l > r),
+    }
+}
+
+impl Script {
+    pub fn parse(text: &str) -> Result<Script> {
+        let mut script = Script::default();
+        let mut section = None;
+        for (i, line) in text.lines().enumerate() {
+            let line_no = i + 1;
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+            match trimmed {
+                "on request" => section = Some(true),
+                "on response" => section = Some(false),
+                _ => {
+                    let in_request = section.ok_or_else(|| {
+                        Error::Script(format!(
+                            "line {}: statements must follow 'on request' or 'on response'",
+                            line_no
+                        ))
+                    })?;
+                    let action = parse_statement(trimmed, in_request)
+                        .map_err(|e| Error::Script(format!("line {}: {}", line_no, e)))?;
+                    let statement = Statement {
+                        line: line_no,
+                        action,
+                    };
+                    match in_request {
+                        true => script.request.push(statement),
+                        false => script.response.push(statement),
+                    }
+                }
+            }
+        }
+        Ok(script)
+    }
+
+    /// Runs `statements` against `request`, which `on request`
+    /// statements change in place.
+    fn run(
+        statements: &[Statement],
+        request: &mut Request,
+        response: Option<&Response>,
+    ) -> Result<()> {
+        let mut vars = BTreeMap::new();
+        let mut failures = Vec::new();
+        for statement in statements {
+            let fail = |e: String| Error::Script(format!("line {}: {}", statement.line, e));
+            // Later statements see the changes of earlier ones.
+            let scope = Scope {
+                request,
+                response,
+                vars: &vars,
+            };
+            match &statement.action {
+                Action::Let(name, expr) => {
+                    let value = scope.eval(expr).map_err(fail)?;
+                    vars.insert(name.clone(), value);
+                }
+                Action::Header(name, expr) => {
+                    let value = scope.eval(expr).map_err(fail)?;
+                    request.headers.insert(name.as_str(), value);
+                }
+                Action::RemoveHeader(name) => {
+                    request.headers.remove(name);
+                }
+                Action::Method(expr) => {
+                    let method = scope.eval(expr).map_err(fail)?;
+                    request.method = Method::parse(&method).map_err(|e| fail(e.to_string()))?;
+                }
+                Action::Body(expr) => {
+                    let body = scope.eval(expr).map_err(fail)?;
+                    if request.headers.contains("Content-Length") {
+                        request
+                            .headers
+                            .insert("Content-Length", body.len().to_string());
+                    }
+                    request.body = Some(body);
+                }
+                Action::Assert(left, right, text) => {
+                    let left = scope.eval(left).map_err(fail)?;
+                    let held = match right {
+                        Some((op, right)) => holds(*op, &left, &scope.eval(right).map_err(fail)?),
+                        None => !left.is_empty(),
+                    };
+                    if !held {
+                        failures.push(format!(
+                            "line {}: {} (got {:?})",
+                            statement.line, text, left
+                        ));
+                    }
+                }
+                Action::Print(expr) => eprintln!("{}", scope.eval(expr).map_err(fail)?),
+            }
+        }
+        if failures.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::ExpectationFailed(failures))
+        }
+    }
+}
+
+impl Middleware for Script {
+    /// The request is only changed if every statement succeeds.
+    fn on_request(&self, request: &mut Request) -> Result<()> {
+        let mut edited = request.clone();
+        Script::run(&self.request, &mut edited, None)?;
+        *request = edited;
+        Ok(())
+    }
+
+    fn on_response(&self, request: &Request, response: &Response) -> Result<()> {
+        // Response statements cannot change the request; this is a copy
+        // only because run takes one to change.
+        Script::run(&self.response, &mut request.clone(), Some(response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headers::HeaderMap;
+    use crate::response::StatusLine;
+    use crate::url::Url;
+
+    fn request() -> Request {
+        let mut headers = HeaderMap::new();
+        headers.append("X-Debug", "1");
+        Request {
+            method: Method::Get,
+            url: Url::parse("http://api.test/items?page=2").unwrap(),
+            headers,
+            body: None,
+        }
+    }
+
+    #[test]
+    fn changes_requests() {
+        let script = Script::parse(
+            "on request\n\
+             \x20 let ts = \"1700000000\"\n\
+             \x20 header X-Sig = hmac_sha256(\"key\", method + \"\\n\" + path + \"\\n\" + ts)\n\
+             \x20 header X-Host = upper(host) # a comment\n\
+             \x20 remove header X-Debug\n\
+             \x20 method = \"POST\"\n",
+        )
+        .unwrap();
+        let mut request = request();
+        script.on_request(&mut request).unwrap();
+        let expected = hex(&sha256::hmac(b"key", b"GET\n/items?page=2\n1700000000"));
+        assert_eq!(request.headers.get("X-Sig"), Some(expected.as_str()));
+        assert_eq!(request.headers.get("X-Host"), Some("API.TEST"));
+        assert!(!request.headers.contains("X-Debug"));
+        assert_eq!(request.method, Method::Post);
+    }
+
+    #[test]
+    fn checks_responses() {
+        let script = Script::parse(
+            "on response\n\
+             assert status == \"200\"\n\
+             assert json(\"$.id\") > 3\n\
+             assert header(\"Content-Type\") contains \"xml\"\n",
+        )
+        .unwrap();
+        let mut headers = HeaderMap::new();
+        headers.append("Content-Type", "application/json");
+        let response = Response {
+            status: StatusLine::parse("HTTP/1.1 200 OK").unwrap(),
+            headers,
+            body: b"{\"id\": 7}".to_vec(),
+            trailers: HeaderMap::new(),
+        };
+        match script.on_response(&request(), &response) {
+            Err(Error::ExpectationFailed(failures)) => {
+                assert_eq!(failures.len(), 1);
+                assert!(failures[0].starts_with("line 4: "));
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+        assert!(Script::parse("header X = \"1\"").is_err());
+        assert!(Script::parse("on response\nheader X = \"1\"").is_err());
+        assert!(Script::parse("on request\nlet x = \"open").is_err());
+    }
+}
@@ -0,0 +1,127 @@
+//! `Server-Timing` (W3C Server Timing), which `--timings` shows beside the
+//! client's own phases.
+//!
+//! A server lists metrics, each a name with optional `dur` (milliseconds)
+//! and `desc` parameters:
+//!
+//! ```text
+//! Server-Timing: db;dur=53.2;desc="Database", cache;desc=hit, total;dur=80
+//! ```
+//!
+//! The header may repeat, and may come as a trailer when the server only
+//! knows its timings once the body is sent.
+
+/// One metric a server reported.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Metric {
+    pub name: String,
+    /// Milliseconds.
+    pub duration: Option<f64>,
+    pub description: Option<String>,
+}
+
+/// The metrics in one `Server-Timing` value. Entries without a name are
+/// left out, and of a repeated parameter the first counts.
+pub fn parse(value: &str) -> Vec<Metric> {
+    let mut metrics = Vec::new();
+    for entry in split(value, ',') {
+        let mut params = split(entry, ';').into_iter();
+        let name = params.next().unwrap_or_default().trim();
+        if name.is_empty() || name.contains(['=', '"']) {
+            continue;
+        }
+        let mut metric = Metric {
+            name: name.to_string(),
+            duration: None,
+            description: None,
+        };
+        for param in params {
+            let (key, arg) = param.split_once('=').unwrap_or((param, ""));
+            let arg = unquote(arg.trim());
+            match key.trim().to_ascii_lowercase().as_str() {
+                "dur" if metric.duration.is_none() => {
+                    metric.duration = arg.parse().ok().filter(|ms: &f64| ms.is_finite())
+                }
+                "desc" if metric.description.is_none() => metric.description = Some(arg),
+                _ => {}
+            }
+        }
+        metrics.push(metric);
+    }
+    metrics
+}
+
+/// `s` split at each `sep` that is not inside a quoted string.
+fn split(s: &str, sep: char) -> Vec<&str> {
+    let mut pieces = Vec::new();
+    let (mut start, mut quoted, mut escaped) = (0, false, false);
+    for (i, c) in s.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            c if c == sep && !quoted => {
+                pieces.push(&s[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    pieces.push(&s[start..]);
+    pieces
+}
+
+/// A quoted string's contents; anything else as it is.
+fn unquote(s: &str) -> String {
+    let Some(inner) = s.strip_prefix('"').and_then(|s| s.strip_suffix('"')) else {
+        return s.to_string();
+    };
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => out.extend(chars.next()),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_metrics_and_params() {
+        let metrics = parse(
+            r#"db;dur=53.2;desc="Query, \"slow\"", cache;desc=hit ,total;DUR=80;dur=1, ;dur=2, miss"#,
+        );
+        assert_eq!(
+            metrics,
+            [
+                Metric {
+                    name: "db".into(),
+                    duration: Some(53.2),
+                    description: Some(r#"Query, "slow""#.into()),
+                },
+                Metric {
+                    name: "cache".into(),
+                    duration: None,
+                    description: Some("hit".into()),
+                },
+                Metric {
+                    name: "total".into(),
+                    duration: Some(80.0),
+                    description: None,
+                },
+                Metric {
+                    name: "miss".into(),
+                    duration: None,
+                    description: None,
+                },
+            ]
+        );
+        assert_eq!(parse("x;dur=fast")[0].duration, None);
+        assert!(parse("").is_empty());
+    }
+}
@@ -0,0 +1,172 @@
+//! Sessions for `--session`: headers, credentials and cookies kept in a
+//! file between invocations, like HTTPie's sessions.
+//!
+//! The file is a JSON object with a `headers` object and a `cookies` array
+//! of `{"name", "value", "domain", "host_only", "path"}` objects, so it can
+//! be read and edited by hand.
+
+use std::env;
+use std::path::PathBuf;
+
+use crate::cookie::{Cookie, Jar};
+use crate::headers::HeaderMap;
+use crate::json::{self, Value};
+
+/// What a session remembers.
+#[derive(Debug, Clone, Default)]
+pub struct Session {
+    /// Sent with every request unless it sets its own.
+    pub headers: HeaderMap,
+    pub cookies: Jar,
+}
+
+/// Where the session `name` is kept: `name` itself if it is a path, else
+/// `http_client/sessions/<name>.json` in `XDG_CONFIG_HOME` or `~/.config`.
+pub fn path(name: &str) -> Option<PathBuf> {
+    if name.contains(['/', '\\']) || name.ends_with(".json") {
+        return Some(PathBuf::from(name));
+    }
+    let config = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| {
+            env::var_os("HOME")
+                .or_else(|| env::var_os("USERPROFILE"))
+                .map(|home| PathBuf::from(home).join(".config"))
+        })?;
+    Some(
+        config
+            .join("http_client/sessions")
+            .join(format!("{}.json", name)),
+    )
+}
+
+/// Whether a header is worth keeping. Ones that describe a single request,
+/// such as its body or a condition on it, are not.
+pub fn remembers(name: &str) -> bool {
+    let lower = name.to_ascii_lowercase();
+    !matches!(
+        lower.as_str(),
+        "host" | "cookie" | "connection" | "transfer-encoding" | "expect"
+    ) && !lower.starts_with("content-")
+        && !lower.starts_with("if-")
+}
+
+impl Session {
+    pub fn new() -> Session {
+        Session::default()
+    }
+
+    /// Reads a session file; `None` if it is not one.
+    pub fn parse(text: &str) -> Option<Session> {
+        let value = json::parse(text).ok()?;
+        let mut session = Session::new();
+        if let Some(headers) = value.get("headers") {
+            let Value::Object(members) = headers else {
+                return None;
+            };
+            for (name, value) in members {
+                session.headers.insert(name.as_str(), value.as_str()?);
+            }
+        }
+        if let Some(cookies) = value.get("cookies") {
+            let Value::Array(items) = cookies else {
+                return None;
+            };
+            for item in items {
+                let field = |key| item.get(key).and_then(Value::as_str).map(str::to_string);
+                session.cookies.add(Cookie {
+                    name: field("name")?,
+                    value: field("value")?,
+                    domain: field("domain")?,
+                    host_only: item.get("host_only") != Some(&Value::Bool(false)),
+                    path: field("path").unwrap_or_else(|| "/".to_string()),
+                });
+            }
+        }
+        Some(session)
+    }
+
+    /// The session file's contents.
+    pub fn to_text(&self) -> String {
+        let string = |s: &str| Value::String(s.to_string());
+        let headers = self
+            .headers
+            .iter()
+            .map(|(name, value)| (name.to_string(), string(value)))
+            .collect();
+        let cookies = self
+            .cookies
+            .iter()
+            .map(|c| {
+                Value::Object(vec![
+                    ("name".to_string(), string(&c.name)),
+                    ("value".to_string(), string(&c.value)),
+                    ("domain".to_string(), string(&c.domain)),
+                    ("host_only".to_string(), Value::Bool(c.host_only)),
+                    ("path".to_string(), string(&c.path)),
+                ])
+            })
+            .collect();
+        let session = Value::Object(vec![
+            ("headers".to_string(), Value::Object(headers)),
+            ("cookies".to_string(), Value::Array(cookies)),
+        ]);
+        format!("{}\n", session)
+    }
+
+    /// Sets the session's headers on a request's built-in ones. Cookies
+    /// are added separately, since unlike the headers they know which
+    /// hosts they belong to.
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        for (name, value) in self.headers.iter() {
+            headers.insert(name, value);
+        }
+    }
+
+    /// Remembers the headers a request was sent with, for those worth
+    /// keeping.
+    pub fn remember(&mut self, headers: &HeaderMap) {
+        for (name, value) in headers.iter().filter(|(name, _)| remembers(name)) {
+            self.headers.insert(name, value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::url::Url;
+
+    #[test]
+    fn round_trips_through_the_file() {
+        let url = Url::parse("http://api.test/v1/items").unwrap();
+        let mut session = Session::new();
+        let mut sent = HeaderMap::new();
+        sent.append("X-Api-Key", "k\"1");
+        sent.append("Authorization", "Bearer t");
+        sent.append("Content-Type", "application/json");
+        sent.append("Host", "api.test");
+        session.remember(&sent);
+        let set: HeaderMap = [("Set-Cookie", "sid=abc; Path=/v1")].into_iter().collect();
+        session.cookies.store(&url, &set);
+
+        let loaded = Session::parse(&session.to_text()).unwrap();
+        let names: Vec<&str> = loaded.headers.iter().map(|(n, _)| n).collect();
+        assert_eq!(names, ["X-Api-Key", "Authorization"]);
+        let mut headers = HeaderMap::new();
+        headers.append("X-Api-Key", "old");
+        loaded.apply(&mut headers);
+        assert_eq!(headers.get("X-Api-Key"), Some("k\"1"));
+        assert_eq!(loaded.cookies.header(&url).as_deref(), Some("sid=abc"));
+        let other = Url::parse("http://api.test/v2").unwrap();
+        assert_eq!(loaded.cookies.header(&other), None);
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        assert!(Session::parse("").is_none());
+        assert!(Session::parse("{\"headers\": [1]}").is_none());
+        assert!(Session::parse("{}").is_some_and(|s| s.cookies.is_empty()));
+    }
+}
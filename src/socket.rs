@@ -0,0 +1,288 @@
+//! TCP socket options for `--tcp-nodelay`, `--keepalive-time`,
+//! `--sndbuf`, `--rcvbuf` and `--local-port`.
+//!
+//! Only `TCP_NODELAY` is in std. The rest are set with `setsockopt`, and a
+//! local port is bound with `bind` before `connect`, on Linux and macOS;
+//! elsewhere asking for them is an error rather than being silently
+//! ignored.
+
+use std::io;
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+/// How to set up each connection before the request is sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SocketOptions {
+    /// Send small writes at once instead of waiting to coalesce them.
+    /// On by default, as in curl.
+    pub nodelay: bool,
+    /// Send TCP keepalive probes after the connection has been idle this
+    /// long.
+    pub keepalive: Option<Duration>,
+    /// `SO_SNDBUF`, in bytes.
+    pub send_buffer: Option<usize>,
+    /// `SO_RCVBUF`, in bytes.
+    pub recv_buffer: Option<usize>,
+    /// The first and last local port to connect from; the first free one
+    /// is used.
+    pub local_ports: Option<(u16, u16)>,
+}
+
+impl Default for SocketOptions {
+    fn default() -> SocketOptions {
+        SocketOptions {
+            nodelay: true,
+            keepalive: None,
+            send_buffer: None,
+            recv_buffer: None,
+            local_ports: None,
+        }
+    }
+}
+
+impl SocketOptions {
+    /// Connects to `addr`, from one of [`SocketOptions::local_ports`] if
+    /// given, and applies the other options.
+    pub fn connect(&self, addr: SocketAddr) -> io::Result<TcpStream> {
+        let stream = match self.local_ports {
+            Some((first, last)) => sys::connect_from(addr, first..=last)?,
+            None => TcpStream::connect(addr)?,
+        };
+        self.apply(&stream)?;
+        Ok(stream)
+    }
+
+    pub fn apply(&self, stream: &TcpStream) -> io::Result<()> {
+        stream.set_nodelay(self.nodelay)?;
+        if let Some(idle) = self.keepalive {
+            let secs = idle.as_secs().clamp(1, i32::MAX as u64) as i32;
+            sys::set(stream, sys::SOL_SOCKET, sys::SO_KEEPALIVE, 1)?;
+            sys::set(stream, sys::IPPROTO_TCP, sys::TCP_KEEPIDLE, secs)?;
+        }
+        if let Some(size) = self.send_buffer {
+            sys::set(stream, sys::SOL_SOCKET, sys::SO_SNDBUF, buffer_size(size))?;
+        }
+        if let Some(size) = self.recv_buffer {
+            sys::set(stream, sys::SOL_SOCKET, sys::SO_RCVBUF, buffer_size(size))?;
+        }
+        Ok(())
+    }
+}
+
+fn buffer_size(size: usize) -> i32 {
+    size.min(i32::MAX as usize) as i32
+}
+
+#[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))]
+mod sys {
+    use std::ffi::{c_int, c_void};
+    use std::io;
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream};
+    use std::ops::RangeInclusive;
+    use std::os::fd::{AsRawFd, FromRawFd, OwnedFd};
+
+    #[cfg(not(target_os = "macos"))]
+    mod consts {
+        pub const SOL_SOCKET: i32 = 1;
+        pub const SO_KEEPALIVE: i32 = 9;
+        pub const SO_SNDBUF: i32 = 7;
+        pub const SO_RCVBUF: i32 = 8;
+        pub const TCP_KEEPIDLE: i32 = 4;
+    }
+
+    #[cfg(target_os = "macos")]
+    mod consts {
+        pub const SOL_SOCKET: i32 = 0xffff;
+        pub const SO_KEEPALIVE: i32 = 0x0008;
+        pub const SO_SNDBUF: i32 = 0x1001;
+        pub const SO_RCVBUF: i32 = 0x1002;
+        /// macOS calls `TCP_KEEPIDLE` `TCP_KEEPALIVE`.
+        pub const TCP_KEEPIDLE: i32 = 0x10;
+    }
+
+    pub use consts::*;
+    pub const IPPROTO_TCP: i32 = 6;
+    const SOCK_STREAM: c_int = 1;
+    const AF_INET: u8 = 2;
+    #[cfg(not(target_os = "macos"))]
+    const AF_INET6: u8 = 10;
+    #[cfg(target_os = "macos")]
+    const AF_INET6: u8 = 30;
+
+    extern "C" {
+        fn setsockopt(
+            socket: c_int,
+            level: c_int,
+            name: c_int,
+            value: *const c_void,
+            len: u32,
+        ) -> c_int;
+        fn socket(domain: c_int, kind: c_int, protocol: c_int) -> c_int;
+        fn bind(socket: c_int, addr: *const c_void, len: u32) -> c_int;
+        fn connect(socket: c_int, addr: *const c_void, len: u32) -> c_int;
+    }
+
+    /// `addr` as a `sockaddr_in` or `sockaddr_in6`. macOS puts a length
+    /// byte before a one-byte family; Linux has a native-endian `u16`
+    /// family.
+    fn sockaddr(addr: SocketAddr) -> Vec<u8> {
+        let family = match addr {
+            SocketAddr::V4(_) => AF_INET,
+            SocketAddr::V6(_) => AF_INET6,
+        };
+        let mut raw = Vec::with_capacity(28);
+        if cfg!(target_os = "macos") {
+            let len = if addr.is_ipv4() { 16 } else { 28 };
+            raw.extend_from_slice(&[len, family]);
+        } else {
+            raw.extend_from_slice(&(family as u16).to_ne_bytes());
+        }
+        raw.extend_from_slice(&addr.port().to_be_bytes());
+        match addr {
+            SocketAddr::V4(v4) => {
+                raw.extend_from_slice(&v4.ip().octets());
+                raw.extend_from_slice(&[0; 8]);
+            }
+            SocketAddr::V6(v6) => {
+                raw.extend_from_slice(&v6.flowinfo().to_be_bytes());
+                raw.extend_from_slice(&v6.ip().octets());
+                raw.extend_from_slice(&v6.scope_id().to_ne_bytes());
+            }
+        }
+        raw
+    }
+
+    fn check(result: c_int) -> io::Result<c_int> {
+        if result < 0 {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(result)
+        }
+    }
+
+    pub fn connect_from(addr: SocketAddr, ports: RangeInclusive<u16>) -> io::Result<TcpStream> {
+        let unspecified: IpAddr = match addr {
+            SocketAddr::V4(_) => Ipv4Addr::UNSPECIFIED.into(),
+            SocketAddr::V6(_) => Ipv6Addr::UNSPECIFIED.into(),
+        };
+        let family = if addr.is_ipv4() { AF_INET } else { AF_INET6 };
+        // SAFETY: `socket` has no memory arguments; the descriptor it
+        // returns is owned from here on, so it is closed on every path.
+        let fd = unsafe { OwnedFd::from_raw_fd(check(socket(family as c_int, SOCK_STREAM, 0))?) };
+        let mut last_error = None;
+        for port in ports {
+            let local = sockaddr(SocketAddr::new(unspecified, port));
+            // SAFETY: `local` is a complete sockaddr of the length given.
+            match check(unsafe { bind(fd.as_raw_fd(), local.as_ptr().cast(), local.len() as u32) })
+            {
+                Ok(_) => {
+                    last_error = None;
+                    break;
+                }
+                Err(e) => last_error = Some(e),
+            }
+        }
+        if let Some(e) = last_error {
+            return Err(e);
+        }
+        let remote = sockaddr(addr);
+        // SAFETY: as for `bind`.
+        check(unsafe { connect(fd.as_raw_fd(), remote.as_ptr().cast(), remote.len() as u32) })?;
+        Ok(TcpStream::from(fd))
+    }
+
+    pub fn set(stream: &TcpStream, level: i32, name: i32, value: i32) -> io::Result<()> {
+        let value: c_int = value;
+        // SAFETY: the descriptor is open for as long as `stream` is
+        // borrowed, and `value` outlives the call.
+        let result = unsafe {
+            setsockopt(
+                stream.as_raw_fd(),
+                level,
+                name,
+                (&value as *const c_int).cast(),
+                std::mem::size_of::<c_int>() as u32,
+            )
+        };
+        if result == 0 {
+            Ok(())
+        } else {
+            Err(io::Error::last_os_error())
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "android", target_os = "macos")))]
+mod sys {
+    use std::io;
+    use std::net::{SocketAddr, TcpStream};
+    use std::ops::RangeInclusive;
+
+    pub const SOL_SOCKET: i32 = 0;
+    pub const SO_KEEPALIVE: i32 = 0;
+    pub const SO_SNDBUF: i32 = 0;
+    pub const SO_RCVBUF: i32 = 0;
+    pub const TCP_KEEPIDLE: i32 = 0;
+    pub const IPPROTO_TCP: i32 = 0;
+
+    pub fn set(_: &TcpStream, _: i32, _: i32, _: i32) -> io::Result<()> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "socket options other than TCP_NODELAY are not supported on this platform",
+        ))
+    }
+
+    pub fn connect_from(_: SocketAddr, _: RangeInclusive<u16>) -> io::Result<TcpStream> {
+        Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "--local-port is not supported on this platform",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn applies_options_to_a_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let stream = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        SocketOptions::default().apply(&stream).unwrap();
+        assert!(stream.nodelay().unwrap());
+        let options = SocketOptions {
+            nodelay: false,
+            keepalive: Some(Duration::from_secs(30)),
+            send_buffer: Some(64 * 1024),
+            recv_buffer: Some(64 * 1024),
+            local_ports: None,
+        };
+        if cfg!(any(
+            target_os = "linux",
+            target_os = "android",
+            target_os = "macos"
+        )) {
+            options.apply(&stream).unwrap();
+            assert!(!stream.nodelay().unwrap());
+        }
+    }
+
+    #[test]
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "macos"))]
+    fn connects_from_a_free_local_port() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        // Hold one port so the range has to move past it.
+        let taken = TcpListener::bind("127.0.0.1:0").unwrap();
+        let start = taken.local_addr().unwrap().port();
+        let options = SocketOptions {
+            local_ports: Some((start, start.saturating_add(20))),
+            ..SocketOptions::default()
+        };
+        let stream = options.connect(listener.local_addr().unwrap()).unwrap();
+        let port = stream.local_addr().unwrap().port();
+        assert!(port > start && port <= start.saturating_add(20));
+        let (accepted, _) = listener.accept().unwrap();
+        assert_eq!(accepted.peer_addr().unwrap().port(), port);
+    }
+}
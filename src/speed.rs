@@ -0,0 +1,260 @@
+//! Low-speed aborts for `--speed-limit` and `--speed-time`, and the
+//! `--limit-rate` cap.
+//!
+//! [`SpeedCheck`] wraps the receiving side of a connection. The socket is
+//! given a short read timeout so that a server that stops sending entirely
+//! is noticed too, not only one that trickles. [`Throttled`] wraps it too,
+//! and holds reads back to what a [`RateLimit`] allows.
+
+use std::io::{self, IoSlice, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// How often the transfer rate is sampled, and the socket read timeout.
+pub const SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Abort when fewer than `bytes_per_sec` arrive for `time` in a row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SpeedLimit {
+    pub bytes_per_sec: u64,
+    pub time: Duration,
+}
+
+/// A reader that fails with `TimedOut` once the transfer has been slower
+/// than its [`SpeedLimit`] for too long.
+pub struct SpeedCheck<R> {
+    inner: R,
+    limit: SpeedLimit,
+    window_start: Instant,
+    window_bytes: u64,
+    slow_since: Option<Instant>,
+}
+
+impl<R> SpeedCheck<R> {
+    pub fn new(inner: R, limit: SpeedLimit) -> SpeedCheck<R> {
+        SpeedCheck {
+            inner,
+            limit,
+            window_start: Instant::now(),
+            window_bytes: 0,
+            slow_since: None,
+        }
+    }
+
+    /// Accounts for `n` bytes received at `now` and fails if the transfer
+    /// has been too slow for the whole of the allowed time.
+    fn check(&mut self, n: usize, now: Instant) -> io::Result<()> {
+        self.window_bytes += n as u64;
+        let elapsed = now.duration_since(self.window_start);
+        if elapsed >= SAMPLE_INTERVAL {
+            let rate = self.window_bytes as f64 / elapsed.as_secs_f64();
+            if rate >= self.limit.bytes_per_sec as f64 {
+                self.slow_since = None;
+            } else {
+                self.slow_since.get_or_insert(self.window_start);
+            }
+            self.window_start = now;
+            self.window_bytes = 0;
+        }
+        match self.slow_since {
+            Some(since) if now.duration_since(since) >= self.limit.time => Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                format!(
+                    "transfer too slow: less than {} bytes/sec for {} seconds",
+                    self.limit.bytes_per_sec,
+                    self.limit.time.as_secs()
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl<R: Read> Read for SpeedCheck<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            match self.inner.read(buf) {
+                Ok(n) => {
+                    self.check(n, Instant::now())?;
+                    return Ok(n);
+                }
+                // The read timeout fired: nothing arrived this interval.
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    self.check(0, Instant::now())?
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+/// Writes go straight through, so a checked connection can still be used
+/// for protocols that talk in both directions.
+impl<W: Write> Write for SpeedCheck<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        self.inner.write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// A cap on the bytes per second received, as a token bucket. One limit
+/// is shared by every transfer it is given to, so transfers running in
+/// parallel stay under it together rather than each.
+#[derive(Debug)]
+pub struct RateLimit {
+    bytes_per_sec: u64,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    /// Bytes that may be read before anyone waits; negative once reads
+    /// have run ahead of the rate.
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl RateLimit {
+    pub fn new(bytes_per_sec: u64) -> RateLimit {
+        let bytes_per_sec = bytes_per_sec.max(1);
+        RateLimit {
+            bytes_per_sec,
+            bucket: Mutex::new(Bucket {
+                tokens: chunk(bytes_per_sec) as f64,
+                refilled: Instant::now(),
+            }),
+        }
+    }
+
+    fn chunk(&self) -> usize {
+        chunk(self.bytes_per_sec)
+    }
+
+    /// Accounts for `n` bytes read at `now`, and how long the reader is to
+    /// wait before reading more.
+    fn take(&self, n: usize, now: Instant) -> Duration {
+        let rate = self.bytes_per_sec as f64;
+        let mut bucket = self.bucket.lock().unwrap_or_else(|e| e.into_inner());
+        let elapsed = now.saturating_duration_since(bucket.refilled).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(self.chunk() as f64);
+        bucket.refilled = now;
+        bucket.tokens -= n as f64;
+        match bucket.tokens < 0.0 {
+            true => Duration::from_secs_f64(-bucket.tokens / rate),
+            false => Duration::ZERO,
+        }
+    }
+}
+
+/// The most one read under a limit of `bytes_per_sec` may take: a tenth of
+/// a second's worth, so the bucket never holds more and no reader gets far
+/// ahead of the others.
+fn chunk(bytes_per_sec: u64) -> usize {
+    (bytes_per_sec / 10).clamp(1, 64 * 1024) as usize
+}
+
+/// A reader that keeps to a [`RateLimit`].
+pub struct Throttled<R> {
+    inner: R,
+    limit: Arc<RateLimit>,
+}
+
+impl<R> Throttled<R> {
+    pub fn new(inner: R, limit: Arc<RateLimit>) -> Throttled<R> {
+        Throttled { inner, limit }
+    }
+}
+
+impl<R: Read> Read for Throttled<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = buf.len().min(self.limit.chunk());
+        let n = self.inner.read(&mut buf[..len])?;
+        thread::sleep(self.limit.take(n, Instant::now()));
+        Ok(n)
+    }
+}
+
+/// Writes go straight through, as for [`SpeedCheck`].
+impl<W: Write> Write for Throttled<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.inner.write(buf)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        self.inner.write_vectored(bufs)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limited(bytes_per_sec: u64, secs: u64) -> SpeedCheck<io::Empty> {
+        SpeedCheck::new(
+            io::empty(),
+            SpeedLimit {
+                bytes_per_sec,
+                time: Duration::from_secs(secs),
+            },
+        )
+    }
+
+    #[test]
+    fn aborts_after_staying_slow() {
+        let mut check = limited(100, 3);
+        let start = check.window_start;
+        for s in 1..3 {
+            check.check(10, start + Duration::from_secs(s)).unwrap();
+        }
+        let err = check.check(10, start + Duration::from_secs(3)).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[test]
+    fn a_fast_interval_resets_the_clock() {
+        let mut check = limited(100, 3);
+        let start = check.window_start;
+        check.check(0, start + Duration::from_secs(1)).unwrap();
+        check.check(0, start + Duration::from_secs(2)).unwrap();
+        check.check(500, start + Duration::from_secs(3)).unwrap();
+        check.check(0, start + Duration::from_secs(4)).unwrap();
+        check.check(0, start + Duration::from_secs(5)).unwrap();
+        assert!(check.check(0, start + Duration::from_secs(6)).is_err());
+    }
+
+    #[test]
+    fn readers_share_one_rate() {
+        let limit = Arc::new(RateLimit::new(1000));
+        let start = limit.bucket.lock().unwrap().refilled;
+        // A tenth of a second's worth is there to begin with.
+        assert_eq!(limit.take(100, start), Duration::ZERO);
+        // Two readers each taking 100 bytes at once wait 0.1 and 0.2 s.
+        assert_eq!(limit.take(100, start), Duration::from_millis(100));
+        assert_eq!(limit.take(100, start), Duration::from_millis(200));
+        // Time pays the debt off, but saves up no more than a chunk.
+        let later = start + Duration::from_secs(10);
+        assert_eq!(limit.take(100, later), Duration::ZERO);
+        assert_eq!(limit.take(50, later), Duration::from_millis(50));
+
+        let mut reader = Throttled::new(io::repeat(7), Arc::new(RateLimit::new(1_000_000)));
+        let mut buf = [0; 1 << 20];
+        assert_eq!(reader.read(&mut buf).unwrap(), 64 * 1024);
+    }
+}
@@ -0,0 +1,265 @@
+//! Server-Sent Events (`text/event-stream`) for `--sse`.
+//!
+//! [`Parser`] implements the event stream interpretation of the HTML
+//! standard; [`EventSource`] holds the state that survives a reconnect (the
+//! last event ID and the retry delay) and reads one connection at a time.
+
+use std::io::BufRead;
+use std::time::Duration;
+
+use crate::error::{Error, Result};
+use crate::headers::HeaderMap;
+use crate::http::{self, Exchange, Limits};
+use crate::trace::Trace;
+use crate::url::Url;
+
+/// The reconnection delay until the server sets one with `retry:`.
+pub const DEFAULT_RETRY: Duration = Duration::from_secs(3);
+
+/// One dispatched event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Event {
+    /// The `event:` field; `message` if none was given.
+    pub event: String,
+    /// The `data:` lines joined with newlines.
+    pub data: String,
+    /// The last event ID in effect when this event was dispatched.
+    pub id: Option<String>,
+}
+
+impl Event {
+    /// The event in normalized `text/event-stream` form, ending with the
+    /// blank line that separates events.
+    pub fn to_wire(&self) -> String {
+        let mut out = String::new();
+        if self.event != "message" {
+            out.push_str(&format!("event: {}\n", self.event));
+        }
+        if let Some(id) = &self.id {
+            out.push_str(&format!("id: {}\n", id));
+        }
+        for line in self.data.split('\n') {
+            out.push_str(&format!("data: {}\n", line));
+        }
+        out.push('\n');
+        out
+    }
+}
+
+/// Incremental event stream parser, fed one line at a time.
+#[derive(Debug, Default)]
+pub struct Parser {
+    event: String,
+    data: String,
+    last_event_id: Option<String>,
+    retry: Option<Duration>,
+}
+
+impl Parser {
+    /// Processes one line (without its line ending). Returns an event when
+    /// the line is the blank line that dispatches one.
+    pub fn feed(&mut self, line: &str) -> Option<Event> {
+        if line.is_empty() {
+            let event = std::mem::take(&mut self.event);
+            let mut data = std::mem::take(&mut self.data);
+            if data.is_empty() {
+                return None;
+            }
+            data.pop();
+            let event = if event.is_empty() {
+                "message".to_string()
+            } else {
+                event
+            };
+            return Some(Event {
+                event,
+                data,
+                id: self.last_event_id.clone(),
+            });
+        }
+        if line.starts_with(':') {
+            return None;
+        }
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "event" => self.event = value.to_string(),
+            "data" => {
+                self.data.push_str(value);
+                self.data.push('\n');
+            }
+            "id" if !value.contains('\0') => {
+                self.last_event_id = Some(value.to_string()).filter(|id| !id.is_empty());
+            }
+            "retry" => {
+                if let Ok(ms) = value.parse() {
+                    self.retry = Some(Duration::from_millis(ms));
+                }
+            }
+            _ => {}
+        }
+        None
+    }
+
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.last_event_id.as_deref()
+    }
+
+    /// The reconnection delay requested by the server, if any.
+    pub fn retry(&self) -> Option<Duration> {
+        self.retry
+    }
+
+    /// Forgets a half-received event, as when the connection drops.
+    fn reset_event(&mut self) {
+        self.event.clear();
+        self.data.clear();
+    }
+}
+
+/// A subscription to an event stream that can be resumed after a drop.
+pub struct EventSource {
+    url: Url,
+    headers: HeaderMap,
+    parser: Parser,
+}
+
+impl EventSource {
+    /// `headers` are the ordinary request headers; `Accept` and
+    /// `Last-Event-ID` are managed here.
+    pub fn new(url: Url, headers: HeaderMap) -> EventSource {
+        EventSource {
+            url,
+            headers,
+            parser: Parser::default(),
+        }
+    }
+
+    /// How long to wait before reconnecting.
+    pub fn retry(&self) -> Duration {
+        self.parser.retry().unwrap_or(DEFAULT_RETRY)
+    }
+
+    pub fn last_event_id(&self) -> Option<&str> {
+        self.parser.last_event_id()
+    }
+
+    /// Opens one connection and hands every event to `on_event` until the
+    /// server ends the stream. Errors that a reconnect can fix are
+    /// [`Error::is_transient`]; anything else, including a response that is
+    /// not a `200 text/event-stream`, means the caller should stop.
+    pub fn connect(
+        &mut self,
+        verbose: bool,
+        trace: Option<&Trace>,
+        limits: &Limits,
+        on_event: &mut dyn FnMut(&Event) -> Result<()>,
+    ) -> Result<()> {
+        let mut headers = self.headers.clone();
+        headers.insert("Accept", "text/event-stream");
+        headers.insert("Cache-Control", "no-cache");
+        if let Some(id) = self.parser.last_event_id() {
+            headers.insert("Last-Event-ID", id);
+        }
+        let target = self.url.request_target();
+        let mut response = http::open_request(&Exchange {
+            verbose,
+            trace,
+            ..Exchange::new(
+                self.url.connect_host(),
+                self.url.port_or_default(),
+                "GET",
+                &target,
+                &headers,
+                limits,
+            )
+        })?;
+        if response.status.code != 200 {
+            return Err(Error::HttpProtocol(format!(
+                "event stream request failed: {}",
+                response.status
+            )));
+        }
+        let media_type = response.headers.get("Content-Type").unwrap_or_default();
+        let media_type = media_type.split(';').next().unwrap_or_default().trim();
+        if !media_type.eq_ignore_ascii_case("text/event-stream") {
+            return Err(Error::HttpProtocol(format!(
+                "expected text/event-stream, got '{}'",
+                media_type
+            )));
+        }
+
+        self.parser.reset_event();
+        let mut reader = std::io::BufReader::new(&mut response.body);
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+            let text = String::from_utf8_lossy(&line);
+            let text = text.strip_suffix('\n').unwrap_or(&text);
+            let text = text.strip_suffix('\r').unwrap_or(text);
+            if let Some(event) = self.parser.feed(text) {
+                on_event(&event)?;
+            }
+        }
+        drop(reader);
+        response.body.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(stream: &str) -> (Vec<Event>, Parser) {
+        let mut parser = Parser::default();
+        let events = stream.lines().filter_map(|l| parser.feed(l)).collect();
+        (events, parser)
+    }
+
+    #[test]
+    fn parses_fields_and_dispatches_on_blank_lines() {
+        let (events, parser) = parse(
+            ": comment\nevent: update\ndata: one\ndata:two\nid: 7\n\ndata: plain\n\nretry: 1500\n\n",
+        );
+        assert_eq!(
+            events,
+            [
+                Event {
+                    event: "update".into(),
+                    data: "one\ntwo".into(),
+                    id: Some("7".into()),
+                },
+                Event {
+                    event: "message".into(),
+                    data: "plain".into(),
+                    id: Some("7".into()),
+                },
+            ]
+        );
+        assert_eq!(parser.retry(), Some(Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn empty_data_dispatches_nothing() {
+        let (events, parser) = parse("event: x\n\nid: 3\n\ndata\n\n");
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].data, "");
+        assert_eq!(events[0].event, "message");
+        assert_eq!(parser.last_event_id(), Some("3"));
+    }
+
+    #[test]
+    fn writes_normalized_events() {
+        let event = Event {
+            event: "tick".into(),
+            data: "a\nb".into(),
+            id: Some("9".into()),
+        };
+        assert_eq!(event.to_wire(), "event: tick\nid: 9\ndata: a\ndata: b\n\n");
+    }
+}
@@ -0,0 +1,270 @@
+//! `{{name}}` placeholders in URLs, headers and bodies.
+//!
+//! [`Vars`] resolves names from values set explicitly (definitions in a
+//! `.http` file, an `--env` file) and then, if enabled, from the process
+//! environment. `\{{` is a literal `{{`; braces around anything that is not
+//! a name, such as `{{ a b }}`, are left alone.
+//!
+//! Values given with [`Vars::define`] may themselves contain placeholders;
+//! they are expanded when used, not when defined, so they can refer to
+//! responses that have not arrived yet. Responses recorded with
+//! [`Vars::add_response`] answer `{{req.response.headers.Name}}`,
+//! `{{req.response.body}}` and JSONPath lookups such as
+//! `{{req.response.body.$.token}}`.
+
+use std::collections::BTreeMap;
+use std::env;
+
+use crate::error::{Error, Result};
+use crate::json::{self, Value};
+use crate::response::Response;
+
+/// How deeply definitions may refer to each other, which also catches
+/// cycles.
+const MAX_NESTING: usize = 16;
+
+#[derive(Debug, Clone)]
+enum Entry {
+    Literal(String),
+    Template(String),
+}
+
+/// Variable values, in lookup order: explicit values, recorded responses,
+/// then the environment.
+#[derive(Debug, Clone, Default)]
+pub struct Vars {
+    values: BTreeMap<String, Entry>,
+    responses: BTreeMap<String, Response>,
+    environment: bool,
+}
+
+impl Vars {
+    /// With `environment`, names without an explicit value are looked up
+    /// as environment variables.
+    pub fn new(environment: bool) -> Vars {
+        Vars {
+            values: BTreeMap::new(),
+            responses: BTreeMap::new(),
+            environment,
+        }
+    }
+
+    /// Sets a value that is used as is.
+    pub fn set(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.values
+            .insert(name.into(), Entry::Literal(value.into()));
+    }
+
+    /// Sets a value whose own placeholders are expanded each time it is
+    /// used.
+    pub fn define(&mut self, name: impl Into<String>, template: impl Into<String>) {
+        self.values
+            .insert(name.into(), Entry::Template(template.into()));
+    }
+
+    /// Records the response to the request called `name`.
+    pub fn add_response(&mut self, name: impl Into<String>, response: Response) {
+        self.responses.insert(name.into(), response);
+    }
+
+    /// The value of `name`, or `None` if it is not defined.
+    pub fn get(&self, name: &str) -> Result<Option<String>> {
+        self.lookup(name, 0)
+    }
+
+    fn lookup(&self, name: &str, depth: usize) -> Result<Option<String>> {
+        match self.values.get(name) {
+            Some(Entry::Literal(value)) => return Ok(Some(value.clone())),
+            Some(Entry::Template(template)) => {
+                if depth >= MAX_NESTING {
+                    return Err(Error::Template(format!(
+                        "variable '{}' refers to itself",
+                        name
+                    )));
+                }
+                return self.expand_at(template, depth + 1).map(Some);
+            }
+            None => {}
+        }
+        if let Some(value) = self.response_value(name) {
+            return Ok(Some(value));
+        }
+        Ok(self.environment.then(|| env::var(name).ok()).flatten())
+    }
+
+    /// Resolves `req.response.headers.Name` and `req.response.body[.path]`.
+    fn response_value(&self, name: &str) -> Option<String> {
+        let (request, rest) = name.split_once(".response.")?;
+        let response = self.responses.get(request)?;
+        if let Some(header) = rest.strip_prefix("headers.") {
+            let values: Vec<&str> = response.headers.get_all(header).collect();
+            return (!values.is_empty()).then(|| values.join(", "));
+        }
+        let path = match rest.strip_prefix("body") {
+            Some("") | Some(".*") => {
+                return Some(String::from_utf8_lossy(&response.body).into_owned());
+            }
+            Some(path) => path.strip_prefix('.')?,
+            None => return None,
+        };
+        let body = std::str::from_utf8(&response.body).ok()?;
+        let value = json::parse(body).ok()?;
+        value.path(path).map(Value::to_text)
+    }
+
+    /// Adds every value of a JSON object, such as
+    /// `{"host": "staging.example.com", "port": 8080}`. Nested objects are
+    /// flattened with dots: `{"db": {"user": "x"}}` defines `db.user`.
+    pub fn load_json(&mut self, text: &str) -> Result<()> {
+        let value = json::parse(text).map_err(|e| Error::Template(e.to_string()))?;
+        if !matches!(value, Value::Object(_)) {
+            return Err(Error::Template(
+                "variables must be given as a JSON object".to_string(),
+            ));
+        }
+        self.add_json("", &value);
+        Ok(())
+    }
+
+    fn add_json(&mut self, prefix: &str, value: &Value) {
+        match value {
+            Value::Object(members) => {
+                for (key, member) in members {
+                    let name = if prefix.is_empty() {
+                        key.clone()
+                    } else {
+                        format!("{}.{}", prefix, key)
+                    };
+                    self.add_json(&name, member);
+                }
+            }
+            Value::Null => self.set(prefix, ""),
+            other => self.set(prefix, other.to_text()),
+        }
+    }
+
+    /// Replaces every `{{name}}` in `text`. An undefined name is an error,
+    /// so that a typo does not go out on the wire.
+    pub fn expand(&self, text: &str) -> Result<String> {
+        self.expand_at(text, 0)
+    }
+
+    fn expand_at(&self, text: &str, depth: usize) -> Result<String> {
+        let mut out = String::with_capacity(text.len());
+        let mut rest = text;
+        while let Some(start) = rest.find("{{") {
+            if rest[..start].ends_with('\\') {
+                out.push_str(&rest[..start - 1]);
+                out.push_str("{{");
+                rest = &rest[start + 2..];
+                continue;
+            }
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 2..];
+            let Some(end) = after.find("}}") else {
+                out.push_str(&rest[start..]);
+                return Ok(out);
+            };
+            let name = after[..end].trim();
+            if !is_name(name) {
+                out.push_str("{{");
+                rest = after;
+                continue;
+            }
+            let value = self
+                .lookup(name, depth)?
+                .ok_or_else(|| Error::Template(format!("variable '{}' is not defined", name)))?;
+            out.push_str(&value);
+            rest = &after[end + 2..];
+        }
+        out.push_str(rest);
+        Ok(out)
+    }
+}
+
+/// Names are non-empty and made of letters, digits and `_ . - $ [ ] ' *`,
+/// which covers JSONPath lookups.
+fn is_name(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || "_.-$[]'*".contains(c))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headers::HeaderMap;
+    use crate::response::StatusLine;
+
+    #[test]
+    fn expands_defined_names() {
+        let mut vars = Vars::new(false);
+        vars.set("host", "example.com");
+        vars.set("id", "7");
+        assert_eq!(
+            vars.expand("http://{{host}}/items/{{ id }}?q={{id}}")
+                .unwrap(),
+            "http://example.com/items/7?q=7"
+        );
+        assert_eq!(
+            vars.expand(r"\{{host}} {{a b}}").unwrap(),
+            "{{host}} {{a b}}"
+        );
+        assert_eq!(vars.expand("{{host").unwrap(), "{{host");
+        let err = vars.expand("{{missing}}").unwrap_err();
+        assert_eq!(err.to_string(), "variable 'missing' is not defined");
+    }
+
+    #[test]
+    fn loads_json_and_falls_back_to_the_environment() {
+        let mut vars = Vars::new(true);
+        vars.load_json(r#"{"base": "http://dev", "port": 8080, "db": {"user": "u", "pw": null}}"#)
+            .unwrap();
+        assert_eq!(vars.get("base").unwrap().as_deref(), Some("http://dev"));
+        assert_eq!(vars.get("port").unwrap().as_deref(), Some("8080"));
+        assert_eq!(vars.get("db.user").unwrap().as_deref(), Some("u"));
+        assert_eq!(vars.get("db.pw").unwrap().as_deref(), Some(""));
+        let path = env::var("PATH").unwrap();
+        assert_eq!(vars.expand("{{PATH}}").unwrap(), path);
+        assert!(Vars::new(false).get("PATH").unwrap().is_none());
+        assert!(vars.load_json("[1]").is_err());
+        assert!(vars.load_json("{").is_err());
+    }
+
+    #[test]
+    fn resolves_definitions_and_responses_lazily() {
+        let mut vars = Vars::new(false);
+        vars.define("auth", "Bearer {{login.response.body.$.token}}");
+        vars.define("loop", "{{loop}}");
+        assert!(vars.expand("{{auth}}").is_err());
+
+        vars.add_response(
+            "login",
+            Response {
+                status: StatusLine {
+                    version: "HTTP/1.1".into(),
+                    code: 200,
+                    reason: "OK".into(),
+                },
+                headers: [("X-Session", "s1")].into_iter().collect(),
+                body: br#"{"token": "t{{x}}", "roles": ["a", "b"]}"#.to_vec(),
+                trailers: HeaderMap::new(),
+            },
+        );
+        // Response values are not expanded again.
+        assert_eq!(vars.expand("{{auth}}").unwrap(), "Bearer t{{x}}");
+        assert_eq!(
+            vars.expand("{{login.response.headers.x-session}} {{login.response.body.$.roles[1]}}")
+                .unwrap(),
+            "s1 b"
+        );
+        assert!(vars
+            .expand("{{login.response.body.*}}")
+            .unwrap()
+            .starts_with("{\"token\""));
+        assert!(vars.expand("{{login.response.body.$.missing}}").is_err());
+        let err = vars.expand("{{loop}}").unwrap_err();
+        assert_eq!(err.to_string(), "variable 'loop' refers to itself");
+    }
+}
@@ -0,0 +1,57 @@
+//! TLS for `https://` URLs, behind a [`Connector`] so the backend can be
+//! chosen at build time or supplied by an embedder.
+//!
+//! With the default `rustls` feature, [`default_connector`] is a rustls
+//! client that trusts the system's CA certificates. A build without it has
+//! no backend, and https fails with [`Error::Tls`] unless one is put in
+//! [`Limits::tls`](crate::http::Limits) by the caller, such as a wrapper
+//! around native-tls for platforms whose certificate store it knows. No
+//! native-tls backend is built in, and the `native-tls` feature only fails
+//! the build to say so.
+
+use std::fmt;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::sync::Arc;
+
+use crate::error::{Error, Result};
+
+#[cfg(feature = "rustls")]
+mod rustls;
+
+#[cfg(feature = "native-tls")]
+compile_error!(
+    "the native-tls backend is not implemented; use the rustls feature, \
+     or supply a tls::Connector through Limits::tls"
+);
+
+/// A connection a request can be sent over: plain TCP, or TLS on top of it.
+pub trait Stream: Read + Write + Send {}
+
+impl<T: Read + Write + Send> Stream for T {}
+
+/// A TLS implementation.
+pub trait Connector: Send + Sync + fmt::Debug {
+    /// Runs the handshake for `host` over `tcp`, verifying that the
+    /// server's certificate is valid for it.
+    fn connect(&self, host: &str, tcp: TcpStream) -> Result<Box<dyn Stream>>;
+}
+
+/// The backend compiled in, shared by every connection.
+pub fn default_connector() -> Result<Arc<dyn Connector>> {
+    #[cfg(feature = "rustls")]
+    {
+        rustls::shared()
+    }
+    #[cfg(not(feature = "rustls"))]
+    {
+        Err(Error::Tls(
+            "https needs TLS support, which this build does not have".to_string(),
+        ))
+    }
+}
+
+/// A handshake failure, in the terms of whatever backend reported it.
+pub fn handshake_error(host: &str, e: impl fmt::Display) -> Error {
+    Error::Tls(format!("TLS handshake with {} failed: {}", host, e))
+}
@@ -0,0 +1,87 @@
+//! The rustls backend.
+
+use std::env;
+use std::net::TcpStream;
+use std::path::Path;
+use std::sync::{Arc, OnceLock};
+
+use ::rustls::pki_types::pem::PemObject;
+use ::rustls::pki_types::{CertificateDer, ServerName};
+use ::rustls::{ClientConfig, ClientConnection, RootCertStore, StreamOwned};
+
+use super::{handshake_error, Connector, Stream};
+use crate::error::{Error, Result};
+
+/// Where distributions keep their CA bundle, as curl and OpenSSL look.
+const BUNDLES: &[&str] = &[
+    "/etc/ssl/certs/ca-certificates.crt",
+    "/etc/pki/tls/certs/ca-bundle.crt",
+    "/etc/ssl/ca-bundle.pem",
+    "/etc/ssl/cert.pem",
+    "/usr/local/etc/openssl/cert.pem",
+];
+
+#[derive(Debug)]
+pub struct Rustls {
+    config: Arc<ClientConfig>,
+}
+
+impl Rustls {
+    /// Trusts the certificates in the PEM file `$SSL_CERT_FILE`, or else in
+    /// the first system bundle there is.
+    pub fn new() -> Result<Rustls> {
+        let path = env::var("SSL_CERT_FILE")
+            .ok()
+            .or_else(|| {
+                BUNDLES
+                    .iter()
+                    .find(|p| Path::new(p).exists())
+                    .map(|p| p.to_string())
+            })
+            .ok_or_else(|| {
+                Error::Tls("no CA certificates found; set SSL_CERT_FILE to a bundle".to_string())
+            })?;
+        let certs = CertificateDer::pem_file_iter(&path)
+            .map_err(|e| Error::Tls(format!("cannot read CA certificates {}: {}", path, e)))?;
+        let mut roots = RootCertStore::empty();
+        roots.add_parsable_certificates(certs.flatten());
+        if roots.is_empty() {
+            return Err(Error::Tls(format!("no CA certificates in {}", path)));
+        }
+        let provider = Arc::new(::rustls::crypto::ring::default_provider());
+        let mut config = ClientConfig::builder_with_provider(provider)
+            .with_safe_default_protocol_versions()
+            .map_err(|e| Error::Tls(e.to_string()))?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+        // Only HTTP/1.1 is spoken over TLS.
+        config.alpn_protocols = vec![b"http/1.1".to_vec()];
+        Ok(Rustls {
+            config: Arc::new(config),
+        })
+    }
+}
+
+impl Connector for Rustls {
+    fn connect(&self, host: &str, mut tcp: TcpStream) -> Result<Box<dyn Stream>> {
+        let name = ServerName::try_from(host.to_string())
+            .map_err(|e| Error::Tls(format!("cannot check a certificate for {}: {}", host, e)))?;
+        let mut conn = ClientConnection::new(self.config.clone(), name)
+            .map_err(|e| handshake_error(host, e))?;
+        // Handshake now, so its failures are told apart from I/O errors.
+        while conn.is_handshaking() {
+            conn.complete_io(&mut tcp)
+                .map_err(|e| handshake_error(host, e))?;
+        }
+        Ok(Box::new(StreamOwned::new(conn, tcp)))
+    }
+}
+
+/// One backend for the whole process, so the CA bundle is read once.
+pub fn shared() -> Result<Arc<dyn Connector>> {
+    static SHARED: OnceLock<std::result::Result<Arc<Rustls>, String>> = OnceLock::new();
+    match SHARED.get_or_init(|| Rustls::new().map(Arc::new).map_err(|e| e.to_string())) {
+        Ok(rustls) => Ok(rustls.clone()),
+        Err(msg) => Err(Error::Tls(msg.clone())),
+    }
+}
@@ -0,0 +1,238 @@
+//! Wire tracing for `--trace` and `--trace-ascii`.
+//!
+//! [`Traced`] wraps a connection and copies every byte read or written to a
+//! [`Trace`] sink as a timestamped dump, in the spirit of curl's trace files.
+
+use std::fmt::Write as _;
+use std::io::{self, IoSlice, Read, Write};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{Error, Result};
+use crate::metrics::Metrics;
+
+/// How bytes are rendered in the trace.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TraceMode {
+    /// Offset, hex bytes and a printable-ASCII column, 16 bytes per line.
+    Hex,
+    /// The data as text, split at line ends, with unprintables shown as `.`.
+    Ascii,
+}
+
+/// Which way the bytes went.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Send,
+    Recv,
+}
+
+/// A sink for trace output.
+pub struct Trace {
+    mode: TraceMode,
+    out: Mutex<Box<dyn Write + Send>>,
+}
+
+impl Trace {
+    pub fn new(mode: TraceMode, out: Box<dyn Write + Send>) -> Trace {
+        Trace {
+            mode,
+            out: Mutex::new(out),
+        }
+    }
+
+    /// Opens the trace destination; `-` means stdout.
+    pub fn open(path: &str, mode: TraceMode) -> Result<Trace> {
+        let out: Box<dyn Write + Send> = if path == "-" {
+            Box::new(io::stdout())
+        } else {
+            Box::new(std::fs::File::create(path).map_err(|source| Error::File {
+                path: path.to_string(),
+                source,
+            })?)
+        };
+        Ok(Trace::new(mode, out))
+    }
+
+    /// Writes a free-form informational line.
+    pub fn info(&self, text: &str) {
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        // Tracing is best-effort; a failing trace file must not fail the
+        // transfer.
+        let _ = writeln!(out, "{} == Info: {}", timestamp(), text);
+    }
+
+    /// Dumps a block of bytes.
+    pub fn data(&self, direction: Direction, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        let arrow = match direction {
+            Direction::Send => "=> Send",
+            Direction::Recv => "<= Recv",
+        };
+        let mut text = format!(
+            "{} {} data, {} bytes (0x{:x})\n",
+            timestamp(),
+            arrow,
+            data.len(),
+            data.len()
+        );
+        match self.mode {
+            TraceMode::Hex => dump_hex(&mut text, data),
+            TraceMode::Ascii => dump_ascii(&mut text, data),
+        }
+        let mut out = self.out.lock().unwrap_or_else(|e| e.into_inner());
+        let _ = out.write_all(text.as_bytes());
+        let _ = out.flush();
+    }
+}
+
+fn dump_hex(text: &mut String, data: &[u8]) {
+    for (line, chunk) in data.chunks(16).enumerate() {
+        let _ = write!(text, "{:04x}: ", line * 16);
+        for i in 0..16 {
+            match chunk.get(i) {
+                Some(b) => {
+                    let _ = write!(text, "{:02x} ", b);
+                }
+                None => text.push_str("   "),
+            }
+        }
+        text.extend(chunk.iter().map(|&b| printable(b)));
+        text.push('\n');
+    }
+}
+
+fn dump_ascii(text: &mut String, data: &[u8]) {
+    let mut offset = 0;
+    for line in data.split_inclusive(|&b| b == b'\n') {
+        let _ = write!(text, "{:04x}: ", offset);
+        offset += line.len();
+        let line = line.strip_suffix(b"\n").unwrap_or(line);
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        text.extend(line.iter().map(|&b| printable(b)));
+        text.push('\n');
+    }
+}
+
+fn printable(b: u8) -> char {
+    if (0x20..0x7f).contains(&b) {
+        b as char
+    } else {
+        '.'
+    }
+}
+
+/// Wall-clock time of day (UTC) with microseconds, as curl's `--trace-time`.
+pub fn timestamp() -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let secs = now.as_secs() % 86_400;
+    format!(
+        "{:02}:{:02}:{:02}.{:06}",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60,
+        now.subsec_micros()
+    )
+}
+
+/// A stream that reports its traffic to an optional trace and counts it in
+/// optional metrics.
+pub struct Traced<'a, S> {
+    inner: S,
+    trace: Option<&'a Trace>,
+    metrics: Option<&'a Metrics>,
+}
+
+impl<'a, S> Traced<'a, S> {
+    pub fn new(inner: S, trace: Option<&'a Trace>) -> Traced<'a, S> {
+        Traced {
+            inner,
+            trace,
+            metrics: None,
+        }
+    }
+
+    /// Also counts the traffic in `metrics`.
+    pub fn measured(mut self, metrics: Option<&'a Metrics>) -> Traced<'a, S> {
+        self.metrics = metrics;
+        self
+    }
+}
+
+impl<S: Read> Read for Traced<'_, S> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        if let Some(trace) = self.trace {
+            trace.data(Direction::Recv, &buf[..n]);
+        }
+        if let Some(metrics) = self.metrics {
+            metrics.received(n);
+        }
+        Ok(n)
+    }
+}
+
+impl<S: Write> Write for Traced<'_, S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        if let Some(trace) = self.trace {
+            trace.data(Direction::Send, &buf[..n]);
+        }
+        if let Some(metrics) = self.metrics {
+            metrics.sent(n);
+        }
+        Ok(n)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        let n = self.inner.write_vectored(bufs)?;
+        if let Some(trace) = self.trace {
+            let mut left = n;
+            for buf in bufs {
+                let sent = left.min(buf.len());
+                trace.data(Direction::Send, &buf[..sent]);
+                left -= sent;
+            }
+        }
+        if let Some(metrics) = self.metrics {
+            metrics.sent(n);
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_dump_pads_short_lines() {
+        let mut text = String::new();
+        dump_hex(&mut text, b"GET / HTTP/1.1\r\nHost: h\r\n");
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(
+            lines[0],
+            "0000: 47 45 54 20 2f 20 48 54 54 50 2f 31 2e 31 0d 0a GET / HTTP/1.1.."
+        );
+        assert!(lines[1].starts_with("0010: 48 6f 73 74 3a 20 68 0d 0a    "));
+        assert!(lines[1].ends_with("Host: h.."));
+    }
+
+    #[test]
+    fn ascii_dump_splits_lines() {
+        let mut text = String::new();
+        dump_ascii(&mut text, b"HTTP/1.1 200 OK\r\nA: \x01\r\n\r\nbody");
+        assert_eq!(
+            text,
+            "0000: HTTP/1.1 200 OK\n0011: A: .\n0017: \n0019: body\n"
+        );
+    }
+}
@@ -0,0 +1,192 @@
+//! What a request is sent over, so the HTTP/1.1 code in [`crate::http`] can
+//! be driven by something other than a real TCP connection.
+//!
+//! [`Tcp`] is what the client normally uses, [`UnixSocket`] reaches servers
+//! listening on a socket file, and [`Mock`] replays a canned response from
+//! memory while recording the request, for testing request building and
+//! response parsing without a server. [`Tcp`] runs TLS over the
+//! connection when [`Limits::tls`] is set.
+
+use std::io::{self, Cursor, Read, Write};
+use std::sync::{Arc, Mutex};
+
+use crate::error::Result;
+use crate::http::{self, Limits};
+use crate::metrics::Metrics;
+use crate::tls::Stream;
+use crate::trace::{Trace, Traced};
+
+/// Opens connections to send a request over.
+pub trait Transport {
+    type Stream: Read + Write;
+
+    /// A connection for a request to `host` and `port`; transports that
+    /// do not use them may ignore them.
+    fn connect(&self, host: &str, port: u16) -> Result<Self::Stream>;
+}
+
+/// TCP with the `--tcp-*` socket options, traced and measured.
+pub struct Tcp<'a> {
+    pub trace: Option<&'a Trace>,
+    pub metrics: Option<&'a Metrics>,
+    pub limits: &'a Limits,
+}
+
+impl<'a> Transport for Tcp<'a> {
+    type Stream = Traced<'a, Box<dyn Stream>>;
+
+    fn connect(&self, host: &str, port: u16) -> Result<Self::Stream> {
+        http::open_connection(host, port, self.trace, self.metrics, self.limits)
+    }
+}
+
+/// A Unix domain socket, whatever the URL's host is.
+#[cfg(unix)]
+pub struct UnixSocket {
+    pub path: std::path::PathBuf,
+}
+
+#[cfg(unix)]
+impl Transport for UnixSocket {
+    type Stream = std::os::unix::net::UnixStream;
+
+    fn connect(&self, host: &str, port: u16) -> Result<Self::Stream> {
+        std::os::unix::net::UnixStream::connect(&self.path).map_err(|source| {
+            crate::error::Error::Connect {
+                host: host.to_string(),
+                port,
+                source,
+            }
+        })
+    }
+}
+
+/// An in-memory server that answers every connection with the same bytes
+/// and keeps everything written to it.
+#[derive(Debug, Clone, Default)]
+pub struct Mock {
+    response: Vec<u8>,
+    sent: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Mock {
+    /// Answers with `response`, a complete HTTP response as it would come
+    /// off the wire.
+    pub fn new(response: impl Into<Vec<u8>>) -> Mock {
+        Mock {
+            response: response.into(),
+            sent: Arc::default(),
+        }
+    }
+
+    /// Everything written over all connections so far.
+    pub fn sent(&self) -> Vec<u8> {
+        self.sent.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+}
+
+/// One connection to a [`Mock`].
+pub struct MockStream {
+    input: Cursor<Vec<u8>>,
+    sent: Arc<Mutex<Vec<u8>>>,
+}
+
+impl Read for MockStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.input.read(buf)
+    }
+}
+
+impl Write for MockStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut sent = self.sent.lock().unwrap_or_else(|e| e.into_inner());
+        sent.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Transport for Mock {
+    type Stream = MockStream;
+
+    fn connect(&self, _: &str, _: u16) -> Result<MockStream> {
+        Ok(MockStream {
+            input: Cursor::new(self.response.clone()),
+            sent: self.sent.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::headers::HeaderMap;
+    use crate::http::{send_over, Exchange};
+
+    #[test]
+    fn sends_over_a_mock() {
+        let mock = Mock::new(
+            "HTTP/1.1 100 Continue\r\n\r\n\
+             HTTP/1.1 201 Created\r\nTransfer-Encoding: chunked\r\n\r\n\
+             3\r\nabc\r\n2\r\nde\r\n0\r\n\r\n",
+        );
+        let headers = http::default_headers("api.test", Some(4));
+        let limits = Limits::default();
+        let response = send_over(
+            &mock,
+            &Exchange {
+                body: Some(b"data"),
+                ..Exchange::new("api.test", 80, "PUT", "/x", &headers, &limits)
+            },
+        )
+        .unwrap();
+        assert_eq!(response.status.code, 201);
+        assert_eq!(response.body, b"abcde");
+        let sent = String::from_utf8(mock.sent()).unwrap();
+        assert!(sent.starts_with("PUT /x HTTP/1.1\r\nHost: api.test\r\n"));
+        assert!(sent.ends_with("Content-Length: 4\r\nConnection: close\r\n\r\ndata"));
+
+        let truncated = Mock::new("HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nshort");
+        let none = HeaderMap::new();
+        let result = send_over(
+            &truncated,
+            &Exchange::new("h", 80, "GET", "/", &none, &limits),
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn sends_over_a_unix_socket() {
+        use std::os::unix::net::UnixListener;
+        let path = std::env::temp_dir().join(format!("http_client-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n") {
+                let n = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..n]);
+            }
+            stream
+                .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok")
+                .unwrap();
+        });
+        let transport = UnixSocket { path: path.clone() };
+        let headers = http::default_headers("localhost", None);
+        let limits = Limits::default();
+        let response = send_over(
+            &transport,
+            &Exchange::new("localhost", 80, "GET", "/", &headers, &limits),
+        )
+        .unwrap();
+        server.join().unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(response.body, b"ok");
+    }
+}
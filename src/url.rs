@@ -0,0 +1,712 @@
+//! URL parsing and normalization.
+//!
+//! Only the subset of RFC 3986 an HTTP client needs is supported: a scheme,
+//! an authority (optional userinfo, host, optional port), a path, a query and
+//! a fragment. Unsafe characters in the path and query are percent-encoded so
+//! the request line never carries raw spaces, controls or non-ASCII bytes.
+
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::error::{Error, Result};
+use crate::idna;
+
+/// A parsed, normalized absolute URL.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Url {
+    scheme: String,
+    userinfo: Option<String>,
+    host: String,
+    port: Option<u16>,
+    path: String,
+    query: Option<String>,
+    fragment: Option<String>,
+}
+
+impl Url {
+    /// Parses an absolute URL such as `http://example.com:8080/a/b?x=1`.
+    /// Unicode host names are converted to punycode.
+    pub fn parse(input: &str) -> Result<Url> {
+        Url::parse_with_idn(input, true)
+    }
+
+    /// Like [`Url::parse`], but with `idn` unset a non-ASCII host name is an
+    /// error instead of being converted.
+    pub fn parse_with_idn(input: &str, idn: bool) -> Result<Url> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Err(Error::UrlParse("URL is empty".to_string()));
+        }
+
+        let (scheme, rest) = input.split_once("://").ok_or_else(|| {
+            Error::UrlParse({
+                format!(
+                    "URL '{}' is missing a scheme (expected e.g. 'http://')",
+                    input
+                )
+            })
+        })?;
+        let scheme = parse_scheme(scheme)?;
+
+        // The authority runs until the first '/', '?' or '#'.
+        let authority_end = rest.find(['/', '?', '#']).unwrap_or(rest.len());
+        let (authority, rest) = rest.split_at(authority_end);
+
+        let (userinfo, hostport) = match authority.rsplit_once('@') {
+            Some((userinfo, hostport)) => (Some(userinfo.to_string()), hostport),
+            None => (None, authority),
+        };
+        // file:///path has an empty authority.
+        let (host, port) = if scheme == "file" && authority.is_empty() {
+            (String::new(), None)
+        } else {
+            parse_host_port(hostport, idn)?
+        };
+
+        let (rest, fragment) = match rest.split_once('#') {
+            Some((rest, fragment)) => (rest, Some(encode(fragment, EncodeSet::Fragment))),
+            None => (rest, None),
+        };
+        let (path, query) = match rest.split_once('?') {
+            Some((path, query)) => (path, Some(encode(query, EncodeSet::Query))),
+            None => (rest, None),
+        };
+        let path = if path.is_empty() {
+            "/".to_string()
+        } else {
+            encode(path, EncodeSet::Path)
+        };
+
+        Ok(Url {
+            scheme,
+            userinfo,
+            host,
+            port,
+            path,
+            query,
+            fragment,
+        })
+    }
+
+    /// Resolves a reference (such as a `Location` header value) against this
+    /// URL, following RFC 3986 section 5.2.
+    pub fn join(&self, reference: &str) -> Result<Url> {
+        let reference = reference.trim();
+        if has_scheme(reference) {
+            return Url::parse(reference);
+        }
+        if let Some(rest) = reference.strip_prefix("//") {
+            return Url::parse(&format!("{}://{}", self.scheme, rest));
+        }
+
+        let base = self.without_userinfo();
+        let origin = format!("{}://{}", base.scheme, base.host_header());
+        let (reference, fragment) = match reference.split_once('#') {
+            Some((r, f)) => (r, Some(f)),
+            None => (reference, None),
+        };
+        let target = if reference.is_empty() {
+            base.request_target()
+        } else if reference.starts_with('?') {
+            format!("{}{}", base.path, reference)
+        } else {
+            let (path, query) = match reference.split_once('?') {
+                Some((p, q)) => (p, Some(q)),
+                None => (reference, None),
+            };
+            let merged = if path.starts_with('/') {
+                path.to_string()
+            } else {
+                let dir = &base.path[..base.path.rfind('/').map_or(0, |i| i + 1)];
+                format!("{}{}", dir, path)
+            };
+            let path = remove_dot_segments(&merged);
+            match query {
+                Some(q) => format!("{}?{}", path, q),
+                None => path,
+            }
+        };
+        let mut joined = format!("{}{}", origin, target);
+        if let Some(fragment) = fragment {
+            joined.push('#');
+            joined.push_str(fragment);
+        }
+        Url::parse(&joined)
+    }
+
+    /// The lowercase scheme, e.g. `http`.
+    pub fn scheme(&self) -> &str {
+        &self.scheme
+    }
+
+    /// The raw (still percent-encoded) `user:password` part, if any.
+    pub fn userinfo(&self) -> Option<&str> {
+        self.userinfo.as_deref()
+    }
+
+    /// The percent-decoded user name from the userinfo, if any.
+    pub fn username(&self) -> Option<String> {
+        let userinfo = self.userinfo.as_deref()?;
+        let user = userinfo.split_once(':').map_or(userinfo, |(user, _)| user);
+        Some(percent_decode(user))
+    }
+
+    /// The percent-decoded password from the userinfo, if one was given.
+    pub fn password(&self) -> Option<String> {
+        let (_, password) = self.userinfo.as_deref()?.split_once(':')?;
+        Some(percent_decode(password))
+    }
+
+    /// A copy of this URL with the userinfo removed, suitable for display and
+    /// for anything that goes on the wire.
+    pub fn without_userinfo(&self) -> Url {
+        Url {
+            userinfo: None,
+            ..self.clone()
+        }
+    }
+
+    /// The host name or IP literal. IPv6 literals keep their brackets.
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    /// The explicit port, if one was given.
+    pub fn port(&self) -> Option<u16> {
+        self.port
+    }
+
+    /// The explicit port, or the scheme's default port.
+    pub fn port_or_default(&self) -> u16 {
+        self.port
+            .or_else(|| default_port(&self.scheme))
+            .unwrap_or(80)
+    }
+
+    /// The normalized, percent-encoded path. Never empty.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// The percent-encoded query without the leading `?`.
+    pub fn query(&self) -> Option<&str> {
+        self.query.as_deref()
+    }
+
+    /// The fragment without the leading `#`. Fragments are never sent.
+    pub fn fragment(&self) -> Option<&str> {
+        self.fragment.as_deref()
+    }
+
+    /// Switches an `http` URL to `https`, as HSTS requires, and a `ws` one
+    /// to `wss`. An explicit port 80 becomes 443; other ports are kept
+    /// (RFC 6797 section 8.3).
+    pub fn upgrade_to_tls(&mut self) {
+        self.scheme = match self.scheme.as_str() {
+            "http" => "https".to_string(),
+            "ws" => "wss".to_string(),
+            _ => return,
+        };
+        if self.port == Some(80) {
+            self.port = None;
+        }
+    }
+
+    /// Replaces the fragment.
+    pub fn set_fragment(&mut self, fragment: Option<&str>) {
+        self.fragment = fragment.map(|f| encode(f, EncodeSet::Fragment));
+    }
+
+    /// The origin-form request target: path plus query.
+    pub fn request_target(&self) -> String {
+        match &self.query {
+            Some(query) => format!("{}?{}", self.path, query),
+            None => self.path.clone(),
+        }
+    }
+
+    /// Whether both URLs have the same scheme, host and port, and so may
+    /// share credentials.
+    pub fn same_origin(&self, other: &Url) -> bool {
+        self.scheme == other.scheme
+            && self.host.eq_ignore_ascii_case(&other.host)
+            && self.port_or_default() == other.port_or_default()
+    }
+
+    /// The absolute-form request target (RFC 9112 section 3.2.2) a proxy is
+    /// sent: the whole URL but its userinfo and fragment.
+    pub fn absolute_form(&self) -> String {
+        format!(
+            "{}://{}{}",
+            self.scheme,
+            self.host_header(),
+            self.request_target()
+        )
+    }
+
+    /// The value for the `Host` header; the port is omitted when it is the
+    /// scheme's default.
+    pub fn host_header(&self) -> String {
+        match self.port {
+            Some(port) if Some(port) != default_port(&self.scheme) => {
+                format!("{}:{}", self.host, port)
+            }
+            _ => self.host.clone(),
+        }
+    }
+
+    /// Appends an already-encoded `name=value` pair to the query string.
+    pub fn append_query(&mut self, pair: &str) {
+        let pair = encode(pair, EncodeSet::Query);
+        match &mut self.query {
+            Some(query) if !query.is_empty() => {
+                query.push('&');
+                query.push_str(&pair);
+            }
+            _ => self.query = Some(pair),
+        }
+    }
+
+    /// The local path a `file:` URL names, percent-decoded. Other schemes
+    /// are refused, as are hosts other than none or `localhost`, since
+    /// only those name this machine.
+    pub fn to_file_path(&self) -> Result<PathBuf> {
+        if self.scheme != "file" {
+            return Err(Error::UrlParse(format!("'{}' is not a file URL", self)));
+        }
+        if !(self.host.is_empty() || self.host == "localhost") {
+            return Err(Error::UrlParse(format!(
+                "file URL '{}' names a remote host",
+                self
+            )));
+        }
+        Ok(PathBuf::from(percent_decode(&self.path)))
+    }
+
+    /// The host in the form accepted by `ToSocketAddrs`: IPv6 literals
+    /// without brackets.
+    pub fn connect_host(&self) -> &str {
+        self.host
+            .strip_prefix('[')
+            .and_then(|h| h.strip_suffix(']'))
+            .unwrap_or(&self.host)
+    }
+}
+
+impl fmt::Display for Url {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}://", self.scheme)?;
+        if let Some(userinfo) = &self.userinfo {
+            write!(f, "{}@", userinfo)?;
+        }
+        f.write_str(&self.host_header())?;
+        f.write_str(&self.request_target())?;
+        if let Some(fragment) = &self.fragment {
+            write!(f, "#{}", fragment)?;
+        }
+        Ok(())
+    }
+}
+
+/// The well-known port for a scheme.
+pub fn default_port(scheme: &str) -> Option<u16> {
+    match scheme {
+        "http" | "ws" => Some(80),
+        "https" | "wss" => Some(443),
+        _ => None,
+    }
+}
+
+/// Whether `reference` starts with a scheme and is so absolute (RFC 3986
+/// section 4.3): letters, digits, `+`, `-` and `.` after a leading letter,
+/// then a `:` ahead of any `/`, `?` or `#`.
+fn has_scheme(reference: &str) -> bool {
+    let Some(end) = reference.find([':', '/', '?', '#']) else {
+        return false;
+    };
+    let scheme = &reference[..end];
+    reference[end..].starts_with(':')
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"+-.".contains(&b))
+}
+
+fn parse_scheme(scheme: &str) -> Result<String> {
+    let mut chars = scheme.chars();
+    match chars.next() {
+        None => return Err(Error::UrlParse("URL scheme is empty".to_string())),
+        Some(c) if !c.is_ascii_alphabetic() => {
+            return Err(Error::UrlParse(format!(
+                "URL scheme '{}' must start with a letter",
+                scheme
+            )));
+        }
+        _ => {}
+    }
+    if let Some(bad) = chars.find(|c| !(c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')))
+    {
+        return Err(Error::UrlParse(format!(
+            "URL scheme '{}' contains invalid character '{}'",
+            scheme, bad
+        )));
+    }
+    Ok(scheme.to_ascii_lowercase())
+}
+
+fn parse_host_port(hostport: &str, idn: bool) -> Result<(String, Option<u16>)> {
+    if hostport.is_empty() {
+        return Err(Error::UrlParse("URL has an empty host".to_string()));
+    }
+
+    let (host, port) = if let Some(rest) = hostport.strip_prefix('[') {
+        let end = rest.find(']').ok_or_else(|| {
+            Error::UrlParse(format!(
+                "IPv6 host '{}' is missing the closing ']'",
+                hostport
+            ))
+        })?;
+        let literal = &rest[..end];
+        if literal.parse::<std::net::Ipv6Addr>().is_err() {
+            return Err(Error::UrlParse(format!(
+                "'{}' is not a valid IPv6 address",
+                literal
+            )));
+        }
+        let after = &rest[end + 1..];
+        let port = match after {
+            "" => None,
+            _ => Some(after.strip_prefix(':').ok_or_else(|| {
+                Error::UrlParse({
+                    format!("unexpected '{}' after IPv6 host '[{}]'", after, literal)
+                })
+            })?),
+        };
+        (format!("[{}]", literal.to_ascii_lowercase()), port)
+    } else {
+        let (host, port) = match hostport.rsplit_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (hostport, None),
+        };
+        if host.is_empty() {
+            return Err(Error::UrlParse("URL has an empty host".to_string()));
+        }
+        let converted;
+        let host = if idn && !host.is_ascii() {
+            converted = idna::to_ascii(host)?;
+            converted.as_str()
+        } else {
+            host
+        };
+        if let Some(bad) = host.chars().find(|c| !is_reg_name_char(*c)) {
+            return Err(Error::UrlParse(if bad.is_ascii() {
+                format!(
+                    "host '{}' contains invalid character '{}'",
+                    host,
+                    bad.escape_default()
+                )
+            } else {
+                format!(
+                    "host '{}' contains non-ASCII characters (IDN conversion is disabled)",
+                    host
+                )
+            }));
+        }
+        (host.to_ascii_lowercase(), port)
+    };
+
+    let port = match port {
+        None | Some("") => None,
+        Some(port) => {
+            if !port.bytes().all(|b| b.is_ascii_digit()) {
+                return Err(Error::UrlParse(format!("port '{}' is not a number", port)));
+            }
+            Some(port.parse::<u16>().map_err(|_| {
+                Error::UrlParse(format!("port '{}' is out of range (0-65535)", port))
+            })?)
+        }
+    };
+    Ok((host, port))
+}
+
+fn is_reg_name_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, '-' | '.' | '_' | '~' | '%')
+}
+
+/// Collapses `.` and `..` segments in an absolute path.
+fn remove_dot_segments(path: &str) -> String {
+    let mut out: Vec<&str> = Vec::new();
+    let segments: Vec<&str> = path.split('/').skip(1).collect();
+    for (i, segment) in segments.iter().enumerate() {
+        let last = i + 1 == segments.len();
+        match *segment {
+            "." => {
+                if last {
+                    out.push("");
+                }
+            }
+            ".." => {
+                out.pop();
+                if last {
+                    out.push("");
+                }
+            }
+            s => out.push(s),
+        }
+    }
+    format!("/{}", out.join("/"))
+}
+
+/// Decodes `%XX` escapes. Invalid escapes are left untouched and invalid
+/// UTF-8 is replaced rather than rejected.
+pub fn percent_decode(input: &str) -> String {
+    String::from_utf8_lossy(&percent_decode_bytes(input)).into_owned()
+}
+
+/// [`percent_decode`] for text that may not be UTF-8.
+pub fn percent_decode_bytes(input: &str) -> Vec<u8> {
+    let bytes = input.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && i + 2 < bytes.len()
+            && bytes[i + 1].is_ascii_hexdigit()
+            && bytes[i + 2].is_ascii_hexdigit()
+        {
+            out.push(hex_value(bytes[i + 1]) << 4 | hex_value(bytes[i + 2]));
+            i += 3;
+            continue;
+        }
+        out.push(bytes[i]);
+        i += 1;
+    }
+    out
+}
+
+/// Percent-encodes everything except RFC 3986 unreserved characters, so the
+/// result is safe inside any URL component (e.g. a query parameter value).
+pub fn encode_component(input: &str) -> String {
+    let mut out = String::with_capacity(input.len());
+    for b in input.bytes() {
+        if b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~') {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+    }
+    out
+}
+
+fn hex_value(b: u8) -> u8 {
+    match b {
+        b'0'..=b'9' => b - b'0',
+        b'a'..=b'f' => b - b'a' + 10,
+        _ => b - b'A' + 10,
+    }
+}
+
+/// Which component is being encoded; each allows a different set of
+/// characters through unchanged.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub(crate) enum EncodeSet {
+    Path,
+    Query,
+    Fragment,
+}
+
+/// Percent-encodes every byte that may not appear raw in the given
+/// component. Existing valid `%XX` escapes are kept as they are; a stray `%`
+/// becomes `%25`.
+pub(crate) fn encode(input: &str, set: EncodeSet) -> String {
+    let bytes = input.as_bytes();
+    let mut out = String::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        if b == b'%' {
+            let valid_escape = i + 2 < bytes.len()
+                && bytes[i + 1].is_ascii_hexdigit()
+                && bytes[i + 2].is_ascii_hexdigit();
+            if valid_escape {
+                out.push_str(&input[i..i + 3]);
+                i += 3;
+                continue;
+            }
+            out.push_str("%25");
+        } else if is_allowed(b, set) {
+            out.push(b as char);
+        } else {
+            out.push_str(&format!("%{:02X}", b));
+        }
+        i += 1;
+    }
+    out
+}
+
+fn is_allowed(b: u8, set: EncodeSet) -> bool {
+    let unreserved = b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_' | b'~');
+    let sub_delims = matches!(
+        b,
+        b'!' | b'$' | b'&' | b'\'' | b'(' | b')' | b'*' | b'+' | b',' | b';' | b'='
+    );
+    let pchar = unreserved || sub_delims || matches!(b, b':' | b'@');
+    match set {
+        EncodeSet::Path => pchar || b == b'/',
+        EncodeSet::Query | EncodeSet::Fragment => pchar || matches!(b, b'/' | b'?'),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_components() {
+        let url = Url::parse("HTTP://User:Pw@Example.COM:8080/a/b?x=1#frag").unwrap();
+        assert_eq!(url.scheme(), "http");
+        assert_eq!(url.userinfo(), Some("User:Pw"));
+        assert_eq!(url.host(), "example.com");
+        assert_eq!(url.port(), Some(8080));
+        assert_eq!(url.path(), "/a/b");
+        assert_eq!(url.query(), Some("x=1"));
+        assert_eq!(url.fragment(), Some("frag"));
+        assert_eq!(url.host_header(), "example.com:8080");
+        assert_eq!(url.request_target(), "/a/b?x=1");
+    }
+
+    #[test]
+    fn normalizes_path_and_encodes_unsafe_bytes() {
+        assert_eq!(Url::parse("http://h").unwrap().request_target(), "/");
+        assert_eq!(Url::parse("http://h?q").unwrap().request_target(), "/?q");
+        let url = Url::parse("http://h/a b/ü?q=a b&r=%41%zz").unwrap();
+        assert_eq!(url.request_target(), "/a%20b/%C3%BC?q=a%20b&r=%41%25zz");
+    }
+
+    #[test]
+    fn default_port_is_omitted_from_host_header() {
+        let url = Url::parse("http://h:80/").unwrap();
+        assert_eq!(url.host_header(), "h");
+        assert_eq!(url.port_or_default(), 80);
+        assert_eq!(Url::parse("https://h/").unwrap().port_or_default(), 443);
+    }
+
+    #[test]
+    fn parses_ipv6_literals() {
+        let url = Url::parse("http://[::1]:8080/").unwrap();
+        assert_eq!(url.host(), "[::1]");
+        assert_eq!(url.connect_host(), "::1");
+        assert_eq!(url.host_header(), "[::1]:8080");
+    }
+
+    #[test]
+    fn parses_file_urls() {
+        let url = Url::parse("file:///tmp/a%20b.txt").unwrap();
+        assert_eq!(url.host(), "");
+        assert_eq!(url.to_string(), "file:///tmp/a%20b.txt");
+        assert_eq!(url.to_file_path().unwrap(), PathBuf::from("/tmp/a b.txt"));
+        let url = Url::parse("file://localhost/etc/hosts").unwrap();
+        assert_eq!(url.to_file_path().unwrap(), PathBuf::from("/etc/hosts"));
+        assert!(Url::parse("file://elsewhere/etc/hosts")
+            .unwrap()
+            .to_file_path()
+            .is_err());
+        assert!(Url::parse("http://h/").unwrap().to_file_path().is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_urls() {
+        for bad in [
+            "",
+            "example.com",
+            "1http://h/",
+            "ht_tp://h/",
+            "http:///path",
+            "http://h:port/",
+            "http://h:70000/",
+            "http://[::1/",
+            "http://[zz]/",
+            "http://a b/",
+        ] {
+            assert!(Url::parse(bad).is_err(), "{:?} should be rejected", bad);
+        }
+    }
+
+    #[test]
+    fn decodes_userinfo() {
+        let url = Url::parse("http://us%40er:p%3Ass@h/").unwrap();
+        assert_eq!(url.username().as_deref(), Some("us@er"));
+        assert_eq!(url.password().as_deref(), Some("p:ss"));
+        assert_eq!(url.without_userinfo().to_string(), "http://h/");
+        assert_eq!(Url::parse("http://u@h/").unwrap().password(), None);
+        let url = Url::parse("http://u:p@h:80/a?b#c").unwrap();
+        assert_eq!(url.absolute_form(), "http://h/a?b");
+        assert!(url.same_origin(&Url::parse("http://H/other").unwrap()));
+        assert!(!url.same_origin(&Url::parse("http://h:81/a").unwrap()));
+    }
+
+    #[test]
+    fn origins_are_scheme_host_and_port() {
+        let url = Url::parse("http://h/a").unwrap();
+        assert!(url.same_origin(&Url::parse("http://H:80/b?c").unwrap()));
+        assert!(!url.same_origin(&Url::parse("https://h/a").unwrap()));
+        assert!(!url.same_origin(&Url::parse("http://h:8080/a").unwrap()));
+        assert!(!url.same_origin(&Url::parse("http://g/a").unwrap()));
+        let https = Url::parse("https://h/").unwrap();
+        assert!(https.same_origin(&Url::parse("https://h:443/x").unwrap()));
+        assert!(!https.same_origin(&Url::parse("https://h:80/").unwrap()));
+    }
+
+    #[test]
+    fn upgrades_to_tls() {
+        let upgraded = |input: &str| {
+            let mut url = Url::parse(input).unwrap();
+            url.upgrade_to_tls();
+            url.to_string()
+        };
+        assert_eq!(upgraded("http://h:80/a?b"), "https://h/a?b");
+        assert_eq!(upgraded("http://h:8080/"), "https://h:8080/");
+        assert_eq!(upgraded("ws://h/chat"), "wss://h/chat");
+        assert_eq!(upgraded("wss://h:80/"), "wss://h:80/");
+        assert_eq!(upgraded("ftp://h/"), "ftp://h/");
+    }
+
+    #[test]
+    fn appends_query_pairs() {
+        let mut url = Url::parse("http://h/p").unwrap();
+        url.append_query(&format!("q={}", encode_component("a b&c")));
+        url.append_query("x=1");
+        assert_eq!(url.request_target(), "/p?q=a%20b%26c&x=1");
+    }
+
+    #[test]
+    fn converts_idn_hosts() {
+        let url = Url::parse("http://münchen.example/").unwrap();
+        assert_eq!(url.host(), "xn--mnchen-3ya.example");
+        assert!(Url::parse_with_idn("http://münchen.example/", false).is_err());
+    }
+
+    #[test]
+    fn joins_references() {
+        let base = Url::parse("http://u:p@h/a/b/c?q#f").unwrap();
+        let join = |r: &str| base.join(r).unwrap().to_string();
+        assert_eq!(join("d"), "http://h/a/b/d");
+        assert_eq!(join("./d/"), "http://h/a/b/d/");
+        assert_eq!(join("../d"), "http://h/a/d");
+        assert_eq!(join("../../../../d"), "http://h/d");
+        assert_eq!(join("/x?y"), "http://h/x?y");
+        assert_eq!(join("?z"), "http://h/a/b/c?z");
+        assert_eq!(join("#g"), "http://h/a/b/c?q#g");
+        assert_eq!(join("//other:81/p"), "http://other:81/p");
+        assert_eq!(join("https://s.example/"), "https://s.example/");
+        assert_eq!(join("HTTP://s.example/"), "http://s.example/");
+        // A URL in the query or path does not make the reference absolute.
+        assert_eq!(
+            join("/login?next=http://foo/"),
+            "http://h/login?next=http://foo/"
+        );
+        assert_eq!(join("d?u=ws://x"), "http://h/a/b/d?u=ws://x");
+        assert_eq!(join("./x:y"), "http://h/a/b/x:y");
+        assert!(!has_scheme("1a://b"));
+    }
+}
@@ -0,0 +1,85 @@
+//! The `--version` report: what this build is and what it was built with,
+//! for bug reports. Everything but the version number comes from `cfg`, so
+//! it cannot go stale.
+
+/// The cargo features this build was compiled with.
+fn features() -> Vec<&'static str> {
+    let mut features = Vec::new();
+    if cfg!(feature = "brotli") {
+        features.push("brotli");
+    }
+    if cfg!(feature = "compression") {
+        features.push("compression");
+    }
+    if cfg!(feature = "http2") {
+        features.push("http2");
+    }
+    if cfg!(feature = "json") {
+        features.push("json");
+    }
+    if cfg!(feature = "rustls") {
+        features.push("rustls");
+    }
+    if cfg!(feature = "zstd") {
+        features.push("zstd");
+    }
+    features
+}
+
+/// The URL schemes this build can fetch.
+fn protocols() -> Vec<&'static str> {
+    let mut protocols = vec!["file", "http"];
+    if cfg!(feature = "rustls") {
+        protocols.push("https");
+    }
+    protocols.push("ws");
+    if cfg!(feature = "rustls") {
+        protocols.push("wss");
+    }
+    protocols
+}
+
+/// The report, as curl lays out its own: the version on the first line,
+/// then one line each for the TLS backend, protocols and features.
+pub fn report() -> String {
+    let tls = if cfg!(feature = "rustls") {
+        "rustls"
+    } else {
+        "none"
+    };
+    format!(
+        "http_client {} ({}-{})\nTLS: {}\nProtocols: {}\nFeatures: {}\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::ARCH,
+        std::env::consts::OS,
+        tls,
+        protocols().join(" "),
+        features().join(" "),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reports_what_was_compiled_in() {
+        let report = report();
+        assert!(report.starts_with(concat!("http_client ", env!("CARGO_PKG_VERSION"))));
+        let line = |label: &str| {
+            report
+                .lines()
+                .find_map(|l| l.strip_prefix(label))
+                .unwrap()
+                .to_string()
+        };
+        let protocols = line("Protocols: ");
+        let tls = ["https", "wss"].map(|p| protocols.split(' ').any(|q| q == p));
+        assert_eq!(tls, [cfg!(feature = "rustls"); 2]);
+        let features = line("Features: ");
+        let has = |feature| features.split(' ').any(|f| f == feature);
+        assert_eq!(has("http2"), cfg!(feature = "http2"));
+        assert_eq!(has("compression"), cfg!(feature = "compression"));
+        assert_eq!(has("json"), cfg!(feature = "json"));
+    }
+}
@@ -0,0 +1,516 @@
+//! WebSocket client mode for `ws://` and `wss://` URLs (RFC 6455).
+//!
+//! [`session`] performs the HTTP Upgrade handshake and then relays lines
+//! from an input as text messages and incoming messages to an output, until
+//! either side closes. Pings are answered and fragmented messages are
+//! reassembled; extensions and subprotocols are not negotiated.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::base64;
+use crate::error::{Error, Result};
+use crate::headers::HeaderMap;
+use crate::http::{self, Limits};
+use crate::sha1;
+use crate::trace::{Trace, Traced};
+use crate::url::Url;
+
+/// The GUID RFC 6455 appends to the key when computing the accept value.
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+
+/// The largest message accepted unless `--max-filesize` says otherwise.
+pub const DEFAULT_MAX_MESSAGE: u64 = 16 << 20;
+
+/// How long to wait for the server before checking the input again.
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Normal closure, sent when the input ends.
+pub const CLOSE_NORMAL: u16 = 1000;
+
+/// The frame types of RFC 6455 section 5.2.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Opcode {
+    Continuation,
+    Text,
+    Binary,
+    Close,
+    Ping,
+    Pong,
+}
+
+impl Opcode {
+    fn from_u8(value: u8) -> Option<Opcode> {
+        Some(match value {
+            0x0 => Opcode::Continuation,
+            0x1 => Opcode::Text,
+            0x2 => Opcode::Binary,
+            0x8 => Opcode::Close,
+            0x9 => Opcode::Ping,
+            0xA => Opcode::Pong,
+            _ => return None,
+        })
+    }
+
+    fn to_u8(self) -> u8 {
+        match self {
+            Opcode::Continuation => 0x0,
+            Opcode::Text => 0x1,
+            Opcode::Binary => 0x2,
+            Opcode::Close => 0x8,
+            Opcode::Ping => 0x9,
+            Opcode::Pong => 0xA,
+        }
+    }
+
+    fn is_control(self) -> bool {
+        matches!(self, Opcode::Close | Opcode::Ping | Opcode::Pong)
+    }
+}
+
+/// One frame as received.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub fin: bool,
+    pub opcode: Opcode,
+    pub payload: Vec<u8>,
+}
+
+/// How the connection was closed, from the server's close frame.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Close {
+    /// The status code; absent if the close frame had no payload.
+    pub code: Option<u16>,
+    pub reason: String,
+}
+
+impl Close {
+    fn parse(payload: &[u8]) -> Close {
+        match payload {
+            [hi, lo, reason @ ..] => Close {
+                code: Some(u16::from_be_bytes([*hi, *lo])),
+                reason: String::from_utf8_lossy(reason).into_owned(),
+            },
+            _ => Close {
+                code: None,
+                reason: String::new(),
+            },
+        }
+    }
+}
+
+fn protocol_error(msg: impl Into<String>) -> Error {
+    Error::HttpProtocol(msg.into())
+}
+
+/// Unpredictable enough for handshake keys and masks, which only have to
+/// stop intermediaries from recognizing the payload, and for NTLM client
+/// challenges, which only have to differ.
+pub(crate) fn random_u64() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    hasher.write_u128(now.as_nanos());
+    hasher.finish()
+}
+
+/// A fresh `Sec-WebSocket-Key`: 16 random bytes, base64-encoded.
+pub fn handshake_key() -> String {
+    let mut bytes = [0u8; 16];
+    bytes[..8].copy_from_slice(&random_u64().to_le_bytes());
+    bytes[8..].copy_from_slice(&random_u64().to_le_bytes());
+    base64::encode(&bytes)
+}
+
+/// The `Sec-WebSocket-Accept` value a server must answer `key` with.
+pub fn accept_key(key: &str) -> String {
+    base64::encode(&sha1::digest(format!("{}{}", key, GUID).as_bytes()))
+}
+
+/// Writes one unfragmented, masked frame, as clients must send them.
+pub fn write_frame<W: Write>(out: &mut W, opcode: Opcode, payload: &[u8]) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(payload.len() + 14);
+    frame.push(0x80 | opcode.to_u8());
+    match payload.len() {
+        len @ 0..=125 => frame.push(0x80 | len as u8),
+        len @ 126..=0xFFFF => {
+            frame.push(0x80 | 126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(0x80 | 127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    let mask = (random_u64() as u32).to_be_bytes();
+    frame.extend_from_slice(&mask);
+    frame.extend(payload.iter().zip(mask.iter().cycle()).map(|(b, m)| b ^ m));
+    out.write_all(&frame)?;
+    out.flush()
+}
+
+/// Reads one frame sent by a server, refusing payloads over `max` bytes.
+pub fn read_frame<R: Read>(input: &mut R, max: u64) -> Result<Frame> {
+    let mut head = [0u8; 2];
+    input.read_exact(&mut head)?;
+    if head[0] & 0x70 != 0 {
+        return Err(protocol_error("WebSocket frame uses reserved bits"));
+    }
+    let opcode = Opcode::from_u8(head[0] & 0x0F)
+        .ok_or_else(|| protocol_error(format!("unknown WebSocket opcode {:#x}", head[0] & 0x0F)))?;
+    let fin = head[0] & 0x80 != 0;
+    if head[1] & 0x80 != 0 {
+        return Err(protocol_error("server sent a masked WebSocket frame"));
+    }
+    let len = match head[1] & 0x7F {
+        126 => {
+            let mut len = [0u8; 2];
+            input.read_exact(&mut len)?;
+            u16::from_be_bytes(len) as u64
+        }
+        127 => {
+            let mut len = [0u8; 8];
+            input.read_exact(&mut len)?;
+            u64::from_be_bytes(len)
+        }
+        len => len as u64,
+    };
+    if opcode.is_control() && (len > 125 || !fin) {
+        return Err(protocol_error("malformed WebSocket control frame"));
+    }
+    if len > max {
+        return Err(Error::FileSizeExceeded(max));
+    }
+    let mut payload = Vec::new();
+    input.take(len).read_to_end(&mut payload)?;
+    if (payload.len() as u64) < len {
+        return Err(Error::PartialFile {
+            expected: len,
+            received: payload.len() as u64,
+        });
+    }
+    Ok(Frame {
+        fin,
+        opcode,
+        payload,
+    })
+}
+
+/// Reads `input` line by line on a separate thread; `None` marks its end.
+/// The thread is left blocked on the input if the server closes first.
+fn spawn_input<I: BufRead + Send + 'static>(input: I) -> Receiver<Option<String>> {
+    let (tx, rx) = mpsc::channel();
+    thread::spawn(move || {
+        for line in input.lines() {
+            let Ok(line) = line else { break };
+            if tx.send(Some(line)).is_err() {
+                return;
+            }
+        }
+        let _ = tx.send(None);
+    });
+    rx
+}
+
+/// Connects to a `ws://` or `wss://` URL, upgrades, and relays messages
+/// until the connection is closed. `headers` are the ordinary request
+/// headers; the upgrade headers are added here. `wss` goes through the TLS
+/// of `limits`, and no message may be larger than its `max_filesize`.
+/// Returns the server's close frame.
+pub fn session<I: BufRead + Send + 'static>(
+    url: &Url,
+    headers: &HeaderMap,
+    verbose: bool,
+    trace: Option<&Trace>,
+    input: I,
+    output: &mut dyn Write,
+    limits: &Limits,
+) -> Result<Close> {
+    let limits = limits.for_url(url)?;
+    let host = url.connect_host();
+    let port = url.port_or_default();
+    let tcp = http::connect(host, port)?;
+    // A clone of the socket under the TLS layer still sets its timeouts.
+    let control = tcp.try_clone()?;
+    let stream = http::secure(host, tcp, &limits)?;
+    let mut stream = BufReader::new(Traced::new(stream, trace));
+
+    let key = handshake_key();
+    let mut headers = headers.clone();
+    headers.insert("Connection", "Upgrade");
+    headers.insert("Upgrade", "websocket");
+    headers.insert("Sec-WebSocket-Version", "13");
+    headers.insert("Sec-WebSocket-Key", key.as_str());
+    http::write_request(
+        stream.get_mut(),
+        "GET",
+        &url.request_target(),
+        &headers,
+        None,
+        verbose,
+    )?;
+
+    let (status, response) = http::read_final_head(&mut stream, verbose, false)?;
+    if status.code != 101 {
+        return Err(protocol_error(format!(
+            "server refused the WebSocket upgrade: {}",
+            status
+        )));
+    }
+    let upgraded = response
+        .get("Upgrade")
+        .is_some_and(|v| v.eq_ignore_ascii_case("websocket"));
+    if !upgraded || response.get("Sec-WebSocket-Accept") != Some(accept_key(&key).as_str()) {
+        return Err(protocol_error("invalid WebSocket handshake response"));
+    }
+
+    let max = limits.max_filesize.unwrap_or(DEFAULT_MAX_MESSAGE);
+    let lines = spawn_input(input);
+    let mut input_open = true;
+    let mut message: Option<(Opcode, Vec<u8>)> = None;
+    loop {
+        while input_open {
+            match lines.try_recv() {
+                Ok(Some(line)) => write_frame(stream.get_mut(), Opcode::Text, line.as_bytes())?,
+                Ok(None) | Err(TryRecvError::Disconnected) => {
+                    write_frame(stream.get_mut(), Opcode::Close, &CLOSE_NORMAL.to_be_bytes())?;
+                    input_open = false;
+                }
+                Err(TryRecvError::Empty) => break,
+            }
+        }
+
+        // Wait briefly for the server so the input is polled regularly,
+        // then read a whole frame without a timeout.
+        control.set_read_timeout(input_open.then_some(POLL_INTERVAL))?;
+        match stream.fill_buf() {
+            Ok([]) => {
+                return Err(protocol_error(
+                    "connection closed without a WebSocket close frame",
+                ))
+            }
+            Ok(_) => {}
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                continue
+            }
+            Err(e) => return Err(e.into()),
+        }
+        control.set_read_timeout(None)?;
+        let frame = read_frame(&mut stream, max)?;
+
+        match frame.opcode {
+            Opcode::Ping => write_frame(stream.get_mut(), Opcode::Pong, &frame.payload)?,
+            Opcode::Pong => {}
+            Opcode::Close => {
+                if input_open {
+                    // Echo the status code back, as RFC 6455 section 5.5.1 asks.
+                    let echo = frame.payload.get(..2).unwrap_or_default();
+                    write_frame(stream.get_mut(), Opcode::Close, echo)?;
+                }
+                return Ok(Close::parse(&frame.payload));
+            }
+            Opcode::Text | Opcode::Binary | Opcode::Continuation => {
+                let (opcode, mut data) = match (frame.opcode, message.take()) {
+                    (Opcode::Continuation, Some(partial)) => partial,
+                    (Opcode::Continuation, None) => {
+                        return Err(protocol_error("unexpected WebSocket continuation frame"))
+                    }
+                    (_, Some(_)) => {
+                        return Err(protocol_error("WebSocket message interrupted by another"))
+                    }
+                    (opcode, None) => (opcode, Vec::new()),
+                };
+                if data.len() as u64 + frame.payload.len() as u64 > max {
+                    return Err(Error::FileSizeExceeded(max));
+                }
+                data.extend_from_slice(&frame.payload);
+                if !frame.fin {
+                    message = Some((opcode, data));
+                    continue;
+                }
+                output.write_all(&data)?;
+                if opcode == Opcode::Text {
+                    output.write_all(b"\n")?;
+                }
+                output.flush()?;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tls::{Connector, Stream};
+    use std::net::{TcpListener, TcpStream};
+    use std::sync::{Arc, Mutex};
+
+    #[test]
+    fn computes_the_rfc_accept_key() {
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+        assert_eq!(handshake_key().len(), 24);
+    }
+
+    /// Reads one masked client frame, as a server would: first byte and
+    /// unmasked payload.
+    fn read_client_frame<R: Read>(input: &mut R) -> (u8, Vec<u8>) {
+        let mut head = [0u8; 2];
+        input.read_exact(&mut head).unwrap();
+        assert_eq!(head[1] & 0x80, 0x80, "client frames are masked");
+        let len = match head[1] & 0x7F {
+            126 => {
+                let mut len = [0u8; 2];
+                input.read_exact(&mut len).unwrap();
+                u16::from_be_bytes(len) as usize
+            }
+            len => len as usize,
+        };
+        let mut mask = [0u8; 4];
+        input.read_exact(&mut mask).unwrap();
+        let mut payload = vec![0u8; len];
+        input.read_exact(&mut payload).unwrap();
+        for (b, m) in payload.iter_mut().zip(mask.iter().cycle()) {
+            *b ^= m;
+        }
+        (head[0], payload)
+    }
+
+    #[test]
+    fn frames_round_trip() {
+        for len in [0, 5, 125, 126, 300] {
+            let payload = vec![b'x'; len];
+            let mut wire = Vec::new();
+            write_frame(&mut wire, Opcode::Binary, &payload).unwrap();
+            let (first, plain) = read_client_frame(&mut wire.as_slice());
+            assert_eq!(first, 0x82);
+            assert_eq!(plain, payload);
+        }
+    }
+
+    #[test]
+    fn reads_server_frames() {
+        let mut wire: &[u8] = b"\x01\x03hel\x80\x02lo\x88\x02\x03\xe8";
+        let first = read_frame(&mut wire, 100).unwrap();
+        assert_eq!((first.fin, first.opcode), (false, Opcode::Text));
+        let second = read_frame(&mut wire, 100).unwrap();
+        assert_eq!((second.fin, second.opcode), (true, Opcode::Continuation));
+        assert_eq!(second.payload, b"lo");
+        let close = read_frame(&mut wire, 100).unwrap();
+        assert_eq!(Close::parse(&close.payload).code, Some(1000));
+    }
+
+    #[test]
+    fn rejects_bad_server_frames() {
+        assert!(read_frame(&mut &b"\x81\x82abcdxy"[..], 100).is_err());
+        assert!(read_frame(&mut &b"\x83\x00"[..], 100).is_err());
+        assert!(read_frame(&mut &b"\x09\x00"[..], 100).is_err());
+        assert!(read_frame(&mut &b"\x82\x05abcde"[..], 4).is_err());
+    }
+
+    /// A plain TCP "TLS" backend that records the hosts it was asked for.
+    #[derive(Debug, Default)]
+    struct Plain {
+        hosts: Mutex<Vec<String>>,
+    }
+
+    impl Connector for Plain {
+        fn connect(&self, host: &str, tcp: TcpStream) -> Result<Box<dyn Stream>> {
+            self.hosts.lock().unwrap().push(host.to_string());
+            Ok(Box::new(tcp))
+        }
+    }
+
+    /// Runs a session with `scheme` against a server that pings, sends two
+    /// messages and closes once the client has.
+    fn relay(scheme: &str, limits: &Limits) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let (socket, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(socket.try_clone().unwrap());
+            let head = crate::response::read_head(&mut reader).unwrap();
+            let head = String::from_utf8(head).unwrap();
+            let key = head
+                .lines()
+                .find_map(|l| l.strip_prefix("Sec-WebSocket-Key: "))
+                .unwrap();
+            let mut socket = socket;
+            write!(
+                socket,
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\n\
+                 Connection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+                accept_key(key)
+            )
+            .unwrap();
+            socket
+                .write_all(b"\x89\x01p\x81\x02hi\x82\x03\x00\x01\x02")
+                .unwrap();
+            // The text line, the pong and the closing handshake may arrive
+            // in any order.
+            let mut received = Vec::new();
+            while !received.iter().any(|(op, _)| *op == 0x88) {
+                received.push(read_client_frame(&mut reader));
+            }
+            socket.write_all(b"\x88\x05\x03\xe8bye").unwrap();
+            received.sort();
+            assert_eq!(
+                received,
+                [
+                    (0x81, b"hello".to_vec()),
+                    (0x88, b"\x03\xe8".to_vec()),
+                    (0x8A, b"p".to_vec())
+                ]
+            );
+        });
+
+        let url = Url::parse(&format!("{}://127.0.0.1:{}/chat", scheme, port)).unwrap();
+        let headers = http::default_headers(&url.host_header(), None);
+        let mut output = Vec::new();
+        let close = session(
+            &url,
+            &headers,
+            false,
+            None,
+            io::Cursor::new(b"hello\n".to_vec()),
+            &mut output,
+            limits,
+        )
+        .unwrap();
+        server.join().unwrap();
+        assert_eq!(output, b"hi\n\x00\x01\x02");
+        assert_eq!(close.code, Some(1000));
+        assert_eq!(close.reason, "bye");
+    }
+
+    #[test]
+    fn session_relays_messages_until_close() {
+        relay("ws", &Limits::default());
+    }
+
+    #[test]
+    fn wss_goes_through_the_connector() {
+        let plain = Arc::new(Plain::default());
+        let limits = Limits {
+            tls: Some(plain.clone()),
+            ..Limits::default()
+        };
+        relay("wss", &limits);
+        assert_eq!(*plain.hosts.lock().unwrap(), ["127.0.0.1"]);
+        // Plain ws ignores the connector.
+        relay("ws", &limits);
+        assert_eq!(plain.hosts.lock().unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,191 @@
+//! `-w`/`--write-out`: text written to stdout after the transfer, with
+//! curl's `%{name}` variables filled in.
+//!
+//! `%%` is a literal `%`, and `\n`, `\r`, `\t` and `\\` are the usual
+//! escapes. An unknown variable is warned about and left out, as in curl.
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use http_client::date;
+use http_client::error::{Error, Result};
+use http_client::response::Response;
+
+use crate::history::Transfer;
+use crate::i18n::{tr, Msg};
+
+/// The variables [`variable`] knows, listed when another is asked for.
+pub const VARIABLES: &[&str] = &[
+    "content_type",
+    "errormsg",
+    "exitcode",
+    "http_code",
+    "http_version",
+    "local_ip",
+    "local_port",
+    "num_redirects",
+    "num_retries",
+    "redirect_url",
+    "remote_ip",
+    "remote_port",
+    "response_code",
+    "retry_after",
+    "size_download",
+    "size_header",
+    "size_upload",
+    "time_connect",
+    "time_namelookup",
+    "time_redirect",
+    "time_starttransfer",
+    "time_total",
+    "url_effective",
+];
+
+/// Fills in `format`, looking each variable up with `lookup`.
+pub fn render(format: &str, lookup: impl Fn(&str) -> Option<String>) -> String {
+    let mut out = String::new();
+    let mut rest = format;
+    while let Some(i) = rest.find(['%', '\\']) {
+        out.push_str(&rest[..i]);
+        let tail = &rest[i..];
+        let (text, len) = match tail.as_bytes().get(1) {
+            Some(b'%') if tail.starts_with('%') => ("%".to_string(), 2),
+            Some(b'{') if tail.starts_with('%') => match tail.find('}') {
+                Some(end) => {
+                    let name = &tail[2..end];
+                    let value = lookup(name).unwrap_or_else(|| {
+                        eprintln!(
+                            "http_client: {}",
+                            tr(Msg::UnsupportedChoice("--write-out", name, VARIABLES))
+                        );
+                        String::new()
+                    });
+                    (value, end + 1)
+                }
+                None => (tail.to_string(), tail.len()),
+            },
+            Some(b'n') if tail.starts_with('\\') => ("\n".to_string(), 2),
+            Some(b'r') if tail.starts_with('\\') => ("\r".to_string(), 2),
+            Some(b't') if tail.starts_with('\\') => ("\t".to_string(), 2),
+            Some(b'\\') if tail.starts_with('\\') => ("\\".to_string(), 2),
+            _ => (tail[..1].to_string(), 1),
+        };
+        out.push_str(&text);
+        rest = &tail[len..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// Seconds with microseconds, as curl writes its `time_*` variables.
+fn seconds(duration: Option<Duration>) -> String {
+    format!("{:.6}", duration.unwrap_or_default().as_secs_f64())
+}
+
+/// The value of variable `name` for a finished transfer.
+pub fn variable(name: &str, transfer: &Transfer, result: &Result<Response>) -> Option<String> {
+    let response = result.as_ref().ok();
+    let metrics = &transfer.metrics;
+    let ip = |addr: Option<SocketAddr>| addr.map_or(String::new(), |a| a.ip().to_string());
+    let port = |addr: Option<SocketAddr>| addr.map_or(String::new(), |a| a.port().to_string());
+    let value = match name {
+        "content_type" => response
+            .and_then(|r| r.headers.get("Content-Type"))
+            .unwrap_or_default()
+            .to_string(),
+        "errormsg" => result
+            .as_ref()
+            .err()
+            .map_or(String::new(), Error::to_string),
+        "exitcode" => result
+            .as_ref()
+            .err()
+            .map_or(0, Error::exit_code)
+            .to_string(),
+        "http_code" | "response_code" => {
+            format!("{:03}", response.map_or(0, |r| r.status.code))
+        }
+        "http_version" => response
+            .map(|r| r.status.version.trim_start_matches("HTTP/").to_string())
+            .unwrap_or_default(),
+        "local_ip" => ip(metrics.local_addr()),
+        "local_port" => port(metrics.local_addr()),
+        "num_redirects" => transfer.hops.len().to_string(),
+        "num_retries" => transfer.retries.to_string(),
+        // Where a redirect that was not followed would have gone.
+        "redirect_url" => response
+            .and_then(|r| r.headers.get("Location"))
+            .and_then(|location| transfer.url.join(location).ok())
+            .map_or(String::new(), |url| url.without_userinfo().to_string()),
+        "remote_ip" => ip(metrics.remote_addr()),
+        // The seconds a 429 or 503 asked for, for scripts with their own
+        // backoff.
+        "retry_after" => response
+            .and_then(|r| r.retry_after(date::now()))
+            .map_or(String::new(), |wait| wait.as_secs().to_string()),
+        "remote_port" => port(metrics.remote_addr()),
+        "size_download" => response.map_or(0, |r| transfer.body_size(r)).to_string(),
+        "size_header" => response.map_or(0, |r| r.head_text().len()).to_string(),
+        "size_upload" => transfer.upload.to_string(),
+        "time_connect" => seconds(metrics.connect()),
+        "time_namelookup" => seconds(metrics.name_lookup()),
+        "time_redirect" => seconds(transfer.hops.last().map(|hop| hop.at)),
+        "time_starttransfer" => seconds(metrics.first_byte()),
+        "time_total" => seconds(Some(transfer.total)),
+        "url_effective" => transfer.url.without_userinfo().to_string(),
+        _ => return None,
+    };
+    Some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use http_client::metrics::Metrics;
+    use http_client::url::Url;
+
+    use crate::history::Hop;
+
+    #[test]
+    fn renders_variables_and_escapes() {
+        let lookup = |name: &str| (name == "x").then(|| "1".to_string());
+        assert_eq!(render("a%{x}b\\n%%\\t\\\\", lookup), "a1b\n%\t\\");
+        assert_eq!(render("100% %{x", lookup), "100% %{x");
+        assert_eq!(render("[%{nope}]", lookup), "[]");
+    }
+
+    #[test]
+    fn describes_redirected_and_failed_transfers() {
+        let url = |s| Url::parse(s).unwrap();
+        let transfer = Transfer {
+            url: url("http://u:p@b.test/end"),
+            hops: vec![Hop::new(
+                &url("http://a.test/"),
+                301,
+                &url("http://b.test/end"),
+                Duration::from_millis(1500),
+            )],
+            metrics: Metrics::new(),
+            total: Duration::from_secs(2),
+            upload: 3,
+            retries: 2,
+            saved: None,
+        };
+        let failed: Result<Response> = Err(Error::TooManyRedirects(1));
+        let value = |name| variable(name, &transfer, &failed).unwrap();
+        assert_eq!(value("url_effective"), "http://b.test/end");
+        assert_eq!(value("num_redirects"), "1");
+        assert_eq!(value("num_retries"), "2");
+        assert_eq!(value("retry_after"), "");
+        assert_eq!(value("http_code"), "000");
+        assert_eq!(value("exitcode"), "47");
+        assert_eq!(value("time_redirect"), "1.500000");
+        assert_eq!(value("time_total"), "2.000000");
+        assert_eq!(value("size_upload"), "3");
+        assert_eq!(value("remote_ip"), "");
+        assert_eq!(variable("nope", &transfer, &failed), None);
+        for name in VARIABLES {
+            assert!(variable(name, &transfer, &failed).is_some(), "{}", name);
+        }
+    }
+}
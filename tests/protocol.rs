@@ -0,0 +1,821 @@
+//! End-to-end protocol tests against the server in `support`.
+
+mod support;
+
+use std::io::Read;
+use std::process::Command;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use http_client::error::Error;
+#[cfg(feature = "compression")]
+use http_client::gzip;
+use http_client::headers::HeaderMap;
+use http_client::http::{self, Exchange, Limits, PersistentConnection};
+use http_client::request::{Method, Request};
+use http_client::response::Response;
+use http_client::speed::SpeedLimit;
+use support::{Reply, Server};
+
+fn get(server: &Server, path: &str, limits: &Limits) -> Result<Response, Error> {
+    let host = format!("127.0.0.1:{}", server.port);
+    let headers = http::default_headers(&host, None);
+    http::send_http_request(&Exchange::new(
+        "127.0.0.1",
+        server.port,
+        "GET",
+        path,
+        &headers,
+        limits,
+    ))
+}
+
+fn client(args: &[&str]) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_http_client"))
+        .args(args)
+        .output()
+        .expect("run the client")
+}
+
+#[test]
+fn reads_chunked_bodies() {
+    let server = Server::start(|_| {
+        Reply::raw(
+            "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n\
+             5;ext=1\r\nhello\r\n1\r\n \r\n5\r\nworld\r\n0\r\nX-Sum: 1\r\n\r\n",
+        )
+    });
+    let response = get(&server, "/chunked", &Limits::default()).unwrap();
+    assert_eq!(response.body, b"hello world");
+}
+
+#[test]
+fn streams_bodies_through_the_request_api() {
+    let server = Server::start(|_| {
+        Reply::raw(
+            &b"HTTP/1.1 201 Created\r\nContent-Type: text/plain; charset=latin1\r\n\
+             Transfer-Encoding: chunked\r\n\r\n3\r\ncaf\r\n1\r\n\xe9\r\n0\r\n\r\n"[..],
+        )
+    });
+    let request = Request::builder()
+        .method(Method::Post)
+        .url(&server.url("/items"))
+        .body("{}")
+        .build()
+        .unwrap();
+    let mut streaming = request.stream(&Limits::default()).unwrap();
+    assert_eq!(streaming.status(), 201);
+    let mut first = [0; 3];
+    streaming.read_exact(&mut first).unwrap();
+    assert_eq!(&first, b"caf");
+    let response = request.send().unwrap();
+    assert_eq!(response.status(), 201);
+    assert_eq!(response.text(), "caf\u{e9}");
+}
+
+#[test]
+fn follows_redirects() {
+    let server = Server::start(|request| match request.target.as_str() {
+        "/start" => {
+            Reply::raw("HTTP/1.1 302 Found\r\nLocation: /next\r\nContent-Length: 0\r\n\r\n")
+        }
+        "/next" => Reply::raw(
+            "HTTP/1.1 307 Temporary Redirect\r\nLocation: /end\r\nContent-Length: 0\r\n\r\n",
+        ),
+        "/end" => Reply::ok(&format!("{} done", request.method)),
+        _ => Reply::raw("HTTP/1.1 301 Moved\r\nLocation: /loop\r\nContent-Length: 0\r\n\r\n"),
+    });
+    let output = client(&["-L", "-d", "x", &server.url("/start")]);
+    assert!(output.status.success());
+    // The 302 turned the POST into a GET, which the 307 kept.
+    assert_eq!(output.stdout, b"GET done");
+
+    let output = client(&["-L", "--max-redirs", "3", &server.url("/loop")]);
+    assert_eq!(output.status.code(), Some(47));
+}
+
+#[test]
+fn times_out_a_stalled_transfer() {
+    let server = Server::start(|_| {
+        thread::sleep(Duration::from_secs(4));
+        Reply::ok("late")
+    });
+    let limits = Limits {
+        speed_limit: Some(SpeedLimit {
+            bytes_per_sec: 1,
+            time: Duration::from_secs(1),
+        }),
+        ..Limits::default()
+    };
+    let start = Instant::now();
+    let result = get(&server, "/", &limits);
+    assert!(matches!(result, Err(Error::Timeout(_))), "{:?}", result);
+    assert!(start.elapsed() < Duration::from_secs(4));
+}
+
+#[test]
+fn keeps_connections_alive() {
+    let server = Server::start(|request| Reply::ok(&request.target));
+    let mut conn = PersistentConnection::new("127.0.0.1", server.port);
+    let mut headers = HeaderMap::new();
+    headers.append("Host", format!("127.0.0.1:{}", server.port));
+    for path in ["/one", "/two", "/three"] {
+        let response = conn
+            .send("GET", path, &headers, None, &Limits::default())
+            .unwrap();
+        assert_eq!(response.body, path.as_bytes());
+    }
+    assert_eq!(server.connections(), 1);
+}
+
+#[test]
+fn rejects_malformed_responses() {
+    let server = Server::start(|request| match request.target.as_str() {
+        "/garbage" => Reply::raw("SMTP ready\r\n\r\n").and_close(),
+        "/bad-chunk" => {
+            Reply::raw("HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\nzz\r\n").and_close()
+        }
+        "/short" => Reply::raw("HTTP/1.1 200 OK\r\nContent-Length: 10\r\n\r\nabc").and_close(),
+        "/close-delimited" => Reply::raw("HTTP/1.1 200 OK\r\n\r\nuntil close").and_close(),
+        _ => Reply::raw("HTTP/1.1 200 OK\r\nBad Header\r\n\r\n").and_close(),
+    });
+    let limits = Limits::default();
+    assert!(matches!(
+        get(&server, "/garbage", &limits),
+        Err(Error::HttpProtocol(_))
+    ));
+    assert!(get(&server, "/bad-chunk", &limits).is_err());
+    assert!(matches!(
+        get(&server, "/short", &limits),
+        Err(Error::PartialFile {
+            expected: 10,
+            received: 3
+        })
+    ));
+    assert!(get(&server, "/bad-header", &limits).is_err());
+    let response = get(&server, "/close-delimited", &limits).unwrap();
+    assert_eq!(response.body, b"until close");
+}
+
+#[test]
+fn warns_when_http3_falls_back() {
+    let server = Server::start(|_| Reply::ok("over h1"));
+    let output = client(&["--lang", "en", "--http3", &server.url("/")]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"over h1");
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(
+        stderr.contains("warning: HTTP/3 is not implemented"),
+        "{}",
+        stderr
+    );
+
+    let output = client(&["--http3-only", &server.url("/")]);
+    assert!(!output.status.success());
+}
+
+#[test]
+fn keeps_credentials_to_the_origin_they_were_meant_for() {
+    let echo = |request: &support::Request| {
+        Reply::ok(&format!(
+            "auth={} cookie={}",
+            request.header("Authorization").unwrap_or("-"),
+            request.header("Cookie").unwrap_or("-"),
+        ))
+    };
+    // A different port is a different origin.
+    let other = Server::start(echo);
+    let away = other.url("/echo");
+    let server = Server::start(move |request| match request.target.as_str() {
+        "/same" => Reply::raw("HTTP/1.1 302 Found\r\nLocation: /echo\r\nContent-Length: 0\r\n\r\n"),
+        "/away" => Reply::raw(format!(
+            "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\n\r\n",
+            away
+        )),
+        _ => echo(request),
+    });
+    let run = |extra: &[&str], path: &str| {
+        let url = server.url(path);
+        let mut args = vec!["-L"];
+        args.extend_from_slice(extra);
+        args.push(&url);
+        let output = Command::new(env!("CARGO_BIN_EXE_http_client"))
+            .args(&args)
+            .env("AWS_ACCESS_KEY_ID", "AKIDEXAMPLE")
+            .env("AWS_SECRET_ACCESS_KEY", "secret")
+            .output()
+            .expect("run the client");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert!(output.status.success(), "{:?}: {}", extra, stderr);
+        String::from_utf8(output.stdout).unwrap()
+    };
+    let headers = ["-H", "Authorization: Basic dTpw", "-H", "Cookie: k=v"];
+
+    assert_eq!(run(&headers, "/same"), "auth=Basic dTpw cookie=k=v");
+    assert_eq!(run(&headers, "/away"), "auth=- cookie=-");
+    let trusted = [&headers[..], &["--location-trusted"]].concat();
+    assert_eq!(run(&trusted, "/away"), "auth=Basic dTpw cookie=k=v");
+
+    let bearer = ["--oauth2-bearer", "tok"];
+    assert_eq!(run(&bearer, "/same"), "auth=Bearer tok cookie=-");
+    assert_eq!(run(&bearer, "/away"), "auth=- cookie=-");
+    let trusted = [&bearer[..], &["--location-trusted"]].concat();
+    assert_eq!(run(&trusted, "/away"), "auth=Bearer tok cookie=-");
+
+    let aws = ["--aws-sigv4", "aws:amz:us-east-1:s3"];
+    assert!(run(&aws, "/same").starts_with("auth=AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/"));
+    assert_eq!(run(&aws, "/away"), "auth=- cookie=-");
+    let trusted = [&aws[..], &["--location-trusted"]].concat();
+    assert!(run(&trusted, "/away").starts_with("auth=AWS4-HMAC-SHA256 "));
+}
+
+#[test]
+fn puts_output_files_under_the_output_dir() {
+    let server = Server::start(|_| Reply::ok("saved"));
+    let dir = std::env::temp_dir().join(format!("http_client-outdir-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let dir_arg = dir.to_str().unwrap();
+
+    // Without --create-dirs a missing directory is a file error.
+    let output = client(&["--output-dir", dir_arg, "-o", "a/b.txt", &server.url("/")]);
+    assert_eq!(output.status.code(), Some(23));
+
+    let output = client(&[
+        "--output-dir",
+        dir_arg,
+        "--create-dirs",
+        "-o",
+        "a/b.txt",
+        &server.url("/"),
+    ]);
+    assert!(output.status.success());
+    assert_eq!(std::fs::read(dir.join("a/b.txt")).unwrap(), b"saved");
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn tees_the_output_to_a_file() {
+    let server = Server::start(|_| Reply::ok("both places"));
+    let path = std::env::temp_dir().join(format!("http_client-tee-{}", std::process::id()));
+    let output = client(&["--tee", path.to_str().unwrap(), &server.url("/")]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"both places");
+    assert_eq!(std::fs::read(&path).unwrap(), b"both places");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn sends_the_request_target_as_given() {
+    let server = Server::start(|request| match request.target.as_str() {
+        "*" => Reply::raw("HTTP/1.1 302 Found\r\nLocation: /next\r\nContent-Length: 0\r\n\r\n"),
+        target => Reply::ok(target),
+    });
+    let output = client(&["--request-target", "/other?q", &server.url("/path")]);
+    assert_eq!(output.stdout, b"/other?q");
+    // A redirect goes to where it points.
+    let output = client(&[
+        "-L",
+        "-X",
+        "OPTIONS",
+        "--request-target",
+        "*",
+        &server.url("/"),
+    ]);
+    assert_eq!(output.stdout, b"/next");
+}
+
+#[test]
+fn shows_what_options_allows() {
+    let server = Server::start(|_| {
+        Reply::raw(
+            "HTTP/1.1 204 No Content\r\nAllow: GET, HEAD\r\nAllow: PURGE\r\n\
+             Access-Control-Allow-Methods: GET\r\n\r\n",
+        )
+    });
+    let output = client(&["-X", "OPTIONS", &server.url("/")]);
+    assert!(output.status.success());
+    assert_eq!(
+        output.stdout,
+        b"Allow: GET, HEAD\nAllow: PURGE\nAccess-Control-Allow-Methods: GET\n"
+    );
+}
+
+#[test]
+fn dry_run_prints_the_request_without_connecting() {
+    let server = Server::start(|_| Reply::ok("sent"));
+    let url = server.url("/a?b");
+    let output = client(&["--dry-run", "-A", "t", "-d", "x=1", &url]);
+    assert!(output.status.success());
+    let host = format!("127.0.0.1:{}", server.port);
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        format!(
+            "POST /a?b HTTP/1.1\r\nHost: {}\r\nUser-Agent: t\r\nAccept: */*\r\n\
+             Content-Length: 3\r\nConnection: close\r\n\r\nx=1",
+            host
+        )
+    );
+    assert_eq!(server.connections(), 0);
+}
+
+#[test]
+fn signs_uploads_without_hashing_them() {
+    let server = Server::start(|request| {
+        Reply::ok(&format!(
+            "{} {} {}|{}|",
+            request.method,
+            request.target,
+            request.header("X-Amz-Content-Sha256").unwrap_or("-"),
+            request.header("Authorization").unwrap_or("-"),
+        ))
+    });
+    let path = temp_file("upload.txt", "file body");
+    let output = Command::new(env!("CARGO_BIN_EXE_http_client"))
+        .args(["--aws-sigv4", "aws:amz:us-east-1:s3", "-T", &path])
+        .args(["-w", "%{http_code}", &server.url("/bucket/key")])
+        .env("AWS_ACCESS_KEY_ID", "AKIDEXAMPLE")
+        .env("AWS_SECRET_ACCESS_KEY", "secret")
+        .output()
+        .expect("run the client");
+    std::fs::remove_file(&path).unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    assert!(output.status.success(), "{}", stdout);
+    let (echoed, code) = stdout.rsplit_once('|').unwrap();
+    assert!(
+        echoed.starts_with(
+            "PUT /bucket/key UNSIGNED-PAYLOAD|AWS4-HMAC-SHA256 Credential=AKIDEXAMPLE/"
+        ),
+        "{}",
+        echoed
+    );
+    assert!(echoed.contains("SignedHeaders=host;x-amz-content-sha256;x-amz-date,"));
+    assert_eq!(code, "200");
+}
+
+#[test]
+#[cfg(feature = "compression")]
+fn dry_run_shows_a_compressed_body() {
+    let server = Server::start(|_| Reply::ok("sent"));
+    let output = client(&[
+        "--dry-run",
+        "--compress-request",
+        "-d",
+        "x=1",
+        &server.url("/"),
+    ]);
+    assert!(output.status.success());
+    let end = output
+        .stdout
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .unwrap()
+        + 4;
+    let (head, body) = output.stdout.split_at(end);
+    let head = String::from_utf8_lossy(head);
+    assert!(head.contains("\r\nContent-Encoding: gzip\r\n"), "{}", head);
+    let length = format!("\r\nContent-Length: {}\r\n", body.len());
+    assert!(head.contains(&length), "{}", head);
+    assert_eq!(gzip::decompress(body, 1024).unwrap(), b"x=1");
+    assert_eq!(server.connections(), 0);
+}
+
+#[test]
+#[cfg(feature = "compression")]
+fn compressed_request_bodies_follow_redirects() {
+    let server = Server::start(|request| match request.target.as_str() {
+        "/old" => Reply::raw(
+            "HTTP/1.1 307 Temporary Redirect\r\nLocation: /new\r\nContent-Length: 0\r\n\r\n",
+        ),
+        _ => {
+            let body = gzip::decompress(&request.body, 1024).unwrap_or_default();
+            let length = request.body.len().to_string();
+            Reply::ok(&format!(
+                "{} {} {}",
+                request.header("Content-Encoding").unwrap_or("-"),
+                request.header("Content-Length") == Some(length.as_str()),
+                String::from_utf8_lossy(&body)
+            ))
+        }
+    });
+    let output = client(&["-L", "--compress-request", "-d", "x=1", &server.url("/old")]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"gzip true x=1");
+    assert_eq!(server.connections(), 2);
+}
+
+#[test]
+#[cfg(feature = "rustls")]
+fn sse_and_bench_speak_tls_to_https_urls() {
+    // A plain-text server answers the TLS handshake with garbage, which
+    // shows the client tried one rather than refusing the scheme.
+    let server = Server::start(|_| Reply::ok("not tls").and_close());
+    let url = server.url("/").replacen("http://", "https://", 1);
+    let output = client(&["--sse", &url]);
+    assert_eq!(output.status.code(), Some(35));
+    let output = client(&["--bench", "--requests", "2", &url]);
+    assert_eq!(output.status.code(), Some(35));
+    assert_eq!(server.connections(), 3);
+}
+
+#[test]
+#[cfg(not(feature = "compression"))]
+fn refuses_compression_without_the_feature() {
+    let server = Server::start(|_| Reply::ok("plain"));
+    for flag in ["--compressed", "--compress-request"] {
+        let output = client(&[flag, "-d", "x", &server.url("/")]);
+        assert_eq!(output.status.code(), Some(1), "{}", flag);
+    }
+    assert_eq!(server.connections(), 0);
+}
+
+#[test]
+#[cfg(not(feature = "json"))]
+fn refuses_json_output_without_the_feature() {
+    let server = Server::start(|_| Reply::ok("{}"));
+    for args in [&["--jq", ".a"][..], &["--pretty"]] {
+        let output = client(&[args, &[server.url("/").as_str()]].concat());
+        assert_eq!(output.status.code(), Some(1), "{:?}", args);
+    }
+    assert_eq!(server.connections(), 0);
+}
+
+#[test]
+fn prints_the_head_before_a_slow_body() {
+    let head = "HTTP/1.1 200 OK\r\nContent-Length: 4\r\n\r\n";
+    let server =
+        Server::start(move |_| Reply::raw(head).then_after(Duration::from_secs(2), "slow"));
+    let start = Instant::now();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_http_client"))
+        .args(["-i", &server.url("/")])
+        .stdout(std::process::Stdio::piped())
+        .spawn()
+        .expect("run the client");
+    let mut stdout = child.stdout.take().unwrap();
+    let mut early = vec![0; head.len()];
+    stdout.read_exact(&mut early).unwrap();
+    assert!(start.elapsed() < Duration::from_secs(2));
+    assert_eq!(early, head.as_bytes());
+    // The rest is the body alone, not the head again.
+    let mut rest = Vec::new();
+    stdout.read_to_end(&mut rest).unwrap();
+    assert_eq!(rest, b"slow");
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn stops_quietly_when_the_reader_goes_away() {
+    let server = Server::start(|_| {
+        let first = format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: 200000\r\n\r\n{}",
+            "a".repeat(100000)
+        );
+        Reply::raw(first).then_after(Duration::from_secs(1), "b".repeat(100000))
+    });
+    let mut child = Command::new(env!("CARGO_BIN_EXE_http_client"))
+        .args(["--retry", "3", &server.url("/")])
+        .stdout(std::process::Stdio::piped())
+        .stderr(std::process::Stdio::piped())
+        .spawn()
+        .expect("run the client");
+    let mut stdout = child.stdout.take().unwrap();
+    let mut some = [0; 10];
+    stdout.read_exact(&mut some).unwrap();
+    drop(stdout);
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+    // No retries.
+    assert_eq!(server.connections(), 1);
+}
+
+#[test]
+fn dumps_every_head_received() {
+    let moved = "HTTP/1.1 302 Found\r\nLocation: /end\r\nContent-Length: 0\r\n\r\n";
+    let end = "HTTP/1.1 200 OK\r\nSet-Cookie: a=1\r\nContent-Length: 4\r\nSet-Cookie: b=2\r\n\r\n";
+    let server = Server::start(move |request| match request.target.as_str() {
+        "/start" => Reply::raw(moved),
+        _ => Reply::raw(format!("{}done", end)),
+    });
+    let path = std::env::temp_dir().join(format!("http_client-dump-{}", std::process::id()));
+    std::fs::write(&path, "stale").unwrap();
+    let output = client(&["-L", "-D", path.to_str().unwrap(), &server.url("/start")]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"done");
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        format!("{}{}", moved, end)
+    );
+    std::fs::remove_file(&path).unwrap();
+
+    let output = client(&["-D", "-", &server.url("/end")]);
+    assert_eq!(output.stdout, format!("{}done", end).as_bytes());
+}
+
+#[test]
+fn points_out_unannounced_close_delimited_bodies() {
+    let note = "the body ends when the server closes the connection";
+    let server = Server::start(|request| match request.target.as_str() {
+        "/silent" => Reply::raw("HTTP/1.1 200 OK\r\n\r\nto eof").and_close(),
+        _ => Reply::raw("HTTP/1.1 200 OK\r\nConnection: close\r\n\r\nto eof").and_close(),
+    });
+    let output = client(&["-v", "--lang", "en", &server.url("/silent")]);
+    assert_eq!(output.stdout, b"to eof");
+    assert!(String::from_utf8_lossy(&output.stderr).contains(note));
+    let output = client(&["-v", "--lang", "zh", &server.url("/silent")]);
+    assert!(String::from_utf8_lossy(&output.stderr).contains("响应体在服务器关闭连接时结束"));
+    let output = client(&["-v", "--lang", "en", &server.url("/announced")]);
+    assert_eq!(output.stdout, b"to eof");
+    assert!(!String::from_utf8_lossy(&output.stderr).contains(note));
+}
+
+#[test]
+fn urls_without_a_scheme_default_to_http() {
+    let server = Server::start(|request| Reply::ok(&request.target));
+    let bare = format!("127.0.0.1:{}/path?q", server.port);
+    let output = client(&[&bare]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"/path?q");
+    // The scheme given wins over the default.
+    let output = client(&["--proto-default", "https", &server.url("/given")]);
+    assert_eq!(output.stdout, b"/given");
+}
+
+#[test]
+fn answers_proxy_challenges_with_the_proxy_credentials() {
+    let proxy = Server::start(|request| {
+        let offer = match request.target.as_str() {
+            "http://origin.test/digest" => r#"Digest realm="proxy", nonce="n0", qop="auth""#,
+            _ => r#"Basic realm="proxy""#,
+        };
+        // Only an answer in the scheme offered gets through.
+        match request.header("Proxy-Authorization") {
+            Some(answer)
+                if answer.starts_with(&offer[..6])
+                    && (answer == "Basic dTpw" || answer.contains(r#"username="u""#)) =>
+            {
+                Reply::ok(answer.split(' ').next().unwrap())
+            }
+            _ => Reply::raw(format!(
+                "HTTP/1.1 407 Proxy Authentication Required\r\nProxy-Authenticate: {}\r\nContent-Length: 4\r\n\r\ndeny",
+                offer
+            )),
+        }
+    });
+    let url = proxy.url("");
+    let run = |extra: &[&str], target: &str| {
+        let mut args = vec!["--lang", "en", "-x", &url];
+        args.extend_from_slice(extra);
+        args.push(target);
+        let output = client(&args);
+        (
+            String::from_utf8(output.stdout).unwrap(),
+            String::from_utf8(output.stderr).unwrap(),
+        )
+    };
+    let user = ["-U", "u:p"];
+
+    // Basic goes up front; a Digest challenge is answered.
+    assert_eq!(run(&user, "http://origin.test/basic").0, "Basic");
+    assert_eq!(run(&user, "http://origin.test/digest").0, "Digest");
+    // Without Basic up front, a Basic challenge is still answered.
+    let digest_only = [&user[..], &["--proxy-digest"]].concat();
+    assert_eq!(run(&digest_only, "http://origin.test/basic").0, "Basic");
+
+    let (stdout, stderr) = run(&[], "http://origin.test/basic");
+    assert_eq!(stdout, "deny");
+    assert!(
+        stderr.contains("requires authentication (it offers Basic)"),
+        "{}",
+        stderr
+    );
+    let (_, stderr) = run(&["-U", "u:wrong"], "http://origin.test/basic");
+    assert!(stderr.contains("rejected its credentials"), "{}", stderr);
+}
+
+#[test]
+fn shows_trailers_after_the_head_and_the_body() {
+    let head = "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\nTrailer: Digest\r\n\r\n";
+    let trailers = "Digest: sha-256=x\r\nServer-Timing: db;dur=53\r\n\r\n";
+    let server =
+        Server::start(move |_| Reply::raw(format!("{}4\r\ndone\r\n0\r\n{}", head, trailers)));
+    let path = std::env::temp_dir().join(format!("http_client-trailers-{}", std::process::id()));
+    let output = client(&["-D", path.to_str().unwrap(), &server.url("/")]);
+    assert!(output.status.success());
+    assert_eq!(output.stdout, b"done");
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        format!("{}{}", head, trailers)
+    );
+    std::fs::remove_file(&path).unwrap();
+
+    let output = client(&["-i", &server.url("/")]);
+    assert_eq!(
+        output.stdout,
+        format!("{}done{}", head, trailers).as_bytes()
+    );
+}
+
+#[test]
+#[cfg(feature = "rustls")]
+fn https_only_upgrades_plain_urls() {
+    use std::sync::{Arc, Mutex};
+
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let log = seen.clone();
+    let server = Server::start_tls(move |request| {
+        let upgrade = request.header("Upgrade").unwrap_or("-");
+        log.lock()
+            .unwrap()
+            .push(format!("{} {}", request.target, upgrade));
+        match request.target.as_str() {
+            "/start" => Reply::raw(format!(
+                "HTTP/1.1 302 Found\r\nLocation: http://{}/end\r\nContent-Length: 0\r\n\r\n",
+                request.header("Host").unwrap()
+            )),
+            "/chat" => Reply::raw("HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n"),
+            _ => Reply::ok("done"),
+        }
+    });
+    let run = |args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_http_client"))
+            .args(["--lang", "en", "--https-only", "-v"])
+            .args(args)
+            .env("SSL_CERT_FILE", support::CA)
+            .output()
+            .expect("run the client");
+        let stderr = String::from_utf8(output.stderr).unwrap();
+        (
+            output.status,
+            String::from_utf8(output.stdout).unwrap(),
+            stderr,
+        )
+    };
+    let plain = |path: &str| server.url(path).replacen("https://", "http://", 1);
+
+    // A redirect to http:// is followed over TLS.
+    let (status, stdout, stderr) = run(&["-L", &server.url("/start")]);
+    assert!(status.success(), "{}", stderr);
+    assert_eq!(stdout, "done");
+    assert_eq!(
+        stderr
+            .matches("Switching to https:// for 127.0.0.1")
+            .count(),
+        1
+    );
+
+    let (status, stdout, _) = run(&[&plain("/plain")]);
+    assert!(status.success());
+    assert_eq!(stdout, "done");
+
+    // So is a request file with only a Host header to go by.
+    let path = std::env::temp_dir().join(format!("http_client-https-only-{}", std::process::id()));
+    let request = format!(
+        "GET /file HTTP/1.1\r\nHost: 127.0.0.1:{}\r\nConnection: close\r\n\r\n",
+        server.port
+    );
+    std::fs::write(&path, request).unwrap();
+    let (status, stdout, stderr) = run(&["--request-file", path.to_str().unwrap()]);
+    std::fs::remove_file(&path).unwrap();
+    assert!(status.success(), "{}", stderr);
+    assert_eq!(stdout, "done");
+
+    // ws:// becomes wss://.
+    let (status, _, stderr) = run(&[&plain("/chat").replacen("http://", "ws://", 1)]);
+    assert!(!status.success());
+    assert!(
+        stderr.contains("Switching to wss:// for 127.0.0.1"),
+        "{}",
+        stderr
+    );
+
+    let (status, _, stderr) = run(&["ftp://127.0.0.1/"]);
+    assert!(!status.success());
+    assert!(stderr.contains("ftp (--https-only)"), "{}", stderr);
+
+    assert_eq!(
+        *seen.lock().unwrap(),
+        [
+            "/start -",
+            "/end -",
+            "/plain -",
+            "/file -",
+            "/chat websocket"
+        ]
+    );
+}
+
+#[test]
+fn sends_the_user_agent_and_referer_given() {
+    let server = Server::start(|request| match request.target.as_str() {
+        "/start" => {
+            Reply::raw("HTTP/1.1 302 Found\r\nLocation: /end#top\r\nContent-Length: 0\r\n\r\n")
+        }
+        _ => Reply::ok(&format!(
+            "ua={} referer={}",
+            request.header("User-Agent").unwrap_or("-"),
+            request.header("Referer").unwrap_or("-"),
+        )),
+    });
+    let body = |args: &[&str]| String::from_utf8(client(args).stdout).unwrap();
+    let end = server.url("/end");
+    let default = format!("ua=http_client/{} referer=-", env!("CARGO_PKG_VERSION"));
+    assert_eq!(body(&[&end]), default);
+    assert_eq!(
+        body(&["-A", "probe/1", "-e", "http://a/", &end]),
+        "ua=probe/1 referer=http://a/"
+    );
+    assert_eq!(body(&["-A", "", &end]), "ua=- referer=-");
+
+    // ';auto' sends the URL redirected from, without its userinfo.
+    let start = server.url("/start").replacen("http://", "http://u:p@", 1);
+    let auto = body(&["-L", "-A", "", "-e", ";auto", &start]);
+    assert_eq!(auto, format!("ua=- referer={}", server.url("/start")));
+    assert_eq!(
+        body(&["-L", "-A", "", "-e", "http://a/", &start]),
+        "ua=- referer=http://a/"
+    );
+}
+
+/// Runs the client with `--dry-run`, then with `--export curl`, in a mode
+/// that sends requests of its own, given `args` for a server URL.
+fn previews_nothing(args: impl Fn(&str) -> Vec<String>) {
+    let server = Server::start(|_| Reply::ok("sent"));
+    for preview in [&["--dry-run"][..], &["--export", "curl"]] {
+        let output = Command::new(env!("CARGO_BIN_EXE_http_client"))
+            .args(["--lang", "en"])
+            .args(preview)
+            .args(args(&server.url("/")))
+            .output()
+            .expect("run the client");
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(2), "{}", stderr);
+        assert!(stderr.contains("nothing was sent"), "{}", stderr);
+        assert!(output.stdout.is_empty());
+    }
+    assert_eq!(server.connections(), 0);
+}
+
+/// A file for a test to hand the client, named after `name`.
+fn temp_file(name: &str, contents: &str) -> String {
+    let path = std::env::temp_dir().join(format!("http_client-{}-{}", name, std::process::id()));
+    std::fs::write(&path, contents).unwrap();
+    path.to_string_lossy().into_owned()
+}
+
+#[test]
+fn dry_run_refuses_http_files() {
+    previews_nothing(|url| {
+        let path = temp_file("preview.http", &format!("GET {}\n", url));
+        vec!["--http-file".into(), path]
+    });
+}
+
+#[test]
+fn dry_run_refuses_url_files() {
+    previews_nothing(|url| vec!["--url-file".into(), temp_file("preview-urls", url)]);
+}
+
+#[test]
+fn dry_run_refuses_url_globs() {
+    previews_nothing(|url| vec![format!("{}{{a,b}}", url)]);
+}
+
+#[test]
+fn dry_run_refuses_request_files() {
+    previews_nothing(|url| {
+        let host = url.trim_start_matches("http://").trim_end_matches('/');
+        let request = format!("GET / HTTP/1.1\r\nHost: {}\r\n\r\n", host);
+        vec!["--request-file".into(), temp_file("preview-raw", &request)]
+    });
+}
+
+#[test]
+fn dry_run_refuses_the_interactive_loop() {
+    previews_nothing(|_| vec!["--interactive".into()]);
+}
+
+#[test]
+fn dry_run_refuses_link_checks() {
+    previews_nothing(|url| vec!["--check-links".into(), url.into()]);
+}
+
+#[test]
+fn dry_run_refuses_mirroring() {
+    let dir =
+        std::env::temp_dir().join(format!("http_client-preview-mirror-{}", std::process::id()));
+    let dir = dir.to_string_lossy().into_owned();
+    previews_nothing(|url| {
+        vec![
+            "--mirror".into(),
+            "--output-dir".into(),
+            dir.clone(),
+            url.into(),
+        ]
+    });
+    assert!(!std::path::Path::new(&dir).exists());
+}
+
+#[test]
+fn dry_run_refuses_websockets() {
+    previews_nothing(|url| vec![url.replacen("http://", "ws://", 1)]);
+}
@@ -0,0 +1,213 @@
+//! A small HTTP/1.1 server for the integration tests, run on a random
+//! local port in a background thread.
+//!
+//! Each request is handed to a closure that returns the raw bytes to
+//! answer with, so tests can send exactly the framing, or the breakage,
+//! they are about. [`Server::start_tls`] serves the same over TLS, with a
+//! certificate for 127.0.0.1 the client trusts when `SSL_CERT_FILE` is
+//! [`CA`].
+
+#![allow(dead_code)]
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+/// The CA that issued the TLS server's certificate.
+pub const CA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/support/ca.pem");
+
+/// A request as the server read it.
+#[derive(Debug, Clone)]
+pub struct Request {
+    pub method: String,
+    pub target: String,
+    /// Names lowercased.
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        let name = name.to_ascii_lowercase();
+        self.headers
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// What to answer a request with.
+pub struct Reply {
+    pub bytes: Vec<u8>,
+    /// Close the connection after writing, as for a close-delimited body.
+    pub close: bool,
+    /// More bytes to write after a pause, for a slow server.
+    pub later: Option<(Duration, Vec<u8>)>,
+}
+
+impl Reply {
+    /// A complete response with a `Content-Length`.
+    pub fn ok(body: &str) -> Reply {
+        Reply::raw(format!(
+            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+            body.len(),
+            body
+        ))
+    }
+
+    pub fn raw(bytes: impl Into<Vec<u8>>) -> Reply {
+        Reply {
+            bytes: bytes.into(),
+            close: false,
+            later: None,
+        }
+    }
+
+    pub fn and_close(mut self) -> Reply {
+        self.close = true;
+        self
+    }
+
+    pub fn then_after(mut self, pause: Duration, rest: impl Into<Vec<u8>>) -> Reply {
+        self.later = Some((pause, rest.into()));
+        self
+    }
+}
+
+/// A running server. It stops when the test process exits.
+pub struct Server {
+    pub port: u16,
+    scheme: &'static str,
+    connections: Arc<AtomicUsize>,
+}
+
+impl Server {
+    pub fn start(handler: impl Fn(&Request) -> Reply + Send + Sync + 'static) -> Server {
+        Server::listen(handler, |stream, handler| serve(stream, handler))
+    }
+
+    /// A server speaking TLS, with the certificate `server.pem`.
+    #[cfg(feature = "rustls")]
+    pub fn start_tls(handler: impl Fn(&Request) -> Reply + Send + Sync + 'static) -> Server {
+        use rustls::pki_types::pem::PemObject;
+        use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/support");
+        let certs = CertificateDer::pem_file_iter(format!("{}/server.pem", dir))
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        let key = PrivateKeyDer::from_pem_file(format!("{}/server-key.pem", dir)).unwrap();
+        let config = rustls::ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(certs, key)
+            .unwrap();
+        let config = Arc::new(config);
+        let mut server = Server::listen(handler, move |stream, handler| {
+            let connection = rustls::ServerConnection::new(config.clone()).unwrap();
+            serve(rustls::StreamOwned::new(connection, stream), handler)
+        });
+        server.scheme = "https";
+        server
+    }
+
+    /// Accepts connections on a random port, each handed to `serve` on a
+    /// thread of its own.
+    fn listen<H: Fn(&Request) -> Reply + Send + Sync + 'static>(
+        handler: H,
+        serve: impl Fn(TcpStream, &H) + Send + Sync + 'static,
+    ) -> Server {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("bind a local port");
+        let port = listener.local_addr().unwrap().port();
+        let connections = Arc::new(AtomicUsize::new(0));
+        let handler = Arc::new(handler);
+        let serve = Arc::new(serve);
+        let counter = connections.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming().flatten() {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let (handler, serve) = (handler.clone(), serve.clone());
+                thread::spawn(move || serve(stream, &*handler));
+            }
+        });
+        Server {
+            port,
+            scheme: "http",
+            connections,
+        }
+    }
+
+    pub fn url(&self, path: &str) -> String {
+        format!("{}://127.0.0.1:{}{}", self.scheme, self.port, path)
+    }
+
+    /// How many connections have been accepted.
+    pub fn connections(&self) -> usize {
+        self.connections.load(Ordering::SeqCst)
+    }
+}
+
+fn serve(stream: impl Read + Write, handler: &(impl Fn(&Request) -> Reply + ?Sized)) {
+    let mut reader = BufReader::new(stream);
+    while let Some(request) = read_request(&mut reader) {
+        let reply = handler(&request);
+        let writer = reader.get_mut();
+        if writer
+            .write_all(&reply.bytes)
+            .and_then(|()| writer.flush())
+            .is_err()
+        {
+            return;
+        }
+        if let Some((pause, rest)) = &reply.later {
+            thread::sleep(*pause);
+            if writer
+                .write_all(rest)
+                .and_then(|()| writer.flush())
+                .is_err()
+            {
+                return;
+            }
+        }
+        if reply.close {
+            return;
+        }
+    }
+}
+
+fn read_request(reader: &mut impl BufRead) -> Option<Request> {
+    let mut line = String::new();
+    if reader.read_line(&mut line).ok()? == 0 {
+        return None;
+    }
+    let mut parts = line.split_whitespace();
+    let method = parts.next()?.to_string();
+    let target = parts.next()?.to_string();
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        reader.read_line(&mut line).ok()?;
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        let (name, value) = line.split_once(':')?;
+        headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+    }
+    let mut request = Request {
+        method,
+        target,
+        headers,
+        body: Vec::new(),
+    };
+    let length: usize = request
+        .header("content-length")
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    request.body = vec![0; length];
+    reader.read_exact(&mut request.body).ok()?;
+    Some(request)
+}